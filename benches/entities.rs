@@ -0,0 +1,179 @@
+//! Criterion benchmarks of PSM row iteration, score histograms and serialization of large entities, to catch
+//! performance regressions e.g. of polars upgrades before a release:
+//!
+//! ```text
+//! cargo bench --bench entities -- --save-baseline main     # record the timings as baseline `main`
+//! cargo bench --bench entities -- --baseline main          # compare against baseline `main`
+//! cargo bench --bench entities -- serialization            # run only the matching benchmarks
+//! ```
+
+// std imports
+use std::hint::black_box;
+
+// 3rd party imports
+use criterion::{criterion_group, criterion_main, Criterion};
+use polars::prelude::*;
+
+// internal imports
+use maccoys_exchange_entities::results_api::psm_columns::{
+    CHARGE, DELTA_CN, E_VALUE, IS_DECOY, PLAIN_PEPTIDE, PROTEIN, XCORR,
+};
+use maccoys_exchange_entities::results_api::{Identification, Precursor, Spectrum};
+use maccoys_exchange_entities::serialization::WireFormat;
+use maccoys_exchange_entities::statistics::histogram::BinStrategy;
+
+const NUM_PEAKS: usize = 50_000;
+const NUM_PSMS: usize = 100_000;
+/// Samples per benchmark, lower than criterion's default of 100 as single iterations take up to 50 ms
+const SAMPLE_SIZE: usize = 20;
+
+const AMINO_ACIDS: &[u8] = b"ACDEFGHIKLMNPQRSTVWY";
+
+/// Deterministic pseudo-random numbers in [0, 1)
+///
+struct Xorshift(u64);
+
+impl Xorshift {
+    fn next(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Spectrum with `NUM_PEAKS` peaks and without identifications
+///
+fn spectrum() -> Spectrum {
+    let mut random = Xorshift(0x2545F4914F6CDD1D);
+    let mut mz = (0..NUM_PEAKS)
+        .map(|_| 100.0 + random.next() * 1900.0)
+        .collect::<Vec<f64>>();
+    mz.sort_by(|a, b| a.total_cmp(b));
+    let intensity = (0..NUM_PEAKS)
+        .map(|_| random.next() * 1e6)
+        .collect::<Vec<f64>>();
+    Spectrum::new(
+        "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+        "run".parse().unwrap(),
+        "scan=1".parse().unwrap(),
+        mz,
+        intensity,
+        Vec::new(),
+    )
+}
+
+/// PSM table with `NUM_PSMS` rows
+///
+fn psms() -> DataFrame {
+    let mut random = Xorshift(0x9E3779B97F4A7C15);
+    let peptides = (0..NUM_PSMS)
+        .map(|_| {
+            let length = 7 + (random.next() * 20.0) as usize;
+            (0..length)
+                .map(|_| AMINO_ACIDS[(random.next() * AMINO_ACIDS.len() as f64) as usize] as char)
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>();
+    let proteins = (0..NUM_PSMS)
+        .map(|_| format!("sp|P{:05}|PROT_HUMAN", (random.next() * 20_000.0) as u32))
+        .collect::<Vec<String>>();
+    let xcorr = (0..NUM_PSMS)
+        .map(|_| random.next() * 5.0)
+        .collect::<Vec<f64>>();
+    let e_value = (0..NUM_PSMS)
+        .map(|_| random.next() * 10.0)
+        .collect::<Vec<f64>>();
+    let delta_cn = (0..NUM_PSMS).map(|_| random.next()).collect::<Vec<f64>>();
+    let charge = (0..NUM_PSMS)
+        .map(|_| 2 + (random.next() * 3.0) as i64)
+        .collect::<Vec<i64>>();
+    let is_decoy = (0..NUM_PSMS)
+        .map(|_| random.next() < 0.5)
+        .collect::<Vec<bool>>();
+    df!(
+        PLAIN_PEPTIDE => peptides,
+        PROTEIN => proteins,
+        XCORR => xcorr,
+        E_VALUE => e_value,
+        DELTA_CN => delta_cn,
+        CHARGE => charge,
+        IS_DECOY => is_decoy
+    )
+    .unwrap()
+}
+
+fn psm_rows(c: &mut Criterion) {
+    let identification = Identification::new(None, Some(psms()), Precursor::new(800.4, 2));
+    let mut group = c.benchmark_group("psm_rows");
+    group.bench_function("iterate", |b| {
+        b.iter(|| {
+            let mut sum = 0.0;
+            for row in identification.iter_psm_rows().unwrap() {
+                sum += row.get_f64(XCORR).unwrap();
+            }
+            black_box(sum)
+        })
+    });
+    // same as `iterate` but reusing one row buffer instead of allocating a row per PSM
+    group.bench_function("for_each_row", |b| {
+        b.iter(|| {
+            let mut sum = 0.0;
+            identification.for_each_psm_row(|row| sum += row.get_f64(XCORR).unwrap());
+            black_box(sum)
+        })
+    });
+    group.bench_function("histogram_sturges", |b| {
+        b.iter(|| identification.get_score_histogram_for(XCORR, BinStrategy::Sturges))
+    });
+    group.bench_function("histogram_freedman_diaconis", |b| {
+        b.iter(|| identification.get_score_histogram_for(XCORR, BinStrategy::FreedmanDiaconis))
+    });
+    group.finish();
+}
+
+fn serialization(c: &mut Criterion) {
+    let spectrum = spectrum();
+    let spectrum_json = serde_json::to_string(&spectrum).unwrap();
+    let spectrum_msgpack = spectrum.to_msgpack().unwrap();
+    let identification = Identification::new(None, Some(psms()), Precursor::new(800.4, 2));
+    let psms_json = serde_json::to_string(&identification).unwrap();
+    let psms_msgpack = identification.to_msgpack().unwrap();
+
+    let mut group = c.benchmark_group("serialization");
+    group.bench_function("spectrum_json", |b| {
+        b.iter(|| serde_json::to_string(black_box(&spectrum)).unwrap())
+    });
+    group.bench_function("spectrum_msgpack", |b| {
+        b.iter(|| black_box(&spectrum).to_msgpack().unwrap())
+    });
+    group.bench_function("psms_json", |b| {
+        b.iter(|| serde_json::to_string(black_box(&identification)).unwrap())
+    });
+    group.bench_function("psms_msgpack", |b| {
+        b.iter(|| black_box(&identification).to_msgpack().unwrap())
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("deserialization");
+    group.bench_function("spectrum_json", |b| {
+        b.iter(|| serde_json::from_str::<Spectrum>(black_box(&spectrum_json)).unwrap())
+    });
+    group.bench_function("spectrum_msgpack", |b| {
+        b.iter(|| Spectrum::from_msgpack(black_box(&spectrum_msgpack)).unwrap())
+    });
+    group.bench_function("psms_json", |b| {
+        b.iter(|| serde_json::from_str::<Identification>(black_box(&psms_json)).unwrap())
+    });
+    group.bench_function("psms_msgpack", |b| {
+        b.iter(|| Identification::from_msgpack(black_box(&psms_msgpack)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(SAMPLE_SIZE);
+    targets = psm_rows, serialization
+}
+criterion_main!(benches);
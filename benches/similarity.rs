@@ -0,0 +1,95 @@
+//! Criterion benchmarks of the spectral similarity scores:
+//!
+//! ```text
+//! cargo bench --bench similarity -- --save-baseline main   # record the timings as baseline `main`
+//! cargo bench --bench similarity -- --baseline main        # compare against baseline `main`
+//! cargo bench --bench similarity -- entropy                # run only the matching benchmarks
+//! ```
+
+// std imports
+use std::hint::black_box;
+
+// 3rd party imports
+use criterion::{criterion_group, criterion_main, Criterion};
+
+// internal imports
+use maccoys_exchange_entities::mass::mz_to_mass;
+use maccoys_exchange_entities::similarity::{
+    cosine, dot_bias, entropy_similarity, modified_cosine, spectral_entropy, Peaks,
+};
+use maccoys_exchange_entities::tolerance::Tolerance;
+
+const NUM_PEAKS: usize = 500;
+
+/// Deterministic pseudo-random numbers in [0, 1)
+///
+struct Xorshift(u64);
+
+impl Xorshift {
+    fn next(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// `NUM_PEAKS` peaks between 100 and 2000 m/z, ordered by m/z
+///
+fn peaks(seed: u64) -> (Vec<f64>, Vec<f64>) {
+    let mut random = Xorshift(seed);
+    let mut mz = (0..NUM_PEAKS)
+        .map(|_| 100.0 + random.next() * 1900.0)
+        .collect::<Vec<f64>>();
+    mz.sort_by(|a, b| a.total_cmp(b));
+    let intensity = (0..NUM_PEAKS).map(|_| random.next() * 1e6).collect();
+    (mz, intensity)
+}
+
+fn similarity(c: &mut Criterion) {
+    let (mz_a, intensity_a) = peaks(0x2545F4914F6CDD1D);
+    // second spectrum shares every other peak with the first one
+    let (mut mz_b, intensity_b) = peaks(0x9E3779B97F4A7C15);
+    mz_b.iter_mut()
+        .zip(mz_a.iter())
+        .step_by(2)
+        .for_each(|(mz_b, mz_a)| *mz_b = *mz_a + 0.001);
+    let mut peaks_b = mz_b.into_iter().zip(intensity_b).collect::<Vec<_>>();
+    peaks_b.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let (mz_b, intensity_b): (Vec<f64>, Vec<f64>) = peaks_b.into_iter().unzip();
+
+    let a = Peaks::new(&mz_a, &intensity_a);
+    let b = Peaks::new(&mz_b, &intensity_b);
+    let tolerance = Tolerance::Da(0.02);
+    let precursor_mass_a = mz_to_mass(800.4, 2);
+    let precursor_mass_b = mz_to_mass(808.4, 2);
+
+    let mut group = c.benchmark_group("similarity");
+    group.bench_function("cosine", |bencher| {
+        bencher.iter(|| cosine(black_box(a), black_box(b), tolerance))
+    });
+    group.bench_function("modified_cosine", |bencher| {
+        bencher.iter(|| {
+            modified_cosine(
+                black_box(a),
+                precursor_mass_a,
+                black_box(b),
+                precursor_mass_b,
+                tolerance,
+            )
+        })
+    });
+    group.bench_function("spectral_entropy", |bencher| {
+        bencher.iter(|| spectral_entropy(black_box(&intensity_a)))
+    });
+    group.bench_function("entropy_similarity", |bencher| {
+        bencher.iter(|| entropy_similarity(black_box(a), black_box(b), tolerance))
+    });
+    group.bench_function("dot_bias", |bencher| {
+        bencher.iter(|| dot_bias(black_box(a), black_box(b), tolerance))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, similarity);
+criterion_main!(benches);
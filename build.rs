@@ -0,0 +1,12 @@
+fn main() {
+    // Rust types of the Protocol Buffers messages, compiled without `protoc` so no system dependency is needed
+    #[cfg(feature = "proto")]
+    {
+        println!("cargo:rerun-if-changed=proto/maccoys_exchange.proto");
+        let file_descriptors = protox::compile(["proto/maccoys_exchange.proto"], ["proto"])
+            .expect("invalid proto/maccoys_exchange.proto");
+        prost_build::Config::new()
+            .compile_fds(file_descriptors)
+            .expect("failed to generate the Protocol Buffers types");
+    }
+}
@@ -0,0 +1,444 @@
+//! Retention time alignment between two MS runs, e.g. for transferring identifications between runs
+//! (match-between-runs).
+//!
+//! Peptides confidently identified in both runs serve as anchors. A LOWESS or piecewise-linear curve is fitted
+//! through the anchors and retention times are mapped by linear interpolation between the points of the curve.
+//! Outside of the anchors the first or last segment is extrapolated.
+
+// std imports
+use std::collections::HashMap;
+
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::results_api::{ScoreSelector, Spectrum};
+use crate::statistics::histogram::quantile;
+
+/// Error of an alignment
+///
+#[derive(Debug, thiserror::Error)]
+pub enum AlignmentError {
+    #[error("{found} shared peptides found, at least {required} needed for the alignment")]
+    TooFewAnchors { found: usize, required: usize },
+    #[error(transparent)]
+    Psm(#[from] anyhow::Error),
+}
+
+/// Curve fitted through the anchors
+///
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum AlignmentMethod {
+    /// Locally weighted linear regression
+    Lowess {
+        /// Fraction of anchors used for each local regression, e.g. 0.3
+        span: f64,
+        /// Robustifying iterations downweighting outliers, e.g. 2
+        iterations: usize,
+    },
+    /// Straight lines between the medians of equally populated segments
+    PiecewiseLinear { num_segments: usize },
+}
+
+impl Default for AlignmentMethod {
+    fn default() -> Self {
+        Self::Lowess {
+            span: 0.3,
+            iterations: 2,
+        }
+    }
+}
+
+/// Parameters of the alignment
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlignmentConfig {
+    method: AlignmentMethod,
+    selector: ScoreSelector,
+    score_threshold: f64,
+    min_anchors: usize,
+}
+
+impl AlignmentConfig {
+    /// LOWESS alignment on target PSMs with a q-value of at most 1 %, requiring 10 anchors
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_method(mut self, method: AlignmentMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Sets which PSMs are confident enough to serve as anchors
+    ///
+    /// # Arguments
+    /// * `selector` - Score to select the best PSM of each spectrum
+    /// * `score_threshold` - Worst accepted score, e.g. 0.01 for q-values
+    ///
+    pub fn with_confidence(mut self, selector: ScoreSelector, score_threshold: f64) -> Self {
+        self.selector = selector;
+        self.score_threshold = score_threshold;
+        self
+    }
+
+    /// Sets the minimal number of shared peptides
+    ///
+    pub fn with_min_anchors(mut self, min_anchors: usize) -> Self {
+        self.min_anchors = min_anchors.max(1);
+        self
+    }
+
+    pub fn get_method(&self) -> &AlignmentMethod {
+        &self.method
+    }
+
+    pub fn get_selector(&self) -> &ScoreSelector {
+        &self.selector
+    }
+
+    pub fn get_score_threshold(&self) -> f64 {
+        self.score_threshold
+    }
+
+    pub fn get_min_anchors(&self) -> usize {
+        self.min_anchors
+    }
+}
+
+impl Default for AlignmentConfig {
+    fn default() -> Self {
+        Self {
+            method: AlignmentMethod::default(),
+            selector: ScoreSelector::q_value(),
+            score_threshold: 0.01,
+            min_anchors: 10,
+        }
+    }
+}
+
+/// Retention time alignment of run A onto run B
+///
+/// ```
+/// use maccoys_exchange_entities::alignment::{AlignmentConfig, RtAlignment};
+/// use maccoys_exchange_entities::results_api::{Identification, Precursor, Spectrum};
+/// use polars::prelude::*;
+///
+/// let run = |name: &str, rt: &dyn Fn(f64) -> f64| {
+///     (0..20)
+///         .map(|idx| {
+///             let psms = df!(
+///                 "plain_peptide" => &[format!("PEPTIDE{}", idx)],
+///                 "q_value" => &[0.001]
+///             )
+///             .unwrap();
+///             Spectrum::new(
+///                 "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+///                 name.parse().unwrap(),
+///                 format!("scan={}", idx).parse().unwrap(),
+///                 vec![100.0],
+///                 vec![1.0],
+///                 vec![Identification::new(None, Some(psms), Precursor::new(400.7, 2))],
+///             )
+///             .with_retention_time(Some(rt(idx as f64 * 100.0)))
+///         })
+///         .collect::<Vec<Spectrum>>()
+/// };
+/// // run B elutes 5 % slower and starts 30 s later
+/// let run_a = run("run_a", &|rt| rt);
+/// let run_b = run("run_b", &|rt| rt * 1.05 + 30.0);
+///
+/// let alignment = RtAlignment::between(&run_a, &run_b, &AlignmentConfig::new()).unwrap();
+/// assert_eq!(alignment.get_anchors().len(), 20);
+/// assert!((alignment.map_rt(550.0) - 607.5).abs() < 1e-6);
+/// // extrapolated beyond the last anchor
+/// assert!((alignment.map_rt(2000.0) - 2130.0).abs() < 1e-6);
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RtAlignment {
+    method: AlignmentMethod,
+    anchors: Vec<(f64, f64)>,
+    curve: Vec<(f64, f64)>,
+}
+
+impl RtAlignment {
+    /// Aligns the retention times of run A onto run B.
+    /// Anchors are the median retention times of the peptides (modified if available, otherwise plain)
+    /// confidently identified in both runs. Spectra without retention time are ignored.
+    ///
+    /// # Arguments
+    /// * `run_a` - Spectra of the run to align
+    /// * `run_b` - Spectra of the reference run
+    /// * `config` - Alignment parameters
+    ///
+    pub fn between<'a>(
+        run_a: impl IntoIterator<Item = &'a Spectrum>,
+        run_b: impl IntoIterator<Item = &'a Spectrum>,
+        config: &AlignmentConfig,
+    ) -> Result<Self, AlignmentError> {
+        let peptides_a = peptide_retention_times(run_a, config)?;
+        let peptides_b = peptide_retention_times(run_b, config)?;
+        let anchors = peptides_a
+            .iter()
+            .filter_map(|(peptide, rt_a)| Some((*rt_a, *peptides_b.get(peptide)?)))
+            .collect::<Vec<(f64, f64)>>();
+        if anchors.len() < config.min_anchors {
+            return Err(AlignmentError::TooFewAnchors {
+                found: anchors.len(),
+                required: config.min_anchors,
+            });
+        }
+        Ok(Self::fit(anchors, config.method))
+    }
+
+    /// Fits the alignment through the given anchors
+    ///
+    /// # Arguments
+    /// * `anchors` - Retention times of the same analytes in run A and run B
+    /// * `method` - Fitted curve
+    ///
+    pub fn fit(mut anchors: Vec<(f64, f64)>, method: AlignmentMethod) -> Self {
+        anchors.retain(|(rt_a, rt_b)| rt_a.is_finite() && rt_b.is_finite());
+        anchors.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        let curve = match method {
+            AlignmentMethod::Lowess { span, iterations } => {
+                let fitted = lowess(&anchors, span, iterations);
+                merge_equal_x(
+                    anchors
+                        .iter()
+                        .zip(fitted)
+                        .map(|((rt_a, _), rt_b)| (*rt_a, rt_b))
+                        .collect(),
+                )
+            }
+            AlignmentMethod::PiecewiseLinear { num_segments } => {
+                segment_medians(&anchors, num_segments)
+            }
+        };
+        Self {
+            method,
+            anchors,
+            curve,
+        }
+    }
+
+    /// Maps a retention time of run A onto run B. Returns the retention time unchanged if there are no anchors.
+    ///
+    pub fn map_rt(&self, rt_a: f64) -> f64 {
+        let curve = &self.curve;
+        match curve.len() {
+            0 => rt_a,
+            // constant shift
+            1 => rt_a + curve[0].1 - curve[0].0,
+            num_points => {
+                let upper = curve
+                    .partition_point(|(x, _)| *x < rt_a)
+                    .clamp(1, num_points - 1);
+                let (x0, y0) = curve[upper - 1];
+                let (x1, y1) = curve[upper];
+                y0 + (rt_a - x0) * (y1 - y0) / (x1 - x0)
+            }
+        }
+    }
+
+    pub fn get_method(&self) -> &AlignmentMethod {
+        &self.method
+    }
+
+    /// Retention times of the shared peptides in run A and B, sorted by run A
+    ///
+    pub fn get_anchors(&self) -> &Vec<(f64, f64)> {
+        &self.anchors
+    }
+
+    /// Points of the fitted curve, sorted by run A
+    ///
+    pub fn get_curve(&self) -> &Vec<(f64, f64)> {
+        &self.curve
+    }
+
+    /// Median absolute difference between the mapped and observed retention times of the anchors,
+    /// None if there are no anchors
+    ///
+    pub fn median_absolute_residual(&self) -> Option<f64> {
+        if self.anchors.is_empty() {
+            return None;
+        }
+        let mut residuals = self
+            .anchors
+            .iter()
+            .map(|(rt_a, rt_b)| (self.map_rt(*rt_a) - rt_b).abs())
+            .collect::<Vec<f64>>();
+        residuals.sort_by(|a, b| a.total_cmp(b));
+        Some(quantile(&residuals, 0.5))
+    }
+}
+
+/// Median retention time of each confidently identified peptide
+///
+fn peptide_retention_times<'a>(
+    spectra: impl IntoIterator<Item = &'a Spectrum>,
+    config: &AlignmentConfig,
+) -> Result<HashMap<String, f64>, AlignmentError> {
+    let mut retention_times: HashMap<String, Vec<f64>> = HashMap::new();
+    for spectrum in spectra {
+        let retention_time = match spectrum.get_retention_time() {
+            Some(retention_time) => *retention_time,
+            None => continue,
+        };
+        let best = match spectrum.best_identification(config.selector.clone())? {
+            Some(best) => best,
+            None => continue,
+        };
+        let is_confident = match config.selector.is_higher_better() {
+            true => best.get_score() >= config.score_threshold,
+            false => best.get_score() <= config.score_threshold,
+        };
+        let peptide = best.get_modified_peptide().or(best.get_plain_peptide());
+        if let (true, Some(peptide)) = (is_confident, peptide) {
+            retention_times
+                .entry(peptide.to_string())
+                .or_default()
+                .push(retention_time);
+        }
+    }
+    Ok(retention_times
+        .into_iter()
+        .map(|(peptide, mut retention_times)| {
+            retention_times.sort_by(|a, b| a.total_cmp(b));
+            (peptide, quantile(&retention_times, 0.5))
+        })
+        .collect())
+}
+
+/// LOWESS with tricube weights on the nearest neighbours and bisquare robustness weights.
+/// Returns the fitted values of the points, which need to be sorted by x.
+///
+fn lowess(points: &[(f64, f64)], span: f64, iterations: usize) -> Vec<f64> {
+    let num_points = points.len();
+    if num_points < 2 {
+        return points.iter().map(|(_, y)| *y).collect();
+    }
+    let num_neighbours = ((span * num_points as f64).ceil() as usize).clamp(2, num_points);
+    let y_magnitude = points.iter().map(|(_, y)| y.abs()).fold(0.0, f64::max);
+    let mut robustness = vec![1.0; num_points];
+    let mut fitted = vec![0.0; num_points];
+    for iteration in 0..=iterations {
+        let mut left = 0;
+        for (idx, (x, y)) in points.iter().enumerate() {
+            // slide the window of nearest neighbours along the sorted points
+            while left + num_neighbours < num_points
+                && points[left + num_neighbours].0 - x < x - points[left].0
+            {
+                left += 1;
+            }
+            let window = &points[left..left + num_neighbours];
+            let max_distance = (x - window[0].0).max(window[num_neighbours - 1].0 - x);
+            let (mut sw, mut swx, mut swy, mut swxx, mut swxy) = (0.0, 0.0, 0.0, 0.0, 0.0);
+            for (offset, (neighbour_x, neighbour_y)) in window.iter().enumerate() {
+                let weight = match max_distance > 0.0 {
+                    true => tricube((neighbour_x - x).abs() / (max_distance * (1.0 + 1e-10))),
+                    false => 1.0,
+                } * robustness[left + offset];
+                sw += weight;
+                swx += weight * neighbour_x;
+                swy += weight * neighbour_y;
+                swxx += weight * neighbour_x * neighbour_x;
+                swxy += weight * neighbour_x * neighbour_y;
+            }
+            let denominator = sw * swxx - swx * swx;
+            fitted[idx] = if sw <= 0.0 {
+                *y
+            } else if denominator.abs() <= f64::EPSILON * sw * swxx {
+                swy / sw
+            } else {
+                let slope = (sw * swxy - swx * swy) / denominator;
+                (swy - slope * swx) / sw + slope * x
+            };
+        }
+        if iteration == iterations {
+            break;
+        }
+        let residuals = points
+            .iter()
+            .zip(fitted.iter())
+            .map(|((_, y), fitted)| y - fitted)
+            .collect::<Vec<f64>>();
+        let mut absolute_residuals = residuals.iter().map(|r| r.abs()).collect::<Vec<f64>>();
+        absolute_residuals.sort_by(|a, b| a.total_cmp(b));
+        let scale = 6.0 * quantile(&absolute_residuals, 0.5);
+        // residuals of (almost) perfect fits are rounding noise, which must not be weighted
+        if scale <= 1e-10 * y_magnitude {
+            break;
+        }
+        for (weight, residual) in robustness.iter_mut().zip(residuals.iter()) {
+            *weight = bisquare(residual / scale);
+        }
+    }
+    fitted
+}
+
+fn tricube(distance: f64) -> f64 {
+    match distance < 1.0 {
+        true => (1.0 - distance.powi(3)).powi(3),
+        false => 0.0,
+    }
+}
+
+fn bisquare(residual: f64) -> f64 {
+    match residual.abs() < 1.0 {
+        true => (1.0 - residual.powi(2)).powi(2),
+        false => 0.0,
+    }
+}
+
+/// Points of equal x are merged by averaging y, so the curve can be interpolated
+///
+fn merge_equal_x(points: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    let mut merged: Vec<(f64, f64, usize)> = Vec::with_capacity(points.len());
+    for (x, y) in points {
+        match merged.last_mut() {
+            Some((last_x, sum_y, count)) if *last_x == x => {
+                *sum_y += y;
+                *count += 1;
+            }
+            _ => merged.push((x, y, 1)),
+        }
+    }
+    merged
+        .into_iter()
+        .map(|(x, sum_y, count)| (x, sum_y / count as f64))
+        .collect()
+}
+
+/// Medians of x and y of equally populated segments of the points sorted by x
+///
+fn segment_medians(points: &[(f64, f64)], num_segments: usize) -> Vec<(f64, f64)> {
+    let num_segments = num_segments.clamp(1, points.len().max(1));
+    let medians = (0..num_segments)
+        .filter_map(|segment| {
+            let start = segment * points.len() / num_segments;
+            let end = (segment + 1) * points.len() / num_segments;
+            if start == end {
+                return None;
+            }
+            let mut xs = points[start..end]
+                .iter()
+                .map(|(x, _)| *x)
+                .collect::<Vec<f64>>();
+            let mut ys = points[start..end]
+                .iter()
+                .map(|(_, y)| *y)
+                .collect::<Vec<f64>>();
+            xs.sort_by(|a, b| a.total_cmp(b));
+            ys.sort_by(|a, b| a.total_cmp(b));
+            Some((quantile(&xs, 0.5), quantile(&ys, 0.5)))
+        })
+        .collect();
+    merge_equal_x(medians)
+}
@@ -0,0 +1,417 @@
+// 3rd party imports
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::export::parse_comet_modifications;
+use crate::mass::{residue_mass, AMMONIA, CARBON_MONOXIDE, HYDROGEN, PROTON, WATER};
+use crate::results_api::exchange_config::DEFAULT_FRAGMENT_TOLERANCE;
+use crate::results_api::{psm_columns, spectrum::Row, ExchangeConfig, Spectrum};
+use crate::tolerance::Tolerance;
+
+/// Fragment ion series
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum IonType {
+    A,
+    B,
+    C,
+    X,
+    Y,
+    /// z+1 (z-dot) ion as observed in ETD/ECD
+    Z,
+}
+
+impl IonType {
+    /// Returns true for the N-terminal ion series a, b and c
+    ///
+    pub fn is_n_terminal(&self) -> bool {
+        matches!(self, Self::A | Self::B | Self::C)
+    }
+
+    /// Mass offset of the neutral ion relative to the summed residue masses
+    ///
+    fn mass_offset(&self) -> f64 {
+        match self {
+            Self::A => -CARBON_MONOXIDE,
+            Self::B => 0.0,
+            Self::C => AMMONIA,
+            Self::X => WATER + CARBON_MONOXIDE - 2.0 * HYDROGEN,
+            Self::Y => WATER,
+            Self::Z => WATER - AMMONIA + HYDROGEN,
+        }
+    }
+
+    fn symbol(&self) -> char {
+        match self {
+            Self::A => 'a',
+            Self::B => 'b',
+            Self::C => 'c',
+            Self::X => 'x',
+            Self::Y => 'y',
+            Self::Z => 'z',
+        }
+    }
+}
+
+/// Theoretical fragment ion
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Fragment {
+    ion_type: IonType,
+    ordinal: usize,
+    charge: u8,
+    mz: f64,
+}
+
+impl Fragment {
+    pub fn get_ion_type(&self) -> IonType {
+        self.ion_type
+    }
+
+    /// Number of residues of the fragment
+    ///
+    pub fn get_ordinal(&self) -> usize {
+        self.ordinal
+    }
+
+    pub fn get_charge(&self) -> u8 {
+        self.charge
+    }
+
+    pub fn get_mz(&self) -> f64 {
+        self.mz
+    }
+
+    /// Label like `b3` or `y5++`
+    ///
+    pub fn label(&self) -> String {
+        let charge = match self.charge {
+            0 | 1 => String::new(),
+            charge => "+".repeat(charge as usize),
+        };
+        format!("{}{}{}", self.ion_type.symbol(), self.ordinal, charge)
+    }
+}
+
+/// Theoretical fragment matched to a peak
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PeakAnnotation {
+    fragment: Fragment,
+    label: String,
+    mz_error: f64,
+}
+
+impl PeakAnnotation {
+    pub fn get_fragment(&self) -> &Fragment {
+        &self.fragment
+    }
+
+    pub fn get_label(&self) -> &str {
+        &self.label
+    }
+
+    /// Observed minus theoretical m/z in Dalton
+    ///
+    pub fn get_mz_error(&self) -> f64 {
+        self.mz_error
+    }
+}
+
+/// Peaks of a spectrum with the fragment ions of a peptide matched to it, ready for rendering
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AnnotatedSpectrum {
+    spectrum_id: String,
+    sequence: String,
+    mz: Vec<f64>,
+    intensity: Vec<f64>,
+    annotations: Vec<Vec<PeakAnnotation>>,
+}
+
+impl AnnotatedSpectrum {
+    pub fn get_spectrum_id(&self) -> &str {
+        &self.spectrum_id
+    }
+
+    pub fn get_sequence(&self) -> &str {
+        &self.sequence
+    }
+
+    pub fn get_mz(&self) -> &Vec<f64> {
+        &self.mz
+    }
+
+    pub fn get_intensity(&self) -> &Vec<f64> {
+        &self.intensity
+    }
+
+    /// Annotations per peak, same length as m/z and intensity
+    ///
+    pub fn get_annotations(&self) -> &Vec<Vec<PeakAnnotation>> {
+        &self.annotations
+    }
+
+    /// Number of annotated peaks
+    ///
+    pub fn num_annotated_peaks(&self) -> usize {
+        self.annotations
+            .iter()
+            .filter(|annotations| !annotations.is_empty())
+            .count()
+    }
+}
+
+/// Parameters for the annotation
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnnotationConfig {
+    ion_types: Vec<IonType>,
+    max_fragment_charge: u8,
+    tolerance: Tolerance,
+    proton_mass: f64,
+}
+
+impl AnnotationConfig {
+    pub fn new(ion_types: Vec<IonType>, max_fragment_charge: u8, tolerance: Tolerance) -> Self {
+        Self {
+            ion_types,
+            max_fragment_charge,
+            tolerance,
+            proton_mass: PROTON,
+        }
+    }
+
+    /// Sets the proton mass in Dalton used for the fragment m/z, see `ExchangeConfig::with_proton_mass`
+    ///
+    pub fn with_proton_mass(mut self, proton_mass: f64) -> Self {
+        self.proton_mass = proton_mass;
+        self
+    }
+
+    pub fn get_ion_types(&self) -> &Vec<IonType> {
+        &self.ion_types
+    }
+
+    pub fn get_max_fragment_charge(&self) -> u8 {
+        self.max_fragment_charge
+    }
+
+    pub fn get_tolerance(&self) -> &Tolerance {
+        &self.tolerance
+    }
+
+    pub fn get_proton_mass(&self) -> f64 {
+        self.proton_mass
+    }
+}
+
+impl Default for AnnotationConfig {
+    /// b and y ions with up to charge 2 and a tolerance of 0.02 Da
+    ///
+    fn default() -> Self {
+        Self::new(vec![IonType::B, IonType::Y], 2, DEFAULT_FRAGMENT_TOLERANCE)
+    }
+}
+
+impl From<&ExchangeConfig> for AnnotationConfig {
+    /// b and y ions with up to charge 2, with the fragment tolerance and proton mass of the search
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::annotation::AnnotationConfig;
+    /// use maccoys_exchange_entities::results_api::ExchangeConfig;
+    /// use maccoys_exchange_entities::tolerance::Tolerance;
+    ///
+    /// let exchange_config = ExchangeConfig::default()
+    ///     .with_fragment_tolerance(Tolerance::Ppm(10.0))
+    ///     .with_proton_mass(1.00728);
+    /// let config = AnnotationConfig::from(&exchange_config);
+    /// assert_eq!(config.get_tolerance(), &Tolerance::Ppm(10.0));
+    /// assert_eq!(config.get_proton_mass(), 1.00728);
+    /// ```
+    ///
+    fn from(config: &ExchangeConfig) -> Self {
+        Self::new(
+            vec![IonType::B, IonType::Y],
+            2,
+            *config.get_fragment_tolerance(),
+        )
+        .with_proton_mass(config.get_proton_mass())
+    }
+}
+
+/// Calculates the theoretical fragment ions of the given peptide, with the default proton mass.
+///
+/// # Arguments
+/// * `sequence` - Plain peptide sequence
+/// * `modifications` - Mass deltas per residue, same length as the sequence
+/// * `ion_types` - Ion series to calculate
+/// * `max_charge` - Maximum fragment charge
+///
+pub fn fragments(
+    sequence: &str,
+    modifications: &[f64],
+    ion_types: &[IonType],
+    max_charge: u8,
+) -> Result<Vec<Fragment>> {
+    fragments_with_proton_mass(sequence, modifications, ion_types, max_charge, PROTON)
+}
+
+/// Same as `fragments` with the given proton mass in Dalton
+///
+fn fragments_with_proton_mass(
+    sequence: &str,
+    modifications: &[f64],
+    ion_types: &[IonType],
+    max_charge: u8,
+    proton_mass: f64,
+) -> Result<Vec<Fragment>> {
+    if modifications.len() != sequence.chars().count() {
+        bail!(
+            "number of modifications does not match sequence `{}`",
+            sequence
+        );
+    }
+    let residues = sequence
+        .chars()
+        .zip(modifications.iter())
+        .map(|(amino_acid, mass_delta)| match residue_mass(amino_acid) {
+            Some(mass) => Ok(mass + mass_delta),
+            None => bail!("unknown amino acid `{}` in `{}`", amino_acid, sequence),
+        })
+        .collect::<Result<Vec<f64>>>()?;
+
+    let mut fragments = Vec::new();
+    for ion_type in ion_types {
+        let mut mass = ion_type.mass_offset();
+        for ordinal in 1..residues.len() {
+            mass += if ion_type.is_n_terminal() {
+                residues[ordinal - 1]
+            } else {
+                residues[residues.len() - ordinal]
+            };
+            for charge in 1..=max_charge.max(1) {
+                fragments.push(Fragment {
+                    ion_type: *ion_type,
+                    ordinal,
+                    charge,
+                    mz: (mass + charge as f64 * proton_mass) / charge as f64,
+                });
+            }
+        }
+    }
+    Ok(fragments)
+}
+
+/// Matches the fragments of the given peptide against the peaks of the spectrum.
+/// Each fragment is assigned to the closest peak within the tolerance.
+/// The m/z values of the spectrum need to be sorted ascending.
+///
+/// # Arguments
+/// * `spectrum` - Spectrum to annotate
+/// * `sequence` - Plain peptide sequence
+/// * `modifications` - Mass deltas per residue, same length as the sequence
+/// * `config` - Annotation parameters
+///
+pub fn annotate_peptide(
+    spectrum: &Spectrum,
+    sequence: &str,
+    modifications: &[f64],
+    config: &AnnotationConfig,
+) -> Result<AnnotatedSpectrum> {
+    let mz = spectrum.get_mz();
+    let mut annotations: Vec<Vec<PeakAnnotation>> = vec![Vec::new(); mz.len()];
+    for fragment in fragments_with_proton_mass(
+        sequence,
+        modifications,
+        config.get_ion_types(),
+        config.get_max_fragment_charge(),
+        config.get_proton_mass(),
+    )? {
+        let closest = config
+            .get_tolerance()
+            .peak_range(mz, fragment.mz)
+            .min_by(|a, b| {
+                (mz[*a] - fragment.mz)
+                    .abs()
+                    .total_cmp(&(mz[*b] - fragment.mz).abs())
+            });
+        if let Some(idx) = closest {
+            annotations[idx].push(PeakAnnotation {
+                label: fragment.label(),
+                mz_error: mz[idx] - fragment.mz,
+                fragment,
+            });
+        }
+    }
+    Ok(AnnotatedSpectrum {
+        spectrum_id: spectrum.get_spectra_id().to_string(),
+        sequence: sequence.to_string(),
+        mz: mz.to_vec(),
+        intensity: spectrum.get_intensity().to_vec(),
+        annotations,
+    })
+}
+
+/// Annotates the spectrum with the peptide of the given PSM row, using the columns
+/// `plain_peptide`, `modifications` (Comet format) and `charge` (optional, limits the fragment charge to precursor charge - 1).
+///
+pub fn annotate(
+    spectrum: &Spectrum,
+    psm: &Row<'_>,
+    config: &AnnotationConfig,
+) -> Result<AnnotatedSpectrum> {
+    let sequence = psm.get_str(psm_columns::PLAIN_PEPTIDE)?;
+    let modifications = match psm.get::<Option<&str>>(psm_columns::MODIFICATIONS) {
+        Ok(Some(modifications)) => modification_deltas(sequence, modifications)?,
+        _ => vec![0.0; sequence.chars().count()],
+    };
+    let max_fragment_charge = match psm.get::<u8>(psm_columns::CHARGE) {
+        Ok(charge) => config
+            .get_max_fragment_charge()
+            .min(charge.saturating_sub(1).max(1)),
+        Err(_) => config.get_max_fragment_charge(),
+    };
+    annotate_peptide(
+        spectrum,
+        sequence,
+        &modifications,
+        &AnnotationConfig {
+            max_fragment_charge,
+            ..config.clone()
+        },
+    )
+}
+
+/// Converts Comet modifications into mass deltas per residue.
+/// Positions are 1-based, `N`/`n` and `C`/`c` denote the peptide termini and are added to the first/last residue.
+///
+pub fn modification_deltas(sequence: &str, modifications: &str) -> Result<Vec<f64>> {
+    let length = sequence.chars().count();
+    let mut deltas = vec![0.0; length];
+    if length == 0 {
+        return Ok(deltas);
+    }
+    for (position, mass) in parse_comet_modifications(modifications) {
+        let idx = match position {
+            "N" | "n" | "0" => 0,
+            "C" | "c" => length - 1,
+            position => match position.parse::<usize>() {
+                Ok(position) if (1..=length).contains(&position) => position - 1,
+                _ => bail!(
+                    "modification position `{}` is invalid for `{}`",
+                    position,
+                    sequence
+                ),
+            },
+        };
+        deltas[idx] += mass;
+    }
+    Ok(deltas)
+}
@@ -0,0 +1,614 @@
+//! Command line tool to inspect, convert, validate and summarize serialized exchange payloads,
+//! e.g. for debugging payloads without writing Rust. Build with `--features cli`.
+//!
+//! Payloads are JSON files of a `Search`, `MsRun`, `Spectrum`, an array of spectra or a bundle
+//! `{"search": ..., "ms_runs": [...], "spectra": [...]}`, directories written by `storage::parquet`,
+//! or PSM tables as Parquet or Arrow IPC file.
+
+// std imports
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+// 3rd party imports
+use anyhow::{bail, Context, Result};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// internal imports
+use maccoys_exchange_entities::migrations::migrate;
+use maccoys_exchange_entities::results_api::search_statistics::DEFAULT_SUMMARY_FDR;
+use maccoys_exchange_entities::results_api::{
+    ExchangeConfig, MsRun, MsRunName, Search, SearchStatistics, SearchUuid, Spectrum,
+};
+use maccoys_exchange_entities::storage::parquet;
+use maccoys_exchange_entities::validate::{
+    Validate, ValidationReport, ViolationKind, REQUIRED_PSM_COLUMNS,
+};
+
+const USAGE: &str = "\
+Usage: maccoys-entities <command> [options]
+
+Commands:
+    inspect <input>                     Prints the content of the payload
+    convert <input> <output>            Converts between JSON, Parquet and Arrow IPC
+        --from <json|parquet|arrow>     Format of the input, defaults to the file extension
+        --to <json|parquet|arrow>       Format of the output, defaults to the file extension
+    validate <input>                    Checks the invariants of the entities, exits with 1 on violations
+    summarize <input>                   Prints identification statistics of the spectra
+        --fdr <threshold>               FDR threshold for identified spectra, defaults to 0.01
+
+Inputs are JSON files of a search, MS run, spectrum, an array of spectra or a bundle
+{\"search\": ..., \"ms_runs\": [...], \"spectra\": [...]}, Parquet directories written by the
+parquet store, or PSM tables as Parquet or Arrow IPC file. Entities are written to Arrow
+as a single PSM table.";
+
+// columns prepended to the PSM tables when flattening spectra
+const MS_RUN_NAME_COL: &str = "ms_run_name";
+const SPECTRUM_ID_COL: &str = "spectrum_id";
+const IDENTIFICATION_INDEX_COL: &str = "identification_index";
+const PRECURSOR_MZ_COL: &str = "precursor_mz";
+const CHARGE_COL: &str = "charge";
+
+/// Format of an input or output
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Json,
+    Parquet,
+    Arrow,
+}
+
+impl Format {
+    fn parse(format: &str) -> Result<Self> {
+        match format.to_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "parquet" | "pq" => Ok(Self::Parquet),
+            "arrow" | "ipc" | "feather" => Ok(Self::Arrow),
+            _ => bail!(
+                "unknown format `{}`, expected json, parquet or arrow",
+                format
+            ),
+        }
+    }
+
+    /// Format by file extension, directories are Parquet stores
+    ///
+    fn from_path(path: &Path) -> Result<Self> {
+        if path.is_dir() {
+            return Ok(Self::Parquet);
+        }
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) => Self::parse(extension),
+            None => bail!(
+                "cannot determine the format of {}, use --from or --to",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// Search, MS runs and spectra in one payload, each part optional
+///
+#[derive(Default, Serialize, Deserialize)]
+struct Bundle {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    search: Option<Search>,
+    #[serde(default)]
+    ms_runs: Vec<MsRun>,
+    #[serde(default)]
+    spectra: Vec<Spectrum>,
+}
+
+impl Bundle {
+    /// Adds the missing search and MS runs, derived from the spectra
+    ///
+    fn complete(mut self) -> Result<Self> {
+        let mut ms_run_names: Vec<MsRunName> = Vec::new();
+        let names = self
+            .ms_runs
+            .iter()
+            .map(|ms_run| ms_run.get_ms_run())
+            .chain(self.spectra.iter().map(|spectrum| spectrum.get_ms_run()));
+        for name in names {
+            if !ms_run_names.contains(name) {
+                ms_run_names.push(name.clone());
+            }
+        }
+        let search = match self.search.take() {
+            Some(search) => search,
+            None => {
+                let search_uuid = self
+                    .spectra
+                    .first()
+                    .map(|spectrum| spectrum.get_search_uuid().clone())
+                    .or_else(|| {
+                        self.ms_runs
+                            .first()
+                            .map(|ms_run| ms_run.get_search_uuid().clone())
+                    })
+                    .unwrap_or_else(SearchUuid::nil);
+                Search::new(search_uuid, ms_run_names.clone())
+            }
+        };
+        for ms_run_name in ms_run_names {
+            if !search.get_ms_run_names().contains(&ms_run_name) {
+                bail!("MS run `{}` is not part of the search", ms_run_name);
+            }
+            if self
+                .ms_runs
+                .iter()
+                .all(|ms_run| ms_run.get_ms_run() != &ms_run_name)
+            {
+                let spectra_ids = self
+                    .spectra
+                    .iter()
+                    .filter(|spectrum| spectrum.get_ms_run() == &ms_run_name)
+                    .map(|spectrum| spectrum.get_spectra_id().clone())
+                    .collect();
+                self.ms_runs.push(MsRun::new(
+                    search.get_search_uuid().clone(),
+                    ms_run_name,
+                    spectra_ids,
+                ));
+            }
+        }
+        self.search = Some(search);
+        Ok(self)
+    }
+}
+
+/// Content of an input
+///
+enum Payload {
+    Search(Box<Search>),
+    MsRun(Box<MsRun>),
+    Spectrum(Box<Spectrum>),
+    Bundle(Box<Bundle>),
+    Table(DataFrame),
+}
+
+impl Payload {
+    fn into_bundle(self) -> Result<Bundle> {
+        Ok(match self {
+            Self::Search(search) => Bundle {
+                search: Some(*search),
+                ..Bundle::default()
+            },
+            Self::MsRun(ms_run) => Bundle {
+                ms_runs: vec![*ms_run],
+                ..Bundle::default()
+            },
+            Self::Spectrum(spectrum) => Bundle {
+                spectra: vec![*spectrum],
+                ..Bundle::default()
+            },
+            Self::Bundle(bundle) => *bundle,
+            Self::Table(_) => bail!("PSM tables cannot be converted into entities"),
+        })
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(exit_code) => exit_code,
+        Err(err) => {
+            eprintln!("error: {:#}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<ExitCode> {
+    let (command, args) = match args.split_first() {
+        Some((command, args)) => (command.as_str(), args),
+        None => {
+            eprintln!("{}", USAGE);
+            return Ok(ExitCode::from(2));
+        }
+    };
+    let (positionals, options) = split_args(args)?;
+    match (command, positionals.as_slice()) {
+        ("inspect", [input]) => {
+            inspect(read(Path::new(input), option_format(&options, "from")?)?);
+            Ok(ExitCode::SUCCESS)
+        }
+        ("convert", [input, output]) => {
+            let input = Path::new(input);
+            let output = PathBuf::from(output);
+            let payload = read(input, option_format(&options, "from")?)?;
+            let to = match option_format(&options, "to")? {
+                Some(format) => format,
+                None => Format::from_path(&output)?,
+            };
+            write(payload, &output, to)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        ("validate", [input]) => {
+            let report = validate(read(Path::new(input), option_format(&options, "from")?)?);
+            match report.is_valid() {
+                true => {
+                    println!("valid");
+                    Ok(ExitCode::SUCCESS)
+                }
+                false => {
+                    for violation in report.get_violations() {
+                        println!("{}", violation);
+                    }
+                    Ok(ExitCode::from(1))
+                }
+            }
+        }
+        ("summarize", [input]) => {
+            let fdr_threshold = match options.get("fdr") {
+                Some(fdr) => fdr
+                    .parse()
+                    .with_context(|| format!("invalid FDR threshold `{}`", fdr))?,
+                None => DEFAULT_SUMMARY_FDR,
+            };
+            summarize(
+                read(Path::new(input), option_format(&options, "from")?)?,
+                fdr_threshold,
+            )?;
+            Ok(ExitCode::SUCCESS)
+        }
+        ("help" | "--help" | "-h", _) => {
+            println!("{}", USAGE);
+            Ok(ExitCode::SUCCESS)
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            Ok(ExitCode::from(2))
+        }
+    }
+}
+
+/// Splits the arguments into positionals and `--key value` options
+///
+fn split_args(args: &[String]) -> Result<(Vec<&str>, BTreeMap<&str, &str>)> {
+    let mut positionals = Vec::new();
+    let mut options = BTreeMap::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.strip_prefix("--") {
+            Some(key) => match args.next() {
+                Some(value) => {
+                    options.insert(key, value.as_str());
+                }
+                None => bail!("option --{} needs a value", key),
+            },
+            None => positionals.push(arg.as_str()),
+        }
+    }
+    Ok((positionals, options))
+}
+
+fn option_format(options: &BTreeMap<&str, &str>, key: &str) -> Result<Option<Format>> {
+    options
+        .get(key)
+        .map(|format| Format::parse(format))
+        .transpose()
+}
+
+fn read(path: &Path, format: Option<Format>) -> Result<Payload> {
+    let format = match format {
+        Some(format) => format,
+        None => Format::from_path(path)?,
+    };
+    match format {
+        Format::Json => read_json(path),
+        Format::Parquet if path.is_dir() => {
+            let (search, ms_runs, spectra) = parquet::read(path)?;
+            Ok(Payload::Bundle(Box::new(Bundle {
+                search: Some(search),
+                ms_runs,
+                spectra,
+            })))
+        }
+        Format::Parquet => Ok(Payload::Table(
+            ParquetReader::new(File::open(path)?).finish()?,
+        )),
+        Format::Arrow => Ok(Payload::Table(IpcReader::new(File::open(path)?).finish()?)),
+    }
+    .with_context(|| format!("could not read {}", path.display()))
+}
+
+/// Reads the JSON payload, detecting the entity by its fields. Entities of older layouts are migrated.
+///
+fn read_json(path: &Path) -> Result<Payload> {
+    let value: Value = serde_json::from_reader(std::io::BufReader::new(File::open(path)?))?;
+    let spectra = |values: Vec<Value>| -> Result<Vec<Spectrum>> {
+        values.into_iter().map(migrate::<Spectrum>).collect()
+    };
+    match value {
+        Value::Array(values) => Ok(Payload::Bundle(Box::new(Bundle {
+            spectra: spectra(values)?,
+            ..Bundle::default()
+        }))),
+        Value::Object(mut object) => {
+            if object.contains_key("spectrum_id") {
+                Ok(Payload::Spectrum(Box::new(migrate(Value::Object(object))?)))
+            } else if object.contains_key("spectra_ids") {
+                Ok(Payload::MsRun(Box::new(migrate(Value::Object(object))?)))
+            } else if object.contains_key("ms_run_names") {
+                Ok(Payload::Search(Box::new(migrate(Value::Object(object))?)))
+            } else if object.contains_key("columns") {
+                Ok(Payload::Table(serde_json::from_value(Value::Object(
+                    object,
+                ))?))
+            } else if ["search", "ms_runs", "spectra"]
+                .iter()
+                .any(|key| object.contains_key(*key))
+            {
+                let search = object.remove("search").map(migrate).transpose()?;
+                let ms_runs = match object.remove("ms_runs") {
+                    Some(Value::Array(values)) => values
+                        .into_iter()
+                        .map(migrate)
+                        .collect::<Result<Vec<MsRun>>>()?,
+                    _ => Vec::new(),
+                };
+                let spectra = match object.remove("spectra") {
+                    Some(Value::Array(values)) => spectra(values)?,
+                    _ => Vec::new(),
+                };
+                Ok(Payload::Bundle(Box::new(Bundle {
+                    search,
+                    ms_runs,
+                    spectra,
+                })))
+            } else {
+                bail!("unknown payload, expected a search, MS run, spectrum, spectra or PSM table")
+            }
+        }
+        _ => bail!("unknown payload, expected a JSON object or array"),
+    }
+}
+
+fn write(payload: Payload, path: &Path, format: Format) -> Result<()> {
+    match (format, payload) {
+        (Format::Json, payload) => {
+            let writer = std::io::BufWriter::new(File::create(path)?);
+            match payload {
+                Payload::Search(search) => serde_json::to_writer(writer, &search)?,
+                Payload::MsRun(ms_run) => serde_json::to_writer(writer, &ms_run)?,
+                Payload::Spectrum(spectrum) => serde_json::to_writer(writer, &spectrum)?,
+                Payload::Bundle(bundle) if bundle.search.is_none() && bundle.ms_runs.is_empty() => {
+                    serde_json::to_writer(writer, &bundle.spectra)?
+                }
+                Payload::Bundle(bundle) => serde_json::to_writer(writer, &bundle)?,
+                Payload::Table(table) => serde_json::to_writer(writer, &table)?,
+            }
+        }
+        (Format::Parquet, Payload::Table(mut table)) => {
+            ParquetWriter::new(File::create(path)?).finish(&mut table)?;
+        }
+        (Format::Parquet, payload) => {
+            if path.is_file() {
+                bail!(
+                    "{} is a file, entities are written to a directory",
+                    path.display()
+                );
+            }
+            let bundle = payload.into_bundle()?.complete()?;
+            let search = bundle.search.as_ref().context("search missing")?;
+            fs::create_dir_all(path)?;
+            parquet::write(path, search, &bundle.ms_runs, &bundle.spectra)?;
+        }
+        (Format::Arrow, payload) => {
+            let mut table = match payload {
+                Payload::Table(table) => table,
+                payload => psm_table(&payload.into_bundle()?.spectra)?,
+            };
+            IpcWriter::new(File::create(path)?).finish(&mut table)?;
+        }
+    }
+    Ok(())
+}
+
+/// PSMs of all spectra stacked into one table, prefixed with the MS run, spectrum ID, identification index,
+/// precursor m/z and charge. The PSM tables need to have the same schema.
+///
+fn psm_table(spectra: &[Spectrum]) -> Result<DataFrame> {
+    let mut stacked: Option<DataFrame> = None;
+    for spectrum in spectra {
+        for (index, identification) in spectrum.get_identifications().iter().enumerate() {
+            let psms = match identification.get_psms() {
+                Some(psms) => psms,
+                None => continue,
+            };
+            let height = psms.height();
+            let mut columns = vec![
+                Series::new(
+                    MS_RUN_NAME_COL,
+                    vec![spectrum.get_ms_run().as_str(); height],
+                ),
+                Series::new(
+                    SPECTRUM_ID_COL,
+                    vec![spectrum.get_spectra_id().as_str(); height],
+                ),
+                Series::new(IDENTIFICATION_INDEX_COL, vec![index as u32; height]),
+                Series::new(
+                    PRECURSOR_MZ_COL,
+                    vec![identification.get_precursor().get_mz(); height],
+                ),
+                Series::new(CHARGE_COL, vec![identification.get_charge() as u32; height]),
+            ];
+            for column in psms.get_columns() {
+                if columns.iter().any(|key| key.name() == column.name()) {
+                    bail!(
+                        "PSMs of spectrum `{}` contain the reserved column `{}`",
+                        spectrum.get_spectra_id(),
+                        column.name()
+                    );
+                }
+                columns.push(column.clone());
+            }
+            let psms = DataFrame::new(columns)?;
+            match stacked.as_mut() {
+                Some(stacked) => {
+                    stacked.vstack_mut(&psms).with_context(|| {
+                        format!(
+                            "PSMs of spectrum `{}` differ from the previous ones",
+                            spectrum.get_spectra_id()
+                        )
+                    })?;
+                }
+                None => stacked = Some(psms),
+            }
+        }
+    }
+    Ok(stacked.unwrap_or_default())
+}
+
+fn inspect(payload: Payload) {
+    match payload {
+        Payload::Search(search) => inspect_search(&search),
+        Payload::MsRun(ms_run) => println!("{}", ms_run),
+        Payload::Spectrum(spectrum) => inspect_spectrum(&spectrum),
+        Payload::Bundle(bundle) => {
+            if let Some(search) = &bundle.search {
+                inspect_search(search);
+            }
+            for ms_run in bundle.ms_runs.iter() {
+                println!("{}", ms_run);
+            }
+            let mut spectra_per_run: BTreeMap<&str, usize> = BTreeMap::new();
+            for spectrum in bundle.spectra.iter() {
+                *spectra_per_run
+                    .entry(spectrum.get_ms_run().as_str())
+                    .or_default() += 1;
+            }
+            println!("{} spectra", bundle.spectra.len());
+            for (ms_run_name, num_spectra) in spectra_per_run {
+                println!("    {}: {} spectra", ms_run_name, num_spectra);
+            }
+        }
+        Payload::Table(table) => {
+            println!("PSM table: {} rows", table.height());
+            for field in table.schema().iter_fields() {
+                println!("    {}: {}", field.name(), field.data_type());
+            }
+        }
+    }
+}
+
+fn inspect_search(search: &Search) {
+    println!("{}", search);
+    println!("    schema version: {}", search.get_schema_version());
+    for ms_run_name in search.get_ms_run_names() {
+        println!("    MS run: {}", ms_run_name);
+    }
+    if let Some(parameters) = search.get_parameters() {
+        println!(
+            "    parameters: {}",
+            serde_json::to_string(parameters).unwrap_or_default()
+        );
+    }
+    for error in search.get_errors() {
+        println!(
+            "    error: {}",
+            serde_json::to_string(error).unwrap_or_default()
+        );
+    }
+    if !search.get_transferred_identifications().is_empty() {
+        println!(
+            "    transferred identifications: {}",
+            search.get_transferred_identifications().len()
+        );
+    }
+}
+
+fn inspect_spectrum(spectrum: &Spectrum) {
+    println!("{}", spectrum);
+    println!("    schema version: {}", spectrum.get_schema_version());
+    if let Some(retention_time) = spectrum.get_retention_time() {
+        println!("    retention time: {} s", retention_time);
+    }
+    if let Some(ms_level) = spectrum.get_ms_level() {
+        println!("    MS level: {}", ms_level);
+    }
+    println!("    peaks: {}", spectrum.get_mz().len());
+    for (index, identification) in spectrum.get_identifications().iter().enumerate() {
+        let precursor = identification.get_precursor();
+        println!(
+            "    identification {}: precursor {} m/z, charge {}",
+            index,
+            precursor.get_mz(),
+            precursor.get_charge()
+        );
+        if let Some(psms) = identification.get_psms() {
+            println!(
+                "        {} PSMs: {}",
+                psms.height(),
+                psms.get_column_names().join(", ")
+            );
+        }
+    }
+    if !spectrum.get_sequence_tags().is_empty() {
+        println!("    sequence tags: {}", spectrum.get_sequence_tags().len());
+    }
+    if let Some(payload_digest) = spectrum.get_payload_digest() {
+        println!("    payload digest: {}", payload_digest);
+    }
+}
+
+fn validate(payload: Payload) -> ValidationReport {
+    match payload {
+        Payload::Search(search) => search.validate(),
+        Payload::MsRun(ms_run) => ms_run.validate(),
+        Payload::Spectrum(spectrum) => spectrum.validate(),
+        Payload::Bundle(bundle) => {
+            let mut report = ValidationReport::default();
+            if let Some(search) = &bundle.search {
+                search.validate_into("search", &mut report);
+            }
+            bundle.ms_runs.validate_into("ms_runs", &mut report);
+            bundle.spectra.validate_into("spectra", &mut report);
+            report
+        }
+        Payload::Table(table) => {
+            let mut report = ValidationReport::default();
+            let columns = table.get_column_names();
+            for column in REQUIRED_PSM_COLUMNS {
+                if !columns.contains(&column) {
+                    report.add(
+                        "",
+                        ViolationKind::MissingPsmColumn {
+                            column: column.to_string(),
+                        },
+                    );
+                }
+            }
+            report
+        }
+    }
+}
+
+fn summarize(payload: Payload, fdr_threshold: f64) -> Result<()> {
+    let bundle = payload.into_bundle()?;
+    let config = bundle
+        .search
+        .as_ref()
+        .map(|search| search.get_config().clone())
+        .unwrap_or_else(ExchangeConfig::default);
+    let statistics = SearchStatistics::compute(bundle.spectra.iter(), &config, fdr_threshold)?;
+    if let Some(search) = &bundle.search {
+        println!("{}", search.summary().with_statistics(statistics.clone()));
+    }
+    println!("score column: {}", statistics.get_score_column());
+    println!("FDR threshold: {}", statistics.get_fdr_threshold());
+    println!("spectra: {}", statistics.get_num_spectra());
+    println!(
+        "identified spectra: {} ({:.1} %)",
+        statistics.get_num_identified_spectra(),
+        statistics.get_id_rate() * 100.0
+    );
+    println!("PSMs: {}", statistics.get_num_psms());
+    println!("peptides: {}", statistics.get_num_peptides());
+    println!("proteins: {}", statistics.get_num_proteins());
+    Ok(())
+}
@@ -0,0 +1,335 @@
+//! Typed client for the MaCcoyS results API, returning the exchange entities.
+//! Payloads of older layouts are migrated (see `migrations`), so consumers do not need to deserialize by hand.
+//!
+//! The HTTP requests are made by a `HttpTransport`, so the client works with any HTTP library and runtime.
+//! `ReqwestTransport` is the default transport on top of reqwest, other libraries only need to implement `HttpTransport`.
+//!
+//! Routes, relative to the base URL:
+//!
+//! ```text
+//! searches/<search_uuid>
+//! searches/<search_uuid>/ms-runs
+//! searches/<search_uuid>/ms-runs/<ms_run>
+//! searches/<search_uuid>/ms-runs/<ms_run>/spectra/<spectrum_id>
+//! ```
+
+// std imports
+use std::future::Future;
+
+// 3rd party imports
+use serde_json::Value;
+
+// internal imports
+use crate::migrations::{migrate, Migratable};
+use crate::results_api::{MsRun, Search, Spectrum};
+
+/// Content type requested from the API
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+
+const SEARCHES_PATH: &str = "searches";
+const MS_RUNS_PATH: &str = "ms-runs";
+const SPECTRA_PATH: &str = "spectra";
+
+/// Error of the results API client
+///
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("`{0}` not found")]
+    NotFound(String),
+    #[error("request to `{url}` failed with status {status}: {body}")]
+    Status {
+        url: String,
+        status: u16,
+        body: String,
+    },
+    /// Error of the transport, e.g. connection errors
+    #[error(transparent)]
+    Transport(#[from] anyhow::Error),
+    #[error("could not decode response of `{url}`: {source}")]
+    Decode { url: String, source: anyhow::Error },
+}
+
+/// Response of a `HttpTransport`
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct HttpResponse {
+    status: u16,
+    body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// # Arguments
+    /// * `status` - HTTP status code
+    /// * `body` - Body of the response
+    ///
+    pub fn new(status: u16, body: Vec<u8>) -> Self {
+        Self { status, body }
+    }
+
+    pub fn get_status(&self) -> u16 {
+        self.status
+    }
+
+    pub fn get_body(&self) -> &[u8] {
+        &self.body
+    }
+}
+
+/// Sends the HTTP requests of the `ResultsClient`
+///
+pub trait HttpTransport: Send + Sync {
+    /// Sends a GET request accepting `JSON_CONTENT_TYPE`.
+    /// Responses with error status are returned as `Ok`, only failed requests are errors.
+    ///
+    fn get(&self, url: &str) -> impl Future<Output = Result<HttpResponse, ClientError>> + Send;
+}
+
+/// `HttpTransport` on top of reqwest, requires a tokio runtime
+///
+/// ```
+/// use std::io::{Read, Write};
+/// use std::net::TcpListener;
+/// use maccoys_exchange_entities::client::{ReqwestTransport, ResultsClient};
+/// use maccoys_exchange_entities::results_api::Search;
+///
+/// let search_uuid = "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c";
+/// let search = Search::new(search_uuid.parse().unwrap(), vec!["run".parse().unwrap()]);
+///
+/// // server answering a single request with the search
+/// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+/// let base_url = format!("http://{}/api", listener.local_addr().unwrap());
+/// let body = serde_json::to_string(&search).unwrap();
+/// let server = std::thread::spawn(move || {
+///     let (mut stream, _) = listener.accept().unwrap();
+///     let mut request = [0u8; 1024];
+///     let length = stream.read(&mut request).unwrap();
+///     let response = format!(
+///         "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+///         body.len(),
+///         body
+///     );
+///     stream.write_all(response.as_bytes()).unwrap();
+///     String::from_utf8_lossy(&request[..length]).into_owned()
+/// });
+///
+/// tokio::runtime::Runtime::new().unwrap().block_on(async {
+///     let client = ResultsClient::new(ReqwestTransport::default(), &base_url);
+///     assert_eq!(client.get_search(search_uuid).await.unwrap(), search);
+/// });
+/// let request = server.join().unwrap();
+/// assert!(request.starts_with(&format!("GET /api/searches/{} HTTP/1.1", search_uuid)));
+/// ```
+///
+#[derive(Clone, Debug, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    /// # Arguments
+    /// * `client` - Client sending the requests, e.g. configured with timeouts or authentication headers
+    ///
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+
+    pub fn get_client(&self) -> &reqwest::Client {
+        &self.client
+    }
+}
+
+impl HttpTransport for ReqwestTransport {
+    async fn get(&self, url: &str) -> Result<HttpResponse, ClientError> {
+        let response = self
+            .client
+            .get(url)
+            .header(reqwest::header::ACCEPT, JSON_CONTENT_TYPE)
+            .send()
+            .await
+            .map_err(anyhow::Error::from)?;
+        let status = response.status().as_u16();
+        let body = response.bytes().await.map_err(anyhow::Error::from)?;
+        Ok(HttpResponse::new(status, body.to_vec()))
+    }
+}
+
+/// Client for the results API
+///
+/// ```
+/// use std::collections::HashMap;
+/// use maccoys_exchange_entities::client::{ClientError, HttpResponse, HttpTransport, ResultsClient};
+/// use maccoys_exchange_entities::results_api::{MsRun, Search};
+///
+/// // Transport answering from a map of URLs to JSON bodies
+/// struct StaticTransport(HashMap<String, String>);
+///
+/// impl HttpTransport for StaticTransport {
+///     async fn get(&self, url: &str) -> Result<HttpResponse, ClientError> {
+///         Ok(match self.0.get(url) {
+///             Some(body) => HttpResponse::new(200, body.clone().into_bytes()),
+///             None => HttpResponse::new(404, Vec::new()),
+///         })
+///     }
+/// }
+///
+/// let search_uuid = "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c";
+/// let search = Search::new(search_uuid.parse().unwrap(), vec!["run".parse().unwrap()]);
+/// let ms_run = MsRun::new(search_uuid.parse().unwrap(), "run".parse().unwrap(), Vec::new());
+/// let base_url = "https://maccoys.example.org/api/";
+/// let transport = StaticTransport(HashMap::from([
+///     (
+///         format!("{}searches/{}", base_url, search_uuid),
+///         serde_json::to_string(&search).unwrap(),
+///     ),
+///     (
+///         format!("{}searches/{}/ms-runs", base_url, search_uuid),
+///         serde_json::to_string(&vec![ms_run]).unwrap(),
+///     ),
+/// ]));
+///
+/// tokio::runtime::Runtime::new().unwrap().block_on(async {
+///     let client = ResultsClient::new(transport, base_url);
+///     assert_eq!(client.get_search(search_uuid).await.unwrap(), search);
+///     assert_eq!(client.list_ms_runs(search_uuid).await.unwrap().len(), 1);
+///     assert!(matches!(
+///         client.get_spectrum(search_uuid, "run", "scan=1").await,
+///         Err(ClientError::NotFound(_))
+///     ));
+/// });
+/// ```
+///
+pub struct ResultsClient<T: HttpTransport> {
+    transport: T,
+    base_url: String,
+}
+
+impl<T: HttpTransport> ResultsClient<T> {
+    /// # Arguments
+    /// * `transport` - Transport sending the requests
+    /// * `base_url` - URL of the results API, e.g. `https://maccoys.example.org/api`
+    ///
+    pub fn new(transport: T, base_url: &str) -> Self {
+        Self {
+            transport,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn get_base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn get_transport(&self) -> &T {
+        &self.transport
+    }
+
+    pub async fn get_search(&self, search_uuid: &str) -> Result<Search, ClientError> {
+        let url = self.url(&[SEARCHES_PATH, search_uuid]);
+        decode(&url, self.fetch(&url).await?)
+    }
+
+    /// Returns the MS runs of the given search
+    ///
+    pub async fn list_ms_runs(&self, search_uuid: &str) -> Result<Vec<MsRun>, ClientError> {
+        let url = self.url(&[SEARCHES_PATH, search_uuid, MS_RUNS_PATH]);
+        match self.fetch(&url).await? {
+            Value::Array(values) => values
+                .into_iter()
+                .map(|value| decode(&url, value))
+                .collect(),
+            _ => Err(ClientError::Decode {
+                url,
+                source: anyhow::anyhow!("expected a list of MS runs"),
+            }),
+        }
+    }
+
+    pub async fn get_ms_run(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+    ) -> Result<MsRun, ClientError> {
+        let url = self.url(&[SEARCHES_PATH, search_uuid, MS_RUNS_PATH, ms_run_name]);
+        decode(&url, self.fetch(&url).await?)
+    }
+
+    /// Returns the given spectrum including its identifications
+    ///
+    /// # Arguments
+    /// * `search_uuid` - UUID of the search
+    /// * `ms_run_name` - Name of the MS run
+    /// * `spectrum_id` - ID of the spectrum
+    ///
+    pub async fn get_spectrum(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+        spectrum_id: &str,
+    ) -> Result<Spectrum, ClientError> {
+        let url = self.url(&[
+            SEARCHES_PATH,
+            search_uuid,
+            MS_RUNS_PATH,
+            ms_run_name,
+            SPECTRA_PATH,
+            spectrum_id,
+        ]);
+        decode(&url, self.fetch(&url).await?)
+    }
+
+    /// URL of the given path segments, which are percent-encoded
+    ///
+    fn url(&self, segments: &[&str]) -> String {
+        let mut url = self.base_url.clone();
+        for segment in segments {
+            url.push('/');
+            url.push_str(&encode_segment(segment));
+        }
+        url
+    }
+
+    /// Requests the URL and parses the JSON body
+    ///
+    async fn fetch(&self, url: &str) -> Result<Value, ClientError> {
+        let response = self.transport.get(url).await?;
+        match response.status {
+            200..=299 => {
+                serde_json::from_slice(&response.body).map_err(|err| ClientError::Decode {
+                    url: url.to_string(),
+                    source: err.into(),
+                })
+            }
+            404 => Err(ClientError::NotFound(url.to_string())),
+            status => Err(ClientError::Status {
+                url: url.to_string(),
+                status,
+                body: String::from_utf8_lossy(&response.body).into_owned(),
+            }),
+        }
+    }
+}
+
+/// Deserializes the entity, migrating older layouts
+///
+fn decode<E: Migratable>(url: &str, value: Value) -> Result<E, ClientError> {
+    migrate(value).map_err(|source| ClientError::Decode {
+        url: url.to_string(),
+        source,
+    })
+}
+
+/// Percent-encodes everything except unreserved characters (RFC 3986), so spectrum IDs
+/// like `controllerType=0 controllerNumber=1 scan=1` form a single path segment
+///
+fn encode_segment(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
@@ -0,0 +1,537 @@
+//! Export of search results to OpenMS idXML (<https://openms.de>), e.g. to continue with the
+//! TOPP tools `FalseDiscoveryRate` or `IDFilter`.
+//!
+//! Each MS run becomes an `IdentificationRun` and each identification with PSMs a `PeptideIdentification`.
+//! Modified sequences are written with mass deltas, e.g. `.[+42.0106]PEPM[+15.9949]IDE`.
+
+// std imports
+use std::collections::HashMap;
+use std::io::Write;
+
+// 3rd party imports
+use anyhow::{bail, Context, Result};
+use polars::prelude::*;
+
+// internal imports
+use crate::export::escape;
+use crate::proforma::Peptidoform;
+use crate::results_api::{
+    psm_columns, Identification, ModificationPosition, Search, SearchModification, Spectrum,
+};
+use crate::statistics::fdr::{decoy_flags, DEFAULT_DECOY_PREFIX};
+use crate::tolerance::Tolerance;
+
+/// Date of the identification runs if none is configured, as the exchange entities do not record one
+pub const DEFAULT_DATE: &str = "1970-01-01T00:00:00";
+
+/// Scores written as user parameters of each peptide hit, next to the main score
+const ADDITIONAL_SCORES: [&str; 6] = [
+    psm_columns::XCORR,
+    psm_columns::DELTA_CN,
+    psm_columns::SP_SCORE,
+    psm_columns::E_VALUE,
+    psm_columns::Q_VALUE,
+    psm_columns::PEP,
+];
+
+/// Parameters for writing idXML
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct IdXmlConfig {
+    score_column: String,
+    higher_score_better: bool,
+    decoy_prefix: String,
+    date: String,
+}
+
+impl IdXmlConfig {
+    /// Creates a new configuration with the default decoy prefix and date
+    ///
+    /// # Arguments
+    /// * `score_column` - Column used as main score of the peptide hits
+    /// * `higher_score_better` - True if higher scores are better, e.g. for xcorr
+    ///
+    pub fn new(score_column: &str, higher_score_better: bool) -> Self {
+        Self {
+            score_column: score_column.to_string(),
+            higher_score_better,
+            decoy_prefix: DEFAULT_DECOY_PREFIX.to_string(),
+            date: DEFAULT_DATE.to_string(),
+        }
+    }
+
+    /// Sets the prefix of decoy accessions, used if the PSMs have no `is_decoy` column
+    ///
+    pub fn with_decoy_prefix(mut self, decoy_prefix: &str) -> Self {
+        self.decoy_prefix = decoy_prefix.to_string();
+        self
+    }
+
+    /// Sets the date of the identification runs, e.g. `2024-05-01T12:00:00`
+    ///
+    pub fn with_date(mut self, date: &str) -> Self {
+        self.date = date.to_string();
+        self
+    }
+
+    pub fn get_score_column(&self) -> &str {
+        &self.score_column
+    }
+
+    pub fn is_higher_score_better(&self) -> bool {
+        self.higher_score_better
+    }
+
+    pub fn get_decoy_prefix(&self) -> &str {
+        &self.decoy_prefix
+    }
+
+    pub fn get_date(&self) -> &str {
+        &self.date
+    }
+}
+
+impl Default for IdXmlConfig {
+    /// Comet's expectation value as main score, like OpenMS' CometAdapter
+    ///
+    fn default() -> Self {
+        Self::new(psm_columns::E_VALUE, false)
+    }
+}
+
+/// PeptideHit
+struct PeptideHit {
+    score: f64,
+    sequence: String,
+    charge: u8,
+    aa_before: String,
+    aa_after: String,
+    protein_refs: Vec<String>,
+    is_decoy: bool,
+    scores: Vec<(&'static str, f64)>,
+}
+
+/// PeptideIdentification
+struct PeptideIdentification {
+    mz: f64,
+    retention_time: Option<f64>,
+    spectrum_reference: String,
+    hits: Vec<PeptideHit>,
+}
+
+/// IdentificationRun of a MS run
+#[derive(Default)]
+struct Run {
+    /// (ID, accession) of the protein hits
+    proteins: Vec<(String, String)>,
+    protein_ids: HashMap<String, String>,
+    peptide_identifications: Vec<PeptideIdentification>,
+}
+
+/// Content of the idXML document collected from the spectra before writing,
+/// as the protein hits need to be written before the peptide identifications.
+#[derive(Default)]
+struct Document {
+    runs: Vec<Run>,
+    num_proteins: usize,
+}
+
+impl Document {
+    fn protein_ref(&mut self, run_index: usize, accession: &str) -> String {
+        let run = &mut self.runs[run_index];
+        if let Some(id) = run.protein_ids.get(accession) {
+            return id.clone();
+        }
+        let id = format!("PH_{}", self.num_proteins);
+        self.num_proteins += 1;
+        run.proteins.push((id.clone(), accession.to_string()));
+        run.protein_ids.insert(accession.to_string(), id.clone());
+        id
+    }
+
+    /// Adds the PSMs of the identification as PeptideIdentification of the given run.
+    /// PSMs without main score are skipped.
+    ///
+    fn add_identification(
+        &mut self,
+        run_index: usize,
+        spectrum: &Spectrum,
+        identification: &Identification,
+        config: &IdXmlConfig,
+    ) -> Result<()> {
+        let psms = match identification.get_psms() {
+            Some(psms) => psms,
+            None => return Ok(()),
+        };
+        let scores = psms
+            .column(config.get_score_column())
+            .and_then(|column| column.cast(&DataType::Float64))
+            .with_context(|| {
+                format!(
+                    "score `{}` is not a numeric column",
+                    config.get_score_column()
+                )
+            })?;
+        let additional_scores = ADDITIONAL_SCORES
+            .iter()
+            .filter(|column| **column != config.get_score_column())
+            .filter_map(|column| {
+                let values = psms.column(column).ok()?.cast(&DataType::Float64).ok()?;
+                Some((*column, values))
+            })
+            .collect::<Vec<(&'static str, Series)>>();
+        let is_decoy = match psms.column(psm_columns::IS_DECOY) {
+            Ok(is_decoy) => is_decoy.bool()?.clone(),
+            Err(_) => decoy_flags(psms, config.get_decoy_prefix())?,
+        };
+        let peptidoforms = identification.get_psm_peptidoforms()?.unwrap_or_default();
+        let charge = identification.get_charge();
+
+        let mut hits = Vec::new();
+        for (row_index, row) in identification
+            .iter_psm_rows()
+            .into_iter()
+            .flatten()
+            .enumerate()
+        {
+            let score = match scores.f64()?.get(row_index) {
+                Some(score) => score,
+                None => continue,
+            };
+            let sequence = match peptidoforms.get(row_index) {
+                Some(Some(peptidoform)) => openms_sequence(peptidoform),
+                _ => row.get_str(psm_columns::PLAIN_PEPTIDE)?.to_string(),
+            };
+            let protein_refs = row
+                .get::<&str>(psm_columns::PROTEIN)
+                .unwrap_or_default()
+                .split(',')
+                .map(|accession| accession.trim())
+                .filter(|accession| !accession.is_empty())
+                .map(|accession| self.protein_ref(run_index, accession))
+                .collect();
+            hits.push(PeptideHit {
+                score,
+                sequence,
+                charge,
+                aa_before: row
+                    .get::<&str>(psm_columns::PREV_AA)
+                    .unwrap_or("-")
+                    .to_string(),
+                aa_after: row
+                    .get::<&str>(psm_columns::NEXT_AA)
+                    .unwrap_or("-")
+                    .to_string(),
+                protein_refs,
+                is_decoy: is_decoy.get(row_index).unwrap_or(false),
+                scores: additional_scores
+                    .iter()
+                    .filter_map(|(column, values)| {
+                        Some((*column, values.f64().ok()?.get(row_index)?))
+                    })
+                    .collect(),
+            });
+        }
+        if hits.is_empty() {
+            return Ok(());
+        }
+        self.runs[run_index]
+            .peptide_identifications
+            .push(PeptideIdentification {
+                mz: identification.get_precursor().get_mz(),
+                retention_time: *spectrum.get_retention_time(),
+                spectrum_reference: spectrum.get_spectra_id().to_string(),
+                hits,
+            });
+        Ok(())
+    }
+}
+
+/// Writes the PSMs of the given search and spectra as idXML 1.5 document.
+/// Each spectrum needs to belong to the search and to one of its MS runs.
+///
+/// # Arguments
+/// * `writer` - Writer to write the idXML to
+/// * `search` - Search to export
+/// * `spectra` - Spectra of the search
+/// * `config` - Main score, decoy prefix and date
+///
+/// ```
+/// use maccoys_exchange_entities::export::idxml::{self, IdXmlConfig};
+/// use maccoys_exchange_entities::results_api::{
+///     Identification, MsRunName, Precursor, Search, SearchUuid, Spectrum, SpectrumId,
+/// };
+/// use polars::prelude::*;
+///
+/// let search_uuid = SearchUuid::new("4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c").unwrap();
+/// let ms_run = MsRunName::new("run_a").unwrap();
+/// let search = Search::new(search_uuid.clone(), vec![ms_run.clone()]);
+/// let psms = df!(
+///     "plain_peptide" => &["PEPMIDE", "PEPTIDE"],
+///     "modified_peptide" => &["PEPM[15.9949]IDE", "PEPTIDE"],
+///     "protein" => &["P1", "DECOY_P2"],
+///     "xcorr" => &[2.5, 1.1],
+///     "e-value" => &[0.001, 2.0]
+/// )
+/// .unwrap();
+/// let spectrum = Spectrum::new(
+///     search_uuid,
+///     ms_run,
+///     SpectrumId::new("scan=42").unwrap(),
+///     Vec::new(),
+///     Vec::new(),
+///     vec![Identification::new(None, Some(psms), Precursor::new(417.7, 2))],
+/// );
+///
+/// let idxml = idxml::to_string(&search, &[spectrum], &IdXmlConfig::default()).unwrap();
+/// assert!(idxml.contains(r#"<PeptideHit score="0.001" sequence="PEPM[+15.9949]IDE" charge="2""#));
+/// assert!(idxml.contains(r#"<ProteinHit id="PH_1" accession="DECOY_P2" score="0" sequence="">"#));
+/// assert!(idxml.contains(r#"<UserParam type="string" name="target_decoy" value="decoy"/>"#));
+/// ```
+///
+pub fn write<W: Write>(
+    writer: &mut W,
+    search: &Search,
+    spectra: &[Spectrum],
+    config: &IdXmlConfig,
+) -> Result<()> {
+    let mut document = Document::default();
+    document
+        .runs
+        .resize_with(search.get_ms_run_names().len(), Run::default);
+    for spectrum in spectra {
+        if spectrum.get_search_uuid() != search.get_search_uuid() {
+            bail!(
+                "spectrum `{}` belongs to search `{}` not `{}`",
+                spectrum.get_spectra_id(),
+                spectrum.get_search_uuid(),
+                search.get_search_uuid()
+            );
+        }
+        let run_index = match search
+            .get_ms_run_names()
+            .iter()
+            .position(|name| name == spectrum.get_ms_run())
+        {
+            Some(index) => index,
+            None => bail!(
+                "spectrum `{}` belongs to unknown MS run `{}`",
+                spectrum.get_spectra_id(),
+                spectrum.get_ms_run()
+            ),
+        };
+        for identification in spectrum.get_identifications() {
+            document.add_identification(run_index, spectrum, identification, config)?;
+        }
+    }
+
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<IdXML version="1.5" xsi:noNamespaceSchemaLocation="https://www.openms.de/xml-schema/IdXML_1_5.xsd" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">"#
+    )?;
+    write_search_parameters(writer, search)?;
+    let (engine, engine_version) = search
+        .get_parameters()
+        .as_ref()
+        .and_then(|parameters| parameters.get_engine_versions().first())
+        .map(|engine| (engine.get_name(), engine.get_version()))
+        .unwrap_or(("Comet", ""));
+    for (ms_run_name, run) in search.get_ms_run_names().iter().zip(document.runs.iter()) {
+        writeln!(
+            writer,
+            r#"  <IdentificationRun date="{}" search_engine="{}" search_engine_version="{}" search_parameters_ref="SP_0">"#,
+            escape(config.get_date()),
+            escape(engine),
+            escape(engine_version)
+        )?;
+        writeln!(
+            writer,
+            r#"    <ProteinIdentification score_type="" higher_score_better="true" significance_threshold="0">"#
+        )?;
+        for (id, accession) in run.proteins.iter() {
+            writeln!(
+                writer,
+                r#"      <ProteinHit id="{}" accession="{}" score="0" sequence="">"#,
+                id,
+                escape(accession)
+            )?;
+            write_target_decoy(
+                writer,
+                "        ",
+                accession.starts_with(config.get_decoy_prefix()),
+            )?;
+            writeln!(writer, "      </ProteinHit>")?;
+        }
+        writeln!(
+            writer,
+            r#"      <UserParam type="stringList" name="spectra_data" value="[{}]"/>"#,
+            escape(ms_run_name)
+        )?;
+        writeln!(writer, "    </ProteinIdentification>")?;
+        for peptide_identification in run.peptide_identifications.iter() {
+            write_peptide_identification(writer, peptide_identification, config)?;
+        }
+        writeln!(writer, "  </IdentificationRun>")?;
+    }
+    writeln!(writer, "</IdXML>")?;
+    Ok(())
+}
+
+/// Same as `write` but returns the idXML as string
+///
+pub fn to_string(search: &Search, spectra: &[Spectrum], config: &IdXmlConfig) -> Result<String> {
+    let mut buffer = Vec::new();
+    write(&mut buffer, search, spectra, config)?;
+    Ok(String::from_utf8(buffer)?)
+}
+
+/// Sequence in OpenMS notation, with N- and C-terminal modifications separated by `.`
+///
+fn openms_sequence(peptidoform: &Peptidoform) -> String {
+    let tags = |position: ModificationPosition| {
+        peptidoform
+            .get_modifications()
+            .iter()
+            .filter(|modification| modification.get_position() == position)
+            .map(|modification| format!("[{:+}]", modification.get_mass_delta()))
+            .collect::<String>()
+    };
+    let mut sequence = String::new();
+    let n_term = tags(ModificationPosition::NTerm);
+    if !n_term.is_empty() {
+        sequence.push('.');
+        sequence.push_str(&n_term);
+    }
+    for (idx, residue) in peptidoform.get_sequence().chars().enumerate() {
+        sequence.push(residue);
+        sequence.push_str(&tags(ModificationPosition::Residue(idx)));
+    }
+    let c_term = tags(ModificationPosition::CTerm);
+    if !c_term.is_empty() {
+        sequence.push('.');
+        sequence.push_str(&c_term);
+    }
+    sequence
+}
+
+/// OpenMS names of a search modification, one per residue, e.g. `Oxidation (M)` or `Acetyl (N-term)`
+///
+fn openms_modification_names(modification: &SearchModification) -> Vec<String> {
+    modification
+        .get_residues()
+        .chars()
+        .map(|residue| {
+            let site = match residue {
+                'n' => "N-term".to_string(),
+                'c' => "C-term".to_string(),
+                _ => residue.to_string(),
+            };
+            format!("{} ({})", modification.get_name(), site)
+        })
+        .collect()
+}
+
+fn write_search_parameters<W: Write>(writer: &mut W, search: &Search) -> Result<()> {
+    let parameters = search.get_parameters().as_ref();
+    let tolerance = |tolerance: Option<&Tolerance>| match tolerance {
+        Some(Tolerance::Ppm(value)) => (*value, true),
+        Some(Tolerance::Da(value)) => (*value, false),
+        None => (0.0, false),
+    };
+    let (precursor_tolerance, precursor_tolerance_ppm) =
+        tolerance(parameters.and_then(|parameters| parameters.get_precursor_tolerance().as_ref()));
+    let (fragment_tolerance, fragment_tolerance_ppm) =
+        tolerance(parameters.and_then(|parameters| parameters.get_fragment_tolerance().as_ref()));
+    writeln!(
+        writer,
+        r#"  <SearchParameters id="SP_0" db="{}" db_version="" taxonomy="" mass_type="monoisotopic" charges="" enzyme="{}" missed_cleavages="{}" precursor_peak_tolerance="{}" precursor_peak_tolerance_ppm="{}" peak_mass_tolerance="{}" peak_mass_tolerance_ppm="{}">"#,
+        escape(parameters.map_or("", |parameters| parameters.get_fasta_path())),
+        escape(parameters.map_or("unknown_enzyme", |parameters| parameters.get_enzyme())),
+        parameters.map_or(0, |parameters| parameters.get_missed_cleavages()),
+        precursor_tolerance,
+        precursor_tolerance_ppm,
+        fragment_tolerance,
+        fragment_tolerance_ppm
+    )?;
+    if let Some(parameters) = parameters {
+        for modification in parameters.get_fixed_modifications() {
+            for name in openms_modification_names(modification) {
+                writeln!(
+                    writer,
+                    r#"    <FixedModification name="{}"/>"#,
+                    escape(&name)
+                )?;
+            }
+        }
+        for modification in parameters.get_variable_modifications() {
+            for name in openms_modification_names(modification) {
+                writeln!(
+                    writer,
+                    r#"    <VariableModification name="{}"/>"#,
+                    escape(&name)
+                )?;
+            }
+        }
+    }
+    writeln!(writer, "  </SearchParameters>")?;
+    Ok(())
+}
+
+fn write_peptide_identification<W: Write>(
+    writer: &mut W,
+    peptide_identification: &PeptideIdentification,
+    config: &IdXmlConfig,
+) -> Result<()> {
+    let retention_time = match peptide_identification.retention_time {
+        Some(retention_time) => format!(r#" RT="{}""#, retention_time),
+        None => String::new(),
+    };
+    writeln!(
+        writer,
+        r#"    <PeptideIdentification score_type="{}" higher_score_better="{}" significance_threshold="0" MZ="{}"{} spectrum_reference="{}">"#,
+        escape(config.get_score_column()),
+        config.is_higher_score_better(),
+        peptide_identification.mz,
+        retention_time,
+        escape(&peptide_identification.spectrum_reference)
+    )?;
+    for hit in peptide_identification.hits.iter() {
+        writeln!(
+            writer,
+            r#"      <PeptideHit score="{}" sequence="{}" charge="{}" aa_before="{}" aa_after="{}" protein_refs="{}">"#,
+            hit.score,
+            escape(&hit.sequence),
+            hit.charge,
+            escape(&hit.aa_before),
+            escape(&hit.aa_after),
+            hit.protein_refs.join(" ")
+        )?;
+        write_target_decoy(writer, "        ", hit.is_decoy)?;
+        for (name, value) in hit.scores.iter() {
+            writeln!(
+                writer,
+                r#"        <UserParam type="float" name="{}" value="{}"/>"#,
+                name, value
+            )?;
+        }
+        writeln!(writer, "      </PeptideHit>")?;
+    }
+    writeln!(writer, "    </PeptideIdentification>")?;
+    Ok(())
+}
+
+/// Writes the `target_decoy` user parameter used by OpenMS' FalseDiscoveryRate
+///
+fn write_target_decoy<W: Write>(writer: &mut W, indent: &str, is_decoy: bool) -> Result<()> {
+    writeln!(
+        writer,
+        r#"{}<UserParam type="string" name="target_decoy" value="{}"/>"#,
+        indent,
+        match is_decoy {
+            true => "decoy",
+            false => "target",
+        }
+    )?;
+    Ok(())
+}
@@ -0,0 +1,37 @@
+/// Export of search results to the OpenMS idXML format
+pub mod idxml;
+/// Export of identifications to the HUPO-PSI mzIdentML format
+pub mod mzidentml;
+/// Export of search results to the HUPO-PSI mzTab format
+pub mod mztab;
+/// Percolator PIN export and POUT import
+pub mod percolator;
+/// Export of identified spectra as NIST MSP or SpectraST spectral library
+pub mod speclib;
+
+/// Parses Comet modifications (`<position>_<type>_<mass>`, comma separated) into (position, mass delta) tuples.
+/// Unparsable modifications, e.g. the placeholder `-`, are skipped.
+///
+pub(crate) fn parse_comet_modifications(modifications: &str) -> Vec<(&str, f64)> {
+    modifications
+        .split(',')
+        .filter_map(|modification| {
+            let mut parts = modification.trim().split('_');
+            let position = parts.next()?;
+            let _mod_type = parts.next()?;
+            let mass = parts.next()?.parse::<f64>().ok()?;
+            Some((position, mass))
+        })
+        .collect()
+}
+
+/// Escapes the XML special characters
+///
+pub(crate) fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -0,0 +1,481 @@
+// std imports
+use std::collections::HashMap;
+use std::io::Write;
+
+// 3rd party imports
+use anyhow::{bail, Result};
+
+// internal imports
+use crate::export::{escape, parse_comet_modifications};
+use crate::mass::mass_to_mz;
+use crate::results_api::{psm_columns, MsRun, Search, Spectrum};
+use crate::statistics::fdr::DEFAULT_DECOY_PREFIX;
+
+/// Search engine scores reported as CV params of each SpectrumIdentificationItem as (column, CV accession, CV name)
+const SEARCH_ENGINE_SCORES: [(&str, &str, &str); 4] = [
+    (psm_columns::XCORR, "MS:1002252", "Comet:xcorr"),
+    (psm_columns::DELTA_CN, "MS:1002253", "Comet:deltacn"),
+    (psm_columns::SP_SCORE, "MS:1002255", "Comet:spscore"),
+    (
+        psm_columns::E_VALUE,
+        "MS:1002257",
+        "Comet:expectation value",
+    ),
+];
+
+/// Peptide of the SequenceCollection
+struct Peptide {
+    id: String,
+    sequence: String,
+    modifications: Vec<(String, f64)>,
+}
+
+/// PeptideEvidence of the SequenceCollection
+struct PeptideEvidence {
+    id: String,
+    peptide_ref: String,
+    db_sequence_ref: String,
+    pre: String,
+    post: String,
+    is_decoy: bool,
+}
+
+/// SpectrumIdentificationItem
+struct Item {
+    id: String,
+    rank: u32,
+    charge: u8,
+    experimental_mass_to_charge: f64,
+    calculated_mass_to_charge: Option<f64>,
+    peptide_ref: String,
+    peptide_evidence_refs: Vec<String>,
+    scores: Vec<(&'static str, &'static str, f64)>,
+}
+
+/// SpectrumIdentificationResult
+struct SpectrumResult {
+    id: String,
+    spectrum_id: String,
+    spectra_data_ref: String,
+    items: Vec<Item>,
+}
+
+/// Content of the mzIdentML document collected from the spectra before writing,
+/// as the sequence collection needs to be written before the results.
+#[derive(Default)]
+struct Document {
+    db_sequences: Vec<String>,
+    db_sequence_ids: HashMap<String, String>,
+    peptides: Vec<Peptide>,
+    peptide_ids: HashMap<(String, String), String>,
+    peptide_evidences: Vec<PeptideEvidence>,
+    peptide_evidence_ids: HashMap<(String, String), String>,
+    results: Vec<SpectrumResult>,
+}
+
+impl Document {
+    fn db_sequence_ref(&mut self, accession: &str) -> String {
+        if let Some(id) = self.db_sequence_ids.get(accession) {
+            return id.clone();
+        }
+        let id = format!("DBSeq_{}", self.db_sequences.len() + 1);
+        self.db_sequences.push(accession.to_string());
+        self.db_sequence_ids
+            .insert(accession.to_string(), id.clone());
+        id
+    }
+
+    fn peptide_ref(&mut self, sequence: &str, modifications: &str) -> String {
+        let key = (sequence.to_string(), modifications.to_string());
+        if let Some(id) = self.peptide_ids.get(&key) {
+            return id.clone();
+        }
+        let id = format!("PEP_{}", self.peptides.len() + 1);
+        self.peptides.push(Peptide {
+            id: id.clone(),
+            sequence: sequence.to_string(),
+            modifications: parse_comet_modifications(modifications)
+                .into_iter()
+                .map(|(position, mass)| (position.to_string(), mass))
+                .collect(),
+        });
+        self.peptide_ids.insert(key, id.clone());
+        id
+    }
+
+    fn peptide_evidence_ref(
+        &mut self,
+        peptide_ref: &str,
+        accession: &str,
+        pre: &str,
+        post: &str,
+    ) -> String {
+        let db_sequence_ref = self.db_sequence_ref(accession);
+        let key = (peptide_ref.to_string(), db_sequence_ref.clone());
+        if let Some(id) = self.peptide_evidence_ids.get(&key) {
+            return id.clone();
+        }
+        let id = format!("PE_{}", self.peptide_evidences.len() + 1);
+        self.peptide_evidences.push(PeptideEvidence {
+            id: id.clone(),
+            peptide_ref: peptide_ref.to_string(),
+            db_sequence_ref,
+            pre: pre.to_string(),
+            post: post.to_string(),
+            is_decoy: accession.starts_with(DEFAULT_DECOY_PREFIX),
+        });
+        self.peptide_evidence_ids.insert(key, id.clone());
+        id
+    }
+
+    /// Adds the PSMs of the given spectrum as SpectrumIdentificationResult
+    ///
+    fn add_spectrum(&mut self, spectrum: &Spectrum, spectra_data_ref: String) -> Result<()> {
+        let result_id = format!("SIR_{}", self.results.len() + 1);
+        let mut items = Vec::new();
+        for identification in spectrum.get_identifications() {
+            let rows = match identification.iter_psm_rows() {
+                Some(rows) => rows,
+                None => continue,
+            };
+            let charge = identification.get_charge();
+            for (row_idx, row) in rows.enumerate() {
+                let sequence = row.get_str(psm_columns::PLAIN_PEPTIDE)?;
+                let modifications = row
+                    .get::<Option<&str>>(psm_columns::MODIFICATIONS)
+                    .unwrap_or(None)
+                    .unwrap_or("");
+                let peptide_ref = self.peptide_ref(sequence, modifications);
+                let pre = row.get::<&str>(psm_columns::PREV_AA).unwrap_or("-");
+                let post = row.get::<&str>(psm_columns::NEXT_AA).unwrap_or("-");
+                let peptide_evidence_refs = row
+                    .get::<Option<&str>>(psm_columns::PROTEIN)
+                    .unwrap_or(None)
+                    .unwrap_or("")
+                    .split(',')
+                    .map(|accession| accession.trim())
+                    .filter(|accession| !accession.is_empty())
+                    .map(|accession| self.peptide_evidence_ref(&peptide_ref, accession, pre, post))
+                    .collect();
+                let scores = SEARCH_ENGINE_SCORES
+                    .iter()
+                    .filter_map(|(column, accession, name)| {
+                        Some((*accession, *name, row.get::<f64>(column).ok()?))
+                    })
+                    .collect();
+                items.push(Item {
+                    id: format!("SII_{}_{}", self.results.len() + 1, items.len() + 1),
+                    rank: row
+                        .get::<u32>(psm_columns::RANK)
+                        .unwrap_or(row_idx as u32 + 1),
+                    charge,
+                    experimental_mass_to_charge: identification.get_precursor().get_mz(),
+                    calculated_mass_to_charge: row
+                        .get::<f64>(psm_columns::CALC_NEUTRAL_MASS)
+                        .ok()
+                        .map(|mass| mass_to_mz(mass, charge)),
+                    peptide_ref,
+                    peptide_evidence_refs,
+                    scores,
+                });
+            }
+        }
+        if items.is_empty() {
+            return Ok(());
+        }
+        self.results.push(SpectrumResult {
+            id: result_id,
+            spectrum_id: spectrum.get_spectra_id().to_string(),
+            spectra_data_ref,
+            items,
+        });
+        Ok(())
+    }
+}
+
+/// Writes the PSMs of the given search with its MS runs and spectra as mzIdentML 1.2 document.
+/// Each spectrum needs to belong to the search and to one of its MS runs.
+///
+/// # Arguments
+/// * `writer` - Writer to write the mzIdentML to
+/// * `search` - Search to export
+/// * `ms_runs` - MS runs of the search
+/// * `spectra` - Spectra of the MS runs
+///
+pub fn write<W: Write>(
+    writer: &mut W,
+    search: &Search,
+    ms_runs: &[MsRun],
+    spectra: &[Spectrum],
+) -> Result<()> {
+    for ms_run_name in search.get_ms_run_names() {
+        if !ms_runs
+            .iter()
+            .any(|ms_run| ms_run.get_ms_run() == ms_run_name)
+        {
+            bail!("MS run `{}` of search is missing", ms_run_name);
+        }
+    }
+
+    let mut document = Document::default();
+    for spectrum in spectra {
+        if spectrum.get_search_uuid() != search.get_search_uuid() {
+            bail!(
+                "spectrum `{}` belongs to search `{}` not `{}`",
+                spectrum.get_spectra_id(),
+                spectrum.get_search_uuid(),
+                search.get_search_uuid()
+            );
+        }
+        let ms_run_index = match search
+            .get_ms_run_names()
+            .iter()
+            .position(|name| name == spectrum.get_ms_run())
+        {
+            Some(index) => index + 1,
+            None => bail!(
+                "spectrum `{}` belongs to unknown MS run `{}`",
+                spectrum.get_spectra_id(),
+                spectrum.get_ms_run()
+            ),
+        };
+        document.add_spectrum(spectrum, format!("SD_{}", ms_run_index))?;
+    }
+
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<MzIdentML id="{}" version="1.2.0" xmlns="http://psidev.info/psi/pi/mzIdentML/1.2" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://psidev.info/psi/pi/mzIdentML/1.2 https://raw.githubusercontent.com/HUPO-PSI/mzIdentML/master/schema/mzIdentML1.2.0.xsd">"#,
+        escape(search.get_search_uuid())
+    )?;
+    write_cv_list(writer)?;
+    write_analysis_software_list(writer)?;
+    write_sequence_collection(writer, &document)?;
+    write_analysis_collection(writer, search)?;
+    write_analysis_protocol_collection(writer)?;
+    write_data_collection(writer, search, &document)?;
+    writeln!(writer, "</MzIdentML>")?;
+    Ok(())
+}
+
+/// Same as `write` but returns the mzIdentML as string
+///
+pub fn to_string(search: &Search, ms_runs: &[MsRun], spectra: &[Spectrum]) -> Result<String> {
+    let mut buffer = Vec::new();
+    write(&mut buffer, search, ms_runs, spectra)?;
+    Ok(String::from_utf8(buffer)?)
+}
+
+fn write_cv_list<W: Write>(writer: &mut W) -> Result<()> {
+    writeln!(writer, "  <cvList>")?;
+    writeln!(
+        writer,
+        r#"    <cv id="PSI-MS" fullName="Proteomics Standards Initiative Mass Spectrometry Vocabularies" uri="https://raw.githubusercontent.com/HUPO-PSI/psi-ms-CV/master/psi-ms.obo"/>"#
+    )?;
+    writeln!(
+        writer,
+        r#"    <cv id="UNIMOD" fullName="UNIMOD" uri="http://www.unimod.org/obo/unimod.obo"/>"#
+    )?;
+    writeln!(
+        writer,
+        r#"    <cv id="UO" fullName="Unit Ontology" uri="https://raw.githubusercontent.com/bio-ontology-research-group/unit-ontology/master/unit.obo"/>"#
+    )?;
+    writeln!(writer, "  </cvList>")?;
+    Ok(())
+}
+
+fn write_analysis_software_list<W: Write>(writer: &mut W) -> Result<()> {
+    writeln!(writer, "  <AnalysisSoftwareList>")?;
+    writeln!(
+        writer,
+        r#"    <AnalysisSoftware id="AS_comet" name="Comet">"#
+    )?;
+    writeln!(writer, "      <SoftwareName>")?;
+    writeln!(
+        writer,
+        r#"        <cvParam cvRef="PSI-MS" accession="MS:1002251" name="Comet"/>"#
+    )?;
+    writeln!(writer, "      </SoftwareName>")?;
+    writeln!(writer, "    </AnalysisSoftware>")?;
+    writeln!(writer, "  </AnalysisSoftwareList>")?;
+    Ok(())
+}
+
+fn write_sequence_collection<W: Write>(writer: &mut W, document: &Document) -> Result<()> {
+    writeln!(writer, "  <SequenceCollection>")?;
+    for (i, accession) in document.db_sequences.iter().enumerate() {
+        writeln!(
+            writer,
+            r#"    <DBSequence id="DBSeq_{}" accession="{}" searchDatabase_ref="SDB_1"/>"#,
+            i + 1,
+            escape(accession)
+        )?;
+    }
+    for peptide in document.peptides.iter() {
+        writeln!(writer, r#"    <Peptide id="{}">"#, peptide.id)?;
+        writeln!(
+            writer,
+            "      <PeptideSequence>{}</PeptideSequence>",
+            escape(&peptide.sequence)
+        )?;
+        for (position, mass) in peptide.modifications.iter() {
+            writeln!(
+                writer,
+                r#"      <Modification location="{}" monoisotopicMassDelta="{}">"#,
+                escape(position),
+                mass
+            )?;
+            writeln!(
+                writer,
+                r#"        <cvParam cvRef="PSI-MS" accession="MS:1001460" name="unknown modification"/>"#
+            )?;
+            writeln!(writer, "      </Modification>")?;
+        }
+        writeln!(writer, "    </Peptide>")?;
+    }
+    for evidence in document.peptide_evidences.iter() {
+        writeln!(
+            writer,
+            r#"    <PeptideEvidence id="{}" peptide_ref="{}" dBSequence_ref="{}" pre="{}" post="{}" isDecoy="{}"/>"#,
+            evidence.id,
+            evidence.peptide_ref,
+            evidence.db_sequence_ref,
+            escape(&evidence.pre),
+            escape(&evidence.post),
+            evidence.is_decoy
+        )?;
+    }
+    writeln!(writer, "  </SequenceCollection>")?;
+    Ok(())
+}
+
+fn write_analysis_collection<W: Write>(writer: &mut W, search: &Search) -> Result<()> {
+    writeln!(writer, "  <AnalysisCollection>")?;
+    writeln!(
+        writer,
+        r#"    <SpectrumIdentification id="SI_1" spectrumIdentificationProtocol_ref="SIP_1" spectrumIdentificationList_ref="SIL_1">"#
+    )?;
+    for i in 0..search.get_ms_run_names().len() {
+        writeln!(
+            writer,
+            r#"      <InputSpectra spectraData_ref="SD_{}"/>"#,
+            i + 1
+        )?;
+    }
+    writeln!(
+        writer,
+        r#"      <SearchDatabaseRef searchDatabase_ref="SDB_1"/>"#
+    )?;
+    writeln!(writer, "    </SpectrumIdentification>")?;
+    writeln!(writer, "  </AnalysisCollection>")?;
+    Ok(())
+}
+
+fn write_analysis_protocol_collection<W: Write>(writer: &mut W) -> Result<()> {
+    writeln!(writer, "  <AnalysisProtocolCollection>")?;
+    writeln!(
+        writer,
+        r#"    <SpectrumIdentificationProtocol id="SIP_1" analysisSoftware_ref="AS_comet">"#
+    )?;
+    writeln!(writer, "      <SearchType>")?;
+    writeln!(
+        writer,
+        r#"        <cvParam cvRef="PSI-MS" accession="MS:1001083" name="ms-ms search"/>"#
+    )?;
+    writeln!(writer, "      </SearchType>")?;
+    writeln!(writer, "      <Threshold>")?;
+    writeln!(
+        writer,
+        r#"        <cvParam cvRef="PSI-MS" accession="MS:1001494" name="no threshold"/>"#
+    )?;
+    writeln!(writer, "      </Threshold>")?;
+    writeln!(writer, "    </SpectrumIdentificationProtocol>")?;
+    writeln!(writer, "  </AnalysisProtocolCollection>")?;
+    Ok(())
+}
+
+fn write_data_collection<W: Write>(
+    writer: &mut W,
+    search: &Search,
+    document: &Document,
+) -> Result<()> {
+    writeln!(writer, "  <DataCollection>")?;
+    writeln!(writer, "    <Inputs>")?;
+    writeln!(
+        writer,
+        r#"      <SearchDatabase id="SDB_1" location="unknown">"#
+    )?;
+    writeln!(writer, "        <DatabaseName>")?;
+    writeln!(writer, r#"          <userParam name="unknown"/>"#)?;
+    writeln!(writer, "        </DatabaseName>")?;
+    writeln!(writer, "      </SearchDatabase>")?;
+    for (i, ms_run_name) in search.get_ms_run_names().iter().enumerate() {
+        writeln!(
+            writer,
+            r#"      <SpectraData id="SD_{}" location="{}">"#,
+            i + 1,
+            escape(ms_run_name)
+        )?;
+        writeln!(writer, "        <FileFormat>")?;
+        writeln!(
+            writer,
+            r#"          <cvParam cvRef="PSI-MS" accession="MS:1000584" name="mzML format"/>"#
+        )?;
+        writeln!(writer, "        </FileFormat>")?;
+        writeln!(writer, "        <SpectrumIDFormat>")?;
+        writeln!(
+            writer,
+            r#"          <cvParam cvRef="PSI-MS" accession="MS:1001530" name="mzML unique identifier"/>"#
+        )?;
+        writeln!(writer, "        </SpectrumIDFormat>")?;
+        writeln!(writer, "      </SpectraData>")?;
+    }
+    writeln!(writer, "    </Inputs>")?;
+    writeln!(writer, "    <AnalysisData>")?;
+    writeln!(writer, r#"      <SpectrumIdentificationList id="SIL_1">"#)?;
+    for result in document.results.iter() {
+        writeln!(
+            writer,
+            r#"        <SpectrumIdentificationResult id="{}" spectrumID="{}" spectraData_ref="{}">"#,
+            result.id,
+            escape(&result.spectrum_id),
+            result.spectra_data_ref
+        )?;
+        for item in result.items.iter() {
+            let calculated_mass_to_charge = match item.calculated_mass_to_charge {
+                Some(mz) => format!(r#" calculatedMassToCharge="{}""#, mz),
+                None => String::new(),
+            };
+            writeln!(
+                writer,
+                r#"          <SpectrumIdentificationItem id="{}" rank="{}" chargeState="{}" experimentalMassToCharge="{}"{} passThreshold="true" peptide_ref="{}">"#,
+                item.id,
+                item.rank,
+                item.charge,
+                item.experimental_mass_to_charge,
+                calculated_mass_to_charge,
+                item.peptide_ref
+            )?;
+            for peptide_evidence_ref in item.peptide_evidence_refs.iter() {
+                writeln!(
+                    writer,
+                    r#"            <PeptideEvidenceRef peptideEvidence_ref="{}"/>"#,
+                    peptide_evidence_ref
+                )?;
+            }
+            for (accession, name, value) in item.scores.iter() {
+                writeln!(
+                    writer,
+                    r#"            <cvParam cvRef="PSI-MS" accession="{}" name="{}" value="{}"/>"#,
+                    accession, name, value
+                )?;
+            }
+            writeln!(writer, "          </SpectrumIdentificationItem>")?;
+        }
+        writeln!(writer, "        </SpectrumIdentificationResult>")?;
+    }
+    writeln!(writer, "      </SpectrumIdentificationList>")?;
+    writeln!(writer, "    </AnalysisData>")?;
+    writeln!(writer, "  </DataCollection>")?;
+    Ok(())
+}
@@ -0,0 +1,374 @@
+// std imports
+use std::io::Write;
+use std::path::Path;
+
+// 3rd party imports
+use anyhow::{bail, Result};
+
+// internal imports
+use crate::export::parse_comet_modifications;
+use crate::mass::mass_to_mz;
+use crate::results_api::modification::{unimod_by_mass, unimod_by_name, UNIMOD_TOLERANCE};
+use crate::results_api::{
+    psm_columns, spectrum::Row, Identification, MsRun, Search, SearchModification, Spectrum,
+};
+
+/// Value for empty cells
+const NULL: &str = "null";
+
+/// Search engine scores reported in the PSM section as (column, CV accession, CV name)
+const SEARCH_ENGINE_SCORES: [(&str, &str, &str); 2] = [
+    (psm_columns::XCORR, "MS:1002252", "Comet:xcorr"),
+    (
+        psm_columns::E_VALUE,
+        "MS:1002257",
+        "Comet:expectation value",
+    ),
+];
+
+/// Header of the PSM section
+const PSM_HEADER: [&str; 20] = [
+    "PSH",
+    "sequence",
+    "PSM_ID",
+    "accession",
+    "unique",
+    "database",
+    "database_version",
+    "search_engine",
+    "search_engine_score[1]",
+    "search_engine_score[2]",
+    "modifications",
+    "retention_time",
+    "charge",
+    "exp_mass_to_charge",
+    "calc_mass_to_charge",
+    "spectra_ref",
+    "pre",
+    "post",
+    "start",
+    "end",
+];
+
+/// Writes the given search with its MS runs and spectra as mzTab 1.0.0 (identification, summary mode).
+/// Each spectrum needs to belong to the search and to one of its MS runs.
+///
+/// # Arguments
+/// * `writer` - Writer to write the mzTab to
+/// * `search` - Search to export
+/// * `ms_runs` - MS runs of the search
+/// * `spectra` - Spectra of the MS runs
+///
+pub fn write<W: Write>(
+    writer: &mut W,
+    search: &Search,
+    ms_runs: &[MsRun],
+    spectra: &[Spectrum],
+) -> Result<()> {
+    write_metadata(writer, search, ms_runs)?;
+    writeln!(writer)?;
+    let parameters = search.get_parameters().as_ref();
+    // the FASTA file and its hash identify the searched database
+    let database = parameters.map_or(NULL, |parameters| parameters.get_fasta_path());
+    let database_version = parameters
+        .and_then(|parameters| parameters.get_fasta_hash().as_deref())
+        .unwrap_or(NULL);
+    writeln!(writer, "{}", PSM_HEADER.join("\t"))?;
+
+    let mut psm_id: usize = 0;
+    for spectrum in spectra {
+        if spectrum.get_search_uuid() != search.get_search_uuid() {
+            bail!(
+                "spectrum `{}` belongs to search `{}` not `{}`",
+                spectrum.get_spectra_id(),
+                spectrum.get_search_uuid(),
+                search.get_search_uuid()
+            );
+        }
+        let ms_run_index = match search
+            .get_ms_run_names()
+            .iter()
+            .position(|name| name == spectrum.get_ms_run())
+        {
+            Some(index) => index + 1,
+            None => bail!(
+                "spectrum `{}` belongs to unknown MS run `{}`",
+                spectrum.get_spectra_id(),
+                spectrum.get_ms_run()
+            ),
+        };
+        let spectra_ref = format!("ms_run[{}]:{}", ms_run_index, spectrum.get_spectra_id());
+
+        for identification in spectrum.get_identifications() {
+            let rows = match identification.iter_psm_rows() {
+                Some(rows) => rows,
+                None => continue,
+            };
+            for row in rows {
+                psm_id += 1;
+                write_psm(
+                    writer,
+                    &row,
+                    psm_id,
+                    identification,
+                    &spectra_ref,
+                    database,
+                    database_version,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Same as `write` but returns the mzTab as string
+///
+/// ```
+/// use maccoys_exchange_entities::export::mztab;
+/// use maccoys_exchange_entities::results_api::{
+///     Identification, MsRun, Precursor, Search, SearchModification, SearchParameters, Spectrum,
+///     SpectrumIndex,
+/// };
+/// use maccoys_exchange_entities::results_api::spectrum_index::SourceFormat;
+/// use polars::prelude::*;
+///
+/// let search_uuid = "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c";
+/// let parameters = SearchParameters::new("human.fasta".to_string(), "Trypsin".to_string(), 2)
+///     .with_fasta_hash("9f86d081".to_string())
+///     .with_fixed_modification(SearchModification::new("Carbamidomethyl".to_string(), "C".to_string(), 57.021464))
+///     .with_variable_modification(SearchModification::new("Oxidation".to_string(), "M".to_string(), 15.994915));
+/// let search = Search::new(search_uuid.parse().unwrap(), vec!["run".parse().unwrap(), "indexed_run".parse().unwrap()])
+///     .with_parameters(parameters);
+/// let mut spectrum_index = SpectrumIndex::new();
+/// spectrum_index.add_file("/data/indexed_run.mzML", SourceFormat::Mzml);
+/// let ms_runs = vec![
+///     MsRun::new(search_uuid.parse().unwrap(), "run".parse().unwrap(), Vec::new()),
+///     MsRun::new(search_uuid.parse().unwrap(), "indexed_run".parse().unwrap(), Vec::new())
+///         .with_spectrum_index(spectrum_index),
+/// ];
+/// let psms = df!("plain_peptide" => &["PEPTIDE"], "xcorr" => &[2.5]).unwrap();
+/// let spectra = vec![Spectrum::new(
+///     search_uuid.parse().unwrap(),
+///     "run".parse().unwrap(),
+///     "scan=1".parse().unwrap(),
+///     Vec::new(),
+///     Vec::new(),
+///     vec![Identification::new(None, Some(psms), Precursor::new(400.7, 2))],
+/// )];
+///
+/// let mztab = mztab::to_string(&search, &ms_runs, &spectra).unwrap();
+/// // the location of MS runs without a spectrum index is unknown
+/// assert!(mztab.contains("MTD\tms_run[1]-location\tnull\n"));
+/// assert!(mztab.contains("MTD\tms_run[2]-location\tfile:///data/indexed_run.mzML\n"));
+/// assert!(mztab.contains("PSM\tPEPTIDE\t1\tnull\tnull\thuman.fasta\t9f86d081\t"));
+/// assert!(mztab.contains("MTD\tfixed_mod[1]\t[UNIMOD, UNIMOD:4, Carbamidomethyl, ]\n"));
+/// assert!(mztab.contains("MTD\tfixed_mod[1]-site\tC\n"));
+/// assert!(mztab.contains("MTD\tvariable_mod[1]\t[UNIMOD, UNIMOD:35, Oxidation, ]\n"));
+/// assert!(!mztab.contains("No fixed modifications searched"));
+/// ```
+///
+pub fn to_string(search: &Search, ms_runs: &[MsRun], spectra: &[Spectrum]) -> Result<String> {
+    let mut buffer = Vec::new();
+    write(&mut buffer, search, ms_runs, spectra)?;
+    Ok(String::from_utf8(buffer)?)
+}
+
+/// Writes the MTD section
+///
+fn write_metadata<W: Write>(writer: &mut W, search: &Search, ms_runs: &[MsRun]) -> Result<()> {
+    writeln!(writer, "MTD\tmzTab-version\t1.0.0")?;
+    writeln!(writer, "MTD\tmzTab-mode\tSummary")?;
+    writeln!(writer, "MTD\tmzTab-type\tIdentification")?;
+    writeln!(writer, "MTD\tmzTab-ID\t{}", search.get_search_uuid())?;
+    writeln!(
+        writer,
+        "MTD\tdescription\tMaCcoyS search {}",
+        search.get_search_uuid()
+    )?;
+    for (i, ms_run_name) in search.get_ms_run_names().iter().enumerate() {
+        let ms_run = match ms_runs
+            .iter()
+            .find(|ms_run| ms_run.get_ms_run() == ms_run_name)
+        {
+            Some(ms_run) => ms_run,
+            None => bail!("MS run `{}` of search is missing", ms_run_name),
+        };
+        writeln!(
+            writer,
+            "MTD\tms_run[{}]-location\t{}",
+            i + 1,
+            ms_run_location(ms_run)
+        )?;
+    }
+    for (i, (_, accession, name)) in SEARCH_ENGINE_SCORES.iter().enumerate() {
+        writeln!(
+            writer,
+            "MTD\tpsm_search_engine_score[{}]\t[MS, {}, {}, ]",
+            i + 1,
+            accession,
+            name
+        )?;
+    }
+    let parameters = search.get_parameters().as_ref();
+    write_modifications(
+        writer,
+        "fixed_mod",
+        parameters.map_or(&[], |parameters| parameters.get_fixed_modifications()),
+        "[MS, MS:1002453, No fixed modifications searched, ]",
+    )?;
+    write_modifications(
+        writer,
+        "variable_mod",
+        parameters.map_or(&[], |parameters| parameters.get_variable_modifications()),
+        "[MS, MS:1002454, No variable modifications searched, ]",
+    )?;
+    Ok(())
+}
+
+/// Location of the MS run as `file://` URI, if it was indexed from a single file with an absolute path,
+/// otherwise `null`, which mzTab allows for unknown locations
+///
+fn ms_run_location(ms_run: &MsRun) -> String {
+    let files = match ms_run.get_spectrum_index() {
+        Some(spectrum_index) => spectrum_index.get_files(),
+        None => return NULL.to_string(),
+    };
+    match files.as_slice() {
+        [file] if Path::new(file.get_path()).is_absolute() => {
+            format!("file://{}", file.get_path())
+        }
+        _ => NULL.to_string(),
+    }
+}
+
+/// Writes one `<key>[n]` entry with site and position per modified residue,
+/// or the given CV term if no modifications were searched
+///
+fn write_modifications<W: Write>(
+    writer: &mut W,
+    key: &str,
+    modifications: &[SearchModification],
+    none: &str,
+) -> Result<()> {
+    if modifications.is_empty() {
+        writeln!(writer, "MTD\t{}[1]\t{}", key, none)?;
+        return Ok(());
+    }
+    let mut index = 0;
+    for modification in modifications {
+        let param = match unimod_by_name(modification.get_name())
+            .or_else(|| unimod_by_mass(modification.get_mass_delta(), UNIMOD_TOLERANCE))
+        {
+            Some(entry) => format!("[UNIMOD, UNIMOD:{}, {}, ]", entry.accession, entry.name),
+            None => format!(
+                "[, , {}, {}]",
+                modification.get_name(),
+                modification.get_mass_delta()
+            ),
+        };
+        for residue in modification.get_residues().chars() {
+            let (site, position) = match residue {
+                'n' => ("N-term".to_string(), "Any N-term"),
+                'c' => ("C-term".to_string(), "Any C-term"),
+                _ => (residue.to_string(), "Anywhere"),
+            };
+            index += 1;
+            writeln!(writer, "MTD\t{}[{}]\t{}", key, index, param)?;
+            writeln!(writer, "MTD\t{}[{}]-site\t{}", key, index, site)?;
+            writeln!(writer, "MTD\t{}[{}]-position\t{}", key, index, position)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes one PSM row per protein the PSM maps to
+///
+fn write_psm<W: Write>(
+    writer: &mut W,
+    row: &Row<'_>,
+    psm_id: usize,
+    identification: &Identification,
+    spectra_ref: &str,
+    database: &str,
+    database_version: &str,
+) -> Result<()> {
+    let precursor = identification.get_precursor().get_mz();
+    let charge = identification.get_charge();
+    let sequence = row.get_str(psm_columns::PLAIN_PEPTIDE)?;
+    let proteins = row
+        .get::<Option<&str>>(psm_columns::PROTEIN)
+        .unwrap_or(None);
+    let unique = match row.get::<u32>(psm_columns::PROTEIN_COUNT) {
+        Ok(1) => "1",
+        Ok(_) => "0",
+        Err(_) => NULL,
+    };
+    let scores = SEARCH_ENGINE_SCORES
+        .iter()
+        .map(|(column, _, _)| optional_cell(row.get::<f64>(column).ok()))
+        .collect::<Vec<String>>();
+    let modifications = row
+        .get::<Option<&str>>(psm_columns::MODIFICATIONS)
+        .unwrap_or(None)
+        .map(convert_modifications)
+        .unwrap_or_else(|| NULL.to_string());
+    let retention_time = optional_cell(row.get::<f64>(psm_columns::RETENTION_TIME_SEC).ok());
+    let calc_mass_to_charge = optional_cell(
+        row.get::<f64>(psm_columns::CALC_NEUTRAL_MASS)
+            .ok()
+            .map(|mass| mass_to_mz(mass, charge)),
+    );
+    let pre = row.get::<&str>(psm_columns::PREV_AA).unwrap_or(NULL);
+    let post = row.get::<&str>(psm_columns::NEXT_AA).unwrap_or(NULL);
+
+    let accessions = match proteins {
+        Some(proteins) => proteins.split(',').map(|acc| acc.trim()).collect(),
+        None => vec![NULL],
+    };
+    for accession in accessions {
+        writeln!(
+            writer,
+            "PSM\t{}\t{}\t{}\t{}\t{}\t{}\t[MS, MS:1002251, Comet, ]\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            sequence,
+            psm_id,
+            accession,
+            unique,
+            database,
+            database_version,
+            scores.join("\t"),
+            modifications,
+            retention_time,
+            charge,
+            precursor,
+            calc_mass_to_charge,
+            spectra_ref,
+            pre,
+            post,
+            NULL,
+            NULL,
+        )?;
+    }
+    Ok(())
+}
+
+/// Converts Comet modifications (`<position>_<type>_<mass>`, comma separated) to mzTab `CHEMMOD`s
+///
+fn convert_modifications(modifications: &str) -> String {
+    let converted = parse_comet_modifications(modifications)
+        .into_iter()
+        .map(|(position, mass)| format!("{}-CHEMMOD:{:+}", position, mass))
+        .collect::<Vec<String>>();
+    if converted.is_empty() {
+        return NULL.to_string();
+    }
+    converted.join(",")
+}
+
+/// Converts an optional value into a cell
+///
+fn optional_cell<T: ToString>(value: Option<T>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => NULL.to_string(),
+    }
+}
@@ -0,0 +1,436 @@
+//! Interoperability with Percolator (<https://github.com/percolator/percolator>).
+//!
+//! PSMs are written in the tab separated PIN format and the rescored PSMs of the POUT results
+//! are merged back into the PSM tables as score set `POUT_SCORE_SET` (see `crate::results_api::score_sets`).
+//! PSMs are matched by their PSM ID, see `psm_id`.
+
+// std imports
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+// 3rd party imports
+use anyhow::{bail, Context, Result};
+use polars::prelude::*;
+
+// internal imports
+use crate::mass::mz_to_mass;
+use crate::results_api::{psm_columns, Identification, Spectrum};
+use crate::statistics::fdr::{decoy_flags, DEFAULT_DECOY_PREFIX};
+
+/// Score set the POUT results are merged into
+pub const POUT_SCORE_SET: &str = "percolator";
+
+/// Comet columns used as features by default
+pub const DEFAULT_FEATURES: [&str; 6] = [
+    psm_columns::XCORR,
+    psm_columns::DELTA_CN,
+    psm_columns::SP_SCORE,
+    psm_columns::E_VALUE,
+    psm_columns::IONS_MATCHED,
+    psm_columns::IONS_TOTAL,
+];
+
+/// Parameters for writing PIN files
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct PinConfig {
+    features: Vec<String>,
+    decoy_prefix: String,
+}
+
+impl PinConfig {
+    /// Creates a new configuration
+    ///
+    /// # Arguments
+    /// * `features` - Numeric PSM columns written as features, missing values are written as 0
+    /// * `decoy_prefix` - Prefix of decoy accessions, used if the PSMs have no `is_decoy` column
+    ///
+    pub fn new(features: Vec<String>, decoy_prefix: String) -> Self {
+        Self {
+            features,
+            decoy_prefix,
+        }
+    }
+
+    pub fn get_features(&self) -> &Vec<String> {
+        &self.features
+    }
+
+    pub fn get_decoy_prefix(&self) -> &str {
+        &self.decoy_prefix
+    }
+}
+
+impl Default for PinConfig {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_FEATURES
+                .iter()
+                .map(|feature| feature.to_string())
+                .collect(),
+            DEFAULT_DECOY_PREFIX.to_string(),
+        )
+    }
+}
+
+/// Unique ID of a PSM in PIN and POUT files: `<MS run>:<spectrum ID>:<identification index>:<PSM index>`
+///
+/// # Arguments
+/// * `spectrum` - Spectrum of the PSM
+/// * `identification_index` - Index of the identification within the spectrum
+/// * `row_index` - Index of the PSM within the PSM table
+///
+pub fn psm_id(spectrum: &Spectrum, identification_index: usize, row_index: usize) -> String {
+    format!(
+        "{}:{}:{}:{}",
+        spectrum.get_ms_run(),
+        spectrum.get_spectra_id(),
+        identification_index,
+        row_index
+    )
+}
+
+/// Writes the PSMs of the spectra as Percolator input (PIN).
+/// Columns are `SpecId`, `Label`, `ScanNr`, `ExpMass`, `CalcMass`, the features, `Peptide` and `Proteins`.
+///
+/// # Arguments
+/// * `writer` - Writer to write the PIN to
+/// * `spectra` - Spectra with PSMs
+/// * `config` - Features and decoy prefix
+///
+pub fn write_pin<W: Write>(writer: &mut W, spectra: &[Spectrum], config: &PinConfig) -> Result<()> {
+    writeln!(
+        writer,
+        "SpecId\tLabel\tScanNr\tExpMass\tCalcMass\t{}\tPeptide\tProteins",
+        config.get_features().join("\t")
+    )?;
+    let mut scan_counter: u64 = 0;
+    for spectrum in spectra {
+        scan_counter += 1;
+        for (identification_index, identification) in
+            spectrum.get_identifications().iter().enumerate()
+        {
+            let psms = match identification.get_psms() {
+                Some(psms) => psms,
+                None => continue,
+            };
+            let is_decoy = match psms.column(psm_columns::IS_DECOY) {
+                Ok(is_decoy) => is_decoy.bool()?.clone(),
+                Err(_) => decoy_flags(psms, config.get_decoy_prefix())?,
+            };
+            let features = config
+                .get_features()
+                .iter()
+                .map(|feature| {
+                    psms.column(feature)
+                        .and_then(|column| column.cast(&DataType::Float64))
+                        .with_context(|| format!("feature `{}` is not a numeric column", feature))
+                })
+                .collect::<Result<Vec<Series>>>()?;
+            let scan_numbers = optional_f64_column(psms, psm_columns::SCAN)?;
+            let exp_masses = optional_f64_column(psms, psm_columns::EXP_NEUTRAL_MASS)?;
+            let calc_masses = optional_f64_column(psms, psm_columns::CALC_NEUTRAL_MASS)?;
+            let default_exp_mass = observed_mass(identification);
+
+            for (row_index, row) in identification
+                .iter_psm_rows()
+                .into_iter()
+                .flatten()
+                .enumerate()
+            {
+                let label = match is_decoy.get(row_index).unwrap_or(false) {
+                    true => -1,
+                    false => 1,
+                };
+                let scan_number = match spectrum.get_scan_number() {
+                    Some(scan_number) => *scan_number as u64,
+                    None => scan_numbers
+                        .as_ref()
+                        .and_then(|scan_numbers| scan_numbers.f64().ok()?.get(row_index))
+                        .map(|scan_number| scan_number as u64)
+                        .unwrap_or(scan_counter),
+                };
+                let exp_mass = exp_masses
+                    .as_ref()
+                    .and_then(|exp_masses| exp_masses.f64().ok()?.get(row_index))
+                    .unwrap_or(default_exp_mass);
+                let calc_mass = calc_masses
+                    .as_ref()
+                    .and_then(|calc_masses| calc_masses.f64().ok()?.get(row_index))
+                    .unwrap_or_default();
+                let feature_values = features
+                    .iter()
+                    .map(|feature| {
+                        Ok(feature
+                            .f64()?
+                            .get(row_index)
+                            .unwrap_or_default()
+                            .to_string())
+                    })
+                    .collect::<Result<Vec<String>>>()?;
+                let peptide = row
+                    .get::<Option<&str>>(psm_columns::MODIFIED_PEPTIDE)
+                    .ok()
+                    .flatten()
+                    .or_else(|| row.get::<&str>(psm_columns::PLAIN_PEPTIDE).ok())
+                    .unwrap_or_default();
+                // Comet may already report the flanking residues (`K.PEPTIDE.R`)
+                let peptide = match peptide.as_bytes() {
+                    [_, b'.', .., b'.', _] => peptide.to_string(),
+                    _ => format!(
+                        "{}.{}.{}",
+                        row.get::<&str>(psm_columns::PREV_AA).unwrap_or("-"),
+                        peptide,
+                        row.get::<&str>(psm_columns::NEXT_AA).unwrap_or("-")
+                    ),
+                };
+                let proteins = row
+                    .get::<&str>(psm_columns::PROTEIN)
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(|accession| accession.trim())
+                    .filter(|accession| !accession.is_empty())
+                    .collect::<Vec<&str>>();
+                writeln!(
+                    writer,
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    psm_id(spectrum, identification_index, row_index),
+                    label,
+                    scan_number,
+                    exp_mass,
+                    calc_mass,
+                    feature_values.join("\t"),
+                    peptide,
+                    proteins.join("\t")
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Same as `write_pin` but returns the PIN as string
+///
+pub fn pin_to_string(spectra: &[Spectrum], config: &PinConfig) -> Result<String> {
+    let mut buffer = Vec::new();
+    write_pin(&mut buffer, spectra, config)?;
+    Ok(String::from_utf8(buffer)?)
+}
+
+/// Rescored PSM of a Percolator result (POUT)
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoutRecord {
+    psm_id: String,
+    score: f64,
+    q_value: f64,
+    pep: f64,
+    peptide: String,
+    proteins: Vec<String>,
+}
+
+impl PoutRecord {
+    pub fn get_psm_id(&self) -> &str {
+        &self.psm_id
+    }
+
+    /// SVM score
+    ///
+    pub fn get_score(&self) -> f64 {
+        self.score
+    }
+
+    pub fn get_q_value(&self) -> f64 {
+        self.q_value
+    }
+
+    /// Posterior error probability
+    ///
+    pub fn get_pep(&self) -> f64 {
+        self.pep
+    }
+
+    pub fn get_peptide(&self) -> &str {
+        &self.peptide
+    }
+
+    pub fn get_proteins(&self) -> &Vec<String> {
+        &self.proteins
+    }
+}
+
+/// Reads Percolator results (POUT), e.g. the target and decoy PSM files (`--results-psms`, `--decoy-results-psms`)
+///
+pub fn read_pout<R: BufRead>(reader: R) -> Result<Vec<PoutRecord>> {
+    let mut lines = reader.lines();
+    let header = match lines.next() {
+        Some(header) => header?,
+        None => bail!("POUT is empty"),
+    };
+    let header: Vec<&str> = header.trim_end().split('\t').collect();
+    let position = |column: &str| {
+        header
+            .iter()
+            .position(|name| *name == column)
+            .with_context(|| format!("POUT column `{}` is missing", column))
+    };
+    let psm_id_idx = position("PSMId")?;
+    let score_idx = position("score")?;
+    let q_value_idx = position("q-value")?;
+    let pep_idx = position("posterior_error_prob")?;
+    let peptide_idx = position("peptide")?;
+    let proteins_idx = position("proteinIds")?;
+
+    let mut records = Vec::new();
+    for (line_number, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let cells: Vec<&str> = line.trim_end().split('\t').collect();
+        let cell = |idx: usize| {
+            cells
+                .get(idx)
+                .copied()
+                .with_context(|| format!("POUT line {} is incomplete", line_number + 2))
+        };
+        let number = |idx: usize| -> Result<f64> {
+            let value = cell(idx)?;
+            value.parse::<f64>().with_context(|| {
+                format!(
+                    "POUT line {} contains invalid number `{}`",
+                    line_number + 2,
+                    value
+                )
+            })
+        };
+        records.push(PoutRecord {
+            psm_id: cell(psm_id_idx)?.to_string(),
+            score: number(score_idx)?,
+            q_value: number(q_value_idx)?,
+            pep: number(pep_idx)?,
+            peptide: cell(peptide_idx)?.to_string(),
+            proteins: cells
+                .iter()
+                .skip(proteins_idx)
+                .map(|protein| protein.to_string())
+                .collect(),
+        });
+    }
+    Ok(records)
+}
+
+/// Merges the Percolator results into the PSM tables of the spectra as score set `POUT_SCORE_SET`
+/// with the columns `score`, `q_value` and `pep`. PSMs without result (e.g. if Percolator only reported
+/// the best PSM per spectrum) get null values.
+///
+/// Returns the number of merged PSMs.
+///
+/// ```
+/// use maccoys_exchange_entities::export::percolator::{pin_to_string, merge_pout, psm_id, read_pout, PinConfig};
+/// use maccoys_exchange_entities::results_api::{Identification, Precursor, Spectrum};
+/// use polars::prelude::*;
+///
+/// let psms = df!(
+///     "plain_peptide" => &["PEPTIDE", "PEPTIDES"],
+///     "protein" => &["P1", "DECOY_P2"],
+///     "xcorr" => &[2.5, 1.5]
+/// ).unwrap();
+/// let mut spectra = vec![Spectrum::new(
+///     "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+///     "run".parse().unwrap(),
+///     "scan=1".parse().unwrap(),
+///     vec![175.119],
+///     vec![1000.0],
+///     vec![Identification::new(None, Some(psms), Precursor::new(400.7, 2))],
+/// )];
+///
+/// let config = PinConfig::new(vec!["xcorr".to_string()], "DECOY_".to_string());
+/// let pin = pin_to_string(&spectra, &config).unwrap();
+/// assert!(pin.lines().nth(2).unwrap().starts_with("run:scan=1:0:1\t-1\t"));
+///
+/// let pout = format!(
+///     "PSMId\tscore\tq-value\tposterior_error_prob\tpeptide\tproteinIds\n{}\t1.2\t0.001\t0.002\t-.PEPTIDE.-\tP1\n",
+///     psm_id(&spectra[0], 0, 0)
+/// );
+/// let records = read_pout(pout.as_bytes()).unwrap();
+/// assert_eq!(merge_pout(&mut spectra, records).unwrap(), 1);
+/// let percolator = spectra[0].get_identifications()[0].select_score_set("percolator").unwrap().unwrap();
+/// assert_eq!(percolator.column("q_value").unwrap().f64().unwrap().get(0), Some(0.001));
+/// assert_eq!(percolator.column("q_value").unwrap().f64().unwrap().get(1), None);
+/// ```
+///
+pub fn merge_pout(
+    spectra: &mut [Spectrum],
+    records: impl IntoIterator<Item = PoutRecord>,
+) -> Result<usize> {
+    let mut records: HashMap<String, PoutRecord> = records
+        .into_iter()
+        .map(|record| (record.psm_id.clone(), record))
+        .collect();
+    let mut num_merged = 0;
+    for spectrum in spectra.iter_mut() {
+        let psm_ids: Vec<Vec<String>> = spectrum
+            .get_identifications()
+            .iter()
+            .enumerate()
+            .map(|(identification_index, identification)| {
+                let num_psms = identification
+                    .get_psms()
+                    .as_ref()
+                    .map(|psms| psms.height())
+                    .unwrap_or_default();
+                (0..num_psms)
+                    .map(|row_index| psm_id(spectrum, identification_index, row_index))
+                    .collect()
+            })
+            .collect();
+        for (identification, psm_ids) in spectrum.get_identifications_mut().iter_mut().zip(psm_ids)
+        {
+            if psm_ids.is_empty() {
+                continue;
+            }
+            let matched: Vec<Option<PoutRecord>> = psm_ids
+                .iter()
+                .map(|psm_id| records.remove(psm_id))
+                .collect();
+            num_merged += matched.iter().flatten().count();
+            let column = |name: &str, value: fn(&PoutRecord) -> f64| {
+                Series::new(
+                    name,
+                    matched
+                        .iter()
+                        .map(|record| record.as_ref().map(value))
+                        .collect::<Vec<Option<f64>>>(),
+                )
+            };
+            identification.add_score_set(
+                POUT_SCORE_SET,
+                &DataFrame::new(vec![
+                    column("score", PoutRecord::get_score),
+                    column(psm_columns::Q_VALUE, PoutRecord::get_q_value),
+                    column(psm_columns::PEP, PoutRecord::get_pep),
+                ])?,
+            )?;
+        }
+    }
+    Ok(num_merged)
+}
+
+/// Casts the column to f64, None if it does not exist
+///
+fn optional_f64_column(psms: &DataFrame, column: &str) -> Result<Option<Series>> {
+    match psms.column(column) {
+        Ok(values) => Ok(Some(values.cast(&DataType::Float64)?)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Neutral mass of the monoisotopic precursor, 0 if the charge is unknown
+///
+fn observed_mass(identification: &Identification) -> f64 {
+    match identification.get_charge() {
+        0 => 0.0,
+        charge => mz_to_mass(identification.get_precursor().get_monoisotopic_mz(), charge),
+    }
+}
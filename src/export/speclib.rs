@@ -0,0 +1,266 @@
+// std imports
+use std::io::Write;
+
+// 3rd party imports
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::annotation::{annotate, modification_deltas, AnnotatedSpectrum, AnnotationConfig};
+use crate::mass::{residue_mass, PROTON};
+use crate::results_api::{
+    psm_columns,
+    spectrum::{is_top_ranked, Row},
+    Spectrum,
+};
+
+/// Spectral library format
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LibraryFormat {
+    /// NIST MSP
+    Msp,
+    /// SpectraST
+    Sptxt,
+}
+
+/// Library entry of a confidently identified PSM
+///
+struct Entry<'a> {
+    spectrum: &'a Spectrum,
+    annotated: AnnotatedSpectrum,
+    modifications: Vec<f64>,
+    charge: u8,
+    precursor_mz: f64,
+    neutral_mass: f64,
+    prev_aa: &'a str,
+    next_aa: &'a str,
+}
+
+/// Writes the top ranked PSMs of the given spectra with a q-value of at most `max_q_value` as spectral library.
+/// PSMs without q-value (see `Identification::append_fdr`) are skipped. Peaks are annotated with the fragment ions
+/// of the peptide (see `annotation::annotate`), unannotated peaks are marked with `?`.
+///
+/// Modifications are written as mass deltas, in the MSP comment as `Mods=<count>/<position>,<residue>,<delta>`
+/// (0-based position) and in the SpectraST name as nominal mass of the modified residue, e.g. `M[147]`.
+///
+/// # Arguments
+/// * `writer` - Writer to write the library to
+/// * `spectra` - Spectra with identifications
+/// * `format` - Library format
+/// * `max_q_value` - Maximum q-value of the PSMs to include
+/// * `config` - Annotation parameters
+///
+pub fn write<W: Write>(
+    writer: &mut W,
+    spectra: &[Spectrum],
+    format: LibraryFormat,
+    max_q_value: f64,
+    config: &AnnotationConfig,
+) -> Result<()> {
+    let mut lib_id: usize = 0;
+    for spectrum in spectra {
+        for identification in spectrum.get_identifications() {
+            let rows = match identification.iter_psm_rows() {
+                Some(rows) => rows,
+                None => continue,
+            };
+            for row in rows {
+                if !is_top_ranked(&row)? {
+                    continue;
+                }
+                match row.get::<Option<f64>>(psm_columns::Q_VALUE) {
+                    Ok(Some(q_value)) if q_value <= max_q_value => (),
+                    _ => continue,
+                }
+                let entry = entry(
+                    spectrum,
+                    &row,
+                    identification.get_precursor().get_mz(),
+                    identification.get_charge(),
+                    config,
+                )?;
+                match format {
+                    LibraryFormat::Msp => write_msp_entry(writer, &entry)?,
+                    LibraryFormat::Sptxt => write_sptxt_entry(writer, &entry, lib_id)?,
+                }
+                lib_id += 1;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Same as `write` but returns the library as string
+///
+pub fn to_string(
+    spectra: &[Spectrum],
+    format: LibraryFormat,
+    max_q_value: f64,
+    config: &AnnotationConfig,
+) -> Result<String> {
+    let mut buffer = Vec::new();
+    write(&mut buffer, spectra, format, max_q_value, config)?;
+    Ok(String::from_utf8(buffer)?)
+}
+
+fn entry<'a>(
+    spectrum: &'a Spectrum,
+    row: &'a Row<'a>,
+    precursor_mz: f64,
+    charge: u8,
+    config: &AnnotationConfig,
+) -> Result<Entry<'a>> {
+    let annotated = annotate(spectrum, row, config)?;
+    let modifications = match row.get::<Option<&str>>(psm_columns::MODIFICATIONS) {
+        Ok(Some(modifications)) => modification_deltas(annotated.get_sequence(), modifications)?,
+        _ => vec![0.0; annotated.get_sequence().chars().count()],
+    };
+    let neutral_mass = match row.get::<f64>(psm_columns::CALC_NEUTRAL_MASS) {
+        Ok(mass) => mass,
+        Err(_) => (precursor_mz - PROTON) * charge as f64,
+    };
+    Ok(Entry {
+        spectrum,
+        annotated,
+        modifications,
+        charge,
+        precursor_mz,
+        neutral_mass,
+        prev_aa: row.get::<&str>(psm_columns::PREV_AA).unwrap_or("X"),
+        next_aa: row.get::<&str>(psm_columns::NEXT_AA).unwrap_or("X"),
+    })
+}
+
+fn write_msp_entry<W: Write>(writer: &mut W, entry: &Entry<'_>) -> Result<()> {
+    let sequence = entry.annotated.get_sequence();
+    writeln!(writer, "Name: {}/{}", sequence, entry.charge)?;
+    writeln!(writer, "MW: {}", entry.neutral_mass)?;
+    writeln!(writer, "PrecursorMZ: {}", entry.precursor_mz)?;
+    write!(
+        writer,
+        "Comment: Spec=Raw Mods={} Parent={} Scan={}",
+        msp_modifications(sequence, &entry.modifications),
+        entry.precursor_mz,
+        entry.spectrum.get_spectra_id()
+    )?;
+    if let Some(retention_time) = entry.spectrum.get_retention_time() {
+        write!(writer, " RetentionTime={}", retention_time)?;
+    }
+    writeln!(writer)?;
+    writeln!(writer, "Num peaks: {}", entry.annotated.get_mz().len())?;
+    for (idx, (mz, intensity)) in entry
+        .annotated
+        .get_mz()
+        .iter()
+        .zip(entry.annotated.get_intensity().iter())
+        .enumerate()
+    {
+        writeln!(
+            writer,
+            "{}\t{}\t\"{}\"",
+            mz,
+            intensity,
+            peak_annotation(&entry.annotated, idx)
+        )?;
+    }
+    writeln!(writer)?;
+    Ok(())
+}
+
+fn write_sptxt_entry<W: Write>(writer: &mut W, entry: &Entry<'_>, lib_id: usize) -> Result<()> {
+    let sequence = sptxt_sequence(entry.annotated.get_sequence(), &entry.modifications);
+    writeln!(writer, "Name: {}/{}", sequence, entry.charge)?;
+    writeln!(writer, "LibID: {}", lib_id)?;
+    writeln!(writer, "MW: {}", entry.neutral_mass)?;
+    writeln!(writer, "PrecursorMZ: {}", entry.precursor_mz)?;
+    writeln!(writer, "Status: Normal")?;
+    writeln!(
+        writer,
+        "FullName: {}.{}.{}/{}",
+        entry.prev_aa, sequence, entry.next_aa, entry.charge
+    )?;
+    write!(
+        writer,
+        "Comment: Spec=Raw Mods={} RawSpectrum={}",
+        msp_modifications(entry.annotated.get_sequence(), &entry.modifications),
+        entry.spectrum.get_spectra_id()
+    )?;
+    if let Some(retention_time) = entry.spectrum.get_retention_time() {
+        write!(writer, " RetentionTime={}", retention_time)?;
+    }
+    writeln!(writer)?;
+    writeln!(writer, "NumPeaks: {}", entry.annotated.get_mz().len())?;
+    for (idx, (mz, intensity)) in entry
+        .annotated
+        .get_mz()
+        .iter()
+        .zip(entry.annotated.get_intensity().iter())
+        .enumerate()
+    {
+        writeln!(
+            writer,
+            "{}\t{}\t{}",
+            mz,
+            intensity,
+            peak_annotation(&entry.annotated, idx)
+        )?;
+    }
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// Annotations of the peak as `<label>/<m/z error>`, comma separated, or `?` if the peak is not annotated
+///
+fn peak_annotation(annotated: &AnnotatedSpectrum, idx: usize) -> String {
+    let annotations = &annotated.get_annotations()[idx];
+    if annotations.is_empty() {
+        return "?".to_string();
+    }
+    annotations
+        .iter()
+        .map(|annotation| {
+            format!(
+                "{}/{:.4}",
+                annotation.get_label(),
+                annotation.get_mz_error()
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Modifications as `<count>/<position>,<residue>,<delta>/...` or `0` if unmodified
+///
+fn msp_modifications(sequence: &str, modifications: &[f64]) -> String {
+    let modified = sequence
+        .chars()
+        .zip(modifications.iter())
+        .enumerate()
+        .filter(|(_, (_, delta))| **delta != 0.0)
+        .map(|(idx, (residue, delta))| format!("{},{},{:+}", idx, residue, delta))
+        .collect::<Vec<String>>();
+    if modified.is_empty() {
+        return "0".to_string();
+    }
+    format!("{}/{}", modified.len(), modified.join("/"))
+}
+
+/// Sequence with the nominal mass of modified residues in brackets, e.g. `PEPM[147]IDE`
+///
+fn sptxt_sequence(sequence: &str, modifications: &[f64]) -> String {
+    sequence
+        .chars()
+        .zip(modifications.iter())
+        .map(|(residue, delta)| {
+            if *delta == 0.0 {
+                return residue.to_string();
+            }
+            match residue_mass(residue) {
+                Some(mass) => format!("{}[{}]", residue, (mass + delta).round()),
+                None => residue.to_string(),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,243 @@
+//! GraphQL mapping of the exchange entities, so a GraphQL results API can be served without a second model.
+//!
+//! `SDL` contains the GraphQL type definitions of `Search`, `MsRun`, `Spectrum`, `Identification` and `Precursor`
+//! and a `Query` type mirroring `ResultStore`. `GraphQlObject` converts the entities into values matching these types,
+//! which can be handed to a dynamic schema of any GraphQL executor, e.g. `async_graphql::dynamic`.
+//! DataFrames (PSMs and goodness of fits) are exposed as lists of rows, each row a `JSON` object of column name to value.
+//! Nested objects without a GraphQL type of their own, e.g. the search parameters, are exposed as `JSON` as well.
+//! As GraphQL's `Int` is a signed 32-bit integer, unsigned integers which may exceed it are exposed as the custom scalars
+//! `UInt32` (a JSON number) and `UInt64` (a decimal string, as JSON numbers above 2^53 lose precision in JavaScript).
+
+// 3rd party imports
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+// internal imports
+use crate::results_api::chunked::any_value_to_json;
+use crate::results_api::spectrum::RowIter;
+use crate::results_api::{Identification, MsRun, Precursor, Search, Spectrum};
+
+/// GraphQL type definitions of the entities
+///
+pub const SDL: &str = r#""""Arbitrary JSON value, e.g. a row of a PSM table"""
+scalar JSON
+
+"""Unsigned 32-bit integer, serialized as JSON number"""
+scalar UInt32
+
+"""Unsigned 64-bit integer, serialized as decimal string"""
+scalar UInt64
+
+type Query {
+  search(searchUuid: ID!): Search
+  msRuns(searchUuid: ID!): [MsRun!]!
+  spectrum(searchUuid: ID!, msRunName: String!, spectrumId: String!): Spectrum
+}
+
+type Search {
+  searchUuid: ID!
+  schemaVersion: UInt32!
+  revision: UInt64!
+  msRunNames: [String!]!
+  parameters: JSON
+  config: JSON!
+  errors: [JSON!]!
+  quantification: JSON
+}
+
+type MsRun {
+  searchUuid: ID!
+  msRunName: String!
+  schemaVersion: UInt32!
+  revision: UInt64!
+  spectraIds: [String!]!
+  errors: [JSON!]!
+}
+
+type Spectrum {
+  searchUuid: ID!
+  msRunName: String!
+  spectrumId: String!
+  schemaVersion: UInt32!
+  revision: UInt64!
+  retentionTime: Float
+  ionMobility: Float
+  msLevel: Int
+  scanNumber: UInt32
+  mz: [Float!]!
+  intensity: [Float!]!
+  identifications: [Identification!]!
+  sequenceTags: [JSON!]!
+  payloadDigest: String
+}
+
+type Identification {
+  precursor: Precursor!
+  charge: Int!
+  psmColumns: [String!]!
+  psms: [JSON!]
+  goodnesses: [JSON!]
+}
+
+type Precursor {
+  mz: Float!
+  charge: Int!
+  monoisotopicMz: Float!
+  intensity: Float
+  isolationWindowLowerOffset: Float
+  isolationWindowUpperOffset: Float
+  monoisotopicCorrection: Int
+}
+"#;
+
+/// Entity with a GraphQL object type in `SDL`
+///
+/// ```
+/// use maccoys_exchange_entities::graphql::{GraphQlObject, SDL};
+/// use maccoys_exchange_entities::results_api::{Identification, Precursor, Spectrum};
+/// use polars::prelude::*;
+///
+/// let psms = df!("plain_peptide" => &["PEPTIDE"], "xcorr" => &[2.5]).unwrap();
+/// let spectrum = Spectrum::new(
+///     "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+///     "run".parse().unwrap(),
+///     "scan=1".parse().unwrap(),
+///     vec![100.0, 200.0],
+///     vec![1.0, 2.0],
+///     vec![Identification::new(None, Some(psms), Precursor::new(400.7, 2))],
+/// );
+///
+/// let value = spectrum.with_revision(u64::MAX).to_graphql().unwrap();
+/// assert_eq!(value["spectrumId"], "scan=1");
+/// assert_eq!(value["revision"], "18446744073709551615");
+/// let identification = &value["identifications"][0];
+/// assert_eq!(identification["precursor"]["charge"], 2);
+/// assert_eq!(identification["psms"][0]["plain_peptide"], "PEPTIDE");
+/// assert!(identification["goodnesses"].is_null());
+///
+/// // every field is declared in the SDL
+/// assert!(SDL.contains(&format!("type {} {{", Spectrum::TYPE_NAME)));
+/// for field in value.as_object().unwrap().keys() {
+///     assert!(SDL.contains(&format!("  {}:", field)), "{} not declared", field);
+/// }
+/// ```
+///
+pub trait GraphQlObject {
+    /// Name of the GraphQL object type
+    const TYPE_NAME: &'static str;
+
+    /// Converts the entity into a value of the GraphQL object type
+    ///
+    fn to_graphql(&self) -> Result<Value>;
+}
+
+impl GraphQlObject for Search {
+    const TYPE_NAME: &'static str = "Search";
+
+    fn to_graphql(&self) -> Result<Value> {
+        Ok(json!({
+            "searchUuid": self.get_search_uuid().as_str(),
+            "schemaVersion": self.get_schema_version(),
+            "revision": self.get_revision().to_string(),
+            "msRunNames": self.get_ms_run_names(),
+            "parameters": to_json(self.get_parameters())?,
+            "config": to_json(self.get_config())?,
+            "errors": to_json(self.get_errors())?,
+            "quantification": to_json(self.get_quantification())?,
+        }))
+    }
+}
+
+impl GraphQlObject for MsRun {
+    const TYPE_NAME: &'static str = "MsRun";
+
+    fn to_graphql(&self) -> Result<Value> {
+        Ok(json!({
+            "searchUuid": self.get_search_uuid().as_str(),
+            "msRunName": self.get_ms_run().as_str(),
+            "schemaVersion": self.get_schema_version(),
+            "revision": self.get_revision().to_string(),
+            "spectraIds": self.get_spectra_ids(),
+            "errors": to_json(self.get_errors())?,
+        }))
+    }
+}
+
+impl GraphQlObject for Spectrum {
+    const TYPE_NAME: &'static str = "Spectrum";
+
+    fn to_graphql(&self) -> Result<Value> {
+        Ok(json!({
+            "searchUuid": self.get_search_uuid().as_str(),
+            "msRunName": self.get_ms_run().as_str(),
+            "spectrumId": self.get_spectra_id().as_str(),
+            "schemaVersion": self.get_schema_version(),
+            "revision": self.get_revision().to_string(),
+            "retentionTime": self.get_retention_time(),
+            "ionMobility": self.get_ion_mobility(),
+            "msLevel": self.get_ms_level(),
+            "scanNumber": self.get_scan_number(),
+            "mz": self.get_mz(),
+            "intensity": self.get_intensity(),
+            "identifications": self
+                .get_identifications()
+                .iter()
+                .map(GraphQlObject::to_graphql)
+                .collect::<Result<Vec<Value>>>()?,
+            "sequenceTags": to_json(self.get_sequence_tags())?,
+            "payloadDigest": self.get_payload_digest(),
+        }))
+    }
+}
+
+impl GraphQlObject for Identification {
+    const TYPE_NAME: &'static str = "Identification";
+
+    fn to_graphql(&self) -> Result<Value> {
+        let psm_columns = match self.get_psms() {
+            Some(psms) => psms.get_column_names(),
+            None => Vec::new(),
+        };
+        Ok(json!({
+            "precursor": self.get_precursor().to_graphql()?,
+            "charge": self.get_charge(),
+            "psmColumns": psm_columns,
+            "psms": self.iter_psm_rows().map(rows_to_graphql),
+            "goodnesses": self.iter_goodness_rows().map(rows_to_graphql),
+        }))
+    }
+}
+
+impl GraphQlObject for Precursor {
+    const TYPE_NAME: &'static str = "Precursor";
+
+    fn to_graphql(&self) -> Result<Value> {
+        Ok(json!({
+            "mz": self.get_mz(),
+            "charge": self.get_charge(),
+            "monoisotopicMz": self.get_monoisotopic_mz(),
+            "intensity": self.get_intensity(),
+            "isolationWindowLowerOffset": self.get_isolation_window_lower_offset(),
+            "isolationWindowUpperOffset": self.get_isolation_window_upper_offset(),
+            "monoisotopicCorrection": self.get_monoisotopic_correction(),
+        }))
+    }
+}
+
+/// Rows as objects of column name to value
+///
+fn rows_to_graphql(rows: RowIter<'_>) -> Vec<Value> {
+    rows.map(|row| {
+        Value::Object(
+            row.iter_named()
+                .map(|(col_name, value)| (col_name.to_string(), any_value_to_json(value)))
+                .collect::<Map<String, Value>>(),
+        )
+    })
+    .collect()
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<Value> {
+    Ok(serde_json::to_value(value)?)
+}
@@ -0,0 +1,320 @@
+//! Reads and writes spectra as Mascot Generic Format (MGF).
+//! The spectrum ID is stored in `TITLE`, so spectra written by this module keep their ID when read again.
+//! The charges of all identifications of a spectrum are written to one `CHARGE` line, e.g. `CHARGE=2+ and 3+`,
+//! and converted back to one identification per charge.
+
+// std imports
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+
+// 3rd party imports
+use anyhow::{bail, Context, Result};
+
+// internal imports
+use crate::results_api::{Identification, Precursor, Spectrum, SpectrumId};
+
+const BEGIN_IONS: &str = "BEGIN IONS";
+const END_IONS: &str = "END IONS";
+
+impl Spectrum {
+    /// Returns the spectrum as MGF block from `BEGIN IONS` to `END IONS`.
+    /// The precursor m/z and intensity are taken from the first identification.
+    ///
+    pub fn to_mgf_block(&self) -> String {
+        let mut block = String::new();
+        // writing to a string does not fail
+        let _ = writeln!(block, "{}", BEGIN_IONS);
+        let _ = writeln!(block, "TITLE={}", self.get_spectra_id());
+        if let Some(precursor) = self
+            .get_identifications()
+            .first()
+            .map(|ident| ident.get_precursor())
+        {
+            match precursor.get_intensity() {
+                Some(intensity) => {
+                    let _ = writeln!(block, "PEPMASS={} {}", precursor.get_mz(), intensity);
+                }
+                None => {
+                    let _ = writeln!(block, "PEPMASS={}", precursor.get_mz());
+                }
+            }
+        }
+        let charges = self
+            .get_identifications()
+            .iter()
+            .map(|ident| ident.get_charge())
+            .filter(|charge| *charge > 0)
+            .map(|charge| format!("{}+", charge))
+            .collect::<Vec<String>>();
+        if !charges.is_empty() {
+            let _ = writeln!(block, "CHARGE={}", charges.join(" and "));
+        }
+        if let Some(retention_time) = self.get_retention_time() {
+            let _ = writeln!(block, "RTINSECONDS={}", retention_time);
+        }
+        if let Some(scan_number) = self.get_scan_number() {
+            let _ = writeln!(block, "SCANS={}", scan_number);
+        }
+        for (mz, intensity) in self.get_mz().iter().zip(self.get_intensity().iter()) {
+            let _ = writeln!(block, "{} {}", mz, intensity);
+        }
+        let _ = writeln!(block, "{}", END_IONS);
+        block
+    }
+}
+
+/// Writes the spectra as MGF
+///
+/// # Arguments
+/// * `writer` - Writer to write the MGF to
+/// * `spectra` - Spectra to write
+///
+pub fn write<W: Write>(writer: &mut W, spectra: &[Spectrum]) -> Result<()> {
+    for spectrum in spectra {
+        writer.write_all(spectrum.to_mgf_block().as_bytes())?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Same as `write` but returns the MGF as string
+///
+pub fn to_string(spectra: &[Spectrum]) -> String {
+    spectra
+        .iter()
+        .map(|spectrum| spectrum.to_mgf_block())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Spectrum while parsing
+///
+#[derive(Default)]
+struct Block {
+    title: Option<String>,
+    precursor_mz: Option<f64>,
+    precursor_intensity: Option<f64>,
+    charges: Vec<u8>,
+    retention_time: Option<f64>,
+    scan_number: Option<u32>,
+    peaks: Vec<(f64, f64)>,
+}
+
+impl Block {
+    fn into_spectrum(
+        mut self,
+        search_uuid: &str,
+        ms_run_name: &str,
+        untitled_id: String,
+    ) -> Result<Spectrum> {
+        // titles are free text, e.g. file paths, which are not valid spectrum IDs
+        let spectrum_id = match self.title {
+            Some(title) => SpectrumId::sanitized(&title).to_string(),
+            None => untitled_id,
+        };
+        self.peaks.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let identifications = match self.precursor_mz {
+            Some(mz) => {
+                if self.charges.is_empty() {
+                    self.charges.push(0);
+                }
+                self.charges
+                    .iter()
+                    .map(|charge| {
+                        Identification::new(
+                            None,
+                            None,
+                            Precursor::new(mz, *charge).with_intensity(self.precursor_intensity),
+                        )
+                    })
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+        Ok(Spectrum::builder()
+            .search_uuid(search_uuid)
+            .ms_run_name(ms_run_name)
+            .spectrum_id(spectrum_id.as_str())
+            .mz(self.peaks.iter().map(|(mz, _)| *mz).collect())
+            .intensity(self.peaks.iter().map(|(_, intensity)| *intensity).collect())
+            .identifications(identifications)
+            .build()
+            .with_context(|| format!("invalid spectrum `{}`", spectrum_id))?
+            .with_retention_time(self.retention_time)
+            .with_scan_number(self.scan_number))
+    }
+}
+
+/// Parses charges like `2+`, `2+ and 3+` or `2,3`
+///
+fn parse_charges(value: &str) -> Result<Vec<u8>> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .map(|charge| charge.trim())
+        .filter(|charge| !charge.is_empty() && *charge != "and")
+        .map(|charge| {
+            charge
+                .trim_end_matches(['+', '-'])
+                .parse::<u8>()
+                .with_context(|| format!("invalid charge `{}`", charge))
+        })
+        .collect()
+}
+
+/// Reads all spectra of the given MGF.
+/// Spectra without `TITLE` get the ID `index=<index of the spectrum in the file>`,
+/// titles which are no valid spectrum ID are sanitized (see `SpectrumId::sanitized`).
+///
+/// # Arguments
+/// * `reader` - Reader of the MGF
+/// * `search_uuid` - UUID of the search the spectra are assigned to
+/// * `ms_run_name` - Name of the MS run the spectra are assigned to
+///
+/// ```
+/// use maccoys_exchange_entities::io::mgf;
+///
+/// let mgf = "BEGIN IONS\nTITLE=C:\\data\\run.raw scan=1\n100.0 1.0\nEND IONS\n\
+///     BEGIN IONS\nTITLE=scan=2\n200.0 2.0\nEND IONS\n";
+/// let spectra = mgf::read(mgf.as_bytes(), "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c", "run").unwrap();
+/// assert_eq!(spectra[0].get_spectra_id(), "C:_data_run.raw scan=1");
+/// assert_eq!(spectra[1].get_spectra_id(), "scan=2");
+/// ```
+///
+pub fn read<R: BufRead>(reader: R, search_uuid: &str, ms_run_name: &str) -> Result<Vec<Spectrum>> {
+    read_with_ids(reader, search_uuid, ms_run_name, untitled_id)
+}
+
+/// ID of a spectrum without `TITLE`
+///
+fn untitled_id(index: usize) -> String {
+    format!("index={}", index)
+}
+
+/// Same as `read`, with the IDs of spectra without `TITLE` created from their index in the reader,
+/// e.g. for reading single blocks of a file
+///
+pub(crate) fn read_with_ids<R: BufRead>(
+    reader: R,
+    search_uuid: &str,
+    ms_run_name: &str,
+    untitled_id: impl Fn(usize) -> String,
+) -> Result<Vec<Spectrum>> {
+    let mut spectra = Vec::new();
+    let mut block: Option<Block> = None;
+    for (line_idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        let line_context = || format!("invalid MGF in line {}", line_idx + 1);
+        if line == BEGIN_IONS {
+            block = Some(Block::default());
+            continue;
+        }
+        let current = match block.as_mut() {
+            Some(current) => current,
+            // global parameters and comments
+            None => continue,
+        };
+        if line == END_IONS {
+            if let Some(current) = block.take() {
+                let untitled_id = untitled_id(spectra.len());
+                spectra.push(current.into_spectrum(search_uuid, ms_run_name, untitled_id)?);
+            }
+        } else if line.is_empty() || line.starts_with(['#', ';', '!', '/']) {
+            continue;
+        } else if let Some((key, value)) = line.split_once('=') {
+            match key.trim().to_ascii_uppercase().as_str() {
+                "TITLE" => current.title = Some(value.trim().to_string()),
+                "PEPMASS" => {
+                    let mut values = value.split_whitespace();
+                    current.precursor_mz = values
+                        .next()
+                        .map(str::parse)
+                        .transpose()
+                        .with_context(line_context)?;
+                    current.precursor_intensity = values
+                        .next()
+                        .map(str::parse)
+                        .transpose()
+                        .with_context(line_context)?;
+                }
+                "CHARGE" => current.charges = parse_charges(value).with_context(line_context)?,
+                "RTINSECONDS" => {
+                    current.retention_time = Some(value.trim().parse().with_context(line_context)?)
+                }
+                "SCANS" => current.scan_number = value.trim().parse().ok(),
+                _ => (),
+            }
+        } else {
+            let mut values = line.split_whitespace();
+            match (values.next(), values.next()) {
+                (Some(mz), Some(intensity)) => current.peaks.push((
+                    mz.parse().with_context(line_context)?,
+                    intensity.parse().with_context(line_context)?,
+                )),
+                _ => bail!("{}: expected m/z and intensity", line_context()),
+            }
+        }
+    }
+    if block.is_some() {
+        bail!("MGF ended without `{}`", END_IONS);
+    }
+    Ok(spectra)
+}
+
+/// Locates the spectra of the given MGF, see `crate::results_api::spectrum_index::SpectrumIndex`
+///
+/// # Arguments
+/// * `reader` - Reader of the MGF
+///
+/// Returns the ID, byte offset of `BEGIN IONS` and length up to the end of the `END IONS` line of each spectrum.
+/// IDs are derived from the titles like in `read`, so they match the IDs of the read spectra.
+///
+/// ```
+/// use maccoys_exchange_entities::io::mgf;
+///
+/// let mgf = "BEGIN IONS\nTITLE=C:\\data\\run.raw scan=1\n100.0 1.0\nEND IONS\n";
+/// let spectra = mgf::read(mgf.as_bytes(), "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c", "run").unwrap();
+/// let locations = mgf::index(mgf.as_bytes()).unwrap();
+/// assert_eq!(locations[0].0, spectra[0].get_spectra_id().to_string());
+/// assert_eq!(locations[0], ("C:_data_run.raw scan=1".to_string(), 0, mgf.len() as u64));
+/// ```
+///
+pub fn index<R: BufRead>(mut reader: R) -> Result<Vec<(String, u64, u64)>> {
+    let mut locations = Vec::new();
+    let mut block: Option<(Option<String>, u64)> = None;
+    let mut line: Vec<u8> = Vec::new();
+    let mut position: u64 = 0;
+    loop {
+        line.clear();
+        let line_length = reader.read_until(b'\n', &mut line)? as u64;
+        if line_length == 0 {
+            break;
+        }
+        let line_start = position;
+        position += line_length;
+        let text = String::from_utf8_lossy(&line);
+        let text = text.trim();
+        if text == BEGIN_IONS {
+            block = Some((None, line_start));
+        } else if text == END_IONS {
+            if let Some((title, offset)) = block.take() {
+                let spectrum_id = match title {
+                    Some(title) => SpectrumId::sanitized(&title).to_string(),
+                    None => untitled_id(locations.len()),
+                };
+                locations.push((spectrum_id, offset, position - offset));
+            }
+        } else if let (Some((title, _)), Some((key, value))) =
+            (block.as_mut(), text.split_once('='))
+        {
+            if key.trim().eq_ignore_ascii_case("TITLE") {
+                *title = Some(value.trim().to_string());
+            }
+        }
+    }
+    if block.is_some() {
+        bail!("MGF ended without `{}`", END_IONS);
+    }
+    Ok(locations)
+}
@@ -0,0 +1,7 @@
+pub mod mgf;
+#[cfg(feature = "mzdata")]
+pub mod mzdata;
+#[cfg(feature = "mzml")]
+pub mod mzml;
+#[cfg(feature = "pepxml")]
+pub mod pepxml;
@@ -0,0 +1,205 @@
+//! Conversions between spectra and the spectrum types of the [mzdata](https://crates.io/crates/mzdata) crate,
+//! e.g. for handing spectra read by one of mzdata's readers to the pipeline or search results back to mzdata's writers.
+//!
+//! mzdata has no notion of searches, MS runs or PSMs, so the search UUID and MS run name are passed in
+//! when converting into a `Spectrum` and each precursor ion becomes an `Identification` without PSMs and goodnesses,
+//! like in `io::mzml`. Converting back keeps peaks, retention time, MS level and precursors only.
+
+// 3rd party imports
+use anyhow::{Context, Result};
+use mzdata::mzpeaks::{CentroidLike, CentroidPeak, DeconvolutedCentroidLike, MZPeakSetType};
+use mzdata::spectrum::{
+    IsolationWindow, IsolationWindowState, MultiLayerSpectrum, Precursor as MzDataPrecursor,
+    ScanEvent, SelectedIon, SignalContinuity, SpectrumDescription, SpectrumLike,
+};
+
+// internal imports
+use crate::results_api::{Identification, Precursor, Spectrum};
+use crate::spectrum_id::NativeSpectrumId;
+
+impl Spectrum {
+    /// Converts a spectrum of mzdata, e.g. one read by `mzdata::MzMLReader`.
+    /// Profile data is taken as is, so centroid the spectrum beforehand if necessary.
+    ///
+    /// # Arguments
+    /// * `spectrum` - mzdata spectrum
+    /// * `search_uuid` - Search UUID
+    /// * `ms_run_name` - MS run name
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::{Identification, Precursor, Spectrum};
+    /// use mzdata::spectrum::MultiLayerSpectrum;
+    ///
+    /// let precursor = Precursor::new(400.7, 2).with_isolation_window(Some(0.8), Some(0.8));
+    /// let spectrum = Spectrum::builder()
+    ///     .search_uuid("4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c")
+    ///     .ms_run_name("run")
+    ///     .spectrum_id("controllerType=0 controllerNumber=1 scan=42")
+    ///     .retention_time(1520.4)
+    ///     .ms_level(2)
+    ///     .scan_number(42)
+    ///     .mz(vec![100.0, 200.0])
+    ///     .intensity(vec![10.0, 20.0])
+    ///     .add_identification(Identification::new(None, None, precursor))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let converted: MultiLayerSpectrum = (&spectrum).into();
+    /// let roundtripped = Spectrum::from_mzdata(
+    ///     &converted,
+    ///     "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c",
+    ///     "run",
+    /// )
+    /// .unwrap();
+    ///
+    /// assert!(roundtripped.approx_eq(&spectrum, 1e-3));
+    /// ```
+    ///
+    pub fn from_mzdata<C, D, S>(spectrum: &S, search_uuid: &str, ms_run_name: &str) -> Result<Self>
+    where
+        C: CentroidLike,
+        D: DeconvolutedCentroidLike,
+        S: SpectrumLike<C, D>,
+    {
+        let (mz, intensity): (Vec<f64>, Vec<f64>) = spectrum
+            .peaks()
+            .iter()
+            .map(|peak| (peak.mz, peak.intensity as f64))
+            .unzip();
+        let identifications = spectrum
+            .description()
+            .precursor
+            .iter()
+            .flat_map(precursors_from_mzdata)
+            .map(|precursor| Identification::new(None, None, precursor))
+            .collect();
+        let retention_time = spectrum
+            .acquisition()
+            .first_scan()
+            .map(|scan| scan.start_time * 60.0);
+        Ok(Spectrum::builder()
+            .search_uuid(search_uuid)
+            .ms_run_name(ms_run_name)
+            .spectrum_id(spectrum.id())
+            .mz(mz)
+            .intensity(intensity)
+            .identifications(identifications)
+            .build()
+            .with_context(|| format!("invalid spectrum `{}`", spectrum.id()))?
+            .with_retention_time(retention_time)
+            .with_ion_mobility(spectrum.ion_mobility())
+            .with_ms_level(Some(spectrum.ms_level()))
+            .with_scan_number(NativeSpectrumId::parse(spectrum.id()).get_scan_number()))
+    }
+}
+
+impl<C, D> From<&Spectrum> for MultiLayerSpectrum<C, D>
+where
+    C: CentroidLike + From<CentroidPeak>,
+    D: DeconvolutedCentroidLike,
+{
+    /// Converts the spectrum into a centroided mzdata spectrum
+    ///
+    fn from(spectrum: &Spectrum) -> Self {
+        let mut description = SpectrumDescription {
+            id: spectrum.get_spectra_id().to_string(),
+            ms_level: spectrum.get_ms_level().unwrap_or(2),
+            signal_continuity: SignalContinuity::Centroid,
+            ..Default::default()
+        };
+        if let Some(retention_time) = spectrum.get_retention_time() {
+            description.acquisition.scans.push(ScanEvent {
+                start_time: retention_time / 60.0,
+                ..Default::default()
+            });
+        }
+        description.precursor = spectrum
+            .get_identifications()
+            .iter()
+            .map(|identification| identification.get_precursor().into())
+            .collect();
+        let peaks = spectrum
+            .get_mz()
+            .iter()
+            .zip(spectrum.get_intensity())
+            .enumerate()
+            .map(|(index, (mz, intensity))| {
+                CentroidPeak::new(*mz, *intensity as f32, index as u32).into()
+            })
+            .collect();
+        MultiLayerSpectrum::new(description, None, Some(MZPeakSetType::new(peaks)), None)
+    }
+}
+
+impl From<&Precursor> for MzDataPrecursor {
+    fn from(precursor: &Precursor) -> Self {
+        let mz = precursor.get_mz();
+        let isolation_window = match (
+            precursor.get_isolation_window_lower_offset(),
+            precursor.get_isolation_window_upper_offset(),
+        ) {
+            (Some(lower_offset), Some(upper_offset)) => IsolationWindow::new(
+                mz as f32,
+                (mz - lower_offset) as f32,
+                (mz + upper_offset) as f32,
+                IsolationWindowState::Complete,
+            ),
+            _ => IsolationWindow::default(),
+        };
+        MzDataPrecursor {
+            ions: vec![SelectedIon {
+                mz,
+                intensity: precursor.get_intensity().unwrap_or_default() as f32,
+                charge: match precursor.get_charge() {
+                    0 => None,
+                    charge => Some(charge as i32),
+                },
+                params: None,
+            }],
+            isolation_window,
+            ..Default::default()
+        }
+    }
+}
+
+/// Converts each selected ion of the precursor, falling back to the isolation window target
+/// if no ion was selected. Unknown charges become 0.
+///
+fn precursors_from_mzdata(precursor: &MzDataPrecursor) -> Vec<Precursor> {
+    let window = &precursor.isolation_window;
+    let has_window = !matches!(
+        window.flags,
+        IsolationWindowState::Unknown | IsolationWindowState::NoIsolation
+    );
+    let with_window = |precursor: Precursor| {
+        let mz = precursor.get_mz();
+        match has_window {
+            true => precursor.with_isolation_window(
+                Some(mz - window.lower_bound as f64),
+                Some(window.upper_bound as f64 - mz),
+            ),
+            false => precursor,
+        }
+    };
+    if precursor.ions.is_empty() {
+        return match has_window && window.target > 0.0 {
+            true => vec![with_window(Precursor::new(window.target as f64, 0))],
+            false => Vec::new(),
+        };
+    }
+    precursor
+        .ions
+        .iter()
+        .map(|ion| {
+            with_window(
+                Precursor::new(
+                    ion.mz,
+                    ion.charge
+                        .and_then(|charge| u8::try_from(charge).ok())
+                        .unwrap_or_default(),
+                )
+                .with_intensity((ion.intensity > 0.0).then_some(ion.intensity as f64)),
+            )
+        })
+        .collect()
+}
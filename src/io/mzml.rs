@@ -0,0 +1,589 @@
+//! Reads spectra from mzML files, indexed (`<indexedmzML>`) and non-indexed.
+//! The index is not used, the file is streamed and the spectra are read in order of appearance.
+//! Each precursor of a spectrum becomes an `Identification` without PSMs and goodnesses,
+//! which can be filled in after the search.
+//! `referenceableParamGroupRef`s are resolved, so shared parameters of the header are applied where referenced.
+//! Binary data arrays need to be uncompressed or zlib compressed 32/64-bit floats,
+//! other compressions (e.g. MS-Numpress) and data types are reported as error.
+
+// std imports
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+// 3rd party imports
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use flate2::read::ZlibDecoder;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+// internal imports
+use crate::results_api::{Identification, Precursor, Spectrum};
+use crate::spectrum_id::NativeSpectrumId;
+
+/// Byte offset and length of an element
+///
+pub type ByteRange = (u64, u64);
+
+/// ID, byte offset and length of a spectrum
+///
+pub type SpectrumLocation = (String, u64, u64);
+
+// controlled vocabulary accessions
+const MS_LEVEL: &str = "MS:1000511";
+const SCAN_START_TIME: &str = "MS:1000016";
+const INVERSE_REDUCED_ION_MOBILITY: &str = "MS:1002815";
+const ION_MOBILITY_DRIFT_TIME: &str = "MS:1002476";
+const ISOLATION_WINDOW_TARGET_MZ: &str = "MS:1000827";
+const ISOLATION_WINDOW_LOWER_OFFSET: &str = "MS:1000828";
+const ISOLATION_WINDOW_UPPER_OFFSET: &str = "MS:1000829";
+const SELECTED_ION_MZ: &str = "MS:1000744";
+const CHARGE_STATE: &str = "MS:1000041";
+const PEAK_INTENSITY: &str = "MS:1000042";
+const FLOAT_32: &str = "MS:1000521";
+const FLOAT_64: &str = "MS:1000523";
+const ZLIB_COMPRESSION: &str = "MS:1000574";
+const NO_COMPRESSION: &str = "MS:1000576";
+const MZ_ARRAY: &str = "MS:1000514";
+const INTENSITY_ARRAY: &str = "MS:1000515";
+const UNIT_MINUTE: &str = "UO:0000031";
+
+/// Compressions and data types of binary data arrays which can not be decoded, as (accession, name)
+const UNSUPPORTED_BINARY_TERMS: [(&str, &str); 14] = [
+    ("MS:1000519", "32-bit integer"),
+    ("MS:1000522", "64-bit integer"),
+    ("MS:1001479", "null-terminated ASCII string"),
+    ("MS:1002312", "MS-Numpress linear prediction compression"),
+    ("MS:1002313", "MS-Numpress positive integer compression"),
+    ("MS:1002314", "MS-Numpress short logged float compression"),
+    (
+        "MS:1002746",
+        "MS-Numpress linear prediction compression followed by zlib compression",
+    ),
+    (
+        "MS:1002747",
+        "MS-Numpress positive integer compression followed by zlib compression",
+    ),
+    (
+        "MS:1002748",
+        "MS-Numpress short logged float compression followed by zlib compression",
+    ),
+    (
+        "MS:1003088",
+        "truncation, delta prediction and zlib compression",
+    ),
+    (
+        "MS:1003089",
+        "truncation, linear prediction and zlib compression",
+    ),
+    ("MS:1003090", "truncation and zlib compression"),
+    ("MS:1003091", "byte shuffled zstd compression"),
+    ("MS:1003092", "zstd compression"),
+];
+
+/// Controlled vocabulary parameter, kept for `referenceableParamGroup`s
+///
+#[derive(Clone)]
+struct CvParam {
+    accession: String,
+    value: Option<String>,
+    unit_accession: Option<String>,
+}
+
+impl CvParam {
+    fn from_element(element: &BytesStart<'_>) -> Result<Self> {
+        Ok(Self {
+            accession: attribute(element, "accession")?.unwrap_or_default(),
+            value: attribute(element, "value")?,
+            unit_accession: attribute(element, "unitAccession")?,
+        })
+    }
+}
+
+/// Precursor while parsing
+///
+#[derive(Default)]
+struct PrecursorState {
+    target_mz: Option<f64>,
+    lower_offset: Option<f64>,
+    upper_offset: Option<f64>,
+    selected_mz: Option<f64>,
+    charge: Option<u8>,
+    intensity: Option<f64>,
+}
+
+impl PrecursorState {
+    fn into_precursor(self) -> Option<Precursor> {
+        Some(
+            Precursor::new(
+                self.selected_mz.or(self.target_mz)?,
+                self.charge.unwrap_or_default(),
+            )
+            .with_intensity(self.intensity)
+            .with_isolation_window(self.lower_offset, self.upper_offset),
+        )
+    }
+}
+
+/// Binary data array while parsing
+///
+#[derive(Default)]
+struct BinaryArrayState {
+    is_mz: bool,
+    is_intensity: bool,
+    is_float_32: bool,
+    is_zlib: bool,
+    /// Name of the unsupported compression or data type
+    unsupported: Option<&'static str>,
+    data: String,
+}
+
+impl BinaryArrayState {
+    fn decode(&self) -> Result<Vec<f64>> {
+        if let Some(unsupported) = self.unsupported {
+            bail!("unsupported binary data array encoding `{}`", unsupported);
+        }
+        let mut bytes = STANDARD
+            .decode(self.data.trim())
+            .context("invalid base64 in binary data array")?;
+        if self.is_zlib {
+            let mut decompressed = Vec::new();
+            std::io::Read::read_to_end(&mut ZlibDecoder::new(bytes.as_slice()), &mut decompressed)
+                .context("invalid zlib data in binary data array")?;
+            bytes = decompressed;
+        }
+        if self.is_float_32 {
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) as f64)
+                .collect())
+        } else {
+            Ok(bytes
+                .chunks_exact(8)
+                .map(|chunk| {
+                    f64::from_le_bytes([
+                        chunk[0], chunk[1], chunk[2], chunk[3], chunk[4], chunk[5], chunk[6],
+                        chunk[7],
+                    ])
+                })
+                .collect())
+        }
+    }
+}
+
+/// Spectrum while parsing
+///
+#[derive(Default)]
+struct SpectrumState {
+    id: String,
+    ms_level: Option<u8>,
+    retention_time: Option<f64>,
+    ion_mobility: Option<f64>,
+    precursors: Vec<PrecursorState>,
+    mz: Vec<f64>,
+    intensity: Vec<f64>,
+}
+
+impl SpectrumState {
+    fn into_spectrum(self, search_uuid: &str, ms_run_name: &str) -> Result<Spectrum> {
+        let scan_number = NativeSpectrumId::parse(&self.id).get_scan_number();
+        let identifications = self
+            .precursors
+            .into_iter()
+            .filter_map(PrecursorState::into_precursor)
+            .map(|precursor| Identification::new(None, None, precursor))
+            .collect();
+        Ok(Spectrum::builder()
+            .search_uuid(search_uuid)
+            .ms_run_name(ms_run_name)
+            .spectrum_id(self.id.as_str())
+            .mz(self.mz)
+            .intensity(self.intensity)
+            .identifications(identifications)
+            .build()
+            .with_context(|| format!("invalid spectrum `{}`", self.id))?
+            .with_retention_time(self.retention_time)
+            .with_ion_mobility(self.ion_mobility)
+            .with_ms_level(self.ms_level)
+            .with_scan_number(scan_number))
+    }
+}
+
+/// Elements the parser is currently in
+///
+#[derive(Default)]
+struct ParserState {
+    spectrum: Option<SpectrumState>,
+    precursor: Option<PrecursorState>,
+    binary_array: Option<BinaryArrayState>,
+    in_isolation_window: bool,
+    in_selected_ion: bool,
+}
+
+impl ParserState {
+    /// Applies the parameter to the innermost element it can belong to
+    ///
+    fn apply(&mut self, param: &CvParam) -> Result<()> {
+        let accession = param.accession.as_str();
+        let value = &param.value;
+        if let Some(binary_array) = self.binary_array.as_mut() {
+            match accession {
+                MZ_ARRAY => binary_array.is_mz = true,
+                INTENSITY_ARRAY => binary_array.is_intensity = true,
+                FLOAT_32 => binary_array.is_float_32 = true,
+                FLOAT_64 => binary_array.is_float_32 = false,
+                ZLIB_COMPRESSION => binary_array.is_zlib = true,
+                NO_COMPRESSION => binary_array.is_zlib = false,
+                _ => {
+                    if let Some((_, name)) = UNSUPPORTED_BINARY_TERMS
+                        .iter()
+                        .find(|(unsupported, _)| *unsupported == accession)
+                    {
+                        binary_array.unsupported = Some(name);
+                    }
+                }
+            }
+        } else if let Some(precursor) = self.precursor.as_mut() {
+            match accession {
+                ISOLATION_WINDOW_TARGET_MZ if self.in_isolation_window => {
+                    precursor.target_mz = Some(parse_value(accession, value)?)
+                }
+                ISOLATION_WINDOW_LOWER_OFFSET if self.in_isolation_window => {
+                    precursor.lower_offset = Some(parse_value(accession, value)?)
+                }
+                ISOLATION_WINDOW_UPPER_OFFSET if self.in_isolation_window => {
+                    precursor.upper_offset = Some(parse_value(accession, value)?)
+                }
+                SELECTED_ION_MZ if self.in_selected_ion => {
+                    precursor.selected_mz = Some(parse_value(accession, value)?)
+                }
+                CHARGE_STATE if self.in_selected_ion => {
+                    precursor.charge = Some(parse_value(accession, value)?)
+                }
+                PEAK_INTENSITY if self.in_selected_ion => {
+                    precursor.intensity = Some(parse_value(accession, value)?)
+                }
+                _ => (),
+            }
+        } else if let Some(spectrum) = self.spectrum.as_mut() {
+            match accession {
+                MS_LEVEL => spectrum.ms_level = Some(parse_value(accession, value)?),
+                SCAN_START_TIME => {
+                    let time: f64 = parse_value(accession, value)?;
+                    spectrum.retention_time = match param.unit_accession.as_deref() {
+                        Some(UNIT_MINUTE) => Some(time * 60.0),
+                        _ => Some(time),
+                    };
+                }
+                INVERSE_REDUCED_ION_MOBILITY | ION_MOBILITY_DRIFT_TIME => {
+                    spectrum.ion_mobility = Some(parse_value(accession, value)?)
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn attribute(element: &BytesStart<'_>, name: &str) -> Result<Option<String>> {
+    match element.try_get_attribute(name)? {
+        Some(attribute) => Ok(Some(attribute.unescape_value()?.into_owned())),
+        None => Ok(None),
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(accession: &str, value: &Option<String>) -> Result<T> {
+    match value.as_deref().map(str::parse::<T>) {
+        Some(Ok(value)) => Ok(value),
+        _ => bail!("cvParam {} has invalid value `{:?}`", accession, value),
+    }
+}
+
+/// Reads all spectra of the given mzML.
+///
+/// # Arguments
+/// * `reader` - Reader of the mzML
+/// * `search_uuid` - UUID of the search the spectra are assigned to
+/// * `ms_run_name` - Name of the MS run the spectra are assigned to
+///
+/// ```
+/// use maccoys_exchange_entities::io::mzml;
+///
+/// let search_uuid = "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c";
+/// let mzml = |compression: &str| format!(r#"<mzML>
+/// <referenceableParamGroupList count="2">
+///   <referenceableParamGroup id="ms2"><cvParam accession="MS:1000511" value="2"/></referenceableParamGroup>
+///   <referenceableParamGroup id="mz_params">
+///     <cvParam accession="MS:1000514"/><cvParam accession="MS:1000523"/><cvParam accession="{}"/>
+///   </referenceableParamGroup>
+/// </referenceableParamGroupList>
+/// <run><spectrumList count="1"><spectrum id="scan=1" index="0">
+///   <referenceableParamGroupRef ref="ms2"/>
+///   <binaryDataArrayList count="2">
+///     <binaryDataArray><referenceableParamGroupRef ref="mz_params"/><binary>AAAAAAAAWUA=</binary></binaryDataArray>
+///     <binaryDataArray>
+///       <cvParam accession="MS:1000515"/><cvParam accession="MS:1000523"/><binary>AAAAAAAA8D8=</binary>
+///     </binaryDataArray>
+///   </binaryDataArrayList>
+/// </spectrum></spectrumList></run></mzML>"#, compression);
+///
+/// let spectra = mzml::read(mzml("MS:1000576").as_bytes(), search_uuid, "run").unwrap();
+/// assert_eq!(spectra[0].get_ms_level(), &Some(2));
+/// assert_eq!(spectra[0].get_mz(), &[100.0]);
+///
+/// let err = mzml::read(mzml("MS:1002312").as_bytes(), search_uuid, "run").unwrap_err();
+/// assert!(format!("{:#}", err).contains("MS-Numpress linear prediction compression"));
+/// ```
+///
+pub fn read<R: BufRead>(reader: R, search_uuid: &str, ms_run_name: &str) -> Result<Vec<Spectrum>> {
+    let mut reader = Reader::from_reader(reader);
+    let mut buffer = Vec::new();
+    let mut spectra = Vec::new();
+
+    let mut param_groups: HashMap<String, Vec<CvParam>> = HashMap::new();
+    let mut param_group: Option<(String, Vec<CvParam>)> = None;
+    let mut state = ParserState::default();
+    let mut in_binary = false;
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buffer)
+            .with_context(|| format!("invalid XML at position {}", reader.buffer_position()))?;
+        match event {
+            Event::Start(ref element) | Event::Empty(ref element) => {
+                let is_empty_element = matches!(event, Event::Empty(_));
+                match element.local_name().as_ref() {
+                    b"spectrum" => {
+                        state.spectrum = Some(SpectrumState {
+                            id: attribute(element, "id")?.unwrap_or_default(),
+                            ..Default::default()
+                        });
+                    }
+                    b"precursor" if state.spectrum.is_some() => {
+                        state.precursor = Some(PrecursorState::default());
+                    }
+                    b"isolationWindow" => state.in_isolation_window = !is_empty_element,
+                    b"selectedIon" => state.in_selected_ion = !is_empty_element,
+                    b"binaryDataArray" if state.spectrum.is_some() => {
+                        state.binary_array = Some(BinaryArrayState::default());
+                    }
+                    b"binary" => in_binary = !is_empty_element,
+                    b"referenceableParamGroup" => {
+                        param_group =
+                            Some((attribute(element, "id")?.unwrap_or_default(), Vec::new()));
+                    }
+                    b"referenceableParamGroupRef" => {
+                        let group_id = attribute(element, "ref")?.unwrap_or_default();
+                        let params = param_groups.get(&group_id).with_context(|| {
+                            format!("unknown referenceableParamGroup `{}`", group_id)
+                        })?;
+                        for param in params {
+                            state.apply(param)?;
+                        }
+                    }
+                    b"cvParam" => {
+                        let param = CvParam::from_element(element)?;
+                        match param_group.as_mut() {
+                            Some((_, params)) => params.push(param),
+                            None => state.apply(&param)?,
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            Event::Text(text) if in_binary => {
+                if let Some(binary_array) = state.binary_array.as_mut() {
+                    binary_array.data.push_str(&text.unescape()?);
+                }
+            }
+            Event::End(element) => match element.local_name().as_ref() {
+                b"spectrum" => {
+                    if let Some(spectrum) = state.spectrum.take() {
+                        spectra.push(spectrum.into_spectrum(search_uuid, ms_run_name)?);
+                    }
+                }
+                b"precursor" => {
+                    if let (Some(spectrum), Some(precursor)) =
+                        (state.spectrum.as_mut(), state.precursor.take())
+                    {
+                        spectrum.precursors.push(precursor);
+                    }
+                }
+                b"isolationWindow" => state.in_isolation_window = false,
+                b"selectedIon" => state.in_selected_ion = false,
+                b"binary" => in_binary = false,
+                b"referenceableParamGroup" => {
+                    if let Some((id, params)) = param_group.take() {
+                        param_groups.insert(id, params);
+                    }
+                }
+                b"binaryDataArray" => {
+                    if let (Some(spectrum), Some(binary_array)) =
+                        (state.spectrum.as_mut(), state.binary_array.take())
+                    {
+                        let context = || format!("invalid peaks of spectrum `{}`", spectrum.id);
+                        if binary_array.is_mz {
+                            spectrum.mz = binary_array.decode().with_context(context)?;
+                        } else if binary_array.is_intensity {
+                            spectrum.intensity = binary_array.decode().with_context(context)?;
+                        }
+                    }
+                }
+                _ => (),
+            },
+            Event::Eof => break,
+            _ => (),
+        }
+        buffer.clear();
+    }
+    Ok(spectra)
+}
+
+/// Locates the spectra of the given mzML, see `crate::results_api::spectrum_index::SpectrumIndex`.
+/// The `<indexList>` of indexed mzMLs is not used, as it only contains the offsets but not the lengths.
+///
+/// # Arguments
+/// * `reader` - Reader of the mzML
+///
+/// Returns the ID, byte offset of `<spectrum` and length up to the end of `</spectrum>` of each spectrum
+///
+/// ```
+/// use maccoys_exchange_entities::io::mzml;
+///
+/// let mzml = r#"<mzML><run><spectrumList count="2">
+/// <spectrum id="scan=1" index="0"></spectrum>
+/// <spectrum id="scan=2" index="1"><cvParam accession="MS:1000511" value="2"/></spectrum>
+/// </spectrumList></run></mzML>"#;
+///
+/// let locations = mzml::index(mzml.as_bytes()).unwrap();
+/// let (spectrum_id, offset, length) = &locations[1];
+/// assert_eq!(spectrum_id, "scan=2");
+/// assert!(mzml[*offset as usize..(offset + length) as usize].starts_with("<spectrum id=\"scan=2\""));
+/// assert!(mzml[*offset as usize..(offset + length) as usize].ends_with("</spectrum>"));
+/// ```
+///
+pub fn index<R: BufRead>(reader: R) -> Result<Vec<SpectrumLocation>> {
+    Ok(index_with_param_groups(reader)?.0)
+}
+
+/// Same as `index` but additionally returns the byte offset and length of the `<referenceableParamGroupList>`,
+/// if any. Spectra referring to param groups can only be read together with the list, see `read_fragment`.
+///
+/// # Arguments
+/// * `reader` - Reader of the mzML
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::spectrum_index::{SourceFormat, SpectrumIndex};
+///
+/// let path = std::env::temp_dir().join("mzml_param_groups_doctest.mzML");
+/// std::fs::write(&path, r#"<mzML>
+/// <referenceableParamGroupList count="1">
+///   <referenceableParamGroup id="ms2"><cvParam accession="MS:1000511" value="2"/></referenceableParamGroup>
+/// </referenceableParamGroupList>
+/// <run><spectrumList count="1"><spectrum id="scan=1" index="0">
+///   <referenceableParamGroupRef ref="ms2"/>
+/// </spectrum></spectrumList></run></mzML>"#).unwrap();
+///
+/// let index = SpectrumIndex::from_file(&path, SourceFormat::Mzml).unwrap();
+/// assert!(index.get_files()[0].get_param_groups().is_some());
+/// let spectrum = index
+///     .read_spectrum("scan=1", "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c", "run")
+///     .unwrap()
+///     .unwrap();
+/// assert_eq!(spectrum.get_ms_level(), &Some(2));
+/// ```
+///
+pub fn index_with_param_groups<R: BufRead>(
+    reader: R,
+) -> Result<(Vec<SpectrumLocation>, Option<ByteRange>)> {
+    let mut reader = Reader::from_reader(reader);
+    let mut buffer = Vec::new();
+    let mut locations = Vec::new();
+    let mut spectrum: Option<(String, u64)> = None;
+    let mut param_groups_start: Option<u64> = None;
+    let mut param_groups: Option<ByteRange> = None;
+    loop {
+        let event_start = reader.buffer_position();
+        let event = reader
+            .read_event_into(&mut buffer)
+            .with_context(|| format!("invalid XML at position {}", reader.buffer_position()))?;
+        match event {
+            Event::Start(ref element) if element.local_name().as_ref() == b"spectrum" => {
+                spectrum = Some((attribute(element, "id")?.unwrap_or_default(), event_start));
+            }
+            Event::End(ref element) if element.local_name().as_ref() == b"spectrum" => {
+                if let Some((spectrum_id, offset)) = spectrum.take() {
+                    locations.push((spectrum_id, offset, reader.buffer_position() - offset));
+                }
+            }
+            Event::Start(ref element)
+                if element.local_name().as_ref() == b"referenceableParamGroupList" =>
+            {
+                param_groups_start = Some(event_start);
+            }
+            Event::End(ref element)
+                if element.local_name().as_ref() == b"referenceableParamGroupList" =>
+            {
+                if let Some(offset) = param_groups_start.take() {
+                    param_groups = Some((offset, reader.buffer_position() - offset));
+                }
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+        buffer.clear();
+    }
+    Ok((locations, param_groups))
+}
+
+/// Reads the spectra of a fragment of an mzML, e.g. a single `<spectrum>` element located with `index`.
+/// `referenceableParamGroupRef`s of the fragment are resolved with the given `<referenceableParamGroupList>`
+/// of the mzML header.
+///
+/// # Arguments
+/// * `param_groups` - `<referenceableParamGroupList>` of the mzML, empty if it has none
+/// * `fragment` - Fragment of the mzML
+/// * `search_uuid` - UUID of the search the spectra are assigned to
+/// * `ms_run_name` - Name of the MS run the spectra are assigned to
+///
+/// ```
+/// use maccoys_exchange_entities::io::mzml;
+///
+/// let mzml = r#"<mzML>
+/// <referenceableParamGroupList count="1">
+///   <referenceableParamGroup id="ms2"><cvParam accession="MS:1000511" value="2"/></referenceableParamGroup>
+/// </referenceableParamGroupList>
+/// <run><spectrumList count="1"><spectrum id="scan=1" index="0">
+///   <referenceableParamGroupRef ref="ms2"/>
+/// </spectrum></spectrumList></run></mzML>"#;
+///
+/// let (locations, param_groups) = mzml::index_with_param_groups(mzml.as_bytes()).unwrap();
+/// let slice = |(offset, length): (u64, u64)| mzml[offset as usize..(offset + length) as usize].as_bytes();
+/// let (_, offset, length) = locations[0];
+/// let spectra = mzml::read_fragment(
+///     slice(param_groups.unwrap()),
+///     slice((offset, length)),
+///     "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c",
+///     "run",
+/// )
+/// .unwrap();
+/// assert_eq!(spectra[0].get_ms_level(), &Some(2));
+/// ```
+///
+pub fn read_fragment(
+    param_groups: &[u8],
+    fragment: &[u8],
+    search_uuid: &str,
+    ms_run_name: &str,
+) -> Result<Vec<Spectrum>> {
+    read(param_groups.chain(fragment), search_uuid, ms_run_name)
+}
+
+/// Same as `read` but opens the file at the given path
+///
+pub fn read_file(path: &Path, search_uuid: &str, ms_run_name: &str) -> Result<Vec<Spectrum>> {
+    let file = File::open(path).with_context(|| format!("could not open {}", path.display()))?;
+    read(BufReader::new(file), search_uuid, ms_run_name)
+}
@@ -0,0 +1,541 @@
+//! Reads search results of other engines (e.g. X!Tandem or standalone Comet) from pepXML
+//! and protein inferences from protXML (<http://tools.proteomecenter.org/wiki/index.php?title=Formats:pepXML>).
+//!
+//! Each `spectrum_query` becomes an `Identification` of the spectrum with the search hits as PSMs,
+//! spectra have no peaks. PSM columns follow the Comet names of `psm_columns`, so the results can be
+//! compared with MaCcoyS results. Search scores are added as columns with their pepXML name, except for
+//! the Comet scores `expect`, `deltacn` and `spscore` which are renamed to their `psm_columns` names.
+
+// std imports
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+// 3rd party imports
+use anyhow::{bail, Context, Result};
+use polars::prelude::*;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+// internal imports
+use crate::mass::{mass_to_mz, residue_mass, HYDROGEN, WATER};
+use crate::results_api::{
+    psm_columns, Identification, MsRunName, Precursor, Protein, SearchUuid, Spectrum, SpectrumId,
+};
+
+/// pepXML score names which are renamed to the Comet column names
+const SCORE_RENAMES: [(&str, &str); 3] = [
+    ("expect", psm_columns::E_VALUE),
+    ("deltacn", psm_columns::DELTA_CN),
+    ("spscore", psm_columns::SP_SCORE),
+];
+
+/// Search hit while parsing
+///
+#[derive(Default)]
+struct HitState {
+    rank: Option<u32>,
+    peptide: String,
+    prev_aa: Option<String>,
+    next_aa: Option<String>,
+    proteins: Vec<String>,
+    ions_matched: Option<u32>,
+    ions_total: Option<u32>,
+    calc_neutral_mass: Option<f64>,
+    /// Mass deltas by 0-based residue position
+    residue_modifications: Vec<(usize, f64)>,
+    n_term_modification: Option<f64>,
+    c_term_modification: Option<f64>,
+    scores: Vec<(String, f64)>,
+}
+
+impl HitState {
+    /// Modified peptide in Comet format, e.g. `n[42.0106]PEPM[15.9949]IDE`
+    ///
+    fn modified_peptide(&self) -> String {
+        let mut modified_peptide = String::new();
+        if let Some(mass_delta) = self.n_term_modification {
+            modified_peptide.push_str(&format!("n[{:.4}]", mass_delta));
+        }
+        for (position, residue) in self.peptide.chars().enumerate() {
+            modified_peptide.push(residue);
+            for (_, mass_delta) in self
+                .residue_modifications
+                .iter()
+                .filter(|(mod_position, _)| *mod_position == position)
+            {
+                modified_peptide.push_str(&format!("[{:.4}]", mass_delta));
+            }
+        }
+        if let Some(mass_delta) = self.c_term_modification {
+            modified_peptide.push_str(&format!("c[{:.4}]", mass_delta));
+        }
+        modified_peptide
+    }
+}
+
+/// Spectrum query while parsing
+///
+#[derive(Default)]
+struct QueryState {
+    spectrum_id: String,
+    scan: Option<u32>,
+    charge: u8,
+    precursor_neutral_mass: f64,
+    retention_time: Option<f64>,
+    hits: Vec<HitState>,
+}
+
+impl QueryState {
+    fn into_identification(self) -> Result<Identification> {
+        let precursor = Precursor::new(
+            mass_to_mz(self.precursor_neutral_mass, self.charge),
+            self.charge,
+        );
+        if self.hits.is_empty() {
+            return Ok(Identification::new(None, None, precursor));
+        }
+        let num_hits = self.hits.len();
+        let mut columns = vec![
+            Series::new(psm_columns::SCAN, vec![self.scan; num_hits]),
+            Series::new(
+                psm_columns::RANK,
+                self.hits.iter().map(|hit| hit.rank).collect::<Vec<_>>(),
+            ),
+            Series::new(psm_columns::CHARGE, vec![self.charge as u32; num_hits]),
+            Series::new(
+                psm_columns::EXP_NEUTRAL_MASS,
+                vec![self.precursor_neutral_mass; num_hits],
+            ),
+            Series::new(
+                psm_columns::CALC_NEUTRAL_MASS,
+                self.hits
+                    .iter()
+                    .map(|hit| hit.calc_neutral_mass)
+                    .collect::<Vec<_>>(),
+            ),
+            Series::new(
+                psm_columns::PLAIN_PEPTIDE,
+                self.hits
+                    .iter()
+                    .map(|hit| hit.peptide.as_str())
+                    .collect::<Vec<_>>(),
+            ),
+            Series::new(
+                psm_columns::MODIFIED_PEPTIDE,
+                self.hits
+                    .iter()
+                    .map(HitState::modified_peptide)
+                    .collect::<Vec<_>>(),
+            ),
+            Series::new(
+                psm_columns::PREV_AA,
+                self.hits
+                    .iter()
+                    .map(|hit| hit.prev_aa.as_deref())
+                    .collect::<Vec<_>>(),
+            ),
+            Series::new(
+                psm_columns::NEXT_AA,
+                self.hits
+                    .iter()
+                    .map(|hit| hit.next_aa.as_deref())
+                    .collect::<Vec<_>>(),
+            ),
+            Series::new(
+                psm_columns::PROTEIN,
+                self.hits
+                    .iter()
+                    .map(|hit| hit.proteins.join(","))
+                    .collect::<Vec<_>>(),
+            ),
+            Series::new(
+                psm_columns::PROTEIN_COUNT,
+                self.hits
+                    .iter()
+                    .map(|hit| hit.proteins.len() as u32)
+                    .collect::<Vec<_>>(),
+            ),
+            Series::new(
+                psm_columns::IONS_MATCHED,
+                self.hits
+                    .iter()
+                    .map(|hit| hit.ions_matched)
+                    .collect::<Vec<_>>(),
+            ),
+            Series::new(
+                psm_columns::IONS_TOTAL,
+                self.hits
+                    .iter()
+                    .map(|hit| hit.ions_total)
+                    .collect::<Vec<_>>(),
+            ),
+            Series::new(
+                psm_columns::RETENTION_TIME_SEC,
+                vec![self.retention_time; num_hits],
+            ),
+        ];
+        // scores in order of first occurrence
+        let mut score_names: Vec<&str> = Vec::new();
+        for hit in self.hits.iter() {
+            for (name, _) in hit.scores.iter() {
+                if !score_names.contains(&name.as_str()) {
+                    score_names.push(name);
+                }
+            }
+        }
+        for score_name in score_names {
+            columns.push(Series::new(
+                score_name,
+                self.hits
+                    .iter()
+                    .map(|hit| {
+                        hit.scores
+                            .iter()
+                            .find(|(name, _)| name == score_name)
+                            .map(|(_, value)| *value)
+                    })
+                    .collect::<Vec<_>>(),
+            ));
+        }
+        Ok(Identification::new(
+            None,
+            Some(DataFrame::new(columns)?),
+            precursor,
+        ))
+    }
+}
+
+fn attribute(element: &BytesStart<'_>, name: &str) -> Result<Option<String>> {
+    match element.try_get_attribute(name)? {
+        Some(attribute) => Ok(Some(attribute.unescape_value()?.into_owned())),
+        None => Ok(None),
+    }
+}
+
+fn parse_attribute<T: std::str::FromStr>(
+    element: &BytesStart<'_>,
+    name: &str,
+) -> Result<Option<T>> {
+    match attribute(element, name)? {
+        Some(value) => match value.trim().parse::<T>() {
+            Ok(value) => Ok(Some(value)),
+            Err(_) => bail!(
+                "attribute `{}` of `{}` has invalid value `{}`",
+                name,
+                String::from_utf8_lossy(element.local_name().as_ref()),
+                value
+            ),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Name of the MS run from the `base_name` of the run summary, i.e. its last path segment
+///
+fn ms_run_name(base_name: &str) -> Result<MsRunName> {
+    let name = base_name.rsplit(['/', '\\']).next().unwrap_or_default();
+    MsRunName::new(name).with_context(|| format!("invalid MS run base name `{}`", base_name))
+}
+
+/// Reads the spectrum queries of the given pepXML as spectra without peaks.
+/// Queries of the same spectrum with different assumed charges become identifications of one spectrum.
+/// The spectrum ID is the native ID (`spectrumNativeID`) if available, otherwise the pepXML spectrum name.
+///
+/// # Arguments
+/// * `reader` - Reader of the pepXML
+/// * `search_uuid` - UUID of the search the spectra are assigned to
+///
+/// ```
+/// use maccoys_exchange_entities::io::pepxml;
+///
+/// let pepxml = r#"<?xml version="1.0" encoding="UTF-8"?>
+/// <msms_pipeline_analysis>
+///   <msms_run_summary base_name="/data/run_a">
+///     <spectrum_query spectrum="run_a.00042.00042.2" start_scan="42" precursor_neutral_mass="818.3665" assumed_charge="2" retention_time_sec="1234.5">
+///       <search_result>
+///         <search_hit hit_rank="1" peptide="PEPMIDE" peptide_prev_aa="K" peptide_next_aa="R" protein="P1" num_matched_ions="5" tot_num_ions="12" calc_neutral_pep_mass="818.3633">
+///           <alternative_protein protein="P2"/>
+///           <modification_info modified_peptide="PEPM[147]IDE">
+///             <mod_aminoacid_mass position="4" mass="147.0354"/>
+///           </modification_info>
+///           <search_score name="hyperscore" value="35.2"/>
+///           <search_score name="expect" value="1.2e-4"/>
+///         </search_hit>
+///       </search_result>
+///     </spectrum_query>
+///   </msms_run_summary>
+/// </msms_pipeline_analysis>"#;
+///
+/// let spectra = pepxml::read(pepxml.as_bytes(), "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c").unwrap();
+/// assert_eq!(spectra[0].get_ms_run(), "run_a");
+/// assert_eq!(spectra[0].get_spectra_id(), "run_a.00042.00042.2");
+/// let identification = &spectra[0].get_identifications()[0];
+/// assert_eq!(identification.get_charge(), 2);
+/// let row = identification.iter_psm_rows().unwrap().next().unwrap();
+/// assert_eq!(row.get_str("modified_peptide").unwrap(), "PEPM[15.9949]IDE");
+/// assert_eq!(row.get_str("protein").unwrap(), "P1,P2");
+/// assert_eq!(row.get_f64("e-value").unwrap(), 1.2e-4);
+/// assert_eq!(row.get_f64("hyperscore").unwrap(), 35.2);
+/// ```
+///
+pub fn read<R: BufRead>(reader: R, search_uuid: &str) -> Result<Vec<Spectrum>> {
+    let search_uuid = SearchUuid::new(search_uuid)?;
+    let mut reader = Reader::from_reader(reader);
+    let mut buffer = Vec::new();
+    let mut spectra: Vec<Spectrum> = Vec::new();
+
+    let mut run_name: Option<MsRunName> = None;
+    let mut query: Option<QueryState> = None;
+    let mut hit: Option<HitState> = None;
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buffer)
+            .with_context(|| format!("invalid XML at position {}", reader.buffer_position()))?;
+        match event {
+            Event::Start(ref element) | Event::Empty(ref element) => {
+                match element.local_name().as_ref() {
+                    b"msms_run_summary" => {
+                        run_name = Some(ms_run_name(
+                            &attribute(element, "base_name")?.unwrap_or_default(),
+                        )?);
+                    }
+                    b"spectrum_query" => {
+                        let spectrum_id = match attribute(element, "spectrumNativeID")? {
+                            Some(native_id) => native_id,
+                            None => attribute(element, "spectrum")?.unwrap_or_default(),
+                        };
+                        query = Some(QueryState {
+                            spectrum_id,
+                            scan: parse_attribute(element, "start_scan")?,
+                            charge: parse_attribute(element, "assumed_charge")?.unwrap_or_default(),
+                            precursor_neutral_mass: parse_attribute(
+                                element,
+                                "precursor_neutral_mass",
+                            )?
+                            .unwrap_or(f64::NAN),
+                            retention_time: parse_attribute(element, "retention_time_sec")?,
+                            hits: Vec::new(),
+                        });
+                    }
+                    b"search_hit" if query.is_some() => {
+                        hit = Some(HitState {
+                            rank: parse_attribute(element, "hit_rank")?,
+                            peptide: attribute(element, "peptide")?.unwrap_or_default(),
+                            prev_aa: attribute(element, "peptide_prev_aa")?,
+                            next_aa: attribute(element, "peptide_next_aa")?,
+                            proteins: attribute(element, "protein")?.into_iter().collect(),
+                            ions_matched: parse_attribute(element, "num_matched_ions")?,
+                            ions_total: parse_attribute(element, "tot_num_ions")?,
+                            calc_neutral_mass: parse_attribute(element, "calc_neutral_pep_mass")?,
+                            ..Default::default()
+                        });
+                    }
+                    b"alternative_protein" => {
+                        if let (Some(hit), Some(protein)) =
+                            (hit.as_mut(), attribute(element, "protein")?)
+                        {
+                            hit.proteins.push(protein);
+                        }
+                    }
+                    b"modification_info" => {
+                        if let Some(hit) = hit.as_mut() {
+                            hit.n_term_modification =
+                                parse_attribute::<f64>(element, "mod_nterm_mass")?
+                                    .map(|mass| mass - HYDROGEN);
+                            hit.c_term_modification =
+                                parse_attribute::<f64>(element, "mod_cterm_mass")?
+                                    .map(|mass| mass - (WATER - HYDROGEN));
+                        }
+                    }
+                    b"mod_aminoacid_mass" => {
+                        if let Some(hit) = hit.as_mut() {
+                            let position: usize = match parse_attribute(element, "position")? {
+                                Some(position) if position > 0 => position,
+                                _ => bail!("modification without position in `{}`", hit.peptide),
+                            };
+                            let residue = hit.peptide.chars().nth(position - 1);
+                            let mass: f64 = parse_attribute(element, "mass")?.unwrap_or_default();
+                            // pepXML reports the mass of the modified residue, newer versions also the delta
+                            let mass_delta = match parse_attribute::<f64>(element, "variable")? {
+                                Some(delta) => delta,
+                                None => match residue.and_then(residue_mass) {
+                                    Some(residue_mass) => mass - residue_mass,
+                                    None => bail!(
+                                        "modification at unknown residue {} of `{}`",
+                                        position,
+                                        hit.peptide
+                                    ),
+                                },
+                            };
+                            hit.residue_modifications.push((position - 1, mass_delta));
+                        }
+                    }
+                    b"search_score" => {
+                        if let Some(hit) = hit.as_mut() {
+                            let name = attribute(element, "name")?.unwrap_or_default();
+                            let name = SCORE_RENAMES
+                                .iter()
+                                .find(|(pepxml_name, _)| *pepxml_name == name)
+                                .map(|(_, column)| column.to_string())
+                                .unwrap_or(name);
+                            // non-numeric scores (e.g. X!Tandem's ion counts as text) are skipped
+                            if let Some(Ok(value)) =
+                                attribute(element, "value")?.map(|value| value.parse::<f64>())
+                            {
+                                hit.scores.push((name, value));
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            Event::End(element) => match element.local_name().as_ref() {
+                b"search_hit" => {
+                    if let (Some(query), Some(hit)) = (query.as_mut(), hit.take()) {
+                        query.hits.push(hit);
+                    }
+                }
+                b"spectrum_query" => {
+                    if let Some(query) = query.take() {
+                        let run_name = match run_name.as_ref() {
+                            Some(run_name) => run_name,
+                            None => bail!("spectrum query outside of a run summary"),
+                        };
+                        let spectrum_id = SpectrumId::new(&query.spectrum_id)
+                            .with_context(|| format!("invalid spectrum `{}`", query.spectrum_id))?;
+                        let scan = query.scan;
+                        let retention_time = query.retention_time;
+                        let identification = query.into_identification()?;
+                        match spectra.iter_mut().rev().find(|spectrum| {
+                            spectrum.get_ms_run() == run_name
+                                && spectrum.get_spectra_id() == &spectrum_id
+                        }) {
+                            Some(spectrum) => {
+                                spectrum.get_identifications_mut().push(identification)
+                            }
+                            None => spectra.push(
+                                Spectrum::new(
+                                    search_uuid.clone(),
+                                    run_name.clone(),
+                                    spectrum_id,
+                                    Vec::new(),
+                                    Vec::new(),
+                                    vec![identification],
+                                )
+                                .with_retention_time(retention_time)
+                                .with_ms_level(Some(2))
+                                .with_scan_number(scan),
+                            ),
+                        }
+                    }
+                }
+                _ => (),
+            },
+            Event::Eof => break,
+            _ => (),
+        }
+        buffer.clear();
+    }
+    Ok(spectra)
+}
+
+/// Same as `read` but opens the file at the given path
+///
+pub fn read_file(path: &Path, search_uuid: &str) -> Result<Vec<Spectrum>> {
+    let file = File::open(path).with_context(|| format!("could not open {}", path.display()))?;
+    read(BufReader::new(file), search_uuid)
+}
+
+/// Reads the proteins of the given protXML, e.g. from ProteinProphet.
+/// Indistinguishable proteins become separate proteins with the same peptides.
+/// Peptides are unique if they are flagged as non-degenerate evidence.
+///
+/// ```
+/// use maccoys_exchange_entities::io::pepxml;
+///
+/// let protxml = r#"<protein_summary>
+///   <protein_group group_number="1" probability="1.0">
+///     <protein protein_name="P1" probability="1.0">
+///       <indistinguishable_protein protein_name="P2"/>
+///       <peptide peptide_sequence="PEPTIDE" is_nondegenerate_evidence="Y"/>
+///       <peptide peptide_sequence="PEPTIDES" is_nondegenerate_evidence="N"/>
+///     </protein>
+///   </protein_group>
+/// </protein_summary>"#;
+///
+/// let proteins = pepxml::read_protxml(protxml.as_bytes()).unwrap();
+/// assert_eq!(proteins.len(), 2);
+/// assert_eq!(proteins[1].get_accession(), "P2");
+/// assert_eq!(proteins[1].get_peptides(), &vec!["PEPTIDE", "PEPTIDES"]);
+/// assert_eq!(proteins[1].get_unique_peptides(), &vec!["PEPTIDE"]);
+/// ```
+///
+pub fn read_protxml<R: BufRead>(reader: R) -> Result<Vec<Protein>> {
+    let mut reader = Reader::from_reader(reader);
+    let mut buffer = Vec::new();
+    let mut proteins = Vec::new();
+
+    // accessions, peptides and unique peptides of the current protein
+    let mut protein: Option<(Vec<String>, BTreeSet<String>, BTreeSet<String>)> = None;
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buffer)
+            .with_context(|| format!("invalid XML at position {}", reader.buffer_position()))?;
+        match event {
+            Event::Start(ref element) | Event::Empty(ref element) => {
+                match element.local_name().as_ref() {
+                    b"protein" => {
+                        let accession = attribute(element, "protein_name")?.unwrap_or_default();
+                        protein = Some((vec![accession], BTreeSet::new(), BTreeSet::new()));
+                    }
+                    b"indistinguishable_protein" => {
+                        if let (Some((accessions, _, _)), Some(accession)) =
+                            (protein.as_mut(), attribute(element, "protein_name")?)
+                        {
+                            accessions.push(accession);
+                        }
+                    }
+                    b"peptide" => {
+                        if let (Some((_, peptides, unique_peptides)), Some(sequence)) =
+                            (protein.as_mut(), attribute(element, "peptide_sequence")?)
+                        {
+                            if attribute(element, "is_nondegenerate_evidence")?.as_deref()
+                                == Some("Y")
+                            {
+                                unique_peptides.insert(sequence.clone());
+                            }
+                            peptides.insert(sequence);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            Event::End(element) if element.local_name().as_ref() == b"protein" => {
+                if let Some((accessions, peptides, unique_peptides)) = protein.take() {
+                    for accession in accessions {
+                        proteins.push(Protein::new(
+                            accession,
+                            peptides.iter().cloned().collect(),
+                            unique_peptides.iter().cloned().collect(),
+                        ));
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+        buffer.clear();
+    }
+    Ok(proteins)
+}
+
+/// Same as `read_protxml` but opens the file at the given path
+///
+pub fn read_protxml_file(path: &Path) -> Result<Vec<Protein>> {
+    let file = File::open(path).with_context(|| format!("could not open {}", path.display()))?;
+    read_protxml(BufReader::new(file))
+}
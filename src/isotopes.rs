@@ -0,0 +1,240 @@
+//! Theoretical isotope distributions of peptides based on the averagine model
+//! (Senko et al. 1995, <https://doi.org/10.1016/1044-0305(95)00017-8>).
+//!
+//! The elemental composition of a peptide is estimated from its mass by scaling the averagine
+//! residue `C4.9384 H7.7583 N1.3577 O1.4773 S0.0417` and the isotope distribution is calculated
+//! by convolving the isotope abundances of the elements.
+
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::mass::{mass_to_mz, mz_to_mass, ISOTOPE_SPACING};
+use crate::results_api::Precursor;
+use crate::tolerance::Tolerance;
+
+/// Average mass of the averagine residue in Dalton
+pub const AVERAGINE_MASS: f64 = 111.1254;
+
+/// Default number of isotope peaks, covers the relevant part of the envelope up to ~5 kDa
+pub const DEFAULT_NUM_PEAKS: usize = 6;
+
+/// Element count of the averagine residue and the relative abundances of its isotopes,
+/// indexed by the nominal mass offset to the lightest isotope
+const AVERAGINE_COMPOSITION: [(f64, &[f64]); 5] = [
+    // C
+    (4.9384, &[0.9893, 0.0107]),
+    // H
+    (7.7583, &[0.999_885, 0.000_115]),
+    // N
+    (1.3577, &[0.996_36, 0.003_64]),
+    // O
+    (1.4773, &[0.997_57, 0.000_38, 0.002_05]),
+    // S
+    (0.0417, &[0.9499, 0.0075, 0.0425, 0.0, 0.0001]),
+];
+
+/// Theoretical isotope envelope of a precursor, e.g. for overlays on MS1 spectra.
+/// Intensities are relative to the most abundant isotope peak.
+///
+/// ```
+/// use maccoys_exchange_entities::isotopes::IsotopeEnvelope;
+///
+/// let envelope = IsotopeEnvelope::averagine(1500.0, 2, 4).unwrap();
+/// assert_eq!(envelope.len(), 4);
+/// assert!((envelope.get_mz()[0] - 751.007276).abs() < 1e-6);
+/// // at 1.5 kDa the monoisotopic peak is the most abundant
+/// assert_eq!(envelope.get_most_abundant_index(), 0);
+/// assert!(envelope.get_intensity()[1] > 0.7 && envelope.get_intensity()[1] < 0.9);
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct IsotopeEnvelope {
+    monoisotopic_mass: f64,
+    charge: u8,
+    mz: Vec<f64>,
+    intensity: Vec<f64>,
+}
+
+impl IsotopeEnvelope {
+    /// Predicts the isotope envelope of a peptide using the averagine model.
+    /// Returns None if the charge is 0 or the mass is not positive.
+    ///
+    /// # Arguments
+    /// * `monoisotopic_mass` - Neutral monoisotopic mass in Dalton
+    /// * `charge` - Charge state
+    /// * `num_peaks` - Number of isotope peaks, starting with the monoisotopic peak
+    ///
+    pub fn averagine(monoisotopic_mass: f64, charge: u8, num_peaks: usize) -> Option<Self> {
+        if charge == 0 || monoisotopic_mass <= 0.0 || num_peaks == 0 {
+            return None;
+        }
+        let num_residues = monoisotopic_mass / AVERAGINE_MASS;
+        let mut distribution = vec![1.0];
+        for (count, abundances) in AVERAGINE_COMPOSITION.iter() {
+            let element = power(abundances, (count * num_residues).round() as u32, num_peaks);
+            distribution = convolve(&distribution, &element, num_peaks);
+        }
+        distribution.resize(num_peaks, 0.0);
+        let max = distribution.iter().copied().fold(0.0, f64::max);
+        let monoisotopic_mz = mass_to_mz(monoisotopic_mass, charge);
+        let spacing = ISOTOPE_SPACING / charge as f64;
+        Some(Self {
+            monoisotopic_mass,
+            charge,
+            mz: (0..num_peaks)
+                .map(|isotope| monoisotopic_mz + isotope as f64 * spacing)
+                .collect(),
+            intensity: distribution.iter().map(|value| value / max).collect(),
+        })
+    }
+
+    pub fn get_monoisotopic_mass(&self) -> f64 {
+        self.monoisotopic_mass
+    }
+
+    pub fn get_charge(&self) -> u8 {
+        self.charge
+    }
+
+    pub fn get_mz(&self) -> &Vec<f64> {
+        &self.mz
+    }
+
+    /// Intensities relative to the most abundant isotope peak
+    ///
+    pub fn get_intensity(&self) -> &Vec<f64> {
+        &self.intensity
+    }
+
+    /// Index of the most abundant isotope peak, i.e. the number of isotopes above the monoisotopic peak
+    ///
+    pub fn get_most_abundant_index(&self) -> usize {
+        self.intensity
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(idx, _)| idx)
+            .unwrap_or(0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.mz.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mz.is_empty()
+    }
+
+    /// Cosine similarity between the theoretical and the observed envelope, between 0 and 1.
+    /// Each isotope peak is matched to the most intense observed peak within the tolerance,
+    /// missing peaks count with intensity 0. Requires m/z values sorted ascending.
+    ///
+    /// # Arguments
+    /// * `mz` - Observed m/z values, e.g. of the MS1 spectrum the precursor was selected from
+    /// * `intensity` - Observed intensities
+    /// * `tolerance` - Tolerance for matching isotope peaks
+    ///
+    pub fn similarity(&self, mz: &[f64], intensity: &[f64], tolerance: Tolerance) -> f64 {
+        let observed: Vec<f64> = self
+            .mz
+            .iter()
+            .map(|expected| {
+                tolerance
+                    .peak_range(mz, *expected)
+                    .map(|idx| intensity[idx])
+                    .fold(0.0, f64::max)
+            })
+            .collect();
+        let dot_product = self
+            .intensity
+            .iter()
+            .zip(observed.iter())
+            .map(|(theoretical, observed)| theoretical * observed)
+            .sum::<f64>();
+        let theoretical_norm = self
+            .intensity
+            .iter()
+            .map(|value| value * value)
+            .sum::<f64>()
+            .sqrt();
+        let observed_norm = observed
+            .iter()
+            .map(|value| value * value)
+            .sum::<f64>()
+            .sqrt();
+        if theoretical_norm > 0.0 && observed_norm > 0.0 {
+            (dot_product / (theoretical_norm * observed_norm)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Precursor {
+    /// Predicts the isotope envelope of the precursor starting at the monoisotopic m/z.
+    /// Returns None if the charge is unknown.
+    ///
+    /// # Arguments
+    /// * `num_peaks` - Number of isotope peaks, e.g. `DEFAULT_NUM_PEAKS`
+    ///
+    pub fn predict_isotope_envelope(&self, num_peaks: usize) -> Option<IsotopeEnvelope> {
+        if self.get_charge() == 0 {
+            return None;
+        }
+        IsotopeEnvelope::averagine(
+            mz_to_mass(self.get_monoisotopic_mz(), self.get_charge()),
+            self.get_charge(),
+            num_peaks,
+        )
+    }
+
+    /// Validates the precursor against the MS1 spectrum it was selected from by comparing the predicted
+    /// with the observed isotope envelope, see `IsotopeEnvelope::similarity`.
+    /// Returns None if the charge is unknown.
+    ///
+    /// # Arguments
+    /// * `mz` - Observed m/z values, sorted ascending
+    /// * `intensity` - Observed intensities
+    /// * `tolerance` - Tolerance for matching isotope peaks
+    ///
+    pub fn isotope_similarity(
+        &self,
+        mz: &[f64],
+        intensity: &[f64],
+        tolerance: Tolerance,
+    ) -> Option<f64> {
+        self.predict_isotope_envelope(DEFAULT_NUM_PEAKS)
+            .map(|envelope| envelope.similarity(mz, intensity, tolerance))
+    }
+}
+
+/// Convolves two isotope distributions, truncated to `num_peaks`
+///
+fn convolve(a: &[f64], b: &[f64], num_peaks: usize) -> Vec<f64> {
+    let mut result = vec![0.0; (a.len() + b.len() - 1).min(num_peaks)];
+    for (i, a_value) in a.iter().enumerate() {
+        for (j, b_value) in b.iter().enumerate().take(result.len().saturating_sub(i)) {
+            result[i + j] += a_value * b_value;
+        }
+    }
+    result
+}
+
+/// Isotope distribution of `count` atoms of the element, truncated to `num_peaks`
+///
+fn power(abundances: &[f64], mut count: u32, num_peaks: usize) -> Vec<f64> {
+    let mut result = vec![1.0];
+    let mut base = abundances.to_vec();
+    while count > 0 {
+        if count & 1 == 1 {
+            result = convolve(&result, &base, num_peaks);
+        }
+        count >>= 1;
+        if count > 0 {
+            base = convolve(&base, &base, num_peaks);
+        }
+    }
+    result
+}
@@ -1,5 +1,85 @@
 // Include readme in doc
 #![doc = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/Readme.md"))]
 
+/// Retention time alignment between MS runs
+pub mod alignment;
+
+/// Annotation of spectra with theoretical fragment ions
+pub mod annotation;
+
+/// Typed client for the results API
+#[cfg(feature = "client")]
+pub mod client;
+
+/// Export of the results into community standard formats
+pub mod export;
+
+/// GraphQL type definitions of the entities and conversion into GraphQL values
+#[cfg(feature = "graphql")]
+pub mod graphql;
+
+/// Reading and writing of spectrum file formats
+pub mod io;
+
+/// Theoretical isotope envelopes of precursors
+pub mod isotopes;
+
+/// Masses of elements, molecules, amino acids and peptides
+pub mod mass;
+
+/// Upgrades of serialized entities from older layouts
+pub mod migrations;
+
+/// ProForma 2.0 notation of peptidoforms
+pub mod proforma;
+
+/// Protocol Buffers messages of the entities, e.g. for gRPC services
+#[cfg(feature = "proto")]
+pub mod proto;
+
+/// Quantification of peptides from spectra
+pub mod quant;
+
+/// Messages exchanged between the API and the workers of the processing pipeline
+pub mod queue;
+
+/// Python bindings, built with maturin (see `pyproject.toml`).
+/// PSMs and goodness of fits are handed over as polars DataFrames.
+#[cfg(feature = "python")]
+pub mod python;
+
 /// Entites for the results API
-pub mod results_api;
\ No newline at end of file
+pub mod results_api;
+
+/// JSON Schema of the exchange entities
+#[cfg(feature = "schema")]
+pub mod schema;
+
+/// Binary wire formats for the entities
+pub mod serialization;
+
+/// Similarity scores of spectra, e.g. for open modification searches
+pub mod similarity;
+
+/// Parsing of vendor native IDs and other spectrum ID formats
+pub mod spectrum_id;
+
+/// Statistical post-processing of the results
+pub mod statistics;
+
+/// Persistence of the results
+pub mod storage;
+
+/// Mass tolerances
+pub mod tolerance;
+
+/// Universal Spectrum Identifiers (USI)
+pub mod usi;
+
+/// Validation of entity invariants
+pub mod validate;
+
+/// wasm-bindgen exports for deserializing payloads in the browser.
+/// DataFrames are handed over as lightweight `TableView`s, so JavaScript does not need polars.
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,165 @@
+// 3rd party imports
+use anyhow::Result;
+use polars::prelude::*;
+
+// internal imports
+use crate::proforma::Peptidoform;
+use crate::results_api::{psm_columns, Identification};
+
+/// Mass of a proton in Dalton
+pub const PROTON: f64 = 1.007_276_466_621;
+/// Monoisotopic mass of hydrogen in Dalton
+pub const HYDROGEN: f64 = 1.007_825_032_23;
+/// Monoisotopic mass of water in Dalton
+pub const WATER: f64 = 18.010_564_683_7;
+/// Monoisotopic mass of ammonia in Dalton
+pub const AMMONIA: f64 = 17.026_549_101;
+/// Monoisotopic mass of carbon monoxide in Dalton
+pub const CARBON_MONOXIDE: f64 = 27.994_914_619_6;
+/// Mass difference between 13C and 12C in Dalton, the spacing of isotope peaks at charge 1
+pub const ISOTOPE_SPACING: f64 = 1.003_354_835;
+
+/// Monoisotopic residue mass of the given amino acid (one letter code) in Dalton.
+/// Returns None for unknown or ambiguous amino acids.
+///
+pub fn residue_mass(amino_acid: char) -> Option<f64> {
+    let mass = match amino_acid.to_ascii_uppercase() {
+        'G' => 57.021_463_72,
+        'A' => 71.037_113_79,
+        'S' => 87.032_028_41,
+        'P' => 97.052_763_85,
+        'V' => 99.068_413_91,
+        'T' => 101.047_678_5,
+        'C' => 103.009_184_5,
+        'L' => 113.084_064,
+        'I' => 113.084_064,
+        'N' => 114.042_927_4,
+        'D' => 115.026_943,
+        'Q' => 128.058_577_5,
+        'K' => 128.094_963_1,
+        'E' => 129.042_593_1,
+        'M' => 131.040_484_6,
+        'H' => 137.058_911_9,
+        'F' => 147.068_414,
+        'U' => 150.953_633_4,
+        'R' => 156.101_111,
+        'Y' => 163.063_328_6,
+        'W' => 186.079_312_9,
+        'O' => 237.147_726_9,
+        _ => return None,
+    };
+    Some(mass)
+}
+
+/// Converts a neutral mass to the m/z of the given charge state, see `ExchangeConfig::mass_to_mz` for
+/// a configured proton mass
+///
+pub fn mass_to_mz(mass: f64, charge: u8) -> f64 {
+    (mass + charge as f64 * PROTON) / charge as f64
+}
+
+/// Converts the m/z of the given charge state to the neutral mass, see `ExchangeConfig::mz_to_mass` for
+/// a configured proton mass
+///
+pub fn mz_to_mass(mz: f64, charge: u8) -> f64 {
+    mz * charge as f64 - charge as f64 * PROTON
+}
+
+/// Monoisotopic neutral mass of the unmodified peptide (residues and water) in Dalton.
+/// Returns None if the sequence contains unknown or ambiguous amino acids.
+///
+pub fn peptide_mass(sequence: &str) -> Option<f64> {
+    sequence
+        .chars()
+        .map(residue_mass)
+        .sum::<Option<f64>>()
+        .map(|mass| mass + WATER)
+}
+
+/// Monoisotopic neutral mass of the peptidoform including all modifications in Dalton.
+/// Returns None if the sequence contains unknown or ambiguous amino acids.
+///
+/// ```
+/// use maccoys_exchange_entities::mass::{modified_peptide_mass, peptide_mass, ppm_error};
+/// use maccoys_exchange_entities::proforma::Peptidoform;
+///
+/// let peptidoform: Peptidoform = "PEPM[Oxidation]IDE".parse().unwrap();
+/// let mass = modified_peptide_mass(&peptidoform).unwrap();
+/// assert!((mass - peptide_mass("PEPMIDE").unwrap() - 15.994915).abs() < 1e-9);
+/// assert!((ppm_error(mass + mass * 5e-6, mass) - 5.0).abs() < 1e-6);
+/// ```
+///
+pub fn modified_peptide_mass(peptidoform: &Peptidoform) -> Option<f64> {
+    let mass_delta: f64 = peptidoform
+        .get_modifications()
+        .iter()
+        .map(|modification| modification.get_mass_delta())
+        .sum();
+    Some(peptide_mass(peptidoform.get_sequence())? + mass_delta)
+}
+
+/// Error of the observed mass relative to the theoretical mass in parts per million
+///
+pub fn ppm_error(observed: f64, theoretical: f64) -> f64 {
+    (observed - theoretical) / theoretical * 1_000_000.0
+}
+
+/// Absolute error of the observed mass in Dalton
+///
+pub fn da_error(observed: f64, theoretical: f64) -> f64 {
+    observed - theoretical
+}
+
+impl Identification {
+    /// Appends the columns `calc_mass` (theoretical neutral mass of the PSM peptide) and `ppm_error`
+    /// (error of the observed precursor mass), replacing existing ones.
+    /// The peptide is taken from `modified_peptide` (Comet format) or `plain_peptide` if the former is missing.
+    /// Values are null for unknown amino acids and the error is null if the precursor charge is unknown.
+    /// Does nothing if the identification has no PSMs.
+    ///
+    pub fn append_mass_errors(&mut self) -> Result<()> {
+        let observed_mass = match self.get_charge() {
+            0 => None,
+            charge => Some(mz_to_mass(
+                self.get_precursor().get_monoisotopic_mz(),
+                charge,
+            )),
+        };
+        let psms = match self.get_psms_mut() {
+            Some(psms) => psms,
+            None => return Ok(()),
+        };
+        let calc_mass = theoretical_masses(psms)?;
+        let ppm_errors: Vec<Option<f64>> = calc_mass
+            .iter()
+            .map(|calc_mass| Some(ppm_error(observed_mass?, (*calc_mass)?)))
+            .collect();
+        psms.with_column(Series::new(psm_columns::CALC_MASS, calc_mass))?;
+        psms.with_column(Series::new(psm_columns::PPM_ERROR, ppm_errors))?;
+        Ok(())
+    }
+}
+
+/// Theoretical neutral masses of the PSM peptides, taken from `modified_peptide` (Comet format)
+/// or `plain_peptide` if the former is missing. Null for unknown amino acids.
+///
+pub(crate) fn theoretical_masses(psms: &DataFrame) -> Result<Vec<Option<f64>>> {
+    match psms.column(psm_columns::MODIFIED_PEPTIDE) {
+        Ok(modified_peptides) => modified_peptides
+            .utf8()?
+            .into_iter()
+            .map(|modified_peptide| match modified_peptide {
+                Some(modified_peptide) => Ok(modified_peptide_mass(&Peptidoform::from_comet(
+                    modified_peptide,
+                )?)),
+                None => Ok(None),
+            })
+            .collect(),
+        Err(_) => Ok(psms
+            .column(psm_columns::PLAIN_PEPTIDE)?
+            .utf8()?
+            .into_iter()
+            .map(|plain_peptide| plain_peptide.and_then(peptide_mass))
+            .collect()),
+    }
+}
@@ -0,0 +1,251 @@
+// 3rd party imports
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+// internal imports
+use crate::results_api::{
+    MsRun, MsRunName, Search, SearchUuid, Spectrum, SpectrumId, SCHEMA_VERSION,
+};
+
+/// Name of the version field in the serialized entities
+pub const SCHEMA_VERSION_FIELD: &str = "schema_version";
+
+/// Upgrades a serialized entity by one version, e.g. by renaming or adding fields
+pub type Migration = fn(&mut Value) -> Result<()>;
+
+/// Entity which can be upgraded from older serialized layouts.
+/// The migration at index `i` upgrades from version `i` to `i + 1`,
+/// so there needs to be one migration per version up to `SCHEMA_VERSION`.
+///
+pub trait Migratable: DeserializeOwned {
+    fn migrations() -> &'static [Migration];
+}
+
+impl Migratable for Search {
+    fn migrations() -> &'static [Migration] {
+        &[add_schema_version, unchanged, repair_search_identifiers]
+    }
+}
+
+impl Migratable for MsRun {
+    fn migrations() -> &'static [Migration] {
+        &[add_schema_version, unchanged, repair_ms_run_identifiers]
+    }
+}
+
+impl Migratable for Spectrum {
+    fn migrations() -> &'static [Migration] {
+        &[
+            add_schema_version,
+            structure_precursor,
+            repair_spectrum_identifiers,
+        ]
+    }
+}
+
+/// Upgrades the given serialized entity to the current layout and deserializes it.
+/// Payloads without version are treated as version 0.
+///
+/// ```
+/// use maccoys_exchange_entities::migrations::migrate;
+/// use maccoys_exchange_entities::results_api::{MsRun, SearchUuid};
+///
+/// // identifiers of version 2 payloads were not validated
+/// let ms_run: MsRun = migrate(serde_json::json!({
+///     "schema_version": 2,
+///     "search_uuid": "",
+///     "ms_run_name": "",
+///     "spectra_ids": ["scan=1", "run/scan=2"]
+/// }))
+/// .unwrap();
+/// assert_eq!(ms_run.get_search_uuid(), &SearchUuid::nil());
+/// assert_eq!(ms_run.get_ms_run(), "unnamed");
+/// assert_eq!(ms_run.get_spectra_ids()[1], "run_scan=2");
+///
+/// // the repaired MS run round trips
+/// let json = serde_json::to_value(&ms_run).unwrap();
+/// assert!(migrate::<MsRun>(json).unwrap() == ms_run);
+/// ```
+///
+pub fn migrate<T: Migratable>(mut value: Value) -> Result<T> {
+    let version = get_schema_version(&value)?;
+    if version > SCHEMA_VERSION {
+        bail!(
+            "schema version {} is newer than supported version {}",
+            version,
+            SCHEMA_VERSION
+        );
+    }
+    let migrations = T::migrations();
+    for (from_version, migration) in migrations
+        .iter()
+        .enumerate()
+        .skip(version as usize)
+        .take((SCHEMA_VERSION - version) as usize)
+    {
+        migration(&mut value).with_context(|| {
+            format!(
+                "migration from schema version {} to {} failed",
+                from_version,
+                from_version + 1
+            )
+        })?;
+    }
+    set_schema_version(&mut value, SCHEMA_VERSION)?;
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Same as `migrate` but parses the JSON first
+///
+pub fn migrate_json<T: Migratable>(json: &str) -> Result<T> {
+    migrate(serde_json::from_str(json)?)
+}
+
+/// Returns the schema version of the serialized entity, 0 if not present
+///
+pub fn get_schema_version(value: &Value) -> Result<u32> {
+    match value.get(SCHEMA_VERSION_FIELD) {
+        None | Some(Value::Null) => Ok(0),
+        Some(version) => match version.as_u64() {
+            Some(version) => Ok(u32::try_from(version)?),
+            None => bail!("schema version `{}` is not an unsigned integer", version),
+        },
+    }
+}
+
+fn as_object(value: &mut Value) -> Result<&mut serde_json::Map<String, Value>> {
+    match value.as_object_mut() {
+        Some(object) => Ok(object),
+        None => bail!("serialized entity is not an object"),
+    }
+}
+
+fn set_schema_version(value: &mut Value, version: u32) -> Result<()> {
+    as_object(value)?.insert(SCHEMA_VERSION_FIELD.to_string(), Value::from(version));
+    Ok(())
+}
+
+/// Renames a field of the serialized entity, does nothing if the field does not exist
+///
+pub fn rename_field(value: &mut Value, old_name: &str, new_name: &str) -> Result<()> {
+    let object = as_object(value)?;
+    if let Some(field) = object.remove(old_name) {
+        object.insert(new_name.to_string(), field);
+    }
+    Ok(())
+}
+
+/// Adds a field with the given default value if it does not exist
+///
+pub fn add_field(value: &mut Value, name: &str, default: Value) -> Result<()> {
+    as_object(value)?.entry(name).or_insert(default);
+    Ok(())
+}
+
+/// Renames a column in the PSM dataframes of all identifications of a serialized spectrum
+///
+pub fn rename_psm_column(spectrum: &mut Value, old_name: &str, new_name: &str) -> Result<()> {
+    let identifications = match as_object(spectrum)?.get_mut("identifications") {
+        Some(Value::Array(identifications)) => identifications,
+        _ => return Ok(()),
+    };
+    for identification in identifications.iter_mut() {
+        let columns = match identification.pointer_mut("/psms/columns") {
+            Some(Value::Array(columns)) => columns,
+            _ => continue,
+        };
+        for column in columns.iter_mut() {
+            if column.get("name").and_then(Value::as_str) == Some(old_name) {
+                column["name"] = Value::from(new_name);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Version 0 -> 1: Introduction of the schema version, which is set after the migrations
+///
+fn add_schema_version(_value: &mut Value) -> Result<()> {
+    Ok(())
+}
+
+/// Version 1 -> 2: The bare precursor m/z and charge of each identification are merged into a `Precursor` object
+///
+fn structure_precursor(spectrum: &mut Value) -> Result<()> {
+    let identifications = match as_object(spectrum)?.get_mut("identifications") {
+        Some(Value::Array(identifications)) => identifications,
+        _ => return Ok(()),
+    };
+    for identification in identifications.iter_mut() {
+        let identification = as_object(identification)?;
+        if let Some(Value::Number(mz)) = identification.get("precursor").cloned() {
+            let charge = identification.remove("charge").unwrap_or(Value::from(0));
+            identification.insert(
+                "precursor".to_string(),
+                serde_json::json!({ "mz": mz, "charge": charge }),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Version 2 -> 3: Identifiers are validated on deserialization, so invalid search UUIDs, MS run names
+/// and spectrum IDs of older payloads are replaced by sanitized ones (see `SearchUuid::sanitized`).
+///
+fn repair_search_identifiers(search: &mut Value) -> Result<()> {
+    repair_identifier(search, "search_uuid", SearchUuid::sanitized)?;
+    repair_identifiers(search, "ms_run_names", MsRunName::sanitized)
+}
+
+/// Version 2 -> 3: See `repair_search_identifiers`
+///
+fn repair_ms_run_identifiers(ms_run: &mut Value) -> Result<()> {
+    repair_identifier(ms_run, "search_uuid", SearchUuid::sanitized)?;
+    repair_identifier(ms_run, "ms_run_name", MsRunName::sanitized)?;
+    repair_identifiers(ms_run, "spectra_ids", SpectrumId::sanitized)
+}
+
+/// Version 2 -> 3: See `repair_search_identifiers`
+///
+fn repair_spectrum_identifiers(spectrum: &mut Value) -> Result<()> {
+    repair_identifier(spectrum, "search_uuid", SearchUuid::sanitized)?;
+    repair_identifier(spectrum, "ms_run_name", MsRunName::sanitized)?;
+    repair_identifier(spectrum, "spectrum_id", SpectrumId::sanitized)
+}
+
+/// Replaces the string field with its sanitized form, does nothing if the field does not exist
+///
+fn repair_identifier<T: Into<String>>(
+    value: &mut Value,
+    name: &str,
+    sanitize: fn(&str) -> T,
+) -> Result<()> {
+    if let Some(Value::String(identifier)) = as_object(value)?.get_mut(name) {
+        *identifier = sanitize(identifier).into();
+    }
+    Ok(())
+}
+
+/// Replaces the strings of the array field with their sanitized form, does nothing if the field does not exist
+///
+fn repair_identifiers<T: Into<String>>(
+    value: &mut Value,
+    name: &str,
+    sanitize: fn(&str) -> T,
+) -> Result<()> {
+    if let Some(Value::Array(identifiers)) = as_object(value)?.get_mut(name) {
+        for identifier in identifiers.iter_mut() {
+            if let Value::String(identifier) = identifier {
+                *identifier = sanitize(identifier).into();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Migration for entities whose layout did not change with the version
+///
+fn unchanged(_value: &mut Value) -> Result<()> {
+    Ok(())
+}
@@ -0,0 +1,378 @@
+//! Parsing and formatting of peptidoforms in the HUPO-PSI ProForma 2.0 notation,
+//! e.g. `[Acetyl]-PEPM[+15.9949]IDE` (<https://github.com/HUPO-PSI/ProForma>).
+//!
+//! Supported are mass deltas (`[+15.9949]`, `[Obs:+15.9949]`), Unimod names and accessions
+//! (`[Oxidation]`, `[U:Oxidation]`, `[UNIMOD:35]`), alternatives separated by `|` and terminal modifications.
+//! Labile, global, ambiguous and cross-linking modifications are rejected.
+
+// std imports
+use std::fmt;
+use std::str::FromStr;
+
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::export::parse_comet_modifications;
+use crate::results_api::modification::{
+    parse_modified_sequence, unimod_by_accession, unimod_by_name, UnimodEntry, UNIMOD_TOLERANCE,
+};
+use crate::results_api::{
+    psm_columns, Identification, Modification, ModificationError, ModificationPosition,
+};
+
+/// Error when parsing a ProForma peptidoform
+///
+#[derive(Debug, thiserror::Error)]
+pub enum ProFormaError {
+    #[error("unexpected character {1:?} in peptidoform `{0}`")]
+    UnexpectedCharacter(String, char),
+    #[error("unclosed bracket in peptidoform `{0}`")]
+    UnclosedBracket(String),
+    #[error("modification `{0}` is neither a mass delta nor a known Unimod entry")]
+    UnknownModification(String),
+    #[error("{1} are not supported, found in `{0}`")]
+    Unsupported(String, &'static str),
+    #[error("modification position `{position}` is invalid for `{sequence}`")]
+    InvalidModificationPosition { position: String, sequence: String },
+    #[error(transparent)]
+    Modification(#[from] ModificationError),
+}
+
+/// Notation of modifications when formatting a peptidoform
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModificationNotation {
+    /// Mass delta, e.g. `[+15.9949]`
+    #[default]
+    MassDelta,
+    /// Name, e.g. `[Oxidation]`, falling back to the mass delta for unnamed modifications
+    Name,
+    /// Unimod accession, e.g. `[UNIMOD:35]`, falling back to the mass delta
+    Unimod,
+}
+
+/// Peptide sequence with its modifications
+///
+/// ```
+/// use maccoys_exchange_entities::proforma::{ModificationNotation, Peptidoform};
+///
+/// let peptidoform: Peptidoform = "[Acetyl]-PEPM[+15.9949]IDE".parse().unwrap();
+/// assert_eq!(peptidoform.get_sequence(), "PEPMIDE");
+/// assert_eq!(peptidoform.to_string(), "[+42.010565]-PEPM[+15.9949]IDE");
+/// assert_eq!(
+///     peptidoform.format(ModificationNotation::Name),
+///     "[Acetyl]-PEPM[Oxidation]IDE"
+/// );
+///
+/// let comet = Peptidoform::from_comet("n[42.0106]PEPM[15.9949]IDE").unwrap();
+/// assert_eq!(comet.format(ModificationNotation::Unimod), "[UNIMOD:1]-PEPM[UNIMOD:35]IDE");
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Peptidoform {
+    sequence: String,
+    modifications: Vec<Modification>,
+}
+
+impl Peptidoform {
+    pub fn new(sequence: String, modifications: Vec<Modification>) -> Self {
+        Self {
+            sequence,
+            modifications,
+        }
+    }
+
+    /// Peptidoform of a modified sequence as written by Comet in the `modified_peptide` column,
+    /// e.g. `n[42.0106]PEPM[15.9949]IDE`
+    ///
+    pub fn from_comet(modified_sequence: &str) -> Result<Self, ProFormaError> {
+        let (sequence, modifications) = parse_modified_sequence(modified_sequence)?;
+        Ok(Self::new(sequence, modifications))
+    }
+
+    /// Peptidoform of a plain sequence and Comet modifications (`<position>_<type>_<mass>`, comma separated).
+    /// Positions are 1-based, `N`/`n` and `C`/`c` denote the peptide termini.
+    ///
+    /// # Arguments
+    /// * `sequence` - Plain peptide sequence
+    /// * `modifications` - Content of the `modifications` column
+    ///
+    pub fn from_comet_modifications(
+        sequence: &str,
+        modifications: &str,
+    ) -> Result<Self, ProFormaError> {
+        let residues: Vec<char> = sequence.chars().collect();
+        let mut parsed = Vec::new();
+        for (position, mass) in parse_comet_modifications(modifications) {
+            let modification = match position {
+                "N" | "n" | "0" => Modification::new(ModificationPosition::NTerm, None, mass),
+                "C" | "c" => Modification::new(ModificationPosition::CTerm, None, mass),
+                _ => match position.parse::<usize>() {
+                    Ok(position) if (1..=residues.len()).contains(&position) => Modification::new(
+                        ModificationPosition::Residue(position - 1),
+                        Some(residues[position - 1]),
+                        mass,
+                    ),
+                    _ => {
+                        return Err(ProFormaError::InvalidModificationPosition {
+                            position: position.to_string(),
+                            sequence: sequence.to_string(),
+                        })
+                    }
+                },
+            };
+            parsed.push(modification);
+        }
+        Ok(Self::new(sequence.to_string(), parsed))
+    }
+
+    pub fn get_sequence(&self) -> &str {
+        &self.sequence
+    }
+
+    pub fn get_modifications(&self) -> &Vec<Modification> {
+        &self.modifications
+    }
+
+    /// Formats the peptidoform in ProForma notation using the given notation for modifications
+    ///
+    pub fn format(&self, notation: ModificationNotation) -> String {
+        let tags = |position: ModificationPosition| {
+            self.modifications
+                .iter()
+                .filter(|modification| modification.get_position() == position)
+                .map(|modification| format_tag(modification, notation))
+                .collect::<String>()
+        };
+        let mut proforma = String::new();
+        let n_term = tags(ModificationPosition::NTerm);
+        if !n_term.is_empty() {
+            proforma.push_str(&n_term);
+            proforma.push('-');
+        }
+        for (idx, residue) in self.sequence.chars().enumerate() {
+            proforma.push(residue);
+            proforma.push_str(&tags(ModificationPosition::Residue(idx)));
+        }
+        let c_term = tags(ModificationPosition::CTerm);
+        if !c_term.is_empty() {
+            proforma.push('-');
+            proforma.push_str(&c_term);
+        }
+        proforma
+    }
+}
+
+/// Formats the modification as ProForma tag, e.g. `[+15.9949]`
+///
+fn format_tag(modification: &Modification, notation: ModificationNotation) -> String {
+    match (
+        notation,
+        modification.get_name(),
+        modification.get_unimod_id(),
+    ) {
+        (ModificationNotation::Name, Some(name), _) => format!("[{}]", name),
+        (ModificationNotation::Unimod, _, Some(unimod_id)) => format!("[{}]", unimod_id),
+        _ => format!("[{:+}]", modification.get_mass_delta()),
+    }
+}
+
+/// Prefixes of ProForma tags, in upper case
+const TAG_PREFIXES: [&str; 15] = [
+    "U", "M", "R", "X", "G", "B", "UNIMOD", "MOD", "RESID", "XLMOD", "GNO", "OBS", "INFO",
+    "FORMULA", "GLYCAN",
+];
+
+/// Parses the content of a ProForma tag (without brackets), e.g. `Oxidation|+15.9949`.
+/// Plain mass deltas are annotated with Unimod entries matching within `UNIMOD_TOLERANCE`.
+///
+fn parse_tag(
+    tag: &str,
+    position: ModificationPosition,
+    residue: Option<char>,
+) -> Result<Modification, ProFormaError> {
+    let mut mass_delta: Option<f64> = None;
+    let mut entry: Option<&UnimodEntry> = None;
+    let mut name: Option<&str> = None;
+    for part in tag.split('|').map(str::trim) {
+        // names may contain colons themselves, e.g. `Label:13C(6)`, so only known prefixes are split off
+        let (prefix, value) = match part.split_once(':') {
+            Some((prefix, value))
+                if TAG_PREFIXES.contains(&prefix.to_ascii_uppercase().as_str()) =>
+            {
+                (prefix.to_ascii_uppercase(), value)
+            }
+            _ => (String::new(), part),
+        };
+        match prefix.as_str() {
+            "" | "OBS" if value.starts_with(['+', '-']) => match value.parse() {
+                Ok(mass) => mass_delta = mass_delta.or(Some(mass)),
+                Err(_) => return Err(ProFormaError::UnknownModification(tag.to_string())),
+            },
+            "UNIMOD" => match value.parse().ok().and_then(unimod_by_accession) {
+                Some(unimod) => entry = entry.or(Some(unimod)),
+                None => return Err(ProFormaError::UnknownModification(tag.to_string())),
+            },
+            "" | "U" => match unimod_by_name(value) {
+                Some(unimod) => entry = entry.or(Some(unimod)),
+                None => name = name.or(Some(value)),
+            },
+            "INFO" => (),
+            "GLYCAN" | "FORMULA" | "XLMOD" | "GNO" => {
+                return Err(ProFormaError::Unsupported(
+                    tag.to_string(),
+                    "formula, glycan and cross-link modifications",
+                ))
+            }
+            // names of other vocabularies, e.g. PSI-MOD (`M:`) or RESID (`R:`)
+            _ => name = name.or(Some(part)),
+        }
+    }
+    let mass_delta = match (mass_delta, entry) {
+        (Some(mass_delta), _) => mass_delta,
+        (None, Some(entry)) => entry.mass_delta,
+        (None, None) => return Err(ProFormaError::UnknownModification(tag.to_string())),
+    };
+    let modification = Modification::new(position, residue, mass_delta);
+    Ok(match (entry, name) {
+        (Some(entry), _) => modification.with_unimod(entry.accession, entry.name.to_string()),
+        (None, Some(name)) => modification.with_name(name.to_string()),
+        (None, None) => modification.annotate_unimod(UNIMOD_TOLERANCE),
+    })
+}
+
+/// Reads a tag starting after the opening bracket and returns its content
+///
+fn read_tag(
+    peptidoform: &str,
+    chars: &mut std::iter::Peekable<std::str::Chars<'_>>,
+) -> Result<String, ProFormaError> {
+    let mut tag = String::new();
+    let mut depth = 0;
+    for character in chars.by_ref() {
+        match character {
+            '[' => depth += 1,
+            ']' if depth == 0 => return Ok(tag),
+            ']' => depth -= 1,
+            _ => (),
+        }
+        tag.push(character);
+    }
+    Err(ProFormaError::UnclosedBracket(peptidoform.to_string()))
+}
+
+impl fmt::Display for Peptidoform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.format(ModificationNotation::MassDelta))
+    }
+}
+
+impl FromStr for Peptidoform {
+    type Err = ProFormaError;
+
+    fn from_str(peptidoform: &str) -> Result<Self, Self::Err> {
+        let unsupported =
+            |feature| Err(ProFormaError::Unsupported(peptidoform.to_string(), feature));
+        let mut sequence = String::with_capacity(peptidoform.len());
+        let mut modifications = Vec::new();
+        let mut chars = peptidoform.trim().chars().peekable();
+
+        // N-terminal modifications
+        let mut n_term_tags = Vec::new();
+        while chars.peek() == Some(&'[') {
+            chars.next();
+            n_term_tags.push(read_tag(peptidoform, &mut chars)?);
+        }
+        if !n_term_tags.is_empty() {
+            match chars.next() {
+                Some('-') => (),
+                Some('?') => return unsupported("modifications of unknown position"),
+                _ => {
+                    return Err(ProFormaError::UnexpectedCharacter(
+                        peptidoform.to_string(),
+                        '[',
+                    ))
+                }
+            }
+        }
+        for tag in n_term_tags {
+            modifications.push(parse_tag(&tag, ModificationPosition::NTerm, None)?);
+        }
+
+        while let Some(character) = chars.next() {
+            match character {
+                residue if residue.is_ascii_uppercase() => sequence.push(residue),
+                '[' => {
+                    let residue = match sequence.chars().last() {
+                        Some(residue) => residue,
+                        None => {
+                            return Err(ProFormaError::UnexpectedCharacter(
+                                peptidoform.to_string(),
+                                '[',
+                            ))
+                        }
+                    };
+                    let tag = read_tag(peptidoform, &mut chars)?;
+                    modifications.push(parse_tag(
+                        &tag,
+                        ModificationPosition::Residue(sequence.len() - 1),
+                        Some(residue),
+                    )?);
+                }
+                // C-terminal modifications end the peptidoform
+                '-' if chars.peek() == Some(&'[') => loop {
+                    match chars.next() {
+                        Some('[') => {
+                            let tag = read_tag(peptidoform, &mut chars)?;
+                            modifications.push(parse_tag(&tag, ModificationPosition::CTerm, None)?);
+                        }
+                        Some(other) => {
+                            return Err(ProFormaError::UnexpectedCharacter(
+                                peptidoform.to_string(),
+                                other,
+                            ))
+                        }
+                        None => break,
+                    }
+                },
+                '{' => return unsupported("labile modifications"),
+                '<' => return unsupported("global modifications"),
+                '(' | '?' => return unsupported("ambiguous modifications"),
+                '/' | '+' => return unsupported("charge states and chimeric spectra"),
+                other => {
+                    return Err(ProFormaError::UnexpectedCharacter(
+                        peptidoform.to_string(),
+                        other,
+                    ))
+                }
+            }
+        }
+        Ok(Self::new(sequence, modifications))
+    }
+}
+
+impl Identification {
+    /// Peptidoforms of the PSMs from the `modified_peptide` column, in order of the PSM rows.
+    /// PSMs without modified peptide are None. Returns None if the identification has no PSMs.
+    ///
+    pub fn get_psm_peptidoforms(&self) -> Result<Option<Vec<Option<Peptidoform>>>, ProFormaError> {
+        let rows = match self
+            .iter_psm_rows_select(&[psm_columns::MODIFIED_PEPTIDE])
+            .map_err(ModificationError::from)?
+        {
+            Some(rows) => rows,
+            None => return Ok(None),
+        };
+        rows.map(|row| {
+            row.get::<Option<&str>>(psm_columns::MODIFIED_PEPTIDE)
+                .map_err(ModificationError::from)?
+                .map(Peptidoform::from_comet)
+                .transpose()
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+    }
+}
@@ -0,0 +1,562 @@
+//! Protocol Buffers encoding of the exchange entities following `proto/maccoys_exchange.proto` (see `PROTO`),
+//! so non-Rust services, e.g. a Java LIMS, can consume the results over gRPC with generated types.
+//! The Rust types in `v1` are generated by prost-build, this module only converts between them and the entities.
+//!
+//! PSM and goodness tables are encoded column-wise, columns other than floats, integers, strings and booleans
+//! are converted to strings. Unknown fields are skipped when decoding, so the definitions can be extended
+//! with new fields without breaking older readers.
+
+/// Message types of the package `maccoys.exchange.v1`, generated from `PROTO` by prost-build
+pub mod v1 {
+    include!(concat!(env!("OUT_DIR"), "/maccoys.exchange.v1.rs"));
+}
+
+// 3rd party imports
+use anyhow::{bail, Context, Result};
+use polars::prelude::*;
+use prost::Message;
+
+// internal imports
+use crate::results_api::chunked::any_value_to_json;
+use crate::results_api::{
+    Identification, MsRun, MsRunName, Precursor, Search, SearchUuid, SequenceTag, Spectrum,
+    SpectrumId,
+};
+use v1::ColumnType;
+
+/// Content of `proto/maccoys_exchange.proto`, e.g. for generating the types of other languages
+pub const PROTO: &str = include_str!("../../proto/maccoys_exchange.proto");
+
+/// Entity with a message in `PROTO`
+///
+/// ```
+/// use maccoys_exchange_entities::proto::ProtoMessage;
+/// use maccoys_exchange_entities::results_api::{Identification, Precursor, Spectrum};
+/// use polars::prelude::*;
+///
+/// let psms = df!(
+///     "plain_peptide" => &[Some("PEPTIDE"), None],
+///     "xcorr" => &[2.5, 1.0],
+///     "is_decoy" => &[false, true],
+///     "charge" => &[2i64, 3]
+/// )
+/// .unwrap();
+/// let spectrum = Spectrum::new(
+///     "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+///     "run".parse().unwrap(),
+///     "scan=1".parse().unwrap(),
+///     vec![100.0, 200.0],
+///     vec![1.0, 2.0],
+///     vec![Identification::new(None, Some(psms.clone()), Precursor::new(400.7, 2))],
+/// )
+/// .with_retention_time(Some(1200.0));
+///
+/// let bytes = spectrum.to_protobuf().unwrap();
+/// let decoded = Spectrum::from_protobuf(&bytes).unwrap();
+/// assert_eq!(decoded.get_spectra_id(), "scan=1");
+/// assert_eq!(decoded.get_mz(), &[100.0, 200.0]);
+/// assert_eq!(*decoded.get_retention_time(), Some(1200.0));
+/// let identification = &decoded.get_identifications()[0];
+/// assert_eq!(identification.get_charge(), 2);
+/// assert!(identification.get_psms().as_ref().unwrap().frame_equal_missing(&psms));
+/// assert!(identification.get_goodnesses().is_none());
+/// ```
+///
+pub trait ProtoMessage: Sized {
+    /// Encodes the entity as its message
+    ///
+    fn to_protobuf(&self) -> Result<Vec<u8>>;
+
+    /// Decodes the entity from its message
+    ///
+    fn from_protobuf(bytes: &[u8]) -> Result<Self>;
+}
+
+/// Implements `ProtoMessage` by converting the entity to and from its generated message type
+///
+macro_rules! impl_proto_message {
+    ($entity:ty, $message:ty) => {
+        impl ProtoMessage for $entity {
+            fn to_protobuf(&self) -> Result<Vec<u8>> {
+                Ok(<$message>::try_from(self)?.encode_to_vec())
+            }
+
+            fn from_protobuf(bytes: &[u8]) -> Result<Self> {
+                <$message>::decode(bytes)?.try_into()
+            }
+        }
+    };
+}
+
+impl_proto_message!(Search, v1::Search);
+impl_proto_message!(MsRun, v1::MsRun);
+impl_proto_message!(Spectrum, v1::Spectrum);
+impl_proto_message!(Identification, v1::Identification);
+
+/// Encodes the MS runs as `ListMsRunsResponse`
+///
+/// ```
+/// use maccoys_exchange_entities::proto::{decode_ms_runs, encode_ms_runs};
+/// use maccoys_exchange_entities::results_api::MsRun;
+///
+/// let search_uuid = "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c";
+/// let ms_runs = vec![
+///     MsRun::new(search_uuid.parse().unwrap(), "run_1".parse().unwrap(), vec!["scan=1".parse().unwrap()]),
+///     MsRun::new(search_uuid.parse().unwrap(), "run_2".parse().unwrap(), Vec::new()).with_revision(3),
+/// ];
+/// let decoded = decode_ms_runs(&encode_ms_runs(&ms_runs).unwrap()).unwrap();
+/// assert!(decoded == ms_runs);
+/// ```
+///
+pub fn encode_ms_runs(ms_runs: &[MsRun]) -> Result<Vec<u8>> {
+    let response = v1::ListMsRunsResponse {
+        ms_runs: ms_runs
+            .iter()
+            .map(v1::MsRun::try_from)
+            .collect::<Result<_>>()?,
+    };
+    Ok(response.encode_to_vec())
+}
+
+/// Decodes the MS runs of a `ListMsRunsResponse`
+///
+pub fn decode_ms_runs(bytes: &[u8]) -> Result<Vec<MsRun>> {
+    v1::ListMsRunsResponse::decode(bytes)?
+        .ms_runs
+        .into_iter()
+        .map(MsRun::try_from)
+        .collect()
+}
+
+impl TryFrom<&Search> for v1::Search {
+    type Error = anyhow::Error;
+
+    fn try_from(search: &Search) -> Result<Self> {
+        Ok(Self {
+            schema_version: search.get_schema_version(),
+            search_uuid: search.get_search_uuid().to_string(),
+            ms_run_names: search
+                .get_ms_run_names()
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            revision: search.get_revision(),
+            parameters_json: search
+                .get_parameters()
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+            config_json: serde_json::to_string(search.get_config())?,
+            errors_json: to_json_strings(search.get_errors())?,
+            quantification_json: search
+                .get_quantification()
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+            transferred_identifications_json: to_json_strings(
+                search.get_transferred_identifications(),
+            )?,
+        })
+    }
+}
+
+impl TryFrom<v1::Search> for Search {
+    type Error = anyhow::Error;
+
+    fn try_from(message: v1::Search) -> Result<Self> {
+        let ms_run_names = message
+            .ms_run_names
+            .iter()
+            .map(|name| MsRunName::new(name))
+            .collect::<Result<_, _>>()?;
+        let mut search = Search::new(parse_search_uuid(&message.search_uuid)?, ms_run_names)
+            .with_revision(message.revision)
+            .with_transferred_identifications(from_json_strings(
+                &message.transferred_identifications_json,
+            )?);
+        if let Some(parameters) = message.parameters_json {
+            search = search.with_parameters(serde_json::from_str(&parameters)?);
+        }
+        // proto3 strings are empty if not set
+        if !message.config_json.is_empty() {
+            search = search.with_config(serde_json::from_str(&message.config_json)?);
+        }
+        if let Some(quantification) = message.quantification_json {
+            search = search.with_quantification(serde_json::from_str(&quantification)?);
+        }
+        for error in from_json_strings(&message.errors_json)? {
+            search.add_error(error);
+        }
+        Ok(search)
+    }
+}
+
+impl TryFrom<&MsRun> for v1::MsRun {
+    type Error = anyhow::Error;
+
+    fn try_from(ms_run: &MsRun) -> Result<Self> {
+        Ok(Self {
+            schema_version: ms_run.get_schema_version(),
+            search_uuid: ms_run.get_search_uuid().to_string(),
+            ms_run_name: ms_run.get_ms_run().to_string(),
+            spectra_ids: ms_run
+                .get_spectra_ids()
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            revision: ms_run.get_revision(),
+            errors_json: to_json_strings(ms_run.get_errors())?,
+            spectrum_index_json: ms_run
+                .get_spectrum_index()
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+        })
+    }
+}
+
+impl TryFrom<v1::MsRun> for MsRun {
+    type Error = anyhow::Error;
+
+    fn try_from(message: v1::MsRun) -> Result<Self> {
+        let spectra_ids = message
+            .spectra_ids
+            .iter()
+            .map(|spectrum_id| SpectrumId::new(spectrum_id))
+            .collect::<Result<_, _>>()?;
+        let mut ms_run = MsRun::new(
+            parse_search_uuid(&message.search_uuid)?,
+            MsRunName::new(&message.ms_run_name).context("MS run without name")?,
+            spectra_ids,
+        )
+        .with_revision(message.revision);
+        if let Some(spectrum_index) = message.spectrum_index_json {
+            ms_run = ms_run.with_spectrum_index(serde_json::from_str(&spectrum_index)?);
+        }
+        for error in from_json_strings(&message.errors_json)? {
+            ms_run.add_error(error);
+        }
+        Ok(ms_run)
+    }
+}
+
+impl TryFrom<&Spectrum> for v1::Spectrum {
+    type Error = anyhow::Error;
+
+    fn try_from(spectrum: &Spectrum) -> Result<Self> {
+        Ok(Self {
+            schema_version: spectrum.get_schema_version(),
+            search_uuid: spectrum.get_search_uuid().to_string(),
+            ms_run_name: spectrum.get_ms_run().to_string(),
+            spectrum_id: spectrum.get_spectra_id().to_string(),
+            retention_time: *spectrum.get_retention_time(),
+            ion_mobility: *spectrum.get_ion_mobility(),
+            ms_level: spectrum.get_ms_level().map(u32::from),
+            scan_number: *spectrum.get_scan_number(),
+            mz: spectrum.get_mz().to_vec(),
+            intensity: spectrum.get_intensity().to_vec(),
+            identifications: spectrum
+                .get_identifications()
+                .iter()
+                .map(v1::Identification::try_from)
+                .collect::<Result<_>>()?,
+            sequence_tags: spectrum
+                .get_sequence_tags()
+                .iter()
+                .map(v1::SequenceTag::from)
+                .collect(),
+            payload_digest: spectrum.get_payload_digest().clone(),
+            revision: spectrum.get_revision(),
+        })
+    }
+}
+
+impl TryFrom<v1::Spectrum> for Spectrum {
+    type Error = anyhow::Error;
+
+    fn try_from(message: v1::Spectrum) -> Result<Self> {
+        Ok(Spectrum::new(
+            parse_search_uuid(&message.search_uuid)?,
+            MsRunName::new(&message.ms_run_name).context("spectrum without MS run name")?,
+            SpectrumId::new(&message.spectrum_id).context("spectrum without ID")?,
+            message.mz,
+            message.intensity,
+            message
+                .identifications
+                .into_iter()
+                .map(Identification::try_from)
+                .collect::<Result<_>>()?,
+        )
+        .with_retention_time(message.retention_time)
+        .with_ion_mobility(message.ion_mobility)
+        .with_ms_level(message.ms_level.map(u8::try_from).transpose()?)
+        .with_scan_number(message.scan_number)
+        .with_sequence_tags(
+            message
+                .sequence_tags
+                .into_iter()
+                .map(SequenceTag::try_from)
+                .collect::<Result<_>>()?,
+        )
+        .with_payload_digest(message.payload_digest)
+        .with_revision(message.revision))
+    }
+}
+
+impl TryFrom<&Identification> for v1::Identification {
+    type Error = anyhow::Error;
+
+    fn try_from(identification: &Identification) -> Result<Self> {
+        Ok(Self {
+            precursor: Some(identification.get_precursor().into()),
+            psms: identification
+                .get_psms()
+                .as_ref()
+                .map(encode_table)
+                .transpose()?,
+            goodnesses: identification
+                .get_goodnesses()
+                .as_ref()
+                .map(encode_table)
+                .transpose()?,
+            reporter_ions_json: identification
+                .get_reporter_ions()
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+            score_descriptors_json: to_json_strings(identification.get_score_descriptors())?,
+        })
+    }
+}
+
+impl TryFrom<v1::Identification> for Identification {
+    type Error = anyhow::Error;
+
+    fn try_from(message: v1::Identification) -> Result<Self> {
+        let mut identification = Identification::new(
+            message.goodnesses.map(decode_table).transpose()?,
+            message.psms.map(decode_table).transpose()?,
+            message
+                .precursor
+                .context("identification without precursor")?
+                .try_into()?,
+        );
+        identification.set_reporter_ions(
+            message
+                .reporter_ions_json
+                .map(|reporter_ions| serde_json::from_str(&reporter_ions))
+                .transpose()?,
+        );
+        for score_descriptor in from_json_strings(&message.score_descriptors_json)? {
+            identification.register_score_descriptor(score_descriptor);
+        }
+        Ok(identification)
+    }
+}
+
+impl From<&Precursor> for v1::Precursor {
+    fn from(precursor: &Precursor) -> Self {
+        Self {
+            mz: precursor.get_mz(),
+            charge: u32::from(precursor.get_charge()),
+            intensity: *precursor.get_intensity(),
+            isolation_window_lower_offset: *precursor.get_isolation_window_lower_offset(),
+            isolation_window_upper_offset: *precursor.get_isolation_window_upper_offset(),
+            monoisotopic_correction: precursor.get_monoisotopic_correction().map(i32::from),
+        }
+    }
+}
+
+impl TryFrom<v1::Precursor> for Precursor {
+    type Error = anyhow::Error;
+
+    fn try_from(message: v1::Precursor) -> Result<Self> {
+        Ok(Precursor::new(message.mz, u8::try_from(message.charge)?)
+            .with_intensity(message.intensity)
+            .with_isolation_window(
+                message.isolation_window_lower_offset,
+                message.isolation_window_upper_offset,
+            )
+            .with_monoisotopic_correction(
+                message
+                    .monoisotopic_correction
+                    .map(i8::try_from)
+                    .transpose()?,
+            ))
+    }
+}
+
+impl From<&SequenceTag> for v1::SequenceTag {
+    fn from(sequence_tag: &SequenceTag) -> Self {
+        Self {
+            sequence: sequence_tag.get_sequence().to_string(),
+            n_term_mass: sequence_tag.get_n_term_mass(),
+            c_term_mass: sequence_tag.get_c_term_mass(),
+            peaks: sequence_tag
+                .get_peaks()
+                .iter()
+                .map(|peak| *peak as u64)
+                .collect(),
+            score: sequence_tag.get_score(),
+            charge: sequence_tag.get_charge().map(u32::from),
+        }
+    }
+}
+
+impl TryFrom<v1::SequenceTag> for SequenceTag {
+    type Error = anyhow::Error;
+
+    fn try_from(message: v1::SequenceTag) -> Result<Self> {
+        Ok(
+            SequenceTag::new(message.sequence, message.n_term_mass, message.c_term_mass)
+                .with_peaks(
+                    message
+                        .peaks
+                        .into_iter()
+                        .map(usize::try_from)
+                        .collect::<Result<_, _>>()?,
+                )
+                .with_score(message.score)
+                .with_charge(message.charge.map(u8::try_from).transpose()?),
+        )
+    }
+}
+
+/// Empty UUIDs (the proto3 default) are read as nil UUID
+///
+fn parse_search_uuid(search_uuid: &str) -> Result<SearchUuid> {
+    Ok(match search_uuid.is_empty() {
+        true => SearchUuid::nil(),
+        false => SearchUuid::new(search_uuid)?,
+    })
+}
+
+fn to_json_strings<T: serde::Serialize>(values: &[T]) -> Result<Vec<String>> {
+    Ok(values
+        .iter()
+        .map(serde_json::to_string)
+        .collect::<Result<_, _>>()?)
+}
+
+fn from_json_strings<T: serde::de::DeserializeOwned>(values: &[String]) -> Result<Vec<T>> {
+    Ok(values
+        .iter()
+        .map(|value| serde_json::from_str(value))
+        .collect::<Result<_, _>>()?)
+}
+
+fn encode_table(table: &DataFrame) -> Result<v1::Table> {
+    let columns = table
+        .get_columns()
+        .iter()
+        .map(encode_column)
+        .collect::<Result<_>>()?;
+    Ok(v1::Table { columns })
+}
+
+fn encode_column(series: &Series) -> Result<v1::Column> {
+    let mut column = v1::Column {
+        name: series.name().to_string(),
+        null_indexes: series
+            .is_null()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, is_null)| is_null.unwrap_or_default())
+            .map(|(index, _)| u32::try_from(index))
+            .collect::<Result<_, _>>()?,
+        ..Default::default()
+    };
+    match series.dtype() {
+        DataType::Float32 | DataType::Float64 => {
+            column.set_type(ColumnType::Float);
+            let values = series.cast(&DataType::Float64)?;
+            column.float_values = values
+                .f64()?
+                .into_iter()
+                .map(Option::unwrap_or_default)
+                .collect();
+        }
+        dtype if dtype.is_integer() => {
+            column.set_type(ColumnType::Int);
+            let values = series.cast(&DataType::Int64)?;
+            column.int_values = values
+                .i64()?
+                .into_iter()
+                .map(Option::unwrap_or_default)
+                .collect();
+        }
+        DataType::Utf8 => {
+            column.set_type(ColumnType::String);
+            column.string_values = series
+                .utf8()?
+                .into_iter()
+                .map(|value| value.unwrap_or_default().to_string())
+                .collect();
+        }
+        DataType::Boolean => {
+            column.set_type(ColumnType::Bool);
+            column.bool_values = series
+                .bool()?
+                .into_iter()
+                .map(Option::unwrap_or_default)
+                .collect();
+        }
+        _ => {
+            column.set_type(ColumnType::String);
+            column.string_values = series
+                .iter()
+                .map(|value| match any_value_to_json(&value) {
+                    serde_json::Value::Null => String::new(),
+                    serde_json::Value::String(value) => value,
+                    value => value.to_string(),
+                })
+                .collect();
+        }
+    }
+    Ok(column)
+}
+
+fn decode_table(table: v1::Table) -> Result<DataFrame> {
+    let columns = table
+        .columns
+        .into_iter()
+        .map(decode_column)
+        .collect::<Result<_>>()?;
+    Ok(DataFrame::new(columns)?)
+}
+
+fn decode_column(column: v1::Column) -> Result<Series> {
+    let column_type = ColumnType::try_from(column.r#type).map_err(|_| {
+        anyhow::anyhow!("unknown type {} of column `{}`", column.r#type, column.name)
+    })?;
+    let name = column.name.as_str();
+    let mut is_null = vec![
+        false;
+        column.float_values.len()
+            + column.int_values.len()
+            + column.string_values.len()
+            + column.bool_values.len()
+    ];
+    for index in column.null_indexes {
+        match is_null.get_mut(index as usize) {
+            Some(is_null) => *is_null = true,
+            None => bail!("null index {} exceeds column `{}`", index, name),
+        }
+    }
+    Ok(match column_type {
+        ColumnType::Float => Series::new(name, with_nulls(column.float_values, &is_null)),
+        ColumnType::Int => Series::new(name, with_nulls(column.int_values, &is_null)),
+        ColumnType::String => Series::new(name, with_nulls(column.string_values, &is_null)),
+        ColumnType::Bool => Series::new(name, with_nulls(column.bool_values, &is_null)),
+    })
+}
+
+/// Replaces the placeholders of null rows with None
+///
+fn with_nulls<T>(values: Vec<T>, is_null: &[bool]) -> Vec<Option<T>> {
+    values
+        .into_iter()
+        .zip(is_null)
+        .map(|(value, is_null)| (!is_null).then_some(value))
+        .collect()
+}
@@ -0,0 +1,211 @@
+// 3rd party imports
+use pyo3::{exceptions::PyValueError, prelude::*};
+use pyo3_polars::PyDataFrame;
+use serde::{de::DeserializeOwned, Serialize};
+
+// internal imports
+use crate::results_api::{Identification, MsRun, Search, Spectrum};
+use crate::serialization::WireFormat;
+
+fn from_json<T: DeserializeOwned>(json: &str) -> PyResult<T> {
+    serde_json::from_str(json).map_err(|err| PyValueError::new_err(err.to_string()))
+}
+
+fn to_json<T: Serialize>(entity: &T) -> PyResult<String> {
+    serde_json::to_string(entity).map_err(|err| PyValueError::new_err(err.to_string()))
+}
+
+fn from_msgpack<T: WireFormat>(msgpack: &[u8]) -> PyResult<T> {
+    T::from_msgpack(msgpack).map_err(|err| PyValueError::new_err(err.to_string()))
+}
+
+/// Python view on `Search`
+///
+#[pyclass(name = "Search", module = "maccoys_exchange_entities")]
+pub struct PySearch(Search);
+
+#[pymethods]
+impl PySearch {
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        Ok(Self(from_json(json)?))
+    }
+
+    #[staticmethod]
+    fn from_msgpack(msgpack: &[u8]) -> PyResult<Self> {
+        Ok(Self(from_msgpack(msgpack)?))
+    }
+
+    fn to_json(&self) -> PyResult<String> {
+        to_json(&self.0)
+    }
+
+    #[getter]
+    fn search_uuid(&self) -> &str {
+        self.0.get_search_uuid()
+    }
+
+    #[getter]
+    fn ms_run_names(&self) -> Vec<String> {
+        self.0
+            .get_ms_run_names()
+            .iter()
+            .map(|ms_run_name| ms_run_name.to_string())
+            .collect()
+    }
+}
+
+/// Python view on `MsRun`
+///
+#[pyclass(name = "MsRun", module = "maccoys_exchange_entities")]
+pub struct PyMsRun(MsRun);
+
+#[pymethods]
+impl PyMsRun {
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        Ok(Self(from_json(json)?))
+    }
+
+    #[staticmethod]
+    fn from_msgpack(msgpack: &[u8]) -> PyResult<Self> {
+        Ok(Self(from_msgpack(msgpack)?))
+    }
+
+    fn to_json(&self) -> PyResult<String> {
+        to_json(&self.0)
+    }
+
+    #[getter]
+    fn search_uuid(&self) -> &str {
+        self.0.get_search_uuid()
+    }
+
+    #[getter]
+    fn ms_run_name(&self) -> &str {
+        self.0.get_ms_run()
+    }
+
+    #[getter]
+    fn spectra_ids(&self) -> Vec<String> {
+        self.0
+            .get_spectra_ids()
+            .iter()
+            .map(|spectrum_id| spectrum_id.to_string())
+            .collect()
+    }
+}
+
+/// Python view on `Spectrum`
+///
+#[pyclass(name = "Spectrum", module = "maccoys_exchange_entities")]
+pub struct PySpectrum(Spectrum);
+
+#[pymethods]
+impl PySpectrum {
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        Ok(Self(from_json(json)?))
+    }
+
+    #[staticmethod]
+    fn from_msgpack(msgpack: &[u8]) -> PyResult<Self> {
+        Ok(Self(from_msgpack(msgpack)?))
+    }
+
+    fn to_json(&self) -> PyResult<String> {
+        to_json(&self.0)
+    }
+
+    #[getter]
+    fn search_uuid(&self) -> &str {
+        self.0.get_search_uuid()
+    }
+
+    #[getter]
+    fn ms_run_name(&self) -> &str {
+        self.0.get_ms_run()
+    }
+
+    #[getter]
+    fn spectrum_id(&self) -> &str {
+        self.0.get_spectra_id()
+    }
+
+    /// Retention time in seconds
+    ///
+    #[getter]
+    fn retention_time(&self) -> Option<f64> {
+        *self.0.get_retention_time()
+    }
+
+    #[getter]
+    fn mz(&self) -> Vec<f64> {
+        self.0.get_mz().to_vec()
+    }
+
+    #[getter]
+    fn intensity(&self) -> Vec<f64> {
+        self.0.get_intensity().to_vec()
+    }
+
+    #[getter]
+    fn identifications(&self) -> Vec<PyIdentification> {
+        self.0
+            .get_identifications()
+            .iter()
+            .cloned()
+            .map(PyIdentification)
+            .collect()
+    }
+}
+
+/// Python view on `Identification`
+///
+#[pyclass(name = "Identification", module = "maccoys_exchange_entities")]
+pub struct PyIdentification(Identification);
+
+#[pymethods]
+impl PyIdentification {
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        Ok(Self(from_json(json)?))
+    }
+
+    fn to_json(&self) -> PyResult<String> {
+        to_json(&self.0)
+    }
+
+    #[getter]
+    fn charge(&self) -> u8 {
+        self.0.get_charge()
+    }
+
+    #[getter]
+    fn precursor_mz(&self) -> f64 {
+        self.0.get_precursor().get_mz()
+    }
+
+    /// PSMs as polars DataFrame, None if there are no PSMs
+    ///
+    #[getter]
+    fn psms(&self) -> Option<PyDataFrame> {
+        self.0.get_psms().clone().map(PyDataFrame)
+    }
+
+    /// Goodness of fits as polars DataFrame, None if there are none
+    ///
+    #[getter]
+    fn goodnesses(&self) -> Option<PyDataFrame> {
+        self.0.get_goodnesses().clone().map(PyDataFrame)
+    }
+}
+
+#[pymodule]
+fn maccoys_exchange_entities(_py: Python, module: &PyModule) -> PyResult<()> {
+    module.add_class::<PySearch>()?;
+    module.add_class::<PyMsRun>()?;
+    module.add_class::<PySpectrum>()?;
+    module.add_class::<PyIdentification>()?;
+    Ok(())
+}
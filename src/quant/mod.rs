@@ -0,0 +1,2 @@
+/// Reporter ion quantification of isobaric labels (TMT, iTRAQ)
+pub mod reporter_ions;
@@ -0,0 +1,237 @@
+//! Extraction of reporter ion intensities of isobaric labels from MS2 or (SPS-)MS3 spectra.
+//!
+//! Reporter ion m/z are the monoisotopic m/z of the singly charged reporter ions, see
+//! <https://www.unimod.org> and the product sheets of the labeling reagents.
+
+// 3rd party imports
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::results_api::Spectrum;
+use crate::tolerance::Tolerance;
+
+/// Reporter ion channel of an isobaric label
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReporterChannel {
+    pub name: &'static str,
+    pub mz: f64,
+}
+
+macro_rules! channels {
+    ($($name:literal => $mz:literal),* $(,)?) => {
+        &[$(ReporterChannel { name: $name, mz: $mz }),*]
+    };
+}
+
+const TMT_6PLEX: &[ReporterChannel] = channels!(
+    "126" => 126.127_726,
+    "127" => 127.124_761,
+    "128" => 128.134_436,
+    "129" => 129.131_471,
+    "130" => 130.141_145,
+    "131" => 131.138_180,
+);
+
+const TMT_10PLEX: &[ReporterChannel] = channels!(
+    "126" => 126.127_726,
+    "127N" => 127.124_761,
+    "127C" => 127.131_081,
+    "128N" => 128.128_116,
+    "128C" => 128.134_436,
+    "129N" => 129.131_471,
+    "129C" => 129.137_790,
+    "130N" => 130.134_825,
+    "130C" => 130.141_145,
+    "131" => 131.138_180,
+);
+
+const TMT_18PLEX: &[ReporterChannel] = channels!(
+    "126" => 126.127_726,
+    "127N" => 127.124_761,
+    "127C" => 127.131_081,
+    "128N" => 128.128_116,
+    "128C" => 128.134_436,
+    "129N" => 129.131_471,
+    "129C" => 129.137_790,
+    "130N" => 130.134_825,
+    "130C" => 130.141_145,
+    "131N" => 131.138_180,
+    "131C" => 131.144_500,
+    "132N" => 132.141_535,
+    "132C" => 132.147_855,
+    "133N" => 133.144_890,
+    "133C" => 133.151_210,
+    "134N" => 134.148_245,
+    "134C" => 134.154_565,
+    "135N" => 135.151_600,
+);
+
+const ITRAQ_4PLEX: &[ReporterChannel] = channels!(
+    "114" => 114.111_228,
+    "115" => 115.108_263,
+    "116" => 116.111_618,
+    "117" => 117.114_973,
+);
+
+const ITRAQ_8PLEX: &[ReporterChannel] = channels!(
+    "113" => 113.107_873,
+    "114" => 114.111_228,
+    "115" => 115.108_263,
+    "116" => 116.111_618,
+    "117" => 117.114_973,
+    "118" => 118.112_008,
+    "119" => 119.115_363,
+    "121" => 121.122_072,
+);
+
+/// Isobaric labeling reagent
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ReporterIonPlex {
+    Tmt6,
+    Tmt10,
+    /// TMTpro 16-plex
+    Tmt16,
+    /// TMTpro 18-plex
+    Tmt18,
+    Itraq4,
+    Itraq8,
+}
+
+impl ReporterIonPlex {
+    /// Reporter ion channels in ascending m/z order
+    ///
+    pub fn get_channels(&self) -> &'static [ReporterChannel] {
+        match self {
+            Self::Tmt6 => TMT_6PLEX,
+            Self::Tmt10 => TMT_10PLEX,
+            Self::Tmt16 => &TMT_18PLEX[..16],
+            Self::Tmt18 => TMT_18PLEX,
+            Self::Itraq4 => ITRAQ_4PLEX,
+            Self::Itraq8 => ITRAQ_8PLEX,
+        }
+    }
+}
+
+/// Reporter ion intensities of a spectrum, one entry per channel of the plex
+///
+/// ```
+/// use maccoys_exchange_entities::quant::reporter_ions::{ReporterIonPlex, ReporterIonTable};
+/// use maccoys_exchange_entities::tolerance::Tolerance;
+///
+/// let mz = [126.1278, 127.1248, 127.1311, 300.0];
+/// let intensity = [1000.0, 2000.0, 1500.0, 10.0];
+/// let table = ReporterIonTable::extract(&mz, &intensity, ReporterIonPlex::Tmt10, Tolerance::Da(0.003));
+/// assert_eq!(table.get_channels()[..3], ["126", "127N", "127C"]);
+/// assert_eq!(table.get_intensity()[..4], [Some(1000.0), Some(2000.0), Some(1500.0), None]);
+/// assert_eq!(table.to_dataframe().unwrap().height(), 10);
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ReporterIonTable {
+    plex: ReporterIonPlex,
+    channels: Vec<String>,
+    /// Observed m/z, None if the reporter ion was not detected
+    mz: Vec<Option<f64>>,
+    /// Observed intensity, None if the reporter ion was not detected
+    intensity: Vec<Option<f64>>,
+}
+
+impl ReporterIonTable {
+    /// Extracts the reporter ions of the plex from the peaks,
+    /// each channel is assigned the closest peak within the tolerance.
+    /// Requires m/z values sorted ascending.
+    ///
+    /// # Arguments
+    /// * `mz` - m/z values of the MS2 or MS3 spectrum
+    /// * `intensity` - Intensities of the MS2 or MS3 spectrum
+    /// * `plex` - Labeling reagent
+    /// * `tolerance` - Tolerance for matching reporter ions
+    ///
+    pub fn extract(
+        mz: &[f64],
+        intensity: &[f64],
+        plex: ReporterIonPlex,
+        tolerance: Tolerance,
+    ) -> Self {
+        let channels = plex.get_channels();
+        let mut table = Self {
+            plex,
+            channels: channels
+                .iter()
+                .map(|channel| channel.name.to_string())
+                .collect(),
+            mz: Vec::with_capacity(channels.len()),
+            intensity: Vec::with_capacity(channels.len()),
+        };
+        for channel in channels.iter() {
+            let tolerance = tolerance.to_da(channel.mz);
+            let lower = mz.partition_point(|peak| *peak < channel.mz - tolerance);
+            let closest = (lower..mz.len())
+                .take_while(|idx| mz[*idx] <= channel.mz + tolerance)
+                .min_by(|a, b| {
+                    (mz[*a] - channel.mz)
+                        .abs()
+                        .total_cmp(&(mz[*b] - channel.mz).abs())
+                });
+            table.mz.push(closest.map(|idx| mz[idx]));
+            table.intensity.push(closest.map(|idx| intensity[idx]));
+        }
+        table
+    }
+
+    pub fn get_plex(&self) -> ReporterIonPlex {
+        self.plex
+    }
+
+    /// Channel names, e.g. `127N`
+    ///
+    pub fn get_channels(&self) -> &Vec<String> {
+        &self.channels
+    }
+
+    pub fn get_mz(&self) -> &Vec<Option<f64>> {
+        &self.mz
+    }
+
+    pub fn get_intensity(&self) -> &Vec<Option<f64>> {
+        &self.intensity
+    }
+
+    /// Intensity of the given channel, None if the channel is not part of the plex or was not detected
+    ///
+    pub fn get_channel_intensity(&self, channel: &str) -> Option<f64> {
+        self.channels
+            .iter()
+            .position(|name| name == channel)
+            .and_then(|idx| self.intensity[idx])
+    }
+
+    /// Converts the table into a DataFrame with the columns `channel`, `mz` and `intensity`
+    ///
+    pub fn to_dataframe(&self) -> PolarsResult<DataFrame> {
+        df!(
+            "channel" => &self.channels,
+            "mz" => &self.mz,
+            "intensity" => &self.intensity
+        )
+    }
+}
+
+impl Spectrum {
+    /// Extracts the reporter ions of the plex from the peaks of this (MS2 or MS3) spectrum,
+    /// see `ReporterIonTable::extract`
+    ///
+    pub fn extract_reporter_ions(
+        &self,
+        plex: ReporterIonPlex,
+        tolerance: Tolerance,
+    ) -> ReporterIonTable {
+        ReporterIonTable::extract(self.get_mz(), self.get_intensity(), plex, tolerance)
+    }
+}
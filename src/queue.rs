@@ -0,0 +1,230 @@
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::results_api::SearchParameters;
+
+/// Current version of the message layout.
+/// Increment on breaking changes, consumers reject messages with a newer version.
+pub const MESSAGE_VERSION: u32 = 1;
+
+/// Error when encoding or decoding a pipeline message
+///
+#[derive(Debug, thiserror::Error)]
+pub enum MessageError {
+    #[error("message version {found} is newer than supported version {supported}")]
+    UnsupportedVersion { found: u32, supported: u32 },
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// A search was submitted and is waiting to be processed
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SearchSubmitted {
+    search_uuid: String,
+    ms_run_names: Vec<String>,
+    #[serde(default)]
+    parameters: Option<SearchParameters>,
+}
+
+impl SearchSubmitted {
+    pub fn new(
+        search_uuid: String,
+        ms_run_names: Vec<String>,
+        parameters: Option<SearchParameters>,
+    ) -> Self {
+        Self {
+            search_uuid,
+            ms_run_names,
+            parameters,
+        }
+    }
+
+    pub fn get_search_uuid(&self) -> &str {
+        &self.search_uuid
+    }
+
+    pub fn get_ms_run_names(&self) -> &Vec<String> {
+        &self.ms_run_names
+    }
+
+    pub fn get_parameters(&self) -> &Option<SearchParameters> {
+        &self.parameters
+    }
+}
+
+/// A spectrum was queued for identification
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SpectrumQueued {
+    search_uuid: String,
+    ms_run_name: String,
+    spectrum_id: String,
+}
+
+impl SpectrumQueued {
+    pub fn new(search_uuid: String, ms_run_name: String, spectrum_id: String) -> Self {
+        Self {
+            search_uuid,
+            ms_run_name,
+            spectrum_id,
+        }
+    }
+
+    pub fn get_search_uuid(&self) -> &str {
+        &self.search_uuid
+    }
+
+    pub fn get_ms_run(&self) -> &str {
+        &self.ms_run_name
+    }
+
+    pub fn get_spectra_id(&self) -> &str {
+        &self.spectrum_id
+    }
+}
+
+/// The identification of a spectrum finished, the results can be fetched from the result store
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct IdentificationFinished {
+    search_uuid: String,
+    ms_run_name: String,
+    spectrum_id: String,
+    num_psms: usize,
+}
+
+impl IdentificationFinished {
+    pub fn new(
+        search_uuid: String,
+        ms_run_name: String,
+        spectrum_id: String,
+        num_psms: usize,
+    ) -> Self {
+        Self {
+            search_uuid,
+            ms_run_name,
+            spectrum_id,
+            num_psms,
+        }
+    }
+
+    pub fn get_search_uuid(&self) -> &str {
+        &self.search_uuid
+    }
+
+    pub fn get_ms_run(&self) -> &str {
+        &self.ms_run_name
+    }
+
+    pub fn get_spectra_id(&self) -> &str {
+        &self.spectrum_id
+    }
+
+    /// Number of PSMs over all identifications of the spectrum
+    ///
+    pub fn get_num_psms(&self) -> usize {
+        self.num_psms
+    }
+}
+
+/// The search failed and will not be processed further
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SearchFailed {
+    search_uuid: String,
+    error: String,
+}
+
+impl SearchFailed {
+    pub fn new(search_uuid: String, error: String) -> Self {
+        Self { search_uuid, error }
+    }
+
+    pub fn get_search_uuid(&self) -> &str {
+        &self.search_uuid
+    }
+
+    pub fn get_error(&self) -> &str {
+        &self.error
+    }
+}
+
+/// Message exchanged between the API and the workers of the processing pipeline, tagged with `type`
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    SearchSubmitted(SearchSubmitted),
+    SpectrumQueued(SpectrumQueued),
+    IdentificationFinished(IdentificationFinished),
+    SearchFailed(SearchFailed),
+}
+
+impl Message {
+    /// UUID of the search the message belongs to, e.g. for routing
+    ///
+    pub fn get_search_uuid(&self) -> &str {
+        match self {
+            Self::SearchSubmitted(message) => message.get_search_uuid(),
+            Self::SpectrumQueued(message) => message.get_search_uuid(),
+            Self::IdentificationFinished(message) => message.get_search_uuid(),
+            Self::SearchFailed(message) => message.get_search_uuid(),
+        }
+    }
+
+    /// Serializes the message with the current version, e.g. `{"version":1,"type":"search_failed",...}`
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::queue::{Message, SearchFailed};
+    ///
+    /// let message = Message::SearchFailed(SearchFailed::new("search".to_string(), "out of memory".to_string()));
+    /// let json = message.to_json().unwrap();
+    /// assert!(json.contains(r#""type":"search_failed""#));
+    /// assert_eq!(Message::from_json(&json).unwrap(), message);
+    ///
+    /// let newer = json.replace(r#""version":1"#, r#""version":99"#);
+    /// assert!(Message::from_json(&newer).is_err());
+    /// ```
+    ///
+    pub fn to_json(&self) -> Result<String, MessageError> {
+        Ok(serde_json::to_string(&Envelope {
+            version: MESSAGE_VERSION,
+            message: self,
+        })?)
+    }
+
+    /// Deserializes the message, messages without version are treated as version 1.
+    /// Fails for messages with a newer version than `MESSAGE_VERSION`.
+    ///
+    pub fn from_json(json: &str) -> Result<Self, MessageError> {
+        let envelope: Envelope<Message> = serde_json::from_str(json)?;
+        if envelope.version > MESSAGE_VERSION {
+            return Err(MessageError::UnsupportedVersion {
+                found: envelope.version,
+                supported: MESSAGE_VERSION,
+            });
+        }
+        Ok(envelope.message)
+    }
+}
+
+/// Message with its version
+///
+#[derive(Serialize, Deserialize)]
+struct Envelope<M> {
+    #[serde(default = "first_version")]
+    version: u32,
+    #[serde(flatten)]
+    message: M,
+}
+
+fn first_version() -> u32 {
+    1
+}
@@ -0,0 +1,346 @@
+//! Response envelope of the results API, shared by the web service and its clients
+
+// std imports
+use std::fmt;
+
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// internal imports
+use crate::results_api::SpectraPage;
+use crate::storage::StoreError;
+
+/// Outcome of a request
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseStatus {
+    Ok,
+    Error,
+}
+
+/// Error returned by the API
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::ApiError;
+/// use maccoys_exchange_entities::storage::StoreError;
+///
+/// let error = ApiError::from(StoreError::SearchNotFound("4f6e8a2c".to_string()));
+/// assert_eq!(error.get_http_status(), 404);
+/// assert_eq!(error.get_code(), ApiError::NOT_FOUND);
+/// assert_eq!(error.to_string(), "not_found: search `4f6e8a2c` not found");
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ApiError {
+    http_status: u16,
+    code: String,
+    message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    details: Option<Value>,
+}
+
+impl ApiError {
+    /// Code of missing entities
+    pub const NOT_FOUND: &'static str = "not_found";
+    /// Code of malformed requests
+    pub const BAD_REQUEST: &'static str = "bad_request";
+    /// Code of unexpected server errors
+    pub const INTERNAL: &'static str = "internal";
+
+    /// # Arguments
+    /// * `http_status` - HTTP status code of the response
+    /// * `code` - Machine readable error code, e.g. `ApiError::NOT_FOUND`
+    /// * `message` - Human readable description
+    ///
+    pub fn new(http_status: u16, code: &str, message: String) -> Self {
+        Self {
+            http_status,
+            code: code.to_string(),
+            message,
+            details: None,
+        }
+    }
+
+    pub fn not_found(message: String) -> Self {
+        Self::new(404, Self::NOT_FOUND, message)
+    }
+
+    pub fn bad_request(message: String) -> Self {
+        Self::new(400, Self::BAD_REQUEST, message)
+    }
+
+    pub fn internal(message: String) -> Self {
+        Self::new(500, Self::INTERNAL, message)
+    }
+
+    /// Sets additional structured information, e.g. validation violations
+    ///
+    pub fn with_details(mut self, details: Option<Value>) -> Self {
+        self.details = details;
+        self
+    }
+
+    pub fn get_http_status(&self) -> u16 {
+        self.http_status
+    }
+
+    pub fn get_code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn get_details(&self) -> &Option<Value> {
+        &self.details
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<StoreError> for ApiError {
+    fn from(err: StoreError) -> Self {
+        match err {
+            StoreError::SearchNotFound(..)
+            | StoreError::MsRunNotFound(..)
+            | StoreError::SpectrumNotFound(..) => Self::not_found(err.to_string()),
+            StoreError::Backend(_) => Self::internal(err.to_string()),
+        }
+    }
+}
+
+/// Envelope of all API responses, containing either the data or an error
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::{ApiError, ApiResponse, ResponseStatus};
+///
+/// let response = ApiResponse::ok(vec![1, 2, 3]).with_message(Some("cached".to_string()));
+/// let json = serde_json::to_string(&response).unwrap();
+/// assert_eq!(json, r#"{"status":"ok","message":"cached","data":[1,2,3]}"#);
+/// let response: ApiResponse<Vec<u32>> = serde_json::from_str(&json).unwrap();
+/// assert_eq!(response.into_result().unwrap(), vec![1, 2, 3]);
+///
+/// let response: ApiResponse<Vec<u32>> = ApiResponse::error(ApiError::bad_request("limit must be positive".to_string()));
+/// assert_eq!(response.get_status(), ResponseStatus::Error);
+/// assert_eq!(response.into_result().unwrap_err().get_http_status(), 400);
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ApiResponse<T> {
+    status: ResponseStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<ApiError>,
+}
+
+impl<T> ApiResponse<T> {
+    /// Successful response with the given data
+    ///
+    pub fn ok(data: T) -> Self {
+        Self {
+            status: ResponseStatus::Ok,
+            message: None,
+            data: Some(data),
+            error: None,
+        }
+    }
+
+    /// Failed response, the message defaults to the error message
+    ///
+    pub fn error(error: ApiError) -> Self {
+        Self {
+            status: ResponseStatus::Error,
+            message: Some(error.get_message().to_string()),
+            data: None,
+            error: Some(error),
+        }
+    }
+
+    /// Sets a human readable message, e.g. a warning
+    ///
+    pub fn with_message(mut self, message: Option<String>) -> Self {
+        self.message = message;
+        self
+    }
+
+    pub fn get_status(&self) -> ResponseStatus {
+        self.status
+    }
+
+    pub fn get_message(&self) -> &Option<String> {
+        &self.message
+    }
+
+    pub fn get_data(&self) -> &Option<T> {
+        &self.data
+    }
+
+    pub fn get_error(&self) -> &Option<ApiError> {
+        &self.error
+    }
+
+    /// HTTP status code of the response
+    ///
+    pub fn get_http_status(&self) -> u16 {
+        match &self.error {
+            Some(error) => error.get_http_status(),
+            None => 200,
+        }
+    }
+
+    /// Returns the data or the error. Responses without either are internal errors.
+    ///
+    pub fn into_result(self) -> Result<T, ApiError> {
+        match (self.status, self.data, self.error) {
+            (ResponseStatus::Ok, Some(data), _) => Ok(data),
+            (_, _, Some(error)) => Err(error),
+            (status, _, _) => Err(ApiError::internal(format!(
+                "response with status `{:?}` contains neither data nor error",
+                status
+            ))),
+        }
+    }
+}
+
+impl<T> From<Result<T, ApiError>> for ApiResponse<T> {
+    fn from(result: Result<T, ApiError>) -> Self {
+        match result {
+            Ok(data) => Self::ok(data),
+            Err(error) => Self::error(error),
+        }
+    }
+}
+
+/// Page of a list with pagination metadata.
+/// Offsets address pages by position, cursors by the last item of the previous page.
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::{MsRun, Paginated};
+///
+/// let ms_run = MsRun::new(
+///     "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+///     "run".parse().unwrap(),
+///     vec!["scan=1".parse().unwrap(), "scan=2".parse().unwrap(), "scan=3".parse().unwrap()],
+/// );
+/// let page = Paginated::from(ms_run.page(0, 2));
+/// assert_eq!(page.get_items(), &vec!["scan=1".to_string(), "scan=2".to_string()]);
+/// assert_eq!(page.get_next_cursor().as_deref(), Some("scan=2"));
+/// assert!(!page.is_last_page());
+///
+/// let lengths = page.map(|spectrum_id| spectrum_id.len());
+/// assert_eq!(lengths.get_items(), &vec![6, 6]);
+/// assert_eq!(lengths.get_total(), 3);
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Paginated<T> {
+    items: Vec<T>,
+    offset: usize,
+    total: usize,
+    #[serde(default)]
+    next_cursor: Option<String>,
+    #[serde(default)]
+    previous_cursor: Option<String>,
+}
+
+impl<T> Paginated<T> {
+    /// # Arguments
+    /// * `items` - Items of the page
+    /// * `offset` - Position of the first item within the whole list
+    /// * `total` - Number of items of the whole list
+    ///
+    pub fn new(items: Vec<T>, offset: usize, total: usize) -> Self {
+        Self {
+            items,
+            offset,
+            total,
+            next_cursor: None,
+            previous_cursor: None,
+        }
+    }
+
+    /// Sets the cursor of the following page, None on the last page
+    ///
+    pub fn with_next_cursor(mut self, next_cursor: Option<String>) -> Self {
+        self.next_cursor = next_cursor;
+        self
+    }
+
+    /// Sets the cursor of the preceding page, None on the first page
+    ///
+    pub fn with_previous_cursor(mut self, previous_cursor: Option<String>) -> Self {
+        self.previous_cursor = previous_cursor;
+        self
+    }
+
+    pub fn get_items(&self) -> &Vec<T> {
+        &self.items
+    }
+
+    pub fn into_items(self) -> Vec<T> {
+        self.items
+    }
+
+    /// Position of the first item within the whole list
+    ///
+    pub fn get_offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Number of items of the whole list
+    ///
+    pub fn get_total(&self) -> usize {
+        self.total
+    }
+
+    pub fn get_next_cursor(&self) -> &Option<String> {
+        &self.next_cursor
+    }
+
+    pub fn get_previous_cursor(&self) -> &Option<String> {
+        &self.previous_cursor
+    }
+
+    /// True if no items follow this page
+    ///
+    pub fn is_last_page(&self) -> bool {
+        self.offset + self.items.len() >= self.total
+    }
+
+    /// Converts the items, keeping the pagination metadata
+    ///
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Paginated<U> {
+        Paginated {
+            items: self.items.into_iter().map(f).collect(),
+            offset: self.offset,
+            total: self.total,
+            next_cursor: self.next_cursor,
+            previous_cursor: self.previous_cursor,
+        }
+    }
+}
+
+impl From<SpectraPage> for Paginated<String> {
+    fn from(page: SpectraPage) -> Self {
+        let offset = page.get_offset();
+        let total = page.get_total();
+        let next_cursor = page.get_next_cursor().clone();
+        Self::new(page.get_spectra_ids().clone(), offset, total).with_next_cursor(next_cursor)
+    }
+}
@@ -0,0 +1,169 @@
+//! Approximate equality of spectra and identifications, e.g. for comparing results after a
+//! serialization roundtrip or a rerun, where floats may differ in the last digits.
+//!
+//! Floats (peaks, precursor, float columns of the PSMs and goodness of fits) are compared with an absolute tolerance,
+//! everything else exactly. NaNs equal NaNs and nulls equal nulls. The payload digest is not compared.
+
+// 3rd party imports
+use polars::prelude::*;
+
+// internal imports
+use crate::quant::reporter_ions::ReporterIonTable;
+use crate::results_api::{Identification, Precursor, Spectrum};
+
+impl Spectrum {
+    /// True if the spectra are equal, comparing floats with the given absolute tolerance
+    ///
+    /// # Arguments
+    /// * `other` - Spectrum to compare with
+    /// * `epsilon` - Absolute tolerance for floats
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::{Identification, Precursor, Spectrum};
+    /// use polars::prelude::*;
+    ///
+    /// let spectrum = |mz: f64, xcorr: f64| {
+    ///     Spectrum::new(
+    ///         "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+    ///         "run".parse().unwrap(),
+    ///         "scan=1".parse().unwrap(),
+    ///         vec![mz, 200.0],
+    ///         vec![1.0, 2.0],
+    ///         vec![Identification::new(
+    ///             None,
+    ///             Some(df!("plain_peptide" => &["PEPTIDE"], "xcorr" => &[xcorr]).unwrap()),
+    ///             Precursor::new(400.7, 2),
+    ///         )],
+    ///     )
+    /// };
+    ///
+    /// assert!(spectrum(100.0, 2.5).approx_eq(&spectrum(100.0 + 1e-9, 2.5 - 1e-9), 1e-6));
+    /// assert!(!spectrum(100.0, 2.5).approx_eq(&spectrum(100.1, 2.5), 1e-6));
+    /// assert!(!spectrum(100.0, 2.5).approx_eq(&spectrum(100.0, 2.6), 1e-6));
+    /// ```
+    ///
+    pub fn approx_eq(&self, other: &Spectrum, epsilon: f64) -> bool {
+        self.get_search_uuid() == other.get_search_uuid()
+            && self.get_ms_run() == other.get_ms_run()
+            && self.get_spectra_id() == other.get_spectra_id()
+            && self.get_ms_level() == other.get_ms_level()
+            && self.get_scan_number() == other.get_scan_number()
+            && option_approx_eq(
+                *self.get_retention_time(),
+                *other.get_retention_time(),
+                epsilon,
+            )
+            && option_approx_eq(*self.get_ion_mobility(), *other.get_ion_mobility(), epsilon)
+            && slice_approx_eq(self.get_mz(), other.get_mz(), epsilon)
+            && slice_approx_eq(self.get_intensity(), other.get_intensity(), epsilon)
+            && self.get_identifications().len() == other.get_identifications().len()
+            && self
+                .get_identifications()
+                .iter()
+                .zip(other.get_identifications())
+                .all(|(identification, other)| identification.approx_eq(other, epsilon))
+    }
+}
+
+impl Identification {
+    /// True if the identifications are equal, comparing floats with the given absolute tolerance.
+    /// PSMs and goodness of fits need the same columns in the same order.
+    ///
+    /// # Arguments
+    /// * `other` - Identification to compare with
+    /// * `epsilon` - Absolute tolerance for floats
+    ///
+    pub fn approx_eq(&self, other: &Identification, epsilon: f64) -> bool {
+        precursor_approx_eq(self.get_precursor(), other.get_precursor(), epsilon)
+            && option_frame_approx_eq(self.get_psms(), other.get_psms(), epsilon)
+            && option_frame_approx_eq(self.get_goodnesses(), other.get_goodnesses(), epsilon)
+            && self.get_score_descriptors() == other.get_score_descriptors()
+            && match (self.get_reporter_ions(), other.get_reporter_ions()) {
+                (Some(reporter_ions), Some(other)) => {
+                    reporter_ions_approx_eq(reporter_ions, other, epsilon)
+                }
+                (None, None) => true,
+                _ => false,
+            }
+    }
+}
+
+fn f64_approx_eq(a: f64, b: f64, epsilon: f64) -> bool {
+    (a.is_nan() && b.is_nan()) || a == b || (a - b).abs() <= epsilon
+}
+
+fn option_approx_eq(a: Option<f64>, b: Option<f64>, epsilon: f64) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => f64_approx_eq(a, b, epsilon),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+fn slice_approx_eq(a: &[f64], b: &[f64], epsilon: f64) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| f64_approx_eq(*a, *b, epsilon))
+}
+
+fn precursor_approx_eq(a: &Precursor, b: &Precursor, epsilon: f64) -> bool {
+    f64_approx_eq(a.get_mz(), b.get_mz(), epsilon)
+        && a.get_charge() == b.get_charge()
+        && option_approx_eq(*a.get_intensity(), *b.get_intensity(), epsilon)
+        && option_approx_eq(
+            *a.get_isolation_window_lower_offset(),
+            *b.get_isolation_window_lower_offset(),
+            epsilon,
+        )
+        && option_approx_eq(
+            *a.get_isolation_window_upper_offset(),
+            *b.get_isolation_window_upper_offset(),
+            epsilon,
+        )
+        && a.get_monoisotopic_correction() == b.get_monoisotopic_correction()
+}
+
+fn reporter_ions_approx_eq(a: &ReporterIonTable, b: &ReporterIonTable, epsilon: f64) -> bool {
+    let values_approx_eq = |a: &Vec<Option<f64>>, b: &Vec<Option<f64>>| {
+        a.len() == b.len()
+            && a.iter()
+                .zip(b)
+                .all(|(a, b)| option_approx_eq(*a, *b, epsilon))
+    };
+    a.get_plex() == b.get_plex()
+        && a.get_channels() == b.get_channels()
+        && values_approx_eq(a.get_mz(), b.get_mz())
+        && values_approx_eq(a.get_intensity(), b.get_intensity())
+}
+
+fn option_frame_approx_eq(a: &Option<DataFrame>, b: &Option<DataFrame>, epsilon: f64) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => frame_approx_eq(a, b, epsilon),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// Compares float columns with the tolerance, all other columns exactly
+///
+fn frame_approx_eq(a: &DataFrame, b: &DataFrame, epsilon: f64) -> bool {
+    a.shape() == b.shape()
+        && a.get_columns().iter().zip(b.get_columns()).all(|(a, b)| {
+            if a.name() != b.name() || a.dtype() != b.dtype() {
+                return false;
+            }
+            match a.dtype() {
+                DataType::Float32 | DataType::Float64 => {
+                    match (a.cast(&DataType::Float64), b.cast(&DataType::Float64)) {
+                        (Ok(a), Ok(b)) => match (a.f64(), b.f64()) {
+                            (Ok(a), Ok(b)) => a
+                                .into_iter()
+                                .zip(b)
+                                .all(|(a, b)| option_approx_eq(a, b, epsilon)),
+                            _ => false,
+                        },
+                        _ => false,
+                    }
+                }
+                _ => a.series_equal_missing(b),
+            }
+        })
+}
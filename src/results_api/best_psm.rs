@@ -0,0 +1,343 @@
+// 3rd party imports
+use anyhow::Result;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::results_api::{psm_columns, Spectrum};
+use crate::statistics::fdr::{decoy_flags, DEFAULT_DECOY_PREFIX};
+
+/// Score column and direction used to select the best PSM
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScoreSelector {
+    column: String,
+    higher_is_better: bool,
+    include_decoys: bool,
+    decoy_prefix: String,
+}
+
+impl ScoreSelector {
+    /// Creates a new selector which ignores decoys, recognized by the default decoy prefix
+    /// unless the PSMs have an `is_decoy` column
+    ///
+    /// # Arguments
+    /// * `column` - Score column
+    /// * `higher_is_better` - True if higher scores are better, e.g. for xcorr
+    ///
+    pub fn new(column: &str, higher_is_better: bool) -> Self {
+        Self {
+            column: column.to_string(),
+            higher_is_better,
+            include_decoys: false,
+            decoy_prefix: DEFAULT_DECOY_PREFIX.to_string(),
+        }
+    }
+
+    /// Selects by xcorr (higher is better)
+    ///
+    pub fn xcorr() -> Self {
+        Self::new(psm_columns::XCORR, true)
+    }
+
+    /// Selects by e-value (lower is better)
+    ///
+    pub fn e_value() -> Self {
+        Self::new(psm_columns::E_VALUE, false)
+    }
+
+    /// Selects by q-value (lower is better), see `Identification::append_fdr`
+    ///
+    pub fn q_value() -> Self {
+        Self::new(psm_columns::Q_VALUE, false)
+    }
+
+    /// Whether decoy PSMs may be selected, e.g. for target-decoy competition
+    ///
+    pub fn with_decoys(mut self, include_decoys: bool) -> Self {
+        self.include_decoys = include_decoys;
+        self
+    }
+
+    /// Prefix of decoy protein accessions, used if the PSMs have no `is_decoy` column
+    ///
+    pub fn with_decoy_prefix(mut self, decoy_prefix: &str) -> Self {
+        self.decoy_prefix = decoy_prefix.to_string();
+        self
+    }
+
+    pub fn get_column(&self) -> &str {
+        &self.column
+    }
+
+    pub fn is_higher_better(&self) -> bool {
+        self.higher_is_better
+    }
+
+    pub fn includes_decoys(&self) -> bool {
+        self.include_decoys
+    }
+
+    pub fn get_decoy_prefix(&self) -> &str {
+        &self.decoy_prefix
+    }
+
+    /// True if score `a` is better than score `b`
+    ///
+    fn is_better(&self, a: f64, b: f64) -> bool {
+        match self.higher_is_better {
+            true => a > b,
+            false => a < b,
+        }
+    }
+}
+
+/// Best PSM of a spectrum across all charge states
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BestPsm {
+    identification_index: usize,
+    row_index: usize,
+    charge: u8,
+    score: f64,
+    rank: Option<u32>,
+    plain_peptide: Option<String>,
+    modified_peptide: Option<String>,
+    proteins: Option<String>,
+    is_decoy: bool,
+    num_tied: usize,
+}
+
+impl BestPsm {
+    /// Index of the identification (charge state) within the spectrum
+    ///
+    pub fn get_identification_index(&self) -> usize {
+        self.identification_index
+    }
+
+    /// Index of the PSM within the PSM table of the identification
+    ///
+    pub fn get_row_index(&self) -> usize {
+        self.row_index
+    }
+
+    pub fn get_charge(&self) -> u8 {
+        self.charge
+    }
+
+    pub fn get_score(&self) -> f64 {
+        self.score
+    }
+
+    /// Rank assigned by the search engine, if available
+    ///
+    pub fn get_rank(&self) -> Option<u32> {
+        self.rank
+    }
+
+    pub fn get_plain_peptide(&self) -> Option<&str> {
+        self.plain_peptide.as_deref()
+    }
+
+    pub fn get_modified_peptide(&self) -> Option<&str> {
+        self.modified_peptide.as_deref()
+    }
+
+    /// Comma separated protein accessions
+    ///
+    pub fn get_proteins(&self) -> Option<&str> {
+        self.proteins.as_deref()
+    }
+
+    pub fn is_decoy(&self) -> bool {
+        self.is_decoy
+    }
+
+    /// Number of other PSMs with the same score, which lost the tie-break
+    ///
+    pub fn get_num_tied(&self) -> usize {
+        self.num_tied
+    }
+
+    /// True if other PSMs have the same score
+    ///
+    pub fn is_tied(&self) -> bool {
+        self.num_tied > 0
+    }
+}
+
+/// Candidate for the best PSM, ordered by score, then target before decoy, then rank,
+/// then charge (lower first) and finally by position
+///
+struct Candidate {
+    identification_index: usize,
+    row_index: usize,
+    score: f64,
+    is_decoy: bool,
+    rank: u32,
+}
+
+impl Spectrum {
+    /// Selects the best PSM across the identifications of all charge states.
+    ///
+    /// PSMs without score are ignored and decoys are ignored unless requested by the selector.
+    /// Decoys are identified by the `is_decoy` column if present (see `Identification::mark_decoys`),
+    /// otherwise by the decoy prefix of the selector (see `ExchangeConfig::to_score_selector`).
+    /// Ties are broken in favour of targets, then by the engine rank, then by the lower charge and finally
+    /// by the order of identifications and PSMs, so the selection is deterministic. The number of tied PSMs is reported.
+    ///
+    /// Returns None if there is no eligible PSM and an error if the score column is missing.
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::{
+    ///     ExchangeConfig, Identification, Precursor, ScoreSelector, Spectrum,
+    /// };
+    /// use polars::prelude::*;
+    ///
+    /// let charge_2 = df!(
+    ///     "plain_peptide" => &["PEPTIDE", "PEPTIDES"],
+    ///     "protein" => &["P1", "DECOY_P2"],
+    ///     "xcorr" => &[2.5, 3.1]
+    /// ).unwrap();
+    /// let charge_3 = df!(
+    ///     "plain_peptide" => &["PEPTIDEK"],
+    ///     "protein" => &["P3"],
+    ///     "xcorr" => &[2.5]
+    /// ).unwrap();
+    /// let spectrum = Spectrum::new(
+    ///     "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+    ///     "run".parse().unwrap(),
+    ///     "scan=1".parse().unwrap(),
+    ///     vec![175.119],
+    ///     vec![1000.0],
+    ///     vec![
+    ///         Identification::new(None, Some(charge_2), Precursor::new(400.7, 2)),
+    ///         Identification::new(None, Some(charge_3), Precursor::new(267.5, 3)),
+    ///     ],
+    /// );
+    ///
+    /// let best = spectrum.best_identification(ScoreSelector::xcorr()).unwrap().unwrap();
+    /// assert_eq!(best.get_plain_peptide(), Some("PEPTIDE"));
+    /// assert_eq!(best.get_charge(), 2);
+    /// assert_eq!(best.get_num_tied(), 1);
+    ///
+    /// let best = spectrum
+    ///     .best_identification(ScoreSelector::xcorr().with_decoys(true))
+    ///     .unwrap()
+    ///     .unwrap();
+    /// assert!(best.is_decoy());
+    ///
+    /// // `DECOY_P2` is a target for a search with another decoy prefix
+    /// let selector = ExchangeConfig::default().with_decoy_prefix("REV_").to_score_selector();
+    /// let best = spectrum.best_identification(selector).unwrap().unwrap();
+    /// assert_eq!(best.get_plain_peptide(), Some("PEPTIDES"));
+    /// assert!(!best.is_decoy());
+    /// ```
+    ///
+    pub fn best_identification(&self, by: ScoreSelector) -> Result<Option<BestPsm>> {
+        let mut best: Option<Candidate> = None;
+        let mut num_tied = 0;
+        for (identification_index, identification) in self.get_identifications().iter().enumerate()
+        {
+            let psms = match identification.get_psms() {
+                Some(psms) => psms,
+                None => continue,
+            };
+            let scores = psms.column(by.get_column())?.cast(&DataType::Float64)?;
+            let is_decoy = decoy_column(psms, by.get_decoy_prefix())?;
+            let ranks = match psms.column(psm_columns::RANK) {
+                Ok(ranks) => Some(ranks.cast(&DataType::UInt32)?),
+                Err(_) => None,
+            };
+            for (row_index, score) in scores.f64()?.into_iter().enumerate() {
+                let score = match score {
+                    Some(score) if !score.is_nan() => score,
+                    _ => continue,
+                };
+                let is_decoy = is_decoy.get(row_index).unwrap_or(false);
+                if is_decoy && !by.includes_decoys() {
+                    continue;
+                }
+                let candidate = Candidate {
+                    identification_index,
+                    row_index,
+                    score,
+                    is_decoy,
+                    rank: ranks
+                        .as_ref()
+                        .and_then(|ranks| ranks.u32().ok()?.get(row_index))
+                        .unwrap_or(u32::MAX),
+                };
+                match best.as_ref() {
+                    None => best = Some(candidate),
+                    Some(current) if by.is_better(candidate.score, current.score) => {
+                        best = Some(candidate);
+                        num_tied = 0;
+                    }
+                    Some(current) if candidate.score == current.score => {
+                        num_tied += 1;
+                        if self.wins_tie(&candidate, current) {
+                            best = Some(candidate);
+                        }
+                    }
+                    Some(_) => (),
+                }
+            }
+        }
+        let best = match best {
+            Some(best) => best,
+            None => return Ok(None),
+        };
+        let identification = &self.get_identifications()[best.identification_index];
+        let psms = identification.get_psms().as_ref().unwrap();
+        Ok(Some(BestPsm {
+            identification_index: best.identification_index,
+            row_index: best.row_index,
+            charge: identification.get_charge(),
+            score: best.score,
+            rank: (best.rank != u32::MAX).then_some(best.rank),
+            plain_peptide: optional_str(psms, psm_columns::PLAIN_PEPTIDE, best.row_index)?,
+            modified_peptide: optional_str(psms, psm_columns::MODIFIED_PEPTIDE, best.row_index)?,
+            proteins: optional_str(psms, psm_columns::PROTEIN, best.row_index)?,
+            is_decoy: best.is_decoy,
+            num_tied,
+        }))
+    }
+
+    /// True if the candidate wins the tie against the current best candidate with the same score
+    ///
+    fn wins_tie(&self, candidate: &Candidate, current: &Candidate) -> bool {
+        let charge = |candidate: &Candidate| {
+            self.get_identifications()[candidate.identification_index].get_charge()
+        };
+        (candidate.is_decoy, candidate.rank, charge(candidate))
+            < (current.is_decoy, current.rank, charge(current))
+    }
+}
+
+/// Decoy flags of the PSMs, from the `is_decoy` column if present, otherwise from the protein accessions
+/// with the given decoy prefix. All PSMs are considered targets if neither column exists.
+///
+pub(crate) fn decoy_column(psms: &DataFrame, decoy_prefix: &str) -> Result<BooleanChunked> {
+    if let Ok(is_decoy) = psms.column(psm_columns::IS_DECOY) {
+        return Ok(is_decoy.bool()?.clone());
+    }
+    if psms.column(psm_columns::PROTEIN).is_ok() {
+        return decoy_flags(psms, decoy_prefix);
+    }
+    Ok(BooleanChunked::full(
+        psm_columns::IS_DECOY,
+        false,
+        psms.height(),
+    ))
+}
+
+/// Value of the string column in the given row, None if the column is missing or the value is null
+///
+fn optional_str(psms: &DataFrame, column: &str, row_index: usize) -> Result<Option<String>> {
+    match psms.column(column) {
+        Ok(values) => Ok(values.utf8()?.get(row_index).map(|value| value.to_string())),
+        Err(_) => Ok(None),
+    }
+}
@@ -0,0 +1,139 @@
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::results_api::{Precursor, Spectrum};
+use crate::tolerance::Tolerance;
+
+/// Extracted ion chromatogram (XIC), the intensity of an m/z range over the retention time
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::{Chromatogram, Spectrum};
+/// use maccoys_exchange_entities::tolerance::Tolerance;
+///
+/// let ms1 = |id: &str, retention_time: f64, intensity: f64| {
+///     Spectrum::new(
+///         "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+///         "run".parse().unwrap(),
+///         id.parse().unwrap(),
+///         vec![500.0, 650.001, 700.0],
+///         vec![10.0, intensity, 10.0],
+///         Vec::new(),
+///     )
+///     .with_retention_time(Some(retention_time))
+///     .with_ms_level(Some(1))
+/// };
+/// let spectra = vec![ms1("scan=3", 62.0, 50.0), ms1("scan=1", 60.0, 100.0), ms1("scan=2", 61.0, 300.0)];
+/// let xic = Chromatogram::extract(&spectra, 650.0, Tolerance::Ppm(10.0));
+/// assert_eq!(xic.get_data_points(), &vec![(60.0, 100.0), (61.0, 300.0), (62.0, 50.0)]);
+/// assert_eq!(xic.get_apex(), Some((61.0, 300.0)));
+/// assert_eq!(xic.get_area(), 375.0);
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Chromatogram {
+    target_mz: f64,
+    tolerance: Tolerance,
+    data_points: Vec<(f64, f64)>,
+}
+
+impl Chromatogram {
+    /// Creates a chromatogram from already extracted data points
+    ///
+    /// # Arguments
+    /// * `target_mz` - Extracted m/z
+    /// * `tolerance` - Tolerance around the target m/z
+    /// * `data_points` - Pairs of retention time and intensity, sorted by retention time
+    ///
+    pub fn new(target_mz: f64, tolerance: Tolerance, data_points: Vec<(f64, f64)>) -> Self {
+        Self {
+            target_mz,
+            tolerance,
+            data_points,
+        }
+    }
+
+    /// Extracts the chromatogram of the target m/z from the given MS1 spectra.
+    /// The intensity of a spectrum is the sum of all peaks within the tolerance, 0 if there are none.
+    /// Spectra without retention time or with an MS level other than 1 are skipped.
+    /// Requires m/z values sorted ascending.
+    ///
+    /// # Arguments
+    /// * `spectra` - MS1 spectra of an MS run, in any order
+    /// * `target_mz` - m/z to extract
+    /// * `tolerance` - Tolerance around the target m/z
+    ///
+    pub fn extract<'a>(
+        spectra: impl IntoIterator<Item = &'a Spectrum>,
+        target_mz: f64,
+        tolerance: Tolerance,
+    ) -> Self {
+        let mut data_points: Vec<(f64, f64)> = spectra
+            .into_iter()
+            .filter(|spectrum| spectrum.get_ms_level().unwrap_or(1) == 1)
+            .filter_map(|spectrum| {
+                let retention_time = (*spectrum.get_retention_time())?;
+                let mz = spectrum.get_mz();
+                let intensity = spectrum.get_intensity();
+                let summed_intensity = tolerance
+                    .peak_range(mz, target_mz)
+                    .map(|idx| intensity[idx])
+                    .sum::<f64>();
+                Some((retention_time, summed_intensity))
+            })
+            .collect();
+        data_points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self::new(target_mz, tolerance, data_points)
+    }
+
+    /// Extracts the chromatogram of the monoisotopic m/z of the precursor, see `Chromatogram::extract`
+    ///
+    pub fn extract_precursor<'a>(
+        spectra: impl IntoIterator<Item = &'a Spectrum>,
+        precursor: &Precursor,
+        tolerance: Tolerance,
+    ) -> Self {
+        Self::extract(spectra, precursor.get_monoisotopic_mz(), tolerance)
+    }
+
+    pub fn get_target_mz(&self) -> f64 {
+        self.target_mz
+    }
+
+    pub fn get_tolerance(&self) -> &Tolerance {
+        &self.tolerance
+    }
+
+    /// Pairs of retention time and intensity, sorted by retention time
+    ///
+    pub fn get_data_points(&self) -> &Vec<(f64, f64)> {
+        &self.data_points
+    }
+
+    pub fn len(&self) -> usize {
+        self.data_points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data_points.is_empty()
+    }
+
+    /// Retention time and intensity of the most intense data point
+    ///
+    pub fn get_apex(&self) -> Option<(f64, f64)> {
+        self.data_points
+            .iter()
+            .copied()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Area under the chromatogram (trapezoidal rule), in intensity times retention time unit
+    ///
+    pub fn get_area(&self) -> f64 {
+        self.data_points
+            .windows(2)
+            .map(|window| (window[1].0 - window[0].0) * (window[0].1 + window[1].1) / 2.0)
+            .sum()
+    }
+}
@@ -0,0 +1,203 @@
+// std imports
+use std::io::Write;
+
+// 3rd party imports
+use anyhow::Result;
+use polars::prelude::*;
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+
+// internal imports
+use crate::results_api::{spectrum::RowIter, Precursor, SequenceTag, Spectrum};
+
+/// Default number of peaks or rows per chunk
+pub const DEFAULT_CHUNK_SIZE: usize = 1024;
+
+/// Line of the chunked format, tagged with `type`
+///
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Chunk<'a> {
+    Spectrum {
+        search_uuid: &'a str,
+        ms_run_name: &'a str,
+        spectrum_id: &'a str,
+        retention_time: Option<f64>,
+        ion_mobility: Option<f64>,
+        ms_level: Option<u8>,
+        scan_number: Option<u32>,
+        num_peaks: usize,
+        num_identifications: usize,
+        num_sequence_tags: usize,
+        payload_digest: Option<&'a str>,
+        revision: u64,
+    },
+    Peaks {
+        mz: &'a [f64],
+        intensity: &'a [f64],
+    },
+    Identification {
+        index: usize,
+        precursor: &'a Precursor,
+    },
+    Psms {
+        identification: usize,
+        rows: Vec<Map<String, Value>>,
+    },
+    Goodnesses {
+        identification: usize,
+        rows: Vec<Map<String, Value>>,
+    },
+    SequenceTag {
+        index: usize,
+        #[serde(flatten)]
+        sequence_tag: &'a SequenceTag,
+    },
+}
+
+impl Spectrum {
+    /// Writes the spectrum as newline delimited JSON, so it can be streamed without serializing everything at once.
+    /// Each line is an object with a `type`:
+    /// 1. `spectrum` - metadata of the spectrum, including retention time, ion mobility, MS level, scan number,
+    ///    payload digest and revision
+    /// 2. `peaks` - up to `chunk_size` m/z and intensity values, repeated until all peaks are written
+    /// 3. `identification` - precursor of an identification, followed by its
+    ///    `psms` and `goodnesses` chunks of up to `chunk_size` rows, each row as object of column name to value
+    /// 4. `sequence_tag` - one line per de novo sequence tag
+    ///
+    /// # Arguments
+    /// * `writer` - Writer to write the spectrum to
+    /// * `chunk_size` - Maximum number of peaks or rows per line, 0 is treated as 1
+    ///
+    pub fn write_chunked<W: Write>(&self, writer: &mut W, chunk_size: usize) -> Result<()> {
+        let chunk_size = chunk_size.max(1);
+
+        write_line(
+            writer,
+            &Chunk::Spectrum {
+                search_uuid: self.get_search_uuid(),
+                ms_run_name: self.get_ms_run(),
+                spectrum_id: self.get_spectra_id(),
+                retention_time: *self.get_retention_time(),
+                ion_mobility: *self.get_ion_mobility(),
+                ms_level: *self.get_ms_level(),
+                scan_number: *self.get_scan_number(),
+                num_peaks: self.get_mz().len(),
+                num_identifications: self.get_identifications().len(),
+                num_sequence_tags: self.get_sequence_tags().len(),
+                payload_digest: self.get_payload_digest().as_deref(),
+                revision: self.get_revision(),
+            },
+        )?;
+
+        for (mz, intensity) in self
+            .get_mz()
+            .chunks(chunk_size)
+            .zip(self.get_intensity().chunks(chunk_size))
+        {
+            write_line(writer, &Chunk::Peaks { mz, intensity })?;
+        }
+
+        for (index, identification) in self.get_identifications().iter().enumerate() {
+            write_line(
+                writer,
+                &Chunk::Identification {
+                    index,
+                    precursor: identification.get_precursor(),
+                },
+            )?;
+            if let Some(rows) = identification.iter_psm_rows() {
+                write_rows(writer, rows, chunk_size, |rows| Chunk::Psms {
+                    identification: index,
+                    rows,
+                })?;
+            }
+            if let Some(rows) = identification.iter_goodness_rows() {
+                write_rows(writer, rows, chunk_size, |rows| Chunk::Goodnesses {
+                    identification: index,
+                    rows,
+                })?;
+            }
+        }
+        for (index, sequence_tag) in self.get_sequence_tags().iter().enumerate() {
+            write_line(
+                writer,
+                &Chunk::SequenceTag {
+                    index,
+                    sequence_tag,
+                },
+            )?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+fn write_line<W: Write>(writer: &mut W, chunk: &Chunk<'_>) -> Result<()> {
+    serde_json::to_writer(&mut *writer, chunk)?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+/// Writes the rows in chunks of `chunk_size`, only one chunk is kept in memory
+///
+fn write_rows<'a, W, F>(
+    writer: &mut W,
+    rows: RowIter<'_>,
+    chunk_size: usize,
+    to_chunk: F,
+) -> Result<()>
+where
+    W: Write,
+    F: Fn(Vec<Map<String, Value>>) -> Chunk<'a>,
+{
+    let mut buffer = Vec::with_capacity(chunk_size);
+    for row in rows {
+        buffer.push(
+            row.iter_named()
+                .map(|(col_name, value)| (col_name.to_string(), any_value_to_json(value)))
+                .collect(),
+        );
+        if buffer.len() == chunk_size {
+            write_line(writer, &to_chunk(std::mem::take(&mut buffer)))?;
+        }
+    }
+    if !buffer.is_empty() {
+        write_line(writer, &to_chunk(buffer))?;
+    }
+    Ok(())
+}
+
+/// Converts a polars value to JSON, unsupported types are written as their string representation
+///
+pub(crate) fn any_value_to_json(value: &AnyValue<'_>) -> Value {
+    match value {
+        AnyValue::Null => Value::Null,
+        AnyValue::Boolean(value) => Value::Bool(*value),
+        AnyValue::Utf8(value) => Value::String(value.to_string()),
+        AnyValue::Utf8Owned(value) => Value::String(value.to_string()),
+        AnyValue::UInt8(value) => Value::from(*value),
+        AnyValue::UInt16(value) => Value::from(*value),
+        AnyValue::UInt32(value) => Value::from(*value),
+        AnyValue::UInt64(value) => Value::from(*value),
+        AnyValue::Int8(value) => Value::from(*value),
+        AnyValue::Int16(value) => Value::from(*value),
+        AnyValue::Int32(value) => Value::from(*value),
+        AnyValue::Int64(value) => Value::from(*value),
+        AnyValue::Float32(value) => float_to_json(*value as f64),
+        AnyValue::Float64(value) => float_to_json(*value),
+        AnyValue::List(series) => {
+            Value::Array(series.iter().map(|v| any_value_to_json(&v)).collect())
+        }
+        other => Value::String(other.to_string()),
+    }
+}
+
+/// JSON has no representation for NaN and infinity, which are converted to null
+///
+fn float_to_json(value: f64) -> Value {
+    match Number::from_f64(value) {
+        Some(number) => Value::Number(number),
+        None => Value::Null,
+    }
+}
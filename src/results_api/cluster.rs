@@ -0,0 +1,251 @@
+//! Greedy cosine clustering of spectra, e.g. to reduce redundancy before searching or to spot
+//! repeatedly acquired precursors.
+//!
+//! Spectra are processed from highest to lowest total intensity. Each spectrum joins the cluster
+//! with the most similar representative (the cluster's first and most intense spectrum) if the cosine
+//! similarity reaches the threshold and precursor and charge match, otherwise it founds a new cluster.
+//! Finally the peaks of the members are merged into consensus peaks per cluster (see `Spectrum::consensus`).
+
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::results_api::exchange_config::{
+    DEFAULT_FRAGMENT_TOLERANCE, DEFAULT_PRECURSOR_TOLERANCE,
+};
+use crate::results_api::{ConsensusPeaks, ExchangeConfig, Spectrum, SpectrumRef};
+use crate::tolerance::Tolerance;
+
+/// Parameters of the clustering
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClusterConfig {
+    fragment_tolerance: Tolerance,
+    precursor_tolerance: Tolerance,
+    min_cosine: f64,
+    min_peak_fraction: f64,
+}
+
+impl ClusterConfig {
+    /// # Arguments
+    /// * `fragment_tolerance` - Tolerance for matching and merging peaks
+    /// * `precursor_tolerance` - Tolerance for the precursor m/z of cluster members
+    /// * `min_cosine` - Minimal cosine similarity to the representative for joining a cluster
+    /// * `min_peak_fraction` - Minimal fraction of members a consensus peak needs to occur in
+    ///
+    pub fn new(
+        fragment_tolerance: Tolerance,
+        precursor_tolerance: Tolerance,
+        min_cosine: f64,
+        min_peak_fraction: f64,
+    ) -> Self {
+        Self {
+            fragment_tolerance,
+            precursor_tolerance,
+            min_cosine,
+            min_peak_fraction,
+        }
+    }
+
+    pub fn get_fragment_tolerance(&self) -> &Tolerance {
+        &self.fragment_tolerance
+    }
+
+    pub fn get_precursor_tolerance(&self) -> &Tolerance {
+        &self.precursor_tolerance
+    }
+
+    pub fn get_min_cosine(&self) -> f64 {
+        self.min_cosine
+    }
+
+    pub fn get_min_peak_fraction(&self) -> f64 {
+        self.min_peak_fraction
+    }
+}
+
+impl Default for ClusterConfig {
+    /// Default tolerances, a cosine of 0.7 and consensus peaks occurring in at least half of the members
+    ///
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_FRAGMENT_TOLERANCE,
+            DEFAULT_PRECURSOR_TOLERANCE,
+            0.7,
+            0.5,
+        )
+    }
+}
+
+impl From<&ExchangeConfig> for ClusterConfig {
+    /// Tolerances of the search, a cosine of 0.7 and consensus peaks occurring in at least half of the members
+    ///
+    fn from(config: &ExchangeConfig) -> Self {
+        Self::new(
+            *config.get_fragment_tolerance(),
+            *config.get_precursor_tolerance(),
+            0.7,
+            0.5,
+        )
+    }
+}
+
+/// Cluster of similar spectra with its consensus spectrum
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SpectrumCluster {
+    members: Vec<SpectrumRef>,
+    precursor_mz: Option<f64>,
+    charge: Option<u8>,
+    consensus: ConsensusPeaks,
+}
+
+impl SpectrumCluster {
+    /// Member spectra, the representative first
+    ///
+    pub fn get_members(&self) -> &Vec<SpectrumRef> {
+        &self.members
+    }
+
+    /// Most intense spectrum of the cluster, the others were compared to it
+    ///
+    pub fn get_representative(&self) -> &SpectrumRef {
+        &self.members[0]
+    }
+
+    /// Mean precursor m/z of the members, None for spectra without precursor
+    ///
+    pub fn get_precursor_mz(&self) -> Option<f64> {
+        self.precursor_mz
+    }
+
+    pub fn get_charge(&self) -> Option<u8> {
+        self.charge
+    }
+
+    /// Consensus peaks of the members, see `Spectrum::consensus`
+    ///
+    pub fn get_consensus(&self) -> &ConsensusPeaks {
+        &self.consensus
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+}
+
+/// Clusters the spectra greedily by cosine similarity, see module documentation.
+/// Spectra are compared by the precursor of their first identification. Requires m/z values sorted ascending.
+///
+/// # Arguments
+/// * `spectra` - Spectra to cluster
+/// * `config` - Clustering parameters
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::{Identification, Precursor, Spectrum};
+/// use maccoys_exchange_entities::results_api::cluster::{cluster_spectra, ClusterConfig};
+///
+/// let spectrum = |id: &str, precursor_mz: f64, intensity: Vec<f64>| {
+///     Spectrum::new(
+///         "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+///         "run".parse().unwrap(),
+///         id.parse().unwrap(),
+///         vec![175.119, 276.155, 389.239, 504.266],
+///         intensity,
+///         vec![Identification::new(None, None, Precursor::new(precursor_mz, 2))],
+///     )
+/// };
+/// let spectra = vec![
+///     spectrum("scan=1", 400.7, vec![10.0, 50.0, 30.0, 20.0]),
+///     spectrum("scan=2", 400.7001, vec![12.0, 45.0, 33.0, 18.0]),
+///     spectrum("scan=3", 400.7, vec![90.0, 1.0, 1.0, 1.0]),
+///     spectrum("scan=4", 512.3, vec![10.0, 50.0, 30.0, 20.0]),
+/// ];
+///
+/// let clusters = cluster_spectra(&spectra, &ClusterConfig::default());
+/// assert_eq!(clusters.len(), 3);
+/// assert_eq!(clusters[0].len(), 2);
+/// assert_eq!(clusters[0].get_representative().get_spectra_id(), "scan=1");
+/// assert_eq!(clusters[0].get_consensus().len(), 4);
+/// ```
+///
+pub fn cluster_spectra(spectra: &[Spectrum], config: &ClusterConfig) -> Vec<SpectrumCluster> {
+    let total_intensity = |spectrum: &Spectrum| spectrum.get_intensity().iter().sum::<f64>();
+    let mut order: Vec<usize> = (0..spectra.len()).collect();
+    order.sort_by(|a, b| total_intensity(&spectra[*b]).total_cmp(&total_intensity(&spectra[*a])));
+
+    // member indexes per cluster, the representative first
+    let mut clusters: Vec<Vec<usize>> = Vec::new();
+    for idx in order {
+        let spectrum = &spectra[idx];
+        let best_cluster = clusters
+            .iter()
+            .enumerate()
+            .filter(|(_, members)| {
+                precursors_match(&spectra[members[0]], spectrum, config.precursor_tolerance)
+            })
+            .map(|(cluster_idx, members)| {
+                let cosine = spectra[members[0]]
+                    .compare(spectrum, config.fragment_tolerance)
+                    .get_cosine();
+                (cluster_idx, cosine)
+            })
+            .filter(|(_, cosine)| *cosine >= config.min_cosine)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match best_cluster {
+            Some((cluster_idx, _)) => clusters[cluster_idx].push(idx),
+            None => clusters.push(vec![idx]),
+        }
+    }
+
+    clusters
+        .into_iter()
+        .map(|members| {
+            let members: Vec<&Spectrum> = members.into_iter().map(|idx| &spectra[idx]).collect();
+            let precursors: Vec<f64> = members
+                .iter()
+                .filter_map(|spectrum| precursor(spectrum).map(|(mz, _)| mz))
+                .collect();
+            let consensus = Spectrum::consensus(&members, config.fragment_tolerance)
+                .filter_by_frequency(config.min_peak_fraction);
+            SpectrumCluster {
+                precursor_mz: match precursors.is_empty() {
+                    true => None,
+                    false => Some(precursors.iter().sum::<f64>() / precursors.len() as f64),
+                },
+                charge: precursor(members[0]).map(|(_, charge)| charge),
+                members: members.iter().map(|spectrum| spectrum.to_ref()).collect(),
+                consensus,
+            }
+        })
+        .collect()
+}
+
+/// Precursor m/z and charge of the first identification
+///
+fn precursor(spectrum: &Spectrum) -> Option<(f64, u8)> {
+    spectrum
+        .get_identifications()
+        .first()
+        .map(|identification| {
+            let precursor = identification.get_precursor();
+            (precursor.get_mz(), precursor.get_charge())
+        })
+}
+
+/// True if both spectra have no precursor or the same charge and precursor m/z within the tolerance
+///
+fn precursors_match(representative: &Spectrum, spectrum: &Spectrum, tolerance: Tolerance) -> bool {
+    match (precursor(representative), precursor(spectrum)) {
+        (Some((mz, charge)), Some((other_mz, other_charge))) => {
+            charge == other_charge && tolerance.contains(other_mz, mz)
+        }
+        (None, None) => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,125 @@
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::results_api::Spectrum;
+use crate::tolerance::Tolerance;
+
+/// Alignment and similarity of two spectra, e.g. to draw a mirror plot.
+/// The spectrum the comparison is created on is the top, the other one the bottom spectrum.
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SpectrumComparison {
+    top_spectrum_id: String,
+    bottom_spectrum_id: String,
+    matched_peaks: Vec<(usize, usize)>,
+    cosine: f64,
+    spectral_angle: f64,
+}
+
+impl SpectrumComparison {
+    pub fn get_top_spectrum_id(&self) -> &str {
+        &self.top_spectrum_id
+    }
+
+    pub fn get_bottom_spectrum_id(&self) -> &str {
+        &self.bottom_spectrum_id
+    }
+
+    /// Pairs of matched peak indexes (top, bottom), ordered by the m/z of the top peak
+    ///
+    pub fn get_matched_peaks(&self) -> &Vec<(usize, usize)> {
+        &self.matched_peaks
+    }
+
+    /// Number of peaks matched between both spectra
+    ///
+    pub fn get_shared_peak_count(&self) -> usize {
+        self.matched_peaks.len()
+    }
+
+    /// Cosine similarity (normalized dot product) of the aligned intensities, between 0 and 1.
+    /// Unmatched peaks count as zero intensity in the other spectrum.
+    ///
+    pub fn get_cosine(&self) -> f64 {
+        self.cosine
+    }
+
+    /// Normalized spectral contrast angle `1 - 2 * acos(cosine) / π`, between 0 and 1
+    ///
+    pub fn get_spectral_angle(&self) -> f64 {
+        self.spectral_angle
+    }
+}
+
+impl Spectrum {
+    /// Aligns the peaks of this (top) and the other (bottom) spectrum and calculates their similarity.
+    /// Peaks are matched if their m/z difference is within the tolerance (relative to the top peak),
+    /// closest pairs first and each peak at most once. Requires m/z values sorted ascending.
+    ///
+    /// # Arguments
+    /// * `other` - Bottom spectrum
+    /// * `tolerance` - Tolerance for matching peaks
+    ///
+    pub fn compare(&self, other: &Spectrum, tolerance: Tolerance) -> SpectrumComparison {
+        let top_mz = self.get_mz();
+        let bottom_mz = other.get_mz();
+
+        // candidate pairs within the tolerance
+        let mut candidates: Vec<(usize, usize, f64)> = Vec::new();
+        for (top_idx, mz) in top_mz.iter().enumerate() {
+            let tolerance = tolerance.to_da(*mz);
+            let lower = bottom_mz.partition_point(|peak| *peak < mz - tolerance);
+            for bottom_idx in
+                (lower..bottom_mz.len()).take_while(|idx| bottom_mz[*idx] <= mz + tolerance)
+            {
+                candidates.push((top_idx, bottom_idx, (bottom_mz[bottom_idx] - mz).abs()));
+            }
+        }
+        candidates.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+        let mut top_matched = vec![false; top_mz.len()];
+        let mut bottom_matched = vec![false; bottom_mz.len()];
+        let mut matched_peaks: Vec<(usize, usize)> = Vec::new();
+        for (top_idx, bottom_idx, _) in candidates.into_iter() {
+            if top_matched[top_idx] || bottom_matched[bottom_idx] {
+                continue;
+            }
+            top_matched[top_idx] = true;
+            bottom_matched[bottom_idx] = true;
+            matched_peaks.push((top_idx, bottom_idx));
+        }
+        matched_peaks.sort_unstable();
+
+        let top_intensity = self.get_intensity();
+        let bottom_intensity = other.get_intensity();
+        let dot_product = matched_peaks
+            .iter()
+            .map(|(top_idx, bottom_idx)| top_intensity[*top_idx] * bottom_intensity[*bottom_idx])
+            .sum::<f64>();
+        let top_norm = top_intensity
+            .iter()
+            .map(|value| value * value)
+            .sum::<f64>()
+            .sqrt();
+        let bottom_norm = bottom_intensity
+            .iter()
+            .map(|value| value * value)
+            .sum::<f64>()
+            .sqrt();
+        let cosine = if top_norm > 0.0 && bottom_norm > 0.0 {
+            (dot_product / (top_norm * bottom_norm)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        SpectrumComparison {
+            top_spectrum_id: self.get_spectra_id().to_string(),
+            bottom_spectrum_id: other.get_spectra_id().to_string(),
+            matched_peaks,
+            cosine,
+            spectral_angle: 1.0 - 2.0 * cosine.acos() / std::f64::consts::PI,
+        }
+    }
+}
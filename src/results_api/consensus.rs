@@ -0,0 +1,149 @@
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::results_api::Spectrum;
+use crate::tolerance::Tolerance;
+
+/// Peak list merged from replicate spectra, e.g. for building a spectral library
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ConsensusPeaks {
+    mz: Vec<f64>,
+    intensity: Vec<f64>,
+    frequencies: Vec<f64>,
+    num_spectra: usize,
+}
+
+impl ConsensusPeaks {
+    /// m/z values, intensity-weighted means of the merged peaks
+    ///
+    pub fn get_mz(&self) -> &Vec<f64> {
+        &self.mz
+    }
+
+    /// Intensities, averaged over the spectra containing the peak
+    ///
+    pub fn get_intensity(&self) -> &Vec<f64> {
+        &self.intensity
+    }
+
+    /// Fraction of the spectra containing the peak, between 0 and 1
+    ///
+    pub fn get_frequencies(&self) -> &Vec<f64> {
+        &self.frequencies
+    }
+
+    /// Number of merged spectra
+    ///
+    pub fn get_num_spectra(&self) -> usize {
+        self.num_spectra
+    }
+
+    pub fn len(&self) -> usize {
+        self.mz.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mz.is_empty()
+    }
+
+    /// Keeps the peaks occurring in at least the given fraction of the spectra, e.g. 0.5 to remove noise
+    ///
+    /// # Arguments
+    /// * `min_frequency` - Minimal fraction of spectra
+    ///
+    pub fn filter_by_frequency(&self, min_frequency: f64) -> ConsensusPeaks {
+        let keep: Vec<usize> = (0..self.len())
+            .filter(|idx| self.frequencies[*idx] >= min_frequency)
+            .collect();
+        ConsensusPeaks {
+            mz: keep.iter().map(|idx| self.mz[*idx]).collect(),
+            intensity: keep.iter().map(|idx| self.intensity[*idx]).collect(),
+            frequencies: keep.iter().map(|idx| self.frequencies[*idx]).collect(),
+            num_spectra: self.num_spectra,
+        }
+    }
+}
+
+impl Spectrum {
+    /// Merges replicate spectra into a consensus peak list.
+    /// The peaks of all spectra are sorted by m/z and grouped, starting with the lowest peak,
+    /// each group takes all following peaks within the tolerance of its first peak.
+    ///
+    /// # Arguments
+    /// * `spectra` - Replicate spectra
+    /// * `tolerance` - Tolerance for merging peaks
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::Spectrum;
+    /// use maccoys_exchange_entities::tolerance::Tolerance;
+    ///
+    /// let spectrum = |id: &str, mz: Vec<f64>, intensity: Vec<f64>| {
+    ///     Spectrum::new(
+    ///         "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+    ///         "run".parse().unwrap(),
+    ///         id.parse().unwrap(),
+    ///         mz,
+    ///         intensity,
+    ///         Vec::new(),
+    ///     )
+    /// };
+    /// let first = spectrum("scan=1", vec![175.119, 276.155], vec![10.0, 40.0]);
+    /// let second = spectrum("scan=2", vec![175.121, 389.239], vec![30.0, 20.0]);
+    ///
+    /// let consensus = Spectrum::consensus(&[&first, &second], Tolerance::Da(0.02));
+    /// assert_eq!(consensus.len(), 3);
+    /// assert!((consensus.get_mz()[0] - 175.1205).abs() < 1e-9);
+    /// assert_eq!(consensus.get_intensity(), &vec![20.0, 40.0, 20.0]);
+    /// assert_eq!(consensus.get_frequencies(), &vec![1.0, 0.5, 0.5]);
+    /// assert_eq!(consensus.filter_by_frequency(1.0).len(), 1);
+    /// ```
+    ///
+    pub fn consensus(spectra: &[&Spectrum], tolerance: Tolerance) -> ConsensusPeaks {
+        let mut peaks: Vec<(f64, f64, usize)> = spectra
+            .iter()
+            .enumerate()
+            .flat_map(|(spectrum_idx, spectrum)| {
+                spectrum
+                    .get_mz()
+                    .iter()
+                    .zip(spectrum.get_intensity())
+                    .map(move |(mz, intensity)| (*mz, *intensity, spectrum_idx))
+            })
+            .collect();
+        peaks.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut consensus = ConsensusPeaks {
+            mz: Vec::new(),
+            intensity: Vec::new(),
+            frequencies: Vec::new(),
+            num_spectra: spectra.len(),
+        };
+        let mut start = 0;
+        while start < peaks.len() {
+            let max_mz = peaks[start].0 + tolerance.to_da(peaks[start].0);
+            let end = start + peaks[start..].partition_point(|peak| peak.0 <= max_mz);
+            let group = &peaks[start..end];
+            start = end;
+
+            let mut group_spectra: Vec<usize> = group.iter().map(|peak| peak.2).collect();
+            group_spectra.sort_unstable();
+            group_spectra.dedup();
+            let intensity = group.iter().map(|peak| peak.1).sum::<f64>();
+            let mz = match intensity > 0.0 {
+                true => group.iter().map(|peak| peak.0 * peak.1).sum::<f64>() / intensity,
+                false => group.iter().map(|peak| peak.0).sum::<f64>() / group.len() as f64,
+            };
+            consensus.mz.push(mz);
+            consensus
+                .intensity
+                .push(intensity / group_spectra.len() as f64);
+            consensus
+                .frequencies
+                .push(group_spectra.len() as f64 / spectra.len() as f64);
+        }
+        consensus
+    }
+}
@@ -0,0 +1,135 @@
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::mass::ISOTOPE_SPACING;
+use crate::results_api::Spectrum;
+use crate::tolerance::Tolerance;
+
+/// Peak list after deisotoping, each isotope envelope is collapsed into its monoisotopic peak
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DeisotopedPeaks {
+    mz: Vec<f64>,
+    intensity: Vec<f64>,
+    charges: Vec<Option<u8>>,
+}
+
+impl DeisotopedPeaks {
+    pub fn get_mz(&self) -> &Vec<f64> {
+        &self.mz
+    }
+
+    /// Intensities, summed over the isotope envelope
+    ///
+    pub fn get_intensity(&self) -> &Vec<f64> {
+        &self.intensity
+    }
+
+    /// Inferred charge per peak, None if the peak is not part of an isotope envelope
+    ///
+    pub fn get_charges(&self) -> &Vec<Option<u8>> {
+        &self.charges
+    }
+
+    pub fn len(&self) -> usize {
+        self.mz.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mz.is_empty()
+    }
+}
+
+impl Spectrum {
+    /// Collapses isotope envelopes into their monoisotopic peak and infers their charge.
+    /// Peaks are considered as monoisotopic peak in ascending m/z order. For each charge from `max_charge` down to 1
+    /// the following isotope peaks are searched within the tolerance, as long as their intensity decreases.
+    /// The charge with the longest envelope (at least two peaks) wins, ties are resolved in favour of the higher charge.
+    /// Peaks without envelope are kept without charge. Requires m/z values sorted ascending.
+    ///
+    /// # Arguments
+    /// * `tolerance` - Tolerance for matching isotope peaks
+    /// * `max_charge` - Highest charge to consider
+    ///
+    pub fn deisotope(&self, tolerance: Tolerance, max_charge: u8) -> DeisotopedPeaks {
+        let mz = self.get_mz();
+        let intensity = self.get_intensity();
+        let mut assigned = vec![false; mz.len()];
+        let mut peaks = DeisotopedPeaks {
+            mz: Vec::new(),
+            intensity: Vec::new(),
+            charges: Vec::new(),
+        };
+
+        for idx in 0..mz.len() {
+            if assigned[idx] {
+                continue;
+            }
+            let mut best: Option<(u8, Vec<usize>)> = None;
+            for charge in (1..=max_charge).rev() {
+                let envelope = isotope_envelope(mz, intensity, &assigned, idx, charge, tolerance);
+                if envelope.len() > 1
+                    && best
+                        .as_ref()
+                        .is_none_or(|(_, best_envelope)| envelope.len() > best_envelope.len())
+                {
+                    best = Some((charge, envelope));
+                }
+            }
+            match best {
+                Some((charge, envelope)) => {
+                    for peak_idx in envelope.iter() {
+                        assigned[*peak_idx] = true;
+                    }
+                    peaks.mz.push(mz[idx]);
+                    peaks
+                        .intensity
+                        .push(envelope.iter().map(|peak_idx| intensity[*peak_idx]).sum());
+                    peaks.charges.push(Some(charge));
+                }
+                None => {
+                    assigned[idx] = true;
+                    peaks.mz.push(mz[idx]);
+                    peaks.intensity.push(intensity[idx]);
+                    peaks.charges.push(None);
+                }
+            }
+        }
+        peaks
+    }
+}
+
+/// Returns the indexes of the isotope envelope starting at `start` for the given charge
+///
+fn isotope_envelope(
+    mz: &[f64],
+    intensity: &[f64],
+    assigned: &[bool],
+    start: usize,
+    charge: u8,
+    tolerance: Tolerance,
+) -> Vec<usize> {
+    let spacing = ISOTOPE_SPACING / charge as f64;
+    let mut envelope = vec![start];
+    let mut current = start;
+    loop {
+        let expected = mz[current] + spacing;
+        let next = tolerance
+            .peak_range(mz, expected)
+            .filter(|idx| !assigned[*idx])
+            .min_by(|a, b| {
+                (mz[*a] - expected)
+                    .abs()
+                    .total_cmp(&(mz[*b] - expected).abs())
+            });
+        match next {
+            Some(next) if intensity[next] < intensity[current] || envelope.len() == 1 => {
+                envelope.push(next);
+                current = next;
+            }
+            _ => return envelope,
+        }
+    }
+}
@@ -0,0 +1,291 @@
+// std imports
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::results_api::{
+    psm_columns,
+    spectrum::{ColumnError, FromAnyValue, Row},
+    Spectrum,
+};
+
+/// Parameters for comparing two searches
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffConfig {
+    score_column: String,
+    score_threshold: f64,
+}
+
+impl DiffConfig {
+    /// # Arguments
+    /// * `score_column` - Numeric PSM column to compare
+    /// * `score_threshold` - Absolute score difference above which a PSM is reported as changed
+    ///
+    pub fn new(score_column: String, score_threshold: f64) -> Self {
+        Self {
+            score_column,
+            score_threshold,
+        }
+    }
+
+    pub fn get_score_column(&self) -> &str {
+        &self.score_column
+    }
+
+    pub fn get_score_threshold(&self) -> f64 {
+        self.score_threshold
+    }
+}
+
+impl Default for DiffConfig {
+    /// xcorr, reporting every score change
+    ///
+    fn default() -> Self {
+        Self::new(psm_columns::XCORR.to_string(), 0.0)
+    }
+}
+
+/// PSM which differs between both searches. A PSM is identified by its spectrum, charge and (modified) peptide.
+/// Rank and score are None if the PSM, the rank or the score is missing in the respective search.
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PsmChange {
+    ms_run_name: String,
+    spectrum_id: String,
+    charge: u8,
+    peptide: String,
+    rank_a: Option<u32>,
+    rank_b: Option<u32>,
+    score_a: Option<f64>,
+    score_b: Option<f64>,
+}
+
+impl PsmChange {
+    pub fn get_ms_run(&self) -> &str {
+        &self.ms_run_name
+    }
+
+    pub fn get_spectrum_id(&self) -> &str {
+        &self.spectrum_id
+    }
+
+    pub fn get_charge(&self) -> u8 {
+        self.charge
+    }
+
+    /// Modified peptide, or the plain peptide if the PSMs have no modified peptide column
+    ///
+    pub fn get_peptide(&self) -> &str {
+        &self.peptide
+    }
+
+    pub fn get_rank_a(&self) -> Option<u32> {
+        self.rank_a
+    }
+
+    pub fn get_rank_b(&self) -> Option<u32> {
+        self.rank_b
+    }
+
+    pub fn get_score_a(&self) -> Option<f64> {
+        self.score_a
+    }
+
+    pub fn get_score_b(&self) -> Option<f64> {
+        self.score_b
+    }
+
+    /// Score of B minus score of A, None if one of the scores is missing
+    ///
+    pub fn score_delta(&self) -> Option<f64> {
+        Some(self.score_b? - self.score_a?)
+    }
+}
+
+/// Differences between the spectra of two searches A and B, e.g. conducted with different parameters
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SearchDiff {
+    spectra_only_in_a: Vec<(String, String)>,
+    spectra_only_in_b: Vec<(String, String)>,
+    changed_psms: Vec<PsmChange>,
+}
+
+impl SearchDiff {
+    /// Compares the spectra of both searches. Spectra are matched by MS run and spectrum ID,
+    /// their PSMs by charge and peptide. A PSM is reported as changed if its rank changed,
+    /// its score changed by more than the threshold or it only exists in one of the searches.
+    ///
+    /// # Arguments
+    /// * `spectra_a` - Spectra of search A
+    /// * `spectra_b` - Spectra of search B
+    /// * `config` - Diff parameters
+    ///
+    pub fn new(
+        spectra_a: &[Spectrum],
+        spectra_b: &[Spectrum],
+        config: &DiffConfig,
+    ) -> Result<Self, ColumnError> {
+        let index_b = spectra_b
+            .iter()
+            .map(|spectrum| {
+                (
+                    (
+                        spectrum.get_ms_run().as_str(),
+                        spectrum.get_spectra_id().as_str(),
+                    ),
+                    spectrum,
+                )
+            })
+            .collect::<HashMap<(&str, &str), &Spectrum>>();
+        let keys_a = spectra_a
+            .iter()
+            .map(|spectrum| {
+                (
+                    spectrum.get_ms_run().as_str(),
+                    spectrum.get_spectra_id().as_str(),
+                )
+            })
+            .collect::<HashSet<(&str, &str)>>();
+
+        let mut diff = Self {
+            spectra_only_in_a: Vec::new(),
+            spectra_only_in_b: spectra_b
+                .iter()
+                .map(|spectrum| {
+                    (
+                        spectrum.get_ms_run().as_str(),
+                        spectrum.get_spectra_id().as_str(),
+                    )
+                })
+                .filter(|key| !keys_a.contains(key))
+                .map(|(ms_run, spectrum_id)| (ms_run.to_string(), spectrum_id.to_string()))
+                .collect(),
+            changed_psms: Vec::new(),
+        };
+
+        for spectrum_a in spectra_a {
+            let spectrum_b =
+                match index_b.get(&(spectrum_a.get_ms_run(), spectrum_a.get_spectra_id())) {
+                    Some(spectrum_b) => spectrum_b,
+                    None => {
+                        diff.spectra_only_in_a.push((
+                            spectrum_a.get_ms_run().to_string(),
+                            spectrum_a.get_spectra_id().to_string(),
+                        ));
+                        continue;
+                    }
+                };
+            let mut psms = collect_psms(spectrum_a, config)?
+                .into_iter()
+                .map(|(key, a)| (key, (Some(a), None)))
+                .collect::<BTreeMap<(u8, String), (Option<PsmValues>, Option<PsmValues>)>>();
+            for (key, b) in collect_psms(spectrum_b, config)? {
+                psms.entry(key).or_insert((None, None)).1 = Some(b);
+            }
+            for ((charge, peptide), (a, b)) in psms.into_iter() {
+                let is_changed = match (a, b) {
+                    (Some((rank_a, score_a)), Some((rank_b, score_b))) => {
+                        rank_a != rank_b
+                            || match (score_a, score_b) {
+                                (Some(score_a), Some(score_b)) => {
+                                    (score_b - score_a).abs() > config.get_score_threshold()
+                                }
+                                (score_a, score_b) => score_a.is_some() != score_b.is_some(),
+                            }
+                    }
+                    _ => true,
+                };
+                if !is_changed {
+                    continue;
+                }
+                diff.changed_psms.push(PsmChange {
+                    ms_run_name: spectrum_a.get_ms_run().to_string(),
+                    spectrum_id: spectrum_a.get_spectra_id().to_string(),
+                    charge,
+                    peptide,
+                    rank_a: a.and_then(|(rank, _)| rank),
+                    rank_b: b.and_then(|(rank, _)| rank),
+                    score_a: a.and_then(|(_, score)| score),
+                    score_b: b.and_then(|(_, score)| score),
+                });
+            }
+        }
+        Ok(diff)
+    }
+
+    /// Spectra of A missing in B as (MS run, spectrum ID)
+    ///
+    pub fn get_spectra_only_in_a(&self) -> &Vec<(String, String)> {
+        &self.spectra_only_in_a
+    }
+
+    /// Spectra of B missing in A as (MS run, spectrum ID)
+    ///
+    pub fn get_spectra_only_in_b(&self) -> &Vec<(String, String)> {
+        &self.spectra_only_in_b
+    }
+
+    /// Changed PSMs of the spectra present in both searches, ordered like the spectra of A
+    ///
+    pub fn get_changed_psms(&self) -> &Vec<PsmChange> {
+        &self.changed_psms
+    }
+
+    /// True if there are no differences
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.spectra_only_in_a.is_empty()
+            && self.spectra_only_in_b.is_empty()
+            && self.changed_psms.is_empty()
+    }
+}
+
+/// Rank and score of a PSM
+type PsmValues = (Option<u32>, Option<f64>);
+
+/// Collects rank and score of the PSMs of the spectrum by charge and peptide. The first PSM wins on duplicates.
+///
+fn collect_psms(
+    spectrum: &Spectrum,
+    config: &DiffConfig,
+) -> Result<HashMap<(u8, String), PsmValues>, ColumnError> {
+    let mut psms: HashMap<(u8, String), PsmValues> = HashMap::new();
+    for identification in spectrum.get_identifications() {
+        let rows = match identification.iter_psm_rows() {
+            Some(rows) => rows,
+            None => continue,
+        };
+        for row in rows {
+            let peptide = match row.get::<Option<&str>>(psm_columns::MODIFIED_PEPTIDE) {
+                Ok(Some(peptide)) => peptide,
+                Ok(None) | Err(ColumnError::Row(_)) => row.get_str(psm_columns::PLAIN_PEPTIDE)?,
+                Err(err) => return Err(err),
+            };
+            psms.entry((identification.get_charge(), peptide.to_string()))
+                .or_insert((
+                    optional_value(&row, psm_columns::RANK)?,
+                    optional_value(&row, config.get_score_column())?,
+                ));
+        }
+    }
+    Ok(psms)
+}
+
+/// Value of the column, None if the value is null or the column does not exist
+///
+fn optional_value<'a, T: FromAnyValue<'a>>(
+    row: &'a Row<'_>,
+    col_name: &str,
+) -> Result<Option<T>, ColumnError> {
+    match row.get::<Option<T>>(col_name) {
+        Ok(value) => Ok(value),
+        Err(ColumnError::Row(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
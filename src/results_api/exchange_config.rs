@@ -0,0 +1,179 @@
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::mass::PROTON;
+use crate::results_api::{psm_columns, ScoreSelector};
+use crate::statistics::fdr::{FdrConfig, DEFAULT_DECOY_PREFIX};
+use crate::statistics::histogram::BinStrategy;
+use crate::tolerance::Tolerance;
+
+/// Comet's default precursor tolerance
+pub const DEFAULT_PRECURSOR_TOLERANCE: Tolerance = Tolerance::Ppm(20.0);
+
+/// Default fragment tolerance, also used by `AnnotationConfig::default`
+pub const DEFAULT_FRAGMENT_TOLERANCE: Tolerance = Tolerance::Da(0.02);
+
+/// Constants shared by all tools of the pipeline (tolerances, decoy prefix, score direction, histogram binning).
+/// Serialized with the `Search`, so every worker processing the search uses identical values.
+/// Payloads without config get the defaults.
+/// The proton mass and tolerances are applied via `mass_to_mz`/`mz_to_mass`,
+/// `AnnotationConfig::from` and `ClusterConfig::from`.
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::{ExchangeConfig, Search};
+/// use maccoys_exchange_entities::tolerance::Tolerance;
+///
+/// let config = ExchangeConfig::default()
+///     .with_precursor_tolerance(Tolerance::Ppm(10.0))
+///     .with_decoy_prefix("REV_");
+/// let search = Search::new("4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(), Vec::new())
+///     .with_config(config);
+///
+/// let search: Search = serde_json::from_str(&serde_json::to_string(&search).unwrap()).unwrap();
+/// assert_eq!(search.get_config().get_precursor_tolerance(), &Tolerance::Ppm(10.0));
+/// assert_eq!(search.get_config().to_fdr_config().get_decoy_prefix(), "REV_");
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct ExchangeConfig {
+    proton_mass: f64,
+    precursor_tolerance: Tolerance,
+    fragment_tolerance: Tolerance,
+    decoy_prefix: String,
+    score_column: String,
+    higher_score_better: bool,
+    histogram_bins: BinStrategy,
+}
+
+impl ExchangeConfig {
+    /// Sets the proton mass in Dalton, e.g. to match an engine using a less precise value
+    ///
+    pub fn with_proton_mass(mut self, proton_mass: f64) -> Self {
+        self.proton_mass = proton_mass;
+        self
+    }
+
+    pub fn with_precursor_tolerance(mut self, precursor_tolerance: Tolerance) -> Self {
+        self.precursor_tolerance = precursor_tolerance;
+        self
+    }
+
+    pub fn with_fragment_tolerance(mut self, fragment_tolerance: Tolerance) -> Self {
+        self.fragment_tolerance = fragment_tolerance;
+        self
+    }
+
+    /// Sets the prefix of decoy accessions
+    ///
+    pub fn with_decoy_prefix(mut self, decoy_prefix: &str) -> Self {
+        self.decoy_prefix = decoy_prefix.to_string();
+        self
+    }
+
+    /// Sets the primary score, used for FDR estimation and best PSM selection
+    ///
+    /// # Arguments
+    /// * `score_column` - PSM column of the score
+    /// * `higher_score_better` - True if higher scores are better, e.g. for xcorr
+    ///
+    pub fn with_score(mut self, score_column: &str, higher_score_better: bool) -> Self {
+        self.score_column = score_column.to_string();
+        self.higher_score_better = higher_score_better;
+        self
+    }
+
+    /// Sets the binning of score histograms
+    ///
+    pub fn with_histogram_bins(mut self, histogram_bins: BinStrategy) -> Self {
+        self.histogram_bins = histogram_bins;
+        self
+    }
+
+    pub fn get_proton_mass(&self) -> f64 {
+        self.proton_mass
+    }
+
+    pub fn get_precursor_tolerance(&self) -> &Tolerance {
+        &self.precursor_tolerance
+    }
+
+    pub fn get_fragment_tolerance(&self) -> &Tolerance {
+        &self.fragment_tolerance
+    }
+
+    pub fn get_decoy_prefix(&self) -> &str {
+        &self.decoy_prefix
+    }
+
+    pub fn get_score_column(&self) -> &str {
+        &self.score_column
+    }
+
+    pub fn is_higher_score_better(&self) -> bool {
+        self.higher_score_better
+    }
+
+    pub fn get_histogram_bins(&self) -> BinStrategy {
+        self.histogram_bins
+    }
+
+    /// Converts a neutral mass to the m/z of the given charge state with the configured proton mass,
+    /// see `mass::mass_to_mz`
+    ///
+    pub fn mass_to_mz(&self, mass: f64, charge: u8) -> f64 {
+        (mass + charge as f64 * self.proton_mass) / charge as f64
+    }
+
+    /// Converts the m/z of the given charge state to the neutral mass with the configured proton mass,
+    /// see `mass::mz_to_mass`
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::mass::mz_to_mass;
+    /// use maccoys_exchange_entities::results_api::ExchangeConfig;
+    ///
+    /// assert_eq!(ExchangeConfig::default().mz_to_mass(500.0, 2), mz_to_mass(500.0, 2));
+    /// let config = ExchangeConfig::default().with_proton_mass(1.0);
+    /// assert_eq!(config.mz_to_mass(500.0, 2), 998.0);
+    /// assert_eq!(config.mass_to_mz(998.0, 2), 500.0);
+    /// ```
+    ///
+    pub fn mz_to_mass(&self, mz: f64, charge: u8) -> f64 {
+        mz * charge as f64 - charge as f64 * self.proton_mass
+    }
+
+    /// FDR parameters with the decoy prefix and primary score
+    ///
+    pub fn to_fdr_config(&self) -> FdrConfig {
+        FdrConfig::new(
+            self.decoy_prefix.clone(),
+            self.score_column.clone(),
+            self.higher_score_better,
+        )
+    }
+
+    /// Selector for the best PSM by the primary score, recognizing decoys by the decoy prefix
+    ///
+    pub fn to_score_selector(&self) -> ScoreSelector {
+        ScoreSelector::new(&self.score_column, self.higher_score_better)
+            .with_decoy_prefix(&self.decoy_prefix)
+    }
+}
+
+impl Default for ExchangeConfig {
+    /// Comet's defaults, xcorr as primary score and Sturges' binning
+    ///
+    fn default() -> Self {
+        Self {
+            proton_mass: PROTON,
+            precursor_tolerance: DEFAULT_PRECURSOR_TOLERANCE,
+            fragment_tolerance: DEFAULT_FRAGMENT_TOLERANCE,
+            decoy_prefix: DEFAULT_DECOY_PREFIX.to_string(),
+            score_column: psm_columns::XCORR.to_string(),
+            higher_score_better: true,
+            histogram_bins: BinStrategy::Sturges,
+        }
+    }
+}
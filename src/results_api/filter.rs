@@ -0,0 +1,196 @@
+// std imports
+use std::ops::Not;
+
+// 3rd party imports
+use anyhow::Result;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::results_api::Identification;
+
+/// Filter on the columns of a PSM table, which can be received as JSON from the web API, e.g.
+/// `{"op": "and", "exprs": [{"op": "gt", "column": "xcorr", "value": 2.0}, {"op": "eq", "column": "num", "value": 1.0}]}`.
+/// Numeric comparisons cast the column to float, set membership to integer.
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum FilterExpr {
+    Gt {
+        column: String,
+        value: f64,
+    },
+    Ge {
+        column: String,
+        value: f64,
+    },
+    Lt {
+        column: String,
+        value: f64,
+    },
+    Le {
+        column: String,
+        value: f64,
+    },
+    Eq {
+        column: String,
+        value: f64,
+    },
+    /// String column contains the pattern literally
+    Contains {
+        column: String,
+        pattern: String,
+    },
+    /// Integer column is one of the values, e.g. charge in {2, 3}
+    IsIn {
+        column: String,
+        values: Vec<i64>,
+    },
+    And {
+        exprs: Vec<FilterExpr>,
+    },
+    Or {
+        exprs: Vec<FilterExpr>,
+    },
+    Not {
+        expr: Box<FilterExpr>,
+    },
+}
+
+impl FilterExpr {
+    pub fn gt(column: &str, value: f64) -> Self {
+        Self::Gt {
+            column: column.to_string(),
+            value,
+        }
+    }
+
+    pub fn ge(column: &str, value: f64) -> Self {
+        Self::Ge {
+            column: column.to_string(),
+            value,
+        }
+    }
+
+    pub fn lt(column: &str, value: f64) -> Self {
+        Self::Lt {
+            column: column.to_string(),
+            value,
+        }
+    }
+
+    pub fn le(column: &str, value: f64) -> Self {
+        Self::Le {
+            column: column.to_string(),
+            value,
+        }
+    }
+
+    pub fn eq(column: &str, value: f64) -> Self {
+        Self::Eq {
+            column: column.to_string(),
+            value,
+        }
+    }
+
+    pub fn contains(column: &str, pattern: &str) -> Self {
+        Self::Contains {
+            column: column.to_string(),
+            pattern: pattern.to_string(),
+        }
+    }
+
+    pub fn is_in(column: &str, values: Vec<i64>) -> Self {
+        Self::IsIn {
+            column: column.to_string(),
+            values,
+        }
+    }
+
+    /// Combines both filters, flattening nested conjunctions
+    ///
+    pub fn and(self, other: FilterExpr) -> Self {
+        match self {
+            Self::And { mut exprs } => {
+                exprs.push(other);
+                Self::And { exprs }
+            }
+            expr => Self::And {
+                exprs: vec![expr, other],
+            },
+        }
+    }
+
+    /// Matches if any of the filters matches, flattening nested disjunctions
+    ///
+    pub fn or(self, other: FilterExpr) -> Self {
+        match self {
+            Self::Or { mut exprs } => {
+                exprs.push(other);
+                Self::Or { exprs }
+            }
+            expr => Self::Or {
+                exprs: vec![expr, other],
+            },
+        }
+    }
+
+    /// Compiles the filter into a polars expression.
+    /// An empty conjunction matches everything, an empty disjunction nothing.
+    ///
+    pub fn to_expr(&self) -> Expr {
+        let float = |column: &str| col(column).cast(DataType::Float64);
+        match self {
+            Self::Gt { column, value } => float(column).gt(lit(*value)),
+            Self::Ge { column, value } => float(column).gt_eq(lit(*value)),
+            Self::Lt { column, value } => float(column).lt(lit(*value)),
+            Self::Le { column, value } => float(column).lt_eq(lit(*value)),
+            Self::Eq { column, value } => float(column).eq(lit(*value)),
+            Self::Contains { column, pattern } => col(column)
+                .cast(DataType::Utf8)
+                .str()
+                .contains_literal(lit(pattern.as_str())),
+            Self::IsIn { column, values } => col(column)
+                .cast(DataType::Int64)
+                .is_in(lit(Series::new("", values))),
+            Self::And { exprs } => exprs
+                .iter()
+                .map(FilterExpr::to_expr)
+                .reduce(|a, b| a.and(b))
+                .unwrap_or(lit(true)),
+            Self::Or { exprs } => exprs
+                .iter()
+                .map(FilterExpr::to_expr)
+                .reduce(|a, b| a.or(b))
+                .unwrap_or(lit(false)),
+            Self::Not { expr } => expr.to_expr().not(),
+        }
+    }
+
+    /// Applies the filter to the given dataframe
+    ///
+    pub fn apply(&self, dataframe: &DataFrame) -> Result<DataFrame> {
+        Ok(dataframe.clone().lazy().filter(self.to_expr()).collect()?)
+    }
+}
+
+impl Not for FilterExpr {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        Self::Not {
+            expr: Box::new(self),
+        }
+    }
+}
+
+impl Identification {
+    /// Returns the PSMs matching the filter, None if there are no PSMs
+    ///
+    pub fn filter(&self, filter: &FilterExpr) -> Result<Option<DataFrame>> {
+        match self.get_psms() {
+            Some(psms) => Ok(Some(filter.apply(psms)?)),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,9 @@
+//! Names of the goodness of fit columns, one row per fitted distribution.
+//! All other (numeric) columns are parameters of the fitted distribution, e.g. `loc` and `scale`.
+
+/// Name of the fitted distribution, e.g. `gumbel`
+pub const DISTRIBUTION: &str = "distribution";
+/// Test statistic of the goodness of fit test, e.g. Kolmogorov-Smirnov's D
+pub const STATISTIC: &str = "statistic";
+/// P-value of the goodness of fit test
+pub const P_VALUE: &str = "p_value";
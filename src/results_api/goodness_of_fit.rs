@@ -0,0 +1,142 @@
+// std imports
+use std::collections::{BTreeMap, BTreeSet};
+
+// 3rd party imports
+use anyhow::Result;
+use polars::prelude::*;
+
+// internal imports
+use crate::results_api::{
+    goodness_columns,
+    spectrum::{ColumnError, RowIter},
+};
+
+/// Goodness of fit of a distribution fitted to the PSM scores, typed view on a row of the goodness table
+///
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GoodnessOfFit {
+    distribution: String,
+    statistic: f64,
+    p_value: f64,
+    parameters: BTreeMap<String, f64>,
+}
+
+impl GoodnessOfFit {
+    pub fn new(
+        distribution: String,
+        statistic: f64,
+        p_value: f64,
+        parameters: BTreeMap<String, f64>,
+    ) -> Self {
+        Self {
+            distribution,
+            statistic,
+            p_value,
+            parameters,
+        }
+    }
+
+    /// Name of the fitted distribution
+    ///
+    pub fn get_distribution(&self) -> &str {
+        &self.distribution
+    }
+
+    /// Test statistic of the goodness of fit test
+    ///
+    pub fn get_statistic(&self) -> f64 {
+        self.statistic
+    }
+
+    pub fn get_p_value(&self) -> f64 {
+        self.p_value
+    }
+
+    /// Fitted parameters by name
+    ///
+    pub fn get_parameters(&self) -> &BTreeMap<String, f64> {
+        &self.parameters
+    }
+
+    pub fn get_parameter(&self, name: &str) -> Option<f64> {
+        self.parameters.get(name).copied()
+    }
+
+    /// Reads the goodness of fit from each row of the given goodness table.
+    /// All columns except distribution, statistic and p-value are considered parameters, nulls are skipped.
+    ///
+    pub fn from_dataframe(goodnesses: &DataFrame) -> Result<Vec<Self>, ColumnError> {
+        let parameter_columns = goodnesses
+            .get_column_names()
+            .into_iter()
+            .filter(|col_name| {
+                ![
+                    goodness_columns::DISTRIBUTION,
+                    goodness_columns::STATISTIC,
+                    goodness_columns::P_VALUE,
+                ]
+                .contains(col_name)
+            })
+            .collect::<Vec<&str>>();
+        RowIter::new(goodnesses)
+            .map(|row| {
+                let mut parameters = BTreeMap::new();
+                for col_name in parameter_columns.iter() {
+                    if let Some(value) = row.get::<Option<f64>>(col_name)? {
+                        parameters.insert(col_name.to_string(), value);
+                    }
+                }
+                Ok(Self {
+                    distribution: row.get_str(goodness_columns::DISTRIBUTION)?.to_string(),
+                    statistic: row.get_f64(goodness_columns::STATISTIC)?,
+                    p_value: row.get_f64(goodness_columns::P_VALUE)?,
+                    parameters,
+                })
+            })
+            .collect()
+    }
+
+    /// Creates the goodness table from the given goodness of fits, with one column per parameter name.
+    /// Parameters not set for a distribution are null.
+    ///
+    pub fn to_dataframe(goodnesses: &[Self]) -> Result<DataFrame> {
+        let parameter_names = goodnesses
+            .iter()
+            .flat_map(|goodness| goodness.parameters.keys())
+            .collect::<BTreeSet<&String>>();
+        let mut columns = vec![
+            Series::new(
+                goodness_columns::DISTRIBUTION,
+                goodnesses
+                    .iter()
+                    .map(|goodness| goodness.distribution.as_str())
+                    .collect::<Vec<&str>>(),
+            ),
+            Series::new(
+                goodness_columns::STATISTIC,
+                goodnesses
+                    .iter()
+                    .map(|goodness| goodness.statistic)
+                    .collect::<Vec<f64>>(),
+            ),
+            Series::new(
+                goodness_columns::P_VALUE,
+                goodnesses
+                    .iter()
+                    .map(|goodness| goodness.p_value)
+                    .collect::<Vec<f64>>(),
+            ),
+        ];
+        for name in parameter_names {
+            columns.push(Series::new(
+                name,
+                goodnesses
+                    .iter()
+                    .map(|goodness| goodness.get_parameter(name))
+                    .collect::<Vec<Option<f64>>>(),
+            ));
+        }
+        Ok(DataFrame::new(columns)?)
+    }
+}
@@ -0,0 +1,160 @@
+//! Aggregation of the PSMs of an identification per peptide or protein, e.g. for per-peptide summaries of the web API.
+//! Computed by the polars lazy engine.
+
+// 3rd party imports
+use anyhow::Result;
+use polars::prelude::*;
+
+// internal imports
+use crate::results_api::best_psm::decoy_column;
+use crate::results_api::{psm_columns, Identification, ScoreSelector};
+
+/// Number of PSMs of the group
+pub const PSM_COUNT: &str = "psm_count";
+/// Number of distinct peptides of the group
+pub const PEPTIDE_COUNT: &str = "peptide_count";
+/// Best score of the group
+pub const BEST_SCORE: &str = "best_score";
+/// Median of the PPM errors of the group (see `crate::mass::append_ppm_error`), null if not calculated
+pub const MEDIAN_PPM_ERROR: &str = "median_ppm_error";
+
+impl Identification {
+    /// PSMs grouped by plain peptide with the columns `plain_peptide`, `psm_count`, `best_score` (xcorr)
+    /// and `median_ppm_error`, best peptide first. Decoys are ignored. Returns None if there are no PSMs.
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::{Identification, Precursor};
+    /// use polars::prelude::*;
+    ///
+    /// let psms = df!(
+    ///     "plain_peptide" => &["PEPTIDE", "PEPTIDE", "PEPTIDES", "EDITPEP"],
+    ///     "protein" => &["P1,P2", "P1", "P2", "DECOY_P1"],
+    ///     "xcorr" => &[2.5, 1.1, 1.8, 3.0],
+    ///     "ppm_error" => &[1.0, 3.0, -2.0, 0.5]
+    /// )
+    /// .unwrap();
+    /// let identification = Identification::new(None, Some(psms), Precursor::new(400.7, 2));
+    ///
+    /// let peptides = identification.group_by_peptide().unwrap().unwrap();
+    /// assert_eq!(peptides.height(), 2);
+    /// assert_eq!(peptides.column("plain_peptide").unwrap().utf8().unwrap().get(0), Some("PEPTIDE"));
+    /// assert_eq!(peptides.column("psm_count").unwrap().u32().unwrap().get(0), Some(2));
+    /// assert_eq!(peptides.column("median_ppm_error").unwrap().f64().unwrap().get(0), Some(2.0));
+    ///
+    /// let proteins = identification.group_by_protein().unwrap().unwrap();
+    /// assert_eq!(proteins.column("protein").unwrap().utf8().unwrap().get(1), Some("P2"));
+    /// assert_eq!(proteins.column("peptide_count").unwrap().u32().unwrap().get(1), Some(2));
+    /// ```
+    ///
+    pub fn group_by_peptide(&self) -> Result<Option<DataFrame>> {
+        self.group_by_peptide_with(&ScoreSelector::xcorr())
+    }
+
+    /// Like `group_by_peptide`, with the score and decoy handling of the selector
+    ///
+    /// # Arguments
+    /// * `selector` - Score column and direction for the best score, decoys are only included if requested
+    ///
+    pub fn group_by_peptide_with(&self, selector: &ScoreSelector) -> Result<Option<DataFrame>> {
+        let psms = match self.prepare_grouping(selector)? {
+            Some(psms) => psms,
+            None => return Ok(None),
+        };
+        Ok(Some(aggregate(
+            psms.lazy(),
+            psm_columns::PLAIN_PEPTIDE,
+            selector,
+            Vec::new(),
+        )?))
+    }
+
+    /// PSMs grouped by protein with the columns `protein`, `psm_count`, `peptide_count`, `best_score` (xcorr)
+    /// and `median_ppm_error`, best protein first. PSMs of shared peptides count for each of their proteins.
+    /// Decoys are ignored. Returns None if there are no PSMs.
+    ///
+    pub fn group_by_protein(&self) -> Result<Option<DataFrame>> {
+        self.group_by_protein_with(&ScoreSelector::xcorr())
+    }
+
+    /// Like `group_by_protein`, with the score and decoy handling of the selector
+    ///
+    /// # Arguments
+    /// * `selector` - Score column and direction for the best score, decoys are only included if requested
+    ///
+    pub fn group_by_protein_with(&self, selector: &ScoreSelector) -> Result<Option<DataFrame>> {
+        let psms = match self.prepare_grouping(selector)? {
+            Some(psms) => psms,
+            None => return Ok(None),
+        };
+        let proteins = psms
+            .lazy()
+            .with_column(col(psm_columns::PROTEIN).str().split(lit(",")))
+            .explode([col(psm_columns::PROTEIN)])
+            .with_column(col(psm_columns::PROTEIN).str().strip_chars(lit(NULL)))
+            .filter(col(psm_columns::PROTEIN).neq(lit("")));
+        Ok(Some(aggregate(
+            proteins,
+            psm_columns::PROTEIN,
+            selector,
+            vec![col(psm_columns::PLAIN_PEPTIDE)
+                .n_unique()
+                .cast(DataType::UInt32)
+                .alias(PEPTIDE_COUNT)],
+        )?))
+    }
+
+    /// PSMs without the decoys (unless requested) and with a PPM error column
+    ///
+    fn prepare_grouping(&self, selector: &ScoreSelector) -> Result<Option<DataFrame>> {
+        let mut psms = match self.get_psms() {
+            Some(psms) => psms.clone(),
+            None => return Ok(None),
+        };
+        if !selector.includes_decoys() {
+            let is_decoy = decoy_column(&psms, selector.get_decoy_prefix())?;
+            psms = psms.filter(&!is_decoy)?;
+        }
+        if psms.column(psm_columns::PPM_ERROR).is_err() {
+            psms.with_column(Series::full_null(
+                psm_columns::PPM_ERROR,
+                psms.height(),
+                &DataType::Float64,
+            ))?;
+        }
+        Ok(Some(psms))
+    }
+}
+
+/// Groups by the key column, best score first and key ascending for equal scores
+///
+fn aggregate(
+    psms: LazyFrame,
+    key: &str,
+    selector: &ScoreSelector,
+    extra_aggregations: Vec<Expr>,
+) -> Result<DataFrame> {
+    let score = col(selector.get_column()).cast(DataType::Float64);
+    let best_score = match selector.is_higher_better() {
+        true => score.max(),
+        false => score.min(),
+    };
+    let mut aggregations = vec![
+        col(key).count().cast(DataType::UInt32).alias(PSM_COUNT),
+        best_score.alias(BEST_SCORE),
+        col(psm_columns::PPM_ERROR)
+            .cast(DataType::Float64)
+            .median()
+            .alias(MEDIAN_PPM_ERROR),
+    ];
+    aggregations.splice(1..1, extra_aggregations);
+    Ok(psms
+        .group_by([col(key)])
+        .agg(aggregations)
+        .sort_by_exprs(
+            [col(BEST_SCORE), col(key)],
+            [selector.is_higher_better(), false],
+            true,
+            false,
+        )
+        .collect()?)
+}
@@ -0,0 +1,341 @@
+//! Strongly typed identifiers of searches, MS runs and spectra.
+//!
+//! The identifiers are validated on construction and deserialization and serialized as plain strings,
+//! so the serialized layout does not change. They dereference to `str`, so they can be passed
+//! wherever a `&str` is expected.
+
+// std imports
+use std::borrow::Borrow;
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+
+// 3rd party imports
+use sha2::{Digest, Sha256};
+use uuid::{Builder, Uuid};
+
+/// Characters which are not allowed in names, as they are used as path separators
+pub const FORBIDDEN_CHARACTERS: [char; 2] = ['/', '\\'];
+
+/// Replacement of empty names, e.g. by `MsRun::empty` or when sanitizing
+pub const UNNAMED: &str = "unnamed";
+
+/// Error when validating an identifier
+///
+#[derive(Debug, thiserror::Error)]
+pub enum IdentifierError {
+    #[error("`{0}` is not a valid UUID")]
+    InvalidUuid(String),
+    #[error("identifier is empty")]
+    Empty,
+    #[error("identifier `{0}` contains forbidden character {1:?}")]
+    ForbiddenCharacter(String, char),
+    #[error("identifier `{0}` is reserved")]
+    Reserved(String),
+}
+
+/// Checks that the name can be used as a path segment,
+/// i.e. it is not empty, `.` or `..` and contains no path separators or control characters
+///
+fn validate_name(name: &str) -> Result<(), IdentifierError> {
+    if name.is_empty() {
+        return Err(IdentifierError::Empty);
+    }
+    if name == "." || name == ".." {
+        return Err(IdentifierError::Reserved(name.to_string()));
+    }
+    if let Some(character) = name
+        .chars()
+        .find(|character| FORBIDDEN_CHARACTERS.contains(character) || character.is_control())
+    {
+        return Err(IdentifierError::ForbiddenCharacter(
+            name.to_string(),
+            character,
+        ));
+    }
+    Ok(())
+}
+
+/// Turns the name into a valid one by replacing forbidden and control characters with `_`,
+/// the reserved names `.` and `..` with `_` and `__` and empty names with `UNNAMED`
+///
+fn sanitize_name(name: &str) -> String {
+    let sanitized = name
+        .chars()
+        .map(|character| {
+            if FORBIDDEN_CHARACTERS.contains(&character) || character.is_control() {
+                '_'
+            } else {
+                character
+            }
+        })
+        .collect::<String>();
+    match sanitized.as_str() {
+        "" => UNNAMED.to_string(),
+        "." | ".." => sanitized.replace('.', "_"),
+        _ => sanitized,
+    }
+}
+
+/// Implements the conversions and string access shared by all identifiers
+///
+macro_rules! identifier {
+    ($name:ident) => {
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+
+            pub fn into_inner(self) -> String {
+                self.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = IdentifierError;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                Self::new(value)
+            }
+        }
+
+        impl TryFrom<String> for $name {
+            type Error = IdentifierError;
+
+            fn try_from(value: String) -> Result<Self, Self::Error> {
+                Self::new(&value)
+            }
+        }
+
+        impl TryFrom<&str> for $name {
+            type Error = IdentifierError;
+
+            fn try_from(value: &str) -> Result<Self, Self::Error> {
+                Self::new(value)
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(identifier: $name) -> Self {
+                identifier.0
+            }
+        }
+
+        impl Deref for $name {
+            type Target = str;
+
+            fn deref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Borrow<str> for $name {
+            fn borrow(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+    };
+}
+
+/// UUID of a search, normalized to the lowercase hyphenated form
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::SearchUuid;
+///
+/// let search_uuid: SearchUuid = "6D2F8C3A-1B4E-4F7A-9C0D-2E5B8A1F3C6D".parse().unwrap();
+/// assert_eq!(search_uuid, "6d2f8c3a-1b4e-4f7a-9c0d-2e5b8a1f3c6d");
+/// assert!("search".parse::<SearchUuid>().is_err());
+/// assert!(serde_json::from_str::<SearchUuid>(r#""search""#).is_err());
+/// ```
+///
+#[derive(
+    Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(transparent)
+)]
+#[serde(try_from = "String", into = "String")]
+pub struct SearchUuid(String);
+
+impl SearchUuid {
+    /// Parses the UUID, accepting the simple, hyphenated, braced and URN forms
+    ///
+    /// # Arguments
+    /// * `search_uuid` - UUID of the search
+    ///
+    pub fn new(search_uuid: &str) -> Result<Self, IdentifierError> {
+        Uuid::try_parse(search_uuid)
+            .map(|uuid| Self(uuid.hyphenated().to_string()))
+            .map_err(|_| IdentifierError::InvalidUuid(search_uuid.to_string()))
+    }
+
+    /// The nil UUID, e.g. as placeholder for entities not yet assigned to a search
+    ///
+    pub fn nil() -> Self {
+        Self(Uuid::nil().hyphenated().to_string())
+    }
+
+    /// Parses the UUID like `new` but never fails. Empty values become the nil UUID, other invalid values
+    /// a custom (version 8) UUID derived from their SHA-256 digest, so distinct values stay distinct.
+    ///
+    /// # Arguments
+    /// * `search_uuid` - UUID of the search
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::SearchUuid;
+    ///
+    /// assert_eq!(SearchUuid::sanitized(""), SearchUuid::nil());
+    /// let derived = SearchUuid::sanitized("search-1");
+    /// assert_eq!(derived, SearchUuid::sanitized("search-1"));
+    /// assert_ne!(derived, SearchUuid::sanitized("search-2"));
+    /// assert!(SearchUuid::new(&derived).is_ok());
+    /// // version 8, as the digest is no SHA-1 of a name-based (version 5) UUID
+    /// assert_eq!(derived.chars().nth(14), Some('8'));
+    /// ```
+    ///
+    pub fn sanitized(search_uuid: &str) -> Self {
+        if search_uuid.is_empty() {
+            return Self::nil();
+        }
+        Self::new(search_uuid).unwrap_or_else(|_| {
+            let mut bytes = [0u8; 16];
+            bytes.copy_from_slice(&Sha256::digest(search_uuid.as_bytes())[..16]);
+            Self(
+                Builder::from_custom_bytes(bytes)
+                    .into_uuid()
+                    .hyphenated()
+                    .to_string(),
+            )
+        })
+    }
+}
+
+impl Default for SearchUuid {
+    fn default() -> Self {
+        Self::nil()
+    }
+}
+
+identifier!(SearchUuid);
+
+/// Name of an MS run, usable as path segment
+///
+#[derive(
+    Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(transparent)
+)]
+#[serde(try_from = "String", into = "String")]
+pub struct MsRunName(String);
+
+impl MsRunName {
+    /// Validates the name, which must not be empty, `.` or `..` and must not contain path separators or control characters
+    ///
+    /// # Arguments
+    /// * `ms_run_name` - Name of the MS run
+    ///
+    pub fn new(ms_run_name: &str) -> Result<Self, IdentifierError> {
+        validate_name(ms_run_name)?;
+        Ok(Self(ms_run_name.to_string()))
+    }
+
+    /// Validates the name like `new` but never fails, invalid characters are replaced with `_`
+    /// and an empty name with `UNNAMED`
+    ///
+    /// # Arguments
+    /// * `ms_run_name` - Name of the MS run
+    ///
+    pub fn sanitized(ms_run_name: &str) -> Self {
+        Self(sanitize_name(ms_run_name))
+    }
+
+    /// Placeholder name `UNNAMED`, e.g. for `MsRun::empty`
+    ///
+    pub fn unnamed() -> Self {
+        Self(UNNAMED.to_string())
+    }
+}
+
+identifier!(MsRunName);
+
+/// ID of a spectrum within its MS run, e.g. the native ID `controllerType=0 controllerNumber=1 scan=1`
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::SpectrumId;
+///
+/// assert!(SpectrumId::new("controllerType=0 controllerNumber=1 scan=1").is_ok());
+/// assert!(SpectrumId::new("../scan=1").is_err());
+/// assert!(SpectrumId::new("").is_err());
+/// ```
+///
+#[derive(
+    Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(transparent)
+)]
+#[serde(try_from = "String", into = "String")]
+pub struct SpectrumId(String);
+
+impl SpectrumId {
+    /// Validates the ID, which must not be empty, `.` or `..` and must not contain path separators or control characters
+    ///
+    /// # Arguments
+    /// * `spectrum_id` - ID of the spectrum
+    ///
+    pub fn new(spectrum_id: &str) -> Result<Self, IdentifierError> {
+        validate_name(spectrum_id)?;
+        Ok(Self(spectrum_id.to_string()))
+    }
+
+    /// Validates the ID like `new` but never fails, invalid characters are replaced with `_`
+    /// and an empty ID with `UNNAMED`
+    ///
+    /// # Arguments
+    /// * `spectrum_id` - ID of the spectrum
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::SpectrumId;
+    ///
+    /// assert_eq!(SpectrumId::sanitized("run/scan=1"), "run_scan=1");
+    /// assert_eq!(SpectrumId::sanitized(".."), "__");
+    /// assert_eq!(SpectrumId::sanitized(""), "unnamed");
+    /// ```
+    ///
+    pub fn sanitized(spectrum_id: &str) -> Self {
+        Self(sanitize_name(spectrum_id))
+    }
+}
+
+identifier!(SpectrumId);
@@ -0,0 +1,112 @@
+// 3rd party imports
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+
+// internal imports
+use crate::results_api::Spectrum;
+
+/// Prefix of SHA-256 payload digests, the algorithm is part of the digest so it can be changed later
+pub const SHA256_PREFIX: &str = "sha256:";
+
+/// Error when verifying the integrity of a payload
+///
+#[derive(Debug, thiserror::Error)]
+pub enum IntegrityError {
+    #[error("payload has no digest")]
+    MissingDigest,
+    #[error("digest `{0}` uses an unsupported algorithm")]
+    UnsupportedAlgorithm(String),
+    #[error("{mz} m/z values but {intensity} intensities, payload is truncated")]
+    PeakLengthMismatch { mz: usize, intensity: usize },
+    #[error("digest mismatch, expected `{expected}` but payload has `{actual}`")]
+    DigestMismatch { expected: String, actual: String },
+    #[error(transparent)]
+    Encoding(#[from] anyhow::Error),
+}
+
+impl Spectrum {
+    /// Computes the SHA-256 digest of the peaks and identifications, including PSM and goodness tables.
+    /// Metadata like the retention time is not part of the digest.
+    ///
+    pub fn compute_payload_digest(&self) -> Result<String> {
+        let mut hasher = Sha256::new();
+        hasher.update((self.get_mz().len() as u64).to_le_bytes());
+        for mz in self.get_mz() {
+            hasher.update(mz.to_le_bytes());
+        }
+        hasher.update((self.get_intensity().len() as u64).to_le_bytes());
+        for intensity in self.get_intensity() {
+            hasher.update(intensity.to_le_bytes());
+        }
+        hasher.update((self.get_identifications().len() as u64).to_le_bytes());
+        for identification in self.get_identifications() {
+            hasher.update(rmp_serde::to_vec_named(identification)?);
+        }
+        Ok(format_digest(&hasher.finalize()))
+    }
+
+    /// Computes and sets the payload digest, e.g. before sending the spectrum
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::Spectrum;
+    ///
+    /// let spectrum = Spectrum::new(
+    ///     "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+    ///     "run".parse().unwrap(),
+    ///     "scan=1".parse().unwrap(),
+    ///     vec![100.0, 200.0],
+    ///     vec![1.0, 2.0],
+    ///     Vec::new(),
+    /// )
+    /// .seal()
+    /// .unwrap();
+    /// assert!(spectrum.verify().is_ok());
+    ///
+    /// let json = serde_json::to_string(&spectrum).unwrap().replace("200.0", "200.5");
+    /// let corrupted: Spectrum = serde_json::from_str(&json).unwrap();
+    /// assert!(corrupted.verify().is_err());
+    /// ```
+    ///
+    pub fn seal(self) -> Result<Self> {
+        let digest = self.compute_payload_digest()?;
+        Ok(self.with_payload_digest(Some(digest)))
+    }
+
+    /// Verifies the peaks and identifications against the payload digest,
+    /// e.g. to detect corrupted or truncated payloads before rendering them
+    ///
+    pub fn verify(&self) -> Result<(), IntegrityError> {
+        let expected = self
+            .get_payload_digest()
+            .as_ref()
+            .ok_or(IntegrityError::MissingDigest)?;
+        if !expected.starts_with(SHA256_PREFIX) {
+            return Err(IntegrityError::UnsupportedAlgorithm(expected.clone()));
+        }
+        if self.get_mz().len() != self.get_intensity().len() {
+            return Err(IntegrityError::PeakLengthMismatch {
+                mz: self.get_mz().len(),
+                intensity: self.get_intensity().len(),
+            });
+        }
+        let actual = self.compute_payload_digest()?;
+        if &actual != expected {
+            return Err(IntegrityError::DigestMismatch {
+                expected: expected.clone(),
+                actual,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Formats the SHA-256 hash as `sha256:<hex>`
+///
+pub(crate) fn format_digest(hash: &[u8]) -> String {
+    let mut digest = String::with_capacity(SHA256_PREFIX.len() + hash.len() * 2);
+    digest.push_str(SHA256_PREFIX);
+    for byte in hash {
+        digest.push_str(&format!("{:02x}", byte));
+    }
+    digest
+}
@@ -0,0 +1,87 @@
+// std imports
+use std::io::Cursor;
+
+// 3rd party imports
+use anyhow::Result;
+use polars::prelude::*;
+
+// internal imports
+use crate::results_api::{Identification, Precursor};
+
+/// Identification with the PSM and goodness tables encoded as Arrow IPC files (Feather v2),
+/// which can be read zero-copy by pyarrow, e.g. `pyarrow.ipc.open_file(psms).read_all()`
+///
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct IdentificationIpc {
+    goodnesses: Option<Vec<u8>>,
+    psms: Option<Vec<u8>>,
+    precursor: Precursor,
+}
+
+impl IdentificationIpc {
+    pub fn new(goodnesses: Option<Vec<u8>>, psms: Option<Vec<u8>>, precursor: Precursor) -> Self {
+        Self {
+            goodnesses,
+            psms,
+            precursor,
+        }
+    }
+
+    pub fn get_goodnesses(&self) -> &Option<Vec<u8>> {
+        &self.goodnesses
+    }
+
+    pub fn get_psms(&self) -> &Option<Vec<u8>> {
+        &self.psms
+    }
+
+    pub fn get_precursor(&self) -> &Precursor {
+        &self.precursor
+    }
+}
+
+/// Writes the dataframe as uncompressed Arrow IPC file
+///
+pub fn dataframe_to_ipc(dataframe: &DataFrame) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    IpcWriter::new(&mut buffer).finish(&mut dataframe.clone())?;
+    Ok(buffer)
+}
+
+/// Reads a dataframe from an Arrow IPC file
+///
+pub fn dataframe_from_ipc(ipc: &[u8]) -> Result<DataFrame> {
+    Ok(IpcReader::new(Cursor::new(ipc)).finish()?)
+}
+
+impl Identification {
+    /// Encodes the PSM and goodness tables as Arrow IPC files
+    ///
+    pub fn to_arrow_ipc(&self) -> Result<IdentificationIpc> {
+        Ok(IdentificationIpc {
+            goodnesses: self
+                .get_goodnesses()
+                .as_ref()
+                .map(dataframe_to_ipc)
+                .transpose()?,
+            psms: self.get_psms().as_ref().map(dataframe_to_ipc).transpose()?,
+            precursor: self.get_precursor().clone(),
+        })
+    }
+
+    /// Decodes an identification from Arrow IPC encoded tables
+    ///
+    pub fn from_arrow_ipc(ipc: &IdentificationIpc) -> Result<Self> {
+        Ok(Self::new(
+            ipc.get_goodnesses()
+                .as_deref()
+                .map(dataframe_from_ipc)
+                .transpose()?,
+            ipc.get_psms()
+                .as_deref()
+                .map(dataframe_from_ipc)
+                .transpose()?,
+            ipc.get_precursor().clone(),
+        ))
+    }
+}
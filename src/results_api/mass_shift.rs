@@ -0,0 +1,318 @@
+//! Histogram of the precursor mass shifts (observed minus theoretical mass) of the PSMs,
+//! e.g. to spot unexpected modifications in the results of open searches.
+
+// std imports
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+
+// 3rd party imports
+use anyhow::{bail, Result};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::mass::theoretical_masses;
+use crate::results_api::best_psm::decoy_column;
+use crate::results_api::modification::UNIMOD_ENTRIES;
+use crate::results_api::spectrum::top_ranked_flags;
+use crate::results_api::{psm_columns, ExchangeConfig, Identification, Search, Spectrum};
+
+/// Unimod entry with a mass delta within a bin
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ModificationCandidate {
+    accession: u32,
+    name: String,
+    mass_delta: f64,
+}
+
+impl ModificationCandidate {
+    pub fn get_accession(&self) -> u32 {
+        self.accession
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Monoisotopic mass delta in Dalton
+    ///
+    pub fn get_mass_delta(&self) -> f64 {
+        self.mass_delta
+    }
+}
+
+/// Bin of the mass shift histogram
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MassShiftBin {
+    center: f64,
+    count: usize,
+    candidates: Vec<ModificationCandidate>,
+}
+
+impl MassShiftBin {
+    /// Mass shift in the middle of the bin in Dalton
+    ///
+    pub fn get_center(&self) -> f64 {
+        self.center
+    }
+
+    /// Number of PSMs within the bin
+    ///
+    pub fn get_count(&self) -> usize {
+        self.count
+    }
+
+    /// Unimod entries with a mass delta within the bin, closest to the center first
+    ///
+    pub fn get_candidates(&self) -> &Vec<ModificationCandidate> {
+        &self.candidates
+    }
+}
+
+/// Histogram of the precursor mass shifts of the top ranked target PSMs.
+/// Bins are centered on multiples of the bin width, so unshifted PSMs fall into the bin centered on 0.
+/// Only bins containing PSMs are kept.
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::{Identification, Precursor, Search, Spectrum};
+/// use polars::prelude::*;
+///
+/// let search_uuid = "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c";
+/// let spectrum = |spectrum_id: &str, psms: DataFrame| {
+///     Spectrum::new(
+///         search_uuid.parse().unwrap(),
+///         "run".parse().unwrap(),
+///         spectrum_id.parse().unwrap(),
+///         vec![100.0],
+///         vec![1.0],
+///         vec![Identification::new(None, Some(psms), Precursor::new(400.7, 2))],
+///     )
+/// };
+/// let psms = |exp_neutral_mass: f64, protein: &str, q_value: f64| {
+///     df!(
+///         "plain_peptide" => &["PEPTIDE", "PEPTIDEK"],
+///         "protein" => &[protein, "P2"],
+///         "exp_neutral_mass" => &[exp_neutral_mass, exp_neutral_mass],
+///         "calc_neutral_mass" => &[799.36, 927.455],
+///         "num" => &[1u32, 2],
+///         "q_value" => &[q_value, 1.0]
+///     )
+///     .unwrap()
+/// };
+/// let spectra = vec![
+///     spectrum("scan=1", psms(799.36, "P1", 0.0)),
+///     spectrum("scan=2", psms(815.3549, "P1", 0.0)),
+///     spectrum("scan=3", psms(879.326, "P1", 0.005)),
+///     // decoys are not binned
+///     spectrum("scan=4", psms(842.1, "DECOY_P1", 0.5)),
+/// ];
+/// let search = Search::new(search_uuid.parse().unwrap(), vec!["run".parse().unwrap()]);
+///
+/// // the rank 2 candidates with a mass shift of -128 Da are not binned
+/// let histogram = search.mass_shift_histogram(&spectra, 0.01, None).unwrap();
+/// assert_eq!(histogram.get_num_psms(), 3);
+/// assert_eq!(histogram.get_bins().len(), 3);
+/// let oxidation = histogram.get_bin(15.9949).unwrap();
+/// assert_eq!(oxidation.get_count(), 1);
+/// assert_eq!(oxidation.get_candidates()[0].get_name(), "Oxidation");
+/// assert_eq!(histogram.get_bin(79.966).unwrap().get_candidates()[0].get_name(), "Phospho");
+/// assert!(histogram.get_bin(0.0).unwrap().get_candidates().is_empty());
+///
+/// let histogram = search.mass_shift_histogram(&spectra, 0.01, Some(0.001)).unwrap();
+/// assert_eq!(histogram.get_num_psms(), 2);
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MassShiftHistogram {
+    bin_width: f64,
+    num_psms: usize,
+    bins: Vec<MassShiftBin>,
+}
+
+impl MassShiftHistogram {
+    /// Aggregates the mass shifts of the top ranked target PSMs of the spectra, as lower ranked candidates
+    /// and decoys are mostly random matches. Decoys are taken from the `is_decoy` column if present,
+    /// otherwise recognized by the decoy prefix of the config.
+    /// The observed mass is taken from `exp_neutral_mass`, otherwise from the precursor,
+    /// the theoretical mass from `calc_neutral_mass`, otherwise from the peptide.
+    /// PSMs without either mass are skipped.
+    ///
+    /// # Arguments
+    /// * `spectra` - Spectra with identifications
+    /// * `bin_width` - Bin width in Dalton
+    /// * `config` - Exchange config of the search, providing the decoy prefix and proton mass
+    /// * `max_q_value` - If given, only PSMs with a `q_value` up to it are binned (see `Identification::append_fdr`)
+    ///
+    pub fn from_spectra<S: Borrow<Spectrum>>(
+        spectra: impl IntoIterator<Item = S>,
+        bin_width: f64,
+        config: &ExchangeConfig,
+        max_q_value: Option<f64>,
+    ) -> Result<Self> {
+        if !(bin_width > 0.0 && bin_width.is_finite()) {
+            bail!("bin width must be positive, got {}", bin_width);
+        }
+        let mut counts: BTreeMap<i64, usize> = BTreeMap::new();
+        let mut num_psms = 0;
+        for spectrum in spectra {
+            for identification in spectrum.borrow().get_identifications() {
+                for mass_shift in mass_shifts(identification, config, max_q_value)? {
+                    *counts
+                        .entry((mass_shift / bin_width).round() as i64)
+                        .or_default() += 1;
+                    num_psms += 1;
+                }
+            }
+        }
+        let bins = counts
+            .into_iter()
+            .map(|(bin, count)| {
+                let center = bin as f64 * bin_width;
+                let mut candidates = UNIMOD_ENTRIES
+                    .iter()
+                    .filter(|entry| (entry.mass_delta / bin_width).round() as i64 == bin)
+                    .map(|entry| ModificationCandidate {
+                        accession: entry.accession,
+                        name: entry.name.to_string(),
+                        mass_delta: entry.mass_delta,
+                    })
+                    .collect::<Vec<ModificationCandidate>>();
+                candidates.sort_by(|a, b| {
+                    (a.mass_delta - center)
+                        .abs()
+                        .total_cmp(&(b.mass_delta - center).abs())
+                });
+                MassShiftBin {
+                    center,
+                    count,
+                    candidates,
+                }
+            })
+            .collect();
+        Ok(Self {
+            bin_width,
+            num_psms,
+            bins,
+        })
+    }
+
+    /// Bin width in Dalton
+    ///
+    pub fn get_bin_width(&self) -> f64 {
+        self.bin_width
+    }
+
+    /// Number of PSMs in the histogram
+    ///
+    pub fn get_num_psms(&self) -> usize {
+        self.num_psms
+    }
+
+    /// Bins containing PSMs, sorted by mass shift
+    ///
+    pub fn get_bins(&self) -> &Vec<MassShiftBin> {
+        &self.bins
+    }
+
+    /// Bin containing the given mass shift, None if it contains no PSMs
+    ///
+    pub fn get_bin(&self, mass_shift: f64) -> Option<&MassShiftBin> {
+        let center = (mass_shift / self.bin_width).round() * self.bin_width;
+        self.bins
+            .iter()
+            .find(|bin| (bin.center - center).abs() < self.bin_width / 2.0)
+    }
+
+    /// The given number of bins with the most PSMs, most populated first
+    ///
+    pub fn get_most_frequent(&self, num_bins: usize) -> Vec<&MassShiftBin> {
+        let mut bins = self.bins.iter().collect::<Vec<&MassShiftBin>>();
+        bins.sort_by(|a, b| b.count.cmp(&a.count).then(a.center.total_cmp(&b.center)));
+        bins.truncate(num_bins);
+        bins
+    }
+}
+
+impl Search {
+    /// Histogram of the precursor mass shifts of the top ranked target PSMs of the search,
+    /// see `MassShiftHistogram::from_spectra`
+    ///
+    /// # Arguments
+    /// * `spectra` - Spectra of the search
+    /// * `bin_width_da` - Bin width in Dalton
+    /// * `max_q_value` - If given, only PSMs with a `q_value` up to it are binned
+    ///
+    pub fn mass_shift_histogram<S: Borrow<Spectrum>>(
+        &self,
+        spectra: impl IntoIterator<Item = S>,
+        bin_width_da: f64,
+        max_q_value: Option<f64>,
+    ) -> Result<MassShiftHistogram> {
+        MassShiftHistogram::from_spectra(spectra, bin_width_da, self.get_config(), max_q_value)
+    }
+}
+
+/// Observed minus theoretical neutral mass of each top ranked target PSM of the identification,
+/// see `MassShiftHistogram::from_spectra`
+///
+fn mass_shifts(
+    identification: &Identification,
+    config: &ExchangeConfig,
+    max_q_value: Option<f64>,
+) -> Result<Vec<f64>> {
+    let psms = match identification.get_psms() {
+        Some(psms) => psms,
+        None => return Ok(Vec::new()),
+    };
+    let mut is_binned = top_ranked_flags(psms)?;
+    let is_decoy = decoy_column(psms, config.get_decoy_prefix())?;
+    for (is_binned, is_decoy) in is_binned.iter_mut().zip(&is_decoy) {
+        *is_binned &= !is_decoy.unwrap_or(false);
+    }
+    if let Some(max_q_value) = max_q_value {
+        let q_values = match psms.column(psm_columns::Q_VALUE) {
+            Ok(q_values) => float_values(q_values)?,
+            Err(_) => vec![None; psms.height()],
+        };
+        for (is_binned, q_value) in is_binned.iter_mut().zip(q_values) {
+            *is_binned &= q_value.is_some_and(|q_value| q_value <= max_q_value);
+        }
+    }
+    let psms = &psms.filter(&BooleanChunked::from_slice("is_binned", &is_binned))?;
+    let observed_masses = match psms.column(psm_columns::EXP_NEUTRAL_MASS) {
+        Ok(masses) => float_values(masses)?,
+        Err(_) => match identification.get_charge() {
+            0 => return Ok(Vec::new()),
+            charge => vec![
+                Some(config.mz_to_mass(
+                    identification.get_precursor().get_monoisotopic_mz(),
+                    charge
+                ));
+                psms.height()
+            ],
+        },
+    };
+    let theoretical_masses = match psms.column(psm_columns::CALC_NEUTRAL_MASS) {
+        Ok(masses) => float_values(masses)?,
+        Err(_) => theoretical_masses(psms)?,
+    };
+    Ok(observed_masses
+        .into_iter()
+        .zip(theoretical_masses)
+        .filter_map(|(observed, theoretical)| Some(observed? - theoretical?))
+        .filter(|mass_shift| mass_shift.is_finite())
+        .collect())
+}
+
+fn float_values(series: &Series) -> Result<Vec<Option<f64>>> {
+    Ok(series
+        .cast(&DataType::Float64)?
+        .f64()?
+        .into_iter()
+        .collect())
+}
@@ -0,0 +1,117 @@
+//! Merging of results from separate searches, MS runs or reruns.
+//!
+//! The merged entities always keep the search UUID of the entity merged into. MS runs with the same name
+//! and spectra with the same MS run and spectrum ID are considered the same and combined.
+//! Use `merge_ms_runs` and `merge_spectra` to combine the MS runs and spectra of the merged searches.
+
+// std imports
+use std::collections::HashMap;
+
+// 3rd party imports
+use polars::prelude::*;
+
+// internal imports
+use crate::results_api::{MsRun, MsRunName, SearchUuid, Spectrum, SpectrumId};
+
+/// Error when merging results
+///
+#[derive(Debug, thiserror::Error)]
+pub enum MergeError {
+    #[error("searches were conducted with different parameters")]
+    ParameterMismatch,
+    #[error("searches were conducted with different configs")]
+    ConfigMismatch,
+    #[error("quantifications were normalized differently")]
+    NormalizationMismatch,
+    #[error("MS run `{0}` cannot be merged with MS run `{1}`")]
+    MsRunMismatch(String, String),
+    #[error("spectrum `{0}` cannot be merged with spectrum `{1}`")]
+    SpectrumMismatch(String, String),
+    #[error("identifications of charge {0} and {1} cannot be concatenated")]
+    ChargeMismatch(u8, u8),
+    #[error(transparent)]
+    Polars(#[from] PolarsError),
+}
+
+/// Assigns the MS runs to the given search and merges MS runs with the same name, in order of first occurrence
+///
+pub fn merge_ms_runs(
+    search_uuid: &SearchUuid,
+    ms_runs: Vec<MsRun>,
+) -> Result<Vec<MsRun>, MergeError> {
+    let mut merged: Vec<MsRun> = Vec::new();
+    let mut positions: HashMap<MsRunName, usize> = HashMap::new();
+    for ms_run in ms_runs {
+        let ms_run = ms_run.with_search_uuid(search_uuid.clone());
+        match positions.get(ms_run.get_ms_run()) {
+            Some(pos) => merged[*pos].merge(ms_run)?,
+            None => {
+                positions.insert(ms_run.get_ms_run().clone(), merged.len());
+                merged.push(ms_run);
+            }
+        }
+    }
+    Ok(merged)
+}
+
+/// Assigns the spectra to the given search and merges spectra with the same MS run and spectrum ID,
+/// in order of first occurrence
+///
+pub fn merge_spectra(
+    search_uuid: &SearchUuid,
+    spectra: Vec<Spectrum>,
+) -> Result<Vec<Spectrum>, MergeError> {
+    let mut merged: Vec<Spectrum> = Vec::new();
+    let mut positions: HashMap<(MsRunName, SpectrumId), usize> = HashMap::new();
+    for spectrum in spectra {
+        let spectrum = spectrum.with_search_uuid(search_uuid.clone());
+        let key = (
+            spectrum.get_ms_run().clone(),
+            spectrum.get_spectra_id().clone(),
+        );
+        match positions.get(&key) {
+            Some(pos) => merged[*pos].merge(spectrum)?,
+            None => {
+                positions.insert(key, merged.len());
+                merged.push(spectrum);
+            }
+        }
+    }
+    Ok(merged)
+}
+
+/// Stacks the given tables into a single chunk. Columns missing in one of the tables are filled with nulls,
+/// columns of the bottom table are cast to the type of the top table.
+///
+pub(crate) fn concat_aligned(
+    top: Option<DataFrame>,
+    bottom: Option<DataFrame>,
+) -> Result<Option<DataFrame>, MergeError> {
+    let (mut top, mut bottom) = match (top, bottom) {
+        (Some(top), Some(bottom)) => (top, bottom),
+        (top, None) => return Ok(top),
+        (None, bottom) => return Ok(bottom),
+    };
+    for (name, dtype) in bottom.schema().iter() {
+        if top.column(name).is_err() {
+            top.with_column(Series::full_null(name, top.height(), dtype))?;
+        }
+    }
+    for (name, dtype) in top.schema().iter() {
+        match bottom.column(name) {
+            Ok(column) if column.dtype() != dtype => {
+                let column = column.strict_cast(dtype)?;
+                bottom.with_column(column)?;
+            }
+            Ok(_) => (),
+            Err(_) => {
+                bottom.with_column(Series::full_null(name, bottom.height(), dtype))?;
+            }
+        }
+    }
+    let bottom = bottom.select(top.get_column_names())?;
+    top.vstack_mut(&bottom)?;
+    // row iteration requires a single chunk per column
+    top.as_single_chunk();
+    Ok(Some(top))
+}
@@ -1,8 +1,101 @@
-pub mod search;
+pub mod api_response;
+pub mod approx_eq;
+pub mod best_psm;
+pub mod chromatogram;
+pub mod chunked;
+pub mod cluster;
+pub mod comparison;
+pub mod consensus;
+pub mod deisotoping;
+pub mod diff;
+pub mod exchange_config;
+#[cfg(feature = "filter")]
+pub mod filter;
+pub mod goodness_columns;
+pub mod goodness_of_fit;
+#[cfg(feature = "group_by")]
+pub mod group_by;
+pub mod identifiers;
+pub mod integrity;
+#[cfg(feature = "ipc")]
+pub mod ipc;
+pub mod mass_shift;
+pub mod merge;
+pub mod modification;
 pub mod ms_run;
+pub mod normalization;
+pub mod peak_filter;
+pub mod peptide;
+pub mod precursor;
+pub mod processing_error;
+pub mod projection;
+pub mod protein;
+pub mod psm_accessors;
+pub mod psm_columns;
+pub mod psm_schema;
+pub mod quantification;
+pub mod score_descriptors;
+pub mod score_sets;
+pub mod search;
+pub mod search_parameters;
+pub mod search_statistics;
+pub mod search_status;
+pub mod sequence_tag;
+pub mod spectra_page;
 pub mod spectrum;
+pub mod spectrum_index;
+pub mod spectrum_ref;
+pub mod summaries;
+pub mod table_view;
+pub mod transfer;
+pub mod versioning;
+
+/// Current version of the serialized layout of `Search`, `MsRun` and `Spectrum`.
+/// Increment on breaking layout changes and add a migration in `crate::migrations`.
+pub const SCHEMA_VERSION: u32 = 3;
 
 //rexports
-pub use search::Search;
+pub use api_response::{ApiError, ApiResponse, Paginated, ResponseStatus};
+pub use best_psm::{BestPsm, ScoreSelector};
+pub use chromatogram::Chromatogram;
+pub use cluster::SpectrumCluster;
+pub use comparison::SpectrumComparison;
+pub use consensus::ConsensusPeaks;
+pub use deisotoping::DeisotopedPeaks;
+pub use diff::{DiffConfig, PsmChange, SearchDiff};
+pub use exchange_config::ExchangeConfig;
+#[cfg(feature = "filter")]
+pub use filter::FilterExpr;
+pub use goodness_of_fit::GoodnessOfFit;
+pub use identifiers::{IdentifierError, MsRunName, SearchUuid, SpectrumId};
+pub use integrity::IntegrityError;
+pub use mass_shift::{MassShiftBin, MassShiftHistogram, ModificationCandidate};
+pub use merge::MergeError;
+pub use modification::{Modification, ModificationError, ModificationPosition};
 pub use ms_run::MsRun;
-pub use spectrum::{Spectrum, Identification};
+pub use normalization::Normalization;
+pub use peak_filter::PeakFilter;
+pub use peptide::Peptide;
+pub use precursor::Precursor;
+pub use processing_error::{ProcessingError, ProcessingErrorKind, ProcessingStage};
+pub use projection::{Field, ProjectionError, SpectrumProjection};
+pub use protein::{Protein, ProteinGroup};
+pub use psm_schema::{PsmSchema, PsmSchemaError};
+pub use quantification::{
+    PeptideQuant, ProteinQuant, QuantNormalization, QuantNormalizationMethod, Quantification,
+    RunQuant,
+};
+pub use score_descriptors::ScoreDescriptor;
+pub use search::Search;
+pub use search_parameters::{EngineVersion, SearchModification, SearchParameters};
+pub use search_statistics::{ScoreDistribution, SearchStatistics};
+pub use search_status::{SearchStatus, TransitionError};
+pub use sequence_tag::SequenceTag;
+pub use spectra_page::SpectraPage;
+pub use spectrum::{BuildError, ColumnError, Identification, RowError, Spectrum, SpectrumBuilder};
+pub use spectrum_index::SpectrumIndex;
+pub use spectrum_ref::{LazySpectrum, SpectrumLoader, SpectrumRef};
+pub use summaries::{MsRunSummary, SearchSummary, SpectrumSummary};
+pub use table_view::{ColumnValues, TableView};
+pub use transfer::TransferredIdentification;
+pub use versioning::ContentHash;
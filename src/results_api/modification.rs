@@ -0,0 +1,392 @@
+//! Post translational modifications (PTMs) of PSMs and their Unimod annotation.
+//!
+//! Comet reports modifications in the `modified_peptide` column as mass deltas in brackets following
+//! the modified residue, e.g. `PEPM[15.9949]IDEK`. Terminal modifications follow `n` and `c`,
+//! e.g. `n[42.0106]PEPTIDE`.
+
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::results_api::{
+    psm_columns,
+    spectrum::{ColumnError, RowError},
+    Identification,
+};
+use crate::tolerance::Tolerance;
+
+/// Default tolerance for matching mass deltas to Unimod entries,
+/// Comet reports mass deltas with four decimals
+pub const UNIMOD_TOLERANCE: Tolerance = Tolerance::Da(0.001);
+
+/// Unimod entry, see <https://www.unimod.org>
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UnimodEntry {
+    pub accession: u32,
+    pub name: &'static str,
+    pub mass_delta: f64,
+}
+
+/// Frequently searched modifications with their monoisotopic mass delta
+pub const UNIMOD_ENTRIES: &[UnimodEntry] = &[
+    UnimodEntry {
+        accession: 1,
+        name: "Acetyl",
+        mass_delta: 42.010_565,
+    },
+    UnimodEntry {
+        accession: 2,
+        name: "Amidated",
+        mass_delta: -0.984_016,
+    },
+    UnimodEntry {
+        accession: 4,
+        name: "Carbamidomethyl",
+        mass_delta: 57.021_464,
+    },
+    UnimodEntry {
+        accession: 5,
+        name: "Carbamyl",
+        mass_delta: 43.005_814,
+    },
+    UnimodEntry {
+        accession: 7,
+        name: "Deamidated",
+        mass_delta: 0.984_016,
+    },
+    UnimodEntry {
+        accession: 21,
+        name: "Phospho",
+        mass_delta: 79.966_331,
+    },
+    UnimodEntry {
+        accession: 27,
+        name: "Glu->pyro-Glu",
+        mass_delta: -18.010_565,
+    },
+    UnimodEntry {
+        accession: 28,
+        name: "Gln->pyro-Glu",
+        mass_delta: -17.026_549,
+    },
+    UnimodEntry {
+        accession: 34,
+        name: "Methyl",
+        mass_delta: 14.015_650,
+    },
+    UnimodEntry {
+        accession: 35,
+        name: "Oxidation",
+        mass_delta: 15.994_915,
+    },
+    UnimodEntry {
+        accession: 36,
+        name: "Dimethyl",
+        mass_delta: 28.031_300,
+    },
+    UnimodEntry {
+        accession: 121,
+        name: "GG",
+        mass_delta: 114.042_927,
+    },
+    UnimodEntry {
+        accession: 122,
+        name: "Formyl",
+        mass_delta: 27.994_915,
+    },
+    UnimodEntry {
+        accession: 214,
+        name: "iTRAQ4plex",
+        mass_delta: 144.102_063,
+    },
+    UnimodEntry {
+        accession: 259,
+        name: "Label:13C(6)15N(2)",
+        mass_delta: 8.014_199,
+    },
+    UnimodEntry {
+        accession: 267,
+        name: "Label:13C(6)15N(4)",
+        mass_delta: 10.008_269,
+    },
+    UnimodEntry {
+        accession: 737,
+        name: "TMT6plex",
+        mass_delta: 229.162_932,
+    },
+];
+
+/// Returns the Unimod entry closest to the given mass delta within the tolerance
+///
+/// # Arguments
+/// * `mass_delta` - Monoisotopic mass delta in Dalton
+/// * `tolerance` - Tolerance around the mass deltas of the entries
+///
+pub fn unimod_by_mass(mass_delta: f64, tolerance: Tolerance) -> Option<&'static UnimodEntry> {
+    UNIMOD_ENTRIES
+        .iter()
+        .filter(|entry| tolerance.contains(mass_delta, entry.mass_delta))
+        .min_by(|a, b| {
+            (a.mass_delta - mass_delta)
+                .abs()
+                .total_cmp(&(b.mass_delta - mass_delta).abs())
+        })
+}
+
+/// Returns the Unimod entry with the given name (case insensitive)
+///
+pub fn unimod_by_name(name: &str) -> Option<&'static UnimodEntry> {
+    UNIMOD_ENTRIES
+        .iter()
+        .find(|entry| entry.name.eq_ignore_ascii_case(name))
+}
+
+/// Returns the Unimod entry with the given accession
+///
+pub fn unimod_by_accession(accession: u32) -> Option<&'static UnimodEntry> {
+    UNIMOD_ENTRIES
+        .iter()
+        .find(|entry| entry.accession == accession)
+}
+
+/// Error when parsing modifications
+///
+#[derive(Debug, thiserror::Error)]
+pub enum ModificationError {
+    #[error("unexpected character {1:?} in modified sequence `{0}`")]
+    UnexpectedCharacter(String, char),
+    #[error("invalid mass delta `{1}` in modified sequence `{0}`")]
+    InvalidMass(String, String),
+    #[error("unclosed bracket in modified sequence `{0}`")]
+    UnclosedBracket(String),
+    #[error("modification without residue in modified sequence `{0}`")]
+    MissingResidue(String),
+    #[error(transparent)]
+    Row(#[from] RowError),
+    #[error(transparent)]
+    Column(#[from] ColumnError),
+}
+
+/// Location of a modification within the peptide
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ModificationPosition {
+    NTerm,
+    /// 0-based position of the modified residue
+    Residue(usize),
+    CTerm,
+}
+
+/// Modification of a peptide, e.g. the oxidation of a methionine
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Modification {
+    position: ModificationPosition,
+    residue: Option<char>,
+    mass_delta: f64,
+    #[serde(default)]
+    unimod_accession: Option<u32>,
+    #[serde(default)]
+    name: Option<String>,
+}
+
+impl Modification {
+    /// Creates a new modification without Unimod annotation
+    ///
+    /// # Arguments
+    /// * `position` - Location within the peptide
+    /// * `residue` - Modified amino acid, None for terminal modifications
+    /// * `mass_delta` - Monoisotopic mass delta in Dalton
+    ///
+    pub fn new(position: ModificationPosition, residue: Option<char>, mass_delta: f64) -> Self {
+        Self {
+            position,
+            residue,
+            mass_delta,
+            unimod_accession: None,
+            name: None,
+        }
+    }
+
+    /// Sets the Unimod accession and name of the modification
+    ///
+    pub fn with_unimod(mut self, accession: u32, name: String) -> Self {
+        self.unimod_accession = Some(accession);
+        self.name = Some(name);
+        self
+    }
+
+    /// Sets the name of the modification, e.g. for modifications from other vocabularies than Unimod
+    ///
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Annotates the modification with the closest Unimod entry within the tolerance, if any
+    ///
+    pub fn annotate_unimod(self, tolerance: Tolerance) -> Self {
+        match unimod_by_mass(self.mass_delta, tolerance) {
+            Some(entry) => self.with_unimod(entry.accession, entry.name.to_string()),
+            None => self,
+        }
+    }
+
+    pub fn get_position(&self) -> ModificationPosition {
+        self.position
+    }
+
+    pub fn get_residue(&self) -> Option<char> {
+        self.residue
+    }
+
+    pub fn get_mass_delta(&self) -> f64 {
+        self.mass_delta
+    }
+
+    pub fn get_unimod_accession(&self) -> Option<u32> {
+        self.unimod_accession
+    }
+
+    /// Unimod accession in the form `UNIMOD:<accession>`
+    ///
+    pub fn get_unimod_id(&self) -> Option<String> {
+        self.unimod_accession
+            .map(|accession| format!("UNIMOD:{}", accession))
+    }
+
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+/// Parses a modified sequence as written by Comet, e.g. `n[42.0106]PEPM[15.9949]IDEK`, into the plain sequence
+/// and its modifications. Flanking residues (`K.PEPTIDE.R`) are removed.
+/// Modifications are annotated with Unimod entries matching within `UNIMOD_TOLERANCE`.
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::modification::{
+///     parse_modified_sequence, ModificationPosition,
+/// };
+///
+/// let (sequence, modifications) = parse_modified_sequence("n[42.0106]PEPM[15.9949]IDEK").unwrap();
+/// assert_eq!(sequence, "PEPMIDEK");
+/// assert_eq!(modifications[0].get_position(), ModificationPosition::NTerm);
+/// assert_eq!(modifications[0].get_name(), Some("Acetyl"));
+/// assert_eq!(modifications[1].get_position(), ModificationPosition::Residue(3));
+/// assert_eq!(modifications[1].get_residue(), Some('M'));
+/// assert_eq!(modifications[1].get_unimod_id().as_deref(), Some("UNIMOD:35"));
+/// ```
+///
+pub fn parse_modified_sequence(
+    modified_sequence: &str,
+) -> Result<(String, Vec<Modification>), ModificationError> {
+    let inner = strip_flanks(modified_sequence);
+    let mut sequence = String::with_capacity(inner.len());
+    let mut modifications = Vec::new();
+    let mut chars = inner.chars().peekable();
+    let mut c_term = false;
+    while let Some(character) = chars.next() {
+        let position = match character {
+            'n' if sequence.is_empty() && modifications.is_empty() => ModificationPosition::NTerm,
+            'c' => {
+                c_term = true;
+                ModificationPosition::CTerm
+            }
+            '[' => {
+                return Err(ModificationError::MissingResidue(
+                    modified_sequence.to_string(),
+                ))
+            }
+            residue if residue.is_ascii_uppercase() && !c_term => {
+                sequence.push(residue);
+                ModificationPosition::Residue(sequence.len() - 1)
+            }
+            other => {
+                return Err(ModificationError::UnexpectedCharacter(
+                    modified_sequence.to_string(),
+                    other,
+                ))
+            }
+        };
+        let is_residue = matches!(position, ModificationPosition::Residue(_));
+        if chars.peek() != Some(&'[') {
+            if !is_residue {
+                return Err(ModificationError::UnexpectedCharacter(
+                    modified_sequence.to_string(),
+                    character,
+                ));
+            }
+            continue;
+        }
+        while chars.peek() == Some(&'[') {
+            chars.next();
+            let mut mass = String::new();
+            let mut is_closed = false;
+            for character in chars.by_ref() {
+                if character == ']' {
+                    is_closed = true;
+                    break;
+                }
+                mass.push(character);
+            }
+            if !is_closed {
+                return Err(ModificationError::UnclosedBracket(
+                    modified_sequence.to_string(),
+                ));
+            }
+            let mass_delta = mass.trim().parse::<f64>().map_err(|_| {
+                ModificationError::InvalidMass(modified_sequence.to_string(), mass.clone())
+            })?;
+            let residue = match is_residue {
+                true => Some(character),
+                false => None,
+            };
+            modifications.push(
+                Modification::new(position, residue, mass_delta).annotate_unimod(UNIMOD_TOLERANCE),
+            );
+        }
+    }
+    Ok((sequence, modifications))
+}
+
+/// Removes flanking residues, e.g. `K.PEPTIDE.R` => `PEPTIDE`
+///
+fn strip_flanks(modified_sequence: &str) -> &str {
+    let bytes = modified_sequence.as_bytes();
+    let mut start = 0;
+    let mut end = bytes.len();
+    if end >= 2 && bytes[1] == b'.' {
+        start = 2;
+    }
+    if end >= start + 2 && bytes[end - 2] == b'.' {
+        end -= 2;
+    }
+    &modified_sequence[start..end]
+}
+
+impl Identification {
+    /// Parses the modifications of each PSM from the `modified_peptide` column, in order of the PSM rows.
+    /// Returns None if the identification has no PSMs.
+    ///
+    pub fn get_psm_modifications(
+        &self,
+    ) -> Result<Option<Vec<Vec<Modification>>>, ModificationError> {
+        let rows = match self.iter_psm_rows_select(&[psm_columns::MODIFIED_PEPTIDE])? {
+            Some(rows) => rows,
+            None => return Ok(None),
+        };
+        rows.map(
+            |row| match row.get::<Option<&str>>(psm_columns::MODIFIED_PEPTIDE)? {
+                Some(modified_sequence) => Ok(parse_modified_sequence(modified_sequence)?.1),
+                None => Ok(Vec::new()),
+            },
+        )
+        .collect::<Result<Vec<_>, ModificationError>>()
+        .map(Some)
+    }
+}
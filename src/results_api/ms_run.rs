@@ -1,38 +1,219 @@
+// std imports
+use std::collections::HashSet;
+
+// internal imports
+use crate::results_api::{
+    merge::MergeError, MsRunName, ProcessingError, SearchUuid, SpectrumId, SpectrumIndex,
+    SCHEMA_VERSION,
+};
+
 /// Represents an MS run and its content (e.g. the spectra that are part of the MS run)
-#[derive(serde::Serialize, serde::Deserialize)]
+/// and the errors of spectra which could not be processed
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct MsRun {
-    search_uuid: String,
-    ms_run_name: String,
-    spectra_ids: Vec<String>,
+    #[serde(default)]
+    schema_version: u32,
+    #[serde(default)]
+    revision: u64,
+    search_uuid: SearchUuid,
+    ms_run_name: MsRunName,
+    spectra_ids: Vec<SpectrumId>,
+    #[serde(default)]
+    errors: Vec<ProcessingError>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    spectrum_index: Option<SpectrumIndex>,
 }
 
 impl MsRun {
-    pub fn new(search_uuid: String, ms_run_name: String, spectra_ids: Vec<String>) -> Self {
+    pub fn new(
+        search_uuid: SearchUuid,
+        ms_run_name: MsRunName,
+        spectra_ids: Vec<SpectrumId>,
+    ) -> Self {
         Self {
+            schema_version: SCHEMA_VERSION,
+            revision: 0,
             search_uuid,
             ms_run_name,
             spectra_ids,
+            errors: Vec::new(),
+            spectrum_index: None,
         }
     }
 
+    /// Placeholder MS run with the nil search UUID and the name `UNNAMED`
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::MsRun;
+    ///
+    /// let json = serde_json::to_string(&MsRun::empty()).unwrap();
+    /// let ms_run: MsRun = serde_json::from_str(&json).unwrap();
+    /// assert!(ms_run == MsRun::empty());
+    /// ```
+    ///
     pub fn empty() -> Self {
         Self {
-            search_uuid: String::new(),
-            ms_run_name: String::new(),
+            schema_version: SCHEMA_VERSION,
+            revision: 0,
+            search_uuid: SearchUuid::nil(),
+            ms_run_name: MsRunName::unnamed(),
             spectra_ids: Vec::with_capacity(0),
+            errors: Vec::new(),
+            spectrum_index: None,
         }
     }
 
-    pub fn get_search_uuid(&self) -> &str {
+    /// Version of the layout the MS run was created with, 0 for payloads from before versioning
+    ///
+    pub fn get_schema_version(&self) -> u32 {
+        self.schema_version
+    }
+
+    /// Sets the revision, a counter incremented by the producer on each change (see `ContentHash`)
+    ///
+    pub fn with_revision(mut self, revision: u64) -> Self {
+        self.revision = revision;
+        self
+    }
+
+    pub fn get_revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Increments and returns the revision, e.g. after updating the MS run
+    ///
+    pub fn increment_revision(&mut self) -> u64 {
+        self.revision += 1;
+        self.revision
+    }
+
+    pub fn get_search_uuid(&self) -> &SearchUuid {
         &self.search_uuid
     }
 
-    pub fn get_ms_run(&self) -> &str {
+    pub fn get_ms_run(&self) -> &MsRunName {
         &self.ms_run_name
     }
 
-    pub fn get_spectra_ids(&self) -> &Vec<String> {
+    pub fn get_spectra_ids(&self) -> &Vec<SpectrumId> {
         &self.spectra_ids
     }
 
-}
\ No newline at end of file
+    /// Registers a spectrum, e.g. once a worker has finished it.
+    /// Does not check for duplicates, see `deduplicate`.
+    ///
+    pub fn push_spectrum_id(&mut self, spectrum_id: SpectrumId) {
+        self.spectra_ids.push(spectrum_id);
+    }
+
+    /// Registers multiple spectra, does not check for duplicates, see `deduplicate`
+    ///
+    pub fn extend_spectra_ids(&mut self, spectra_ids: impl IntoIterator<Item = SpectrumId>) {
+        self.spectra_ids.extend(spectra_ids);
+    }
+
+    /// Removes duplicate spectrum IDs, e.g. of spectra registered twice by retried workers.
+    /// The first occurrence is kept, so the order of the spectra stays the same.
+    ///
+    /// Returns the number of removed IDs
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::MsRun;
+    ///
+    /// let mut ms_run = MsRun::new(
+    ///     "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+    ///     "run".parse().unwrap(),
+    ///     Vec::new(),
+    /// );
+    /// ms_run.push_spectrum_id("scan=2".parse().unwrap());
+    /// ms_run.extend_spectra_ids(["scan=1".parse().unwrap(), "scan=2".parse().unwrap()]);
+    ///
+    /// assert_eq!(ms_run.deduplicate(), 1);
+    /// assert_eq!(ms_run.get_spectra_ids().len(), 2);
+    /// assert_eq!(ms_run.get_spectra_ids()[0].as_str(), "scan=2");
+    /// ```
+    ///
+    pub fn deduplicate(&mut self) -> usize {
+        let num_spectra = self.spectra_ids.len();
+        let mut seen: HashSet<SpectrumId> = HashSet::with_capacity(num_spectra);
+        self.spectra_ids
+            .retain(|spectrum_id| seen.insert(spectrum_id.clone()));
+        num_spectra - self.spectra_ids.len()
+    }
+
+    /// Assigns the MS run to the given search
+    ///
+    pub fn with_search_uuid(mut self, search_uuid: SearchUuid) -> Self {
+        self.search_uuid = search_uuid;
+        self
+    }
+
+    /// Attaches the locations of the spectra within the input files
+    ///
+    pub fn with_spectrum_index(mut self, spectrum_index: SpectrumIndex) -> Self {
+        self.spectrum_index = Some(spectrum_index);
+        self
+    }
+
+    pub fn get_spectrum_index(&self) -> &Option<SpectrumIndex> {
+        &self.spectrum_index
+    }
+
+    /// Attaches an error, e.g. of a spectrum that could not be searched
+    ///
+    pub fn with_error(mut self, error: ProcessingError) -> Self {
+        self.errors.push(error);
+        self
+    }
+
+    pub fn add_error(&mut self, error: ProcessingError) {
+        self.errors.push(error);
+    }
+
+    pub fn get_errors(&self) -> &Vec<ProcessingError> {
+        &self.errors
+    }
+
+    /// Returns the errors of the given spectrum
+    ///
+    pub fn get_spectrum_errors<'a>(
+        &'a self,
+        spectrum_id: &'a str,
+    ) -> impl Iterator<Item = &'a ProcessingError> + 'a {
+        self.errors
+            .iter()
+            .filter(move |error| error.get_spectrum_id() == Some(spectrum_id))
+    }
+
+    /// Adds the spectrum IDs of the other MS run (e.g. a rerun) which are not yet part of this one.
+    /// The spectrum index of the other MS run is only taken if this one has none.
+    /// The revision is set above the revisions of both MS runs.
+    /// Fails if the MS runs have different names.
+    ///
+    pub fn merge(&mut self, other: MsRun) -> Result<(), MergeError> {
+        if self.ms_run_name != other.ms_run_name {
+            return Err(MergeError::MsRunMismatch(
+                self.ms_run_name.to_string(),
+                other.ms_run_name.into_inner(),
+            ));
+        }
+        self.revision = self.revision.max(other.revision) + 1;
+        let mut spectra_ids: HashSet<SpectrumId> = self.spectra_ids.iter().cloned().collect();
+        for spectrum_id in other.spectra_ids {
+            if spectra_ids.insert(spectrum_id.clone()) {
+                self.spectra_ids.push(spectrum_id);
+            }
+        }
+        let mut errors: HashSet<ProcessingError> = self.errors.iter().cloned().collect();
+        for error in other.errors {
+            if errors.insert(error.clone()) {
+                self.errors.push(error);
+            }
+        }
+        if self.spectrum_index.is_none() {
+            self.spectrum_index = other.spectrum_index;
+        }
+        Ok(())
+    }
+}
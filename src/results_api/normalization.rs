@@ -0,0 +1,32 @@
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+/// Normalization of spectrum intensities
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Normalization {
+    /// Divides by the total ion current (sum of all intensities)
+    Tic,
+    /// Divides by the highest intensity
+    BasePeak,
+    /// Square root of the intensities, dampening dominant peaks
+    Sqrt,
+}
+
+impl Normalization {
+    /// Returns the normalized intensities.
+    /// Intensities are returned unchanged if the TIC or base peak is zero or not finite.
+    ///
+    pub fn apply(&self, intensity: &[f64]) -> Vec<f64> {
+        let divisor = match self {
+            Self::Tic => intensity.iter().sum(),
+            Self::BasePeak => intensity.iter().copied().fold(0.0, f64::max),
+            Self::Sqrt => return intensity.iter().map(|value| value.sqrt()).collect(),
+        };
+        if divisor == 0.0 || !divisor.is_finite() {
+            return intensity.to_vec();
+        }
+        intensity.iter().map(|value| value / divisor).collect()
+    }
+}
@@ -0,0 +1,53 @@
+// std imports
+use std::collections::HashMap;
+
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+/// Filter for thinning out the peaks of a spectrum
+///
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PeakFilter {
+    /// Keeps the `n` most intense peaks in each m/z window of `window_da` Dalton, windows start at 0 m/z
+    TopNPerWindow { n: usize, window_da: f64 },
+    /// Keeps peaks with at least the given fraction (0.0 - 1.0) of the base peak intensity
+    RelativeIntensity(f64),
+}
+
+impl PeakFilter {
+    /// Returns the indexes of the peaks to keep in ascending order
+    ///
+    pub fn apply(&self, mz: &[f64], intensity: &[f64]) -> Vec<usize> {
+        match self {
+            Self::TopNPerWindow { n, window_da } => {
+                if *window_da <= 0.0 || !window_da.is_finite() {
+                    return (0..mz.len()).collect();
+                }
+                let mut windows: HashMap<i64, Vec<usize>> = HashMap::new();
+                for (idx, peak_mz) in mz.iter().enumerate() {
+                    windows
+                        .entry((peak_mz / window_da).floor() as i64)
+                        .or_default()
+                        .push(idx);
+                }
+                let mut keep = windows
+                    .into_values()
+                    .flat_map(|mut window| {
+                        window.sort_by(|a, b| intensity[*b].total_cmp(&intensity[*a]));
+                        window.truncate(*n);
+                        window
+                    })
+                    .collect::<Vec<usize>>();
+                keep.sort_unstable();
+                keep
+            }
+            Self::RelativeIntensity(fraction) => {
+                let threshold = intensity.iter().copied().fold(0.0, f64::max) * fraction;
+                (0..intensity.len())
+                    .filter(|idx| intensity[*idx] >= threshold)
+                    .collect()
+            }
+        }
+    }
+}
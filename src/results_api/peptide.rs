@@ -0,0 +1,166 @@
+// std imports
+use std::collections::{HashMap, HashSet};
+
+// internal imports
+use crate::results_api::{
+    psm_columns,
+    spectrum::{is_top_ranked, ColumnError},
+    Spectrum,
+};
+
+/// Represents a peptide and the PSMs supporting it across the spectra of a search
+/// (e.g. best score, spectral count, observed charge states)
+///
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Peptide {
+    search_uuid: String,
+    sequence: String,
+    best_score: f64,
+    spectrum_ids: Vec<String>,
+    charges: Vec<u8>,
+    retention_times: Vec<f64>,
+}
+
+impl Peptide {
+    pub fn new(
+        search_uuid: String,
+        sequence: String,
+        best_score: f64,
+        spectrum_ids: Vec<String>,
+        charges: Vec<u8>,
+        retention_times: Vec<f64>,
+    ) -> Self {
+        Self {
+            search_uuid,
+            sequence,
+            best_score,
+            spectrum_ids,
+            charges,
+            retention_times,
+        }
+    }
+
+    pub fn get_search_uuid(&self) -> &str {
+        &self.search_uuid
+    }
+
+    pub fn get_sequence(&self) -> &str {
+        &self.sequence
+    }
+
+    /// Best (highest) xcorr of all PSMs
+    ///
+    pub fn get_best_score(&self) -> f64 {
+        self.best_score
+    }
+
+    /// IDs of the spectra identifying the peptide. Spectrum IDs are only unique within an MS run,
+    /// so the same ID may occur once per MS run.
+    ///
+    pub fn get_spectrum_ids(&self) -> &Vec<String> {
+        &self.spectrum_ids
+    }
+
+    /// Number of spectra identifying the peptide
+    ///
+    pub fn get_spectral_count(&self) -> usize {
+        self.spectrum_ids.len()
+    }
+
+    /// Observed charge states, sorted ascending
+    ///
+    pub fn get_charges(&self) -> &Vec<u8> {
+        &self.charges
+    }
+
+    /// Retention times (seconds) of the PSMs, if reported by the search engine
+    ///
+    pub fn get_retention_times(&self) -> &Vec<f64> {
+        &self.retention_times
+    }
+
+    /// Aggregates the top ranked PSMs of the given spectra into peptides, ordered by best score descending.
+    /// If the PSMs have no rank column, all PSMs are considered. Spectra are identified by MS run and spectrum ID.
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::{Identification, Peptide, Precursor, Spectrum};
+    /// use polars::prelude::*;
+    ///
+    /// let search_uuid = "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c";
+    /// let spectrum = |ms_run: &str| {
+    ///     let psms = df!("plain_peptide" => &["PEPTIDE"], "xcorr" => &[2.5]).unwrap();
+    ///     Spectrum::new(
+    ///         search_uuid.parse().unwrap(),
+    ///         ms_run.parse().unwrap(),
+    ///         "scan=1".parse().unwrap(),
+    ///         vec![100.0],
+    ///         vec![1.0],
+    ///         vec![Identification::new(None, Some(psms), Precursor::new(400.7, 2))],
+    ///     )
+    /// };
+    /// let peptides = Peptide::from_spectra(search_uuid, &[spectrum("run_1"), spectrum("run_2")]).unwrap();
+    /// assert_eq!(peptides[0].get_spectral_count(), 2);
+    /// ```
+    ///
+    /// # Arguments
+    /// * `search_uuid` - UUID of the search the spectra belong to
+    /// * `spectra` - Spectra to aggregate
+    ///
+    pub fn from_spectra(search_uuid: &str, spectra: &[Spectrum]) -> Result<Vec<Self>, ColumnError> {
+        let mut peptides: HashMap<String, Self> = HashMap::new();
+        let mut counted_spectra = HashSet::new();
+        for spectrum in spectra {
+            for identification in spectrum.get_identifications() {
+                let rows = match identification.iter_psm_rows() {
+                    Some(rows) => rows,
+                    None => continue,
+                };
+                for row in rows {
+                    if !is_top_ranked(&row)? {
+                        continue;
+                    }
+                    let sequence = row.get_str(psm_columns::PLAIN_PEPTIDE)?;
+                    let score = row.get_f64(psm_columns::XCORR)?;
+                    let retention_time = row
+                        .get::<Option<f64>>(psm_columns::RETENTION_TIME_SEC)
+                        .unwrap_or(None);
+
+                    let peptide = peptides
+                        .entry(sequence.to_string())
+                        .or_insert_with(|| Self {
+                            search_uuid: search_uuid.to_string(),
+                            sequence: sequence.to_string(),
+                            best_score: f64::NEG_INFINITY,
+                            spectrum_ids: Vec::new(),
+                            charges: Vec::new(),
+                            retention_times: Vec::new(),
+                        });
+                    peptide.best_score = peptide.best_score.max(score);
+                    if counted_spectra.insert((
+                        peptide.sequence.clone(),
+                        spectrum.get_ms_run(),
+                        spectrum.get_spectra_id(),
+                    )) {
+                        peptide
+                            .spectrum_ids
+                            .push(spectrum.get_spectra_id().to_string());
+                    }
+                    if !peptide.charges.contains(&identification.get_charge()) {
+                        peptide.charges.push(identification.get_charge());
+                    }
+                    if let Some(retention_time) = retention_time {
+                        peptide.retention_times.push(retention_time);
+                    }
+                }
+            }
+        }
+
+        let mut peptides = peptides.into_values().collect::<Vec<Self>>();
+        for peptide in peptides.iter_mut() {
+            peptide.charges.sort_unstable();
+        }
+        peptides.sort_by(|a, b| b.best_score.total_cmp(&a.best_score));
+        Ok(peptides)
+    }
+}
@@ -0,0 +1,115 @@
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::mass::ISOTOPE_SPACING;
+
+/// Precursor ion of an identification and the isolation window it was selected with
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Precursor {
+    mz: f64,
+    charge: u8,
+    #[serde(default)]
+    intensity: Option<f64>,
+    #[serde(default)]
+    isolation_window_lower_offset: Option<f64>,
+    #[serde(default)]
+    isolation_window_upper_offset: Option<f64>,
+    #[serde(default)]
+    monoisotopic_correction: Option<i8>,
+}
+
+impl Precursor {
+    pub fn new(mz: f64, charge: u8) -> Self {
+        Self {
+            mz,
+            charge,
+            intensity: None,
+            isolation_window_lower_offset: None,
+            isolation_window_upper_offset: None,
+            monoisotopic_correction: None,
+        }
+    }
+
+    pub fn with_intensity(mut self, intensity: Option<f64>) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Sets the isolation window as offsets in m/z below and above the precursor m/z
+    ///
+    pub fn with_isolation_window(
+        mut self,
+        lower_offset: Option<f64>,
+        upper_offset: Option<f64>,
+    ) -> Self {
+        self.isolation_window_lower_offset = lower_offset;
+        self.isolation_window_upper_offset = upper_offset;
+        self
+    }
+
+    /// Sets the number of isotope peaks the selected peak is above the monoisotopic peak, e.g. 1 if the 13C peak was selected
+    ///
+    pub fn with_monoisotopic_correction(mut self, monoisotopic_correction: Option<i8>) -> Self {
+        self.monoisotopic_correction = monoisotopic_correction;
+        self
+    }
+
+    /// Selected m/z
+    ///
+    pub fn get_mz(&self) -> f64 {
+        self.mz
+    }
+
+    pub fn get_charge(&self) -> u8 {
+        self.charge
+    }
+
+    pub fn get_intensity(&self) -> &Option<f64> {
+        &self.intensity
+    }
+
+    pub fn get_isolation_window_lower_offset(&self) -> &Option<f64> {
+        &self.isolation_window_lower_offset
+    }
+
+    pub fn get_isolation_window_upper_offset(&self) -> &Option<f64> {
+        &self.isolation_window_upper_offset
+    }
+
+    pub fn get_monoisotopic_correction(&self) -> &Option<i8> {
+        &self.monoisotopic_correction
+    }
+
+    /// Lower and upper m/z of the isolation window, if both offsets are known
+    ///
+    pub fn get_isolation_window(&self) -> Option<(f64, f64)> {
+        Some((
+            self.mz - self.isolation_window_lower_offset?,
+            self.mz + self.isolation_window_upper_offset?,
+        ))
+    }
+
+    /// m/z of the monoisotopic peak, i.e. the selected m/z shifted by the monoisotopic correction
+    ///
+    pub fn get_monoisotopic_mz(&self) -> f64 {
+        match self.monoisotopic_correction {
+            Some(correction) if self.charge > 0 => {
+                self.mz - correction as f64 * ISOTOPE_SPACING / self.charge as f64
+            }
+            _ => self.mz,
+        }
+    }
+}
+
+/// Precursor as serialized before the introduction of `Precursor`, where it was the bare m/z
+/// and the charge a field of the identification
+///
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(crate) enum PrecursorPayload {
+    Mz(f64),
+    Precursor(Precursor),
+}
@@ -0,0 +1,124 @@
+// std imports
+use std::fmt;
+
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+/// Stage of the processing pipeline an error occurred in
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessingStage {
+    /// Reading and converting the MS run
+    Preparation,
+    Indexing,
+    Searching,
+    PostProcessing,
+}
+
+/// Kind of a processing error, e.g. to group failed spectra
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessingErrorKind {
+    /// Spectrum or MS run could not be parsed
+    InvalidInput,
+    /// No peptide within the precursor tolerance
+    NoCandidates,
+    /// Search engine exited with an error
+    EngineFailure,
+    Timeout,
+    OutOfMemory,
+    Other,
+}
+
+impl fmt::Display for ProcessingErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidInput => write!(f, "invalid input"),
+            Self::NoCandidates => write!(f, "no candidates"),
+            Self::EngineFailure => write!(f, "engine failure"),
+            Self::Timeout => write!(f, "timeout"),
+            Self::OutOfMemory => write!(f, "out of memory"),
+            Self::Other => write!(f, "other"),
+        }
+    }
+}
+
+/// Reason why a spectrum (or the whole MS run or search if no spectrum is given) produced no identifications
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::{
+///     MsRun, ProcessingError, ProcessingErrorKind, ProcessingStage,
+/// };
+///
+/// let ms_run = MsRun::new("4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(), "run".parse().unwrap(), vec!["scan=1".parse().unwrap()])
+///     .with_error(ProcessingError::new(
+///         Some("scan=1".to_string()),
+///         ProcessingStage::Searching,
+///         ProcessingErrorKind::Timeout,
+///         "search engine did not finish within 60 s".to_string(),
+///         true,
+///     ));
+/// assert_eq!(ms_run.get_spectrum_errors("scan=1").count(), 1);
+/// assert!(ms_run.get_errors()[0].is_retryable());
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProcessingError {
+    spectrum_id: Option<String>,
+    stage: ProcessingStage,
+    kind: ProcessingErrorKind,
+    message: String,
+    retryable: bool,
+}
+
+impl ProcessingError {
+    /// Creates a new processing error
+    ///
+    /// # Arguments
+    /// * `spectrum_id` - Spectrum the error occurred for, `None` if it concerns the whole MS run or search
+    /// * `stage` - Stage the error occurred in
+    /// * `kind` - Kind of the error
+    /// * `message` - Human readable description
+    /// * `retryable` - True if processing the spectrum again may succeed, e.g. after a timeout
+    ///
+    pub fn new(
+        spectrum_id: Option<String>,
+        stage: ProcessingStage,
+        kind: ProcessingErrorKind,
+        message: String,
+        retryable: bool,
+    ) -> Self {
+        Self {
+            spectrum_id,
+            stage,
+            kind,
+            message,
+            retryable,
+        }
+    }
+
+    pub fn get_spectrum_id(&self) -> Option<&str> {
+        self.spectrum_id.as_deref()
+    }
+
+    pub fn get_stage(&self) -> ProcessingStage {
+        self.stage
+    }
+
+    pub fn get_kind(&self) -> ProcessingErrorKind {
+        self.kind
+    }
+
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.retryable
+    }
+}
@@ -0,0 +1,258 @@
+//! Partial responses of spectra, so clients can request e.g. only the peaks or only the identifications
+
+// std imports
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::results_api::{
+    Identification, MsRunName, SearchUuid, SequenceTag, Spectrum, SpectrumId,
+};
+
+/// Error when parsing fields of a projection
+///
+#[derive(Debug, thiserror::Error)]
+pub enum ProjectionError {
+    #[error("unknown field `{0}`, expected one of metadata, peaks, identifications, psms, goodnesses, sequence_tags, digest")]
+    UnknownField(String),
+}
+
+/// Selectable part of a spectrum. The search UUID, MS run and spectrum ID are always part of a projection.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    /// Retention time, ion mobility, MS level, scan number and revision
+    Metadata,
+    /// m/z and intensity values
+    Peaks,
+    /// Identifications with PSMs and goodness of fit tables
+    Identifications,
+    /// Identifications with PSMs only
+    Psms,
+    /// Identifications with goodness of fit tables only
+    Goodnesses,
+    SequenceTags,
+    /// Payload digest
+    Digest,
+}
+
+impl Field {
+    /// All fields, projecting on them keeps the whole spectrum
+    pub const ALL: [Field; 7] = [
+        Field::Metadata,
+        Field::Peaks,
+        Field::Identifications,
+        Field::Psms,
+        Field::Goodnesses,
+        Field::SequenceTags,
+        Field::Digest,
+    ];
+
+    /// Parses a comma separated list of fields, e.g. the `fields` query parameter `peaks,psms`
+    ///
+    pub fn parse_list(fields: &str) -> Result<Vec<Field>, ProjectionError> {
+        fields
+            .split(',')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .map(Field::from_str)
+            .collect()
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Metadata => write!(f, "metadata"),
+            Self::Peaks => write!(f, "peaks"),
+            Self::Identifications => write!(f, "identifications"),
+            Self::Psms => write!(f, "psms"),
+            Self::Goodnesses => write!(f, "goodnesses"),
+            Self::SequenceTags => write!(f, "sequence_tags"),
+            Self::Digest => write!(f, "digest"),
+        }
+    }
+}
+
+impl FromStr for Field {
+    type Err = ProjectionError;
+
+    fn from_str(field: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|candidate| candidate.to_string() == field)
+            .ok_or_else(|| ProjectionError::UnknownField(field.to_string()))
+    }
+}
+
+/// Trimmed spectrum containing only the requested fields, unrequested fields are omitted when serialized.
+/// Peaks and tables are shared with the spectrum, so projecting does not copy them.
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::{Field, Identification, Precursor, Spectrum};
+/// use polars::prelude::*;
+///
+/// let psms = df!("plain_peptide" => &["PEPTIDE"], "xcorr" => &[2.5]).unwrap();
+/// let goodnesses = df!("name" => &["kolmogorov_smirnov"], "value" => &[0.1]).unwrap();
+/// let spectrum = Spectrum::new(
+///     "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+///     "run".parse().unwrap(),
+///     "scan=1".parse().unwrap(),
+///     vec![100.0, 200.0],
+///     vec![1.0, 2.0],
+///     vec![Identification::new(Some(goodnesses), Some(psms), Precursor::new(400.7, 2))],
+/// )
+/// .with_retention_time(Some(1200.0));
+///
+/// let fields = Field::parse_list("peaks,psms").unwrap();
+/// let projection = spectrum.project(&fields);
+/// assert_eq!(projection.get_mz().as_deref(), Some(&[100.0, 200.0][..]));
+/// let identifications = projection.get_identifications().as_ref().unwrap();
+/// assert!(identifications[0].get_psms().is_some());
+/// assert!(identifications[0].get_goodnesses().is_none());
+///
+/// let json = serde_json::to_string(&spectrum.project(&[Field::Metadata])).unwrap();
+/// assert!(json.contains("\"retention_time\":1200.0"));
+/// assert!(!json.contains("mz"));
+/// assert!(Field::parse_list("peaks,spectra").is_err());
+/// ```
+///
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SpectrumProjection {
+    fields: Vec<Field>,
+    search_uuid: SearchUuid,
+    ms_run_name: MsRunName,
+    spectrum_id: SpectrumId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retention_time: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ion_mobility: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ms_level: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scan_number: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    revision: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mz: Option<Arc<[f64]>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    intensity: Option<Arc<[f64]>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    identifications: Option<Vec<Identification>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sequence_tags: Option<Vec<SequenceTag>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    payload_digest: Option<String>,
+}
+
+impl SpectrumProjection {
+    /// Requested fields
+    ///
+    pub fn get_fields(&self) -> &Vec<Field> {
+        &self.fields
+    }
+
+    pub fn get_search_uuid(&self) -> &SearchUuid {
+        &self.search_uuid
+    }
+
+    pub fn get_ms_run(&self) -> &MsRunName {
+        &self.ms_run_name
+    }
+
+    pub fn get_spectra_id(&self) -> &SpectrumId {
+        &self.spectrum_id
+    }
+
+    pub fn get_retention_time(&self) -> &Option<f64> {
+        &self.retention_time
+    }
+
+    pub fn get_ion_mobility(&self) -> &Option<f64> {
+        &self.ion_mobility
+    }
+
+    pub fn get_ms_level(&self) -> &Option<u8> {
+        &self.ms_level
+    }
+
+    pub fn get_scan_number(&self) -> &Option<u32> {
+        &self.scan_number
+    }
+
+    pub fn get_revision(&self) -> &Option<u64> {
+        &self.revision
+    }
+
+    pub fn get_mz(&self) -> &Option<Arc<[f64]>> {
+        &self.mz
+    }
+
+    pub fn get_intensity(&self) -> &Option<Arc<[f64]>> {
+        &self.intensity
+    }
+
+    pub fn get_identifications(&self) -> &Option<Vec<Identification>> {
+        &self.identifications
+    }
+
+    pub fn get_sequence_tags(&self) -> &Option<Vec<SequenceTag>> {
+        &self.sequence_tags
+    }
+
+    pub fn get_payload_digest(&self) -> &Option<String> {
+        &self.payload_digest
+    }
+}
+
+impl Spectrum {
+    /// Projects the spectrum on the given fields, e.g. for partial responses of the API
+    ///
+    /// # Arguments
+    /// * `fields` - Fields to keep, see `Field::parse_list` for parsing them from a query parameter
+    ///
+    pub fn project(&self, fields: &[Field]) -> SpectrumProjection {
+        let has = |field: Field| fields.contains(&field);
+        let metadata = has(Field::Metadata);
+        let peaks = has(Field::Peaks);
+        let psms = has(Field::Identifications) || has(Field::Psms);
+        let goodnesses = has(Field::Identifications) || has(Field::Goodnesses);
+        let mut unique_fields: Vec<Field> = Vec::with_capacity(fields.len());
+        for field in fields {
+            if !unique_fields.contains(field) {
+                unique_fields.push(*field);
+            }
+        }
+        SpectrumProjection {
+            fields: unique_fields,
+            search_uuid: self.get_search_uuid().clone(),
+            ms_run_name: self.get_ms_run().clone(),
+            spectrum_id: self.get_spectra_id().clone(),
+            retention_time: self.get_retention_time().filter(|_| metadata),
+            ion_mobility: self.get_ion_mobility().filter(|_| metadata),
+            ms_level: self.get_ms_level().filter(|_| metadata),
+            scan_number: self.get_scan_number().filter(|_| metadata),
+            revision: Some(self.get_revision()).filter(|_| metadata),
+            mz: Some(self.get_mz_shared().clone()).filter(|_| peaks),
+            intensity: Some(self.get_intensity_shared().clone()).filter(|_| peaks),
+            identifications: (psms || goodnesses).then(|| {
+                self.get_identifications()
+                    .iter()
+                    .map(|identification| identification.select_tables(psms, goodnesses))
+                    .collect()
+            }),
+            sequence_tags: has(Field::SequenceTags).then(|| self.get_sequence_tags().clone()),
+            payload_digest: self
+                .get_payload_digest()
+                .clone()
+                .filter(|_| has(Field::Digest)),
+        }
+    }
+}
@@ -0,0 +1,233 @@
+// std imports
+use std::collections::{BTreeMap, BTreeSet};
+
+// internal imports
+use crate::results_api::{
+    psm_columns,
+    spectrum::{is_top_ranked, ColumnError},
+    Spectrum,
+};
+
+/// Represents a protein and the peptides identifying it
+///
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Protein {
+    accession: String,
+    peptides: Vec<String>,
+    unique_peptides: Vec<String>,
+}
+
+impl Protein {
+    pub fn new(accession: String, peptides: Vec<String>, unique_peptides: Vec<String>) -> Self {
+        Self {
+            accession,
+            peptides,
+            unique_peptides,
+        }
+    }
+
+    pub fn get_accession(&self) -> &str {
+        &self.accession
+    }
+
+    /// All peptides (plain sequences) identifying the protein
+    ///
+    pub fn get_peptides(&self) -> &Vec<String> {
+        &self.peptides
+    }
+
+    /// Peptides which are not shared with other proteins
+    ///
+    pub fn get_unique_peptides(&self) -> &Vec<String> {
+        &self.unique_peptides
+    }
+
+    /// Collects the proteins from the top ranked PSMs of the given spectra, ordered by accession.
+    ///
+    pub fn from_spectra(spectra: &[Spectrum]) -> Result<Vec<Self>, ColumnError> {
+        let protein_peptides = collect_protein_peptides(spectra)?;
+        let mut peptide_protein_count: BTreeMap<&str, usize> = BTreeMap::new();
+        for peptides in protein_peptides.values() {
+            for peptide in peptides {
+                *peptide_protein_count.entry(peptide.as_str()).or_default() += 1;
+            }
+        }
+        Ok(protein_peptides
+            .iter()
+            .map(|(accession, peptides)| Self {
+                accession: accession.clone(),
+                peptides: peptides.iter().cloned().collect(),
+                unique_peptides: peptides
+                    .iter()
+                    .filter(|peptide| peptide_protein_count[peptide.as_str()] == 1)
+                    .cloned()
+                    .collect(),
+            })
+            .collect())
+    }
+}
+
+/// Group of proteins which cannot be distinguished by their peptides.
+/// The group leader is the first accession in alphabetical order.
+///
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProteinGroup {
+    accessions: Vec<String>,
+    peptides: Vec<String>,
+    unique_peptides: Vec<String>,
+}
+
+impl ProteinGroup {
+    pub fn new(
+        accessions: Vec<String>,
+        peptides: Vec<String>,
+        unique_peptides: Vec<String>,
+    ) -> Self {
+        Self {
+            accessions,
+            peptides,
+            unique_peptides,
+        }
+    }
+
+    /// Accession of the group leader
+    ///
+    pub fn get_leader(&self) -> &str {
+        &self.accessions[0]
+    }
+
+    /// Accessions of all proteins in the group, starting with the leader
+    ///
+    pub fn get_accessions(&self) -> &Vec<String> {
+        &self.accessions
+    }
+
+    /// All peptides (plain sequences) identifying the group
+    ///
+    pub fn get_peptides(&self) -> &Vec<String> {
+        &self.peptides
+    }
+
+    /// Peptides which are not shared with other groups
+    ///
+    pub fn get_unique_peptides(&self) -> &Vec<String> {
+        &self.unique_peptides
+    }
+
+    /// Peptides which are shared with other groups
+    ///
+    pub fn get_shared_peptides(&self) -> Vec<&String> {
+        self.peptides
+            .iter()
+            .filter(|peptide| !self.unique_peptides.contains(peptide))
+            .collect()
+    }
+
+    /// Infers the protein groups from the top ranked PSMs of the given spectra using parsimony:
+    /// 1. Proteins with identical peptide sets are grouped
+    /// 2. Groups are selected greedily by number of not yet explained peptides until all peptides are explained
+    ///
+    /// Groups are returned in order of selection.
+    ///
+    pub fn infer(spectra: &[Spectrum]) -> Result<Vec<Self>, ColumnError> {
+        let protein_peptides = collect_protein_peptides(spectra)?;
+
+        // group indistinguishable proteins, accessions are sorted as they come from a BTreeMap
+        let mut candidates: BTreeMap<BTreeSet<String>, Vec<String>> = BTreeMap::new();
+        for (accession, peptides) in protein_peptides.into_iter() {
+            candidates.entry(peptides).or_default().push(accession);
+        }
+        let mut candidates = candidates
+            .into_iter()
+            .map(|(peptides, accessions)| (accessions, peptides))
+            .collect::<Vec<(Vec<String>, BTreeSet<String>)>>();
+
+        // greedy set cover
+        let mut unexplained = candidates
+            .iter()
+            .flat_map(|(_, peptides)| peptides.iter().cloned())
+            .collect::<BTreeSet<String>>();
+        let mut selected: Vec<(Vec<String>, BTreeSet<String>)> = Vec::new();
+        while !unexplained.is_empty() {
+            let best_idx = candidates
+                .iter()
+                .enumerate()
+                .max_by(|(_, (a_acc, a_pep)), (_, (b_acc, b_pep))| {
+                    let a_new = a_pep.intersection(&unexplained).count();
+                    let b_new = b_pep.intersection(&unexplained).count();
+                    // prefer more new peptides, then more peptides in total, then the alphabetically first leader
+                    a_new
+                        .cmp(&b_new)
+                        .then(a_pep.len().cmp(&b_pep.len()))
+                        .then(b_acc[0].cmp(&a_acc[0]))
+                })
+                .map(|(idx, _)| idx);
+            let best_idx = match best_idx {
+                Some(idx) => idx,
+                None => break,
+            };
+            let candidate = candidates.swap_remove(best_idx);
+            for peptide in candidate.1.iter() {
+                unexplained.remove(peptide);
+            }
+            selected.push(candidate);
+        }
+
+        let mut peptide_group_count: BTreeMap<&str, usize> = BTreeMap::new();
+        for (_, peptides) in selected.iter() {
+            for peptide in peptides {
+                *peptide_group_count.entry(peptide.as_str()).or_default() += 1;
+            }
+        }
+        Ok(selected
+            .iter()
+            .map(|(accessions, peptides)| Self {
+                accessions: accessions.clone(),
+                peptides: peptides.iter().cloned().collect(),
+                unique_peptides: peptides
+                    .iter()
+                    .filter(|peptide| peptide_group_count[peptide.as_str()] == 1)
+                    .cloned()
+                    .collect(),
+            })
+            .collect())
+    }
+}
+
+/// Collects the protein accessions and their peptides (plain sequences) from the top ranked PSMs of the given spectra
+///
+fn collect_protein_peptides(
+    spectra: &[Spectrum],
+) -> Result<BTreeMap<String, BTreeSet<String>>, ColumnError> {
+    let mut protein_peptides: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for spectrum in spectra {
+        for identification in spectrum.get_identifications() {
+            let rows = match identification.iter_psm_rows() {
+                Some(rows) => rows,
+                None => continue,
+            };
+            for row in rows {
+                if !is_top_ranked(&row)? {
+                    continue;
+                }
+                let sequence = row.get_str(psm_columns::PLAIN_PEPTIDE)?;
+                let proteins = match row.get::<Option<&str>>(psm_columns::PROTEIN)? {
+                    Some(proteins) => proteins,
+                    None => continue,
+                };
+                for accession in proteins.split(',').map(|acc| acc.trim()) {
+                    if accession.is_empty() {
+                        continue;
+                    }
+                    protein_peptides
+                        .entry(accession.to_string())
+                        .or_default()
+                        .insert(sequence.to_string());
+                }
+            }
+        }
+    }
+    Ok(protein_peptides)
+}
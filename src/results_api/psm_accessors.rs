@@ -0,0 +1,129 @@
+//! Typed access to whole PSM columns, for hot paths where iterating rows (see `Identification::iter_psm_rows`)
+//! and converting `AnyValue`s is too slow. Columns are borrowed, not cast, so they need the expected data type.
+//! Use `Identification::conform_schema` to cast the columns of other sources beforehand.
+
+// 3rd party imports
+use polars::prelude::*;
+
+// internal imports
+use crate::results_api::{psm_columns, ColumnError, Identification, RowError};
+
+impl Identification {
+    /// Scores of the original search engine (xcorr, for Comet)
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::{Identification, Precursor};
+    /// use polars::prelude::*;
+    ///
+    /// let psms = df!(
+    ///     "plain_peptide" => &["PEPTIDE", "PEPTIDES"],
+    ///     "xcorr" => &[2.5, 1.1],
+    ///     "charge" => &[2u32, 2]
+    /// )
+    /// .unwrap();
+    /// let identification = Identification::new(None, Some(psms), Precursor::new(400.7, 2));
+    ///
+    /// let scores = identification.scores().unwrap().unwrap();
+    /// assert_eq!(scores.max(), Some(2.5));
+    /// assert_eq!(
+    ///     identification.sequences().unwrap().collect::<Vec<_>>(),
+    ///     vec!["PEPTIDE", "PEPTIDES"]
+    /// );
+    /// // charges are not stored as floats
+    /// assert!(identification.psm_f64("charge").is_err());
+    /// ```
+    ///
+    pub fn scores(&self) -> Result<Option<&Float64Chunked>, ColumnError> {
+        self.psm_f64(psm_columns::XCORR)
+    }
+
+    /// Charges of the PSMs
+    ///
+    pub fn charges(&self) -> Result<Option<&UInt32Chunked>, ColumnError> {
+        self.psm_u32(psm_columns::CHARGE)
+    }
+
+    /// Peptide sequences without modifications, nulls as empty strings. Empty if there are no PSMs.
+    ///
+    pub fn sequences(&self) -> Result<impl Iterator<Item = &str>, ColumnError> {
+        self.psm_strs(psm_columns::PLAIN_PEPTIDE)
+    }
+
+    /// Comma separated protein accessions, nulls as empty strings. Empty if there are no PSMs.
+    ///
+    pub fn proteins(&self) -> Result<impl Iterator<Item = &str>, ColumnError> {
+        self.psm_strs(psm_columns::PROTEIN)
+    }
+
+    /// Decoy flags (see `mark_decoys`)
+    ///
+    pub fn is_decoy(&self) -> Result<Option<&BooleanChunked>, ColumnError> {
+        self.psm_bool(psm_columns::IS_DECOY)
+    }
+
+    /// The given `Float64` PSM column, None if there are no PSMs
+    ///
+    /// # Arguments
+    /// * `column` - Name of the PSM column
+    ///
+    pub fn psm_f64(&self, column: &str) -> Result<Option<&Float64Chunked>, ColumnError> {
+        self.psm_typed(column, "f64", |series| series.f64())
+    }
+
+    /// The given `UInt32` PSM column, None if there are no PSMs
+    ///
+    /// # Arguments
+    /// * `column` - Name of the PSM column
+    ///
+    pub fn psm_u32(&self, column: &str) -> Result<Option<&UInt32Chunked>, ColumnError> {
+        self.psm_typed(column, "u32", |series| series.u32())
+    }
+
+    /// The given `Utf8` PSM column, None if there are no PSMs
+    ///
+    /// # Arguments
+    /// * `column` - Name of the PSM column
+    ///
+    pub fn psm_utf8(&self, column: &str) -> Result<Option<&Utf8Chunked>, ColumnError> {
+        self.psm_typed(column, "&str", |series| series.utf8())
+    }
+
+    /// The given `Boolean` PSM column, None if there are no PSMs
+    ///
+    /// # Arguments
+    /// * `column` - Name of the PSM column
+    ///
+    pub fn psm_bool(&self, column: &str) -> Result<Option<&BooleanChunked>, ColumnError> {
+        self.psm_typed(column, "bool", |series| series.bool())
+    }
+
+    fn psm_strs(&self, column: &str) -> Result<impl Iterator<Item = &str>, ColumnError> {
+        Ok(self
+            .psm_utf8(column)?
+            .into_iter()
+            .flat_map(|values| values.into_iter())
+            .map(Option::unwrap_or_default))
+    }
+
+    fn psm_typed<'a, T>(
+        &'a self,
+        column: &str,
+        expected: &'static str,
+        downcast: impl Fn(&'a Series) -> PolarsResult<&'a T>,
+    ) -> Result<Option<&'a T>, ColumnError> {
+        let psms = match self.get_psms() {
+            Some(psms) => psms,
+            None => return Ok(None),
+        };
+        let series = psms
+            .column(column)
+            .map_err(|_| RowError::UnknownColumn(column.to_string()))?;
+        downcast(series)
+            .map(Some)
+            .map_err(|_| ColumnError::TypeMismatch {
+                column: column.to_string(),
+                expected,
+                found: series.dtype().to_string(),
+            })
+    }
+}
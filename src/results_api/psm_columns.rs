@@ -0,0 +1,55 @@
+//! Names of the PSM columns written by Comet, which is used by MaCcoyS as search engine.
+
+/// Spectrum scan number
+pub const SCAN: &str = "scan";
+/// Rank of the PSM within the spectrum
+pub const RANK: &str = "num";
+/// Charge state
+pub const CHARGE: &str = "charge";
+/// Experimental neutral mass of the precursor
+pub const EXP_NEUTRAL_MASS: &str = "exp_neutral_mass";
+/// Calculated neutral mass of the peptide
+pub const CALC_NEUTRAL_MASS: &str = "calc_neutral_mass";
+/// Expectation value
+pub const E_VALUE: &str = "e-value";
+/// Cross correlation score
+pub const XCORR: &str = "xcorr";
+/// Delta CN
+pub const DELTA_CN: &str = "delta_cn";
+/// Preliminary score
+pub const SP_SCORE: &str = "sp_score";
+/// Number of matched fragment ions
+pub const IONS_MATCHED: &str = "ions_matched";
+/// Total number of theoretical fragment ions
+pub const IONS_TOTAL: &str = "ions_total";
+/// Peptide sequence without modifications
+pub const PLAIN_PEPTIDE: &str = "plain_peptide";
+/// Peptide sequence with modifications
+pub const MODIFIED_PEPTIDE: &str = "modified_peptide";
+/// Amino acid preceeding the peptide
+pub const PREV_AA: &str = "prev_aa";
+/// Amino acid following the peptide
+pub const NEXT_AA: &str = "next_aa";
+/// Comma separated protein accessions
+pub const PROTEIN: &str = "protein";
+/// Number of proteins containing the peptide
+pub const PROTEIN_COUNT: &str = "protein_count";
+/// Comma separated modifications in the form `<position>_<type>_<mass>`
+pub const MODIFICATIONS: &str = "modifications";
+/// Retention time in seconds
+pub const RETENTION_TIME_SEC: &str = "retention_time_sec";
+
+// Columns added by this crate
+
+/// Flag if the PSM is a decoy
+pub const IS_DECOY: &str = "is_decoy";
+/// False discovery rate at the score of the PSM
+pub const FDR: &str = "fdr";
+/// Minimal FDR at which the PSM is accepted
+pub const Q_VALUE: &str = "q_value";
+/// Posterior error probability of the PSM
+pub const PEP: &str = "pep";
+/// Theoretical neutral mass of the peptide including modifications
+pub const CALC_MASS: &str = "calc_mass";
+/// Error of the observed precursor mass in parts per million
+pub const PPM_ERROR: &str = "ppm_error";
@@ -0,0 +1,206 @@
+//! Contract for the columns and data types of PSM tables.
+//!
+//! Comet versions and readers differ in the data types they produce, e.g. `charge` as `Int64` or `UInt32`
+//! or scores as strings. `Identification::conform_schema` casts the columns to the data types of the contract,
+//! so the typed getters of `Row` work regardless of the source.
+
+// 3rd party imports
+use polars::prelude::*;
+
+// internal imports
+use crate::results_api::{psm_columns, Identification};
+
+/// Error of a PSM table violating the contract
+///
+#[derive(Debug, thiserror::Error)]
+pub enum PsmSchemaError {
+    #[error("PSMs have no column `{0}`")]
+    MissingColumn(String),
+    #[error("column `{column}` of type {actual} cannot be converted to {expected}")]
+    IncompatibleDtype {
+        column: String,
+        expected: DataType,
+        actual: DataType,
+    },
+    #[error(transparent)]
+    Polars(#[from] PolarsError),
+}
+
+/// Expected column of the PSMs
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct PsmColumnSpec {
+    name: String,
+    dtype: DataType,
+    required: bool,
+}
+
+impl PsmColumnSpec {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_dtype(&self) -> &DataType {
+        &self.dtype
+    }
+
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+}
+
+/// Columns and data types PSM tables have to conform to.
+/// Columns not in the schema are kept as they are.
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::{psm_columns, Identification, Precursor};
+/// use maccoys_exchange_entities::results_api::psm_schema::PsmSchema;
+/// use polars::prelude::*;
+///
+/// // e.g. from an older Comet version writing charges as floats and xcorr as string
+/// let psms = df!(
+///     psm_columns::PLAIN_PEPTIDE => &["PEPTIDE"],
+///     psm_columns::PROTEIN => &["sp|P12345|TEST"],
+///     psm_columns::CHARGE => &[2.0],
+///     psm_columns::XCORR => &["2.5"]
+/// )
+/// .unwrap();
+/// let mut identification = Identification::new(None, Some(psms), Precursor::new(400.7, 2));
+///
+/// identification.conform_schema(&PsmSchema::comet()).unwrap();
+/// let psms = identification.get_psms().as_ref().unwrap();
+/// assert_eq!(psms.column(psm_columns::CHARGE).unwrap().dtype(), &DataType::UInt32);
+/// assert_eq!(psms.column(psm_columns::XCORR).unwrap().dtype(), &DataType::Float64);
+///
+/// let schema = PsmSchema::comet().with_required(psm_columns::Q_VALUE, DataType::Float64);
+/// assert!(identification.conform_schema(&schema).is_err());
+/// ```
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PsmSchema {
+    columns: Vec<PsmColumnSpec>,
+}
+
+impl PsmSchema {
+    /// Schema without columns, accepting every table
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds or replaces a column every table needs
+    ///
+    pub fn with_required(self, name: &str, dtype: DataType) -> Self {
+        self.with_column(name, dtype, true)
+    }
+
+    /// Adds or replaces a column which is converted if present
+    ///
+    pub fn with_optional(self, name: &str, dtype: DataType) -> Self {
+        self.with_column(name, dtype, false)
+    }
+
+    fn with_column(mut self, name: &str, dtype: DataType, required: bool) -> Self {
+        self.columns.retain(|column| column.name != name);
+        self.columns.push(PsmColumnSpec {
+            name: name.to_string(),
+            dtype,
+            required,
+        });
+        self
+    }
+
+    /// Columns of Comet and the columns added by this crate, with peptide and protein required
+    /// (see `crate::validate::REQUIRED_PSM_COLUMNS`)
+    ///
+    pub fn comet() -> Self {
+        let mut schema = Self::new()
+            .with_required(psm_columns::PLAIN_PEPTIDE, DataType::Utf8)
+            .with_required(psm_columns::PROTEIN, DataType::Utf8);
+        for column in [
+            psm_columns::SCAN,
+            psm_columns::RANK,
+            psm_columns::CHARGE,
+            psm_columns::IONS_MATCHED,
+            psm_columns::IONS_TOTAL,
+            psm_columns::PROTEIN_COUNT,
+        ] {
+            schema = schema.with_optional(column, DataType::UInt32);
+        }
+        for column in [
+            psm_columns::EXP_NEUTRAL_MASS,
+            psm_columns::CALC_NEUTRAL_MASS,
+            psm_columns::E_VALUE,
+            psm_columns::XCORR,
+            psm_columns::DELTA_CN,
+            psm_columns::SP_SCORE,
+            psm_columns::RETENTION_TIME_SEC,
+            psm_columns::FDR,
+            psm_columns::Q_VALUE,
+            psm_columns::PEP,
+            psm_columns::CALC_MASS,
+            psm_columns::PPM_ERROR,
+        ] {
+            schema = schema.with_optional(column, DataType::Float64);
+        }
+        for column in [
+            psm_columns::MODIFIED_PEPTIDE,
+            psm_columns::PREV_AA,
+            psm_columns::NEXT_AA,
+            psm_columns::MODIFICATIONS,
+        ] {
+            schema = schema.with_optional(column, DataType::Utf8);
+        }
+        schema.with_optional(psm_columns::IS_DECOY, DataType::Boolean)
+    }
+
+    pub fn get_columns(&self) -> &Vec<PsmColumnSpec> {
+        &self.columns
+    }
+
+    /// Checks the PSMs and casts the columns to the data types of the schema.
+    /// Casts are strict, so values which cannot be converted (e.g. `abc` to a float) fail instead of becoming null.
+    ///
+    /// # Arguments
+    /// * `psms` - PSMs to conform
+    ///
+    pub fn conform(&self, mut psms: DataFrame) -> Result<DataFrame, PsmSchemaError> {
+        for spec in self.columns.iter() {
+            let column = match psms.column(&spec.name) {
+                Ok(column) => column,
+                Err(_) if spec.required => {
+                    return Err(PsmSchemaError::MissingColumn(spec.name.clone()))
+                }
+                Err(_) => continue,
+            };
+            if column.dtype() == &spec.dtype {
+                continue;
+            }
+            let converted =
+                column
+                    .strict_cast(&spec.dtype)
+                    .map_err(|_| PsmSchemaError::IncompatibleDtype {
+                        column: spec.name.clone(),
+                        expected: spec.dtype.clone(),
+                        actual: column.dtype().clone(),
+                    })?;
+            psms.with_column(converted)?;
+        }
+        Ok(psms)
+    }
+}
+
+impl Identification {
+    /// Conforms the PSMs to the schema, see `PsmSchema::conform`.
+    /// The PSMs are left untouched on error. Identifications without PSMs always conform.
+    ///
+    /// # Arguments
+    /// * `schema` - Expected columns and data types
+    ///
+    pub fn conform_schema(&mut self, schema: &PsmSchema) -> Result<(), PsmSchemaError> {
+        if let Some(psms) = self.get_psms_mut() {
+            *psms = schema.conform(psms.clone())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,314 @@
+//! Label-free quantification (LFQ) of peptides and proteins across the MS runs of a search.
+//!
+//! Quantities are stored per MS run. Whether they are already normalized is recorded in the
+//! normalization metadata, so consumers can tell raw from normalized values.
+
+// std imports
+use std::collections::BTreeMap;
+
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::results_api::{merge::MergeError, MsRunName};
+
+/// Quantity of a peptide or protein in a single MS run
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RunQuant {
+    /// Apex intensity, e.g. of the extracted ion chromatogram
+    #[serde(default)]
+    intensity: Option<f64>,
+    /// Area under the extracted ion chromatogram
+    #[serde(default)]
+    area: Option<f64>,
+}
+
+impl RunQuant {
+    pub fn new(intensity: Option<f64>, area: Option<f64>) -> Self {
+        Self { intensity, area }
+    }
+
+    pub fn get_intensity(&self) -> Option<f64> {
+        self.intensity
+    }
+
+    pub fn get_area(&self) -> Option<f64> {
+        self.area
+    }
+}
+
+/// Quantities of a peptide (precursor) across MS runs
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PeptideQuant {
+    /// Peptide in ProForma notation, see `crate::proforma::Peptidoform`
+    sequence: String,
+    #[serde(default)]
+    charge: Option<u8>,
+    quantities: BTreeMap<MsRunName, RunQuant>,
+}
+
+impl PeptideQuant {
+    /// Creates a new peptide quantification without quantities
+    ///
+    /// # Arguments
+    /// * `sequence` - Peptide in ProForma notation
+    /// * `charge` - Charge state, None if the quantities are summed over all charge states
+    ///
+    pub fn new(sequence: String, charge: Option<u8>) -> Self {
+        Self {
+            sequence,
+            charge,
+            quantities: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the quantity in the given MS run
+    ///
+    pub fn with_quantity(mut self, ms_run_name: MsRunName, quantity: RunQuant) -> Self {
+        self.quantities.insert(ms_run_name, quantity);
+        self
+    }
+
+    pub fn get_sequence(&self) -> &str {
+        &self.sequence
+    }
+
+    pub fn get_charge(&self) -> Option<u8> {
+        self.charge
+    }
+
+    pub fn get_quantities(&self) -> &BTreeMap<MsRunName, RunQuant> {
+        &self.quantities
+    }
+
+    /// Quantity in the given MS run, None if the peptide was not quantified in it
+    ///
+    pub fn get_quantity(&self, ms_run_name: &str) -> Option<&RunQuant> {
+        self.quantities.get(ms_run_name)
+    }
+}
+
+/// Quantities of a protein (or protein group) across MS runs
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProteinQuant {
+    accession: String,
+    /// Number of peptides the quantities are based on
+    num_peptides: usize,
+    quantities: BTreeMap<MsRunName, RunQuant>,
+}
+
+impl ProteinQuant {
+    /// Creates a new protein quantification without quantities
+    ///
+    /// # Arguments
+    /// * `accession` - Accession of the protein, for protein groups the accession of the leading protein
+    /// * `num_peptides` - Number of peptides the quantities are based on
+    ///
+    pub fn new(accession: String, num_peptides: usize) -> Self {
+        Self {
+            accession,
+            num_peptides,
+            quantities: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the quantity in the given MS run
+    ///
+    pub fn with_quantity(mut self, ms_run_name: MsRunName, quantity: RunQuant) -> Self {
+        self.quantities.insert(ms_run_name, quantity);
+        self
+    }
+
+    pub fn get_accession(&self) -> &str {
+        &self.accession
+    }
+
+    pub fn get_num_peptides(&self) -> usize {
+        self.num_peptides
+    }
+
+    pub fn get_quantities(&self) -> &BTreeMap<MsRunName, RunQuant> {
+        &self.quantities
+    }
+
+    /// Quantity in the given MS run, None if the protein was not quantified in it
+    ///
+    pub fn get_quantity(&self, ms_run_name: &str) -> Option<&RunQuant> {
+        self.quantities.get(ms_run_name)
+    }
+}
+
+/// Method the quantities were normalized with
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum QuantNormalizationMethod {
+    /// Equal summed intensity in each MS run
+    TotalIntensity,
+    /// Equal median intensity in each MS run
+    Median,
+    /// Pairwise peptide ratios as in MaxLFQ (Cox et al. 2014)
+    MaxLfq,
+}
+
+/// Normalization the quantities were processed with
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct QuantNormalization {
+    method: QuantNormalizationMethod,
+    /// Factor the raw quantities of each MS run were multiplied with
+    #[serde(default)]
+    factors: BTreeMap<MsRunName, f64>,
+}
+
+impl QuantNormalization {
+    pub fn new(method: QuantNormalizationMethod) -> Self {
+        Self {
+            method,
+            factors: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the factor the raw quantities of the MS run were multiplied with
+    ///
+    pub fn with_factor(mut self, ms_run_name: MsRunName, factor: f64) -> Self {
+        self.factors.insert(ms_run_name, factor);
+        self
+    }
+
+    pub fn get_method(&self) -> QuantNormalizationMethod {
+        self.method
+    }
+
+    pub fn get_factors(&self) -> &BTreeMap<MsRunName, f64> {
+        &self.factors
+    }
+}
+
+/// Quantification of the peptides and proteins of a search
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::{
+///     PeptideQuant, Quantification, QuantNormalization, QuantNormalizationMethod, RunQuant, Search,
+/// };
+///
+/// let quantification = Quantification::new()
+///     .with_peptide(
+///         PeptideQuant::new("PEPM[Oxidation]IDE".to_string(), Some(2))
+///             .with_quantity("run_a".parse().unwrap(), RunQuant::new(Some(1.2e6), Some(3.4e7))),
+///     )
+///     .with_normalization(
+///         QuantNormalization::new(QuantNormalizationMethod::Median)
+///             .with_factor("run_a".parse().unwrap(), 0.8),
+///     );
+/// let search = Search::new(
+///     "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+///     vec!["run_a".parse().unwrap()],
+/// )
+/// .with_quantification(quantification);
+///
+/// let json = serde_json::to_string(&search).unwrap();
+/// let search: Search = serde_json::from_str(&json).unwrap();
+/// let quantification = search.get_quantification().as_ref().unwrap();
+/// let quantity = quantification.get_peptides()[0].get_quantity("run_a").unwrap();
+/// assert_eq!(quantity.get_area(), Some(3.4e7));
+/// ```
+///
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Quantification {
+    #[serde(default)]
+    peptides: Vec<PeptideQuant>,
+    #[serde(default)]
+    proteins: Vec<ProteinQuant>,
+    /// None if the quantities are not normalized
+    #[serde(default)]
+    normalization: Option<QuantNormalization>,
+}
+
+impl Quantification {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_peptide(mut self, peptide: PeptideQuant) -> Self {
+        self.peptides.push(peptide);
+        self
+    }
+
+    pub fn with_protein(mut self, protein: ProteinQuant) -> Self {
+        self.proteins.push(protein);
+        self
+    }
+
+    pub fn with_normalization(mut self, normalization: QuantNormalization) -> Self {
+        self.normalization = Some(normalization);
+        self
+    }
+
+    pub fn get_peptides(&self) -> &Vec<PeptideQuant> {
+        &self.peptides
+    }
+
+    pub fn get_proteins(&self) -> &Vec<ProteinQuant> {
+        &self.proteins
+    }
+
+    pub fn get_normalization(&self) -> &Option<QuantNormalization> {
+        &self.normalization
+    }
+
+    /// Adds the quantities of the other quantification, e.g. of additional MS runs.
+    /// Peptides with the same sequence and charge and proteins with the same accession are combined,
+    /// quantities of the other quantification replace existing ones of the same MS run.
+    /// Fails if the quantifications were normalized with different methods, leaving this quantification unchanged.
+    ///
+    pub fn merge(&mut self, other: Quantification) -> Result<(), MergeError> {
+        match (&mut self.normalization, other.normalization) {
+            (Some(normalization), Some(other_normalization)) => {
+                if normalization.method != other_normalization.method {
+                    return Err(MergeError::NormalizationMismatch);
+                }
+                normalization.factors.extend(other_normalization.factors);
+            }
+            (None, Some(_)) if !self.peptides.is_empty() || !self.proteins.is_empty() => {
+                return Err(MergeError::NormalizationMismatch)
+            }
+            (None, other_normalization) => self.normalization = other_normalization,
+            (Some(_), None) if !other.peptides.is_empty() || !other.proteins.is_empty() => {
+                return Err(MergeError::NormalizationMismatch)
+            }
+            (Some(_), None) => (),
+        }
+        for peptide in other.peptides {
+            match self.peptides.iter_mut().find(|existing| {
+                existing.sequence == peptide.sequence && existing.charge == peptide.charge
+            }) {
+                Some(existing) => existing.quantities.extend(peptide.quantities),
+                None => self.peptides.push(peptide),
+            }
+        }
+        for protein in other.proteins {
+            match self
+                .proteins
+                .iter_mut()
+                .find(|existing| existing.accession == protein.accession)
+            {
+                Some(existing) => {
+                    existing.num_peptides = existing.num_peptides.max(protein.num_peptides);
+                    existing.quantities.extend(protein.quantities)
+                }
+                None => self.proteins.push(protein),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+//! Metadata of score columns, so generic components (plots, FDR estimation, best PSM selection)
+//! can handle scores of arbitrary engines, not only Comet's.
+//!
+//! Descriptors are registered per identification (see `Identification::register_score_descriptor`).
+//! The Comet columns of `psm_columns` are described by default.
+
+// std imports
+use std::cmp::Ordering;
+
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::results_api::{psm_columns, Identification, ScoreSelector};
+
+/// Describes a score column of the PSMs
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::ScoreDescriptor;
+///
+/// let hyperscore = ScoreDescriptor::new("hyperscore", "X!Tandem:hyperscore", true)
+///     .with_range(Some(0.0), None)
+///     .with_cv_accession("MS:1001331");
+/// assert!(hyperscore.is_better(35.2, 20.1));
+/// assert!(hyperscore.is_in_range(35.2));
+/// assert!(!hyperscore.is_in_range(-1.0));
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ScoreDescriptor {
+    column: String,
+    name: String,
+    higher_is_better: bool,
+    min: Option<f64>,
+    max: Option<f64>,
+    cv_accession: Option<String>,
+}
+
+impl ScoreDescriptor {
+    /// Creates a new descriptor without range and CV term
+    ///
+    /// # Arguments
+    /// * `column` - PSM column of the score
+    /// * `name` - Human readable name, e.g. `Comet:xcorr`
+    /// * `higher_is_better` - True if higher scores are better, e.g. for xcorr
+    ///
+    pub fn new(column: &str, name: &str, higher_is_better: bool) -> Self {
+        Self {
+            column: column.to_string(),
+            name: name.to_string(),
+            higher_is_better,
+            min: None,
+            max: None,
+            cv_accession: None,
+        }
+    }
+
+    /// Sets the inclusive range of valid scores, None for unbounded
+    ///
+    pub fn with_range(mut self, min: Option<f64>, max: Option<f64>) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    /// Sets the PSI-MS CV accession of the score, e.g. `MS:1002252` for Comet's xcorr
+    ///
+    pub fn with_cv_accession(mut self, cv_accession: &str) -> Self {
+        self.cv_accession = Some(cv_accession.to_string());
+        self
+    }
+
+    pub fn get_column(&self) -> &str {
+        &self.column
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_higher_better(&self) -> bool {
+        self.higher_is_better
+    }
+
+    pub fn get_min(&self) -> Option<f64> {
+        self.min
+    }
+
+    pub fn get_max(&self) -> Option<f64> {
+        self.max
+    }
+
+    pub fn get_cv_accession(&self) -> Option<&str> {
+        self.cv_accession.as_deref()
+    }
+
+    /// True if score `a` is better than score `b`
+    ///
+    pub fn is_better(&self, a: f64, b: f64) -> bool {
+        self.compare(a, b) == Ordering::Greater
+    }
+
+    /// Orders the scores from worse to better, e.g. for sorting PSMs so the best comes last.
+    /// NaN is worse than every other score.
+    ///
+    pub fn compare(&self, a: f64, b: f64) -> Ordering {
+        match (a.is_nan(), b.is_nan()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => match self.higher_is_better {
+                true => a.total_cmp(&b),
+                false => b.total_cmp(&a),
+            },
+        }
+    }
+
+    /// True if the score is within the range
+    ///
+    pub fn is_in_range(&self, score: f64) -> bool {
+        self.min.is_none_or(|min| score >= min) && self.max.is_none_or(|max| score <= max)
+    }
+
+    /// Selector for the best PSM by this score, ignoring decoys (see `Spectrum::best_identification`)
+    ///
+    pub fn to_selector(&self) -> ScoreSelector {
+        ScoreSelector::new(&self.column, self.higher_is_better)
+    }
+}
+
+/// Descriptors of the Comet score columns, used for columns without registered descriptor
+///
+pub fn comet_score_descriptors() -> Vec<ScoreDescriptor> {
+    vec![
+        ScoreDescriptor::new(psm_columns::XCORR, "Comet:xcorr", true)
+            .with_cv_accession("MS:1002252"),
+        ScoreDescriptor::new(psm_columns::DELTA_CN, "Comet:deltacn", true)
+            .with_range(Some(0.0), Some(1.0))
+            .with_cv_accession("MS:1002253"),
+        ScoreDescriptor::new(psm_columns::SP_SCORE, "Comet:spscore", true)
+            .with_range(Some(0.0), None)
+            .with_cv_accession("MS:1002255"),
+        ScoreDescriptor::new(psm_columns::E_VALUE, "Comet:expectation value", false)
+            .with_range(Some(0.0), None)
+            .with_cv_accession("MS:1002257"),
+        ScoreDescriptor::new(psm_columns::Q_VALUE, "PSM-level q-value", false)
+            .with_range(Some(0.0), Some(1.0))
+            .with_cv_accession("MS:1002354"),
+        ScoreDescriptor::new(psm_columns::PEP, "posterior error probability", false)
+            .with_range(Some(0.0), Some(1.0))
+            .with_cv_accession("MS:1001493"),
+    ]
+}
+
+impl Identification {
+    /// Descriptor of the given score column, the registered one or the Comet default.
+    /// Returns None for unknown columns.
+    ///
+    /// # Arguments
+    /// * `column` - PSM column of the score
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::{Identification, Precursor, ScoreDescriptor};
+    ///
+    /// let mut identification = Identification::new(None, None, Precursor::new(400.7, 2));
+    /// identification.register_score_descriptor(ScoreDescriptor::new("hyperscore", "X!Tandem:hyperscore", true));
+    ///
+    /// assert!(identification.describe_score("hyperscore").unwrap().is_higher_better());
+    /// assert!(!identification.describe_score("e-value").unwrap().is_higher_better());
+    /// assert!(identification.describe_score("unknown").is_none());
+    /// ```
+    ///
+    pub fn describe_score(&self, column: &str) -> Option<ScoreDescriptor> {
+        self.get_score_descriptors()
+            .iter()
+            .find(|descriptor| descriptor.get_column() == column)
+            .cloned()
+            .or_else(|| {
+                comet_score_descriptors()
+                    .into_iter()
+                    .find(|descriptor| descriptor.get_column() == column)
+            })
+    }
+
+    /// Descriptors of all score columns of the PSMs, in order of the columns.
+    /// Columns without registered or default descriptor are omitted.
+    ///
+    pub fn describe_psm_scores(&self) -> Vec<ScoreDescriptor> {
+        let psms = match self.get_psms() {
+            Some(psms) => psms,
+            None => return Vec::new(),
+        };
+        psms.get_column_names()
+            .into_iter()
+            .filter_map(|column| self.describe_score(column))
+            .collect()
+    }
+}
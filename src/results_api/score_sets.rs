@@ -0,0 +1,156 @@
+//! Named score sets of PSMs, e.g. the scores of rescoring tools like mokapot next to the engine scores.
+//!
+//! Score sets are stored as additional PSM columns named `<score set>:<column>`, so they are persisted
+//! by every storage backend and the original engine columns stay untouched. The engine columns form the
+//! implicit score set `ENGINE_SCORE_SET`.
+
+// std imports
+use std::collections::BTreeSet;
+
+// 3rd party imports
+use anyhow::{bail, Result};
+use polars::prelude::*;
+
+// internal imports
+use crate::results_api::Identification;
+
+/// Name of the implicit score set of the search engine columns
+pub const ENGINE_SCORE_SET: &str = "comet";
+
+/// Separator between score set name and column name
+pub const SCORE_SET_SEPARATOR: char = ':';
+
+/// Name of the PSM column of the given score set and column
+///
+pub fn score_set_column(score_set: &str, column: &str) -> String {
+    format!("{}{}{}", score_set, SCORE_SET_SEPARATOR, column)
+}
+
+impl Identification {
+    /// Adds (or replaces) a named score set, e.g. `maccoys` or `mokapot`.
+    /// The scores need one row per PSM, in order of the PSM rows.
+    /// Fails if there are no PSMs, the number of rows differ or the name is invalid
+    /// (empty, the engine score set or containing the separator).
+    ///
+    /// # Arguments
+    /// * `score_set` - Name of the score set
+    /// * `scores` - Score columns, e.g. `score`, `q_value` and `pep`
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::{Identification, Precursor};
+    /// use polars::prelude::*;
+    ///
+    /// let psms = df!("plain_peptide" => &["PEPTIDE", "PEPTIDES"], "xcorr" => &[2.5, 1.5]).unwrap();
+    /// let mut identification = Identification::new(None, Some(psms), Precursor::new(400.7, 2));
+    /// identification
+    ///     .add_score_set("mokapot", &df!("score" => &[0.8, -0.3], "q_value" => &[0.01, 0.2]).unwrap())
+    ///     .unwrap();
+    ///
+    /// assert_eq!(identification.list_score_sets(), vec!["comet", "mokapot"]);
+    /// let mokapot = identification.select_score_set("mokapot").unwrap().unwrap();
+    /// assert_eq!(mokapot.get_column_names(), vec!["score", "q_value"]);
+    /// let comet = identification.select_score_set("comet").unwrap().unwrap();
+    /// assert_eq!(comet.get_column_names(), vec!["plain_peptide", "xcorr"]);
+    /// ```
+    ///
+    pub fn add_score_set(&mut self, score_set: &str, scores: &DataFrame) -> Result<()> {
+        if score_set.is_empty()
+            || score_set == ENGINE_SCORE_SET
+            || score_set.contains(SCORE_SET_SEPARATOR)
+        {
+            bail!("invalid score set name `{}`", score_set);
+        }
+        let psms = match self.get_psms_mut() {
+            Some(psms) => psms,
+            None => bail!("cannot add score set `{}` without PSMs", score_set),
+        };
+        if scores.height() != psms.height() {
+            bail!(
+                "score set `{}` has {} rows but there are {} PSMs",
+                score_set,
+                scores.height(),
+                psms.height()
+            );
+        }
+        drop_score_set(psms, score_set);
+        for column in scores.get_columns() {
+            let mut column = column.clone();
+            column.rename(&score_set_column(score_set, column.name()));
+            psms.with_column(column)?;
+        }
+        Ok(())
+    }
+
+    /// Removes the score set, does nothing if it does not exist.
+    /// Fails for the engine score set.
+    ///
+    pub fn remove_score_set(&mut self, score_set: &str) -> Result<()> {
+        if score_set == ENGINE_SCORE_SET {
+            bail!("the engine score set cannot be removed");
+        }
+        if let Some(psms) = self.get_psms_mut() {
+            drop_score_set(psms, score_set);
+        }
+        Ok(())
+    }
+
+    /// Names of the score sets, starting with the engine score set. Empty if there are no PSMs.
+    ///
+    pub fn list_score_sets(&self) -> Vec<String> {
+        let psms = match self.get_psms() {
+            Some(psms) => psms,
+            None => return Vec::new(),
+        };
+        let score_sets: BTreeSet<&str> = psms
+            .get_column_names()
+            .into_iter()
+            .filter_map(|column| Some(column.split_once(SCORE_SET_SEPARATOR)?.0))
+            .collect();
+        std::iter::once(ENGINE_SCORE_SET)
+            .chain(score_sets)
+            .map(|score_set| score_set.to_string())
+            .collect()
+    }
+
+    /// Columns of the score set with their plain names, e.g. `score` instead of `mokapot:score`.
+    /// The engine score set consists of all PSM columns which are not part of another score set.
+    /// Returns None if there are no PSMs or the score set does not exist.
+    ///
+    pub fn select_score_set(&self, score_set: &str) -> Result<Option<DataFrame>> {
+        let psms = match self.get_psms() {
+            Some(psms) => psms,
+            None => return Ok(None),
+        };
+        let columns: Vec<Series> = psms
+            .get_columns()
+            .iter()
+            .filter_map(|column| {
+                let name = match column.name().split_once(SCORE_SET_SEPARATOR) {
+                    Some((set, name)) if set == score_set => name,
+                    None if score_set == ENGINE_SCORE_SET => column.name(),
+                    _ => return None,
+                };
+                let mut column = column.clone();
+                column.rename(name);
+                Some(column)
+            })
+            .collect();
+        if columns.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(DataFrame::new(columns)?))
+    }
+}
+
+/// Drops the columns of the score set from the PSMs
+///
+fn drop_score_set(psms: &mut DataFrame, score_set: &str) {
+    let prefix = score_set_column(score_set, "");
+    let columns: Vec<String> = psms
+        .get_column_names()
+        .into_iter()
+        .filter(|column| column.starts_with(&prefix))
+        .map(|column| column.to_string())
+        .collect();
+    *psms = psms.drop_many(&columns);
+}
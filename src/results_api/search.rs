@@ -1,28 +1,268 @@
+// std imports
+use std::collections::HashSet;
+
+// internal imports
+use crate::results_api::{
+    merge::MergeError, ExchangeConfig, MsRunName, ProcessingError, Quantification,
+    SearchParameters, SearchUuid, TransferredIdentification, SCHEMA_VERSION,
+};
+
 /// Represents a search and it content (e.g. the ms runs that are part of the search)
-/// 
-#[derive(serde::Serialize, serde::Deserialize)]
+///
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Search {
-    search_uuid: String,
-    ms_run_names: Vec<String>,
+    #[serde(default)]
+    schema_version: u32,
+    #[serde(default)]
+    revision: u64,
+    search_uuid: SearchUuid,
+    ms_run_names: Vec<MsRunName>,
+    #[serde(default)]
+    parameters: Option<SearchParameters>,
+    #[serde(default)]
+    errors: Vec<ProcessingError>,
+    #[serde(default)]
+    quantification: Option<Quantification>,
+    #[serde(default)]
+    config: ExchangeConfig,
+    #[serde(default)]
+    transferred_identifications: Vec<TransferredIdentification>,
 }
 
 impl Search {
-    pub fn new(search_uuid: String, ms_run_names: Vec<String>) -> Self {
-        Self { search_uuid, ms_run_names }
+    pub fn new(search_uuid: SearchUuid, ms_run_names: Vec<MsRunName>) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            revision: 0,
+            search_uuid,
+            ms_run_names,
+            parameters: None,
+            errors: Vec::new(),
+            quantification: None,
+            config: ExchangeConfig::default(),
+            transferred_identifications: Vec::new(),
+        }
     }
 
     pub fn empty() -> Self {
         Self {
-            search_uuid: String::new(),
+            schema_version: SCHEMA_VERSION,
+            revision: 0,
+            search_uuid: SearchUuid::nil(),
             ms_run_names: Vec::with_capacity(0),
+            parameters: None,
+            errors: Vec::new(),
+            quantification: None,
+            config: ExchangeConfig::default(),
+            transferred_identifications: Vec::new(),
         }
     }
 
-    pub fn get_search_uuid(&self) -> &str {
+    /// Version of the layout the search was created with, 0 for payloads from before versioning
+    ///
+    pub fn get_schema_version(&self) -> u32 {
+        self.schema_version
+    }
+
+    /// Sets the revision, a counter incremented by the producer on each change (see `ContentHash`)
+    ///
+    pub fn with_revision(mut self, revision: u64) -> Self {
+        self.revision = revision;
+        self
+    }
+
+    pub fn get_revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Increments and returns the revision, e.g. after updating the search
+    ///
+    pub fn increment_revision(&mut self) -> u64 {
+        self.revision += 1;
+        self.revision
+    }
+
+    pub fn get_search_uuid(&self) -> &SearchUuid {
         &self.search_uuid
     }
 
-    pub fn get_ms_run_names(&self) -> &Vec<String> {
+    pub fn get_ms_run_names(&self) -> &Vec<MsRunName> {
         &self.ms_run_names
     }
-}
\ No newline at end of file
+
+    /// Attaches the parameters the search was conducted with
+    ///
+    pub fn with_parameters(mut self, parameters: SearchParameters) -> Self {
+        self.parameters = Some(parameters);
+        self
+    }
+
+    pub fn get_parameters(&self) -> &Option<SearchParameters> {
+        &self.parameters
+    }
+
+    /// Attaches an error, e.g. of a failed stage
+    ///
+    pub fn with_error(mut self, error: ProcessingError) -> Self {
+        self.errors.push(error);
+        self
+    }
+
+    pub fn add_error(&mut self, error: ProcessingError) {
+        self.errors.push(error);
+    }
+
+    pub fn get_errors(&self) -> &Vec<ProcessingError> {
+        &self.errors
+    }
+
+    /// Attaches the quantification of the peptides and proteins
+    ///
+    pub fn with_quantification(mut self, quantification: Quantification) -> Self {
+        self.quantification = Some(quantification);
+        self
+    }
+
+    pub fn get_quantification(&self) -> &Option<Quantification> {
+        &self.quantification
+    }
+
+    /// Attaches the constants shared by the tools processing the search
+    ///
+    pub fn with_config(mut self, config: ExchangeConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn get_config(&self) -> &ExchangeConfig {
+        &self.config
+    }
+
+    /// Attaches identifications transferred between the MS runs of the search (match-between-runs)
+    ///
+    pub fn with_transferred_identifications(
+        mut self,
+        transferred_identifications: Vec<TransferredIdentification>,
+    ) -> Self {
+        self.transferred_identifications = transferred_identifications;
+        self
+    }
+
+    pub fn add_transferred_identification(&mut self, transferred: TransferredIdentification) {
+        self.transferred_identifications.push(transferred);
+    }
+
+    pub fn get_transferred_identifications(&self) -> &Vec<TransferredIdentification> {
+        &self.transferred_identifications
+    }
+
+    /// Merges the other search (e.g. a search of additional MS runs or a rerun) into this one, keeping this UUID.
+    /// MS runs with the same name are considered reruns and listed once.
+    /// The revision is set above the revisions of both searches.
+    /// Fails if both searches have parameters and they differ, the configs differ or their quantifications cannot be merged
+    /// (see `Quantification::merge`), leaving this search unchanged.
+    ///
+    /// The MS runs and spectra of both searches can be combined with `merge::merge_ms_runs` and `merge::merge_spectra`.
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::{
+    ///     ExchangeConfig, PeptideQuant, QuantNormalization, QuantNormalizationMethod, Quantification, Search,
+    ///     SearchParameters,
+    /// };
+    ///
+    /// let search = |ms_run: &str| Search::new("4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(), vec![ms_run.parse().unwrap()]);
+    /// let parameters = SearchParameters::new("human.fasta".to_string(), "trypsin".to_string(), 2);
+    /// let mut merged = search("run_1").with_quantification(
+    ///     Quantification::new().with_peptide(PeptideQuant::new("PEPTIDE".to_string(), Some(2))),
+    /// );
+    /// let unchanged = merged.clone();
+    ///
+    /// // different configs
+    /// let other = search("run_2")
+    ///     .with_parameters(parameters.clone())
+    ///     .with_config(ExchangeConfig::default().with_decoy_prefix("REV_"));
+    /// assert!(merged.merge(other).is_err());
+    /// assert!(merged == unchanged);
+    ///
+    /// // quantifications normalized differently
+    /// let other = search("run_2")
+    ///     .with_parameters(parameters.clone())
+    ///     .with_quantification(Quantification::new().with_normalization(QuantNormalization::new(QuantNormalizationMethod::Median)))
+    ///     .with_revision(4);
+    /// assert!(merged.merge(other).is_err());
+    /// assert!(merged == unchanged);
+    ///
+    /// merged.merge(search("run_2").with_parameters(parameters).with_revision(4)).unwrap();
+    /// assert_eq!(merged.get_ms_run_names().len(), 2);
+    /// assert_eq!(merged.get_revision(), 5);
+    /// assert!(merged.get_parameters().is_some());
+    /// ```
+    ///
+    pub fn merge(&mut self, other: Search) -> Result<(), MergeError> {
+        if let (Some(parameters), Some(other_parameters)) = (&self.parameters, &other.parameters) {
+            if parameters != other_parameters {
+                return Err(MergeError::ParameterMismatch);
+            }
+        }
+        if self.config != other.config {
+            return Err(MergeError::ConfigMismatch);
+        }
+        // `Quantification::merge` fails before changing anything, so the quantifications are merged
+        // first and nothing else is changed if they are incompatible
+        match (&mut self.quantification, other.quantification) {
+            (Some(quantification), Some(other_quantification)) => {
+                quantification.merge(other_quantification)?
+            }
+            (None, other_quantification) => self.quantification = other_quantification,
+            _ => (),
+        }
+        if self.parameters.is_none() {
+            self.parameters = other.parameters;
+        }
+        self.revision = self.revision.max(other.revision) + 1;
+        let mut ms_run_names: HashSet<MsRunName> = self.ms_run_names.iter().cloned().collect();
+        for ms_run_name in other.ms_run_names {
+            if ms_run_names.insert(ms_run_name.clone()) {
+                self.ms_run_names.push(ms_run_name);
+            }
+        }
+        let mut errors: HashSet<ProcessingError> = self.errors.iter().cloned().collect();
+        for error in other.errors {
+            if errors.insert(error.clone()) {
+                self.errors.push(error);
+            }
+        }
+        // transferred identifications contain floats and are not hashable, so they are only compared
+        // in full if their donor spectrum, peptide and acceptor run are already known
+        let mut transfer_keys: HashSet<TransferKey> = self
+            .transferred_identifications
+            .iter()
+            .map(transfer_key)
+            .collect();
+        for transferred in other.transferred_identifications {
+            if transfer_keys.insert(transfer_key(&transferred))
+                || !self.transferred_identifications.contains(&transferred)
+            {
+                self.transferred_identifications.push(transferred);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Search UUID, MS run and spectrum ID of the donor, peptide, charge and acceptor run of a transferred identification
+///
+type TransferKey = (String, String, String, String, u8, MsRunName);
+
+fn transfer_key(transferred: &TransferredIdentification) -> TransferKey {
+    let donor = transferred.get_donor();
+    (
+        donor.get_search_uuid().to_string(),
+        donor.get_ms_run().to_string(),
+        donor.get_spectra_id().to_string(),
+        transferred.get_plain_peptide().to_string(),
+        transferred.get_charge(),
+        transferred.get_acceptor_ms_run().clone(),
+    )
+}
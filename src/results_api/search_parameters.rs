@@ -0,0 +1,157 @@
+// internal imports
+use crate::tolerance::Tolerance;
+
+/// Modification considered by the search engine
+///
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SearchModification {
+    name: String,
+    residues: String,
+    mass_delta: f64,
+}
+
+impl SearchModification {
+    /// # Arguments
+    /// * `name` - Name of the modification, e.g. `Oxidation`
+    /// * `residues` - Modified amino acids, e.g. `M`, with `n`/`c` for peptide termini
+    /// * `mass_delta` - Monoisotopic mass delta in Dalton
+    ///
+    pub fn new(name: String, residues: String, mass_delta: f64) -> Self {
+        Self {
+            name,
+            residues,
+            mass_delta,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_residues(&self) -> &str {
+        &self.residues
+    }
+
+    pub fn get_mass_delta(&self) -> f64 {
+        self.mass_delta
+    }
+}
+
+/// Name and version of a software used during the search
+///
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EngineVersion {
+    name: String,
+    version: String,
+}
+
+impl EngineVersion {
+    pub fn new(name: String, version: String) -> Self {
+        Self { name, version }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_version(&self) -> &str {
+        &self.version
+    }
+}
+
+/// Parameters the search was conducted with
+///
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SearchParameters {
+    fasta_path: String,
+    fasta_hash: Option<String>,
+    enzyme: String,
+    missed_cleavages: u8,
+    precursor_tolerance: Option<Tolerance>,
+    fragment_tolerance: Option<Tolerance>,
+    fixed_modifications: Vec<SearchModification>,
+    variable_modifications: Vec<SearchModification>,
+    engine_versions: Vec<EngineVersion>,
+}
+
+impl SearchParameters {
+    pub fn new(fasta_path: String, enzyme: String, missed_cleavages: u8) -> Self {
+        Self {
+            fasta_path,
+            enzyme,
+            missed_cleavages,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_fasta_hash(mut self, fasta_hash: String) -> Self {
+        self.fasta_hash = Some(fasta_hash);
+        self
+    }
+
+    pub fn with_precursor_tolerance(mut self, tolerance: Tolerance) -> Self {
+        self.precursor_tolerance = Some(tolerance);
+        self
+    }
+
+    pub fn with_fragment_tolerance(mut self, tolerance: Tolerance) -> Self {
+        self.fragment_tolerance = Some(tolerance);
+        self
+    }
+
+    pub fn with_fixed_modification(mut self, modification: SearchModification) -> Self {
+        self.fixed_modifications.push(modification);
+        self
+    }
+
+    pub fn with_variable_modification(mut self, modification: SearchModification) -> Self {
+        self.variable_modifications.push(modification);
+        self
+    }
+
+    pub fn with_engine_version(mut self, engine_version: EngineVersion) -> Self {
+        self.engine_versions.push(engine_version);
+        self
+    }
+
+    pub fn get_fasta_path(&self) -> &str {
+        &self.fasta_path
+    }
+
+    /// Hash of the FASTA file to verify the database, algorithm is up to the producer
+    ///
+    pub fn get_fasta_hash(&self) -> &Option<String> {
+        &self.fasta_hash
+    }
+
+    pub fn get_enzyme(&self) -> &str {
+        &self.enzyme
+    }
+
+    pub fn get_missed_cleavages(&self) -> u8 {
+        self.missed_cleavages
+    }
+
+    pub fn get_precursor_tolerance(&self) -> &Option<Tolerance> {
+        &self.precursor_tolerance
+    }
+
+    pub fn get_fragment_tolerance(&self) -> &Option<Tolerance> {
+        &self.fragment_tolerance
+    }
+
+    pub fn get_fixed_modifications(&self) -> &Vec<SearchModification> {
+        &self.fixed_modifications
+    }
+
+    pub fn get_variable_modifications(&self) -> &Vec<SearchModification> {
+        &self.variable_modifications
+    }
+
+    pub fn get_engine_versions(&self) -> &Vec<EngineVersion> {
+        &self.engine_versions
+    }
+}
@@ -0,0 +1,335 @@
+//! Identification statistics of a whole search, e.g. for the landing page of the dashboard.
+//! Computing them touches every spectrum, so they are meant to be computed once after the search
+//! has finished and cached as part of the `SearchSummary`.
+
+// std imports
+use std::borrow::Borrow;
+use std::collections::HashSet;
+
+// 3rd party imports
+use anyhow::Result;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::results_api::best_psm::decoy_column;
+use crate::results_api::spectrum::top_ranked_flags;
+use crate::results_api::{psm_columns, ExchangeConfig, Search, SearchSummary, Spectrum};
+use crate::statistics::fdr;
+use crate::statistics::histogram::Histogram;
+use crate::statistics::summary::{par_histogram, par_summary, Summary};
+
+/// FDR at which PSMs, peptides and proteins are counted by default
+///
+pub const DEFAULT_SUMMARY_FDR: f64 = 0.01;
+
+/// Quantiles of the score summaries
+///
+const SCORE_QUANTILES: [f64; 3] = [0.25, 0.5, 0.75];
+
+/// Distribution of the scores of targets or decoys
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ScoreDistribution {
+    summary: Summary,
+    histogram: Histogram,
+}
+
+impl ScoreDistribution {
+    /// Descriptive statistics including the quartiles
+    ///
+    pub fn get_summary(&self) -> &Summary {
+        &self.summary
+    }
+
+    /// Histogram, binned by the strategy of the search's `ExchangeConfig`
+    ///
+    pub fn get_histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+}
+
+/// Identification statistics over all MS runs of a search.
+/// FDRs are calculated search-wide over the top ranked PSMs by target-decoy counting on the primary score
+/// of the search's `ExchangeConfig`. Lower ranked PSMs are ignored, like in the peptide and protein aggregations.
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SearchStatistics {
+    fdr_threshold: f64,
+    score_column: String,
+    num_spectra: usize,
+    num_identified_spectra: usize,
+    num_psms: usize,
+    num_peptides: usize,
+    num_proteins: usize,
+    target_scores: Option<ScoreDistribution>,
+    decoy_scores: Option<ScoreDistribution>,
+}
+
+impl SearchStatistics {
+    /// Computes the statistics of the given spectra
+    ///
+    /// # Arguments
+    /// * `spectra` - Spectra of all MS runs, spectra are only read once, so they can be streamed from a store
+    /// * `config` - Exchange config of the search, providing the score, decoy prefix and histogram bins.
+    ///   Decoys are taken from the `is_decoy` column if present (see `Identification::mark_decoys`),
+    ///   otherwise recognized by the decoy prefix.
+    /// * `fdr_threshold` - Maximum q-value of accepted PSMs, e.g. `DEFAULT_SUMMARY_FDR`
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::{
+    ///     ExchangeConfig, Identification, Precursor, SearchStatistics, Spectrum,
+    /// };
+    /// use polars::prelude::*;
+    ///
+    /// let spectrum = |spectrum_id: &str, psms: DataFrame| {
+    ///     let mut identification = Identification::new(None, Some(psms), Precursor::new(400.7, 2));
+    ///     identification.mark_decoys("REV_").unwrap();
+    ///     Spectrum::new(
+    ///         "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+    ///         "run".parse().unwrap(),
+    ///         spectrum_id.parse().unwrap(),
+    ///         vec![175.119],
+    ///         vec![1000.0],
+    ///         vec![identification],
+    ///     )
+    /// };
+    /// let spectra = [
+    ///     spectrum("scan=1", df!(
+    ///         "plain_peptide" => &["PEPTIDE", "PEPTIDES"],
+    ///         "protein" => &["P1", "P2"],
+    ///         "xcorr" => &[3.5, 2.5],
+    ///         "num" => &[1u32, 2]
+    ///     ).unwrap()),
+    ///     spectrum("scan=2", df!(
+    ///         "plain_peptide" => &["PEPTIDEK"],
+    ///         "protein" => &["REV_P3"],
+    ///         "xcorr" => &[1.5],
+    ///         "num" => &[1u32]
+    ///     ).unwrap()),
+    /// ];
+    ///
+    /// // decoys are taken from the `is_decoy` column, although the config has the default prefix
+    /// let statistics = SearchStatistics::compute(&spectra, &ExchangeConfig::default(), 0.01).unwrap();
+    /// // the rank 2 candidate is not counted
+    /// assert_eq!(statistics.get_num_psms(), 1);
+    /// assert_eq!(statistics.get_num_peptides(), 1);
+    /// assert_eq!(statistics.get_decoy_scores().unwrap().get_summary().get_count(), 1);
+    /// ```
+    ///
+    pub fn compute<S: Borrow<Spectrum>>(
+        spectra: impl IntoIterator<Item = S>,
+        config: &ExchangeConfig,
+        fdr_threshold: f64,
+    ) -> Result<Self> {
+        let mut num_spectra: usize = 0;
+        let mut spectrum_indices: Vec<usize> = Vec::new();
+        let mut scores: Vec<Option<f64>> = Vec::new();
+        let mut is_decoy: Vec<bool> = Vec::new();
+        let mut peptides: Vec<Option<String>> = Vec::new();
+        let mut proteins: Vec<Option<String>> = Vec::new();
+        for spectrum in spectra {
+            for identification in spectrum.borrow().get_identifications() {
+                let psms = match identification.get_psms() {
+                    Some(psms) => psms,
+                    None => continue,
+                };
+                let top_ranked = BooleanChunked::from_slice("top_ranked", &top_ranked_flags(psms)?);
+                let psms = psms.filter(&top_ranked)?;
+                spectrum_indices.extend(std::iter::repeat_n(num_spectra, psms.height()));
+                match psms.column(config.get_score_column()) {
+                    Ok(score) => scores.extend(score.cast(&DataType::Float64)?.f64()?),
+                    Err(_) => scores.extend(std::iter::repeat_n(None, psms.height())),
+                }
+                is_decoy.extend(
+                    decoy_column(&psms, config.get_decoy_prefix())?
+                        .into_iter()
+                        .map(|is_decoy| is_decoy.unwrap_or(false)),
+                );
+                peptides.extend(optional_strings(&psms, psm_columns::PLAIN_PEPTIDE)?);
+                proteins.extend(optional_strings(&psms, psm_columns::PROTEIN)?);
+            }
+            num_spectra += 1;
+        }
+
+        let (_, q_values) =
+            fdr::compute_from_flags(&scores, &is_decoy, config.is_higher_score_better());
+
+        let mut num_psms: usize = 0;
+        let mut identified_spectra: HashSet<usize> = HashSet::new();
+        let mut accepted_peptides: HashSet<&str> = HashSet::new();
+        let mut accepted_proteins: HashSet<&str> = HashSet::new();
+        for idx in 0..scores.len() {
+            if is_decoy[idx] || scores[idx].is_none() || q_values[idx] > fdr_threshold {
+                continue;
+            }
+            num_psms += 1;
+            identified_spectra.insert(spectrum_indices[idx]);
+            if let Some(peptide) = peptides[idx].as_deref() {
+                accepted_peptides.insert(peptide);
+            }
+            if let Some(accessions) = proteins[idx].as_deref() {
+                accepted_proteins.extend(
+                    accessions
+                        .split(',')
+                        .map(|accession| accession.trim())
+                        .filter(|accession| !accession.is_empty()),
+                );
+            }
+        }
+
+        let distribution = |decoys: bool| {
+            let values = scores
+                .iter()
+                .zip(is_decoy.iter())
+                .filter(|(_, is_decoy)| **is_decoy == decoys)
+                .map(|(score, _)| *score)
+                .collect::<Float64Chunked>();
+            Some(ScoreDistribution {
+                summary: par_summary(&values, &SCORE_QUANTILES)?,
+                histogram: par_histogram(&values, config.get_histogram_bins())?,
+            })
+        };
+
+        Ok(Self {
+            fdr_threshold,
+            score_column: config.get_score_column().to_string(),
+            num_spectra,
+            num_identified_spectra: identified_spectra.len(),
+            num_psms,
+            num_peptides: accepted_peptides.len(),
+            num_proteins: accepted_proteins.len(),
+            target_scores: distribution(false),
+            decoy_scores: distribution(true),
+        })
+    }
+
+    /// Maximum q-value of the counted PSMs, peptides and proteins
+    ///
+    pub fn get_fdr_threshold(&self) -> f64 {
+        self.fdr_threshold
+    }
+
+    /// Score the FDR and distributions are based on
+    ///
+    pub fn get_score_column(&self) -> &str {
+        &self.score_column
+    }
+
+    pub fn get_num_spectra(&self) -> usize {
+        self.num_spectra
+    }
+
+    /// Spectra with at least one accepted target PSM
+    ///
+    pub fn get_num_identified_spectra(&self) -> usize {
+        self.num_identified_spectra
+    }
+
+    /// Fraction of identified spectra, 0.0 for searches without spectra
+    ///
+    pub fn get_id_rate(&self) -> f64 {
+        match self.num_spectra {
+            0 => 0.0,
+            num_spectra => self.num_identified_spectra as f64 / num_spectra as f64,
+        }
+    }
+
+    /// Accepted target PSMs
+    ///
+    pub fn get_num_psms(&self) -> usize {
+        self.num_psms
+    }
+
+    /// Distinct plain peptides of the accepted PSMs
+    ///
+    pub fn get_num_peptides(&self) -> usize {
+        self.num_peptides
+    }
+
+    /// Distinct protein accessions of the accepted PSMs
+    ///
+    pub fn get_num_proteins(&self) -> usize {
+        self.num_proteins
+    }
+
+    /// Score distribution of all target PSMs, None if there are none
+    ///
+    pub fn get_target_scores(&self) -> Option<&ScoreDistribution> {
+        self.target_scores.as_ref()
+    }
+
+    /// Score distribution of all decoy PSMs, None if there are none
+    ///
+    pub fn get_decoy_scores(&self) -> Option<&ScoreDistribution> {
+        self.decoy_scores.as_ref()
+    }
+}
+
+impl Search {
+    /// Summary of the search including the statistics of the given spectra, see `SearchStatistics::compute`.
+    /// Use `ResultStore::search_summary` to compute it from the spectra of all MS runs of a store.
+    ///
+    /// # Arguments
+    /// * `spectra` - Spectra of all MS runs of the search
+    /// * `fdr_threshold` - Maximum q-value of accepted PSMs, e.g. `DEFAULT_SUMMARY_FDR`
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::search_statistics::DEFAULT_SUMMARY_FDR;
+    /// use maccoys_exchange_entities::results_api::{Identification, Precursor, Search, Spectrum};
+    /// use polars::prelude::*;
+    ///
+    /// let search_uuid = "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c";
+    /// let spectra = (0..4)
+    ///     .map(|idx| {
+    ///         let psms = df!(
+    ///             "plain_peptide" => &[["PEPTIDE", "PEPTIDES", "EDITPEP", "PEPTIDER"][idx]],
+    ///             "protein" => &[["P1", "P1,P2", "DECOY_P3", "P4"][idx]],
+    ///             "xcorr" => &[[3.0, 2.5, 2.0, 1.0][idx]]
+    ///         )
+    ///         .unwrap();
+    ///         Spectrum::new(
+    ///             search_uuid.parse().unwrap(),
+    ///             "run".parse().unwrap(),
+    ///             format!("scan={}", idx).parse().unwrap(),
+    ///             vec![100.0],
+    ///             vec![1.0],
+    ///             vec![Identification::new(None, Some(psms), Precursor::new(400.7, 2))],
+    ///         )
+    ///     })
+    ///     .collect::<Vec<Spectrum>>();
+    /// let search = Search::new(search_uuid.parse().unwrap(), vec!["run".parse().unwrap()]);
+    ///
+    /// let summary = search.summary_with_statistics(&spectra, DEFAULT_SUMMARY_FDR).unwrap();
+    /// let statistics = summary.get_statistics().unwrap();
+    /// // the decoy is scored between the second and the last target
+    /// assert_eq!(statistics.get_num_identified_spectra(), 2);
+    /// assert_eq!(statistics.get_id_rate(), 0.5);
+    /// assert_eq!(statistics.get_num_proteins(), 2);
+    /// assert_eq!(statistics.get_decoy_scores().unwrap().get_summary().get_count(), 1);
+    /// ```
+    ///
+    pub fn summary_with_statistics<S: Borrow<Spectrum>>(
+        &self,
+        spectra: impl IntoIterator<Item = S>,
+        fdr_threshold: f64,
+    ) -> Result<SearchSummary> {
+        let statistics = SearchStatistics::compute(spectra, self.get_config(), fdr_threshold)?;
+        Ok(self.summary().with_statistics(statistics))
+    }
+}
+
+/// Values of the string column, all None if the column is missing
+///
+fn optional_strings(psms: &DataFrame, column: &str) -> Result<Vec<Option<String>>> {
+    match psms.column(column) {
+        Ok(values) => Ok(values
+            .utf8()?
+            .into_iter()
+            .map(|value| value.map(|value| value.to_string()))
+            .collect()),
+        Err(_) => Ok(vec![None; psms.height()]),
+    }
+}
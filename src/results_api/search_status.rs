@@ -0,0 +1,132 @@
+// std imports
+use std::fmt;
+
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+/// Error when advancing the status of a search
+///
+#[derive(Debug, thiserror::Error)]
+pub enum TransitionError {
+    #[error("search cannot change from `{from}` to `{to}`")]
+    InvalidTransition {
+        from: SearchStatus,
+        to: SearchStatus,
+    },
+    #[error("{done} of {total} spectra searched is not a valid progress")]
+    InvalidProgress { done: u64, total: u64 },
+}
+
+/// Processing status of a search.
+///
+/// Searches move through `Queued` -> `Indexing` -> `Searching` -> `PostProcessing` -> `Finished`
+/// and can fail from every non-terminal state. While searching, the number of searched spectra
+/// can only increase and post processing starts once all spectra are searched.
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::SearchStatus;
+///
+/// let status = SearchStatus::Queued
+///     .transition(SearchStatus::Indexing).unwrap()
+///     .transition(SearchStatus::Searching { done: 0, total: 2 }).unwrap()
+///     .transition(SearchStatus::Searching { done: 1, total: 2 }).unwrap();
+/// assert_eq!(status.get_progress(), Some(0.5));
+/// assert!(status.transition(SearchStatus::PostProcessing).is_err());
+/// assert!(status.transition(SearchStatus::Failed).is_ok());
+/// ```
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum SearchStatus {
+    #[default]
+    Queued,
+    Indexing,
+    Searching {
+        done: u64,
+        total: u64,
+    },
+    PostProcessing,
+    Finished,
+    Failed,
+}
+
+impl SearchStatus {
+    /// Returns true if the search is finished or failed
+    ///
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Finished | Self::Failed)
+    }
+
+    /// Fraction of searched spectra while searching, e.g. for progress bars.
+    /// Searches without spectra are reported as completely searched.
+    ///
+    pub fn get_progress(&self) -> Option<f64> {
+        match self {
+            Self::Searching { total: 0, .. } => Some(1.0),
+            Self::Searching { done, total } => Some(*done as f64 / *total as f64),
+            _ => None,
+        }
+    }
+
+    /// Checks if the search can change to the given status
+    ///
+    /// # Arguments
+    /// * `next` - Next status
+    ///
+    pub fn validate_transition(&self, next: &SearchStatus) -> Result<(), TransitionError> {
+        if let Self::Searching { done, total } = next {
+            if done > total {
+                return Err(TransitionError::InvalidProgress {
+                    done: *done,
+                    total: *total,
+                });
+            }
+        }
+        let is_valid = match (self, next) {
+            (current, Self::Failed) => !current.is_terminal(),
+            (Self::Queued, Self::Indexing) => true,
+            (Self::Indexing, Self::Searching { done: 0, .. }) => true,
+            (
+                Self::Searching { done, total },
+                Self::Searching {
+                    done: next_done,
+                    total: next_total,
+                },
+            ) => total == next_total && done <= next_done,
+            (Self::Searching { done, total }, Self::PostProcessing) => done == total,
+            (Self::PostProcessing, Self::Finished) => true,
+            _ => false,
+        };
+        if !is_valid {
+            return Err(TransitionError::InvalidTransition {
+                from: *self,
+                to: *next,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the next status if the transition is valid
+    ///
+    /// # Arguments
+    /// * `next` - Next status
+    ///
+    pub fn transition(&self, next: SearchStatus) -> Result<SearchStatus, TransitionError> {
+        self.validate_transition(&next)?;
+        Ok(next)
+    }
+}
+
+impl fmt::Display for SearchStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Queued => write!(f, "queued"),
+            Self::Indexing => write!(f, "indexing"),
+            Self::Searching { done, total } => write!(f, "searching ({}/{})", done, total),
+            Self::PostProcessing => write!(f, "post processing"),
+            Self::Finished => write!(f, "finished"),
+            Self::Failed => write!(f, "failed"),
+        }
+    }
+}
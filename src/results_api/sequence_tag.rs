@@ -0,0 +1,171 @@
+//! De novo sequence tags, i.e. short sequences read directly from the mass differences of fragment peaks.
+//! The unexplained masses before and after the tag (flanking masses) allow matching the tag to peptides.
+
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::mass::{residue_mass, WATER};
+use crate::tolerance::Tolerance;
+
+/// Sequence tag of a spectrum
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::SequenceTag;
+/// use maccoys_exchange_entities::tolerance::Tolerance;
+///
+/// // `PTI` of PEPTIDE, preceded by PE and followed by DE
+/// let tag = SequenceTag::new("PTI".to_string(), 226.0954, 244.0695)
+///     .with_peaks(vec![3, 5, 8, 10])
+///     .with_score(Some(0.9));
+/// assert!((tag.get_peptide_mass().unwrap() - 799.3599).abs() < 0.001);
+/// assert!(tag.matches_peptide("PEPTIDE", Tolerance::Da(0.01)));
+/// // I and L have the same mass
+/// assert!(tag.matches_peptide("PEPTLDE", Tolerance::Da(0.01)));
+/// assert!(!tag.matches_peptide("PEPTIDEK", Tolerance::Da(0.01)));
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SequenceTag {
+    sequence: String,
+    n_term_mass: f64,
+    c_term_mass: f64,
+    #[serde(default)]
+    peaks: Vec<usize>,
+    #[serde(default)]
+    score: Option<f64>,
+    #[serde(default)]
+    charge: Option<u8>,
+}
+
+impl SequenceTag {
+    /// # Arguments
+    /// * `sequence` - Amino acids of the tag, N- to C-terminal
+    /// * `n_term_mass` - Mass of the residues preceding the tag in Dalton, 0 if the tag starts the peptide
+    /// * `c_term_mass` - Mass of the residues following the tag in Dalton, 0 if the tag ends the peptide
+    ///
+    pub fn new(sequence: String, n_term_mass: f64, c_term_mass: f64) -> Self {
+        Self {
+            sequence,
+            n_term_mass,
+            c_term_mass,
+            peaks: Vec::new(),
+            score: None,
+            charge: None,
+        }
+    }
+
+    /// Sets the indexes of the spectrum peaks the tag was read from, one more than amino acids
+    ///
+    pub fn with_peaks(mut self, peaks: Vec<usize>) -> Self {
+        self.peaks = peaks;
+        self
+    }
+
+    /// Sets the score of the de novo engine, higher is better
+    ///
+    pub fn with_score(mut self, score: Option<f64>) -> Self {
+        self.score = score;
+        self
+    }
+
+    /// Sets the charge of the fragment ions the tag was read from
+    ///
+    pub fn with_charge(mut self, charge: Option<u8>) -> Self {
+        self.charge = charge;
+        self
+    }
+
+    pub fn get_sequence(&self) -> &str {
+        &self.sequence
+    }
+
+    /// Mass of the residues preceding the tag in Dalton
+    ///
+    pub fn get_n_term_mass(&self) -> f64 {
+        self.n_term_mass
+    }
+
+    /// Mass of the residues following the tag in Dalton
+    ///
+    pub fn get_c_term_mass(&self) -> f64 {
+        self.c_term_mass
+    }
+
+    /// Indexes of the supporting peaks within the spectrum
+    ///
+    pub fn get_peaks(&self) -> &Vec<usize> {
+        &self.peaks
+    }
+
+    pub fn get_score(&self) -> Option<f64> {
+        self.score
+    }
+
+    pub fn get_charge(&self) -> Option<u8> {
+        self.charge
+    }
+
+    /// Number of amino acids
+    ///
+    pub fn len(&self) -> usize {
+        self.sequence.chars().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sequence.is_empty()
+    }
+
+    /// Summed residue masses of the tag, None for unknown amino acids
+    ///
+    pub fn get_tag_mass(&self) -> Option<f64> {
+        self.sequence.chars().map(residue_mass).sum()
+    }
+
+    /// Neutral mass of the peptide the tag is part of, None for unknown amino acids
+    ///
+    pub fn get_peptide_mass(&self) -> Option<f64> {
+        Some(self.n_term_mass + self.get_tag_mass()? + self.c_term_mass + WATER)
+    }
+
+    /// True if the tag occurs in the peptide with matching flanking masses.
+    /// I and L are not distinguished.
+    ///
+    /// # Arguments
+    /// * `peptide` - Plain peptide sequence
+    /// * `tolerance` - Tolerance for the flanking masses
+    ///
+    pub fn matches_peptide(&self, peptide: &str, tolerance: Tolerance) -> bool {
+        let normalize = |sequence: &str| -> Vec<char> {
+            sequence
+                .chars()
+                .map(|amino_acid| match amino_acid.to_ascii_uppercase() {
+                    'I' => 'L',
+                    amino_acid => amino_acid,
+                })
+                .collect()
+        };
+        let tag = normalize(&self.sequence);
+        let peptide = normalize(peptide);
+        if tag.is_empty() || tag.len() > peptide.len() {
+            return false;
+        }
+        let masses =
+            |residues: &[char]| -> Option<f64> { residues.iter().copied().map(residue_mass).sum() };
+        (0..=peptide.len() - tag.len())
+            .filter(|start| peptide[*start..*start + tag.len()] == tag[..])
+            .any(|start| {
+                match (
+                    masses(&peptide[..start]),
+                    masses(&peptide[start + tag.len()..]),
+                ) {
+                    (Some(n_term_mass), Some(c_term_mass)) => {
+                        tolerance.contains(self.n_term_mass, n_term_mass)
+                            && tolerance.contains(self.c_term_mass, c_term_mass)
+                    }
+                    _ => false,
+                }
+            })
+    }
+}
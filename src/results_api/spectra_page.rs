@@ -0,0 +1,86 @@
+// internal imports
+use crate::results_api::MsRun;
+
+/// Slice of the spectrum IDs of an MS run with pagination metadata
+///
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SpectraPage {
+    search_uuid: String,
+    ms_run_name: String,
+    spectra_ids: Vec<String>,
+    offset: usize,
+    total: usize,
+    next_cursor: Option<String>,
+}
+
+impl SpectraPage {
+    pub fn get_search_uuid(&self) -> &str {
+        &self.search_uuid
+    }
+
+    pub fn get_ms_run(&self) -> &str {
+        &self.ms_run_name
+    }
+
+    pub fn get_spectra_ids(&self) -> &Vec<String> {
+        &self.spectra_ids
+    }
+
+    /// Position of the first spectrum of the page within the MS run
+    ///
+    pub fn get_offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Number of spectra in the MS run
+    ///
+    pub fn get_total(&self) -> usize {
+        self.total
+    }
+
+    /// Cursor for requesting the following page with `MsRun::page_after`, None on the last page
+    ///
+    pub fn get_next_cursor(&self) -> &Option<String> {
+        &self.next_cursor
+    }
+}
+
+impl MsRun {
+    /// Returns up to `limit` spectrum IDs starting at `offset`.
+    /// An offset beyond the end results in an empty page.
+    ///
+    pub fn page(&self, offset: usize, limit: usize) -> SpectraPage {
+        let spectra_ids = self.get_spectra_ids();
+        let start = offset.min(spectra_ids.len());
+        let end = start.saturating_add(limit).min(spectra_ids.len());
+        let next_cursor = if end < spectra_ids.len() && end > start {
+            Some(spectra_ids[end - 1].to_string())
+        } else {
+            None
+        };
+        SpectraPage {
+            search_uuid: self.get_search_uuid().to_string(),
+            ms_run_name: self.get_ms_run().to_string(),
+            spectra_ids: spectra_ids[start..end]
+                .iter()
+                .map(|spectrum_id| spectrum_id.to_string())
+                .collect(),
+            offset: start,
+            total: spectra_ids.len(),
+            next_cursor,
+        }
+    }
+
+    /// Returns up to `limit` spectrum IDs following the given cursor.
+    /// The cursor is the last spectrum ID of the previous page, so pages stay consistent
+    /// even if spectra are added to the MS run in between. Returns None for unknown cursors.
+    ///
+    pub fn page_after(&self, cursor: &str, limit: usize) -> Option<SpectraPage> {
+        let position = self
+            .get_spectra_ids()
+            .iter()
+            .position(|spectrum_id| spectrum_id == cursor)?;
+        Some(self.page(position + 1, limit))
+    }
+}
@@ -1,17 +1,50 @@
 // std imports
-use std::{collections::HashMap, rc::Rc, slice::Iter};
+use std::{collections::HashMap, slice::Iter, sync::Arc};
 
 // 3rd party imports
 use polars::{prelude::*, series::SeriesIter};
 
-/// Row of a dataframe
+// internal imports
+use crate::quant::reporter_ions::ReporterIonTable;
+use crate::results_api::merge::{concat_aligned, MergeError};
+use crate::results_api::precursor::PrecursorPayload;
+use crate::results_api::{
+    psm_columns, GoodnessOfFit, IdentifierError, MsRunName, Normalization, PeakFilter, Precursor,
+    ScoreDescriptor, SearchUuid, SequenceTag, SpectrumId, SCHEMA_VERSION,
+};
+use crate::statistics::distributions::Distribution;
+use crate::statistics::fdr::{decoy_flags, is_decoy_accession, DEFAULT_DECOY_PREFIX};
+use crate::statistics::histogram::{BinStrategy, Histogram};
+use crate::statistics::summary::{par_histogram, par_summary, Summary};
+
+/// Row of a dataframe. Rows are `Send` and `Sync`, so they can be processed on other threads:
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::{Identification, Precursor};
+/// use polars::prelude::*;
+///
+/// let psms = df!("plain_peptide" => &["PEPTIDE", "PEPTIDES"], "xcorr" => &[2.5, 1.5]).unwrap();
+/// let identification = Identification::new(None, Some(psms), Precursor::new(400.7, 2));
+/// let sum = std::thread::scope(|scope| {
+///     identification
+///         .iter_psm_rows()
+///         .unwrap()
+///         .map(|row| scope.spawn(move || row.get_f64("xcorr").unwrap()))
+///         .collect::<Vec<_>>()
+///         .into_iter()
+///         .map(|handle| handle.join().unwrap())
+///         .sum::<f64>()
+/// });
+/// assert_eq!(sum, 4.0);
+/// ```
+///
 pub struct Row<'a> {
-    col_index: Rc<HashMap<String, usize>>,
+    col_index: Arc<HashMap<String, usize>>,
     col_values: Vec<AnyValue<'a>>,
 }
 
 impl<'a> Row<'a> {
-    pub fn new(col_index: Rc<HashMap<String, usize>>, col_values: Vec<AnyValue<'a>>) -> Row<'a> {
+    pub fn new(col_index: Arc<HashMap<String, usize>>, col_values: Vec<AnyValue<'a>>) -> Row<'a> {
         Row {
             col_index,
             col_values,
@@ -26,30 +59,277 @@ impl<'a> Row<'a> {
         self.col_values.iter()
     }
 
+    /// Iterates the column names and values, in no particular order
+    ///
+    pub fn iter_named(&self) -> impl Iterator<Item = (&str, &AnyValue<'a>)> {
+        self.col_index
+            .iter()
+            .map(|(col_name, col_index)| (col_name.as_str(), &self.col_values[*col_index]))
+    }
+
     pub fn len(&self) -> usize {
         self.col_values.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.col_values.is_empty()
+    }
+
+    /// Returns the value of the given column or an error if the column does not exist
+    ///
+    pub fn try_get(&self, col_name: &str) -> Result<&AnyValue<'a>, RowError> {
+        let col_index = self
+            .col_index
+            .get(col_name)
+            .ok_or_else(|| RowError::UnknownColumn(col_name.to_string()))?;
+        self.try_get_at(*col_index)
+    }
+
+    /// Returns the value at the given position or an error if the position is out of range
+    ///
+    pub fn try_get_at(&self, index: usize) -> Result<&AnyValue<'a>, RowError> {
+        self.col_values.get(index).ok_or(RowError::OutOfRange {
+            index,
+            len: self.col_values.len(),
+        })
+    }
+
+    /// Returns the value of the given column converted to `T`
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::spectrum::Row;
+    /// use polars::prelude::*;
+    /// use std::collections::HashMap;
+    /// use std::sync::Arc;
+    ///
+    /// let col_index = Arc::new(HashMap::from([("peptide".to_string(), 0), ("protein".to_string(), 1)]));
+    /// let row = Row::new(
+    ///     col_index,
+    ///     vec![AnyValue::Utf8("PEPTIDE"), AnyValue::Utf8Owned("P12345".into())],
+    /// );
+    /// assert_eq!(row.get::<&str>("peptide").unwrap(), "PEPTIDE");
+    /// assert_eq!(row.get::<&str>("protein").unwrap(), "P12345");
+    /// assert_eq!(row.get::<Option<&str>>("protein").unwrap(), Some("P12345"));
+    /// ```
+    ///
+    pub fn get<'r, T: FromAnyValue<'r>>(&'r self, col_name: &str) -> Result<T, ColumnError> {
+        T::from_any_value(self.try_get(col_name)?).map_err(|err| err.with_column(col_name))
+    }
+
+    pub fn get_bool(&self, col_name: &str) -> Result<bool, ColumnError> {
+        self.get(col_name)
+    }
+
+    pub fn get_str(&self, col_name: &str) -> Result<&str, ColumnError> {
+        self.get(col_name)
+    }
+
+    pub fn get_u8(&self, col_name: &str) -> Result<u8, ColumnError> {
+        self.get(col_name)
+    }
+
+    pub fn get_u32(&self, col_name: &str) -> Result<u32, ColumnError> {
+        self.get(col_name)
+    }
+
+    pub fn get_u64(&self, col_name: &str) -> Result<u64, ColumnError> {
+        self.get(col_name)
+    }
+
+    pub fn get_i32(&self, col_name: &str) -> Result<i32, ColumnError> {
+        self.get(col_name)
+    }
+
+    pub fn get_i64(&self, col_name: &str) -> Result<i64, ColumnError> {
+        self.get(col_name)
+    }
+
+    pub fn get_f32(&self, col_name: &str) -> Result<f32, ColumnError> {
+        self.get(col_name)
+    }
+
+    pub fn get_f64(&self, col_name: &str) -> Result<f64, ColumnError> {
+        self.get(col_name)
+    }
 }
 
+/// Discouraged: `row[col_name]` panics if the column does not exist, e.g. for PSMs of another search engine.
+/// Use `Row::try_get` or the typed getters (`Row::get`, `Row::get_f64`, ...) instead, which return an error.
+///
+/// # Panics
+/// If the column does not exist
+///
 impl<'a> std::ops::Index<&str> for Row<'a> {
     type Output = AnyValue<'a>;
 
     fn index(&self, col_name: &str) -> &Self::Output {
-        let col_index = self.col_index.get(col_name).unwrap();
-        &self.col_values[*col_index]
+        match self.try_get(col_name) {
+            Ok(value) => value,
+            Err(err) => panic!("{}", err),
+        }
+    }
+}
+
+/// Error when accessing a value of a row
+///
+#[derive(Debug, thiserror::Error)]
+pub enum RowError {
+    #[error("column `{0}` does not exist")]
+    UnknownColumn(String),
+    #[error("index {index} is out of range for row with {len} values")]
+    OutOfRange { index: usize, len: usize },
+}
+
+/// Error when accessing a typed value of a row
+///
+#[derive(Debug, thiserror::Error)]
+pub enum ColumnError {
+    #[error(transparent)]
+    Row(#[from] RowError),
+    #[error("column `{column}` is null")]
+    NullValue { column: String },
+    #[error("column `{column}` contains `{found}` which cannot be converted to `{expected}`")]
+    TypeMismatch {
+        column: String,
+        expected: &'static str,
+        found: String,
+    },
+}
+
+impl ColumnError {
+    /// Creates a null or type mismatch error for the given value. The column name is empty and needs to be set via `with_column`.
+    ///
+    fn from_value<T>(value: &AnyValue<'_>) -> Self {
+        match value {
+            AnyValue::Null => Self::NullValue {
+                column: String::new(),
+            },
+            _ => Self::TypeMismatch {
+                column: String::new(),
+                expected: std::any::type_name::<T>(),
+                found: value.dtype().to_string(),
+            },
+        }
+    }
+
+    fn with_column(self, col_name: &str) -> Self {
+        match self {
+            Self::Row(err) => Self::Row(err),
+            Self::NullValue { .. } => Self::NullValue {
+                column: col_name.to_string(),
+            },
+            Self::TypeMismatch {
+                expected, found, ..
+            } => Self::TypeMismatch {
+                column: col_name.to_string(),
+                expected,
+                found,
+            },
+        }
+    }
+}
+
+/// Conversion of a polars `AnyValue` into a Rust type. Integers are only converted if they fit losslessly into the target type,
+/// floats are accepted for both `f32` and `f64`. `'a` is the lifetime of the borrowed value,
+/// so `&str` can be borrowed from both `AnyValue::Utf8` and `AnyValue::Utf8Owned`.
+///
+pub trait FromAnyValue<'a>: Sized {
+    fn from_any_value(value: &'a AnyValue<'a>) -> Result<Self, ColumnError>;
+}
+
+impl<'a> FromAnyValue<'a> for bool {
+    fn from_any_value(value: &'a AnyValue<'a>) -> Result<Self, ColumnError> {
+        match value {
+            AnyValue::Boolean(value) => Ok(*value),
+            other => Err(ColumnError::from_value::<Self>(other)),
+        }
+    }
+}
+
+impl<'a> FromAnyValue<'a> for &'a str {
+    fn from_any_value(value: &'a AnyValue<'a>) -> Result<Self, ColumnError> {
+        match value {
+            AnyValue::Utf8(value) => Ok(value),
+            AnyValue::Utf8Owned(value) => Ok(value.as_str()),
+            other => Err(ColumnError::from_value::<Self>(other)),
+        }
+    }
+}
+
+impl<'a> FromAnyValue<'a> for String {
+    fn from_any_value(value: &'a AnyValue<'a>) -> Result<Self, ColumnError> {
+        match value {
+            AnyValue::Utf8(value) => Ok(value.to_string()),
+            AnyValue::Utf8Owned(value) => Ok(value.to_string()),
+            other => Err(ColumnError::from_value::<Self>(other)),
+        }
+    }
+}
+
+macro_rules! impl_from_any_value_for_int {
+    ($($int:ty),*) => {
+        $(
+            impl<'a> FromAnyValue<'a> for $int {
+                fn from_any_value(value: &'a AnyValue<'a>) -> Result<Self, ColumnError> {
+                    let converted = match value {
+                        AnyValue::UInt8(value) => <$int>::try_from(*value).ok(),
+                        AnyValue::UInt16(value) => <$int>::try_from(*value).ok(),
+                        AnyValue::UInt32(value) => <$int>::try_from(*value).ok(),
+                        AnyValue::UInt64(value) => <$int>::try_from(*value).ok(),
+                        AnyValue::Int8(value) => <$int>::try_from(*value).ok(),
+                        AnyValue::Int16(value) => <$int>::try_from(*value).ok(),
+                        AnyValue::Int32(value) => <$int>::try_from(*value).ok(),
+                        AnyValue::Int64(value) => <$int>::try_from(*value).ok(),
+                        _ => None,
+                    };
+                    converted.ok_or_else(|| ColumnError::from_value::<Self>(value))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_any_value_for_int!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+impl<'a> FromAnyValue<'a> for f64 {
+    fn from_any_value(value: &'a AnyValue<'a>) -> Result<Self, ColumnError> {
+        match value {
+            AnyValue::Float64(value) => Ok(*value),
+            AnyValue::Float32(value) => Ok(*value as f64),
+            other => Err(ColumnError::from_value::<Self>(other)),
+        }
+    }
+}
+
+impl<'a> FromAnyValue<'a> for f32 {
+    fn from_any_value(value: &'a AnyValue<'a>) -> Result<Self, ColumnError> {
+        match value {
+            AnyValue::Float32(value) => Ok(*value),
+            AnyValue::Float64(value) => Ok(*value as f32),
+            other => Err(ColumnError::from_value::<Self>(other)),
+        }
+    }
+}
+
+impl<'a, T: FromAnyValue<'a>> FromAnyValue<'a> for Option<T> {
+    fn from_any_value(value: &'a AnyValue<'a>) -> Result<Self, ColumnError> {
+        match value {
+            AnyValue::Null => Ok(None),
+            other => T::from_any_value(other).map(Some),
+        }
     }
 }
 
 /// Iterates the rows of the dataframe. Probably a bit more efficient than using the `DataFrame::get_row` method,
 /// which is discouraged in the polars documentation.
 pub struct RowIter<'a> {
-    col_index: Rc<HashMap<String, usize>>,
+    col_index: Arc<HashMap<String, usize>>,
     col_iterators: Vec<SeriesIter<'a>>,
 }
 
 impl<'a> RowIter<'a> {
-    fn new(dataframe: &'a DataFrame) -> Self {
-        let col_index = Rc::new(
+    pub(crate) fn new(dataframe: &'a DataFrame) -> Self {
+        let col_index = Arc::new(
             dataframe
                 .get_columns()
                 .iter()
@@ -59,7 +339,7 @@ impl<'a> RowIter<'a> {
         );
         let col_iterators = dataframe
             .get_columns()
-            .into_iter()
+            .iter()
             .map(|col| col.iter())
             .collect::<Vec<SeriesIter<'_>>>();
         Self {
@@ -67,45 +347,180 @@ impl<'a> RowIter<'a> {
             col_iterators,
         }
     }
+
+    /// Iterates only the given columns, in the given order
+    ///
+    fn select(dataframe: &'a DataFrame, columns: &[&str]) -> Result<Self, RowError> {
+        let col_iterators = columns
+            .iter()
+            .map(|col_name| match dataframe.column(col_name) {
+                Ok(col) => Ok(col.iter()),
+                Err(_) => Err(RowError::UnknownColumn(col_name.to_string())),
+            })
+            .collect::<Result<Vec<SeriesIter<'_>>, RowError>>()?;
+        let col_index = Arc::new(
+            columns
+                .iter()
+                .enumerate()
+                .map(|(i, col_name)| (col_name.to_string(), i))
+                .collect::<HashMap<String, usize>>(),
+        );
+        Ok(Self {
+            col_index,
+            col_iterators,
+        })
+    }
 }
 
-impl<'a> Iterator for RowIter<'a> {
-    type Item = Row<'a>;
+// Rows and row iterators are shared across threads by the results API server (async handlers, rayon),
+// so losing `Send`/`Sync` breaks the build here instead of in downstream crates.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Row<'static>>();
+    assert_send_sync::<RowIter<'static>>();
+};
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut values = Vec::with_capacity(self.col_iterators.len());
+impl<'a> RowIter<'a> {
+    /// Calls `f` for each row without allocating a row per iteration. The values of all rows are written into
+    /// the same buffer, so the row passed to `f` is only valid for the duration of the call.
+    /// Considerably faster than iterating for large tables.
+    ///
+    /// # Arguments
+    /// * `f` - Called with each row
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::{Identification, Precursor};
+    /// use polars::prelude::*;
+    ///
+    /// let psms = df!("plain_peptide" => &["PEPTIDE", "PEPTIDES"], "xcorr" => &[2.5, 1.5]).unwrap();
+    /// let identification = Identification::new(None, Some(psms), Precursor::new(400.7, 2));
+    /// let mut sum = 0.0;
+    /// identification
+    ///     .iter_psm_rows()
+    ///     .unwrap()
+    ///     .for_each_row(|row| sum += row.get_f64("xcorr").unwrap());
+    /// assert_eq!(sum, 4.0);
+    /// ```
+    ///
+    pub fn for_each_row(mut self, mut f: impl FnMut(&Row<'a>)) {
+        let mut row = Row::new(
+            self.col_index.clone(),
+            Vec::with_capacity(self.col_iterators.len()),
+        );
+        while self.next_values(&mut row.col_values) {
+            f(&row);
+        }
+    }
+
+    /// Replaces the values with the ones of the next row, returns false if there is no next row
+    ///
+    fn next_values(&mut self, values: &mut Vec<AnyValue<'a>>) -> bool {
+        values.clear();
         for iter in self.col_iterators.iter_mut() {
             match iter.next() {
                 Some(value) => values.push(value),
-                None => return None,
+                None => return false,
             }
         }
-        Some(Row::new(self.col_index.clone(), values))
+        true
+    }
+}
+
+impl<'a> Iterator for RowIter<'a> {
+    type Item = Row<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut values = Vec::with_capacity(self.col_iterators.len());
+        self.next_values(&mut values)
+            .then(|| Row::new(self.col_index.clone(), values))
+    }
+}
+
+/// Checks if the PSM of the given row is top ranked. PSMs without rank column are considered top ranked.
+///
+pub(crate) fn is_top_ranked(row: &Row<'_>) -> Result<bool, ColumnError> {
+    match row.get::<Option<u32>>(psm_columns::RANK) {
+        Ok(Some(rank)) => Ok(rank == 1),
+        Ok(None) | Err(ColumnError::Row(_)) => Ok(true),
+        Err(err) => Err(err),
+    }
+}
+
+/// Same as `is_top_ranked` for all PSMs of the dataframe at once
+///
+pub(crate) fn top_ranked_flags(psms: &DataFrame) -> PolarsResult<Vec<bool>> {
+    match psms.column(psm_columns::RANK) {
+        Ok(ranks) => Ok(ranks
+            .cast(&DataType::UInt32)?
+            .u32()?
+            .into_iter()
+            .map(|rank| rank.is_none_or(|rank| rank == 1))
+            .collect()),
+        Err(_) => Ok(vec![true; psms.height()]),
     }
 }
 
 /// PSMS and goodness of fit for a spectrums charge state
 ///
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(from = "IdentificationPayload")]
 pub struct Identification {
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "Option<crate::schema::DataFrameLayout>")
+    )]
     goodnesses: Option<DataFrame>,
+    #[cfg_attr(
+        feature = "schema",
+        schemars(with = "Option<crate::schema::DataFrameLayout>")
+    )]
     psms: Option<DataFrame>,
-    precursor: f64,
-    charge: u8,
+    precursor: Precursor,
+    reporter_ions: Option<ReporterIonTable>,
+    score_descriptors: Vec<ScoreDescriptor>,
+}
+
+/// Deserializable layout of `Identification`, accepting the bare precursor m/z with separate charge of older payloads
+///
+#[derive(serde::Deserialize)]
+struct IdentificationPayload {
+    goodnesses: Option<DataFrame>,
+    psms: Option<DataFrame>,
+    precursor: PrecursorPayload,
+    #[serde(default)]
+    charge: Option<u8>,
+    #[serde(default)]
+    reporter_ions: Option<ReporterIonTable>,
+    #[serde(default)]
+    score_descriptors: Vec<ScoreDescriptor>,
+}
+
+impl From<IdentificationPayload> for Identification {
+    fn from(payload: IdentificationPayload) -> Self {
+        let precursor = match payload.precursor {
+            PrecursorPayload::Mz(mz) => Precursor::new(mz, payload.charge.unwrap_or_default()),
+            PrecursorPayload::Precursor(precursor) => precursor,
+        };
+        let mut identification = Self::new(payload.goodnesses, payload.psms, precursor);
+        identification.reporter_ions = payload.reporter_ions;
+        identification.score_descriptors = payload.score_descriptors;
+        identification
+    }
 }
 
 impl Identification {
     pub fn new(
         goodnesses: Option<DataFrame>,
         psms: Option<DataFrame>,
-        precursor: f64,
-        charge: u8,
+        precursor: Precursor,
     ) -> Self {
         Self {
             goodnesses,
             psms,
             precursor,
-            charge,
+            reporter_ions: None,
+            score_descriptors: Vec::new(),
         }
     }
 
@@ -117,122 +532,841 @@ impl Identification {
         &self.psms
     }
 
-    pub fn get_precursor(&self) -> f64 {
-        self.precursor
+    pub fn get_psms_mut(&mut self) -> &mut Option<DataFrame> {
+        &mut self.psms
+    }
+
+    /// Copy of the identification keeping only the selected tables, see `Spectrum::project`
+    ///
+    pub(crate) fn select_tables(&self, psms: bool, goodnesses: bool) -> Self {
+        Self {
+            goodnesses: self.goodnesses.as_ref().filter(|_| goodnesses).cloned(),
+            psms: self.psms.as_ref().filter(|_| psms).cloned(),
+            precursor: self.precursor.clone(),
+            reporter_ions: self.reporter_ions.clone(),
+            score_descriptors: self.score_descriptors.clone(),
+        }
+    }
+
+    pub fn get_precursor(&self) -> &Precursor {
+        &self.precursor
     }
 
+    /// Charge of the precursor
+    ///
     pub fn get_charge(&self) -> u8 {
-        self.charge
+        self.precursor.get_charge()
+    }
+
+    /// Attaches the reporter ions, e.g. extracted from the spectrum itself or from the associated SPS-MS3 spectrum
+    /// (see `Spectrum::extract_reporter_ions`)
+    ///
+    pub fn with_reporter_ions(mut self, reporter_ions: ReporterIonTable) -> Self {
+        self.reporter_ions = Some(reporter_ions);
+        self
+    }
+
+    pub fn set_reporter_ions(&mut self, reporter_ions: Option<ReporterIonTable>) {
+        self.reporter_ions = reporter_ions;
+    }
+
+    /// Reporter ion intensities of isobaric labels, None if not quantified
+    ///
+    pub fn get_reporter_ions(&self) -> &Option<ReporterIonTable> {
+        &self.reporter_ions
+    }
+
+    /// Registers the descriptor of a score column, replacing the descriptor of the same column
+    /// (see `Identification::describe_score`)
+    ///
+    pub fn register_score_descriptor(&mut self, descriptor: ScoreDescriptor) {
+        self.score_descriptors
+            .retain(|registered| registered.get_column() != descriptor.get_column());
+        self.score_descriptors.push(descriptor);
+    }
+
+    /// Registered score descriptors, without the Comet defaults
+    ///
+    pub fn get_score_descriptors(&self) -> &Vec<ScoreDescriptor> {
+        &self.score_descriptors
     }
 
     pub fn iter_psm_rows(&self) -> Option<RowIter<'_>> {
-        if self.psms.is_none() {
-            return None;
-        }
-        let iter = RowIter::new(self.psms.as_ref().unwrap());
+        let iter = RowIter::new(self.psms.as_ref()?);
         Some(iter)
     }
 
-    pub fn iter_goodness_rows(&self) -> Option<RowIter<'_>> {
-        if self.goodnesses.is_none() {
-            return None;
+    /// Calls `f` for each PSM reusing a single row buffer, see `RowIter::for_each_row`.
+    /// Returns false if there are no PSMs.
+    ///
+    pub fn for_each_psm_row<'a>(&'a self, f: impl FnMut(&Row<'a>)) -> bool {
+        match self.iter_psm_rows() {
+            Some(rows) => {
+                rows.for_each_row(f);
+                true
+            }
+            None => false,
         }
-        let iter = RowIter::new(self.goodnesses.as_ref().unwrap());
-        Some(iter)
     }
 
-    /// Histogram of the original search engine score (xcorr, for Comet)
-    /// Bin number is calculated using the rule of Sturges
+    /// Iterates the PSMs with only the given columns, which avoids materializing all values of wide tables.
+    /// Returns None if there are no PSMs and an error if a column does not exist.
     ///
-    pub fn get_score_histogram(&self) -> Option<(Vec<f64>, Vec<usize>)> {
-        if self.psms.is_none() {
-            return None;
+    pub fn iter_psm_rows_select(&self, columns: &[&str]) -> Result<Option<RowIter<'_>>, RowError> {
+        match self.psms.as_ref() {
+            Some(psms) => Ok(Some(RowIter::select(psms, columns)?)),
+            None => Ok(None),
         }
+    }
 
-        let score = &self.psms.as_ref().unwrap()["xcorr"];
+    /// Appends the PSMs and goodness of fits of the other identification of the same precursor, e.g. from a rerun.
+    /// Columns missing in one of the tables are filled with nulls, columns of the other identification are cast
+    /// to the types of this one. Fails if the charges differ or the column types are incompatible.
+    ///
+    pub fn concat(&mut self, other: Identification) -> Result<(), MergeError> {
+        if self.get_charge() != other.get_charge() {
+            return Err(MergeError::ChargeMismatch(
+                self.get_charge(),
+                other.get_charge(),
+            ));
+        }
+        let psms = concat_aligned(self.psms.clone(), other.psms)?;
+        let goodnesses = concat_aligned(self.goodnesses.clone(), other.goodnesses)?;
+        self.psms = psms;
+        self.goodnesses = goodnesses;
+        if self.reporter_ions.is_none() {
+            self.reporter_ions = other.reporter_ions;
+        }
+        for descriptor in other.score_descriptors {
+            if !self
+                .score_descriptors
+                .iter()
+                .any(|registered| registered.get_column() == descriptor.get_column())
+            {
+                self.score_descriptors.push(descriptor);
+            }
+        }
+        Ok(())
+    }
 
-        // rule of sturges to determine number of bins
-        let num_bins = (1.0 + (score.len() as f64).log2()).round() as usize;
+    /// Flags the PSMs as decoys if all their proteins start with the given prefix
+    /// by adding/replacing the boolean column `is_decoy`. Does nothing if there are no PSMs.
+    ///
+    pub fn mark_decoys(&mut self, prefix: &str) -> anyhow::Result<()> {
+        if let Some(psms) = self.psms.as_mut() {
+            let is_decoy = decoy_flags(psms, prefix)?;
+            psms.with_column(is_decoy.into_series())?;
+        }
+        Ok(())
+    }
 
-        let min = score.min::<f64>().unwrap();
-        let max = score.max::<f64>().unwrap();
+    /// Iterates the target PSMs. Uses the `is_decoy` column if present (see `mark_decoys`),
+    /// otherwise the proteins are checked for the default decoy prefix.
+    ///
+    pub fn iter_target_psm_rows(&self) -> Option<impl Iterator<Item = Row<'_>>> {
+        Some(self.iter_psm_rows()?.filter(|row| {
+            match row.get::<bool>(psm_columns::IS_DECOY) {
+                Ok(is_decoy) => !is_decoy,
+                Err(_) => !row
+                    .get::<&str>(psm_columns::PROTEIN)
+                    .is_ok_and(|proteins| is_decoy_accession(proteins, DEFAULT_DECOY_PREFIX)),
+            }
+        }))
+    }
+
+    /// Returns the PSMs sorted by the given columns, None if there are no PSMs.
+    ///
+    /// # Arguments
+    /// * `by` - Columns to sort by, in order of priority
+    /// * `descending` - Sort order per column, a single value is applied to all columns
+    ///
+    pub fn sort_psms(&self, by: &[&str], descending: &[bool]) -> anyhow::Result<Option<DataFrame>> {
+        match self.psms.as_ref() {
+            Some(psms) => Ok(Some(psms.sort(by, descending.to_vec(), true)?)),
+            None => Ok(None),
+        }
+    }
 
-        let bin_width = (max - min) as f64 / num_bins as f64;
+    /// Returns the best `k` PSMs according to the given column, e.g. `top_k_psms(10, "xcorr", true)`.
+    /// PSMs without value in the column come last. None if there are no PSMs.
+    ///
+    pub fn top_k_psms(
+        &self,
+        k: usize,
+        by: &str,
+        descending: bool,
+    ) -> anyhow::Result<Option<DataFrame>> {
+        match self.psms.as_ref() {
+            Some(psms) => Ok(Some(
+                psms.sort_with_options(
+                    by,
+                    SortOptions {
+                        descending,
+                        nulls_last: true,
+                        maintain_order: true,
+                        ..Default::default()
+                    },
+                )?
+                .head(Some(k)),
+            )),
+            None => Ok(None),
+        }
+    }
 
-        // Define bins
-        let mut bins: Vec<f64> = Vec::new();
-        for i in 0..=num_bins {
-            bins.push(min as f64 + i as f64 * bin_width);
+    /// Recomputes the rank column (`num`) of the PSMs, e.g. after filtering or rescoring.
+    /// PSMs are ranked by the score column, ties are broken by the tie breaker columns in order,
+    /// remaining ties keep their current order. PSMs without score are ranked last.
+    /// Ranks are consecutive starting at 1, the row order is not changed. Does nothing if there are no PSMs.
+    ///
+    /// # Arguments
+    /// * `score_column` - Column to rank by
+    /// * `tie_breakers` - Columns to break ties with, in order of priority
+    /// * `descending` - Sort order per column (score column first), a single value is applied to all columns
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::{psm_columns, Identification, Precursor};
+    /// use polars::prelude::*;
+    ///
+    /// let psms = df!(
+    ///     "num" => &[1u32, 2, 3],
+    ///     "xcorr" => &[Some(2.5), None, Some(2.5)],
+    ///     "delta_cn" => &[0.1, 0.3, 0.2]
+    /// ).unwrap();
+    /// let mut identification = Identification::new(None, Some(psms), Precursor::new(400.7, 2));
+    /// identification.rerank("xcorr", &["delta_cn"], &[true]).unwrap();
+    /// let ranks = identification.get_psms().as_ref().unwrap().column(psm_columns::RANK).unwrap().clone();
+    /// assert_eq!(ranks.u32().unwrap().into_no_null_iter().collect::<Vec<_>>(), vec![2, 3, 1]);
+    /// ```
+    ///
+    pub fn rerank(
+        &mut self,
+        score_column: &str,
+        tie_breakers: &[&str],
+        descending: &[bool],
+    ) -> anyhow::Result<()> {
+        let psms = match self.psms.as_mut() {
+            Some(psms) => psms,
+            None => return Ok(()),
+        };
+        let by_columns = std::iter::once(score_column)
+            .chain(tie_breakers.iter().copied())
+            .map(|column| psms.column(column).cloned())
+            .collect::<PolarsResult<Vec<Series>>>()?;
+        let descending = match descending {
+            [descending] => vec![*descending; by_columns.len()],
+            descending => descending.to_vec(),
+        };
+        let order = DataFrame::new(vec![Series::new(
+            "row_index",
+            (0..psms.height() as u32).collect::<Vec<u32>>(),
+        )])?
+        .sort_impl(by_columns, descending, true, true, None, false)?;
+        let mut ranks = vec![0u32; psms.height()];
+        for (rank, row_index) in order
+            .column("row_index")?
+            .u32()?
+            .into_no_null_iter()
+            .enumerate()
+        {
+            ranks[row_index as usize] = rank as u32 + 1;
         }
+        psms.with_column(Series::new(psm_columns::RANK, ranks))?;
+        Ok(())
+    }
 
-        // Calculate histogram counts
-        let mut histogram: Vec<usize> = vec![0; num_bins];
+    pub fn iter_goodness_rows(&self) -> Option<RowIter<'_>> {
+        let iter = RowIter::new(self.goodnesses.as_ref()?);
+        Some(iter)
+    }
 
-        for val in score.f64().unwrap() {
-            let val = val.unwrap();
-            for (i, &bin) in bins.iter().enumerate().skip(1) {
-                if (val as f64) <= bin {
-                    histogram[i - 1] += 1;
-                    break;
-                }
+    /// Calls `f` for each goodness of fit reusing a single row buffer, see `RowIter::for_each_row`.
+    /// Returns false if there are no goodness of fits.
+    ///
+    pub fn for_each_goodness_row<'a>(&'a self, f: impl FnMut(&Row<'a>)) -> bool {
+        match self.iter_goodness_rows() {
+            Some(rows) => {
+                rows.for_each_row(f);
+                true
             }
+            None => false,
+        }
+    }
+
+    /// Typed goodness of fits (see `GoodnessOfFit::from_dataframe`), None if there is no goodness table
+    ///
+    pub fn get_goodness_of_fits(&self) -> Result<Option<Vec<GoodnessOfFit>>, ColumnError> {
+        match self.goodnesses.as_ref() {
+            Some(goodnesses) => Ok(Some(GoodnessOfFit::from_dataframe(goodnesses)?)),
+            None => Ok(None),
         }
+    }
 
-        Some((bins, histogram))
+    /// Replaces the goodness table with the given goodness of fits
+    ///
+    pub fn set_goodness_of_fits(&mut self, goodnesses: &[GoodnessOfFit]) -> anyhow::Result<()> {
+        self.goodnesses = Some(GoodnessOfFit::to_dataframe(goodnesses)?);
+        Ok(())
+    }
+
+    /// Histogram of the original search engine score (xcorr, for Comet)
+    /// Bin number is calculated using the rule of Sturges
+    ///
+    pub fn get_score_histogram(&self) -> Option<(Vec<f64>, Vec<usize>)> {
+        self.get_score_histogram_for(psm_columns::XCORR, BinStrategy::Sturges)
+            .map(Histogram::into_parts)
+    }
+
+    /// Histogram of the given numeric PSM column, nulls are ignored.
+    /// Returns None if there are no PSMs, the column does not exist, is not numeric or contains no values.
+    ///
+    /// # Arguments
+    /// * `column` - Name of the PSM column
+    /// * `binning` - Strategy to determine the number of bins
+    ///
+    pub fn get_score_histogram_for(&self, column: &str, binning: BinStrategy) -> Option<Histogram> {
+        let score = self
+            .psms
+            .as_ref()?
+            .column(column)
+            .ok()?
+            .cast(&DataType::Float64)
+            .ok()?;
+        let values = score.f64().ok()?.into_no_null_iter().collect::<Vec<f64>>();
+        Histogram::from_values(&values, binning)
+    }
+
+    /// Histogram of the given numeric PSM column like `get_score_histogram_for`, but computed in parallel
+    /// on the chunks of the column without copying the values (see `par_histogram`).
+    ///
+    pub fn par_score_histogram(&self, column: &str, binning: BinStrategy) -> Option<Histogram> {
+        par_histogram(&self.get_psm_column_f64(column)?, binning)
+    }
+
+    /// Mean, standard deviation, min, max, median and the given quantiles of the numeric PSM column,
+    /// computed in parallel (see `par_summary`).
+    /// Returns None if there are no PSMs, the column does not exist, is not numeric or contains no values.
+    ///
+    pub fn par_score_summary(&self, column: &str, quantiles: &[f64]) -> Option<Summary> {
+        par_summary(&self.get_psm_column_f64(column)?, quantiles)
+    }
+
+    /// Fits the given distributions to the numeric PSM column, nulls are ignored.
+    /// Distributions which cannot be fitted to the values are omitted (see `Distribution::fit`).
+    /// Returns None if there are no PSMs or the column does not exist or is not numeric.
+    ///
+    /// The fits can be stored alongside the PSMs with `set_goodness_of_fits`.
+    ///
+    pub fn fit_score_distributions(
+        &self,
+        column: &str,
+        distributions: &[Distribution],
+    ) -> Option<Vec<GoodnessOfFit>> {
+        let values = self
+            .get_psm_column_f64(column)?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<f64>>();
+        Some(
+            distributions
+                .iter()
+                .filter_map(|distribution| distribution.fit(&values))
+                .collect(),
+        )
+    }
+
+    fn get_psm_column_f64(&self, column: &str) -> Option<Float64Chunked> {
+        let values = self
+            .psms
+            .as_ref()?
+            .column(column)
+            .ok()?
+            .cast(&DataType::Float64)
+            .ok()?;
+        Some(values.f64().ok()?.clone())
     }
 }
 
-/// Represents a spectrum and its content (e.g. the identifications that are part of the spectrum)
-#[derive(serde::Serialize, serde::Deserialize)]
+/// Represents a spectrum and its content (e.g. the identifications that are part of the spectrum).
+/// Peaks are shared between clones, so cloning does not copy the m/z and intensity values.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Spectrum {
-    search_uuid: String,
-    ms_run_name: String,
-    spectrum_id: String,
-    mz: Vec<f64>,
-    intensity: Vec<f64>,
+    #[serde(default)]
+    schema_version: u32,
+    #[serde(default)]
+    revision: u64,
+    search_uuid: SearchUuid,
+    ms_run_name: MsRunName,
+    spectrum_id: SpectrumId,
+    #[serde(default)]
+    retention_time: Option<f64>,
+    #[serde(default)]
+    ion_mobility: Option<f64>,
+    #[serde(default)]
+    ms_level: Option<u8>,
+    #[serde(default)]
+    scan_number: Option<u32>,
+    #[cfg_attr(
+        feature = "binary_peaks",
+        serde(deserialize_with = "crate::serialization::binary_peaks::deserialize_peaks")
+    )]
+    mz: Arc<[f64]>,
+    #[cfg_attr(
+        feature = "binary_peaks",
+        serde(deserialize_with = "crate::serialization::binary_peaks::deserialize_peaks")
+    )]
+    intensity: Arc<[f64]>,
     identifications: Vec<Identification>,
+    #[serde(default)]
+    sequence_tags: Vec<SequenceTag>,
+    #[serde(default)]
+    payload_digest: Option<String>,
 }
 
 impl Spectrum {
+    /// Peaks can be passed as `Vec<f64>` or already shared as `Arc<[f64]>`
+    ///
     pub fn new(
-        search_uuid: String,
-        ms_run_name: String,
-        spectrum_id: String,
-        mz: Vec<f64>,
-        intensity: Vec<f64>,
+        search_uuid: SearchUuid,
+        ms_run_name: MsRunName,
+        spectrum_id: SpectrumId,
+        mz: impl Into<Arc<[f64]>>,
+        intensity: impl Into<Arc<[f64]>>,
         identifications: Vec<Identification>,
     ) -> Self {
         Self {
+            schema_version: SCHEMA_VERSION,
+            revision: 0,
             search_uuid,
             ms_run_name,
             spectrum_id,
-            mz,
-            intensity,
+            retention_time: None,
+            ion_mobility: None,
+            ms_level: None,
+            scan_number: None,
+            mz: mz.into(),
+            intensity: intensity.into(),
             identifications,
+            sequence_tags: Vec::new(),
+            payload_digest: None,
         }
     }
 
-    pub fn get_search_uuid(&self) -> &str {
+    /// Sets the retention time in seconds
+    ///
+    pub fn with_retention_time(mut self, retention_time: Option<f64>) -> Self {
+        self.retention_time = retention_time;
+        self
+    }
+
+    /// Sets the ion mobility, e.g. the inverse reduced mobility (1/K0) for timsTOF data
+    ///
+    pub fn with_ion_mobility(mut self, ion_mobility: Option<f64>) -> Self {
+        self.ion_mobility = ion_mobility;
+        self
+    }
+
+    pub fn with_ms_level(mut self, ms_level: Option<u8>) -> Self {
+        self.ms_level = ms_level;
+        self
+    }
+
+    pub fn with_scan_number(mut self, scan_number: Option<u32>) -> Self {
+        self.scan_number = scan_number;
+        self
+    }
+
+    pub fn get_search_uuid(&self) -> &SearchUuid {
         &self.search_uuid
     }
 
-    pub fn get_ms_run(&self) -> &str {
+    pub fn get_ms_run(&self) -> &MsRunName {
         &self.ms_run_name
     }
 
-    pub fn get_spectra_id(&self) -> &str {
+    pub fn get_spectra_id(&self) -> &SpectrumId {
         &self.spectrum_id
     }
 
-    pub fn get_mz(&self) -> &Vec<f64> {
+    /// Retention time in seconds
+    ///
+    pub fn get_retention_time(&self) -> &Option<f64> {
+        &self.retention_time
+    }
+
+    pub fn get_ion_mobility(&self) -> &Option<f64> {
+        &self.ion_mobility
+    }
+
+    pub fn get_ms_level(&self) -> &Option<u8> {
+        &self.ms_level
+    }
+
+    pub fn get_scan_number(&self) -> &Option<u32> {
+        &self.scan_number
+    }
+
+    pub fn get_mz(&self) -> &[f64] {
+        &self.mz
+    }
+
+    pub fn get_intensity(&self) -> &[f64] {
+        &self.intensity
+    }
+
+    /// Shared m/z values, cloning only increments the reference count
+    ///
+    pub fn get_mz_shared(&self) -> &Arc<[f64]> {
         &self.mz
     }
 
-    pub fn get_intensity(&self) -> &Vec<f64> {
+    /// Shared intensities, cloning only increments the reference count
+    ///
+    pub fn get_intensity_shared(&self) -> &Arc<[f64]> {
         &self.intensity
     }
 
     pub fn get_identifications(&self) -> &Vec<Identification> {
         &self.identifications
     }
+
+    pub fn get_identifications_mut(&mut self) -> &mut Vec<Identification> {
+        &mut self.identifications
+    }
+
+    /// Attaches de novo sequence tags, e.g. to exchange them alongside the PSMs
+    ///
+    pub fn with_sequence_tags(mut self, sequence_tags: Vec<SequenceTag>) -> Self {
+        self.sequence_tags = sequence_tags;
+        self
+    }
+
+    pub fn add_sequence_tag(&mut self, sequence_tag: SequenceTag) {
+        self.sequence_tags.push(sequence_tag);
+    }
+
+    pub fn get_sequence_tags(&self) -> &Vec<SequenceTag> {
+        &self.sequence_tags
+    }
+
+    /// Sets the digest of peaks and identifications, see `Spectrum::seal` for computing it
+    ///
+    pub fn with_payload_digest(mut self, payload_digest: Option<String>) -> Self {
+        self.payload_digest = payload_digest;
+        self
+    }
+
+    /// Digest of peaks and identifications, e.g. `sha256:<hex>`
+    ///
+    pub fn get_payload_digest(&self) -> &Option<String> {
+        &self.payload_digest
+    }
+
+    /// Copy of the spectrum with the given peaks. All other fields, including revision and payload digest,
+    /// are kept, so reseal the copy (see `Spectrum::seal`) if the peaks differ from the digested ones.
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::Spectrum;
+    ///
+    /// let spectrum = Spectrum::new(
+    ///     "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+    ///     "run".parse().unwrap(),
+    ///     "scan=1".parse().unwrap(),
+    ///     vec![100.0, 200.0],
+    ///     vec![1.0, 2.0],
+    ///     Vec::new(),
+    /// )
+    /// .with_scan_number(Some(1))
+    /// .with_revision(3);
+    /// let copy = spectrum.with_peaks(vec![100.0], vec![1.0]);
+    /// assert_eq!(copy.get_mz(), &[100.0]);
+    /// assert_eq!(copy.get_scan_number(), &Some(1));
+    /// assert_eq!(copy.get_revision(), 3);
+    /// ```
+    ///
+    /// # Arguments
+    /// * `mz` - m/z values of the copy
+    /// * `intensity` - Intensities of the copy
+    ///
+    pub fn with_peaks(&self, mz: impl Into<Arc<[f64]>>, intensity: impl Into<Arc<[f64]>>) -> Self {
+        Self {
+            mz: mz.into(),
+            intensity: intensity.into(),
+            ..self.clone()
+        }
+    }
+
+    /// Assigns the spectrum to the given search
+    ///
+    pub fn with_search_uuid(mut self, search_uuid: SearchUuid) -> Self {
+        self.search_uuid = search_uuid;
+        self
+    }
+
+    /// Merges the identifications of the other spectrum (e.g. from a rerun) into this one.
+    /// Identifications of the same precursor (m/z and charge) are concatenated (see `Identification::concat`),
+    /// others are added, as are sequence tags not yet part of this spectrum.
+    /// Peaks are kept, missing metadata is taken from the other spectrum.
+    /// Fails if the spectra have a different MS run or spectrum ID or identifications cannot be concatenated,
+    /// leaving this spectrum unchanged. The payload digest is removed and the revision is set above the revisions
+    /// of both spectra.
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::{Identification, Precursor, Spectrum};
+    /// use polars::prelude::*;
+    ///
+    /// let spectrum = |psms: DataFrame| {
+    ///     Spectrum::new(
+    ///         "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+    ///         "run".parse().unwrap(),
+    ///         "scan=1".parse().unwrap(),
+    ///         vec![100.0],
+    ///         vec![1.0],
+    ///         vec![Identification::new(None, Some(psms), Precursor::new(400.7, 2))],
+    ///     )
+    /// };
+    /// let mut merged = spectrum(df!("xcorr" => &[2.5]).unwrap());
+    /// // xcorr cannot be cast to a float, so the PSMs cannot be concatenated
+    /// let other = spectrum(df!("xcorr" => &["high"]).unwrap()).with_retention_time(Some(12.0)).with_revision(3);
+    /// assert!(merged.merge(other).is_err());
+    /// assert_eq!(merged.get_retention_time(), &None);
+    /// assert_eq!(merged.get_revision(), 0);
+    /// assert_eq!(merged.get_identifications()[0].get_psms().as_ref().unwrap().height(), 1);
+    ///
+    /// merged.merge(spectrum(df!("xcorr" => &[1.5]).unwrap())).unwrap();
+    /// let xcorrs = merged.get_identifications()[0]
+    ///     .iter_psm_rows()
+    ///     .unwrap()
+    ///     .map(|row| row.get_f64("xcorr").unwrap())
+    ///     .collect::<Vec<f64>>();
+    /// assert_eq!(xcorrs, vec![2.5, 1.5]);
+    /// ```
+    ///
+    pub fn merge(&mut self, other: Spectrum) -> Result<(), MergeError> {
+        if self.ms_run_name != other.ms_run_name || self.spectrum_id != other.spectrum_id {
+            return Err(MergeError::SpectrumMismatch(
+                format!("{}:{}", self.ms_run_name, self.spectrum_id),
+                format!("{}:{}", other.ms_run_name, other.spectrum_id),
+            ));
+        }
+        // concatenation is the only step which can fail, so it is done on a copy before changing anything
+        let mut identifications = self.identifications.clone();
+        for identification in other.identifications {
+            match identifications.iter_mut().find(|existing| {
+                existing.get_charge() == identification.get_charge()
+                    && existing.get_precursor().get_mz() == identification.get_precursor().get_mz()
+            }) {
+                Some(existing) => existing.concat(identification)?,
+                None => identifications.push(identification),
+            }
+        }
+        self.identifications = identifications;
+        self.retention_time = self.retention_time.or(other.retention_time);
+        self.ion_mobility = self.ion_mobility.or(other.ion_mobility);
+        self.ms_level = self.ms_level.or(other.ms_level);
+        self.scan_number = self.scan_number.or(other.scan_number);
+        self.payload_digest = None;
+        self.revision = self.revision.max(other.revision) + 1;
+        for sequence_tag in other.sequence_tags {
+            if !self.sequence_tags.contains(&sequence_tag) {
+                self.sequence_tags.push(sequence_tag);
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the identification or replaces the identification of the same precursor (m/z and charge).
+    /// The payload digest is removed.
+    ///
+    /// # Arguments
+    /// * `identification` - Identification to add
+    ///
+    pub fn put_identification(&mut self, identification: Identification) {
+        self.payload_digest = None;
+        match self.identifications.iter_mut().find(|existing| {
+            existing.get_charge() == identification.get_charge()
+                && existing.get_precursor().get_mz() == identification.get_precursor().get_mz()
+        }) {
+            Some(existing) => *existing = identification,
+            None => self.identifications.push(identification),
+        }
+    }
+
+    /// Version of the layout the spectrum was created with, 0 for payloads from before versioning
+    ///
+    pub fn get_schema_version(&self) -> u32 {
+        self.schema_version
+    }
+
+    /// Sets the revision, a counter incremented by the producer on each change (see `ContentHash`)
+    ///
+    pub fn with_revision(mut self, revision: u64) -> Self {
+        self.revision = revision;
+        self
+    }
+
+    pub fn get_revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Increments and returns the revision, e.g. after updating the identifications
+    ///
+    pub fn increment_revision(&mut self) -> u64 {
+        self.revision += 1;
+        self.revision
+    }
+
+    /// Returns the normalized intensities, leaving the spectrum untouched
+    ///
+    pub fn normalized_intensity(&self, normalization: Normalization) -> Vec<f64> {
+        normalization.apply(&self.intensity)
+    }
+
+    /// Normalizes the intensities in place, the payload digest is removed
+    ///
+    pub fn normalize(&mut self, normalization: Normalization) {
+        self.payload_digest = None;
+        self.intensity = normalization.apply(&self.intensity).into();
+    }
+
+    /// Removes the peaks rejected by the filter in place, the payload digest is removed
+    ///
+    pub fn filter_peaks(&mut self, filter: PeakFilter) {
+        self.payload_digest = None;
+        let keep = filter.apply(&self.mz, &self.intensity);
+        self.mz = keep.iter().map(|idx| self.mz[*idx]).collect();
+        self.intensity = keep.iter().map(|idx| self.intensity[*idx]).collect();
+    }
+
+    /// Returns a builder for validated construction of a spectrum
+    ///
+    pub fn builder() -> SpectrumBuilder {
+        SpectrumBuilder::default()
+    }
+}
+
+/// Error when building a spectrum with invalid content
+///
+#[derive(Debug, thiserror::Error)]
+pub enum BuildError {
+    #[error("spectrum id is empty")]
+    EmptySpectrumId,
+    #[error("number of m/z values ({mz}) and intensities ({intensity}) differ")]
+    PeakLengthMismatch { mz: usize, intensity: usize },
+    #[error("m/z values are not sorted ascending at index {0}")]
+    UnsortedMz(usize),
+    #[error(transparent)]
+    InvalidIdentifier(#[from] IdentifierError),
+}
+
+/// Builder for `Spectrum`, validating the content on `build`
+///
+#[derive(Clone, Default)]
+pub struct SpectrumBuilder {
+    search_uuid: String,
+    ms_run_name: String,
+    spectrum_id: String,
+    retention_time: Option<f64>,
+    ion_mobility: Option<f64>,
+    ms_level: Option<u8>,
+    scan_number: Option<u32>,
+    mz: Vec<f64>,
+    intensity: Vec<f64>,
+    identifications: Vec<Identification>,
+}
+
+impl SpectrumBuilder {
+    /// UUID of the search, the nil UUID if not set
+    ///
+    pub fn search_uuid(mut self, search_uuid: impl Into<String>) -> Self {
+        self.search_uuid = search_uuid.into();
+        self
+    }
+
+    pub fn ms_run_name(mut self, ms_run_name: impl Into<String>) -> Self {
+        self.ms_run_name = ms_run_name.into();
+        self
+    }
+
+    pub fn spectrum_id(mut self, spectrum_id: impl Into<String>) -> Self {
+        self.spectrum_id = spectrum_id.into();
+        self
+    }
+
+    /// Retention time in seconds
+    ///
+    pub fn retention_time(mut self, retention_time: f64) -> Self {
+        self.retention_time = Some(retention_time);
+        self
+    }
+
+    pub fn ion_mobility(mut self, ion_mobility: f64) -> Self {
+        self.ion_mobility = Some(ion_mobility);
+        self
+    }
+
+    pub fn ms_level(mut self, ms_level: u8) -> Self {
+        self.ms_level = Some(ms_level);
+        self
+    }
+
+    pub fn scan_number(mut self, scan_number: u32) -> Self {
+        self.scan_number = Some(scan_number);
+        self
+    }
+
+    pub fn mz(mut self, mz: Vec<f64>) -> Self {
+        self.mz = mz;
+        self
+    }
+
+    pub fn intensity(mut self, intensity: Vec<f64>) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn identifications(mut self, identifications: Vec<Identification>) -> Self {
+        self.identifications = identifications;
+        self
+    }
+
+    pub fn add_identification(mut self, identification: Identification) -> Self {
+        self.identifications.push(identification);
+        self
+    }
+
+    /// Validates the content and builds the spectrum.
+    /// Fails if the spectrum id is empty, an identifier is invalid, m/z and intensity have different lengths
+    /// or m/z is not sorted ascending.
+    ///
+    pub fn build(self) -> Result<Spectrum, BuildError> {
+        if self.spectrum_id.is_empty() {
+            return Err(BuildError::EmptySpectrumId);
+        }
+        if self.mz.len() != self.intensity.len() {
+            return Err(BuildError::PeakLengthMismatch {
+                mz: self.mz.len(),
+                intensity: self.intensity.len(),
+            });
+        }
+        if let Some(index) = self.mz.windows(2).position(|pair| pair[0] > pair[1]) {
+            return Err(BuildError::UnsortedMz(index + 1));
+        }
+        let search_uuid = match self.search_uuid.is_empty() {
+            true => SearchUuid::nil(),
+            false => SearchUuid::new(&self.search_uuid)?,
+        };
+        Ok(Spectrum::new(
+            search_uuid,
+            MsRunName::new(&self.ms_run_name)?,
+            SpectrumId::new(&self.spectrum_id)?,
+            self.mz,
+            self.intensity,
+            self.identifications,
+        )
+        .with_retention_time(self.retention_time)
+        .with_ion_mobility(self.ion_mobility)
+        .with_ms_level(self.ms_level)
+        .with_scan_number(self.scan_number))
+    }
 }
@@ -0,0 +1,308 @@
+//! Byte offsets of the spectra within their original input files, so single raw spectra
+//! can be fetched on demand without rescanning the files. Stored with the `MsRun`.
+
+// std imports
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+// 3rd party imports
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::io::mgf;
+#[cfg(feature = "mzml")]
+use crate::io::mzml;
+use crate::results_api::{Spectrum, SpectrumId};
+
+/// Format of an indexed input file
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum SourceFormat {
+    Mzml,
+    Mgf,
+}
+
+/// Indexed input file
+///
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SourceFile {
+    path: String,
+    format: SourceFormat,
+    /// Byte offset and length of the `<referenceableParamGroupList>` of an mzML
+    #[serde(default)]
+    param_groups: Option<(u64, u64)>,
+}
+
+impl SourceFile {
+    /// Path of the file as given when indexing, relative paths are relative to the working directory of the reader
+    ///
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn get_format(&self) -> SourceFormat {
+        self.format
+    }
+
+    /// Byte offset and length of the `<referenceableParamGroupList>` of an mzML, which is needed to read
+    /// spectra referring to param groups
+    ///
+    pub fn get_param_groups(&self) -> Option<(u64, u64)> {
+        self.param_groups
+    }
+}
+
+/// Location of a spectrum, from the start of the `<spectrum>` element or `BEGIN IONS`
+/// to the end of `</spectrum>` or `END IONS`
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SpectrumOffset {
+    file_index: usize,
+    offset: u64,
+    length: u64,
+}
+
+impl SpectrumOffset {
+    /// Index of the file in `SpectrumIndex::get_files`
+    ///
+    pub fn get_file_index(&self) -> usize {
+        self.file_index
+    }
+
+    /// Byte offset within the file
+    ///
+    pub fn get_offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Length in bytes
+    ///
+    pub fn get_length(&self) -> u64 {
+        self.length
+    }
+}
+
+/// Maps spectrum IDs to their location within the input files
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::spectrum_index::{SourceFormat, SpectrumIndex};
+///
+/// let path = std::env::temp_dir().join("spectrum_index_doctest.mgf");
+/// std::fs::write(
+///     &path,
+///     "BEGIN IONS\nTITLE=scan=1\nPEPMASS=400.7\nCHARGE=2+\n175.119 100\nEND IONS\n\
+///      BEGIN IONS\nTITLE=scan=2\nPEPMASS=500.2\nCHARGE=3+\n276.155 200\nEND IONS\n",
+/// )
+/// .unwrap();
+///
+/// let index = SpectrumIndex::from_file(&path, SourceFormat::Mgf).unwrap();
+/// assert_eq!(index.len(), 2);
+/// assert_eq!(index.get("scan=2").unwrap().get_offset(), 69);
+///
+/// let spectrum = index
+///     .read_spectrum("scan=2", "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c", "run")
+///     .unwrap()
+///     .unwrap();
+/// assert_eq!(spectrum.get_mz(), &[276.155]);
+/// assert!(index.read_spectrum("scan=3", "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c", "run").unwrap().is_none());
+/// ```
+///
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SpectrumIndex {
+    files: Vec<SourceFile>,
+    offsets: BTreeMap<SpectrumId, SpectrumOffset>,
+}
+
+impl SpectrumIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Indexes the spectra of the given file
+    ///
+    /// # Arguments
+    /// * `path` - Path of the mzML or MGF
+    /// * `format` - Format of the file
+    ///
+    pub fn from_file(path: &Path, format: SourceFormat) -> Result<Self> {
+        let mut index = Self::new();
+        index.index_file(path, format)?;
+        Ok(index)
+    }
+
+    /// Adds the spectra of the given file, e.g. of another fraction of the MS run.
+    /// Spectra already in the index are replaced.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the mzML or MGF
+    /// * `format` - Format of the file
+    ///
+    pub fn index_file(&mut self, path: &Path, format: SourceFormat) -> Result<()> {
+        let file =
+            File::open(path).with_context(|| format!("could not open {}", path.display()))?;
+        let reader = std::io::BufReader::new(file);
+        let (locations, param_groups) = match format {
+            SourceFormat::Mgf => (mgf::index(reader)?, None),
+            #[cfg(feature = "mzml")]
+            SourceFormat::Mzml => mzml::index_with_param_groups(reader)?,
+            #[cfg(not(feature = "mzml"))]
+            SourceFormat::Mzml => bail!("indexing mzML requires the `mzml` feature"),
+        };
+        let file_index = self.add_file(&path.to_string_lossy(), format);
+        self.files[file_index].param_groups = param_groups;
+        for (spectrum_id, offset, length) in locations {
+            let spectrum_id = SpectrumId::new(&spectrum_id)
+                .with_context(|| format!("invalid spectrum ID in {}", path.display()))?;
+            self.insert(spectrum_id, file_index, offset, length);
+        }
+        Ok(())
+    }
+
+    /// Adds the file if not yet part of the index
+    ///
+    /// Returns the index of the file
+    ///
+    pub fn add_file(&mut self, path: &str, format: SourceFormat) -> usize {
+        match self.files.iter().position(|file| file.path == path) {
+            Some(file_index) => file_index,
+            None => {
+                self.files.push(SourceFile {
+                    path: path.to_string(),
+                    format,
+                    param_groups: None,
+                });
+                self.files.len() - 1
+            }
+        }
+    }
+
+    /// Adds or replaces the location of a spectrum
+    ///
+    /// # Arguments
+    /// * `spectrum_id` - ID of the spectrum
+    /// * `file_index` - Index of the file, see `add_file`
+    /// * `offset` - Byte offset within the file
+    /// * `length` - Length in bytes
+    ///
+    pub fn insert(&mut self, spectrum_id: SpectrumId, file_index: usize, offset: u64, length: u64) {
+        self.offsets.insert(
+            spectrum_id,
+            SpectrumOffset {
+                file_index,
+                offset,
+                length,
+            },
+        );
+    }
+
+    /// Location of the given spectrum
+    ///
+    pub fn get(&self, spectrum_id: &str) -> Option<&SpectrumOffset> {
+        self.offsets.get(spectrum_id)
+    }
+
+    pub fn get_files(&self) -> &Vec<SourceFile> {
+        &self.files
+    }
+
+    /// File containing the spectrum at the given location
+    ///
+    pub fn get_file(&self, offset: &SpectrumOffset) -> Option<&SourceFile> {
+        self.files.get(offset.file_index)
+    }
+
+    /// Number of indexed spectra
+    ///
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Reads the raw `<spectrum>` element or MGF block of the given spectrum.
+    /// Returns None if the spectrum is not indexed.
+    ///
+    pub fn read_raw(&self, spectrum_id: &str) -> Result<Option<(SourceFormat, Vec<u8>)>> {
+        let location = match self.get(spectrum_id) {
+            Some(location) => location,
+            None => return Ok(None),
+        };
+        let source = match self.get_file(location) {
+            Some(source) => source,
+            None => bail!(
+                "spectrum `{}` refers to unknown file {}",
+                spectrum_id,
+                location.file_index
+            ),
+        };
+        let raw = read_range(source, location.offset, location.length)?;
+        Ok(Some((source.format, raw)))
+    }
+
+    /// Reads and parses the given spectrum from its input file. Returns None if the spectrum is not indexed.
+    ///
+    /// # Arguments
+    /// * `spectrum_id` - ID of the spectrum
+    /// * `search_uuid` - UUID of the search the spectrum is assigned to
+    /// * `ms_run_name` - Name of the MS run the spectrum is assigned to
+    ///
+    pub fn read_spectrum(
+        &self,
+        spectrum_id: &str,
+        search_uuid: &str,
+        ms_run_name: &str,
+    ) -> Result<Option<Spectrum>> {
+        let (format, raw) = match self.read_raw(spectrum_id)? {
+            Some(raw) => raw,
+            None => return Ok(None),
+        };
+        let spectra = match format {
+            SourceFormat::Mgf => {
+                mgf::read_with_ids(raw.as_slice(), search_uuid, ms_run_name, |_| {
+                    spectrum_id.to_string()
+                })?
+            }
+            #[cfg(feature = "mzml")]
+            SourceFormat::Mzml => {
+                // the spectrum may refer to param groups of the header
+                let param_groups = match self
+                    .get(spectrum_id)
+                    .and_then(|location| self.get_file(location))
+                    .and_then(|source| Some((source, source.param_groups?)))
+                {
+                    Some((source, (offset, length))) => read_range(source, offset, length)?,
+                    None => Vec::new(),
+                };
+                mzml::read_fragment(&param_groups, &raw, search_uuid, ms_run_name)?
+            }
+            #[cfg(not(feature = "mzml"))]
+            SourceFormat::Mzml => bail!("reading mzML requires the `mzml` feature"),
+        };
+        match spectra.into_iter().next() {
+            Some(spectrum) => Ok(Some(spectrum)),
+            None => bail!("no spectrum at the indexed location of `{}`", spectrum_id),
+        }
+    }
+}
+
+/// Reads `length` bytes from the given offset of the source file
+///
+fn read_range(source: &SourceFile, offset: u64, length: u64) -> Result<Vec<u8>> {
+    let mut file =
+        File::open(&source.path).with_context(|| format!("could not open {}", source.path))?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut raw = vec![0; length as usize];
+    file.read_exact(&mut raw)
+        .with_context(|| format!("{} is shorter than indexed", source.path))?;
+    Ok(raw)
+}
@@ -0,0 +1,181 @@
+// std imports
+use std::sync::OnceLock;
+
+// 3rd party imports
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::results_api::Spectrum;
+
+/// Metadata of a spectrum without peaks and identifications, e.g. for listing spectra.
+/// The optional URI tells the client where to fetch the full spectrum from.
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SpectrumRef {
+    search_uuid: String,
+    ms_run_name: String,
+    spectrum_id: String,
+    #[serde(default)]
+    retention_time: Option<f64>,
+    #[serde(default)]
+    ion_mobility: Option<f64>,
+    #[serde(default)]
+    ms_level: Option<u8>,
+    #[serde(default)]
+    scan_number: Option<u32>,
+    num_peaks: usize,
+    num_identifications: usize,
+    #[serde(default)]
+    uri: Option<String>,
+}
+
+impl SpectrumRef {
+    pub fn get_search_uuid(&self) -> &str {
+        &self.search_uuid
+    }
+
+    pub fn get_ms_run(&self) -> &str {
+        &self.ms_run_name
+    }
+
+    pub fn get_spectra_id(&self) -> &str {
+        &self.spectrum_id
+    }
+
+    /// Retention time in seconds
+    ///
+    pub fn get_retention_time(&self) -> &Option<f64> {
+        &self.retention_time
+    }
+
+    pub fn get_ion_mobility(&self) -> &Option<f64> {
+        &self.ion_mobility
+    }
+
+    pub fn get_ms_level(&self) -> &Option<u8> {
+        &self.ms_level
+    }
+
+    pub fn get_scan_number(&self) -> &Option<u32> {
+        &self.scan_number
+    }
+
+    pub fn get_num_peaks(&self) -> usize {
+        self.num_peaks
+    }
+
+    pub fn get_num_identifications(&self) -> usize {
+        self.num_identifications
+    }
+
+    /// Sets the location of the full spectrum, e.g. the URL of the results API endpoint
+    ///
+    pub fn with_uri(mut self, uri: Option<String>) -> Self {
+        self.uri = uri;
+        self
+    }
+
+    pub fn get_uri(&self) -> &Option<String> {
+        &self.uri
+    }
+}
+
+impl Spectrum {
+    /// Metadata of the spectrum without peaks and identifications
+    ///
+    pub fn to_ref(&self) -> SpectrumRef {
+        SpectrumRef {
+            search_uuid: self.get_search_uuid().to_string(),
+            ms_run_name: self.get_ms_run().to_string(),
+            spectrum_id: self.get_spectra_id().to_string(),
+            retention_time: *self.get_retention_time(),
+            ion_mobility: *self.get_ion_mobility(),
+            ms_level: *self.get_ms_level(),
+            scan_number: *self.get_scan_number(),
+            num_peaks: self.get_mz().len(),
+            num_identifications: self.get_identifications().len(),
+            uri: None,
+        }
+    }
+}
+
+/// Loads the full spectrum of a reference, e.g. from storage or via its URI
+pub type SpectrumLoader = Box<dyn Fn(&SpectrumRef) -> Result<Spectrum> + Send + Sync>;
+
+/// Spectrum reference which loads the peaks and identifications on first access
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::{LazySpectrum, Spectrum};
+///
+/// let spectrum = Spectrum::new(
+///     "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+///     "run".parse().unwrap(),
+///     "scan=1".parse().unwrap(),
+///     vec![100.0, 200.0],
+///     vec![1.0, 2.0],
+///     Vec::new(),
+/// );
+/// let lazy = LazySpectrum::new(
+///     spectrum.to_ref(),
+///     Box::new(|_| Ok(Spectrum::new(
+///         "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+///         "run".parse().unwrap(),
+///         "scan=1".parse().unwrap(),
+///         vec![100.0, 200.0],
+///         vec![1.0, 2.0],
+///         Vec::new(),
+///     ))),
+/// );
+/// assert_eq!(lazy.get_ref().get_num_peaks(), 2);
+/// assert!(!lazy.is_loaded());
+/// assert_eq!(lazy.load().unwrap().get_mz(), &vec![100.0, 200.0]);
+/// assert!(lazy.is_loaded());
+/// ```
+///
+pub struct LazySpectrum {
+    reference: SpectrumRef,
+    loader: SpectrumLoader,
+    spectrum: OnceLock<Spectrum>,
+}
+
+impl LazySpectrum {
+    pub fn new(reference: SpectrumRef, loader: SpectrumLoader) -> Self {
+        Self {
+            reference,
+            loader,
+            spectrum: OnceLock::new(),
+        }
+    }
+
+    /// Metadata, available without loading
+    ///
+    pub fn get_ref(&self) -> &SpectrumRef {
+        &self.reference
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.spectrum.get().is_some()
+    }
+
+    /// Returns the full spectrum, calling the loader on first access.
+    /// If loading fails, the next access tries again.
+    ///
+    pub fn load(&self) -> Result<&Spectrum> {
+        if let Some(spectrum) = self.spectrum.get() {
+            return Ok(spectrum);
+        }
+        let spectrum = (self.loader)(&self.reference)?;
+        Ok(self.spectrum.get_or_init(|| spectrum))
+    }
+
+    /// Returns the full spectrum, loading it if not yet done
+    ///
+    pub fn into_spectrum(mut self) -> Result<Spectrum> {
+        match self.spectrum.take() {
+            Some(spectrum) => Ok(spectrum),
+            None => (self.loader)(&self.reference),
+        }
+    }
+}
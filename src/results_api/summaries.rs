@@ -0,0 +1,300 @@
+//! Concise summaries of searches, MS runs and spectra for logs and CLI output.
+//!
+//! `Display` prints a one-line summary, e.g. `Spectrum scan=2310: 1,204 peaks, 3 charge states, 150 PSMs`,
+//! and `Debug` prints the summary struct instead of dumping the peaks and DataFrames.
+
+// std imports
+use std::fmt;
+
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::results_api::{
+    MsRun, MsRunName, Search, SearchStatistics, SearchUuid, Spectrum, SpectrumId,
+};
+
+/// Summary of a search
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SearchSummary {
+    search_uuid: SearchUuid,
+    num_ms_runs: usize,
+    has_parameters: bool,
+    num_errors: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    statistics: Option<SearchStatistics>,
+}
+
+impl SearchSummary {
+    pub fn get_search_uuid(&self) -> &SearchUuid {
+        &self.search_uuid
+    }
+
+    pub fn get_num_ms_runs(&self) -> usize {
+        self.num_ms_runs
+    }
+
+    pub fn has_parameters(&self) -> bool {
+        self.has_parameters
+    }
+
+    pub fn get_num_errors(&self) -> usize {
+        self.num_errors
+    }
+
+    /// Adds identification statistics, see `Search::summary_with_statistics`
+    ///
+    pub fn with_statistics(mut self, statistics: SearchStatistics) -> Self {
+        self.statistics = Some(statistics);
+        self
+    }
+
+    /// Identification statistics over all MS runs, None if not computed
+    ///
+    pub fn get_statistics(&self) -> Option<&SearchStatistics> {
+        self.statistics.as_ref()
+    }
+}
+
+/// Summary of an MS run
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MsRunSummary {
+    search_uuid: SearchUuid,
+    ms_run_name: MsRunName,
+    num_spectra: usize,
+    num_errors: usize,
+}
+
+impl MsRunSummary {
+    pub fn get_search_uuid(&self) -> &SearchUuid {
+        &self.search_uuid
+    }
+
+    pub fn get_ms_run(&self) -> &MsRunName {
+        &self.ms_run_name
+    }
+
+    pub fn get_num_spectra(&self) -> usize {
+        self.num_spectra
+    }
+
+    pub fn get_num_errors(&self) -> usize {
+        self.num_errors
+    }
+}
+
+/// Summary of a spectrum
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SpectrumSummary {
+    ms_run_name: MsRunName,
+    spectrum_id: SpectrumId,
+    ms_level: Option<u8>,
+    retention_time: Option<f64>,
+    num_peaks: usize,
+    charges: Vec<u8>,
+    num_psms: usize,
+}
+
+impl SpectrumSummary {
+    pub fn get_ms_run(&self) -> &MsRunName {
+        &self.ms_run_name
+    }
+
+    pub fn get_spectra_id(&self) -> &SpectrumId {
+        &self.spectrum_id
+    }
+
+    pub fn get_ms_level(&self) -> Option<u8> {
+        self.ms_level
+    }
+
+    pub fn get_retention_time(&self) -> Option<f64> {
+        self.retention_time
+    }
+
+    pub fn get_num_peaks(&self) -> usize {
+        self.num_peaks
+    }
+
+    /// Charges of the identifications, one per charge state
+    ///
+    pub fn get_charges(&self) -> &Vec<u8> {
+        &self.charges
+    }
+
+    /// Number of PSMs over all charge states
+    ///
+    pub fn get_num_psms(&self) -> usize {
+        self.num_psms
+    }
+}
+
+impl Search {
+    pub fn summary(&self) -> SearchSummary {
+        SearchSummary {
+            search_uuid: self.get_search_uuid().clone(),
+            num_ms_runs: self.get_ms_run_names().len(),
+            has_parameters: self.get_parameters().is_some(),
+            num_errors: self.get_errors().len(),
+            statistics: None,
+        }
+    }
+}
+
+impl MsRun {
+    pub fn summary(&self) -> MsRunSummary {
+        MsRunSummary {
+            search_uuid: self.get_search_uuid().clone(),
+            ms_run_name: self.get_ms_run().clone(),
+            num_spectra: self.get_spectra_ids().len(),
+            num_errors: self.get_errors().len(),
+        }
+    }
+}
+
+impl Spectrum {
+    /// Summary of the spectrum
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::{Identification, Precursor, Spectrum};
+    /// use polars::prelude::*;
+    ///
+    /// let psms = df!("plain_peptide" => &["PEPTIDE", "PEPTIDES"]).unwrap();
+    /// let spectrum = Spectrum::new(
+    ///     "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+    ///     "run".parse().unwrap(),
+    ///     "scan=2310".parse().unwrap(),
+    ///     vec![100.0; 1204],
+    ///     vec![1.0; 1204],
+    ///     vec![
+    ///         Identification::new(None, Some(psms), Precursor::new(400.7, 2)),
+    ///         Identification::new(None, None, Precursor::new(267.5, 3)),
+    ///     ],
+    /// );
+    ///
+    /// assert_eq!(spectrum.summary().get_charges(), &vec![2, 3]);
+    /// assert_eq!(
+    ///     spectrum.to_string(),
+    ///     "Spectrum scan=2310: 1,204 peaks, 2 charge states, 2 PSMs"
+    /// );
+    /// ```
+    ///
+    pub fn summary(&self) -> SpectrumSummary {
+        SpectrumSummary {
+            ms_run_name: self.get_ms_run().clone(),
+            spectrum_id: self.get_spectra_id().clone(),
+            ms_level: *self.get_ms_level(),
+            retention_time: *self.get_retention_time(),
+            num_peaks: self.get_mz().len(),
+            charges: self
+                .get_identifications()
+                .iter()
+                .map(|identification| identification.get_charge())
+                .collect(),
+            num_psms: self
+                .get_identifications()
+                .iter()
+                .filter_map(|identification| identification.get_psms().as_ref())
+                .map(|psms| psms.height())
+                .sum(),
+        }
+    }
+}
+
+impl fmt::Display for SearchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Search {}: {}",
+            self.search_uuid,
+            count(self.num_ms_runs, "MS run", "MS runs")
+        )?;
+        if let Some(statistics) = &self.statistics {
+            write!(
+                f,
+                ", {} ({:.1} %)",
+                count(
+                    statistics.get_num_identified_spectra(),
+                    "identified spectrum",
+                    "identified spectra"
+                ),
+                statistics.get_id_rate() * 100.0
+            )?;
+        }
+        if self.num_errors > 0 {
+            write!(f, ", {}", count(self.num_errors, "error", "errors"))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for MsRunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "MS run {}: {}",
+            self.ms_run_name,
+            count(self.num_spectra, "spectrum", "spectra")
+        )?;
+        if self.num_errors > 0 {
+            write!(f, ", {}", count(self.num_errors, "error", "errors"))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SpectrumSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Spectrum {}: {}, {}, {}",
+            self.spectrum_id,
+            count(self.num_peaks, "peak", "peaks"),
+            count(self.charges.len(), "charge state", "charge states"),
+            count(self.num_psms, "PSM", "PSMs")
+        )
+    }
+}
+
+macro_rules! summarized {
+    ($($entity:ty),*) => {
+        $(
+            impl fmt::Display for $entity {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    fmt::Display::fmt(&self.summary(), f)
+                }
+            }
+
+            impl fmt::Debug for $entity {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    fmt::Debug::fmt(&self.summary(), f)
+                }
+            }
+        )*
+    };
+}
+
+summarized!(Search, MsRun, Spectrum);
+
+/// Formats the count with thousands separators and the singular or plural noun, e.g. `1,204 peaks`
+///
+fn count(count: usize, singular: &str, plural: &str) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (idx, digit) in digits.chars().enumerate() {
+        if idx > 0 && (digits.len() - idx).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    match count {
+        1 => format!("{} {}", formatted, singular),
+        _ => format!("{} {}", formatted, plural),
+    }
+}
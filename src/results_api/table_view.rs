@@ -0,0 +1,114 @@
+// 3rd party imports
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Values of a table column, null values are kept as `None`
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type", content = "values", rename_all = "snake_case")]
+pub enum ColumnValues {
+    Bool(Vec<Option<bool>>),
+    Int(Vec<Option<i64>>),
+    Float(Vec<Option<f64>>),
+    /// Strings and all other types, formatted as string
+    Str(Vec<Option<String>>),
+}
+
+impl ColumnValues {
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Bool(values) => values.len(),
+            Self::Int(values) => values.len(),
+            Self::Float(values) => values.len(),
+            Self::Str(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Lightweight column-store view of a PSM or goodness table, which serializes to plain arrays.
+/// Meant for clients without polars, e.g. the WASM bindings of the frontend.
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::{ColumnValues, TableView};
+/// use polars::prelude::*;
+///
+/// let psms = df!("plain_peptide" => &["PEPTIDE"], "xcorr" => &[2.5], "charge" => &[2u32]).unwrap();
+/// let view = TableView::from_dataframe(&psms).unwrap();
+/// assert_eq!(view.get_num_rows(), 1);
+/// assert_eq!(view.get_column("xcorr"), Some(&ColumnValues::Float(vec![Some(2.5)])));
+/// assert_eq!(view.get_column("charge"), Some(&ColumnValues::Int(vec![Some(2)])));
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TableView {
+    columns: Vec<String>,
+    values: Vec<ColumnValues>,
+    num_rows: usize,
+}
+
+impl TableView {
+    /// Converts the columns of the dataframe. Integer types are widened to i64, floats to f64
+    /// and all types other than booleans and numbers are casted to strings.
+    ///
+    /// # Arguments
+    /// * `dataframe` - Dataframe to convert
+    ///
+    pub fn from_dataframe(dataframe: &DataFrame) -> PolarsResult<Self> {
+        let mut columns = Vec::with_capacity(dataframe.width());
+        let mut values = Vec::with_capacity(dataframe.width());
+        for series in dataframe.get_columns() {
+            columns.push(series.name().to_string());
+            values.push(column_values(series)?);
+        }
+        Ok(Self {
+            columns,
+            values,
+            num_rows: dataframe.height(),
+        })
+    }
+
+    pub fn get_columns(&self) -> &Vec<String> {
+        &self.columns
+    }
+
+    /// Values of the given column, None if the column does not exist
+    ///
+    pub fn get_column(&self, column: &str) -> Option<&ColumnValues> {
+        self.columns
+            .iter()
+            .position(|name| name == column)
+            .map(|idx| &self.values[idx])
+    }
+
+    pub fn get_num_rows(&self) -> usize {
+        self.num_rows
+    }
+}
+
+fn column_values(series: &Series) -> PolarsResult<ColumnValues> {
+    let dtype = series.dtype();
+    if matches!(dtype, DataType::Boolean) {
+        Ok(ColumnValues::Bool(series.bool()?.into_iter().collect()))
+    } else if dtype.is_float() {
+        let values = series.cast(&DataType::Float64)?;
+        Ok(ColumnValues::Float(values.f64()?.into_iter().collect()))
+    } else if dtype.is_integer() {
+        let values = series.cast(&DataType::Int64)?;
+        Ok(ColumnValues::Int(values.i64()?.into_iter().collect()))
+    } else {
+        let values = series.cast(&DataType::Utf8)?;
+        Ok(ColumnValues::Str(
+            values
+                .utf8()?
+                .into_iter()
+                .map(|value| value.map(str::to_string))
+                .collect(),
+        ))
+    }
+}
@@ -0,0 +1,442 @@
+//! Identifications transferred from a donor run to an acceptor run (match-between-runs).
+//!
+//! Confident PSMs of peptides not identified in the acceptor run are mapped onto the acceptor run
+//! with a retention time alignment (see `crate::alignment`). The transfer is supported by the
+//! precursor's extracted ion chromatogram and/or an MS2 spectrum of the acceptor run
+//! near the predicted retention time.
+
+// std imports
+use std::collections::{HashMap, HashSet};
+
+// 3rd party imports
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::alignment::RtAlignment;
+use crate::results_api::exchange_config::DEFAULT_PRECURSOR_TOLERANCE;
+use crate::results_api::{BestPsm, Chromatogram, MsRunName, ScoreSelector, Spectrum, SpectrumRef};
+use crate::tolerance::Tolerance;
+
+/// Parameters of the transfer
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransferConfig {
+    selector: ScoreSelector,
+    score_threshold: f64,
+    rt_window: f64,
+    precursor_tolerance: Tolerance,
+}
+
+impl TransferConfig {
+    /// Transfers target PSMs with a q-value of at most 1 % within 60 s of the predicted retention time
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets which PSMs are confident enough to be transferred and count as identified in the acceptor run
+    ///
+    /// # Arguments
+    /// * `selector` - Score to select the best PSM of each spectrum
+    /// * `score_threshold` - Worst accepted score, e.g. 0.01 for q-values
+    ///
+    pub fn with_confidence(mut self, selector: ScoreSelector, score_threshold: f64) -> Self {
+        self.selector = selector;
+        self.score_threshold = score_threshold;
+        self
+    }
+
+    /// Sets the maximal distance between the predicted and observed retention time
+    ///
+    pub fn with_rt_window(mut self, rt_window: f64) -> Self {
+        self.rt_window = rt_window;
+        self
+    }
+
+    /// Sets the tolerance for the precursor m/z of the XIC and acceptor spectra
+    ///
+    pub fn with_precursor_tolerance(mut self, precursor_tolerance: Tolerance) -> Self {
+        self.precursor_tolerance = precursor_tolerance;
+        self
+    }
+
+    pub fn get_selector(&self) -> &ScoreSelector {
+        &self.selector
+    }
+
+    pub fn get_score_threshold(&self) -> f64 {
+        self.score_threshold
+    }
+
+    pub fn get_rt_window(&self) -> f64 {
+        self.rt_window
+    }
+
+    pub fn get_precursor_tolerance(&self) -> &Tolerance {
+        &self.precursor_tolerance
+    }
+
+    fn is_confident(&self, best: &BestPsm) -> bool {
+        match self.selector.is_higher_better() {
+            true => best.get_score() >= self.score_threshold,
+            false => best.get_score() <= self.score_threshold,
+        }
+    }
+}
+
+impl Default for TransferConfig {
+    fn default() -> Self {
+        Self {
+            selector: ScoreSelector::q_value(),
+            score_threshold: 0.01,
+            rt_window: 60.0,
+            precursor_tolerance: DEFAULT_PRECURSOR_TOLERANCE,
+        }
+    }
+}
+
+/// Identification transferred from a PSM of the donor run to the acceptor run
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TransferredIdentification {
+    donor: SpectrumRef,
+    plain_peptide: String,
+    #[serde(default)]
+    modified_peptide: Option<String>,
+    charge: u8,
+    precursor_mz: f64,
+    donor_score: f64,
+    acceptor_ms_run: MsRunName,
+    predicted_retention_time: f64,
+    #[serde(default)]
+    acceptor_spectrum: Option<SpectrumRef>,
+    #[serde(default)]
+    xic: Option<Chromatogram>,
+    confidence: f64,
+}
+
+impl TransferredIdentification {
+    /// # Arguments
+    /// * `donor` - Spectrum of the donor PSM
+    /// * `plain_peptide` - Peptide of the donor PSM
+    /// * `charge` - Precursor charge of the donor PSM
+    /// * `precursor_mz` - Monoisotopic precursor m/z of the donor PSM
+    /// * `donor_score` - Score of the donor PSM
+    /// * `acceptor_ms_run` - MS run the identification is transferred to
+    /// * `predicted_retention_time` - Retention time of the donor mapped onto the acceptor run
+    ///
+    pub fn new(
+        donor: SpectrumRef,
+        plain_peptide: String,
+        charge: u8,
+        precursor_mz: f64,
+        donor_score: f64,
+        acceptor_ms_run: MsRunName,
+        predicted_retention_time: f64,
+    ) -> Self {
+        Self {
+            donor,
+            plain_peptide,
+            modified_peptide: None,
+            charge,
+            precursor_mz,
+            donor_score,
+            acceptor_ms_run,
+            predicted_retention_time,
+            acceptor_spectrum: None,
+            xic: None,
+            confidence: 0.0,
+        }
+    }
+
+    pub fn with_modified_peptide(mut self, modified_peptide: Option<String>) -> Self {
+        self.modified_peptide = modified_peptide;
+        self
+    }
+
+    /// Attaches the MS2 spectrum of the acceptor run supporting the transfer
+    ///
+    pub fn with_acceptor_spectrum(mut self, acceptor_spectrum: Option<SpectrumRef>) -> Self {
+        self.acceptor_spectrum = acceptor_spectrum;
+        self
+    }
+
+    /// Attaches the precursor's extracted ion chromatogram in the acceptor run
+    ///
+    pub fn with_xic(mut self, xic: Option<Chromatogram>) -> Self {
+        self.xic = xic;
+        self
+    }
+
+    /// Sets the confidence of the transfer, clamped to 0 to 1
+    ///
+    pub fn with_confidence(mut self, confidence: f64) -> Self {
+        self.confidence = confidence.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn get_donor(&self) -> &SpectrumRef {
+        &self.donor
+    }
+
+    pub fn get_plain_peptide(&self) -> &str {
+        &self.plain_peptide
+    }
+
+    pub fn get_modified_peptide(&self) -> Option<&str> {
+        self.modified_peptide.as_deref()
+    }
+
+    pub fn get_charge(&self) -> u8 {
+        self.charge
+    }
+
+    pub fn get_precursor_mz(&self) -> f64 {
+        self.precursor_mz
+    }
+
+    pub fn get_donor_score(&self) -> f64 {
+        self.donor_score
+    }
+
+    pub fn get_acceptor_ms_run(&self) -> &MsRunName {
+        &self.acceptor_ms_run
+    }
+
+    pub fn get_predicted_retention_time(&self) -> f64 {
+        self.predicted_retention_time
+    }
+
+    pub fn get_acceptor_spectrum(&self) -> &Option<SpectrumRef> {
+        &self.acceptor_spectrum
+    }
+
+    pub fn get_xic(&self) -> &Option<Chromatogram> {
+        &self.xic
+    }
+
+    /// Confidence of the transfer from 0 to 1
+    ///
+    pub fn get_confidence(&self) -> f64 {
+        self.confidence
+    }
+
+    /// Retention time of the acceptor spectrum, otherwise of the XIC's apex
+    ///
+    pub fn get_observed_retention_time(&self) -> Option<f64> {
+        self.acceptor_spectrum
+            .as_ref()
+            .and_then(|spectrum| *spectrum.get_retention_time())
+            .or_else(|| {
+                self.xic
+                    .as_ref()
+                    .and_then(|xic| xic.get_apex())
+                    .map(|(retention_time, _)| retention_time)
+            })
+    }
+
+    /// Observed minus predicted retention time
+    ///
+    pub fn get_rt_error(&self) -> Option<f64> {
+        self.get_observed_retention_time()
+            .map(|retention_time| retention_time - self.predicted_retention_time)
+    }
+}
+
+/// Transfers the confident PSMs of the donor run to the acceptor run, see module documentation.
+/// Peptides (modified if available, otherwise plain) confidently identified in the acceptor run
+/// are skipped, as are transfers supported neither by an XIC nor an acceptor spectrum.
+/// Each peptide and charge is transferred once, from its best scoring donor PSM.
+/// The confidence decreases linearly from 1 at the predicted retention time to 0 at the edge of the window.
+///
+/// # Arguments
+/// * `donor_run` - Spectra of the donor run
+/// * `acceptor_run` - Spectra of the acceptor run
+/// * `alignment` - Alignment of the donor run onto the acceptor run
+/// * `config` - Transfer parameters
+///
+/// ```
+/// use maccoys_exchange_entities::alignment::{AlignmentMethod, RtAlignment};
+/// use maccoys_exchange_entities::results_api::{Identification, Precursor, Spectrum};
+/// use maccoys_exchange_entities::results_api::transfer::{transfer_identifications, TransferConfig};
+/// use polars::prelude::*;
+///
+/// let spectrum = |run: &str, id: &str, rt: f64, intensity: f64, peptide: Option<&str>| {
+///     let identifications = match peptide {
+///         Some(peptide) => {
+///             let psms = df!("plain_peptide" => &[peptide], "q_value" => &[0.001]).unwrap();
+///             vec![Identification::new(None, Some(psms), Precursor::new(400.7, 2))]
+///         }
+///         None => Vec::new(),
+///     };
+///     Spectrum::new(
+///         "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+///         run.parse().unwrap(),
+///         id.parse().unwrap(),
+///         vec![400.7],
+///         vec![intensity],
+///         identifications,
+///     )
+///     .with_retention_time(Some(rt))
+///     .with_ms_level(Some(if peptide.is_some() { 2 } else { 1 }))
+/// };
+/// let donor = vec![spectrum("donor", "scan=1", 600.0, 1000.0, Some("PEPTIDE"))];
+/// let acceptor = vec![
+///     spectrum("acceptor", "scan=1", 610.0, 200.0, None),
+///     spectrum("acceptor", "scan=2", 625.0, 1000.0, None),
+///     spectrum("acceptor", "scan=3", 640.0, 300.0, None),
+/// ];
+/// // acceptor elutes 30 s later
+/// let alignment = RtAlignment::fit(
+///     vec![(0.0, 30.0), (1000.0, 1030.0)],
+///     AlignmentMethod::PiecewiseLinear { num_segments: 2 },
+/// );
+///
+/// let transfers = transfer_identifications(&donor, &acceptor, &alignment, &TransferConfig::new()).unwrap();
+/// assert_eq!(transfers.len(), 1);
+/// assert_eq!(transfers[0].get_plain_peptide(), "PEPTIDE");
+/// assert_eq!(transfers[0].get_predicted_retention_time(), 630.0);
+/// assert_eq!(transfers[0].get_observed_retention_time(), Some(625.0));
+/// assert!((transfers[0].get_confidence() - 55.0 / 60.0).abs() < 1e-9);
+/// ```
+///
+pub fn transfer_identifications(
+    donor_run: &[Spectrum],
+    acceptor_run: &[Spectrum],
+    alignment: &RtAlignment,
+    config: &TransferConfig,
+) -> Result<Vec<TransferredIdentification>> {
+    let mut acceptor_peptides: HashSet<String> = HashSet::new();
+    for spectrum in acceptor_run {
+        if let Some(best) = spectrum.best_identification(config.selector.clone())? {
+            if let (true, Some(peptide)) = (config.is_confident(&best), peptide(&best)) {
+                acceptor_peptides.insert(peptide.to_string());
+            }
+        }
+    }
+
+    // best donor PSM per peptide and charge
+    let mut donors: HashMap<(String, u8), (&Spectrum, BestPsm)> = HashMap::new();
+    for spectrum in donor_run {
+        if spectrum.get_retention_time().is_none() {
+            continue;
+        }
+        let best = match spectrum.best_identification(config.selector.clone())? {
+            Some(best) if config.is_confident(&best) && best.get_plain_peptide().is_some() => best,
+            _ => continue,
+        };
+        let key = match peptide(&best) {
+            Some(peptide) if !acceptor_peptides.contains(peptide) => {
+                (peptide.to_string(), best.get_charge())
+            }
+            _ => continue,
+        };
+        let is_better = |current: &BestPsm| match config.selector.is_higher_better() {
+            true => best.get_score() > current.get_score(),
+            false => best.get_score() < current.get_score(),
+        };
+        match donors.get(&key) {
+            Some((_, current)) if !is_better(current) => (),
+            _ => {
+                donors.insert(key, (spectrum, best));
+            }
+        }
+    }
+
+    let mut transfers = donors
+        .into_values()
+        .filter_map(|(donor, best)| transfer(donor, &best, acceptor_run, alignment, config))
+        .collect::<Vec<TransferredIdentification>>();
+    transfers.sort_by(|a, b| {
+        a.predicted_retention_time
+            .total_cmp(&b.predicted_retention_time)
+            .then_with(|| a.plain_peptide.cmp(&b.plain_peptide))
+            .then(a.charge.cmp(&b.charge))
+    });
+    Ok(transfers)
+}
+
+/// Transfers a single donor PSM, None if neither XIC nor acceptor spectrum support it
+///
+fn transfer(
+    donor: &Spectrum,
+    best: &BestPsm,
+    acceptor_run: &[Spectrum],
+    alignment: &RtAlignment,
+    config: &TransferConfig,
+) -> Option<TransferredIdentification> {
+    let acceptor = acceptor_run.first()?;
+    let precursor = donor
+        .get_identifications()
+        .get(best.get_identification_index())?
+        .get_precursor();
+    let precursor_mz = precursor.get_monoisotopic_mz();
+    let predicted_retention_time = alignment.map_rt((*donor.get_retention_time())?);
+    let in_window = |spectrum: &&Spectrum| {
+        spectrum.get_retention_time().is_some_and(|retention_time| {
+            (retention_time - predicted_retention_time).abs() <= config.rt_window
+        })
+    };
+
+    let xic = Chromatogram::extract(
+        acceptor_run.iter().filter(in_window),
+        precursor_mz,
+        config.precursor_tolerance,
+    );
+    let xic = match xic.get_apex() {
+        Some((_, intensity)) if intensity > 0.0 => Some(xic),
+        _ => None,
+    };
+
+    let acceptor_spectrum = acceptor_run
+        .iter()
+        .filter(in_window)
+        .filter(|spectrum| spectrum.get_ms_level().unwrap_or(1) > 1)
+        .filter(|spectrum| {
+            spectrum.get_identifications().iter().any(|identification| {
+                let acceptor_precursor = identification.get_precursor();
+                acceptor_precursor.get_charge() == best.get_charge()
+                    && config
+                        .precursor_tolerance
+                        .contains(acceptor_precursor.get_monoisotopic_mz(), precursor_mz)
+            })
+        })
+        .min_by(|a, b| {
+            let distance = |spectrum: &Spectrum| {
+                (spectrum.get_retention_time().unwrap_or_default() - predicted_retention_time).abs()
+            };
+            distance(a).total_cmp(&distance(b))
+        })
+        .map(Spectrum::to_ref);
+
+    if xic.is_none() && acceptor_spectrum.is_none() {
+        return None;
+    }
+
+    let transferred = TransferredIdentification::new(
+        donor.to_ref(),
+        best.get_plain_peptide()?.to_string(),
+        best.get_charge(),
+        precursor_mz,
+        best.get_score(),
+        acceptor.get_ms_run().clone(),
+        predicted_retention_time,
+    )
+    .with_modified_peptide(best.get_modified_peptide().map(str::to_string))
+    .with_acceptor_spectrum(acceptor_spectrum)
+    .with_xic(xic);
+    let confidence = match (transferred.get_rt_error(), config.rt_window > 0.0) {
+        (Some(rt_error), true) => 1.0 - rt_error.abs() / config.rt_window,
+        (Some(_), false) => 1.0,
+        (None, _) => 0.0,
+    };
+    Some(transferred.with_confidence(confidence))
+}
+
+/// Modified peptide if available, otherwise plain
+///
+fn peptide(best: &BestPsm) -> Option<&str> {
+    best.get_modified_peptide().or(best.get_plain_peptide())
+}
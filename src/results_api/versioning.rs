@@ -0,0 +1,70 @@
+//! Content hashes and ETags of the entities for HTTP caching, e.g. conditional GETs of multi-MB spectra.
+//! The revision of an entity is a counter maintained by the producer, the content hash is derived from
+//! the whole serialized entity including the revision.
+
+// 3rd party imports
+use anyhow::Result;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+// internal imports
+use crate::results_api::integrity::{format_digest, SHA256_PREFIX};
+use crate::results_api::{MsRun, Search, Spectrum};
+
+/// Content hash and ETag of an entity
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::{ContentHash, Search};
+///
+/// let search = Search::new(
+///     "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+///     vec!["run".parse().unwrap()],
+/// );
+/// let etag = search.etag().unwrap();
+/// assert!(search.content_hash().unwrap().starts_with("sha256:"));
+/// assert!(search.matches_etag(&etag).unwrap());
+/// assert!(search.matches_etag(&format!("\"other\", W/{}", etag)).unwrap());
+/// assert!(search.matches_etag("*").unwrap());
+///
+/// let mut updated = search.clone();
+/// assert_eq!(updated.increment_revision(), 1);
+/// assert!(!updated.matches_etag(&etag).unwrap());
+/// ```
+///
+pub trait ContentHash: Serialize {
+    /// SHA-256 digest of the MessagePack encoded entity as `sha256:<hex>`
+    ///
+    fn content_hash(&self) -> Result<String> {
+        let mut hasher = Sha256::new();
+        hasher.update(rmp_serde::to_vec_named(self)?);
+        Ok(format_digest(&hasher.finalize()))
+    }
+
+    /// Strong ETag, i.e. the quoted hex digest of the content hash
+    ///
+    fn etag(&self) -> Result<String> {
+        let content_hash = self.content_hash()?;
+        Ok(format!(
+            "\"{}\"",
+            content_hash.trim_start_matches(SHA256_PREFIX)
+        ))
+    }
+
+    /// True if one of the ETags of an `If-None-Match` header matches the entity,
+    /// i.e. the API can answer with `304 Not Modified`. Weak ETags (`W/"..."`) are compared weakly.
+    ///
+    /// # Arguments
+    /// * `if_none_match` - Value of the `If-None-Match` header, comma separated ETags or `*`
+    ///
+    fn matches_etag(&self, if_none_match: &str) -> Result<bool> {
+        let etag = self.etag()?;
+        Ok(if_none_match
+            .split(',')
+            .map(str::trim)
+            .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag))
+    }
+}
+
+impl ContentHash for Search {}
+impl ContentHash for MsRun {}
+impl ContentHash for Spectrum {}
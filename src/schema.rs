@@ -0,0 +1,103 @@
+// std imports
+use std::collections::BTreeMap;
+
+// 3rd party imports
+use schemars::{gen::SchemaSettings, schema::RootSchema, JsonSchema};
+use serde_json::Value;
+
+// internal imports
+use crate::alignment::RtAlignment;
+use crate::annotation::AnnotatedSpectrum;
+use crate::isotopes::IsotopeEnvelope;
+use crate::proforma::Peptidoform;
+use crate::queue::Message;
+use crate::results_api::{
+    ApiError, BestPsm, Chromatogram, ConsensusPeaks, DeisotopedPeaks, ExchangeConfig,
+    GoodnessOfFit, Identification, MassShiftHistogram, Modification, MsRun, MsRunSummary, Peptide,
+    Protein, ProteinGroup, ScoreDescriptor, Search, SearchDiff, SearchParameters, SearchStatistics,
+    SearchStatus, SearchSummary, SequenceTag, SpectraPage, Spectrum, SpectrumCluster,
+    SpectrumComparison, SpectrumIndex, SpectrumProjection, SpectrumRef, SpectrumSummary, TableView,
+    TransferredIdentification,
+};
+use crate::statistics::{histogram::Histogram, summary::Summary};
+
+/// Serialized layout of a polars `DataFrame`, which does not provide a schema itself
+///
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+pub(crate) struct DataFrameLayout {
+    columns: Vec<SeriesLayout>,
+}
+
+/// Serialized layout of a polars `Series`
+///
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct SeriesLayout {
+    name: String,
+    /// Polars data type, e.g. `Float64` or `Utf8`
+    datatype: Value,
+    bit_settings: String,
+    values: Vec<Value>,
+}
+
+/// JSON Schemas (draft 2019-09) of the exchange entities by type name, e.g. for publishing an OpenAPI spec.
+/// The schemas describe the current layout, older payloads (see `crate::migrations`) may deviate.
+///
+pub fn schemas() -> BTreeMap<&'static str, RootSchema> {
+    let settings = SchemaSettings::draft2019_09();
+    let mut schemas = BTreeMap::new();
+    macro_rules! add {
+        ($($entity:ty),*) => {
+            $(
+                schemas.insert(
+                    stringify!($entity),
+                    settings.clone().into_generator().into_root_schema_for::<$entity>(),
+                );
+            )*
+        };
+    }
+    add!(
+        AnnotatedSpectrum,
+        ApiError,
+        BestPsm,
+        Chromatogram,
+        ConsensusPeaks,
+        DeisotopedPeaks,
+        ExchangeConfig,
+        GoodnessOfFit,
+        Histogram,
+        Identification,
+        IsotopeEnvelope,
+        MassShiftHistogram,
+        Message,
+        Modification,
+        MsRun,
+        MsRunSummary,
+        Peptide,
+        Peptidoform,
+        Protein,
+        ProteinGroup,
+        RtAlignment,
+        ScoreDescriptor,
+        Search,
+        SearchDiff,
+        SearchParameters,
+        SearchStatistics,
+        SearchStatus,
+        SearchSummary,
+        SequenceTag,
+        SpectraPage,
+        Spectrum,
+        SpectrumCluster,
+        SpectrumComparison,
+        SpectrumIndex,
+        SpectrumProjection,
+        SpectrumRef,
+        SpectrumSummary,
+        Summary,
+        TableView,
+        TransferredIdentification
+    );
+    schemas
+}
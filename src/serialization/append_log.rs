@@ -0,0 +1,181 @@
+//! Append-only log of an MS run as JSON lines, so workers can register spectra while the run is still processed.
+//!
+//! The first line is the header with the search and MS run name, each further line registers a spectrum or an error.
+//! Entries are written as a single line each, so concurrent appends (e.g. with `O_APPEND`) do not interleave.
+//! A truncated last line, e.g. of a worker killed while writing, is ignored when reading the log.
+
+// std imports
+use std::io::{BufRead, Write};
+
+// 3rd party imports
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::results_api::{MsRun, MsRunName, ProcessingError, SearchUuid, SpectrumId};
+
+/// Line of the log
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "entry", rename_all = "snake_case")]
+pub enum MsRunLogEntry {
+    /// First line of the log
+    Header {
+        schema_version: u32,
+        search_uuid: SearchUuid,
+        ms_run_name: MsRunName,
+    },
+    /// Registered spectrum
+    Spectrum { spectrum_id: SpectrumId },
+    /// Error of the MS run or a spectrum
+    Error { error: ProcessingError },
+}
+
+/// Appends the entries to the log, one line per entry
+///
+/// # Arguments
+/// * `writer` - Writer of the log, e.g. a file opened in append mode
+/// * `entries` - Entries to append
+///
+pub fn append_entries<W: Write>(
+    mut writer: W,
+    entries: impl IntoIterator<Item = MsRunLogEntry>,
+) -> Result<()> {
+    for entry in entries {
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        // one write per line, so appends of other workers do not end up within the line
+        writer.write_all(&line)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Appends the spectra to the log
+///
+/// # Arguments
+/// * `writer` - Writer of the log, e.g. a file opened in append mode
+/// * `spectra_ids` - IDs of the spectra to register
+///
+pub fn append_spectra<W: Write>(
+    writer: W,
+    spectra_ids: impl IntoIterator<Item = SpectrumId>,
+) -> Result<()> {
+    append_entries(
+        writer,
+        spectra_ids
+            .into_iter()
+            .map(|spectrum_id| MsRunLogEntry::Spectrum { spectrum_id }),
+    )
+}
+
+impl MsRun {
+    /// Writes the MS run as a new log, starting with the header
+    ///
+    /// # Arguments
+    /// * `writer` - Writer of the log
+    ///
+    pub fn write_log<W: Write>(&self, writer: W) -> Result<()> {
+        let header = MsRunLogEntry::Header {
+            schema_version: self.get_schema_version(),
+            search_uuid: self.get_search_uuid().clone(),
+            ms_run_name: self.get_ms_run().clone(),
+        };
+        let spectra = self
+            .get_spectra_ids()
+            .iter()
+            .map(|spectrum_id| MsRunLogEntry::Spectrum {
+                spectrum_id: spectrum_id.clone(),
+            });
+        let errors = self.get_errors().iter().map(|error| MsRunLogEntry::Error {
+            error: error.clone(),
+        });
+        append_entries(writer, std::iter::once(header).chain(spectra).chain(errors))
+    }
+
+    /// Reads the MS run from a log. Spectra registered multiple times are only kept once,
+    /// as are identical errors. A truncated last line is ignored.
+    ///
+    /// # Arguments
+    /// * `reader` - Reader of the log
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::MsRun;
+    /// use maccoys_exchange_entities::serialization::append_log::append_spectra;
+    ///
+    /// let ms_run = MsRun::new(
+    ///     "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+    ///     "run".parse().unwrap(),
+    ///     vec!["scan=1".parse().unwrap()],
+    /// );
+    /// let mut log: Vec<u8> = Vec::new();
+    /// ms_run.write_log(&mut log).unwrap();
+    ///
+    /// // workers register spectra, one of them twice and one crashing while writing
+    /// append_spectra(&mut log, ["scan=2".parse().unwrap()]).unwrap();
+    /// append_spectra(&mut log, ["scan=2".parse().unwrap(), "scan=3".parse().unwrap()]).unwrap();
+    /// log.extend_from_slice(br#"{"entry":"spectrum","spec"#);
+    ///
+    /// let read = MsRun::read_log(log.as_slice()).unwrap();
+    /// let spectra_ids: Vec<&str> = read.get_spectra_ids().iter().map(|id| id.as_str()).collect();
+    /// assert_eq!(spectra_ids, vec!["scan=1", "scan=2", "scan=3"]);
+    /// ```
+    ///
+    pub fn read_log<R: BufRead>(mut reader: R) -> Result<MsRun> {
+        let mut ms_run: Option<MsRun> = None;
+        let mut line = String::new();
+        let mut line_number: usize = 0;
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                break;
+            }
+            line_number += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = match serde_json::from_str::<MsRunLogEntry>(&line) {
+                Ok(entry) => entry,
+                // incomplete last line
+                Err(_) if !line.ends_with('\n') => break,
+                Err(err) => {
+                    return Err(err).with_context(|| format!("invalid log line {}", line_number))
+                }
+            };
+            match (entry, ms_run.as_mut()) {
+                (
+                    MsRunLogEntry::Header {
+                        search_uuid,
+                        ms_run_name,
+                        ..
+                    },
+                    None,
+                ) => {
+                    ms_run = Some(MsRun::new(search_uuid, ms_run_name, Vec::new()));
+                }
+                (MsRunLogEntry::Header { ms_run_name, .. }, Some(ms_run)) => {
+                    if &ms_run_name != ms_run.get_ms_run() {
+                        bail!(
+                            "log of MS run `{}` contains header of MS run `{}` in line {}",
+                            ms_run.get_ms_run(),
+                            ms_run_name,
+                            line_number
+                        );
+                    }
+                }
+                (MsRunLogEntry::Spectrum { spectrum_id }, Some(ms_run)) => {
+                    ms_run.push_spectrum_id(spectrum_id);
+                }
+                (MsRunLogEntry::Error { error }, Some(ms_run)) => {
+                    if !ms_run.get_errors().contains(&error) {
+                        ms_run.add_error(error);
+                    }
+                }
+                (_, None) => bail!("log does not start with a header"),
+            }
+        }
+        let mut ms_run = ms_run.context("log does not contain a header")?;
+        ms_run.deduplicate();
+        Ok(ms_run)
+    }
+}
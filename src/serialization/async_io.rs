@@ -0,0 +1,95 @@
+// std imports
+use std::future::Future;
+
+// 3rd party imports
+use anyhow::Result;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+// internal imports
+use crate::serialization::WireFormat;
+
+/// Reads the whole stream and decodes it on the blocking thread pool
+///
+async fn decode<T, R, F>(mut reader: R, decode: F) -> Result<T>
+where
+    T: Send + 'static,
+    R: AsyncRead + Unpin + Send,
+    F: FnOnce(&[u8]) -> Result<T> + Send + 'static,
+{
+    let mut buffer = Vec::new();
+    reader.read_to_end(&mut buffer).await?;
+    tokio::task::spawn_blocking(move || decode(&buffer)).await?
+}
+
+async fn write_all<W: AsyncWrite + Unpin + Send>(mut writer: W, buffer: &[u8]) -> Result<()> {
+    writer.write_all(buffer).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Asynchronous reading and writing of the entities with tokio's `AsyncRead`/`AsyncWrite`, e.g. in request handlers.
+/// Payloads are read completely and decoded on the blocking thread pool, so multi-MB spectra
+/// do not block the runtime. Encoding happens in place, as it is considerably faster than decoding.
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::Spectrum;
+/// use maccoys_exchange_entities::serialization::AsyncWireFormat;
+///
+/// tokio::runtime::Runtime::new().unwrap().block_on(async {
+///     let spectrum = Spectrum::new(
+///         "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+///         "run".parse().unwrap(),
+///         "scan=1".parse().unwrap(),
+///         vec![100.0, 200.0],
+///         vec![1.0, 2.0],
+///         Vec::new(),
+///     );
+///     let mut buffer = Vec::new();
+///     spectrum.write_msgpack_async(&mut buffer).await.unwrap();
+///     let decoded = Spectrum::read_msgpack_async(buffer.as_slice()).await.unwrap();
+///     assert_eq!(decoded.get_mz(), &[100.0, 200.0]);
+/// });
+/// ```
+///
+pub trait AsyncWireFormat: WireFormat + Send + Sync + 'static {
+    fn read_json_async<R: AsyncRead + Unpin + Send>(
+        reader: R,
+    ) -> impl Future<Output = Result<Self>> + Send {
+        decode(reader, |buffer| Ok(serde_json::from_slice(buffer)?))
+    }
+
+    fn write_json_async<W: AsyncWrite + Unpin + Send>(
+        &self,
+        writer: W,
+    ) -> impl Future<Output = Result<()>> + Send {
+        async move { write_all(writer, &serde_json::to_vec(self)?).await }
+    }
+
+    fn read_msgpack_async<R: AsyncRead + Unpin + Send>(
+        reader: R,
+    ) -> impl Future<Output = Result<Self>> + Send {
+        decode(reader, |buffer| Self::from_msgpack(buffer))
+    }
+
+    fn write_msgpack_async<W: AsyncWrite + Unpin + Send>(
+        &self,
+        writer: W,
+    ) -> impl Future<Output = Result<()>> + Send {
+        async move { write_all(writer, &self.to_msgpack()?).await }
+    }
+
+    fn read_cbor_async<R: AsyncRead + Unpin + Send>(
+        reader: R,
+    ) -> impl Future<Output = Result<Self>> + Send {
+        decode(reader, |buffer| Self::from_cbor(buffer))
+    }
+
+    fn write_cbor_async<W: AsyncWrite + Unpin + Send>(
+        &self,
+        writer: W,
+    ) -> impl Future<Output = Result<()>> + Send {
+        async move { write_all(writer, &self.to_cbor()?).await }
+    }
+}
+
+impl<T: WireFormat + Send + Sync + 'static> AsyncWireFormat for T {}
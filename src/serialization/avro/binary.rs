@@ -0,0 +1,188 @@
+//! Avro binary encoding, see <https://avro.apache.org/docs/1.11.1/specification/#binary-encoding>
+
+// 3rd party imports
+use anyhow::{bail, Context, Result};
+
+/// Encodes values in the order of the schema
+///
+#[derive(Default)]
+pub(crate) struct Encoder {
+    buffer: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buffer
+    }
+
+    pub fn raw(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    pub fn long(&mut self, value: i64) {
+        let mut value = ((value << 1) ^ (value >> 63)) as u64;
+        while value >= 0x80 {
+            self.buffer.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.buffer.push(value as u8);
+    }
+
+    pub fn int(&mut self, value: i32) {
+        self.long(value as i64);
+    }
+
+    pub fn boolean(&mut self, value: bool) {
+        self.buffer.push(value as u8);
+    }
+
+    pub fn double(&mut self, value: f64) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn bytes(&mut self, value: &[u8]) {
+        self.long(value.len() as i64);
+        self.buffer.extend_from_slice(value);
+    }
+
+    pub fn string(&mut self, value: &str) {
+        self.bytes(value.as_bytes());
+    }
+
+    /// Writes the items as a single block followed by the terminating empty block
+    ///
+    pub fn array<T>(
+        &mut self,
+        items: impl ExactSizeIterator<Item = T>,
+        mut encode: impl FnMut(&mut Self, T) -> Result<()>,
+    ) -> Result<()> {
+        if items.len() > 0 {
+            self.long(items.len() as i64);
+            for item in items {
+                encode(self, item)?;
+            }
+        }
+        self.long(0);
+        Ok(())
+    }
+
+    /// Writes a `["null", <type>]` union
+    ///
+    pub fn optional<T>(
+        &mut self,
+        value: Option<T>,
+        encode: impl FnOnce(&mut Self, T) -> Result<()>,
+    ) -> Result<()> {
+        match value {
+            Some(value) => {
+                self.long(1);
+                encode(self, value)
+            }
+            None => {
+                self.long(0);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Decodes values in the order of the schema
+///
+pub(crate) struct Decoder<'a> {
+    buffer: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(buffer: &'a [u8]) -> Self {
+        Self {
+            buffer,
+            position: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.position >= self.buffer.len()
+    }
+
+    pub fn raw(&mut self, length: usize) -> Result<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.buffer.len())
+            .context("truncated Avro data")?;
+        let bytes = &self.buffer[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    pub fn long(&mut self) -> Result<i64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.raw(1)?[0];
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte < 0x80 {
+                return Ok(((value >> 1) as i64) ^ -((value & 1) as i64));
+            }
+        }
+        bail!("Avro long exceeds 64 bits")
+    }
+
+    pub fn int(&mut self) -> Result<i32> {
+        Ok(i32::try_from(self.long()?)?)
+    }
+
+    pub fn boolean(&mut self) -> Result<bool> {
+        match self.raw(1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => bail!("invalid Avro boolean {}", value),
+        }
+    }
+
+    pub fn double(&mut self) -> Result<f64> {
+        Ok(f64::from_le_bytes(self.raw(8)?.try_into()?))
+    }
+
+    pub fn bytes(&mut self) -> Result<&'a [u8]> {
+        let length = usize::try_from(self.long()?)?;
+        self.raw(length)
+    }
+
+    pub fn string(&mut self) -> Result<&'a str> {
+        Ok(std::str::from_utf8(self.bytes()?)?)
+    }
+
+    /// Reads the blocks of an array (or map) until the terminating empty block
+    ///
+    pub fn array<T>(&mut self, mut decode: impl FnMut(&mut Self) -> Result<T>) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        loop {
+            let count = match self.long()? {
+                0 => return Ok(items),
+                // negative counts are followed by the size of the block in bytes
+                count if count < 0 => {
+                    self.long()?;
+                    count.unsigned_abs()
+                }
+                count => count as u64,
+            };
+            for _ in 0..count {
+                items.push(decode(self)?);
+            }
+        }
+    }
+
+    /// Reads a `["null", <type>]` union
+    ///
+    pub fn optional<T>(
+        &mut self,
+        decode: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<Option<T>> {
+        match self.long()? {
+            0 => Ok(None),
+            1 => Ok(Some(decode(self)?)),
+            index => bail!("invalid index {} of optional union", index),
+        }
+    }
+}
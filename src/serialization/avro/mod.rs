@@ -0,0 +1,519 @@
+//! Avro object container files of a whole search for long-term archival, e.g. in data lakes.
+//!
+//! The file embeds the schema (`avro/search.avsc`, see `SCHEMA`) and contains one record per search
+//! with its MS runs, spectra and identifications nested in it. Fields added later get defaults, so readers
+//! with schema resolution (Spark, Hive, `apache-avro`, ...) can read older archives with newer schemas and vice versa.
+//! PSM and goodness tables are stored column-wise, columns other than floats, integers, strings and booleans
+//! are stored as strings.
+
+/// Avro binary encoding
+mod binary;
+
+// std imports
+use std::io::{Read, Write};
+
+// 3rd party imports
+use anyhow::{bail, Context, Result};
+use polars::prelude::*;
+use sha2::{Digest, Sha256};
+
+// internal imports
+use crate::results_api::chunked::any_value_to_json;
+use crate::results_api::{
+    Identification, MsRun, MsRunName, Precursor, Search, SearchUuid, SequenceTag, Spectrum,
+    SpectrumId,
+};
+use binary::{Decoder, Encoder};
+
+/// Avro schema of the archived search
+pub const SCHEMA: &str = include_str!("../../../avro/search.avsc");
+
+const MAGIC: &[u8; 4] = b"Obj\x01";
+const SCHEMA_KEY: &str = "avro.schema";
+const CODEC_KEY: &str = "avro.codec";
+const NULL_CODEC: &str = "null";
+const SYNC_MARKER_LEN: usize = 16;
+
+// branches of the `values` union of a column
+const FLOAT_VALUES: i64 = 0;
+const INT_VALUES: i64 = 1;
+const STRING_VALUES: i64 = 2;
+const BOOL_VALUES: i64 = 3;
+
+/// Writes the search hierarchy as Avro object container file (uncompressed)
+///
+/// # Arguments
+/// * `writer` - Writer
+/// * `search` - Search
+/// * `ms_runs` - MS runs of the search
+/// * `spectra` - Spectra of the MS runs
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::{
+///     ContentHash, Identification, MsRun, Precursor, Search, Spectrum,
+/// };
+/// use maccoys_exchange_entities::serialization::avro;
+/// use polars::prelude::*;
+///
+/// let search_uuid = "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c";
+/// let psms = df!(
+///     "plain_peptide" => &[Some("PEPTIDE"), None],
+///     "xcorr" => &[Some(2.5), None],
+///     "is_decoy" => &[false, true],
+///     "charge" => &[2i64, 3]
+/// )
+/// .unwrap();
+/// let search = Search::new(search_uuid.parse().unwrap(), vec!["run".parse().unwrap()]);
+/// let ms_runs = vec![MsRun::new(
+///     search_uuid.parse().unwrap(),
+///     "run".parse().unwrap(),
+///     vec!["scan=1".parse().unwrap()],
+/// )];
+/// let spectra = vec![Spectrum::new(
+///     search_uuid.parse().unwrap(),
+///     "run".parse().unwrap(),
+///     "scan=1".parse().unwrap(),
+///     vec![100.0, 200.0],
+///     vec![1.0, 2.0],
+///     vec![Identification::new(None, Some(psms.clone()), Precursor::new(400.7, 2))],
+/// )
+/// .with_retention_time(Some(1200.0))
+/// .with_revision(2)];
+///
+/// let mut archive = Vec::new();
+/// avro::write(&mut archive, &search, &ms_runs, &spectra).unwrap();
+/// assert!(archive.starts_with(b"Obj\x01"));
+///
+/// let (read_search, read_ms_runs, read_spectra) = avro::read(archive.as_slice()).unwrap();
+/// assert_eq!(read_search.content_hash().unwrap(), search.content_hash().unwrap());
+/// assert!(read_ms_runs == ms_runs);
+/// assert_eq!(read_spectra[0].content_hash().unwrap(), spectra[0].content_hash().unwrap());
+/// let read_psms = read_spectra[0].get_identifications()[0].get_psms().as_ref().unwrap();
+/// assert!(read_psms.frame_equal_missing(&psms));
+/// ```
+///
+pub fn write<W: Write>(
+    mut writer: W,
+    search: &Search,
+    ms_runs: &[MsRun],
+    spectra: &[Spectrum],
+) -> Result<()> {
+    for spectrum in spectra {
+        if !ms_runs
+            .iter()
+            .any(|ms_run| ms_run.get_ms_run() == spectrum.get_ms_run())
+        {
+            bail!(
+                "MS run `{}` of spectrum `{}` is missing",
+                spectrum.get_ms_run(),
+                spectrum.get_spectra_id()
+            );
+        }
+    }
+
+    let mut datum = Encoder::default();
+    encode_search(&mut datum, search, ms_runs, spectra)?;
+    let datum = datum.into_bytes();
+    // the sync marker only needs to be unlikely to occur in the data, a digest of the data is sufficient
+    let sync_marker = &Sha256::digest(&datum)[..SYNC_MARKER_LEN];
+
+    let mut file = Encoder::default();
+    file.raw(MAGIC);
+    let metadata = [(SCHEMA_KEY, SCHEMA), (CODEC_KEY, NULL_CODEC)];
+    file.array(metadata.into_iter(), |file, (key, value)| {
+        file.string(key);
+        file.bytes(value.as_bytes());
+        Ok(())
+    })?;
+    file.raw(sync_marker);
+    // single block with the search
+    file.long(1);
+    file.bytes(&datum);
+    file.raw(sync_marker);
+    writer.write_all(&file.into_bytes())?;
+    Ok(())
+}
+
+/// Reads the search hierarchy from an Avro object container file written by `write`.
+/// Files written with another schema, e.g. by a newer version, need to be read by an Avro library with schema resolution.
+///
+/// # Arguments
+/// * `reader` - Reader
+///
+pub fn read<R: Read>(mut reader: R) -> Result<(Search, Vec<MsRun>, Vec<Spectrum>)> {
+    let mut buffer = Vec::new();
+    reader.read_to_end(&mut buffer)?;
+    let mut file = Decoder::new(&buffer);
+    if file.raw(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+        bail!("not an Avro object container file");
+    }
+
+    let metadata = file.array(|file| Ok((file.string()?, file.bytes()?)))?;
+    let metadata_value = |key: &str| {
+        metadata
+            .iter()
+            .find(|(metadata_key, _)| *metadata_key == key)
+            .map(|(_, value)| *value)
+    };
+    let codec = metadata_value(CODEC_KEY).unwrap_or(NULL_CODEC.as_bytes());
+    if codec != NULL_CODEC.as_bytes() {
+        bail!("unsupported codec `{}`", String::from_utf8_lossy(codec));
+    }
+    let schema: serde_json::Value =
+        serde_json::from_slice(metadata_value(SCHEMA_KEY).context("schema is missing")?)?;
+    if schema != serde_json::from_str::<serde_json::Value>(SCHEMA)? {
+        bail!("file was written with another schema, use an Avro library with schema resolution");
+    }
+    let sync_marker = file.raw(SYNC_MARKER_LEN)?;
+
+    let mut searches = Vec::new();
+    while !file.is_empty() {
+        let count = file.long()?;
+        let mut block = Decoder::new(file.bytes()?);
+        for _ in 0..count {
+            searches.push(decode_search(&mut block)?);
+        }
+        if file.raw(SYNC_MARKER_LEN)? != sync_marker {
+            bail!("invalid sync marker");
+        }
+    }
+    match searches.len() {
+        1 => Ok(searches.remove(0)),
+        count => bail!("expected one search, found {}", count),
+    }
+}
+
+fn encode_search(
+    encoder: &mut Encoder,
+    search: &Search,
+    ms_runs: &[MsRun],
+    spectra: &[Spectrum],
+) -> Result<()> {
+    encoder.int(i32::try_from(search.get_schema_version())?);
+    encoder.string(search.get_search_uuid());
+    encoder.long(i64::try_from(search.get_revision())?);
+    encoder.array(search.get_ms_run_names().iter(), |encoder, ms_run_name| {
+        encoder.string(ms_run_name);
+        Ok(())
+    })?;
+    encoder.optional(search.get_parameters().as_ref(), encode_json)?;
+    encode_json(encoder, search.get_config())?;
+    encoder.array(search.get_errors().iter(), encode_json)?;
+    encoder.optional(search.get_quantification().as_ref(), encode_json)?;
+    encoder.array(search.get_transferred_identifications().iter(), encode_json)?;
+    encoder.array(ms_runs.iter(), |encoder, ms_run| {
+        encode_ms_run(
+            encoder,
+            ms_run,
+            spectra
+                .iter()
+                .filter(|spectrum| spectrum.get_ms_run() == ms_run.get_ms_run())
+                .collect(),
+        )
+    })
+}
+
+fn decode_search(decoder: &mut Decoder) -> Result<(Search, Vec<MsRun>, Vec<Spectrum>)> {
+    decoder.int()?;
+    let search_uuid = SearchUuid::new(decoder.string()?)?;
+    let revision = u64::try_from(decoder.long()?)?;
+    let ms_run_names = decoder.array(|decoder| Ok(MsRunName::new(decoder.string()?)?))?;
+    let parameters = decoder.optional(decode_json)?;
+    let config = decode_json(decoder)?;
+    let errors = decoder.array(decode_json)?;
+    let quantification = decoder.optional(decode_json)?;
+    let transferred_identifications = decoder.array(decode_json)?;
+    let mut spectra = Vec::new();
+    let ms_runs = decoder.array(|decoder| {
+        let (ms_run, ms_run_spectra) = decode_ms_run(decoder, &search_uuid)?;
+        spectra.extend(ms_run_spectra);
+        Ok(ms_run)
+    })?;
+
+    let mut search = Search::new(search_uuid, ms_run_names)
+        .with_revision(revision)
+        .with_config(config)
+        .with_transferred_identifications(transferred_identifications);
+    if let Some(parameters) = parameters {
+        search = search.with_parameters(parameters);
+    }
+    if let Some(quantification) = quantification {
+        search = search.with_quantification(quantification);
+    }
+    for error in errors {
+        search.add_error(error);
+    }
+    Ok((search, ms_runs, spectra))
+}
+
+fn encode_ms_run(encoder: &mut Encoder, ms_run: &MsRun, spectra: Vec<&Spectrum>) -> Result<()> {
+    encoder.int(i32::try_from(ms_run.get_schema_version())?);
+    encoder.string(ms_run.get_ms_run());
+    encoder.long(i64::try_from(ms_run.get_revision())?);
+    encoder.array(ms_run.get_spectra_ids().iter(), |encoder, spectrum_id| {
+        encoder.string(spectrum_id);
+        Ok(())
+    })?;
+    encoder.array(ms_run.get_errors().iter(), encode_json)?;
+    encoder.optional(ms_run.get_spectrum_index().as_ref(), encode_json)?;
+    encoder.array(spectra.into_iter(), encode_spectrum)
+}
+
+fn decode_ms_run(
+    decoder: &mut Decoder,
+    search_uuid: &SearchUuid,
+) -> Result<(MsRun, Vec<Spectrum>)> {
+    decoder.int()?;
+    let ms_run_name = MsRunName::new(decoder.string()?)?;
+    let revision = u64::try_from(decoder.long()?)?;
+    let spectra_ids = decoder.array(|decoder| Ok(SpectrumId::new(decoder.string()?)?))?;
+    let errors = decoder.array(decode_json)?;
+    let spectrum_index = decoder.optional(decode_json)?;
+    let spectra = decoder.array(|decoder| decode_spectrum(decoder, search_uuid, &ms_run_name))?;
+
+    let mut ms_run =
+        MsRun::new(search_uuid.clone(), ms_run_name, spectra_ids).with_revision(revision);
+    if let Some(spectrum_index) = spectrum_index {
+        ms_run = ms_run.with_spectrum_index(spectrum_index);
+    }
+    for error in errors {
+        ms_run.add_error(error);
+    }
+    Ok((ms_run, spectra))
+}
+
+fn encode_spectrum(encoder: &mut Encoder, spectrum: &Spectrum) -> Result<()> {
+    encoder.int(i32::try_from(spectrum.get_schema_version())?);
+    encoder.string(spectrum.get_spectra_id());
+    encoder.optional(*spectrum.get_retention_time(), encode_double)?;
+    encoder.optional(*spectrum.get_ion_mobility(), encode_double)?;
+    encoder.optional(*spectrum.get_ms_level(), |encoder, ms_level| {
+        encoder.int(ms_level as i32);
+        Ok(())
+    })?;
+    encoder.optional(*spectrum.get_scan_number(), |encoder, scan_number| {
+        encoder.long(scan_number as i64);
+        Ok(())
+    })?;
+    encoder.array(spectrum.get_mz().iter().copied(), encode_double)?;
+    encoder.array(spectrum.get_intensity().iter().copied(), encode_double)?;
+    encoder.array(spectrum.get_identifications().iter(), encode_identification)?;
+    encoder.array(spectrum.get_sequence_tags().iter(), encode_sequence_tag)?;
+    encoder.optional(spectrum.get_payload_digest().as_ref(), |encoder, digest| {
+        encoder.string(digest);
+        Ok(())
+    })?;
+    encoder.long(i64::try_from(spectrum.get_revision())?);
+    Ok(())
+}
+
+fn decode_spectrum(
+    decoder: &mut Decoder,
+    search_uuid: &SearchUuid,
+    ms_run_name: &MsRunName,
+) -> Result<Spectrum> {
+    decoder.int()?;
+    let spectrum_id = SpectrumId::new(decoder.string()?)?;
+    let retention_time = decoder.optional(Decoder::double)?;
+    let ion_mobility = decoder.optional(Decoder::double)?;
+    let ms_level = decoder.optional(|decoder| Ok(u8::try_from(decoder.int()?)?))?;
+    let scan_number = decoder.optional(|decoder| Ok(u32::try_from(decoder.long()?)?))?;
+    let mz = decoder.array(Decoder::double)?;
+    let intensity = decoder.array(Decoder::double)?;
+    let identifications = decoder.array(decode_identification)?;
+    let sequence_tags = decoder.array(decode_sequence_tag)?;
+    let payload_digest = decoder.optional(|decoder| Ok(decoder.string()?.to_string()))?;
+    let revision = u64::try_from(decoder.long()?)?;
+    Ok(Spectrum::new(
+        search_uuid.clone(),
+        ms_run_name.clone(),
+        spectrum_id,
+        mz,
+        intensity,
+        identifications,
+    )
+    .with_retention_time(retention_time)
+    .with_ion_mobility(ion_mobility)
+    .with_ms_level(ms_level)
+    .with_scan_number(scan_number)
+    .with_sequence_tags(sequence_tags)
+    .with_payload_digest(payload_digest)
+    .with_revision(revision))
+}
+
+fn encode_identification(encoder: &mut Encoder, identification: &Identification) -> Result<()> {
+    let precursor = identification.get_precursor();
+    encoder.double(precursor.get_mz());
+    encoder.int(precursor.get_charge() as i32);
+    encoder.optional(*precursor.get_intensity(), encode_double)?;
+    encoder.optional(
+        *precursor.get_isolation_window_lower_offset(),
+        encode_double,
+    )?;
+    encoder.optional(
+        *precursor.get_isolation_window_upper_offset(),
+        encode_double,
+    )?;
+    encoder.optional(
+        *precursor.get_monoisotopic_correction(),
+        |encoder, correction| {
+            encoder.int(correction as i32);
+            Ok(())
+        },
+    )?;
+    encoder.optional(identification.get_psms().as_ref(), encode_table)?;
+    encoder.optional(identification.get_goodnesses().as_ref(), encode_table)?;
+    encoder.optional(identification.get_reporter_ions().as_ref(), encode_json)?;
+    encoder.array(identification.get_score_descriptors().iter(), encode_json)
+}
+
+fn decode_identification(decoder: &mut Decoder) -> Result<Identification> {
+    let precursor = Precursor::new(decoder.double()?, u8::try_from(decoder.int()?)?)
+        .with_intensity(decoder.optional(Decoder::double)?)
+        .with_isolation_window(
+            decoder.optional(Decoder::double)?,
+            decoder.optional(Decoder::double)?,
+        )
+        .with_monoisotopic_correction(
+            decoder.optional(|decoder| Ok(i8::try_from(decoder.int()?)?))?,
+        );
+    let psms = decoder.optional(decode_table)?;
+    let goodnesses = decoder.optional(decode_table)?;
+    let mut identification = Identification::new(goodnesses, psms, precursor);
+    identification.set_reporter_ions(decoder.optional(decode_json)?);
+    for score_descriptor in decoder.array(decode_json)? {
+        identification.register_score_descriptor(score_descriptor);
+    }
+    Ok(identification)
+}
+
+fn encode_sequence_tag(encoder: &mut Encoder, sequence_tag: &SequenceTag) -> Result<()> {
+    encoder.string(sequence_tag.get_sequence());
+    encoder.double(sequence_tag.get_n_term_mass());
+    encoder.double(sequence_tag.get_c_term_mass());
+    encoder.array(sequence_tag.get_peaks().iter(), |encoder, peak| {
+        encoder.long(i64::try_from(*peak)?);
+        Ok(())
+    })?;
+    encoder.optional(sequence_tag.get_score(), encode_double)?;
+    encoder.optional(sequence_tag.get_charge(), |encoder, charge| {
+        encoder.int(charge as i32);
+        Ok(())
+    })
+}
+
+fn decode_sequence_tag(decoder: &mut Decoder) -> Result<SequenceTag> {
+    let sequence = decoder.string()?.to_string();
+    let n_term_mass = decoder.double()?;
+    let c_term_mass = decoder.double()?;
+    let peaks = decoder.array(|decoder| Ok(usize::try_from(decoder.long()?)?))?;
+    let score = decoder.optional(Decoder::double)?;
+    let charge = decoder.optional(|decoder| Ok(u8::try_from(decoder.int()?)?))?;
+    Ok(SequenceTag::new(sequence, n_term_mass, c_term_mass)
+        .with_peaks(peaks)
+        .with_score(score)
+        .with_charge(charge))
+}
+
+fn encode_table(encoder: &mut Encoder, table: &DataFrame) -> Result<()> {
+    encoder.array(table.get_columns().iter(), |encoder, series| {
+        encoder.string(series.name());
+        match series.dtype() {
+            DataType::Float32 | DataType::Float64 => {
+                encoder.long(FLOAT_VALUES);
+                let values = series.cast(&DataType::Float64)?;
+                encoder.array(values.f64()?.into_iter(), |encoder, value| {
+                    encoder.optional(value, encode_double)
+                })
+            }
+            dtype if dtype.is_integer() => {
+                encoder.long(INT_VALUES);
+                let values = series.cast(&DataType::Int64)?;
+                encoder.array(values.i64()?.into_iter(), |encoder, value| {
+                    encoder.optional(value, |encoder, value| {
+                        encoder.long(value);
+                        Ok(())
+                    })
+                })
+            }
+            DataType::Boolean => {
+                encoder.long(BOOL_VALUES);
+                encoder.array(series.bool()?.into_iter(), |encoder, value| {
+                    encoder.optional(value, |encoder, value| {
+                        encoder.boolean(value);
+                        Ok(())
+                    })
+                })
+            }
+            DataType::Utf8 => {
+                encoder.long(STRING_VALUES);
+                encoder.array(series.utf8()?.into_iter(), |encoder, value| {
+                    encoder.optional(value, |encoder, value| {
+                        encoder.string(value);
+                        Ok(())
+                    })
+                })
+            }
+            _ => {
+                encoder.long(STRING_VALUES);
+                encoder.array(series.iter(), |encoder, value| {
+                    let value = match any_value_to_json(&value) {
+                        serde_json::Value::Null => None,
+                        serde_json::Value::String(value) => Some(value),
+                        value => Some(value.to_string()),
+                    };
+                    encoder.optional(value, |encoder, value| {
+                        encoder.string(&value);
+                        Ok(())
+                    })
+                })
+            }
+        }
+    })
+}
+
+fn decode_table(decoder: &mut Decoder) -> Result<DataFrame> {
+    let columns = decoder.array(|decoder| {
+        let name = decoder.string()?;
+        Ok(match decoder.long()? {
+            FLOAT_VALUES => Series::new(
+                name,
+                decoder.array(|decoder| decoder.optional(Decoder::double))?,
+            ),
+            INT_VALUES => Series::new(
+                name,
+                decoder.array(|decoder| decoder.optional(Decoder::long))?,
+            ),
+            STRING_VALUES => Series::new(
+                name,
+                decoder.array(|decoder| decoder.optional(Decoder::string))?,
+            ),
+            BOOL_VALUES => Series::new(
+                name,
+                decoder.array(|decoder| decoder.optional(Decoder::boolean))?,
+            ),
+            index => bail!(
+                "invalid index {} of values union of column `{}`",
+                index,
+                name
+            ),
+        })
+    })?;
+    Ok(DataFrame::new(columns)?)
+}
+
+fn encode_double(encoder: &mut Encoder, value: f64) -> Result<()> {
+    encoder.double(value);
+    Ok(())
+}
+
+/// Encodes nested objects without a record of their own
+///
+fn encode_json<T: serde::Serialize>(encoder: &mut Encoder, value: &T) -> Result<()> {
+    encoder.string(&serde_json::to_string(value)?);
+    Ok(())
+}
+
+fn decode_json<T: serde::de::DeserializeOwned>(decoder: &mut Decoder) -> Result<T> {
+    Ok(serde_json::from_str(decoder.string()?)?)
+}
@@ -0,0 +1,248 @@
+//! Peak arrays encoded like the binary data arrays of mzML: little-endian floats, optionally zlib compressed, base64 encoded.
+//! Shrinks JSON payloads of spectra with many peaks considerably, while staying self-describing.
+//!
+//! With the `binary_peaks` feature spectra accept both, plain arrays and binary arrays, for `mz` and `intensity`
+//! in every format. `Spectrum::to_binary_json` writes the binary arrays.
+
+// std imports
+use std::fmt;
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+// 3rd party imports
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use flate2::{read::ZlibDecoder, write::ZlibEncoder};
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// internal imports
+use crate::results_api::Spectrum;
+
+/// Float precision of the encoded values
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    /// 32-bit floats, about 7 significant digits, sufficient for most intensities
+    Float32,
+    /// 64-bit floats, lossless
+    Float64,
+}
+
+/// Compression of the encoded bytes
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArrayCompression {
+    None,
+    Zlib,
+}
+
+/// Base64 encoded array of little-endian floats
+///
+/// ```
+/// use maccoys_exchange_entities::serialization::binary_peaks::{
+///     ArrayCompression, BinaryArray, Precision,
+/// };
+///
+/// let values = vec![175.119, 276.155, 389.239];
+/// let array = BinaryArray::encode(&values, Precision::Float64, ArrayCompression::Zlib).unwrap();
+/// assert_eq!(array.decode().unwrap(), values);
+///
+/// let array = BinaryArray::encode(&values, Precision::Float32, ArrayCompression::None).unwrap();
+/// assert!((array.decode().unwrap()[0] - 175.119).abs() < 1e-4);
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BinaryArray {
+    precision: Precision,
+    compression: ArrayCompression,
+    data: String,
+}
+
+impl BinaryArray {
+    /// Encodes the values
+    ///
+    /// # Arguments
+    /// * `values` - Values to encode
+    /// * `precision` - Float precision, 32-bit floats are lossy
+    /// * `compression` - Compression of the bytes before base64 encoding
+    ///
+    pub fn encode(
+        values: &[f64],
+        precision: Precision,
+        compression: ArrayCompression,
+    ) -> Result<Self> {
+        let mut bytes = match precision {
+            Precision::Float32 => values
+                .iter()
+                .flat_map(|value| (*value as f32).to_le_bytes())
+                .collect::<Vec<u8>>(),
+            Precision::Float64 => values
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect::<Vec<u8>>(),
+        };
+        if compression == ArrayCompression::Zlib {
+            let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&bytes)?;
+            bytes = encoder.finish()?;
+        }
+        Ok(Self {
+            precision,
+            compression,
+            data: STANDARD.encode(bytes),
+        })
+    }
+
+    /// Decodes the values
+    ///
+    pub fn decode(&self) -> Result<Vec<f64>> {
+        let mut bytes = STANDARD
+            .decode(&self.data)
+            .context("invalid base64 in binary array")?;
+        if self.compression == ArrayCompression::Zlib {
+            let mut decompressed = Vec::new();
+            ZlibDecoder::new(bytes.as_slice())
+                .read_to_end(&mut decompressed)
+                .context("invalid zlib data in binary array")?;
+            bytes = decompressed;
+        }
+        match self.precision {
+            Precision::Float32 => {
+                if !bytes.len().is_multiple_of(4) {
+                    bail!(
+                        "binary array of {} bytes is no 32-bit float array",
+                        bytes.len()
+                    );
+                }
+                Ok(bytes
+                    .chunks_exact(4)
+                    .map(|chunk| {
+                        f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) as f64
+                    })
+                    .collect())
+            }
+            Precision::Float64 => {
+                if !bytes.len().is_multiple_of(8) {
+                    bail!(
+                        "binary array of {} bytes is no 64-bit float array",
+                        bytes.len()
+                    );
+                }
+                Ok(bytes
+                    .chunks_exact(8)
+                    .map(|chunk| {
+                        f64::from_le_bytes([
+                            chunk[0], chunk[1], chunk[2], chunk[3], chunk[4], chunk[5], chunk[6],
+                            chunk[7],
+                        ])
+                    })
+                    .collect())
+            }
+        }
+    }
+
+    pub fn get_precision(&self) -> Precision {
+        self.precision
+    }
+
+    pub fn get_compression(&self) -> ArrayCompression {
+        self.compression
+    }
+
+    /// Base64 encoded bytes
+    ///
+    pub fn get_data(&self) -> &str {
+        &self.data
+    }
+}
+
+/// Deserializes peaks from a plain array or a `BinaryArray`
+///
+pub(crate) fn deserialize_peaks<'de, D>(deserializer: D) -> Result<Arc<[f64]>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct PeaksVisitor;
+
+    impl<'de> Visitor<'de> for PeaksVisitor {
+        type Value = Arc<[f64]>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("an array of floats or a binary array")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut values = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+            while let Some(value) = seq.next_element::<f64>()? {
+                values.push(value);
+            }
+            Ok(values.into())
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+            let array = BinaryArray::deserialize(de::value::MapAccessDeserializer::new(map))?;
+            array
+                .decode()
+                .map(Into::into)
+                .map_err(|err| de::Error::custom(format!("{:#}", err)))
+        }
+    }
+
+    deserializer.deserialize_any(PeaksVisitor)
+}
+
+impl Spectrum {
+    /// Serializes the spectrum to JSON with `mz` and `intensity` as binary arrays.
+    /// The result can be deserialized like any other spectrum payload.
+    ///
+    /// # Arguments
+    /// * `precision` - Float precision of the peaks, 32-bit floats are lossy
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::Spectrum;
+    /// use maccoys_exchange_entities::serialization::binary_peaks::Precision;
+    ///
+    /// let spectrum = Spectrum::new(
+    ///     "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+    ///     "run".parse().unwrap(),
+    ///     "scan=1".parse().unwrap(),
+    ///     (0..1000).map(|idx| 100.0 + idx as f64 * 0.5).collect::<Vec<f64>>(),
+    ///     vec![1000.0; 1000],
+    ///     Vec::new(),
+    /// );
+    ///
+    /// let json = spectrum.to_binary_json(Precision::Float64).unwrap();
+    /// assert!(json.len() < serde_json::to_string(&spectrum).unwrap().len() / 2);
+    ///
+    /// let decoded: Spectrum = serde_json::from_str(&json).unwrap();
+    /// assert_eq!(decoded.get_mz(), spectrum.get_mz());
+    /// assert_eq!(decoded.get_intensity(), spectrum.get_intensity());
+    /// ```
+    ///
+    pub fn to_binary_json(&self, precision: Precision) -> Result<String> {
+        let mut value = serde_json::to_value(self)?;
+        if let Value::Object(fields) = &mut value {
+            fields.insert(
+                "mz".to_string(),
+                serde_json::to_value(BinaryArray::encode(
+                    self.get_mz(),
+                    precision,
+                    ArrayCompression::Zlib,
+                )?)?,
+            );
+            fields.insert(
+                "intensity".to_string(),
+                serde_json::to_value(BinaryArray::encode(
+                    self.get_intensity(),
+                    precision,
+                    ArrayCompression::Zlib,
+                )?)?,
+            );
+        }
+        Ok(serde_json::to_string(&value)?)
+    }
+}
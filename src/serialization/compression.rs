@@ -0,0 +1,113 @@
+// std imports
+use std::io::{BufRead, BufReader, Read, Write};
+
+// 3rd party imports
+use anyhow::{bail, Result};
+use flate2::{read::GzDecoder, write::GzEncoder};
+use serde::{de::DeserializeOwned, Serialize};
+
+// internal imports
+use crate::results_api::{MsRun, Search, Spectrum};
+
+/// Magic bytes of gzip streams
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// Magic bytes of Zstandard frames
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compression algorithm and level
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// Gzip with level 0 (none) to 9 (best)
+    Gzip(u32),
+    /// Zstandard with level 1 (fastest) to 22 (best)
+    Zstd(i32),
+}
+
+impl Compression {
+    /// Gzip with the default level 6
+    ///
+    pub fn gzip() -> Self {
+        Self::Gzip(6)
+    }
+
+    /// Zstandard with the default level 3, which is faster and smaller than gzip for peak lists
+    ///
+    pub fn zstd() -> Self {
+        Self::Zstd(3)
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self::zstd()
+    }
+}
+
+/// Compressed JSON for large payloads, e.g. spectra with many peaks.
+/// The compression is detected on decoding, so the algorithm does not need to be known by the receiver.
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::Search;
+/// use maccoys_exchange_entities::serialization::compression::{CompressedJson, Compression};
+///
+/// let search = Search::new("4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(), vec!["run".parse().unwrap()]);
+/// for compression in [Compression::gzip(), Compression::zstd()] {
+///     let compressed = search.to_compressed_json(compression).unwrap();
+///     let decoded = Search::from_compressed_json(&compressed).unwrap();
+///     assert_eq!(decoded.get_ms_run_names(), search.get_ms_run_names());
+/// }
+/// ```
+///
+pub trait CompressedJson: Serialize + DeserializeOwned {
+    /// Serializes to JSON and compresses it while writing
+    ///
+    fn write_compressed_json<W: Write>(&self, writer: W, compression: Compression) -> Result<()> {
+        match compression {
+            Compression::Gzip(level) => {
+                let mut encoder = GzEncoder::new(writer, flate2::Compression::new(level));
+                serde_json::to_writer(&mut encoder, self)?;
+                encoder.finish()?;
+            }
+            Compression::Zstd(level) => {
+                let mut encoder = zstd::Encoder::new(writer, level)?;
+                serde_json::to_writer(&mut encoder, self)?;
+                encoder.finish()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn to_compressed_json(&self, compression: Compression) -> Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        self.write_compressed_json(&mut buffer, compression)?;
+        Ok(buffer)
+    }
+
+    /// Decompresses and deserializes while reading, without buffering the decompressed JSON.
+    /// Fails if the stream is neither gzip nor Zstandard compressed.
+    ///
+    fn read_compressed_json<R: Read>(reader: R) -> Result<Self> {
+        let mut reader = BufReader::new(reader);
+        let header = reader.fill_buf()?;
+        if header.starts_with(&ZSTD_MAGIC) {
+            Ok(serde_json::from_reader(BufReader::new(
+                zstd::Decoder::with_buffer(reader)?,
+            ))?)
+        } else if header.starts_with(&GZIP_MAGIC) {
+            Ok(serde_json::from_reader(BufReader::new(GzDecoder::new(
+                reader,
+            )))?)
+        } else {
+            bail!("payload is neither gzip nor Zstandard compressed")
+        }
+    }
+
+    fn from_compressed_json(compressed: &[u8]) -> Result<Self> {
+        Self::read_compressed_json(compressed)
+    }
+}
+
+impl CompressedJson for Search {}
+impl CompressedJson for MsRun {}
+impl CompressedJson for Spectrum {}
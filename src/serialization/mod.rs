@@ -0,0 +1,90 @@
+/// Append-only JSON lines log of MS runs
+pub mod append_log;
+
+/// Reading and writing with tokio's `AsyncRead`/`AsyncWrite`
+#[cfg(feature = "async")]
+pub mod async_io;
+
+/// Avro object container files of whole searches for archival
+#[cfg(feature = "avro")]
+pub mod avro;
+
+/// Base64 encoded, zlib compressed peak arrays
+#[cfg(feature = "binary_peaks")]
+pub mod binary_peaks;
+
+/// Gzip and Zstandard compressed JSON
+#[cfg(feature = "compression")]
+pub mod compression;
+
+/// Rounding of peaks for smaller payloads
+pub mod precision;
+
+//rexports
+#[cfg(feature = "async")]
+pub use async_io::AsyncWireFormat;
+
+// 3rd party imports
+use anyhow::Result;
+use serde::{de::DeserializeOwned, Serialize};
+
+// internal imports
+use crate::results_api::{
+    Identification, MsRun, Peptide, Protein, ProteinGroup, Search, SpectraPage, Spectrum,
+    SpectrumRef,
+};
+
+/// Binary wire formats for the exchange entities, which are considerably smaller and faster than JSON
+/// for spectra with many peaks.
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::{Identification, Precursor};
+/// use maccoys_exchange_entities::serialization::WireFormat;
+/// use polars::prelude::*;
+///
+/// let psms = df!("plain_peptide" => &["PEPTIDE"], "xcorr" => &[2.5]).unwrap();
+/// let identification = Identification::new(None, Some(psms.clone()), Precursor::new(400.7, 2));
+///
+/// let msgpack = identification.to_msgpack().unwrap();
+/// let decoded = Identification::from_msgpack(&msgpack).unwrap();
+/// assert!(decoded.get_psms().as_ref().unwrap().frame_equal(&psms));
+///
+/// let cbor = identification.to_cbor().unwrap();
+/// let decoded = Identification::from_cbor(&cbor).unwrap();
+/// assert!(decoded.get_psms().as_ref().unwrap().frame_equal(&psms));
+/// assert_eq!(decoded.get_charge(), 2);
+/// ```
+///
+pub trait WireFormat: Serialize + DeserializeOwned {
+    /// Serializes to MessagePack, structs are encoded as maps to keep field names
+    ///
+    fn to_msgpack(&self) -> Result<Vec<u8>> {
+        Ok(rmp_serde::to_vec_named(self)?)
+    }
+
+    fn from_msgpack(msgpack: &[u8]) -> Result<Self> {
+        Ok(rmp_serde::from_slice(msgpack)?)
+    }
+
+    /// Serializes to CBOR
+    ///
+    fn to_cbor(&self) -> Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        ciborium::into_writer(self, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    fn from_cbor(cbor: &[u8]) -> Result<Self> {
+        Ok(ciborium::from_reader(cbor)?)
+    }
+}
+
+impl WireFormat for Search {}
+impl WireFormat for MsRun {}
+impl WireFormat for Spectrum {}
+impl WireFormat for Identification {}
+impl WireFormat for Peptide {}
+impl WireFormat for Protein {}
+impl WireFormat for ProteinGroup {}
+impl WireFormat for SpectraPage {}
+impl WireFormat for SpectrumRef {}
@@ -0,0 +1,158 @@
+// 3rd party imports
+use anyhow::Result;
+
+// internal imports
+use crate::results_api::Spectrum;
+
+/// Numeric precision of serialized peaks, e.g. for sending spectra to a viewer
+/// where a few decimals are sufficient. Lossless by default.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NumericPrecision {
+    mz_decimals: Option<u32>,
+    intensity_significant_figures: Option<u32>,
+    float32: bool,
+}
+
+impl NumericPrecision {
+    /// Lossless precision, see the `with_*` methods for reducing it
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rounds m/z values to the given number of decimals, e.g. 4 for high resolution spectra
+    ///
+    pub fn with_mz_decimals(mut self, mz_decimals: u32) -> Self {
+        self.mz_decimals = Some(mz_decimals);
+        self
+    }
+
+    /// Rounds intensities to the given number of significant figures, e.g. 3
+    ///
+    pub fn with_intensity_significant_figures(mut self, significant_figures: u32) -> Self {
+        self.intensity_significant_figures = Some(significant_figures.max(1));
+        self
+    }
+
+    /// Reduces all values to 32-bit float precision (about 7 significant figures)
+    ///
+    pub fn with_float32(mut self) -> Self {
+        self.float32 = true;
+        self
+    }
+
+    pub fn get_mz_decimals(&self) -> Option<u32> {
+        self.mz_decimals
+    }
+
+    pub fn get_intensity_significant_figures(&self) -> Option<u32> {
+        self.intensity_significant_figures
+    }
+
+    pub fn is_float32(&self) -> bool {
+        self.float32
+    }
+
+    /// Rounds the m/z value
+    ///
+    pub fn round_mz(&self, mz: f64) -> f64 {
+        let mz = match self.mz_decimals {
+            Some(decimals) => round_to_exponent(mz, decimals as i32),
+            None => mz,
+        };
+        self.downcast(mz)
+    }
+
+    /// Rounds the intensity
+    ///
+    pub fn round_intensity(&self, intensity: f64) -> f64 {
+        let intensity = match self.intensity_significant_figures {
+            Some(figures) if intensity != 0.0 && intensity.is_finite() => {
+                let magnitude = intensity.abs().log10().floor() as i32;
+                round_to_exponent(intensity, figures as i32 - 1 - magnitude)
+            }
+            _ => intensity,
+        };
+        self.downcast(intensity)
+    }
+
+    /// Shortest decimal representation of the value as 32-bit float, so serializers
+    /// writing decimals (like JSON) do not write the digits of the f64 conversion
+    ///
+    fn downcast(&self, value: f64) -> f64 {
+        match self.float32 {
+            true => (value as f32).to_string().parse().unwrap_or(value),
+            false => value,
+        }
+    }
+}
+
+/// Rounds to `exponent` decimals, negative exponents round to tens, hundreds, ...
+///
+fn round_to_exponent(value: f64, exponent: i32) -> f64 {
+    // dividing by an exact power of ten results in the closest f64 to the rounded decimal
+    match exponent >= 0 {
+        true => {
+            let factor = 10f64.powi(exponent);
+            (value * factor).round() / factor
+        }
+        false => {
+            let factor = 10f64.powi(-exponent);
+            (value / factor).round() * factor
+        }
+    }
+}
+
+impl Spectrum {
+    /// Copy of the spectrum with rounded peaks, identifications and metadata are kept as they are,
+    /// except for the payload digest which no longer matches the peaks
+    ///
+    /// # Arguments
+    /// * `precision` - Precision of the peaks
+    ///
+    pub fn round_peaks(&self, precision: &NumericPrecision) -> Spectrum {
+        let mz: Vec<f64> = self
+            .get_mz()
+            .iter()
+            .map(|mz| precision.round_mz(*mz))
+            .collect();
+        let intensity: Vec<f64> = self
+            .get_intensity()
+            .iter()
+            .map(|intensity| precision.round_intensity(*intensity))
+            .collect();
+        // the digest covers the unrounded peaks
+        self.with_peaks(mz, intensity).with_payload_digest(None)
+    }
+
+    /// Serializes the spectrum to JSON with rounded peaks
+    ///
+    /// # Arguments
+    /// * `precision` - Precision of the peaks
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::results_api::Spectrum;
+    /// use maccoys_exchange_entities::serialization::precision::NumericPrecision;
+    ///
+    /// let spectrum = Spectrum::new(
+    ///     "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+    ///     "run".parse().unwrap(),
+    ///     "scan=1".parse().unwrap(),
+    ///     vec![175.118953, 276.155201],
+    ///     vec![12345.678, 0.0123456],
+    ///     Vec::new(),
+    /// );
+    /// let precision = NumericPrecision::new()
+    ///     .with_mz_decimals(3)
+    ///     .with_intensity_significant_figures(3);
+    ///
+    /// let json = spectrum.to_json_with_precision(&precision).unwrap();
+    /// assert!(json.contains(r#""mz":[175.119,276.155]"#));
+    /// assert!(json.contains(r#""intensity":[12300.0,0.0123]"#));
+    /// ```
+    ///
+    pub fn to_json_with_precision(&self, precision: &NumericPrecision) -> Result<String> {
+        Ok(serde_json::to_string(&self.round_peaks(precision))?)
+    }
+}
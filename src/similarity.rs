@@ -0,0 +1,310 @@
+//! Similarity scores of two spectra, e.g. for finding spectra of modified variants of an identified peptide
+//! (open modification search) or checking PSMs against library spectra.
+//!
+//! Peaks are matched greedily: candidate pairs within the tolerance are accepted in the order of their
+//! intensity product, each peak at most once. All functions require m/z values sorted ascending.
+
+// internal imports
+use crate::results_api::Spectrum;
+use crate::tolerance::Tolerance;
+
+/// Entropy below which intensities are weighted before calculating the entropy similarity
+///
+const ENTROPY_WEIGHTING_THRESHOLD: f64 = 3.0;
+
+/// Peak arrays of a spectrum
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Peaks<'a> {
+    mz: &'a [f64],
+    intensity: &'a [f64],
+}
+
+impl<'a> Peaks<'a> {
+    /// # Arguments
+    /// * `mz` - m/z values sorted ascending
+    /// * `intensity` - Intensities, same length as `mz`
+    ///
+    pub fn new(mz: &'a [f64], intensity: &'a [f64]) -> Self {
+        Self { mz, intensity }
+    }
+
+    pub fn get_mz(&self) -> &'a [f64] {
+        self.mz
+    }
+
+    pub fn get_intensity(&self) -> &'a [f64] {
+        self.intensity
+    }
+
+    pub fn len(&self) -> usize {
+        self.mz.len().min(self.intensity.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<'a> From<&'a Spectrum> for Peaks<'a> {
+    fn from(spectrum: &'a Spectrum) -> Self {
+        Self::new(spectrum.get_mz(), spectrum.get_intensity())
+    }
+}
+
+/// Cosine similarity (normalized dot product) of the matched intensities, between 0 and 1.
+/// Unmatched peaks count as zero intensity in the other spectrum.
+///
+/// # Arguments
+/// * `a` - Peaks of the first spectrum
+/// * `b` - Peaks of the second spectrum
+/// * `tolerance` - Tolerance for matching peaks, relative to the peaks of `a`
+///
+/// ```
+/// use maccoys_exchange_entities::similarity::{cosine, Peaks};
+/// use maccoys_exchange_entities::tolerance::Tolerance;
+///
+/// let a = Peaks::new(&[100.0, 200.0, 300.0], &[1.0, 2.0, 2.0]);
+/// let b = Peaks::new(&[100.001, 200.001, 400.0], &[1.0, 2.0, 2.0]);
+/// assert!((cosine(a, a, Tolerance::Da(0.01)) - 1.0).abs() < 1e-12);
+/// assert!((cosine(a, b, Tolerance::Da(0.01)) - 5.0 / 9.0).abs() < 1e-12);
+/// ```
+///
+pub fn cosine(a: Peaks, b: Peaks, tolerance: Tolerance) -> f64 {
+    normalized_dot_product(a, b, &match_peaks(a, b, tolerance, None))
+}
+
+/// Modified cosine similarity, additionally matching peaks shifted by the precursor mass difference,
+/// e.g. fragments carrying an unknown modification. Between 0 and 1.
+/// Fragments are assumed to be singly charged, so the shift is the difference of the neutral precursor masses,
+/// not of the precursor m/z values (see `crate::mass::mz_to_mass`).
+///
+/// # Arguments
+/// * `a` - Peaks of the first spectrum
+/// * `precursor_mass_a` - Neutral precursor mass of the first spectrum
+/// * `b` - Peaks of the second spectrum
+/// * `precursor_mass_b` - Neutral precursor mass of the second spectrum
+/// * `tolerance` - Tolerance for matching peaks, relative to the (shifted) peaks of `a`
+///
+/// ```
+/// use maccoys_exchange_entities::mass::mz_to_mass;
+/// use maccoys_exchange_entities::similarity::{cosine, modified_cosine, Peaks};
+/// use maccoys_exchange_entities::tolerance::Tolerance;
+///
+/// let a = Peaks::new(&[100.0, 200.0, 300.0], &[1.0, 1.0, 1.0]);
+/// // +16 Da on the fragment at 300
+/// let b = Peaks::new(&[100.0, 200.0, 316.0], &[1.0, 1.0, 1.0]);
+/// let tolerance = Tolerance::Da(0.01);
+/// assert!((cosine(a, b, tolerance) - 2.0 / 3.0).abs() < 1e-12);
+/// assert!((modified_cosine(a, mz_to_mass(500.0, 1), b, mz_to_mass(516.0, 1), tolerance) - 1.0).abs() < 1e-12);
+/// // doubly charged precursors differ by only 8 m/z, the singly charged fragments still by 16 Da
+/// assert!((modified_cosine(a, mz_to_mass(500.0, 2), b, mz_to_mass(508.0, 2), tolerance) - 1.0).abs() < 1e-12);
+/// ```
+///
+pub fn modified_cosine(
+    a: Peaks,
+    precursor_mass_a: f64,
+    b: Peaks,
+    precursor_mass_b: f64,
+    tolerance: Tolerance,
+) -> f64 {
+    let shift = precursor_mass_b - precursor_mass_a;
+    normalized_dot_product(a, b, &match_peaks(a, b, tolerance, Some(shift)))
+}
+
+/// Shannon entropy of the intensities normalized to a sum of 1, in nats.
+/// Low for spectra dominated by few peaks, high for noisy spectra.
+///
+/// ```
+/// use maccoys_exchange_entities::similarity::spectral_entropy;
+///
+/// assert_eq!(spectral_entropy(&[5.0]), 0.0);
+/// assert!((spectral_entropy(&[1.0, 1.0, 1.0, 1.0]) - 4.0_f64.ln()).abs() < 1e-12);
+/// ```
+///
+pub fn spectral_entropy(intensity: &[f64]) -> f64 {
+    let total = intensity.iter().filter(|value| **value > 0.0).sum::<f64>();
+    if total <= 0.0 {
+        return 0.0;
+    }
+    entropy(intensity.iter().map(|value| value / total))
+}
+
+/// Entropy similarity (Li et al. 2021, Nature Methods), between 0 and 1.
+/// Intensities of spectra with an entropy below 3 are weighted by `intensity ^ (0.25 + entropy / 4)` first.
+///
+/// # Arguments
+/// * `a` - Peaks of the first spectrum
+/// * `b` - Peaks of the second spectrum
+/// * `tolerance` - Tolerance for matching peaks, relative to the peaks of `a`
+///
+/// ```
+/// use maccoys_exchange_entities::similarity::{entropy_similarity, Peaks};
+/// use maccoys_exchange_entities::tolerance::Tolerance;
+///
+/// let a = Peaks::new(&[100.0, 200.0, 300.0], &[1.0, 2.0, 2.0]);
+/// let b = Peaks::new(&[400.0, 500.0], &[1.0, 2.0]);
+/// assert!((entropy_similarity(a, a, Tolerance::Da(0.01)) - 1.0).abs() < 1e-12);
+/// assert!(entropy_similarity(a, b, Tolerance::Da(0.01)).abs() < 1e-12);
+/// ```
+///
+pub fn entropy_similarity(a: Peaks, b: Peaks, tolerance: Tolerance) -> f64 {
+    let (weighted_a, weighted_b) = (weighted_probabilities(a), weighted_probabilities(b));
+    if weighted_a.is_empty() || weighted_b.is_empty() {
+        return 0.0;
+    }
+    let matched_peaks = match_peaks(a, b, tolerance, None);
+    let mut a_matched = vec![false; weighted_a.len()];
+    let mut b_matched = vec![false; weighted_b.len()];
+    let mut merged: Vec<f64> = Vec::with_capacity(weighted_a.len() + weighted_b.len());
+    for (a_idx, b_idx) in matched_peaks.iter() {
+        a_matched[*a_idx] = true;
+        b_matched[*b_idx] = true;
+        merged.push((weighted_a[*a_idx] + weighted_b[*b_idx]) / 2.0);
+    }
+    for (probabilities, matched) in [(&weighted_a, &a_matched), (&weighted_b, &b_matched)] {
+        merged.extend(
+            probabilities
+                .iter()
+                .zip(matched.iter())
+                .filter(|(_, matched)| !**matched)
+                .map(|(probability, _)| probability / 2.0),
+        );
+    }
+    let entropy_a = entropy(weighted_a.iter().copied());
+    let entropy_b = entropy(weighted_b.iter().copied());
+    let entropy_merged = entropy(merged.into_iter());
+    (1.0 - (2.0 * entropy_merged - entropy_a - entropy_b) / 4.0_f64.ln()).clamp(0.0, 1.0)
+}
+
+/// Dot bias (Lam et al. 2007), i.e. how much the cosine is dominated by few matched peaks.
+/// Around 0.1 to 0.4 for good matches, close to 1 if a single peak pair dominates.
+/// None if no peaks are matched.
+///
+/// # Arguments
+/// * `a` - Peaks of the first spectrum
+/// * `b` - Peaks of the second spectrum
+/// * `tolerance` - Tolerance for matching peaks, relative to the peaks of `a`
+///
+/// ```
+/// use maccoys_exchange_entities::similarity::{dot_bias, Peaks};
+/// use maccoys_exchange_entities::tolerance::Tolerance;
+///
+/// let a = Peaks::new(&[100.0, 200.0, 300.0, 400.0], &[1.0, 1.0, 1.0, 1.0]);
+/// let b = Peaks::new(&[100.0, 500.0], &[1.0, 1.0]);
+/// assert!((dot_bias(a, a, Tolerance::Da(0.01)).unwrap() - 0.5).abs() < 1e-12);
+/// assert!((dot_bias(a, b, Tolerance::Da(0.01)).unwrap() - 1.0).abs() < 1e-12);
+/// assert_eq!(dot_bias(b, Peaks::new(&[300.0], &[1.0]), Tolerance::Da(0.01)), None);
+/// ```
+///
+pub fn dot_bias(a: Peaks, b: Peaks, tolerance: Tolerance) -> Option<f64> {
+    let products = match_peaks(a, b, tolerance, None)
+        .into_iter()
+        .map(|(a_idx, b_idx)| a.intensity[a_idx] * b.intensity[b_idx])
+        .collect::<Vec<f64>>();
+    let dot_product = products.iter().sum::<f64>();
+    if dot_product <= 0.0 {
+        return None;
+    }
+    Some(
+        products
+            .iter()
+            .map(|product| product * product)
+            .sum::<f64>()
+            .sqrt()
+            / dot_product,
+    )
+}
+
+/// Matches the peaks greedily by intensity product, see module documentation.
+/// With a shift, peaks of `b` shifted by it are matched as well.
+///
+/// Returns pairs of peak indexes (a, b), ordered by the peaks of `a`
+///
+fn match_peaks(
+    a: Peaks,
+    b: Peaks,
+    tolerance: Tolerance,
+    shift: Option<f64>,
+) -> Vec<(usize, usize)> {
+    let (a_len, b_len) = (a.len(), b.len());
+    let b_mz = &b.mz[..b_len];
+    let mut candidates: Vec<(usize, usize, f64)> = Vec::new();
+    for a_idx in 0..a_len {
+        let shifts = [Some(0.0), shift.filter(|shift| *shift != 0.0)];
+        for shift in shifts.into_iter().flatten() {
+            for b_idx in tolerance.peak_range(b_mz, a.mz[a_idx] + shift) {
+                candidates.push((a_idx, b_idx, a.intensity[a_idx] * b.intensity[b_idx]));
+            }
+        }
+    }
+    candidates.sort_by(|x, y| y.2.total_cmp(&x.2));
+
+    let mut a_matched = vec![false; a_len];
+    let mut b_matched = vec![false; b_len];
+    let mut matched_peaks: Vec<(usize, usize)> = Vec::new();
+    for (a_idx, b_idx, _) in candidates {
+        if a_matched[a_idx] || b_matched[b_idx] {
+            continue;
+        }
+        a_matched[a_idx] = true;
+        b_matched[b_idx] = true;
+        matched_peaks.push((a_idx, b_idx));
+    }
+    matched_peaks.sort_unstable();
+    matched_peaks
+}
+
+fn normalized_dot_product(a: Peaks, b: Peaks, matched_peaks: &[(usize, usize)]) -> f64 {
+    let dot_product = matched_peaks
+        .iter()
+        .map(|(a_idx, b_idx)| a.intensity[*a_idx] * b.intensity[*b_idx])
+        .sum::<f64>();
+    let norm = |peaks: Peaks| {
+        peaks.intensity[..peaks.len()]
+            .iter()
+            .map(|value| value * value)
+            .sum::<f64>()
+            .sqrt()
+    };
+    let (norm_a, norm_b) = (norm(a), norm(b));
+    match norm_a > 0.0 && norm_b > 0.0 {
+        true => (dot_product / (norm_a * norm_b)).clamp(0.0, 1.0),
+        false => 0.0,
+    }
+}
+
+/// Intensities normalized to a sum of 1, weighted for low entropy spectra (see `entropy_similarity`).
+/// Empty if the spectrum has no intensity.
+///
+fn weighted_probabilities(peaks: Peaks) -> Vec<f64> {
+    let intensity = &peaks.intensity[..peaks.len()];
+    let total = intensity.iter().map(|value| value.max(0.0)).sum::<f64>();
+    if total <= 0.0 {
+        return Vec::new();
+    }
+    let probabilities = intensity
+        .iter()
+        .map(|value| value.max(0.0) / total)
+        .collect::<Vec<f64>>();
+    let spectrum_entropy = entropy(probabilities.iter().copied());
+    if spectrum_entropy >= ENTROPY_WEIGHTING_THRESHOLD {
+        return probabilities;
+    }
+    let weight = 0.25 + spectrum_entropy * 0.25;
+    let weighted = probabilities
+        .iter()
+        .map(|probability| probability.powf(weight))
+        .collect::<Vec<f64>>();
+    let total = weighted.iter().sum::<f64>();
+    weighted.into_iter().map(|value| value / total).collect()
+}
+
+/// Shannon entropy of the probabilities, zeros are skipped
+///
+fn entropy(probabilities: impl Iterator<Item = f64>) -> f64 {
+    -probabilities
+        .filter(|probability| *probability > 0.0)
+        .map(|probability| probability * probability.ln())
+        .sum::<f64>()
+}
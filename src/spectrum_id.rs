@@ -0,0 +1,254 @@
+//! Parsing of spectrum IDs as written by vendors, converters and search engines, e.g. Thermo's
+//! `controllerType=0 controllerNumber=1 scan=42`, the `index=41` of index based mzMLs
+//! or TPP style MGF titles like `run.42.42.2`. The scan number and index allow matching IDs
+//! of the same spectrum written by different tools.
+
+// std imports
+use std::collections::BTreeMap;
+use std::fmt;
+
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+/// Format of a spectrum ID
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NativeIdFormat {
+    /// `controllerType=0 controllerNumber=1 scan=42`
+    Thermo,
+    /// `function=1 process=0 scan=42`
+    Waters,
+    /// `sample=1 period=1 cycle=42 experiment=1`
+    Sciex,
+    /// `scanId=42`
+    Agilent,
+    /// `merged=42 frame=42 scanStart=1 scanEnd=500` or `frame=42 scan=1`
+    BrukerTdf,
+    /// `index=41`, zero-based index within the file
+    Index,
+    /// `scan=42`
+    Scan,
+    /// MGF title of the TPP, `<run>.<start scan>.<end scan>.<charge>`
+    TppTitle,
+    /// Any other ID, e.g. free text MGF titles
+    Unknown,
+}
+
+/// Spectrum ID split into its fields
+///
+/// ```
+/// use maccoys_exchange_entities::spectrum_id::{NativeIdFormat, NativeSpectrumId};
+///
+/// let thermo = NativeSpectrumId::parse("controllerType=0 controllerNumber=1 scan=42");
+/// assert_eq!(thermo.get_format(), NativeIdFormat::Thermo);
+/// assert_eq!(thermo.get_scan_number(), Some(42));
+/// assert_eq!(thermo.get_field("controllerNumber"), Some("1"));
+///
+/// let title = NativeSpectrumId::parse("20230101_HeLa.42.42.2");
+/// assert_eq!(title.get_format(), NativeIdFormat::TppTitle);
+/// assert_eq!(title.get_charge(), Some(2));
+/// assert!(title.matches(&thermo));
+///
+/// // titles of msconvert contain the native ID of the original file
+/// let msconvert = NativeSpectrumId::parse(
+///     r#"HeLa.42.42.2 File:"HeLa.raw", NativeID:"controllerType=0 controllerNumber=1 scan=42""#,
+/// );
+/// assert_eq!(msconvert.get_format(), NativeIdFormat::Thermo);
+/// assert!(msconvert.matches(&thermo));
+///
+/// assert_eq!(NativeSpectrumId::parse("index=41").get_index(), Some(41));
+/// assert_eq!(NativeSpectrumId::parse("my spectrum").get_scan_number(), None);
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NativeSpectrumId {
+    raw: String,
+    format: NativeIdFormat,
+    fields: BTreeMap<String, String>,
+    scan_number: Option<u32>,
+    index: Option<usize>,
+    charge: Option<u8>,
+}
+
+impl NativeSpectrumId {
+    /// Parses the ID, IDs of unknown formats are kept as they are
+    ///
+    /// # Arguments
+    /// * `spectrum_id` - Spectrum ID, native ID or MGF title
+    ///
+    pub fn parse(spectrum_id: &str) -> Self {
+        let raw = spectrum_id.trim();
+        if let Some(native_id) = embedded_native_id(raw) {
+            let mut parsed = Self::parse(native_id);
+            parsed.raw = raw.to_string();
+            return parsed;
+        }
+        if let Some(fields) = key_values(raw) {
+            return Self::from_fields(raw, fields);
+        }
+        if let Some((scan_number, charge)) = tpp_title(raw) {
+            return Self {
+                raw: raw.to_string(),
+                format: NativeIdFormat::TppTitle,
+                fields: BTreeMap::new(),
+                scan_number: Some(scan_number),
+                index: None,
+                charge,
+            };
+        }
+        Self {
+            raw: raw.to_string(),
+            format: NativeIdFormat::Unknown,
+            fields: BTreeMap::new(),
+            scan_number: None,
+            index: None,
+            charge: None,
+        }
+    }
+
+    fn from_fields(raw: &str, fields: BTreeMap<String, String>) -> Self {
+        let has = |keys: &[&str]| keys.iter().all(|key| fields.contains_key(*key));
+        let number = |key: &str| fields.get(key).and_then(|value| value.parse::<u32>().ok());
+        let (format, scan_number) = if has(&["controllerType", "controllerNumber", "scan"]) {
+            (NativeIdFormat::Thermo, number("scan"))
+        } else if has(&["function", "process", "scan"]) {
+            (NativeIdFormat::Waters, number("scan"))
+        } else if has(&["sample", "period", "cycle", "experiment"]) {
+            (NativeIdFormat::Sciex, number("cycle"))
+        } else if has(&["scanId"]) {
+            (NativeIdFormat::Agilent, number("scanId"))
+        } else if has(&["frame"]) {
+            (NativeIdFormat::BrukerTdf, number("frame"))
+        } else if has(&["scan"]) {
+            (NativeIdFormat::Scan, number("scan"))
+        } else if has(&["index"]) {
+            (NativeIdFormat::Index, None)
+        } else {
+            (NativeIdFormat::Unknown, None)
+        };
+        let index = fields.get("index").and_then(|value| value.parse().ok());
+        Self {
+            raw: raw.to_string(),
+            format,
+            fields,
+            scan_number,
+            index,
+            charge: None,
+        }
+    }
+
+    /// The ID as given
+    ///
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    pub fn get_format(&self) -> NativeIdFormat {
+        self.format
+    }
+
+    /// Value of the given `key=value` field of the native ID
+    ///
+    pub fn get_field(&self, key: &str) -> Option<&str> {
+        self.fields.get(key).map(String::as_str)
+    }
+
+    /// All `key=value` fields of the native ID
+    ///
+    pub fn get_fields(&self) -> &BTreeMap<String, String> {
+        &self.fields
+    }
+
+    /// Scan number, for Sciex the cycle, for Bruker TDF the frame
+    ///
+    pub fn get_scan_number(&self) -> Option<u32> {
+        self.scan_number
+    }
+
+    /// Zero-based index within the file, if part of the ID
+    ///
+    pub fn get_index(&self) -> Option<usize> {
+        self.index
+    }
+
+    /// Precursor charge, only part of TPP style MGF titles
+    ///
+    pub fn get_charge(&self) -> Option<u8> {
+        self.charge
+    }
+
+    /// True if both IDs refer to the same spectrum, compared by scan number if both have one,
+    /// otherwise by index if both have one, otherwise by the IDs as given.
+    /// IDs of different MS runs are not distinguished.
+    ///
+    pub fn matches(&self, other: &NativeSpectrumId) -> bool {
+        match (self.scan_number, other.scan_number) {
+            (Some(scan_number), Some(other_scan_number)) => scan_number == other_scan_number,
+            _ => match (self.index, other.index) {
+                (Some(index), Some(other_index)) => index == other_index,
+                _ => self.raw == other.raw,
+            },
+        }
+    }
+}
+
+impl From<&str> for NativeSpectrumId {
+    fn from(spectrum_id: &str) -> Self {
+        Self::parse(spectrum_id)
+    }
+}
+
+impl fmt::Display for NativeSpectrumId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+/// Native ID within MGF titles of msconvert, e.g. `... NativeID:"controllerType=0 controllerNumber=1 scan=42"`
+///
+fn embedded_native_id(title: &str) -> Option<&str> {
+    let (_, native_id) = title.split_once("NativeID:\"")?;
+    let (native_id, _) = native_id.split_once('"')?;
+    Some(native_id)
+}
+
+/// Splits IDs consisting only of whitespace separated `key=value` pairs
+///
+fn key_values(spectrum_id: &str) -> Option<BTreeMap<String, String>> {
+    let mut fields = BTreeMap::new();
+    for part in spectrum_id.split_whitespace() {
+        let (key, value) = part.split_once('=')?;
+        if key.is_empty() || value.is_empty() {
+            return None;
+        }
+        fields.insert(key.to_string(), value.to_string());
+    }
+    match fields.is_empty() {
+        true => None,
+        false => Some(fields),
+    }
+}
+
+/// Start scan and charge of TPP style titles `<run>.<start scan>.<end scan>.<charge>`.
+/// Titles without charge (`<run>.<start scan>.<end scan>`) are accepted as well.
+///
+fn tpp_title(title: &str) -> Option<(u32, Option<u8>)> {
+    // only the first word, as some converters append further information
+    let title = title.split_whitespace().next()?;
+    let parts = title.rsplitn(4, '.').collect::<Vec<&str>>();
+    let numbers = parts
+        .iter()
+        .take(3)
+        .map(|part| part.parse::<u32>().ok())
+        .collect::<Vec<Option<u32>>>();
+    match (parts.len(), numbers.as_slice()) {
+        // <run>.<start>.<end>.<charge>
+        (4, [Some(charge), Some(_), Some(start)]) if !parts[3].is_empty() => {
+            Some((*start, u8::try_from(*charge).ok()))
+        }
+        // <run>.<start>.<end>
+        (3.., [Some(end), Some(start), ..]) if start <= end => Some((*start, None)),
+        _ => None,
+    }
+}
@@ -0,0 +1,321 @@
+// std imports
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::results_api::GoodnessOfFit;
+
+/// Name of the location parameter
+pub const LOC: &str = "loc";
+/// Name of the scale parameter
+pub const SCALE: &str = "scale";
+/// Name of the shape parameter
+pub const SHAPE: &str = "shape";
+
+/// Euler-Mascheroni constant
+const EULER_GAMMA: f64 = 0.577_215_664_901_532_9;
+
+/// Distributions which can be fitted to PSM scores
+///
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Distribution {
+    /// Exponential distribution shifted to the minimal score, parameters `loc` and `scale`
+    Exponential,
+    /// Gamma distribution, parameters `shape` and `scale`. Requires positive scores.
+    Gamma,
+    /// Gumbel distribution of maxima, parameters `loc` and `scale`
+    Gumbel,
+}
+
+impl Distribution {
+    /// All supported distributions
+    ///
+    pub const ALL: [Distribution; 3] = [Self::Exponential, Self::Gamma, Self::Gumbel];
+
+    /// Name as used in the goodness table
+    ///
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Exponential => "exponential",
+            Self::Gamma => "gamma",
+            Self::Gumbel => "gumbel",
+        }
+    }
+
+    /// Distribution with the given name, see `name`
+    ///
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|distribution| distribution.name() == name)
+    }
+
+    /// Estimates the parameters from the given values, None if the values do not allow a fit
+    /// (less than two values, no variance or non-positive values for gamma).
+    ///
+    /// * Exponential: maximum likelihood
+    /// * Gamma: maximum likelihood using the approximation of Thom for the shape
+    /// * Gumbel: method of moments
+    ///
+    pub fn fit_parameters(&self, values: &[f64]) -> Option<BTreeMap<String, f64>> {
+        if values.len() < 2 {
+            return None;
+        }
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let parameters = match self {
+            Self::Exponential => {
+                let loc = values.iter().copied().fold(f64::INFINITY, f64::min);
+                vec![(LOC, loc), (SCALE, mean - loc)]
+            }
+            Self::Gamma => {
+                if values.iter().any(|value| *value <= 0.0) {
+                    return None;
+                }
+                let mean_ln = values.iter().map(|value| value.ln()).sum::<f64>() / n;
+                let s = mean.ln() - mean_ln;
+                let shape = (3.0 - s + ((s - 3.0).powi(2) + 24.0 * s).sqrt()) / (12.0 * s);
+                vec![(SHAPE, shape), (SCALE, mean / shape)]
+            }
+            Self::Gumbel => {
+                let variance = values
+                    .iter()
+                    .map(|value| (value - mean).powi(2))
+                    .sum::<f64>()
+                    / (n - 1.0);
+                let scale = variance.sqrt() * 6.0_f64.sqrt() / PI;
+                vec![(LOC, mean - EULER_GAMMA * scale), (SCALE, scale)]
+            }
+        };
+        // only the location may be non-positive
+        if !parameters.iter().all(|(_, value)| value.is_finite())
+            || parameters
+                .iter()
+                .any(|(name, value)| *name != LOC && *value <= 0.0)
+        {
+            return None;
+        }
+        Some(
+            parameters
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        )
+    }
+
+    /// Cumulative distribution function with the given parameters (see `fit_parameters`),
+    /// None if a parameter is missing.
+    ///
+    pub fn cdf(&self, parameters: &BTreeMap<String, f64>, x: f64) -> Option<f64> {
+        let scale = *parameters.get(SCALE)?;
+        Some(match self {
+            Self::Exponential => {
+                let z = (x - parameters.get(LOC)?) / scale;
+                if z <= 0.0 {
+                    0.0
+                } else {
+                    1.0 - (-z).exp()
+                }
+            }
+            Self::Gamma => {
+                if x <= 0.0 {
+                    0.0
+                } else {
+                    regularized_lower_gamma(*parameters.get(SHAPE)?, x / scale)
+                }
+            }
+            Self::Gumbel => (-(-(x - parameters.get(LOC)?) / scale).exp()).exp(),
+        })
+    }
+
+    /// Fits the distribution to the given values and tests the goodness of fit with the one-sample
+    /// Kolmogorov-Smirnov test. NaNs are ignored. None if the values do not allow a fit.
+    ///
+    pub fn fit(&self, values: &[f64]) -> Option<GoodnessOfFit> {
+        let mut sorted_values = values
+            .iter()
+            .copied()
+            .filter(|value| !value.is_nan())
+            .collect::<Vec<f64>>();
+        sorted_values.sort_unstable_by(|a, b| a.total_cmp(b));
+        let parameters = self.fit_parameters(&sorted_values)?;
+
+        let n = sorted_values.len() as f64;
+        let mut statistic: f64 = 0.0;
+        for (i, value) in sorted_values.iter().enumerate() {
+            let cdf = self.cdf(&parameters, *value)?;
+            statistic = statistic
+                .max(cdf - i as f64 / n)
+                .max((i + 1) as f64 / n - cdf);
+        }
+        Some(GoodnessOfFit::new(
+            self.name().to_string(),
+            statistic,
+            kolmogorov_smirnov_p_value(statistic, n),
+            parameters,
+        ))
+    }
+}
+
+/// Asymptotic p-value of the Kolmogorov-Smirnov statistic `d` for `n` values,
+/// using the approximation of Stephens for small `n`
+///
+fn kolmogorov_smirnov_p_value(d: f64, n: f64) -> f64 {
+    let sqrt_n = n.sqrt();
+    let lambda = (sqrt_n + 0.12 + 0.11 / sqrt_n) * d;
+    if lambda < 1e-3 {
+        return 1.0;
+    }
+    let mut sum = 0.0;
+    for k in 1..=100 {
+        let k = k as f64;
+        let term = 2.0 * (-2.0 * k * k * lambda * lambda).exp();
+        sum += if k as u32 % 2 == 1 { term } else { -term };
+        if term < 1e-12 {
+            break;
+        }
+    }
+    sum.clamp(0.0, 1.0)
+}
+
+/// Natural logarithm of the gamma function (Lanczos approximation)
+///
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.120_865_097_386_617_9e-2,
+        -0.539_523_938_495_3e-5,
+    ];
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let mut series = 1.000_000_000_190_015;
+    for (i, coefficient) in COEFFICIENTS.iter().enumerate() {
+        series += coefficient / (x + 1.0 + i as f64);
+    }
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+/// Regularized lower incomplete gamma function P(a, x), using the series expansion for `x < a + 1`
+/// and the continued fraction otherwise
+///
+fn regularized_lower_gamma(a: f64, x: f64) -> f64 {
+    const MAX_ITERATIONS: usize = 500;
+    const EPSILON: f64 = 1e-14;
+    let ln_prefactor = a * x.ln() - x - ln_gamma(a);
+    if x < a + 1.0 {
+        let mut denominator = a;
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for _ in 0..MAX_ITERATIONS {
+            denominator += 1.0;
+            term *= x / denominator;
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+        (sum * ln_prefactor.exp()).clamp(0.0, 1.0)
+    } else {
+        // modified Lentz's method
+        let tiny = f64::MIN_POSITIVE / EPSILON;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..=MAX_ITERATIONS {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < EPSILON {
+                break;
+            }
+        }
+        (1.0 - ln_prefactor.exp() * h).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() < tolerance,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn ln_gamma_matches_factorials() {
+        assert_close(ln_gamma(1.0), 0.0, 1e-10);
+        assert_close(ln_gamma(2.0), 0.0, 1e-10);
+        // ln(9!)
+        assert_close(ln_gamma(10.0), 12.801_827_480_081_467, 1e-9);
+        // ln(sqrt(pi))
+        assert_close(ln_gamma(0.5), 0.572_364_942_924_700_4, 1e-9);
+        assert_close(ln_gamma(4.5), 2.453_736_570_842_443, 1e-9);
+    }
+
+    #[test]
+    fn regularized_lower_gamma_matches_closed_forms() {
+        // P(1, x) = 1 - e^-x
+        assert_close(
+            regularized_lower_gamma(1.0, 0.5),
+            0.393_469_340_287_366_6,
+            1e-9,
+        );
+        // P(0.5, x) = erf(sqrt(x))
+        assert_close(
+            regularized_lower_gamma(0.5, 2.0),
+            0.954_499_736_103_641_6,
+            1e-9,
+        );
+        // series expansion, P(3, 2) = 1 - 5 e^-2
+        assert_close(
+            regularized_lower_gamma(3.0, 2.0),
+            0.323_323_583_816_936_5,
+            1e-9,
+        );
+        // continued fraction, P(2, 5) = 1 - 6 e^-5
+        assert_close(
+            regularized_lower_gamma(2.0, 5.0),
+            0.959_572_318_005_487_1,
+            1e-9,
+        );
+    }
+
+    #[test]
+    fn kolmogorov_smirnov_p_value_matches_kolmogorov_distribution() {
+        // statistics for which Stephens' lambda is 1 and 1.36 with n = 100
+        let scale = 10.0 + 0.12 + 0.011;
+        assert_close(
+            kolmogorov_smirnov_p_value(1.0 / scale, 100.0),
+            0.269_999_671_677_354_56,
+            1e-12,
+        );
+        assert_close(
+            kolmogorov_smirnov_p_value(1.36 / scale, 100.0),
+            0.049_485_876_755_377_876,
+            1e-12,
+        );
+        assert_eq!(kolmogorov_smirnov_p_value(0.0, 100.0), 1.0);
+    }
+}
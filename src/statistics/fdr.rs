@@ -0,0 +1,244 @@
+// 3rd party imports
+use anyhow::Result;
+use polars::prelude::*;
+
+// internal imports
+use crate::results_api::{psm_columns, Identification};
+
+/// Comet's default prefix for decoy accessions
+pub const DEFAULT_DECOY_PREFIX: &str = "DECOY_";
+
+/// Parameters for the target-decoy FDR estimation
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct FdrConfig {
+    decoy_prefix: String,
+    score_column: String,
+    higher_is_better: bool,
+}
+
+impl FdrConfig {
+    pub fn new(decoy_prefix: String, score_column: String, higher_is_better: bool) -> Self {
+        Self {
+            decoy_prefix,
+            score_column,
+            higher_is_better,
+        }
+    }
+
+    pub fn get_decoy_prefix(&self) -> &str {
+        &self.decoy_prefix
+    }
+
+    pub fn get_score_column(&self) -> &str {
+        &self.score_column
+    }
+
+    pub fn is_higher_better(&self) -> bool {
+        self.higher_is_better
+    }
+}
+
+impl Default for FdrConfig {
+    /// Comet's decoy prefix and xcorr
+    ///
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_DECOY_PREFIX.to_string(),
+            psm_columns::XCORR.to_string(),
+            true,
+        )
+    }
+}
+
+/// Checks if the given comma separated accessions only contain decoys
+///
+pub fn is_decoy_accession(accessions: &str, decoy_prefix: &str) -> bool {
+    let mut has_accession = false;
+    for accession in accessions.split(',').map(|acc| acc.trim()) {
+        if accession.is_empty() {
+            continue;
+        }
+        if !accession.starts_with(decoy_prefix) {
+            return false;
+        }
+        has_accession = true;
+    }
+    has_accession
+}
+
+/// Flags each PSM of the given dataframe as decoy if all its proteins have the given prefix
+///
+pub fn decoy_flags(psms: &DataFrame, decoy_prefix: &str) -> Result<BooleanChunked> {
+    let proteins = psms.column(psm_columns::PROTEIN)?.utf8()?;
+    Ok(proteins
+        .into_iter()
+        .map(|accessions| Some(accessions.is_some_and(|acc| is_decoy_accession(acc, decoy_prefix))))
+        .collect::<BooleanChunked>()
+        .with_name(psm_columns::IS_DECOY))
+}
+
+/// Calculates the FDR (decoys / targets) and q-value for each PSM of the given dataframe.
+/// PSMs with equal scores share the same FDR, PSMs without score are considered worst.
+///
+/// # Arguments
+/// * `psms` - PSM dataframe
+/// * `config` - FDR parameters
+///
+/// Returns FDRs and q-values in the order of the dataframe
+///
+pub fn compute(psms: &DataFrame, config: &FdrConfig) -> Result<(Vec<f64>, Vec<f64>)> {
+    let is_decoy = decoy_flags(psms, config.get_decoy_prefix())?;
+    let scores = psms
+        .column(config.get_score_column())?
+        .cast(&DataType::Float64)?;
+    let scores = scores.f64()?.into_iter().collect::<Vec<Option<f64>>>();
+    let is_decoy = is_decoy
+        .into_iter()
+        .map(|is_decoy| is_decoy.unwrap_or(false))
+        .collect::<Vec<bool>>();
+    Ok(compute_from_flags(
+        &scores,
+        &is_decoy,
+        config.is_higher_better(),
+    ))
+}
+
+/// Same as `compute` for already extracted scores and decoy flags, e.g. from an `is_decoy` column
+///
+/// # Arguments
+/// * `scores` - Scores of the PSMs
+/// * `is_decoy` - Decoy flags of the PSMs, same length as `scores`
+/// * `higher_is_better` - True if higher scores are better
+///
+/// Returns FDRs and q-values in the order of the scores
+///
+pub fn compute_from_flags(
+    scores: &[Option<f64>],
+    is_decoy: &[bool],
+    higher_is_better: bool,
+) -> (Vec<f64>, Vec<f64>) {
+    // order from best to worst score, missing scores last
+    let mut order = (0..scores.len()).collect::<Vec<usize>>();
+    order.sort_by(|a, b| match (scores[*a], scores[*b]) {
+        (Some(a), Some(b)) if higher_is_better => b.total_cmp(&a),
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+
+    let mut fdr = vec![0.0; scores.len()];
+    let mut targets: usize = 0;
+    let mut decoys: usize = 0;
+    let mut block_start = 0;
+    for pos in 0..order.len() {
+        if is_decoy[order[pos]] {
+            decoys += 1;
+        } else {
+            targets += 1;
+        }
+        // assign FDR at the end of a block of equal scores
+        let is_block_end = pos + 1 == order.len() || scores[order[pos]] != scores[order[pos + 1]];
+        if is_block_end {
+            let block_fdr = if targets == 0 {
+                1.0
+            } else {
+                (decoys as f64 / targets as f64).min(1.0)
+            };
+            for idx in order[block_start..=pos].iter() {
+                fdr[*idx] = block_fdr;
+            }
+            block_start = pos + 1;
+        }
+    }
+
+    // q-value is the minimal FDR at which the PSM is accepted
+    let mut q_value = vec![0.0; scores.len()];
+    let mut min_fdr: f64 = 1.0;
+    for idx in order.iter().rev() {
+        min_fdr = min_fdr.min(fdr[*idx]);
+        q_value[*idx] = min_fdr;
+    }
+
+    (fdr, q_value)
+}
+
+/// Appends the columns `is_decoy`, `fdr` and `q_value` to the given dataframe, replacing existing ones
+///
+pub fn append(psms: &mut DataFrame, config: &FdrConfig) -> Result<()> {
+    let is_decoy = decoy_flags(psms, config.get_decoy_prefix())?;
+    let (fdr, q_value) = compute(psms, config)?;
+    psms.with_column(is_decoy.into_series())?;
+    psms.with_column(Series::new(psm_columns::FDR, fdr))?;
+    psms.with_column(Series::new(psm_columns::Q_VALUE, q_value))?;
+    Ok(())
+}
+
+impl Identification {
+    /// Appends target-decoy FDR and q-value columns to the PSMs, see `statistics::fdr::append`.
+    /// Does nothing if the identification has no PSMs.
+    ///
+    pub fn append_fdr(&mut self, config: &FdrConfig) -> Result<()> {
+        if let Some(psms) = self.get_psms_mut() {
+            append(psms, config)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_all_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!(
+                (actual - expected).abs() < 1e-12,
+                "{:?} != {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn monotonizes_q_values() {
+        let scores = [10.0, 9.0, 8.0, 7.0, 6.0, 5.0].map(Some);
+        let is_decoy = [false, true, false, false, false, true];
+        let (fdr, q_value) = compute_from_flags(&scores, &is_decoy, true);
+        assert_all_close(&fdr, &[0.0, 1.0, 0.5, 1.0 / 3.0, 0.25, 0.5]);
+        // the minimal FDR of the PSM and all worse ones
+        assert_all_close(&q_value, &[0.0, 0.25, 0.25, 0.25, 0.25, 0.5]);
+    }
+
+    #[test]
+    fn orders_by_score_direction() {
+        let scores = [0.001, 0.01, 0.1, 1.0, 10.0, 100.0].map(Some);
+        let is_decoy = [false, true, false, false, false, true];
+        let (fdr, q_value) = compute_from_flags(&scores, &is_decoy, false);
+        assert_all_close(&fdr, &[0.0, 1.0, 0.5, 1.0 / 3.0, 0.25, 0.5]);
+        assert_all_close(&q_value, &[0.0, 0.25, 0.25, 0.25, 0.25, 0.5]);
+    }
+
+    #[test]
+    fn equal_scores_share_fdr_and_missing_scores_are_worst() {
+        let scores = [None, Some(2.0), Some(2.0), Some(1.0)];
+        let is_decoy = [true, false, true, false];
+        let (fdr, q_value) = compute_from_flags(&scores, &is_decoy, true);
+        assert_all_close(&fdr, &[1.0, 1.0, 1.0, 0.5]);
+        assert_all_close(&q_value, &[1.0, 0.5, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn flags_decoys_by_prefix() {
+        assert!(is_decoy_accession(
+            "DECOY_P1, DECOY_P2",
+            DEFAULT_DECOY_PREFIX
+        ));
+        assert!(!is_decoy_accession("DECOY_P1,P2", DEFAULT_DECOY_PREFIX));
+        assert!(!is_decoy_accession(" , ", DEFAULT_DECOY_PREFIX));
+        assert!(is_decoy_accession("REV_P1", "REV_"));
+    }
+}
@@ -0,0 +1,180 @@
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+/// Upper bound of the number of bins of the Freedman-Diaconis and Scott rules, which would otherwise
+/// result in millions of bins for a few extreme outliers
+///
+pub const MAX_BINS: usize = 10_000;
+
+/// Bin widths below this fraction of the range are treated as zero, e.g. if most values are equal
+///
+const MIN_RELATIVE_BIN_WIDTH: f64 = 1e-9;
+
+/// Strategy to determine the number of histogram bins
+///
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum BinStrategy {
+    /// `1 + log2(n)` bins
+    Sturges,
+    /// Bin width of `2 * IQR / n^(1/3)`, at most `n` and `MAX_BINS` bins
+    FreedmanDiaconis,
+    /// Bin width of `3.49 * σ / n^(1/3)`, at most `n` and `MAX_BINS` bins
+    Scott,
+    /// Fixed number of bins
+    Fixed(usize),
+}
+
+impl BinStrategy {
+    /// Calculates the number of bins for the given values, which need to be sorted ascending.
+    /// Falls back to Sturges if the bin width of Freedman-Diaconis or Scott is (close to) zero.
+    ///
+    fn num_bins(&self, sorted_values: &[f64]) -> usize {
+        let n = sorted_values.len() as f64;
+        self.num_bins_with(
+            n,
+            sorted_values[sorted_values.len() - 1] - sorted_values[0],
+            || quantile(sorted_values, 0.75) - quantile(sorted_values, 0.25),
+            || {
+                let mean = sorted_values.iter().sum::<f64>() / n;
+                let variance = sorted_values
+                    .iter()
+                    .map(|value| (value - mean).powi(2))
+                    .sum::<f64>()
+                    / n;
+                variance.sqrt()
+            },
+        )
+    }
+
+    /// Calculates the number of bins from the given statistics, the interquartile range and the (population)
+    /// standard deviation are only calculated if required by the strategy.
+    ///
+    pub(crate) fn num_bins_with<I, S>(&self, n: f64, range: f64, iqr: I, std_dev: S) -> usize
+    where
+        I: FnOnce() -> f64,
+        S: FnOnce() -> f64,
+    {
+        let bin_width = match self {
+            Self::Sturges => return sturges(n),
+            Self::Fixed(num_bins) => return (*num_bins).max(1),
+            Self::FreedmanDiaconis => 2.0 * iqr() / n.cbrt(),
+            Self::Scott => 3.49 * std_dev() / n.cbrt(),
+        };
+        // negated to fall back on NaN as well
+        if !(range > 0.0 && bin_width > range * MIN_RELATIVE_BIN_WIDTH) {
+            return sturges(n);
+        }
+        ((range / bin_width).ceil() as usize).clamp(1, (n as usize).clamp(1, MAX_BINS))
+    }
+}
+
+/// Rule of Sturges
+///
+fn sturges(n: f64) -> usize {
+    ((1.0 + n.log2()).round() as usize).max(1)
+}
+
+/// Linear interpolated quantile of the given sorted values
+///
+pub(crate) fn quantile(sorted_values: &[f64], q: f64) -> f64 {
+    let pos = q * (sorted_values.len() - 1) as f64;
+    let lower = pos.floor() as usize;
+    let upper = pos.ceil() as usize;
+    sorted_values[lower] + (sorted_values[upper] - sorted_values[lower]) * (pos - lower as f64)
+}
+
+/// Index of the bin the value belongs to, see `Histogram::from_values`
+///
+pub(crate) fn bin_index(value: f64, min: f64, bin_width: f64, num_bins: usize) -> usize {
+    if bin_width > 0.0 {
+        (((value - min) / bin_width).ceil() as usize).clamp(1, num_bins) - 1
+    } else {
+        0
+    }
+}
+
+/// Histogram with equally sized bins
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Histogram {
+    edges: Vec<f64>,
+    counts: Vec<usize>,
+}
+
+impl Histogram {
+    pub fn new(edges: Vec<f64>, counts: Vec<usize>) -> Self {
+        Self { edges, counts }
+    }
+
+    /// Creates a histogram of the given values. NaNs are ignored.
+    /// A value belongs to bin `i` if `edges[i] < value <= edges[i + 1]`, the minimum belongs to the first bin.
+    /// Returns None if there are no values.
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::statistics::histogram::{BinStrategy, Histogram};
+    ///
+    /// // the interquartile range is zero, so Sturges is used
+    /// let mut values = vec![1.0; 1000];
+    /// values.push(1e12);
+    /// let histogram = Histogram::from_values(&values, BinStrategy::FreedmanDiaconis).unwrap();
+    /// assert_eq!(histogram.num_bins(), 11);
+    ///
+    /// // tiny interquartile range compared to the outlier, at most one bin per value
+    /// let mut values = (0..1000).map(|i| i as f64 * 1e-3).collect::<Vec<f64>>();
+    /// values.push(1e6);
+    /// let histogram = Histogram::from_values(&values, BinStrategy::FreedmanDiaconis).unwrap();
+    /// assert_eq!(histogram.num_bins(), 1001);
+    /// ```
+    ///
+    pub fn from_values(values: &[f64], strategy: BinStrategy) -> Option<Self> {
+        let mut sorted_values = values
+            .iter()
+            .copied()
+            .filter(|value| !value.is_nan())
+            .collect::<Vec<f64>>();
+        if sorted_values.is_empty() {
+            return None;
+        }
+        sorted_values.sort_by(|a, b| a.total_cmp(b));
+
+        let num_bins = strategy.num_bins(&sorted_values);
+        let min = sorted_values[0];
+        let max = sorted_values[sorted_values.len() - 1];
+        let bin_width = (max - min) / num_bins as f64;
+
+        let edges = (0..=num_bins)
+            .map(|i| min + i as f64 * bin_width)
+            .collect::<Vec<f64>>();
+
+        let mut counts: Vec<usize> = vec![0; num_bins];
+        for value in sorted_values.iter() {
+            counts[bin_index(*value, min, bin_width, num_bins)] += 1;
+        }
+
+        Some(Self { edges, counts })
+    }
+
+    /// Bin edges, one more than bins
+    ///
+    pub fn get_edges(&self) -> &Vec<f64> {
+        &self.edges
+    }
+
+    /// Number of values per bin
+    ///
+    pub fn get_counts(&self) -> &Vec<usize> {
+        &self.counts
+    }
+
+    pub fn num_bins(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Returns edges and counts
+    ///
+    pub fn into_parts(self) -> (Vec<f64>, Vec<usize>) {
+        (self.edges, self.counts)
+    }
+}
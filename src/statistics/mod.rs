@@ -0,0 +1,10 @@
+/// Fitting of score distributions
+pub mod distributions;
+/// Target-decoy FDR and q-value estimation
+pub mod fdr;
+/// Histograms with different binning strategies
+pub mod histogram;
+/// Posterior error probability estimation
+pub mod pep;
+/// Parallel descriptive statistics and histograms on polars columns
+pub mod summary;
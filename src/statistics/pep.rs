@@ -0,0 +1,203 @@
+// 3rd party imports
+use anyhow::Result;
+use polars::prelude::*;
+
+// internal imports
+use crate::results_api::{psm_columns, Identification};
+
+/// Estimates the posterior error probability (PEP) for each PSM of the given dataframe.
+///
+/// The probability of a PSM at a given score to be a decoy is estimated nonparametrically by isotonic regression
+/// (pool adjacent violators) of the decoy flags against the score, so it can only increase with decreasing score.
+/// As decoys model the incorrect targets, the PEP is the ratio of decoys to targets at that score, capped at 1.
+/// PSMs with equal scores share the same PEP, PSMs without score get a PEP of 1.
+///
+/// # Arguments
+/// * `psms` - PSM dataframe
+/// * `score_column` - Numeric score column
+/// * `decoy_column` - Boolean decoy column (see `Identification::mark_decoys`), nulls are considered targets
+/// * `higher_is_better` - True if higher scores are better (e.g. xcorr), false otherwise (e.g. e-value)
+///
+/// Returns the PEPs in the order of the dataframe
+///
+pub fn compute(
+    psms: &DataFrame,
+    score_column: &str,
+    decoy_column: &str,
+    higher_is_better: bool,
+) -> Result<Vec<f64>> {
+    let scores = psms.column(score_column)?.cast(&DataType::Float64)?;
+    let scores = scores.f64()?.into_iter().collect::<Vec<Option<f64>>>();
+    let is_decoy = psms
+        .column(decoy_column)?
+        .bool()?
+        .into_iter()
+        .map(|is_decoy| is_decoy.unwrap_or(false))
+        .collect::<Vec<bool>>();
+    Ok(compute_from_flags(&scores, &is_decoy, higher_is_better))
+}
+
+/// Same as `compute` for already extracted scores and decoy flags
+///
+/// # Arguments
+/// * `scores` - Scores of the PSMs, NaN is considered missing
+/// * `is_decoy` - Decoy flags of the PSMs, same length as `scores`
+/// * `higher_is_better` - True if higher scores are better
+///
+/// Returns the PEPs in the order of the scores
+///
+pub fn compute_from_flags(
+    scores: &[Option<f64>],
+    is_decoy: &[bool],
+    higher_is_better: bool,
+) -> Vec<f64> {
+    let scores = scores
+        .iter()
+        .map(|score| score.filter(|score| !score.is_nan()))
+        .collect::<Vec<Option<f64>>>();
+
+    // order from best to worst score, PSMs without score are left out
+    let mut order = (0..scores.len())
+        .filter(|idx| scores[*idx].is_some())
+        .collect::<Vec<usize>>();
+    order.sort_by(|a, b| {
+        let (a, b) = (scores[*a].unwrap(), scores[*b].unwrap());
+        if higher_is_better {
+            b.total_cmp(&a)
+        } else {
+            a.total_cmp(&b)
+        }
+    });
+
+    // pool adjacent violators, each block is (number of decoys, number of PSMs, end position in `order`)
+    let mut blocks: Vec<(usize, usize, usize)> = Vec::new();
+    let mut block_start = 0;
+    for pos in 0..order.len() {
+        // start with one block per group of equal scores
+        let is_block_end = pos + 1 == order.len() || scores[order[pos]] != scores[order[pos + 1]];
+        if !is_block_end {
+            continue;
+        }
+        let decoys = order[block_start..=pos]
+            .iter()
+            .filter(|idx| is_decoy[**idx])
+            .count();
+        blocks.push((decoys, pos + 1 - block_start, pos + 1));
+        block_start = pos + 1;
+        // merge while the decoy ratio of the previous block is higher
+        while blocks.len() > 1 {
+            let (decoys, count, end) = blocks[blocks.len() - 1];
+            let (prev_decoys, prev_count, _) = blocks[blocks.len() - 2];
+            if prev_decoys * count <= decoys * prev_count {
+                break;
+            }
+            blocks.pop();
+            let last_idx = blocks.len() - 1;
+            blocks[last_idx] = (prev_decoys + decoys, prev_count + count, end);
+        }
+    }
+
+    let mut pep = vec![1.0; scores.len()];
+    let mut block_start = 0;
+    for (decoys, count, end) in blocks.into_iter() {
+        let targets = count - decoys;
+        let block_pep = if targets == 0 {
+            1.0
+        } else {
+            (decoys as f64 / targets as f64).min(1.0)
+        };
+        for idx in order[block_start..end].iter() {
+            pep[*idx] = block_pep;
+        }
+        block_start = end;
+    }
+
+    pep
+}
+
+impl Identification {
+    /// Appends the posterior error probability as column `pep` to the PSMs, replacing an existing one,
+    /// see `statistics::pep::compute`. Does nothing if the identification has no PSMs.
+    ///
+    /// # Arguments
+    /// * `score_col` - Numeric score column (e.g. `xcorr`)
+    /// * `decoy_col` - Boolean decoy column (e.g. `is_decoy`)
+    /// * `higher_is_better` - True if higher scores are better
+    ///
+    pub fn compute_pep(
+        &mut self,
+        score_col: &str,
+        decoy_col: &str,
+        higher_is_better: bool,
+    ) -> Result<()> {
+        if let Some(psms) = self.get_psms_mut() {
+            let pep = compute(psms, score_col, decoy_col, higher_is_better)?;
+            psms.with_column(Series::new(psm_columns::PEP, pep))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_all_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!(
+                (actual - expected).abs() < 1e-12,
+                "{:?} != {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn pools_adjacent_violators() {
+        // decoy ratios 0, 0, 0, 1, 0, 0, 1, 1 from best to worst, the 4th to 6th PSM are pooled to 1 of 3
+        let scores = [8.0, 7.0, 6.0, 5.0, 4.0, 3.0, 2.0, 1.0].map(Some);
+        let is_decoy = [false, false, false, true, false, false, true, true];
+        let pep = compute_from_flags(&scores, &is_decoy, true);
+        assert_all_close(&pep, &[0.0, 0.0, 0.0, 0.5, 0.5, 0.5, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn orders_by_score_direction() {
+        let scores = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0].map(Some);
+        let is_decoy = [false, false, false, true, false, false, true, true];
+        let pep = compute_from_flags(&scores, &is_decoy, false);
+        assert_all_close(&pep, &[0.0, 0.0, 0.0, 0.5, 0.5, 0.5, 1.0, 1.0]);
+        // with higher scores being better the decoys are among the best PSMs, so all PSMs are pooled
+        // into one block of 3 decoys and 5 targets
+        let pep = compute_from_flags(&scores, &is_decoy, true);
+        assert_all_close(&pep, &[0.6; 8]);
+    }
+
+    #[test]
+    fn equal_scores_share_pep_and_missing_scores_get_one() {
+        let scores = [
+            Some(3.0),
+            Some(2.0),
+            Some(2.0),
+            Some(1.0),
+            None,
+            Some(f64::NAN),
+        ];
+        let is_decoy = [false, false, true, false, false, false];
+        let pep = compute_from_flags(&scores, &is_decoy, true);
+        assert_all_close(&pep, &[0.0, 0.5, 0.5, 0.5, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn computes_from_dataframe() {
+        let psms = df!(
+            "e_value" => &[0.001, 0.01, 0.1, 1.0],
+            "is_decoy" => &[Some(false), None, Some(true), Some(true)]
+        )
+        .unwrap();
+        let pep = compute(&psms, "e_value", "is_decoy", false).unwrap();
+        assert_all_close(&pep, &[0.0, 0.0, 1.0, 1.0]);
+    }
+}
@@ -0,0 +1,211 @@
+// 3rd party imports
+use polars::export::arrow::array::{Array, PrimitiveArray};
+use polars::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::statistics::histogram::{bin_index, quantile, BinStrategy, Histogram};
+
+/// Descriptive statistics of a score column
+///
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Summary {
+    count: usize,
+    mean: f64,
+    std_dev: f64,
+    min: f64,
+    max: f64,
+    median: f64,
+    quantiles: Vec<(f64, f64)>,
+}
+
+impl Summary {
+    /// Number of values, nulls and NaNs excluded
+    ///
+    pub fn get_count(&self) -> usize {
+        self.count
+    }
+
+    pub fn get_mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Population standard deviation
+    ///
+    pub fn get_std_dev(&self) -> f64 {
+        self.std_dev
+    }
+
+    pub fn get_min(&self) -> f64 {
+        self.min
+    }
+
+    pub fn get_max(&self) -> f64 {
+        self.max
+    }
+
+    pub fn get_median(&self) -> f64 {
+        self.median
+    }
+
+    /// Requested quantiles as (q, value) tuples
+    ///
+    pub fn get_quantiles(&self) -> &Vec<(f64, f64)> {
+        &self.quantiles
+    }
+}
+
+/// Count, sum, sum of squares, min and max, combinable across threads
+///
+#[derive(Clone, Copy)]
+struct Moments {
+    count: usize,
+    sum: f64,
+    sum_of_squares: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Moments {
+    fn identity() -> Self {
+        Self {
+            count: 0,
+            sum: 0.0,
+            sum_of_squares: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    fn add(mut self, value: f64) -> Self {
+        self.count += 1;
+        self.sum += value;
+        self.sum_of_squares += value * value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self
+    }
+
+    fn combine(self, other: Self) -> Self {
+        Self {
+            count: self.count + other.count,
+            sum: self.sum + other.sum,
+            sum_of_squares: self.sum_of_squares + other.sum_of_squares,
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    fn mean(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+
+    fn std_dev(&self) -> f64 {
+        let mean = self.mean();
+        (self.sum_of_squares / self.count as f64 - mean * mean)
+            .max(0.0)
+            .sqrt()
+    }
+}
+
+/// Parallel iterator over the non-null, non-NaN values of all chunks, without copying them
+///
+fn par_values(values: &Float64Chunked) -> impl ParallelIterator<Item = f64> + '_ {
+    values
+        .downcast_iter()
+        .collect::<Vec<&PrimitiveArray<f64>>>()
+        .into_par_iter()
+        .flat_map(|array| {
+            array
+                .values()
+                .as_slice()
+                .par_iter()
+                .enumerate()
+                .filter(move |(idx, value)| array.is_valid(*idx) && !value.is_nan())
+                .map(|(_, value)| *value)
+        })
+}
+
+fn moments(values: &Float64Chunked) -> Moments {
+    par_values(values)
+        .fold(Moments::identity, Moments::add)
+        .reduce(Moments::identity, Moments::combine)
+}
+
+fn par_sorted(values: &Float64Chunked) -> Vec<f64> {
+    let mut sorted_values = par_values(values).collect::<Vec<f64>>();
+    sorted_values.par_sort_unstable_by(|a, b| a.total_cmp(b));
+    sorted_values
+}
+
+/// Creates a histogram like `Histogram::from_values`, but counts in parallel on the chunks of the column.
+/// Values are only copied for the Freedman-Diaconis rule, which needs the interquartile range.
+/// Returns None if there are no values.
+///
+pub fn par_histogram(values: &Float64Chunked, strategy: BinStrategy) -> Option<Histogram> {
+    let moments = moments(values);
+    if moments.count == 0 {
+        return None;
+    }
+    let num_bins = strategy.num_bins_with(
+        moments.count as f64,
+        moments.max - moments.min,
+        || {
+            let sorted_values = par_sorted(values);
+            quantile(&sorted_values, 0.75) - quantile(&sorted_values, 0.25)
+        },
+        || moments.std_dev(),
+    );
+    let bin_width = (moments.max - moments.min) / num_bins as f64;
+    let edges = (0..=num_bins)
+        .map(|i| moments.min + i as f64 * bin_width)
+        .collect::<Vec<f64>>();
+    let counts = par_values(values)
+        .fold(
+            || vec![0; num_bins],
+            |mut counts, value| {
+                counts[bin_index(value, moments.min, bin_width, num_bins)] += 1;
+                counts
+            },
+        )
+        .reduce(
+            || vec![0; num_bins],
+            |mut counts, other| {
+                counts
+                    .iter_mut()
+                    .zip(other.iter())
+                    .for_each(|(count, other)| *count += other);
+                counts
+            },
+        );
+    Some(Histogram::new(edges, counts))
+}
+
+/// Calculates mean, standard deviation, min, max, median and the given quantiles in parallel.
+/// Returns None if there are no values.
+///
+/// # Arguments
+/// * `values` - Values, nulls and NaNs are ignored
+/// * `quantiles` - Quantiles between 0.0 and 1.0, e.g. 0.95 for the 95th percentile
+///
+pub fn par_summary(values: &Float64Chunked, quantiles: &[f64]) -> Option<Summary> {
+    let moments = moments(values);
+    if moments.count == 0 {
+        return None;
+    }
+    let sorted_values = par_sorted(values);
+    Some(Summary {
+        count: moments.count,
+        mean: moments.mean(),
+        std_dev: moments.std_dev(),
+        min: moments.min,
+        max: moments.max,
+        median: quantile(&sorted_values, 0.5),
+        quantiles: quantiles
+            .iter()
+            .map(|q| (*q, quantile(&sorted_values, q.clamp(0.0, 1.0))))
+            .collect(),
+    })
+}
@@ -0,0 +1,130 @@
+// std imports
+use std::future::Future;
+use std::sync::Arc;
+
+// internal imports
+use crate::results_api::{Identification, MsRun, Search, Spectrum};
+use crate::storage::store::{ResultStore, StoreError};
+
+/// Asynchronous counterpart of `ResultStore` for async services, with the same semantics.
+/// Synchronous stores can be used behind an `Arc`, their calls are moved to the blocking thread pool.
+///
+/// ```
+/// use std::sync::Arc;
+/// use maccoys_exchange_entities::results_api::Search;
+/// use maccoys_exchange_entities::storage::{AsyncResultStore, MemoryStore};
+///
+/// tokio::runtime::Runtime::new().unwrap().block_on(async {
+///     let store = Arc::new(MemoryStore::new());
+///     let search_uuid = "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c";
+///     store.put_search(Search::new(search_uuid.parse().unwrap(), Vec::new())).await.unwrap();
+///     assert_eq!(store.get_search(search_uuid).await.unwrap().get_search_uuid(), search_uuid);
+/// });
+/// ```
+///
+pub trait AsyncResultStore: Send + Sync {
+    fn get_search(
+        &self,
+        search_uuid: &str,
+    ) -> impl Future<Output = Result<Search, StoreError>> + Send;
+
+    /// Returns the MS runs of the given search, ordered by name
+    ///
+    fn list_ms_runs(
+        &self,
+        search_uuid: &str,
+    ) -> impl Future<Output = Result<Vec<MsRun>, StoreError>> + Send;
+
+    fn get_spectrum(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+        spectrum_id: &str,
+    ) -> impl Future<Output = Result<Spectrum, StoreError>> + Send;
+
+    fn put_search(&self, search: Search) -> impl Future<Output = Result<(), StoreError>> + Send;
+
+    fn put_ms_run(&self, ms_run: MsRun) -> impl Future<Output = Result<(), StoreError>> + Send;
+
+    fn put_spectrum(
+        &self,
+        spectrum: Spectrum,
+    ) -> impl Future<Output = Result<(), StoreError>> + Send;
+
+    fn put_identification(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+        spectrum_id: &str,
+        identification: Identification,
+    ) -> impl Future<Output = Result<(), StoreError>> + Send;
+}
+
+/// Runs the call of a synchronous store on the blocking thread pool
+///
+async fn blocking<S, T, F>(store: &Arc<S>, call: F) -> Result<T, StoreError>
+where
+    S: ResultStore + 'static,
+    T: Send + 'static,
+    F: FnOnce(&S) -> Result<T, StoreError> + Send + 'static,
+{
+    let store = Arc::clone(store);
+    tokio::task::spawn_blocking(move || call(&store))
+        .await
+        .map_err(anyhow::Error::from)?
+}
+
+impl<S: ResultStore + 'static> AsyncResultStore for Arc<S> {
+    async fn get_search(&self, search_uuid: &str) -> Result<Search, StoreError> {
+        let search_uuid = search_uuid.to_string();
+        blocking(self, move |store| store.get_search(&search_uuid)).await
+    }
+
+    async fn list_ms_runs(&self, search_uuid: &str) -> Result<Vec<MsRun>, StoreError> {
+        let search_uuid = search_uuid.to_string();
+        blocking(self, move |store| store.list_ms_runs(&search_uuid)).await
+    }
+
+    async fn get_spectrum(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+        spectrum_id: &str,
+    ) -> Result<Spectrum, StoreError> {
+        let search_uuid = search_uuid.to_string();
+        let ms_run_name = ms_run_name.to_string();
+        let spectrum_id = spectrum_id.to_string();
+        blocking(self, move |store| {
+            store.get_spectrum(&search_uuid, &ms_run_name, &spectrum_id)
+        })
+        .await
+    }
+
+    async fn put_search(&self, search: Search) -> Result<(), StoreError> {
+        blocking(self, move |store| store.put_search(search)).await
+    }
+
+    async fn put_ms_run(&self, ms_run: MsRun) -> Result<(), StoreError> {
+        blocking(self, move |store| store.put_ms_run(ms_run)).await
+    }
+
+    async fn put_spectrum(&self, spectrum: Spectrum) -> Result<(), StoreError> {
+        blocking(self, move |store| store.put_spectrum(spectrum)).await
+    }
+
+    async fn put_identification(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+        spectrum_id: &str,
+        identification: Identification,
+    ) -> Result<(), StoreError> {
+        let search_uuid = search_uuid.to_string();
+        let ms_run_name = ms_run_name.to_string();
+        let spectrum_id = spectrum_id.to_string();
+        blocking(self, move |store| {
+            store.put_identification(&search_uuid, &ms_run_name, &spectrum_id, identification)
+        })
+        .await
+    }
+}
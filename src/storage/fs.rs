@@ -0,0 +1,437 @@
+//! Result store on a plain directory, so results can be persisted and served without a database:
+//!
+//! ```text
+//! <root>/
+//!     index.json                    UUIDs of the stored searches
+//!     <search_uuid>/
+//!         search.json
+//!         <ms_run>/
+//!             ms_run.json
+//!             manifest.json         `SpectrumRef`s of the stored spectra
+//!             manifest.log          `SpectrumRef`s stored since the last compaction, one JSON object per line
+//!             <spectrum_id>.parquet peaks (mz, intensity)
+//!             <spectrum_id>.json    metadata and identifications
+//! ```
+//!
+//! Files are written to a temporary file first and renamed afterwards, so readers never see partial files.
+//! As a spectrum is split into two files, which are renamed one after the other, reading and writing
+//! spectra is guarded by a lock of the store, so use a single `FsStore` per directory.
+//! Storing a spectrum only appends its reference to `manifest.log`, which is compacted into `manifest.json`
+//! when the spectra are listed, so storing many spectra does not rewrite the manifest each time.
+//! Names are percent-encoded if they contain characters other than ASCII letters, digits, `-`, `_` and `=`,
+//! names exceeding `MAX_NAME_LENGTH` after encoding are truncated and suffixed with a digest of the full name.
+//! Search UUIDs are normalized to lowercase, so they can be looked up in any form `SearchUuid` accepts.
+
+// std imports
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{PoisonError, RwLock};
+
+// 3rd party imports
+use anyhow::{Context, Result};
+use polars::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+
+// internal imports
+use crate::results_api::{Identification, MsRun, Search, SearchUuid, Spectrum, SpectrumRef};
+use crate::storage::store::{ResultStore, StoreError};
+
+const INDEX_FILE: &str = "index.json";
+const SEARCH_FILE: &str = "search.json";
+const MS_RUN_FILE: &str = "ms_run.json";
+const MANIFEST_FILE: &str = "manifest.json";
+const MANIFEST_LOG_FILE: &str = "manifest.log";
+
+/// Maximum length of an encoded name, leaving room for extensions like `.parquet.tmp`
+/// within the file name limit of 255 bytes of common file systems
+///
+pub const MAX_NAME_LENGTH: usize = 200;
+
+/// Result store on a directory, see the module documentation for the layout
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::{MsRun, Search, Spectrum};
+/// use maccoys_exchange_entities::storage::{FsStore, ResultStore};
+///
+/// let root = std::env::temp_dir().join("maccoys_fs_store_doctest");
+/// let store = FsStore::new(&root).unwrap();
+/// let search_uuid = "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c";
+/// store.put_search(Search::new(search_uuid.parse().unwrap(), vec!["run".parse().unwrap()])).unwrap();
+/// store.put_ms_run(MsRun::new(search_uuid.parse().unwrap(), "run".parse().unwrap(), vec!["scan=1".parse().unwrap()])).unwrap();
+/// store.put_spectrum(Spectrum::new(
+///     search_uuid.parse().unwrap(),
+///     "run".parse().unwrap(),
+///     "controllerType=0 scan=1".parse().unwrap(),
+///     vec![100.0, 200.0],
+///     vec![1.0, 2.0],
+///     Vec::new(),
+/// ).with_retention_time(Some(12.5))).unwrap();
+///
+/// let spectrum = store.get_spectrum(search_uuid, "run", "controllerType=0 scan=1").unwrap();
+/// assert_eq!(spectrum.get_mz(), &[100.0, 200.0]);
+/// assert_eq!(spectrum.get_retention_time(), &Some(12.5));
+/// assert_eq!(store.list_searches().unwrap(), vec![search_uuid.to_string()]);
+/// assert_eq!(store.list_spectra(search_uuid, "run").unwrap().len(), 1);
+///
+/// // UUIDs are looked up case-insensitively, long spectrum IDs are shortened to valid file names
+/// let long_id = format!("scan=1 {}", "x".repeat(300));
+/// store.put_spectrum(Spectrum::new(
+///     search_uuid.parse().unwrap(),
+///     "run".parse().unwrap(),
+///     long_id.parse().unwrap(),
+///     vec![100.0],
+///     vec![1.0],
+///     Vec::new(),
+/// )).unwrap();
+/// let spectrum = store.get_spectrum(&search_uuid.to_uppercase(), "run", &long_id).unwrap();
+/// assert_eq!(spectrum.get_spectra_id(), long_id.as_str());
+/// assert_eq!(store.list_spectra(search_uuid, "run").unwrap().len(), 2);
+/// # std::fs::remove_dir_all(&root).unwrap();
+/// ```
+///
+pub struct FsStore {
+    root: PathBuf,
+    // serializes the read-modify-write of the index and manifests and keeps readers of a spectrum
+    // from seeing the peaks of one write and the metadata of another
+    lock: RwLock<()>,
+}
+
+impl FsStore {
+    /// Opens the store in the given directory, which is created if necessary
+    ///
+    /// # Arguments
+    /// * `root` - Root directory
+    ///
+    pub fn new(root: &Path) -> Result<Self> {
+        fs::create_dir_all(root)?;
+        Ok(Self {
+            root: root.to_path_buf(),
+            lock: RwLock::new(()),
+        })
+    }
+
+    pub fn get_root(&self) -> &Path {
+        &self.root
+    }
+
+    /// UUIDs of the stored searches, in ascending order
+    ///
+    pub fn list_searches(&self) -> Result<Vec<String>, StoreError> {
+        Ok(self.read_index()?.into_iter().collect())
+    }
+
+    /// References of the stored spectra of the given MS run, ordered by spectrum ID.
+    /// References stored since the last call are compacted into the manifest.
+    ///
+    /// # Arguments
+    /// * `search_uuid` - UUID of the search
+    /// * `ms_run_name` - Name of the MS run
+    ///
+    pub fn list_spectra(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+    ) -> Result<Vec<SpectrumRef>, StoreError> {
+        let _guard = self.lock.write().unwrap_or_else(PoisonError::into_inner);
+        self.ensure_ms_run(search_uuid, ms_run_name)?;
+        Ok(compact_manifest(
+            &self.ms_run_path(search_uuid, ms_run_name),
+        )?)
+    }
+
+    fn search_path(&self, search_uuid: &str) -> PathBuf {
+        self.root.join(encode_name(&normalize_uuid(search_uuid)))
+    }
+
+    fn ms_run_path(&self, search_uuid: &str, ms_run_name: &str) -> PathBuf {
+        self.search_path(search_uuid).join(encode_name(ms_run_name))
+    }
+
+    fn read_index(&self) -> Result<BTreeSet<String>> {
+        read_json_or_default(&self.root.join(INDEX_FILE))
+    }
+
+    fn ensure_search(&self, search_uuid: &str) -> Result<(), StoreError> {
+        if self.search_path(search_uuid).join(SEARCH_FILE).is_file() {
+            Ok(())
+        } else {
+            Err(StoreError::SearchNotFound(search_uuid.to_string()))
+        }
+    }
+
+    fn ensure_ms_run(&self, search_uuid: &str, ms_run_name: &str) -> Result<(), StoreError> {
+        self.ensure_search(search_uuid)?;
+        if self
+            .ms_run_path(search_uuid, ms_run_name)
+            .join(MS_RUN_FILE)
+            .is_file()
+        {
+            Ok(())
+        } else {
+            Err(StoreError::MsRunNotFound(
+                search_uuid.to_string(),
+                ms_run_name.to_string(),
+            ))
+        }
+    }
+
+    /// Reads the metadata and peaks of a spectrum, the caller has to hold the lock
+    ///
+    fn read_spectrum(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+        spectrum_id: &str,
+    ) -> Result<Spectrum, StoreError> {
+        self.ensure_ms_run(search_uuid, ms_run_name)?;
+        let ms_run_path = self.ms_run_path(search_uuid, ms_run_name);
+        let file_stem = encode_name(spectrum_id);
+        let spectrum_file = ms_run_path.join(format!("{}.json", file_stem));
+        if !spectrum_file.is_file() {
+            return Err(StoreError::SpectrumNotFound(
+                search_uuid.to_string(),
+                ms_run_name.to_string(),
+                spectrum_id.to_string(),
+            ));
+        }
+        let spectrum: Spectrum = read_json(&spectrum_file)?;
+        let peaks_file = ms_run_path.join(format!("{}.parquet", file_stem));
+        let peaks = File::open(&peaks_file)
+            .map_err(anyhow::Error::from)
+            .and_then(|file| Ok(ParquetReader::new(file).finish()?))
+            .with_context(|| format!("reading peaks from {}", peaks_file.display()))?;
+        let mz = column_values(&peaks, "mz")?;
+        let intensity = column_values(&peaks, "intensity")?;
+        Ok(spectrum.with_peaks(mz, intensity))
+    }
+
+    fn write_spectrum(&self, spectrum: &Spectrum) -> Result<(), StoreError> {
+        let ms_run_path = self.ms_run_path(spectrum.get_search_uuid(), spectrum.get_ms_run());
+        let file_stem = encode_name(spectrum.get_spectra_id());
+
+        let mut peaks = DataFrame::new(vec![
+            Series::new("mz", spectrum.get_mz()),
+            Series::new("intensity", spectrum.get_intensity()),
+        ])
+        .map_err(anyhow::Error::from)?;
+        write_atomic(
+            &ms_run_path.join(format!("{}.parquet", file_stem)),
+            |file| {
+                ParquetWriter::new(file).finish(&mut peaks)?;
+                Ok(())
+            },
+        )?;
+        // peaks are stored in the Parquet file, so they are left out here
+        write_json(
+            &ms_run_path.join(format!("{}.json", file_stem)),
+            &spectrum.with_peaks(Vec::new(), Vec::new()),
+        )?;
+
+        append_to_manifest_log(&ms_run_path, &spectrum.to_ref())?;
+        Ok(())
+    }
+}
+
+impl ResultStore for FsStore {
+    fn get_search(&self, search_uuid: &str) -> Result<Search, StoreError> {
+        self.ensure_search(search_uuid)?;
+        Ok(read_json(&self.search_path(search_uuid).join(SEARCH_FILE))?)
+    }
+
+    fn list_ms_runs(&self, search_uuid: &str) -> Result<Vec<MsRun>, StoreError> {
+        self.ensure_search(search_uuid)?;
+        let mut ms_runs = Vec::new();
+        for entry in fs::read_dir(self.search_path(search_uuid)).map_err(anyhow::Error::from)? {
+            let ms_run_file = entry.map_err(anyhow::Error::from)?.path().join(MS_RUN_FILE);
+            if ms_run_file.is_file() {
+                ms_runs.push(read_json::<MsRun>(&ms_run_file)?);
+            }
+        }
+        ms_runs.sort_by(|a, b| a.get_ms_run().cmp(b.get_ms_run()));
+        Ok(ms_runs)
+    }
+
+    fn get_spectrum(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+        spectrum_id: &str,
+    ) -> Result<Spectrum, StoreError> {
+        let _guard = self.lock.read().unwrap_or_else(PoisonError::into_inner);
+        self.read_spectrum(search_uuid, ms_run_name, spectrum_id)
+    }
+
+    fn put_search(&self, search: Search) -> Result<(), StoreError> {
+        let _guard = self.lock.write().unwrap_or_else(PoisonError::into_inner);
+        let search_path = self.search_path(search.get_search_uuid());
+        fs::create_dir_all(&search_path).map_err(anyhow::Error::from)?;
+        write_json(&search_path.join(SEARCH_FILE), &search)?;
+
+        let mut index = self.read_index()?;
+        if index.insert(normalize_uuid(search.get_search_uuid())) {
+            write_json(&self.root.join(INDEX_FILE), &index)?;
+        }
+        Ok(())
+    }
+
+    fn put_ms_run(&self, ms_run: MsRun) -> Result<(), StoreError> {
+        let _guard = self.lock.write().unwrap_or_else(PoisonError::into_inner);
+        self.ensure_search(ms_run.get_search_uuid())?;
+        let ms_run_path = self.ms_run_path(ms_run.get_search_uuid(), ms_run.get_ms_run());
+        fs::create_dir_all(&ms_run_path).map_err(anyhow::Error::from)?;
+        write_json(&ms_run_path.join(MS_RUN_FILE), &ms_run)?;
+        Ok(())
+    }
+
+    fn put_spectrum(&self, spectrum: Spectrum) -> Result<(), StoreError> {
+        let _guard = self.lock.write().unwrap_or_else(PoisonError::into_inner);
+        self.ensure_ms_run(spectrum.get_search_uuid(), spectrum.get_ms_run())?;
+        self.write_spectrum(&spectrum)
+    }
+
+    fn put_identification(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+        spectrum_id: &str,
+        identification: Identification,
+    ) -> Result<(), StoreError> {
+        let _guard = self.lock.write().unwrap_or_else(PoisonError::into_inner);
+        let mut spectrum = self.read_spectrum(search_uuid, ms_run_name, spectrum_id)?;
+        spectrum.put_identification(identification);
+        self.write_spectrum(&spectrum)
+    }
+}
+
+/// Lowercase hyphenated form of the UUID, other values are returned as they are
+///
+fn normalize_uuid(search_uuid: &str) -> String {
+    SearchUuid::new(search_uuid)
+        .map(|search_uuid| search_uuid.to_string())
+        .unwrap_or_else(|_| search_uuid.to_string())
+}
+
+/// Percent-encodes all characters except ASCII letters, digits, `-`, `_` and `=`,
+/// so the name is usable as file name on all platforms.
+/// Encoded names longer than `MAX_NAME_LENGTH` are truncated and suffixed with `~` and the
+/// first 32 hex digits of the SHA-256 digest of the name, so they stay distinct.
+///
+fn encode_name(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'=') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    if encoded.len() > MAX_NAME_LENGTH {
+        let digest = Sha256::digest(name.as_bytes())
+            .iter()
+            .take(16)
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        // encoded names are ASCII, so every index is a char boundary
+        encoded.truncate(MAX_NAME_LENGTH - digest.len() - 1);
+        encoded.push('~');
+        encoded.push_str(&digest);
+    }
+    encoded
+}
+
+/// Appends the reference as one line to the manifest log of the MS run
+///
+fn append_to_manifest_log(ms_run_path: &Path, reference: &SpectrumRef) -> Result<()> {
+    let log_path = ms_run_path.join(MANIFEST_LOG_FILE);
+    let mut line = serde_json::to_vec(reference)?;
+    line.push(b'\n');
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .with_context(|| format!("opening {}", log_path.display()))?;
+    file.write_all(&line)?;
+    file.sync_data()?;
+    Ok(())
+}
+
+/// Merges the manifest log into the manifest, later references replacing earlier ones of the same spectrum,
+/// and returns the references ordered by spectrum ID
+///
+fn compact_manifest(ms_run_path: &Path) -> Result<Vec<SpectrumRef>> {
+    let manifest_path = ms_run_path.join(MANIFEST_FILE);
+    let log_path = ms_run_path.join(MANIFEST_LOG_FILE);
+    let manifest: Vec<SpectrumRef> = read_json_or_default(&manifest_path)?;
+    let log = match fs::read(&log_path) {
+        Ok(log) => log,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(manifest),
+        Err(err) => return Err(err.into()),
+    };
+    let mut references = manifest
+        .into_iter()
+        .map(|reference| (reference.get_spectra_id().to_string(), reference))
+        .collect::<BTreeMap<String, SpectrumRef>>();
+    // lines which are not valid JSON can only stem from interrupted appends and are skipped
+    for reference in log
+        .split(|byte| *byte == b'\n')
+        .filter_map(|line| serde_json::from_slice::<SpectrumRef>(line).ok())
+    {
+        references.insert(reference.get_spectra_id().to_string(), reference);
+    }
+    let references = references.into_values().collect::<Vec<SpectrumRef>>();
+    // the log is only removed after the manifest is written, replaying it again is harmless
+    write_json(&manifest_path, &references)?;
+    fs::remove_file(&log_path)?;
+    Ok(references)
+}
+
+fn column_values(dataframe: &DataFrame, column: &str) -> Result<Vec<f64>> {
+    Ok(dataframe
+        .column(column)?
+        .f64()?
+        .into_iter()
+        .map(|value| value.unwrap_or(f64::NAN))
+        .collect())
+}
+
+/// Writes to a temporary file next to the target and renames it afterwards
+///
+fn write_atomic(path: &Path, write: impl FnOnce(&mut File) -> Result<()>) -> Result<()> {
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+    let mut file = File::create(&tmp_path)?;
+    if let Err(err) = write(&mut file).and_then(|_| Ok(file.sync_all()?)) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(err.context(format!("writing {}", path.display())));
+    }
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    write_atomic(path, |file| {
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, value)?;
+        writer.flush()?;
+        Ok(())
+    })
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let file = File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("parsing {}", path.display()))
+}
+
+fn read_json_or_default<T: DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    match File::open(path) {
+        Ok(file) => serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("parsing {}", path.display())),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(T::default()),
+        Err(err) => Err(err.into()),
+    }
+}
@@ -0,0 +1,202 @@
+// std imports
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+// internal imports
+use crate::results_api::{Identification, MsRun, Search, Spectrum};
+use crate::storage::store::{ResultStore, StoreError};
+
+struct MsRunEntry {
+    ms_run: MsRun,
+    spectra: HashMap<String, Spectrum>,
+}
+
+struct SearchEntry {
+    search: Search,
+    ms_runs: BTreeMap<String, MsRunEntry>,
+}
+
+/// Result store keeping everything in memory, e.g. for tests or small CLI runs.
+/// Entities are cloned on access, which is cheap for the peaks of spectra.
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::{Identification, MsRun, Precursor, Search, Spectrum};
+/// use maccoys_exchange_entities::storage::{MemoryStore, ResultStore};
+///
+/// let store = MemoryStore::new();
+/// let search_uuid = "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c";
+/// store.put_search(Search::new(search_uuid.parse().unwrap(), vec!["run".parse().unwrap()])).unwrap();
+/// store.put_ms_run(MsRun::new(search_uuid.parse().unwrap(), "run".parse().unwrap(), vec!["scan=1".parse().unwrap()])).unwrap();
+/// store.put_spectrum(Spectrum::new(
+///     search_uuid.parse().unwrap(),
+///     "run".parse().unwrap(),
+///     "scan=1".parse().unwrap(),
+///     vec![100.0, 200.0],
+///     vec![1.0, 2.0],
+///     Vec::new(),
+/// )).unwrap();
+/// store.put_identification(
+///     search_uuid,
+///     "run",
+///     "scan=1",
+///     Identification::new(None, None, Precursor::new(400.7, 2)),
+/// ).unwrap();
+///
+/// let spectrum = store.get_spectrum(search_uuid, "run", "scan=1").unwrap();
+/// assert_eq!(spectrum.get_identifications().len(), 1);
+/// assert!(store.get_spectrum(search_uuid, "run", "scan=2").is_err());
+/// ```
+///
+#[derive(Default)]
+pub struct MemoryStore {
+    searches: RwLock<HashMap<String, SearchEntry>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A panic while holding the lock cannot leave an entry half-written, so poisoning is ignored
+    fn read(&self) -> RwLockReadGuard<'_, HashMap<String, SearchEntry>> {
+        self.searches.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, HashMap<String, SearchEntry>> {
+        self.searches
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl ResultStore for MemoryStore {
+    fn get_search(&self, search_uuid: &str) -> Result<Search, StoreError> {
+        self.read()
+            .get(search_uuid)
+            .map(|entry| entry.search.clone())
+            .ok_or_else(|| StoreError::SearchNotFound(search_uuid.to_string()))
+    }
+
+    fn list_ms_runs(&self, search_uuid: &str) -> Result<Vec<MsRun>, StoreError> {
+        self.read()
+            .get(search_uuid)
+            .map(|entry| {
+                entry
+                    .ms_runs
+                    .values()
+                    .map(|ms_run| ms_run.ms_run.clone())
+                    .collect()
+            })
+            .ok_or_else(|| StoreError::SearchNotFound(search_uuid.to_string()))
+    }
+
+    fn get_spectrum(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+        spectrum_id: &str,
+    ) -> Result<Spectrum, StoreError> {
+        let searches = self.read();
+        let ms_run = searches
+            .get(search_uuid)
+            .ok_or_else(|| StoreError::SearchNotFound(search_uuid.to_string()))?
+            .ms_runs
+            .get(ms_run_name)
+            .ok_or_else(|| {
+                StoreError::MsRunNotFound(search_uuid.to_string(), ms_run_name.to_string())
+            })?;
+        ms_run.spectra.get(spectrum_id).cloned().ok_or_else(|| {
+            StoreError::SpectrumNotFound(
+                search_uuid.to_string(),
+                ms_run_name.to_string(),
+                spectrum_id.to_string(),
+            )
+        })
+    }
+
+    fn put_search(&self, search: Search) -> Result<(), StoreError> {
+        let mut searches = self.write();
+        match searches.get_mut(search.get_search_uuid().as_str()) {
+            Some(entry) => entry.search = search,
+            None => {
+                searches.insert(
+                    search.get_search_uuid().to_string(),
+                    SearchEntry {
+                        search,
+                        ms_runs: BTreeMap::new(),
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn put_ms_run(&self, ms_run: MsRun) -> Result<(), StoreError> {
+        let mut searches = self.write();
+        let search = searches
+            .get_mut(ms_run.get_search_uuid().as_str())
+            .ok_or_else(|| StoreError::SearchNotFound(ms_run.get_search_uuid().to_string()))?;
+        match search.ms_runs.get_mut(ms_run.get_ms_run().as_str()) {
+            Some(entry) => entry.ms_run = ms_run,
+            None => {
+                search.ms_runs.insert(
+                    ms_run.get_ms_run().to_string(),
+                    MsRunEntry {
+                        ms_run,
+                        spectra: HashMap::new(),
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn put_spectrum(&self, spectrum: Spectrum) -> Result<(), StoreError> {
+        let mut searches = self.write();
+        let ms_run = searches
+            .get_mut(spectrum.get_search_uuid().as_str())
+            .ok_or_else(|| StoreError::SearchNotFound(spectrum.get_search_uuid().to_string()))?
+            .ms_runs
+            .get_mut(spectrum.get_ms_run().as_str())
+            .ok_or_else(|| {
+                StoreError::MsRunNotFound(
+                    spectrum.get_search_uuid().to_string(),
+                    spectrum.get_ms_run().to_string(),
+                )
+            })?;
+        ms_run
+            .spectra
+            .insert(spectrum.get_spectra_id().to_string(), spectrum);
+        Ok(())
+    }
+
+    fn put_identification(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+        spectrum_id: &str,
+        identification: Identification,
+    ) -> Result<(), StoreError> {
+        let mut searches = self.write();
+        let ms_run = searches
+            .get_mut(search_uuid)
+            .ok_or_else(|| StoreError::SearchNotFound(search_uuid.to_string()))?
+            .ms_runs
+            .get_mut(ms_run_name)
+            .ok_or_else(|| {
+                StoreError::MsRunNotFound(search_uuid.to_string(), ms_run_name.to_string())
+            })?;
+        ms_run
+            .spectra
+            .get_mut(spectrum_id)
+            .ok_or_else(|| {
+                StoreError::SpectrumNotFound(
+                    search_uuid.to_string(),
+                    ms_run_name.to_string(),
+                    spectrum_id.to_string(),
+                )
+            })?
+            .put_identification(identification);
+        Ok(())
+    }
+}
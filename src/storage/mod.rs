@@ -0,0 +1,37 @@
+/// Asynchronous result stores
+#[cfg(feature = "async")]
+pub mod async_store;
+
+/// Result store on a plain directory
+#[cfg(feature = "parquet")]
+pub mod fs;
+
+/// In-memory result store
+pub mod memory;
+
+/// Result store on object stores like S3
+#[cfg(feature = "object_store")]
+pub mod object;
+
+/// Partitioned Parquet directory layout for whole searches
+#[cfg(feature = "parquet")]
+pub mod parquet;
+
+/// Result store in a single SQLite file
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+/// Storage abstraction for search results
+pub mod store;
+
+//rexports
+#[cfg(feature = "async")]
+pub use async_store::AsyncResultStore;
+#[cfg(feature = "parquet")]
+pub use fs::FsStore;
+pub use memory::MemoryStore;
+#[cfg(feature = "object_store")]
+pub use object::ObjectResultStore;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+pub use store::{ResultStore, StoreError};
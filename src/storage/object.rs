@@ -0,0 +1,336 @@
+//! Result store on an object store (S3, MinIO, GCS, Azure or local), for cloud deployments of the pipeline.
+//! Payloads are stored as MessagePack (see `WireFormat`):
+//!
+//! ```text
+//! <prefix>/<search_uuid>/
+//!     search.msgpack
+//!     <ms_run>/
+//!         ms_run.msgpack
+//!         spectra/<spectrum_id>.msgpack
+//! ```
+//!
+//! Large spectra are uploaded in parts and all payloads are downloaded as stream of chunks.
+//! The cloud backends are enabled by the features of the `object_store` crate, e.g. `object_store/aws`.
+
+// std imports
+use std::sync::Arc;
+
+// 3rd party imports
+use futures::TryStreamExt;
+use object_store::{path::Path, ObjectStore, WriteMultipart};
+
+// internal imports
+use crate::results_api::{Identification, MsRun, Search, Spectrum};
+use crate::serialization::WireFormat;
+use crate::storage::store::StoreError;
+
+const SEARCH_FILE: &str = "search.msgpack";
+const MS_RUN_FILE: &str = "ms_run.msgpack";
+const SPECTRA_DIR: &str = "spectra";
+
+/// Default size from which payloads are uploaded in parts, which is also the minimum part size of S3
+pub const DEFAULT_MULTIPART_THRESHOLD: usize = 5 * 1024 * 1024;
+/// Maximum number of parts uploaded concurrently
+const MAX_CONCURRENT_PARTS: usize = 8;
+
+/// Asynchronous result store on an object store, mirroring the methods of `ResultStore`
+///
+/// ```
+/// use std::sync::Arc;
+/// use maccoys_exchange_entities::results_api::{MsRun, Search, Spectrum};
+/// use maccoys_exchange_entities::storage::ObjectResultStore;
+/// use object_store::{memory::InMemory, path::Path};
+///
+/// tokio::runtime::Runtime::new().unwrap().block_on(async {
+///     let store = ObjectResultStore::new(Arc::new(InMemory::new()), Path::from("results"));
+///     let search_uuid = "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c";
+///     store.put_search(&Search::new(search_uuid.parse().unwrap(), vec!["run".parse().unwrap()])).await.unwrap();
+///     store.put_ms_run(&MsRun::new(search_uuid.parse().unwrap(), "run".parse().unwrap(), vec!["scan=1".parse().unwrap()])).await.unwrap();
+///     store.put_spectrum(&Spectrum::new(
+///         search_uuid.parse().unwrap(),
+///         "run".parse().unwrap(),
+///         "scan=1".parse().unwrap(),
+///         vec![100.0, 200.0],
+///         vec![1.0, 2.0],
+///         Vec::new(),
+///     )).await.unwrap();
+///
+///     let spectrum = store.get_spectrum(search_uuid, "run", "scan=1").await.unwrap();
+///     assert_eq!(spectrum.get_mz(), &[100.0, 200.0]);
+///     assert_eq!(store.list_ms_runs(search_uuid).await.unwrap().len(), 1);
+///     assert!(store.get_search("unknown").await.is_err());
+/// });
+/// ```
+///
+pub struct ObjectResultStore {
+    store: Arc<dyn ObjectStore>,
+    prefix: Path,
+    multipart_threshold: usize,
+}
+
+impl ObjectResultStore {
+    /// # Arguments
+    /// * `store` - Object store, e.g. `AmazonS3Builder::from_env().build()`
+    /// * `prefix` - Prefix of all keys within the store
+    ///
+    pub fn new(store: Arc<dyn ObjectStore>, prefix: Path) -> Self {
+        Self {
+            store,
+            prefix,
+            multipart_threshold: DEFAULT_MULTIPART_THRESHOLD,
+        }
+    }
+
+    /// Sets the payload size in bytes from which uploads are split into parts
+    ///
+    pub fn with_multipart_threshold(mut self, multipart_threshold: usize) -> Self {
+        self.multipart_threshold = multipart_threshold;
+        self
+    }
+
+    pub fn get_prefix(&self) -> &Path {
+        &self.prefix
+    }
+
+    fn search_path(&self, search_uuid: &str) -> Path {
+        self.prefix.child(search_uuid)
+    }
+
+    fn ms_run_path(&self, search_uuid: &str, ms_run_name: &str) -> Path {
+        self.search_path(search_uuid).child(ms_run_name)
+    }
+
+    fn spectrum_path(&self, search_uuid: &str, ms_run_name: &str, spectrum_id: &str) -> Path {
+        self.ms_run_path(search_uuid, ms_run_name)
+            .child(SPECTRA_DIR)
+            .child(format!("{}.msgpack", spectrum_id))
+    }
+
+    /// Downloads and decodes the payload, None if the object does not exist
+    ///
+    async fn read<T: WireFormat>(&self, location: &Path) -> Result<Option<T>, StoreError> {
+        let payload = match self.store.get(location).await {
+            Ok(payload) => payload,
+            Err(object_store::Error::NotFound { .. }) => return Ok(None),
+            Err(err) => return Err(anyhow::Error::from(err).into()),
+        };
+        let mut buffer = Vec::with_capacity(payload.meta.size);
+        let mut chunks = payload.into_stream();
+        while let Some(chunk) = chunks.try_next().await.map_err(anyhow::Error::from)? {
+            buffer.extend_from_slice(&chunk);
+        }
+        Ok(Some(T::from_msgpack(&buffer)?))
+    }
+
+    /// Encodes and uploads the payload, in parts if it exceeds the multipart threshold
+    ///
+    async fn write<T: WireFormat>(&self, location: &Path, entity: &T) -> Result<(), StoreError> {
+        let payload = entity.to_msgpack()?;
+        if payload.len() < self.multipart_threshold {
+            self.store
+                .put(location, payload.into())
+                .await
+                .map_err(anyhow::Error::from)?;
+            return Ok(());
+        }
+        let upload = self
+            .store
+            .put_multipart(location)
+            .await
+            .map_err(anyhow::Error::from)?;
+        let mut writer = WriteMultipart::new_with_chunk_size(upload, self.multipart_threshold);
+        for chunk in payload.chunks(self.multipart_threshold) {
+            if let Err(err) = writer.wait_for_capacity(MAX_CONCURRENT_PARTS).await {
+                writer.abort().await.map_err(anyhow::Error::from)?;
+                return Err(anyhow::Error::from(err).into());
+            }
+            writer.write(chunk);
+        }
+        writer.finish().await.map_err(anyhow::Error::from)?;
+        Ok(())
+    }
+
+    async fn exists(&self, location: &Path) -> Result<bool, StoreError> {
+        match self.store.head(location).await {
+            Ok(_) => Ok(true),
+            Err(object_store::Error::NotFound { .. }) => Ok(false),
+            Err(err) => Err(anyhow::Error::from(err).into()),
+        }
+    }
+
+    pub async fn get_search(&self, search_uuid: &str) -> Result<Search, StoreError> {
+        self.read(&self.search_path(search_uuid).child(SEARCH_FILE))
+            .await?
+            .ok_or_else(|| StoreError::SearchNotFound(search_uuid.to_string()))
+    }
+
+    /// Returns the MS runs of the given search, ordered by name
+    ///
+    pub async fn list_ms_runs(&self, search_uuid: &str) -> Result<Vec<MsRun>, StoreError> {
+        let search_path = self.search_path(search_uuid);
+        if !self.exists(&search_path.child(SEARCH_FILE)).await? {
+            return Err(StoreError::SearchNotFound(search_uuid.to_string()));
+        }
+        let listing = self
+            .store
+            .list_with_delimiter(Some(&search_path))
+            .await
+            .map_err(anyhow::Error::from)?;
+        let mut ms_runs = Vec::with_capacity(listing.common_prefixes.len());
+        for ms_run_path in listing.common_prefixes {
+            if let Some(ms_run) = self.read::<MsRun>(&ms_run_path.child(MS_RUN_FILE)).await? {
+                ms_runs.push(ms_run);
+            }
+        }
+        ms_runs.sort_by(|a, b| a.get_ms_run().cmp(b.get_ms_run()));
+        Ok(ms_runs)
+    }
+
+    pub async fn get_ms_run(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+    ) -> Result<MsRun, StoreError> {
+        self.read(
+            &self
+                .ms_run_path(search_uuid, ms_run_name)
+                .child(MS_RUN_FILE),
+        )
+        .await?
+        .ok_or_else(|| StoreError::MsRunNotFound(search_uuid.to_string(), ms_run_name.to_string()))
+    }
+
+    pub async fn get_spectrum(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+        spectrum_id: &str,
+    ) -> Result<Spectrum, StoreError> {
+        self.read(&self.spectrum_path(search_uuid, ms_run_name, spectrum_id))
+            .await?
+            .ok_or_else(|| {
+                StoreError::SpectrumNotFound(
+                    search_uuid.to_string(),
+                    ms_run_name.to_string(),
+                    spectrum_id.to_string(),
+                )
+            })
+    }
+
+    /// Adds or replaces the search
+    ///
+    pub async fn put_search(&self, search: &Search) -> Result<(), StoreError> {
+        self.write(
+            &self
+                .search_path(search.get_search_uuid())
+                .child(SEARCH_FILE),
+            search,
+        )
+        .await
+    }
+
+    /// Adds or replaces the MS run, the search needs to exist
+    ///
+    pub async fn put_ms_run(&self, ms_run: &MsRun) -> Result<(), StoreError> {
+        let search_path = self.search_path(ms_run.get_search_uuid());
+        if !self.exists(&search_path.child(SEARCH_FILE)).await? {
+            return Err(StoreError::SearchNotFound(
+                ms_run.get_search_uuid().to_string(),
+            ));
+        }
+        self.write(
+            &search_path
+                .child(ms_run.get_ms_run().as_str())
+                .child(MS_RUN_FILE),
+            ms_run,
+        )
+        .await
+    }
+
+    /// Adds or replaces the spectrum, the MS run needs to exist
+    ///
+    pub async fn put_spectrum(&self, spectrum: &Spectrum) -> Result<(), StoreError> {
+        let ms_run_path = self.ms_run_path(spectrum.get_search_uuid(), spectrum.get_ms_run());
+        if !self.exists(&ms_run_path.child(MS_RUN_FILE)).await? {
+            return Err(StoreError::MsRunNotFound(
+                spectrum.get_search_uuid().to_string(),
+                spectrum.get_ms_run().to_string(),
+            ));
+        }
+        self.write(
+            &self.spectrum_path(
+                spectrum.get_search_uuid(),
+                spectrum.get_ms_run(),
+                spectrum.get_spectra_id(),
+            ),
+            spectrum,
+        )
+        .await
+    }
+
+    /// Adds the identification to the given spectrum or replaces the identification of the same precursor.
+    /// Object stores do not support transactions, so concurrent updates of the same spectrum may get lost.
+    ///
+    pub async fn put_identification(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+        spectrum_id: &str,
+        identification: Identification,
+    ) -> Result<(), StoreError> {
+        let mut spectrum = self
+            .get_spectrum(search_uuid, ms_run_name, spectrum_id)
+            .await?;
+        spectrum.put_identification(identification);
+        self.put_spectrum(&spectrum).await
+    }
+}
+
+#[cfg(feature = "async")]
+impl crate::storage::async_store::AsyncResultStore for ObjectResultStore {
+    async fn get_search(&self, search_uuid: &str) -> Result<Search, StoreError> {
+        ObjectResultStore::get_search(self, search_uuid).await
+    }
+
+    async fn list_ms_runs(&self, search_uuid: &str) -> Result<Vec<MsRun>, StoreError> {
+        ObjectResultStore::list_ms_runs(self, search_uuid).await
+    }
+
+    async fn get_spectrum(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+        spectrum_id: &str,
+    ) -> Result<Spectrum, StoreError> {
+        ObjectResultStore::get_spectrum(self, search_uuid, ms_run_name, spectrum_id).await
+    }
+
+    async fn put_search(&self, search: Search) -> Result<(), StoreError> {
+        ObjectResultStore::put_search(self, &search).await
+    }
+
+    async fn put_ms_run(&self, ms_run: MsRun) -> Result<(), StoreError> {
+        ObjectResultStore::put_ms_run(self, &ms_run).await
+    }
+
+    async fn put_spectrum(&self, spectrum: Spectrum) -> Result<(), StoreError> {
+        ObjectResultStore::put_spectrum(self, &spectrum).await
+    }
+
+    async fn put_identification(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+        spectrum_id: &str,
+        identification: Identification,
+    ) -> Result<(), StoreError> {
+        ObjectResultStore::put_identification(
+            self,
+            search_uuid,
+            ms_run_name,
+            spectrum_id,
+            identification,
+        )
+        .await
+    }
+}
@@ -0,0 +1,474 @@
+//! Persists a search with its MS runs, spectra and identifications to a Hive style partitioned directory:
+//!
+//! ```text
+//! <root>/
+//!     search.json
+//!     ms_run=<name>/
+//!         ms_run.json
+//!         spectra.parquet            spectrum_id, retention_time, ion_mobility, ms_level, scan_number, payload_digest,
+//!                                    sequence_tags (JSON), revision
+//!         peaks.parquet              spectrum_id, mz, intensity (one row per peak)
+//!         identifications.parquet    spectrum_id, identification_index, precursor, charge, precursor_intensity,
+//!                                    isolation_window_lower_offset, isolation_window_upper_offset, monoisotopic_correction
+//!         charge=<charge>/
+//!             psms.parquet           spectrum_id, identification_index, <PSM columns>
+//!             goodnesses.parquet     spectrum_id, identification_index, <goodness columns>
+//! ```
+
+// std imports
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+// 3rd party imports
+use anyhow::{bail, Context, Result};
+use polars::prelude::*;
+
+// internal imports
+use crate::results_api::{
+    Identification, MsRun, MsRunName, Precursor, Search, SearchUuid, SequenceTag, Spectrum,
+    SpectrumId,
+};
+
+/// Column with the spectrum ID in all tables
+pub const SPECTRUM_ID_COL: &str = "spectrum_id";
+/// Column with the index of the identification within the spectrum
+pub const IDENTIFICATION_INDEX_COL: &str = "identification_index";
+
+// columns with the acquisition metadata in the spectra table
+const RETENTION_TIME_COL: &str = "retention_time";
+const ION_MOBILITY_COL: &str = "ion_mobility";
+const MS_LEVEL_COL: &str = "ms_level";
+const SCAN_NUMBER_COL: &str = "scan_number";
+const PAYLOAD_DIGEST_COL: &str = "payload_digest";
+const SEQUENCE_TAGS_COL: &str = "sequence_tags";
+const REVISION_COL: &str = "revision";
+
+// columns with the precursor details in the identifications table
+const PRECURSOR_INTENSITY_COL: &str = "precursor_intensity";
+const ISOLATION_WINDOW_LOWER_OFFSET_COL: &str = "isolation_window_lower_offset";
+const ISOLATION_WINDOW_UPPER_OFFSET_COL: &str = "isolation_window_upper_offset";
+const MONOISOTOPIC_CORRECTION_COL: &str = "monoisotopic_correction";
+
+const SEARCH_FILE: &str = "search.json";
+const MS_RUN_FILE: &str = "ms_run.json";
+const SPECTRA_FILE: &str = "spectra.parquet";
+const PEAKS_FILE: &str = "peaks.parquet";
+const IDENTIFICATIONS_FILE: &str = "identifications.parquet";
+const PSMS_FILE: &str = "psms.parquet";
+const GOODNESSES_FILE: &str = "goodnesses.parquet";
+
+/// Writes the search hierarchy to the given directory, which is created if necessary.
+/// PSM and goodness tables within a partition (MS run & charge) need to have the same schema.
+///
+/// # Arguments
+/// * `path` - Root directory
+/// * `search` - Search
+/// * `ms_runs` - MS runs of the search
+/// * `spectra` - Spectra of the MS runs
+///
+pub fn write(path: &Path, search: &Search, ms_runs: &[MsRun], spectra: &[Spectrum]) -> Result<()> {
+    fs::create_dir_all(path)?;
+    serde_json::to_writer(File::create(path.join(SEARCH_FILE))?, search)?;
+
+    for ms_run in ms_runs {
+        let ms_run_path = ms_run_path(path, ms_run.get_ms_run())?;
+        fs::create_dir_all(&ms_run_path)?;
+        serde_json::to_writer(File::create(ms_run_path.join(MS_RUN_FILE))?, ms_run)?;
+
+        let ms_run_spectra = spectra
+            .iter()
+            .filter(|spectrum| spectrum.get_ms_run() == ms_run.get_ms_run())
+            .collect::<Vec<&Spectrum>>();
+        write_ms_run_spectra(&ms_run_path, &ms_run_spectra)?;
+    }
+    Ok(())
+}
+
+/// Reads the search hierarchy from the given directory
+///
+pub fn read(path: &Path) -> Result<(Search, Vec<MsRun>, Vec<Spectrum>)> {
+    let search: Search = serde_json::from_reader(File::open(path.join(SEARCH_FILE))?)?;
+    let mut ms_runs = Vec::with_capacity(search.get_ms_run_names().len());
+    let mut spectra = Vec::new();
+    for ms_run_name in search.get_ms_run_names() {
+        let ms_run_path = ms_run_path(path, ms_run_name)?;
+        let ms_run: MsRun = serde_json::from_reader(
+            File::open(ms_run_path.join(MS_RUN_FILE))
+                .with_context(|| format!("MS run `{}` not found", ms_run_name))?,
+        )?;
+        spectra.extend(read_ms_run_spectra(
+            &ms_run_path,
+            search.get_search_uuid(),
+            ms_run_name,
+        )?);
+        ms_runs.push(ms_run);
+    }
+    Ok((search, ms_runs, spectra))
+}
+
+fn ms_run_path(path: &Path, ms_run_name: &str) -> Result<PathBuf> {
+    if ms_run_name.contains(['/', '\\']) || ms_run_name == ".." {
+        bail!("MS run name `{}` is not usable as directory", ms_run_name);
+    }
+    Ok(path.join(format!("ms_run={}", ms_run_name)))
+}
+
+fn write_parquet(path: &Path, dataframe: &mut DataFrame) -> Result<()> {
+    ParquetWriter::new(File::create(path)?).finish(dataframe)?;
+    Ok(())
+}
+
+fn read_parquet(path: &Path) -> Result<DataFrame> {
+    Ok(ParquetReader::new(File::open(path)?).finish()?)
+}
+
+/// Prepends the spectrum ID and identification index columns to the given table
+///
+fn with_keys(
+    dataframe: &DataFrame,
+    spectrum_id: &str,
+    identification_index: u32,
+) -> Result<DataFrame> {
+    for key in [SPECTRUM_ID_COL, IDENTIFICATION_INDEX_COL] {
+        if dataframe.get_column_names().contains(&key) {
+            bail!(
+                "table of spectrum `{}` already contains column `{}`",
+                spectrum_id,
+                key
+            );
+        }
+    }
+    let height = dataframe.height();
+    let mut columns = vec![
+        Series::new(SPECTRUM_ID_COL, vec![spectrum_id; height]),
+        Series::new(IDENTIFICATION_INDEX_COL, vec![identification_index; height]),
+    ];
+    columns.extend(dataframe.get_columns().iter().cloned());
+    Ok(DataFrame::new(columns)?)
+}
+
+/// Stacks the tables, failing with a descriptive error on schema mismatches
+///
+fn stack(tables: Vec<DataFrame>, partition: &Path) -> Result<Option<DataFrame>> {
+    let mut tables = tables.into_iter();
+    let mut stacked = match tables.next() {
+        Some(table) => table,
+        None => return Ok(None),
+    };
+    for table in tables {
+        stacked
+            .vstack_mut(&table)
+            .with_context(|| format!("schema mismatch in partition {}", partition.display()))?;
+    }
+    Ok(Some(stacked))
+}
+
+fn write_ms_run_spectra(ms_run_path: &Path, spectra: &[&Spectrum]) -> Result<()> {
+    let mut spectrum_ids: Vec<&str> = Vec::with_capacity(spectra.len());
+    let mut retention_times: Vec<Option<f64>> = Vec::with_capacity(spectra.len());
+    let mut ion_mobilities: Vec<Option<f64>> = Vec::with_capacity(spectra.len());
+    let mut ms_levels: Vec<Option<u32>> = Vec::with_capacity(spectra.len());
+    let mut scan_numbers: Vec<Option<u32>> = Vec::with_capacity(spectra.len());
+    let mut payload_digests: Vec<Option<&str>> = Vec::with_capacity(spectra.len());
+    let mut sequence_tags: Vec<Option<String>> = Vec::with_capacity(spectra.len());
+    let mut revisions: Vec<u64> = Vec::with_capacity(spectra.len());
+    let mut peak_spectrum_ids: Vec<&str> = Vec::new();
+    let mut mz: Vec<f64> = Vec::new();
+    let mut intensity: Vec<f64> = Vec::new();
+    let mut ident_spectrum_ids: Vec<&str> = Vec::new();
+    let mut ident_indexes: Vec<u32> = Vec::new();
+    let mut ident_precursors: Vec<f64> = Vec::new();
+    let mut ident_charges: Vec<u32> = Vec::new();
+    let mut ident_intensities: Vec<Option<f64>> = Vec::new();
+    let mut ident_lower_offsets: Vec<Option<f64>> = Vec::new();
+    let mut ident_upper_offsets: Vec<Option<f64>> = Vec::new();
+    let mut ident_corrections: Vec<Option<i32>> = Vec::new();
+    // charge => (psms, goodnesses)
+    let mut partitions: BTreeMap<u8, (Vec<DataFrame>, Vec<DataFrame>)> = BTreeMap::new();
+
+    for spectrum in spectra {
+        spectrum_ids.push(spectrum.get_spectra_id());
+        retention_times.push(*spectrum.get_retention_time());
+        ion_mobilities.push(*spectrum.get_ion_mobility());
+        ms_levels.push(spectrum.get_ms_level().map(u32::from));
+        scan_numbers.push(*spectrum.get_scan_number());
+        payload_digests.push(spectrum.get_payload_digest().as_deref());
+        sequence_tags.push(match spectrum.get_sequence_tags().is_empty() {
+            true => None,
+            false => Some(serde_json::to_string(spectrum.get_sequence_tags())?),
+        });
+        revisions.push(spectrum.get_revision());
+        peak_spectrum_ids.extend(std::iter::repeat_n(
+            spectrum.get_spectra_id().as_str(),
+            spectrum.get_mz().len(),
+        ));
+        mz.extend_from_slice(spectrum.get_mz());
+        intensity.extend_from_slice(spectrum.get_intensity());
+        for (idx, identification) in spectrum.get_identifications().iter().enumerate() {
+            let idx = idx as u32;
+            ident_spectrum_ids.push(spectrum.get_spectra_id());
+            ident_indexes.push(idx);
+            let precursor = identification.get_precursor();
+            ident_precursors.push(precursor.get_mz());
+            ident_charges.push(precursor.get_charge() as u32);
+            ident_intensities.push(*precursor.get_intensity());
+            ident_lower_offsets.push(*precursor.get_isolation_window_lower_offset());
+            ident_upper_offsets.push(*precursor.get_isolation_window_upper_offset());
+            ident_corrections.push(precursor.get_monoisotopic_correction().map(i32::from));
+            let partition = partitions.entry(identification.get_charge()).or_default();
+            if let Some(psms) = identification.get_psms() {
+                partition
+                    .0
+                    .push(with_keys(psms, spectrum.get_spectra_id(), idx)?);
+            }
+            if let Some(goodnesses) = identification.get_goodnesses() {
+                partition
+                    .1
+                    .push(with_keys(goodnesses, spectrum.get_spectra_id(), idx)?);
+            }
+        }
+    }
+
+    write_parquet(
+        &ms_run_path.join(SPECTRA_FILE),
+        &mut DataFrame::new(vec![
+            Series::new(SPECTRUM_ID_COL, spectrum_ids),
+            Series::new(RETENTION_TIME_COL, retention_times),
+            Series::new(ION_MOBILITY_COL, ion_mobilities),
+            Series::new(MS_LEVEL_COL, ms_levels),
+            Series::new(SCAN_NUMBER_COL, scan_numbers),
+            Series::new(PAYLOAD_DIGEST_COL, payload_digests),
+            Series::new(SEQUENCE_TAGS_COL, sequence_tags),
+            Series::new(REVISION_COL, revisions),
+        ])?,
+    )?;
+    write_parquet(
+        &ms_run_path.join(PEAKS_FILE),
+        &mut DataFrame::new(vec![
+            Series::new(SPECTRUM_ID_COL, peak_spectrum_ids),
+            Series::new("mz", mz),
+            Series::new("intensity", intensity),
+        ])?,
+    )?;
+    write_parquet(
+        &ms_run_path.join(IDENTIFICATIONS_FILE),
+        &mut DataFrame::new(vec![
+            Series::new(SPECTRUM_ID_COL, ident_spectrum_ids),
+            Series::new(IDENTIFICATION_INDEX_COL, ident_indexes),
+            Series::new("precursor", ident_precursors),
+            Series::new("charge", ident_charges),
+            Series::new(PRECURSOR_INTENSITY_COL, ident_intensities),
+            Series::new(ISOLATION_WINDOW_LOWER_OFFSET_COL, ident_lower_offsets),
+            Series::new(ISOLATION_WINDOW_UPPER_OFFSET_COL, ident_upper_offsets),
+            Series::new(MONOISOTOPIC_CORRECTION_COL, ident_corrections),
+        ])?,
+    )?;
+
+    for (charge, (psms, goodnesses)) in partitions.into_iter() {
+        if psms.is_empty() && goodnesses.is_empty() {
+            continue;
+        }
+        let partition_path = ms_run_path.join(format!("charge={}", charge));
+        fs::create_dir_all(&partition_path)?;
+        if let Some(mut psms) = stack(psms, &partition_path)? {
+            write_parquet(&partition_path.join(PSMS_FILE), &mut psms)?;
+        }
+        if let Some(mut goodnesses) = stack(goodnesses, &partition_path)? {
+            write_parquet(&partition_path.join(GOODNESSES_FILE), &mut goodnesses)?;
+        }
+    }
+    Ok(())
+}
+
+/// Splits a table sorted by spectrum ID and identification index into the tables of the single identifications
+///
+fn split_by_keys(dataframe: DataFrame) -> Result<BTreeMap<(String, u32), DataFrame>> {
+    let mut tables = BTreeMap::new();
+    let spectrum_ids = dataframe.column(SPECTRUM_ID_COL)?.utf8()?.clone();
+    let indexes = dataframe.column(IDENTIFICATION_INDEX_COL)?.u32()?.clone();
+    let values = dataframe.drop_many(&[SPECTRUM_ID_COL, IDENTIFICATION_INDEX_COL]);
+
+    let mut start = 0;
+    let keys = spectrum_ids
+        .into_iter()
+        .zip(&indexes)
+        .map(|(spectrum_id, idx)| {
+            (
+                spectrum_id.unwrap_or_default().to_string(),
+                idx.unwrap_or_default(),
+            )
+        })
+        .collect::<Vec<(String, u32)>>();
+    for pos in 0..keys.len() {
+        if pos + 1 == keys.len() || keys[pos] != keys[pos + 1] {
+            tables.insert(
+                keys[pos].clone(),
+                values.slice(start as i64, pos + 1 - start),
+            );
+            start = pos + 1;
+        }
+    }
+    Ok(tables)
+}
+
+/// Returns the column cast to the given type or a null column if it does not exist
+///
+fn optional_column(dataframe: &DataFrame, name: &str, dtype: &DataType) -> Result<Series> {
+    match dataframe.column(name) {
+        Ok(column) => Ok(column.cast(dtype)?),
+        Err(_) => Ok(Series::full_null(name, dataframe.height(), dtype)),
+    }
+}
+
+fn read_ms_run_spectra(
+    ms_run_path: &Path,
+    search_uuid: &SearchUuid,
+    ms_run_name: &MsRunName,
+) -> Result<Vec<Spectrum>> {
+    let spectra = read_parquet(&ms_run_path.join(SPECTRA_FILE))?;
+    let peaks = read_parquet(&ms_run_path.join(PEAKS_FILE))?;
+    let identifications = read_parquet(&ms_run_path.join(IDENTIFICATIONS_FILE))?;
+
+    // spectrum ID => (mz, intensity)
+    let mut spectrum_peaks: BTreeMap<&str, (Vec<f64>, Vec<f64>)> = BTreeMap::new();
+    let peak_spectrum_ids = peaks.column(SPECTRUM_ID_COL)?.utf8()?;
+    let mz = peaks.column("mz")?.f64()?;
+    let intensity = peaks.column("intensity")?.f64()?;
+    for ((spectrum_id, mz), intensity) in peak_spectrum_ids.into_iter().zip(mz).zip(intensity) {
+        let entry = spectrum_peaks
+            .entry(spectrum_id.unwrap_or_default())
+            .or_default();
+        entry.0.push(mz.unwrap_or(f64::NAN));
+        entry.1.push(intensity.unwrap_or(f64::NAN));
+    }
+
+    // (spectrum ID, identification index) => (psms, goodnesses)
+    let mut tables: BTreeMap<(String, u32), (Option<DataFrame>, Option<DataFrame>)> =
+        BTreeMap::new();
+    for entry in fs::read_dir(ms_run_path)? {
+        let partition_path = entry?.path();
+        if !partition_path.is_dir() {
+            continue;
+        }
+        let psms_path = partition_path.join(PSMS_FILE);
+        if psms_path.exists() {
+            for (key, psms) in split_by_keys(read_parquet(&psms_path)?)? {
+                tables.entry(key).or_default().0 = Some(psms);
+            }
+        }
+        let goodnesses_path = partition_path.join(GOODNESSES_FILE);
+        if goodnesses_path.exists() {
+            for (key, goodnesses) in split_by_keys(read_parquet(&goodnesses_path)?)? {
+                tables.entry(key).or_default().1 = Some(goodnesses);
+            }
+        }
+    }
+
+    // spectrum ID => identifications
+    let mut spectrum_identifications: BTreeMap<String, Vec<Identification>> = BTreeMap::new();
+    let ident_spectrum_ids = identifications.column(SPECTRUM_ID_COL)?.utf8()?;
+    let ident_indexes = identifications.column(IDENTIFICATION_INDEX_COL)?.u32()?;
+    let ident_precursors = identifications.column("precursor")?.f64()?;
+    let ident_charges = identifications.column("charge")?.u32()?;
+    // precursor details are missing in files written before they were introduced
+    let ident_intensities = optional_column(
+        &identifications,
+        PRECURSOR_INTENSITY_COL,
+        &DataType::Float64,
+    )?;
+    let ident_lower_offsets = optional_column(
+        &identifications,
+        ISOLATION_WINDOW_LOWER_OFFSET_COL,
+        &DataType::Float64,
+    )?;
+    let ident_upper_offsets = optional_column(
+        &identifications,
+        ISOLATION_WINDOW_UPPER_OFFSET_COL,
+        &DataType::Float64,
+    )?;
+    let ident_corrections = optional_column(
+        &identifications,
+        MONOISOTOPIC_CORRECTION_COL,
+        &DataType::Int32,
+    )?;
+    for (row, (((spectrum_id, idx), precursor), charge)) in ident_spectrum_ids
+        .into_iter()
+        .zip(ident_indexes)
+        .zip(ident_precursors)
+        .zip(ident_charges)
+        .enumerate()
+    {
+        let key = (
+            spectrum_id.unwrap_or_default().to_string(),
+            idx.unwrap_or_default(),
+        );
+        let (psms, goodnesses) = tables.remove(&key).unwrap_or_default();
+        let monoisotopic_correction = match ident_corrections.i32()?.get(row) {
+            Some(correction) => Some(i8::try_from(correction)?),
+            None => None,
+        };
+        let precursor = Precursor::new(
+            precursor.unwrap_or(f64::NAN),
+            u8::try_from(charge.unwrap_or_default())?,
+        )
+        .with_intensity(ident_intensities.f64()?.get(row))
+        .with_isolation_window(
+            ident_lower_offsets.f64()?.get(row),
+            ident_upper_offsets.f64()?.get(row),
+        )
+        .with_monoisotopic_correction(monoisotopic_correction);
+        spectrum_identifications
+            .entry(key.0)
+            .or_default()
+            .push(Identification::new(goodnesses, psms, precursor));
+    }
+
+    // metadata columns are missing in files written before they were introduced
+    let retention_times = optional_column(&spectra, RETENTION_TIME_COL, &DataType::Float64)?;
+    let ion_mobilities = optional_column(&spectra, ION_MOBILITY_COL, &DataType::Float64)?;
+    let ms_levels = optional_column(&spectra, MS_LEVEL_COL, &DataType::UInt32)?;
+    let scan_numbers = optional_column(&spectra, SCAN_NUMBER_COL, &DataType::UInt32)?;
+    let payload_digests = optional_column(&spectra, PAYLOAD_DIGEST_COL, &DataType::Utf8)?;
+    let sequence_tags = optional_column(&spectra, SEQUENCE_TAGS_COL, &DataType::Utf8)?;
+    let revisions = optional_column(&spectra, REVISION_COL, &DataType::UInt64)?;
+
+    let mut result = Vec::with_capacity(spectra.height());
+    for (row, spectrum_id) in spectra
+        .column(SPECTRUM_ID_COL)?
+        .utf8()?
+        .into_iter()
+        .enumerate()
+    {
+        let spectrum_id = spectrum_id.unwrap_or_default();
+        let (mz, intensity) = spectrum_peaks.remove(spectrum_id).unwrap_or_default();
+        let ms_level = match ms_levels.u32()?.get(row) {
+            Some(ms_level) => Some(u8::try_from(ms_level)?),
+            None => None,
+        };
+        let spectrum_sequence_tags: Vec<SequenceTag> = match sequence_tags.utf8()?.get(row) {
+            Some(json) => serde_json::from_str(json)?,
+            None => Vec::new(),
+        };
+        result.push(
+            Spectrum::new(
+                search_uuid.clone(),
+                ms_run_name.clone(),
+                SpectrumId::new(spectrum_id)?,
+                mz,
+                intensity,
+                spectrum_identifications
+                    .remove(spectrum_id)
+                    .unwrap_or_default(),
+            )
+            .with_retention_time(retention_times.f64()?.get(row))
+            .with_ion_mobility(ion_mobilities.f64()?.get(row))
+            .with_ms_level(ms_level)
+            .with_scan_number(scan_numbers.u32()?.get(row))
+            .with_sequence_tags(spectrum_sequence_tags)
+            .with_payload_digest(payload_digests.utf8()?.get(row).map(str::to_string))
+            .with_revision(revisions.u64()?.get(row).unwrap_or_default()),
+        );
+    }
+    Ok(result)
+}
@@ -0,0 +1,416 @@
+// std imports
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+// 3rd party imports
+use anyhow::{bail, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+
+// internal imports
+use crate::results_api::{
+    Identification, MsRun, MsRunName, Search, SearchUuid, SequenceTag, Spectrum, SpectrumId,
+};
+use crate::storage::store::{ResultStore, StoreError};
+
+const SCHEMA: &str = "
+    PRAGMA foreign_keys = ON;
+    CREATE TABLE IF NOT EXISTS searches (
+        search_uuid TEXT PRIMARY KEY,
+        payload TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS ms_runs (
+        search_uuid TEXT NOT NULL REFERENCES searches (search_uuid) ON DELETE CASCADE,
+        ms_run_name TEXT NOT NULL,
+        payload TEXT NOT NULL,
+        PRIMARY KEY (search_uuid, ms_run_name)
+    );
+    CREATE TABLE IF NOT EXISTS spectra (
+        search_uuid TEXT NOT NULL,
+        ms_run_name TEXT NOT NULL,
+        spectrum_id TEXT NOT NULL,
+        retention_time REAL,
+        ion_mobility REAL,
+        ms_level INTEGER,
+        scan_number INTEGER,
+        mz BLOB NOT NULL,
+        intensity BLOB NOT NULL,
+        identifications BLOB NOT NULL,
+        payload_digest TEXT,
+        sequence_tags BLOB,
+        revision INTEGER NOT NULL DEFAULT 0,
+        PRIMARY KEY (search_uuid, ms_run_name, spectrum_id),
+        FOREIGN KEY (search_uuid, ms_run_name) REFERENCES ms_runs (search_uuid, ms_run_name) ON DELETE CASCADE
+    );
+";
+
+/// Result store in a single SQLite file, e.g. for small deployments.
+/// Searches and MS runs are stored as JSON, spectrum metadata in columns, peaks as little endian `f64` blobs
+/// and the identifications including their PSM tables as well as the sequence tags as MessagePack blobs.
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::{Identification, MsRun, Precursor, Search, Spectrum};
+/// use maccoys_exchange_entities::storage::{ResultStore, SqliteStore};
+/// use polars::prelude::*;
+///
+/// let store = SqliteStore::in_memory().unwrap();
+/// let search_uuid = "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c";
+/// store.put_search(Search::new(search_uuid.parse().unwrap(), vec!["run".parse().unwrap()])).unwrap();
+/// store.put_ms_run(MsRun::new(search_uuid.parse().unwrap(), "run".parse().unwrap(), vec!["scan=1".parse().unwrap()])).unwrap();
+/// store.put_spectrum(Spectrum::new(
+///     search_uuid.parse().unwrap(),
+///     "run".parse().unwrap(),
+///     "scan=1".parse().unwrap(),
+///     vec![100.0, 200.0],
+///     vec![1.0, 2.0],
+///     Vec::new(),
+/// ).with_scan_number(Some(1))).unwrap();
+/// let psms = df!("plain_peptide" => &["PEPTIDE"], "xcorr" => &[2.5]).unwrap();
+/// store.put_identification(
+///     search_uuid,
+///     "run",
+///     "scan=1",
+///     Identification::new(None, Some(psms.clone()), Precursor::new(400.7, 2)),
+/// ).unwrap();
+///
+/// let spectrum = store.get_spectrum(search_uuid, "run", "scan=1").unwrap();
+/// assert_eq!(spectrum.get_mz(), &[100.0, 200.0]);
+/// assert_eq!(spectrum.get_scan_number(), &Some(1));
+/// assert!(spectrum.get_identifications()[0].get_psms().as_ref().unwrap().frame_equal(&psms));
+/// ```
+///
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Opens the database file, which is created and initialized if necessary
+    ///
+    /// # Arguments
+    /// * `path` - Path to the database file
+    ///
+    pub fn new(path: &Path) -> Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    /// Database which only lives in memory, e.g. for tests
+    ///
+    pub fn in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        // databases created before payload digests were introduced
+        let has_digest_column = connection
+            .prepare("SELECT 1 FROM pragma_table_info('spectra') WHERE name = 'payload_digest'")?
+            .exists([])?;
+        if !has_digest_column {
+            connection.execute("ALTER TABLE spectra ADD COLUMN payload_digest TEXT", [])?;
+        }
+        // databases created before sequence tags were introduced
+        let has_tag_column = connection
+            .prepare("SELECT 1 FROM pragma_table_info('spectra') WHERE name = 'sequence_tags'")?
+            .exists([])?;
+        if !has_tag_column {
+            connection.execute("ALTER TABLE spectra ADD COLUMN sequence_tags BLOB", [])?;
+        }
+        // databases created before revisions were introduced
+        let has_revision_column = connection
+            .prepare("SELECT 1 FROM pragma_table_info('spectra') WHERE name = 'revision'")?
+            .exists([])?;
+        if !has_revision_column {
+            connection.execute(
+                "ALTER TABLE spectra ADD COLUMN revision INTEGER NOT NULL DEFAULT 0",
+                [],
+            )?;
+        }
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    // A panic while holding the lock happens outside of a committed transaction, so poisoning is ignored
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn search_exists(connection: &Connection, search_uuid: &str) -> Result<bool> {
+    Ok(connection
+        .query_row(
+            "SELECT 1 FROM searches WHERE search_uuid = ?1",
+            params![search_uuid],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some())
+}
+
+fn ms_run_exists(connection: &Connection, search_uuid: &str, ms_run_name: &str) -> Result<bool> {
+    Ok(connection
+        .query_row(
+            "SELECT 1 FROM ms_runs WHERE search_uuid = ?1 AND ms_run_name = ?2",
+            params![search_uuid, ms_run_name],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some())
+}
+
+/// Checks the hierarchy above a spectrum, returning the matching not found error
+///
+fn check_ms_run(
+    connection: &Connection,
+    search_uuid: &str,
+    ms_run_name: &str,
+) -> Result<(), StoreError> {
+    if !search_exists(connection, search_uuid)? {
+        return Err(StoreError::SearchNotFound(search_uuid.to_string()));
+    }
+    if !ms_run_exists(connection, search_uuid, ms_run_name)? {
+        return Err(StoreError::MsRunNotFound(
+            search_uuid.to_string(),
+            ms_run_name.to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn to_blob(values: &[f64]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+fn from_blob(blob: &[u8]) -> Result<Vec<f64>> {
+    if !blob.len().is_multiple_of(8) {
+        bail!("peak blob of {} bytes is not a multiple of 8", blob.len());
+    }
+    Ok(blob
+        .chunks_exact(8)
+        .map(|chunk| f64::from_le_bytes(chunk.try_into().unwrap()))
+        .collect())
+}
+
+fn read_spectrum(
+    connection: &Connection,
+    search_uuid: &str,
+    ms_run_name: &str,
+    spectrum_id: &str,
+) -> Result<Spectrum, StoreError> {
+    check_ms_run(connection, search_uuid, ms_run_name)?;
+    let row = connection
+        .query_row(
+            "SELECT retention_time, ion_mobility, ms_level, scan_number, mz, intensity, identifications,
+                payload_digest, sequence_tags, revision
+            FROM spectra WHERE search_uuid = ?1 AND ms_run_name = ?2 AND spectrum_id = ?3",
+            params![search_uuid, ms_run_name, spectrum_id],
+            |row| {
+                Ok((
+                    row.get::<_, Option<f64>>(0)?,
+                    row.get::<_, Option<f64>>(1)?,
+                    row.get::<_, Option<u8>>(2)?,
+                    row.get::<_, Option<u32>>(3)?,
+                    row.get::<_, Vec<u8>>(4)?,
+                    row.get::<_, Vec<u8>>(5)?,
+                    row.get::<_, Vec<u8>>(6)?,
+                    row.get::<_, Option<String>>(7)?,
+                    row.get::<_, Option<Vec<u8>>>(8)?,
+                    row.get::<_, u64>(9)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(anyhow::Error::from)?;
+    let (
+        retention_time,
+        ion_mobility,
+        ms_level,
+        scan_number,
+        mz,
+        intensity,
+        identifications,
+        payload_digest,
+        sequence_tags,
+        revision,
+    ) = row.ok_or_else(|| {
+        StoreError::SpectrumNotFound(
+            search_uuid.to_string(),
+            ms_run_name.to_string(),
+            spectrum_id.to_string(),
+        )
+    })?;
+    let identifications: Vec<Identification> =
+        rmp_serde::from_slice(&identifications).map_err(anyhow::Error::from)?;
+    let sequence_tags: Vec<SequenceTag> = match sequence_tags {
+        Some(sequence_tags) => {
+            rmp_serde::from_slice(&sequence_tags).map_err(anyhow::Error::from)?
+        }
+        None => Vec::new(),
+    };
+    Ok(Spectrum::new(
+        SearchUuid::new(search_uuid).map_err(anyhow::Error::from)?,
+        MsRunName::new(ms_run_name).map_err(anyhow::Error::from)?,
+        SpectrumId::new(spectrum_id).map_err(anyhow::Error::from)?,
+        from_blob(&mz)?,
+        from_blob(&intensity)?,
+        identifications,
+    )
+    .with_retention_time(retention_time)
+    .with_ion_mobility(ion_mobility)
+    .with_ms_level(ms_level)
+    .with_scan_number(scan_number)
+    .with_sequence_tags(sequence_tags)
+    .with_payload_digest(payload_digest)
+    .with_revision(revision))
+}
+
+fn write_spectrum(connection: &Connection, spectrum: &Spectrum) -> Result<()> {
+    connection.execute(
+        "INSERT INTO spectra (
+            search_uuid, ms_run_name, spectrum_id, retention_time, ion_mobility, ms_level, scan_number,
+            mz, intensity, identifications, payload_digest, sequence_tags, revision
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+        ON CONFLICT (search_uuid, ms_run_name, spectrum_id) DO UPDATE SET
+            retention_time = excluded.retention_time,
+            ion_mobility = excluded.ion_mobility,
+            ms_level = excluded.ms_level,
+            scan_number = excluded.scan_number,
+            mz = excluded.mz,
+            intensity = excluded.intensity,
+            identifications = excluded.identifications,
+            payload_digest = excluded.payload_digest,
+            sequence_tags = excluded.sequence_tags,
+            revision = excluded.revision",
+        params![
+            spectrum.get_search_uuid().as_str(),
+            spectrum.get_ms_run().as_str(),
+            spectrum.get_spectra_id().as_str(),
+            spectrum.get_retention_time(),
+            spectrum.get_ion_mobility(),
+            spectrum.get_ms_level(),
+            spectrum.get_scan_number(),
+            to_blob(spectrum.get_mz()),
+            to_blob(spectrum.get_intensity()),
+            rmp_serde::to_vec_named(spectrum.get_identifications())?,
+            spectrum.get_payload_digest(),
+            match spectrum.get_sequence_tags().is_empty() {
+                true => None,
+                false => Some(rmp_serde::to_vec_named(spectrum.get_sequence_tags())?),
+            },
+            spectrum.get_revision(),
+        ],
+    )?;
+    Ok(())
+}
+
+impl ResultStore for SqliteStore {
+    fn get_search(&self, search_uuid: &str) -> Result<Search, StoreError> {
+        let payload: Option<String> = self
+            .connection()
+            .query_row(
+                "SELECT payload FROM searches WHERE search_uuid = ?1",
+                params![search_uuid],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(anyhow::Error::from)?;
+        match payload {
+            Some(payload) => Ok(serde_json::from_str(&payload).map_err(anyhow::Error::from)?),
+            None => Err(StoreError::SearchNotFound(search_uuid.to_string())),
+        }
+    }
+
+    fn list_ms_runs(&self, search_uuid: &str) -> Result<Vec<MsRun>, StoreError> {
+        let connection = self.connection();
+        if !search_exists(&connection, search_uuid)? {
+            return Err(StoreError::SearchNotFound(search_uuid.to_string()));
+        }
+        let mut statement = connection
+            .prepare("SELECT payload FROM ms_runs WHERE search_uuid = ?1 ORDER BY ms_run_name ASC")
+            .map_err(anyhow::Error::from)?;
+        let payloads = statement
+            .query_map(params![search_uuid], |row| row.get::<_, String>(0))
+            .map_err(anyhow::Error::from)?;
+        let mut ms_runs = Vec::new();
+        for payload in payloads {
+            let payload = payload.map_err(anyhow::Error::from)?;
+            ms_runs.push(serde_json::from_str(&payload).map_err(anyhow::Error::from)?);
+        }
+        Ok(ms_runs)
+    }
+
+    fn get_spectrum(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+        spectrum_id: &str,
+    ) -> Result<Spectrum, StoreError> {
+        read_spectrum(&self.connection(), search_uuid, ms_run_name, spectrum_id)
+    }
+
+    fn put_search(&self, search: Search) -> Result<(), StoreError> {
+        self.connection()
+            .execute(
+                "INSERT INTO searches (search_uuid, payload) VALUES (?1, ?2)
+                ON CONFLICT (search_uuid) DO UPDATE SET payload = excluded.payload",
+                params![
+                    search.get_search_uuid().as_str(),
+                    serde_json::to_string(&search).map_err(anyhow::Error::from)?
+                ],
+            )
+            .map_err(anyhow::Error::from)?;
+        Ok(())
+    }
+
+    fn put_ms_run(&self, ms_run: MsRun) -> Result<(), StoreError> {
+        let connection = self.connection();
+        if !search_exists(&connection, ms_run.get_search_uuid())? {
+            return Err(StoreError::SearchNotFound(
+                ms_run.get_search_uuid().to_string(),
+            ));
+        }
+        connection
+            .execute(
+                "INSERT INTO ms_runs (search_uuid, ms_run_name, payload) VALUES (?1, ?2, ?3)
+                ON CONFLICT (search_uuid, ms_run_name) DO UPDATE SET payload = excluded.payload",
+                params![
+                    ms_run.get_search_uuid().as_str(),
+                    ms_run.get_ms_run().as_str(),
+                    serde_json::to_string(&ms_run).map_err(anyhow::Error::from)?
+                ],
+            )
+            .map_err(anyhow::Error::from)?;
+        Ok(())
+    }
+
+    fn put_spectrum(&self, spectrum: Spectrum) -> Result<(), StoreError> {
+        let connection = self.connection();
+        check_ms_run(
+            &connection,
+            spectrum.get_search_uuid(),
+            spectrum.get_ms_run(),
+        )?;
+        write_spectrum(&connection, &spectrum)?;
+        Ok(())
+    }
+
+    fn put_identification(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+        spectrum_id: &str,
+        identification: Identification,
+    ) -> Result<(), StoreError> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction().map_err(anyhow::Error::from)?;
+        let mut spectrum = read_spectrum(&transaction, search_uuid, ms_run_name, spectrum_id)?;
+        spectrum.put_identification(identification);
+        write_spectrum(&transaction, &spectrum)?;
+        transaction.commit().map_err(anyhow::Error::from)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+// internal imports
+use crate::results_api::{Identification, MsRun, Search, SearchSummary, Spectrum};
+
+/// Error of a result store
+///
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("search `{0}` not found")]
+    SearchNotFound(String),
+    #[error("MS run `{1}` of search `{0}` not found")]
+    MsRunNotFound(String, String),
+    #[error("spectrum `{2}` of MS run `{1}` of search `{0}` not found")]
+    SpectrumNotFound(String, String, String),
+    /// Error of the underlying storage, e.g. IO or (de)serialization
+    #[error(transparent)]
+    Backend(#[from] anyhow::Error),
+}
+
+/// Storage of search results, shared by the web service, CLI tools and tests.
+/// Entities are stored hierarchically, so a MS run can only be added to an existing search
+/// and a spectrum only to an existing MS run.
+///
+pub trait ResultStore: Send + Sync {
+    /// Returns the search with the given UUID
+    ///
+    fn get_search(&self, search_uuid: &str) -> Result<Search, StoreError>;
+
+    /// Returns the MS runs of the given search, ordered by name
+    ///
+    fn list_ms_runs(&self, search_uuid: &str) -> Result<Vec<MsRun>, StoreError>;
+
+    /// Returns the given spectrum including its identifications
+    ///
+    /// # Arguments
+    /// * `search_uuid` - UUID of the search
+    /// * `ms_run_name` - Name of the MS run
+    /// * `spectrum_id` - ID of the spectrum
+    ///
+    fn get_spectrum(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+        spectrum_id: &str,
+    ) -> Result<Spectrum, StoreError>;
+
+    /// Adds or replaces the search
+    ///
+    fn put_search(&self, search: Search) -> Result<(), StoreError>;
+
+    /// Adds or replaces the MS run, the search needs to exist
+    ///
+    fn put_ms_run(&self, ms_run: MsRun) -> Result<(), StoreError>;
+
+    /// Adds or replaces the spectrum, the search and MS run need to exist
+    ///
+    fn put_spectrum(&self, spectrum: Spectrum) -> Result<(), StoreError>;
+
+    /// Adds the identification to the given spectrum or replaces the identification
+    /// of the same precursor (see `Spectrum::put_identification`)
+    ///
+    /// # Arguments
+    /// * `search_uuid` - UUID of the search
+    /// * `ms_run_name` - Name of the MS run
+    /// * `spectrum_id` - ID of the spectrum
+    /// * `identification` - Identification
+    ///
+    fn put_identification(
+        &self,
+        search_uuid: &str,
+        ms_run_name: &str,
+        spectrum_id: &str,
+        identification: Identification,
+    ) -> Result<(), StoreError>;
+
+    /// Summary of the search with identification statistics over the spectra of all its MS runs,
+    /// see `Search::summary_with_statistics`. Spectra are loaded one by one, yet the whole search is read,
+    /// so the summary should be cached, e.g. once the search has finished.
+    ///
+    /// # Arguments
+    /// * `search_uuid` - UUID of the search
+    /// * `fdr_threshold` - Maximum q-value of accepted PSMs, e.g. `DEFAULT_SUMMARY_FDR`
+    ///
+    fn search_summary(
+        &self,
+        search_uuid: &str,
+        fdr_threshold: f64,
+    ) -> Result<SearchSummary, StoreError> {
+        let search = self.get_search(search_uuid)?;
+        let ms_runs = self.list_ms_runs(search_uuid)?;
+        let mut load_error: Option<StoreError> = None;
+        let spectra = ms_runs
+            .iter()
+            .flat_map(|ms_run| {
+                ms_run
+                    .get_spectra_ids()
+                    .iter()
+                    .map(move |spectrum_id| (ms_run.get_ms_run(), spectrum_id))
+            })
+            .map_while(|(ms_run_name, spectrum_id)| {
+                match self.get_spectrum(search_uuid, ms_run_name, spectrum_id) {
+                    Ok(spectrum) => Some(spectrum),
+                    Err(err) => {
+                        load_error = Some(err);
+                        None
+                    }
+                }
+            });
+        let summary = search.summary_with_statistics(spectra, fdr_threshold)?;
+        match load_error {
+            Some(err) => Err(err),
+            None => Ok(summary),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+// std imports
+use std::ops::Range;
+
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+/// Mass tolerance, either relative in parts per million or absolute in Dalton
+///
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "unit", content = "value", rename_all = "lowercase")]
+pub enum Tolerance {
+    Ppm(f64),
+    Da(f64),
+}
+
+impl Tolerance {
+    /// Absolute tolerance in Dalton at the given m/z
+    ///
+    pub fn to_da(&self, mz: f64) -> f64 {
+        match self {
+            Self::Da(da) => *da,
+            Self::Ppm(ppm) => mz * ppm / 1_000_000.0,
+        }
+    }
+
+    /// Lowest and highest m/z within the tolerance around the given m/z
+    ///
+    /// ```
+    /// use maccoys_exchange_entities::tolerance::Tolerance;
+    ///
+    /// assert_eq!(Tolerance::Ppm(10.0).bounds(500.0), (499.995, 500.005));
+    /// assert_eq!(Tolerance::Da(0.5).bounds(500.0), (499.5, 500.5));
+    /// assert!(Tolerance::Ppm(10.0).contains(500.004, 500.0));
+    /// assert!(!Tolerance::Da(0.02).contains(500.03, 500.0));
+    /// ```
+    ///
+    pub fn bounds(&self, mz: f64) -> (f64, f64) {
+        let tolerance = self.to_da(mz);
+        (mz - tolerance, mz + tolerance)
+    }
+
+    /// True if the observed m/z is within the tolerance around the theoretical m/z.
+    /// Relative tolerances refer to the theoretical m/z.
+    ///
+    /// # Arguments
+    /// * `observed` - Observed m/z or mass
+    /// * `theoretical` - Theoretical m/z or mass
+    ///
+    pub fn contains(&self, observed: f64, theoretical: f64) -> bool {
+        (observed - theoretical).abs() <= self.to_da(theoretical)
+    }
+
+    /// Indexes of the sorted m/z values within the tolerance around the given m/z
+    ///
+    /// # Arguments
+    /// * `mz` - m/z values sorted ascending, e.g. the peaks of a spectrum
+    /// * `theoretical` - Theoretical m/z
+    ///
+    pub fn peak_range(&self, mz: &[f64], theoretical: f64) -> Range<usize> {
+        let (lower, upper) = self.bounds(theoretical);
+        mz.partition_point(|peak| *peak < lower)..mz.partition_point(|peak| *peak <= upper)
+    }
+}
@@ -0,0 +1,234 @@
+// std imports
+use std::fmt;
+use std::str::FromStr;
+
+// 3rd party imports
+use serde::{Deserialize, Serialize};
+
+// internal imports
+use crate::proforma::{Peptidoform, ProFormaError};
+use crate::results_api::{psm_columns, spectrum::Row, ColumnError, Identification, Spectrum};
+
+/// Prefix of all spectrum USIs
+pub const USI_PREFIX: &str = "mzspec";
+
+/// Error when parsing or creating a USI
+///
+#[derive(Debug, thiserror::Error)]
+pub enum UsiError {
+    #[error("USI `{0}` does not start with `mzspec:`")]
+    MissingPrefix(String),
+    #[error("USI `{0}` has too few components, expected `mzspec:<collection>:<ms run>:<index type>:<index>`")]
+    MissingComponents(String),
+    #[error("unknown index type `{0}`, expected `scan`, `index` or `nativeId`")]
+    UnknownIndexType(String),
+    #[error("invalid index `{0}`")]
+    InvalidIndex(String),
+    #[error("interpretation `{0}` is not of the form `<peptide>/<charge>`")]
+    InvalidInterpretation(String),
+    #[error("modification position `{position}` is invalid for `{sequence}`")]
+    InvalidModificationPosition { position: String, sequence: String },
+    #[error(transparent)]
+    Column(#[from] ColumnError),
+}
+
+/// Reference to the spectrum within the MS run
+///
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpectrumIndex {
+    Scan(u32),
+    Index(usize),
+    NativeId(String),
+}
+
+/// Peptide (ProForma notation) and charge explaining the spectrum
+///
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interpretation {
+    peptide: String,
+    charge: u8,
+}
+
+impl Interpretation {
+    pub fn new(peptide: String, charge: u8) -> Self {
+        Self { peptide, charge }
+    }
+
+    /// Peptide in ProForma notation, e.g. `PEPM[+15.994915]IDE`
+    ///
+    pub fn get_peptide(&self) -> &str {
+        &self.peptide
+    }
+
+    pub fn get_charge(&self) -> u8 {
+        self.charge
+    }
+}
+
+/// Universal Spectrum Identifier as defined by HUPO-PSI, e.g. `mzspec:PXD000561:run:scan:17555:VLHPLEGAVVIIFK/2`
+///
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usi {
+    collection: String,
+    ms_run: String,
+    index: SpectrumIndex,
+    interpretation: Option<Interpretation>,
+}
+
+impl Usi {
+    pub fn new(collection: String, ms_run: String, index: SpectrumIndex) -> Self {
+        Self {
+            collection,
+            ms_run,
+            index,
+            interpretation: None,
+        }
+    }
+
+    pub fn with_interpretation(mut self, interpretation: Option<Interpretation>) -> Self {
+        self.interpretation = interpretation;
+        self
+    }
+
+    /// USI of the spectrum, referenced by scan number if known, otherwise by spectrum ID as native ID.
+    ///
+    /// # Arguments
+    /// * `collection` - Dataset identifier, e.g. a ProteomeXchange accession (`PXD...`) or `USI000000` for unpublished data
+    /// * `spectrum` - Spectrum to reference
+    ///
+    pub fn for_spectrum(collection: &str, spectrum: &Spectrum) -> Self {
+        let index = match spectrum.get_scan_number() {
+            Some(scan_number) => SpectrumIndex::Scan(*scan_number),
+            None => SpectrumIndex::NativeId(spectrum.get_spectra_id().to_string()),
+        };
+        Self::new(
+            collection.to_string(),
+            spectrum.get_ms_run().to_string(),
+            index,
+        )
+    }
+
+    /// USI of the PSM, using the columns `plain_peptide` and `modifications` (Comet format) of the row
+    /// and the charge of the identification.
+    ///
+    /// # Arguments
+    /// * `collection` - Dataset identifier
+    /// * `spectrum` - Spectrum of the PSM
+    /// * `identification` - Identification the row belongs to
+    /// * `psm` - PSM row
+    ///
+    pub fn for_psm(
+        collection: &str,
+        spectrum: &Spectrum,
+        identification: &Identification,
+        psm: &Row<'_>,
+    ) -> Result<Self, UsiError> {
+        let sequence = psm.get_str(psm_columns::PLAIN_PEPTIDE)?;
+        let modifications = psm
+            .get::<Option<&str>>(psm_columns::MODIFICATIONS)
+            .unwrap_or(None)
+            .unwrap_or("");
+        Ok(
+            Self::for_spectrum(collection, spectrum).with_interpretation(Some(
+                Interpretation::new(
+                    to_proforma(sequence, modifications)?,
+                    identification.get_charge(),
+                ),
+            )),
+        )
+    }
+
+    pub fn get_collection(&self) -> &str {
+        &self.collection
+    }
+
+    pub fn get_ms_run(&self) -> &str {
+        &self.ms_run
+    }
+
+    pub fn get_index(&self) -> &SpectrumIndex {
+        &self.index
+    }
+
+    pub fn get_interpretation(&self) -> &Option<Interpretation> {
+        &self.interpretation
+    }
+}
+
+impl fmt::Display for Usi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}:", USI_PREFIX, self.collection, self.ms_run)?;
+        match &self.index {
+            SpectrumIndex::Scan(scan) => write!(f, "scan:{}", scan)?,
+            SpectrumIndex::Index(index) => write!(f, "index:{}", index)?,
+            SpectrumIndex::NativeId(native_id) => write!(f, "nativeId:{}", native_id)?,
+        }
+        if let Some(interpretation) = &self.interpretation {
+            write!(f, ":{}/{}", interpretation.peptide, interpretation.charge)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Usi {
+    type Err = UsiError;
+
+    fn from_str(usi: &str) -> Result<Self, Self::Err> {
+        let mut components = usi.splitn(6, ':');
+        if components.next() != Some(USI_PREFIX) {
+            return Err(UsiError::MissingPrefix(usi.to_string()));
+        }
+        let (collection, ms_run, index_type, index) = match (
+            components.next(),
+            components.next(),
+            components.next(),
+            components.next(),
+        ) {
+            (Some(collection), Some(ms_run), Some(index_type), Some(index)) => {
+                (collection, ms_run, index_type, index)
+            }
+            _ => return Err(UsiError::MissingComponents(usi.to_string())),
+        };
+        let index = match index_type {
+            "scan" => SpectrumIndex::Scan(
+                index
+                    .parse()
+                    .map_err(|_| UsiError::InvalidIndex(index.to_string()))?,
+            ),
+            "index" => SpectrumIndex::Index(
+                index
+                    .parse()
+                    .map_err(|_| UsiError::InvalidIndex(index.to_string()))?,
+            ),
+            "nativeId" => SpectrumIndex::NativeId(index.to_string()),
+            other => return Err(UsiError::UnknownIndexType(other.to_string())),
+        };
+        let interpretation = match components.next() {
+            Some(interpretation) => match interpretation.rsplit_once('/') {
+                Some((peptide, charge)) if !peptide.is_empty() => Some(Interpretation::new(
+                    peptide.to_string(),
+                    charge
+                        .parse()
+                        .map_err(|_| UsiError::InvalidInterpretation(interpretation.to_string()))?,
+                )),
+                _ => return Err(UsiError::InvalidInterpretation(interpretation.to_string())),
+            },
+            None => None,
+        };
+        Ok(Self::new(collection.to_string(), ms_run.to_string(), index)
+            .with_interpretation(interpretation))
+    }
+}
+
+/// Converts a plain sequence with Comet modifications into ProForma notation, e.g. `[+42.010565]-PEPM[+15.994915]IDE`.
+/// Positions are 1-based, `N`/`n` and `C`/`c` denote the peptide termini.
+///
+pub(crate) fn to_proforma(sequence: &str, modifications: &str) -> Result<String, UsiError> {
+    match Peptidoform::from_comet_modifications(sequence, modifications) {
+        Ok(peptidoform) => Ok(peptidoform.to_string()),
+        Err(ProFormaError::InvalidModificationPosition { position, sequence }) => {
+            Err(UsiError::InvalidModificationPosition { position, sequence })
+        }
+        Err(err) => Err(UsiError::InvalidInterpretation(err.to_string())),
+    }
+}
@@ -0,0 +1,417 @@
+//! Validation of entity invariants, e.g. before payloads are served or persisted.
+//!
+//! Deserialization only checks the layout, so an entity may still have unsorted peaks,
+//! impossible charges or PSM tables without the required columns. `Validate::validate` collects
+//! all violations of an entity and its children into a `ValidationReport` instead of stopping at the first one.
+
+// std imports
+use std::collections::HashSet;
+use std::fmt;
+
+// 3rd party imports
+use serde::Serialize;
+
+// internal imports
+use crate::results_api::{
+    psm_columns, Identification, MsRun, Peptide, Precursor, Protein, ProteinGroup, Search,
+    SearchUuid, Spectrum,
+};
+
+/// Lowest valid precursor charge
+pub const MIN_CHARGE: u8 = 1;
+
+/// Highest valid precursor charge
+pub const MAX_CHARGE: u8 = 10;
+
+/// Columns every PSM table needs
+pub const REQUIRED_PSM_COLUMNS: [&str; 2] = [psm_columns::PLAIN_PEPTIDE, psm_columns::PROTEIN];
+
+/// Kind of a violated invariant
+///
+#[derive(Clone, Debug, PartialEq, Serialize, thiserror::Error)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ViolationKind {
+    #[error("`{field}` is empty")]
+    EmptyIdentifier { field: String },
+    #[error("search UUID is nil")]
+    NilSearchUuid,
+    #[error("`{field}` contains `{value}` more than once")]
+    Duplicate { field: String, value: String },
+    #[error("{mz} m/z values but {intensity} intensities")]
+    PeakLengthMismatch { mz: usize, intensity: usize },
+    #[error("m/z values are not sorted ascending at index {index}")]
+    UnsortedMz { index: usize },
+    #[error("`{field}` has a non-finite value at index {index}")]
+    NonFiniteValue { field: String, index: usize },
+    #[error("`{field}` has a negative value at index {index}")]
+    NegativeValue { field: String, index: usize },
+    #[error("charge {charge} is not within {MIN_CHARGE}..={MAX_CHARGE}")]
+    ChargeOutOfRange { charge: u8 },
+    #[error("PSMs have no column `{column}`")]
+    MissingPsmColumn { column: String },
+    #[error("peak index {index} is out of range for {num_peaks} peaks")]
+    PeakIndexOutOfRange { index: usize, num_peaks: usize },
+}
+
+/// Violated invariant with the path of the offending entity or field, e.g. `identifications[0].precursor`
+///
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Violation {
+    path: String,
+    #[serde(flatten)]
+    kind: ViolationKind,
+}
+
+impl Violation {
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn get_kind(&self) -> &ViolationKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.path.is_empty() {
+            true => write!(f, "{}", self.kind),
+            false => write!(f, "{}: {}", self.path, self.kind),
+        }
+    }
+}
+
+/// Violations found by `Validate::validate`
+///
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ValidationReport {
+    violations: Vec<Violation>,
+}
+
+impl ValidationReport {
+    /// Adds a violation
+    ///
+    /// # Arguments
+    /// * `path` - Path of the offending entity or field
+    /// * `kind` - Violated invariant
+    ///
+    pub fn add(&mut self, path: &str, kind: ViolationKind) {
+        self.violations.push(Violation {
+            path: path.to_string(),
+            kind,
+        });
+    }
+
+    pub fn get_violations(&self) -> &Vec<Violation> {
+        &self.violations
+    }
+
+    /// True if there are no violations
+    ///
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Returns an error containing the report if there are violations
+    ///
+    pub fn into_result(self) -> Result<(), ValidationError> {
+        match self.is_valid() {
+            true => Ok(()),
+            false => Err(ValidationError(self)),
+        }
+    }
+}
+
+/// Error of an invalid entity, see `ValidationReport::into_result`
+///
+#[derive(Debug, thiserror::Error)]
+#[error("{} invariant violation(s), first: {}", .0.violations.len(), .0.violations[0])]
+pub struct ValidationError(pub ValidationReport);
+
+/// Check of the invariants of an entity
+///
+/// ```
+/// use maccoys_exchange_entities::results_api::{Identification, Precursor, Spectrum};
+/// use maccoys_exchange_entities::validate::{Validate, ViolationKind};
+///
+/// let spectrum = Spectrum::new(
+///     "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c".parse().unwrap(),
+///     "run".parse().unwrap(),
+///     "scan=1".parse().unwrap(),
+///     vec![200.0, 100.0],
+///     vec![1.0, 2.0],
+///     vec![Identification::new(None, None, Precursor::new(400.7, 12))],
+/// );
+///
+/// let report = spectrum.validate();
+/// assert!(!report.is_valid());
+/// assert_eq!(report.get_violations()[0].get_kind(), &ViolationKind::UnsortedMz { index: 1 });
+/// assert_eq!(
+///     report.get_violations()[1].to_string(),
+///     "identifications[0].precursor: charge 12 is not within 1..=10"
+/// );
+/// assert!(report.into_result().is_err());
+/// ```
+///
+pub trait Validate {
+    /// Adds the violations of the entity to the report, prefixing the paths with the given path
+    ///
+    /// # Arguments
+    /// * `path` - Path of the entity, empty for the root
+    /// * `report` - Report to add the violations to
+    ///
+    fn validate_into(&self, path: &str, report: &mut ValidationReport);
+
+    /// Validates the entity and its children
+    ///
+    fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        self.validate_into("", &mut report);
+        report
+    }
+}
+
+impl<T: Validate> Validate for [T] {
+    fn validate_into(&self, path: &str, report: &mut ValidationReport) {
+        for (index, entity) in self.iter().enumerate() {
+            entity.validate_into(&format!("{}[{}]", path, index), report);
+        }
+    }
+}
+
+impl<T: Validate> Validate for Vec<T> {
+    fn validate_into(&self, path: &str, report: &mut ValidationReport) {
+        self.as_slice().validate_into(path, report);
+    }
+}
+
+impl Validate for Search {
+    fn validate_into(&self, path: &str, report: &mut ValidationReport) {
+        validate_search_uuid(self.get_search_uuid(), path, report);
+        validate_identifiers(
+            self.get_ms_run_names().iter().map(|name| name.as_str()),
+            &join(path, "ms_run_names"),
+            report,
+        );
+    }
+}
+
+impl Validate for MsRun {
+    fn validate_into(&self, path: &str, report: &mut ValidationReport) {
+        validate_search_uuid(self.get_search_uuid(), path, report);
+        if self.get_ms_run().is_empty() {
+            report.add(
+                &join(path, "ms_run_name"),
+                ViolationKind::EmptyIdentifier {
+                    field: "ms_run_name".to_string(),
+                },
+            );
+        }
+        validate_identifiers(
+            self.get_spectra_ids().iter().map(|id| id.as_str()),
+            &join(path, "spectra_ids"),
+            report,
+        );
+    }
+}
+
+impl Validate for Spectrum {
+    fn validate_into(&self, path: &str, report: &mut ValidationReport) {
+        validate_search_uuid(self.get_search_uuid(), path, report);
+        if self.get_ms_run().is_empty() {
+            report.add(
+                &join(path, "ms_run_name"),
+                ViolationKind::EmptyIdentifier {
+                    field: "ms_run_name".to_string(),
+                },
+            );
+        }
+        let mz = self.get_mz();
+        let intensity = self.get_intensity();
+        if mz.len() != intensity.len() {
+            report.add(
+                &join(path, "intensity"),
+                ViolationKind::PeakLengthMismatch {
+                    mz: mz.len(),
+                    intensity: intensity.len(),
+                },
+            );
+        }
+        validate_values(mz, "mz", path, report);
+        if let Some(index) = (1..mz.len()).find(|&index| mz[index] < mz[index - 1]) {
+            report.add(&join(path, "mz"), ViolationKind::UnsortedMz { index });
+        }
+        validate_values(intensity, "intensity", path, report);
+        if let Some(retention_time) = self.get_retention_time() {
+            validate_values(&[*retention_time], "retention_time", path, report);
+        }
+        self.get_identifications()
+            .validate_into(&join(path, "identifications"), report);
+        for (tag_index, sequence_tag) in self.get_sequence_tags().iter().enumerate() {
+            if let Some(index) = sequence_tag
+                .get_peaks()
+                .iter()
+                .find(|index| **index >= mz.len())
+            {
+                report.add(
+                    &format!("{}[{}].peaks", join(path, "sequence_tags"), tag_index),
+                    ViolationKind::PeakIndexOutOfRange {
+                        index: *index,
+                        num_peaks: mz.len(),
+                    },
+                );
+            }
+        }
+    }
+}
+
+impl Validate for Identification {
+    fn validate_into(&self, path: &str, report: &mut ValidationReport) {
+        self.get_precursor()
+            .validate_into(&join(path, "precursor"), report);
+        if let Some(psms) = self.get_psms() {
+            let columns = psms.get_column_names();
+            for column in REQUIRED_PSM_COLUMNS {
+                if !columns.contains(&column) {
+                    report.add(
+                        &join(path, "psms"),
+                        ViolationKind::MissingPsmColumn {
+                            column: column.to_string(),
+                        },
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl Validate for Precursor {
+    fn validate_into(&self, path: &str, report: &mut ValidationReport) {
+        validate_values(&[self.get_mz()], "mz", path, report);
+        if !(MIN_CHARGE..=MAX_CHARGE).contains(&self.get_charge()) {
+            report.add(
+                path,
+                ViolationKind::ChargeOutOfRange {
+                    charge: self.get_charge(),
+                },
+            );
+        }
+        if let Some(intensity) = self.get_intensity() {
+            validate_values(&[*intensity], "intensity", path, report);
+        }
+    }
+}
+
+impl Validate for Peptide {
+    fn validate_into(&self, path: &str, report: &mut ValidationReport) {
+        if self.get_sequence().is_empty() {
+            report.add(
+                &join(path, "sequence"),
+                ViolationKind::EmptyIdentifier {
+                    field: "sequence".to_string(),
+                },
+            );
+        }
+        for charge in self.get_charges() {
+            if !(MIN_CHARGE..=MAX_CHARGE).contains(charge) {
+                report.add(
+                    &join(path, "charges"),
+                    ViolationKind::ChargeOutOfRange { charge: *charge },
+                );
+            }
+        }
+        validate_values(self.get_retention_times(), "retention_times", path, report);
+    }
+}
+
+impl Validate for Protein {
+    fn validate_into(&self, path: &str, report: &mut ValidationReport) {
+        if self.get_accession().is_empty() {
+            report.add(
+                &join(path, "accession"),
+                ViolationKind::EmptyIdentifier {
+                    field: "accession".to_string(),
+                },
+            );
+        }
+    }
+}
+
+impl Validate for ProteinGroup {
+    fn validate_into(&self, path: &str, report: &mut ValidationReport) {
+        validate_identifiers(
+            self.get_accessions()
+                .iter()
+                .map(|accession| accession.as_str()),
+            &join(path, "accessions"),
+            report,
+        );
+    }
+}
+
+/// Appends the field to the path
+///
+fn join(path: &str, field: &str) -> String {
+    match path.is_empty() {
+        true => field.to_string(),
+        false => format!("{}.{}", path, field),
+    }
+}
+
+fn validate_search_uuid(search_uuid: &SearchUuid, path: &str, report: &mut ValidationReport) {
+    if search_uuid == &SearchUuid::nil() {
+        report.add(&join(path, "search_uuid"), ViolationKind::NilSearchUuid);
+    }
+}
+
+/// Checks that the identifiers are neither empty nor duplicated
+///
+fn validate_identifiers<'a>(
+    identifiers: impl Iterator<Item = &'a str>,
+    path: &str,
+    report: &mut ValidationReport,
+) {
+    let field = path.rsplit('.').next().unwrap_or(path);
+    let mut seen = HashSet::new();
+    for identifier in identifiers {
+        if identifier.is_empty() {
+            report.add(
+                path,
+                ViolationKind::EmptyIdentifier {
+                    field: field.to_string(),
+                },
+            );
+        } else if !seen.insert(identifier) {
+            report.add(
+                path,
+                ViolationKind::Duplicate {
+                    field: field.to_string(),
+                    value: identifier.to_string(),
+                },
+            );
+        }
+    }
+}
+
+/// Reports the first non-finite and the first negative value
+///
+fn validate_values(values: &[f64], field: &str, path: &str, report: &mut ValidationReport) {
+    if let Some(index) = values.iter().position(|value| !value.is_finite()) {
+        report.add(
+            &join(path, field),
+            ViolationKind::NonFiniteValue {
+                field: field.to_string(),
+                index,
+            },
+        );
+    }
+    if let Some(index) = values.iter().position(|value| *value < 0.0) {
+        report.add(
+            &join(path, field),
+            ViolationKind::NegativeValue {
+                field: field.to_string(),
+                index,
+            },
+        );
+    }
+}
@@ -0,0 +1,129 @@
+// 3rd party imports
+use serde::{de::DeserializeOwned, Serialize};
+use serde_wasm_bindgen::Serializer;
+use wasm_bindgen::prelude::*;
+
+// internal imports
+use crate::results_api::{
+    Identification, MsRun, Precursor, Search, SequenceTag, Spectrum, TableView,
+};
+use crate::serialization::WireFormat;
+
+/// Spectrum as handed over to JavaScript, with the dataframes replaced by `TableView`s
+///
+#[derive(Serialize)]
+struct SpectrumView<'a> {
+    search_uuid: &'a str,
+    ms_run_name: &'a str,
+    spectrum_id: &'a str,
+    retention_time: Option<f64>,
+    ion_mobility: Option<f64>,
+    ms_level: Option<u8>,
+    scan_number: Option<u32>,
+    mz: &'a [f64],
+    intensity: &'a [f64],
+    identifications: Vec<IdentificationView<'a>>,
+    sequence_tags: &'a [SequenceTag],
+    payload_digest: Option<&'a str>,
+    revision: u64,
+}
+
+/// Identification as handed over to JavaScript
+///
+#[derive(Serialize)]
+struct IdentificationView<'a> {
+    precursor: &'a Precursor,
+    psms: Option<TableView>,
+    goodnesses: Option<TableView>,
+}
+
+impl<'a> IdentificationView<'a> {
+    fn new(identification: &'a Identification) -> Result<Self, JsError> {
+        let view = |dataframe: &Option<_>| {
+            dataframe
+                .as_ref()
+                .map(TableView::from_dataframe)
+                .transpose()
+                .map_err(|err| JsError::new(&err.to_string()))
+        };
+        Ok(Self {
+            precursor: identification.get_precursor(),
+            psms: view(identification.get_psms())?,
+            goodnesses: view(identification.get_goodnesses())?,
+        })
+    }
+}
+
+impl<'a> SpectrumView<'a> {
+    fn new(spectrum: &'a Spectrum) -> Result<Self, JsError> {
+        Ok(Self {
+            search_uuid: spectrum.get_search_uuid(),
+            ms_run_name: spectrum.get_ms_run(),
+            spectrum_id: spectrum.get_spectra_id(),
+            retention_time: *spectrum.get_retention_time(),
+            ion_mobility: *spectrum.get_ion_mobility(),
+            ms_level: *spectrum.get_ms_level(),
+            scan_number: *spectrum.get_scan_number(),
+            mz: spectrum.get_mz(),
+            intensity: spectrum.get_intensity(),
+            identifications: spectrum
+                .get_identifications()
+                .iter()
+                .map(IdentificationView::new)
+                .collect::<Result<_, _>>()?,
+            sequence_tags: spectrum.get_sequence_tags(),
+            payload_digest: spectrum.get_payload_digest().as_deref(),
+            revision: spectrum.get_revision(),
+        })
+    }
+}
+
+/// Converts to a plain JavaScript object, maps become objects instead of `Map`s
+///
+fn to_js<T: Serialize>(entity: &T) -> Result<JsValue, JsError> {
+    Ok(entity.serialize(&Serializer::json_compatible())?)
+}
+
+fn from_json<T: DeserializeOwned>(json: &str) -> Result<T, JsError> {
+    Ok(serde_json::from_str(json)?)
+}
+
+fn from_msgpack<T: WireFormat>(msgpack: &[u8]) -> Result<T, JsError> {
+    T::from_msgpack(msgpack).map_err(|err| JsError::new(&err.to_string()))
+}
+
+#[wasm_bindgen(js_name = searchFromJson)]
+pub fn search_from_json(json: &str) -> Result<JsValue, JsError> {
+    to_js(&from_json::<Search>(json)?)
+}
+
+#[wasm_bindgen(js_name = searchFromMsgpack)]
+pub fn search_from_msgpack(msgpack: &[u8]) -> Result<JsValue, JsError> {
+    to_js(&from_msgpack::<Search>(msgpack)?)
+}
+
+#[wasm_bindgen(js_name = msRunFromJson)]
+pub fn ms_run_from_json(json: &str) -> Result<JsValue, JsError> {
+    to_js(&from_json::<MsRun>(json)?)
+}
+
+#[wasm_bindgen(js_name = msRunFromMsgpack)]
+pub fn ms_run_from_msgpack(msgpack: &[u8]) -> Result<JsValue, JsError> {
+    to_js(&from_msgpack::<MsRun>(msgpack)?)
+}
+
+/// Deserializes a spectrum, PSMs and goodness of fits are returned as `TableView`s
+///
+#[wasm_bindgen(js_name = spectrumFromJson)]
+pub fn spectrum_from_json(json: &str) -> Result<JsValue, JsError> {
+    let spectrum: Spectrum = from_json(json)?;
+    to_js(&SpectrumView::new(&spectrum)?)
+}
+
+/// Deserializes a spectrum, PSMs and goodness of fits are returned as `TableView`s
+///
+#[wasm_bindgen(js_name = spectrumFromMsgpack)]
+pub fn spectrum_from_msgpack(msgpack: &[u8]) -> Result<JsValue, JsError> {
+    let spectrum: Spectrum = from_msgpack(msgpack)?;
+    to_js(&SpectrumView::new(&spectrum)?)
+}
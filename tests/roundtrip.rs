@@ -0,0 +1,223 @@
+//! Round trips of the exchanged entities through JSON and the binary wire formats (MessagePack, CBOR),
+//! with and without optional fields and with empty tables
+
+// 3rd party imports
+use polars::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+// internal imports
+use maccoys_exchange_entities::results_api::{
+    Identification, MsRun, Precursor, ProcessingError, ProcessingErrorKind, ProcessingStage,
+    Search, SearchModification, SearchParameters, SequenceTag, Spectrum,
+};
+use maccoys_exchange_entities::serialization::WireFormat;
+
+const SEARCH_UUID: &str = "4f6e8a2c-1d3b-4c5e-9a7f-0b2d4e6f8a1c";
+
+/// Serializes and deserializes the value with each format, returning the decoded values
+/// in the order JSON, MessagePack, CBOR
+///
+fn round_trip<T>(value: &T) -> Vec<T>
+where
+    T: Serialize + DeserializeOwned + WireFormat,
+{
+    vec![
+        serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap(),
+        T::from_msgpack(&value.to_msgpack().unwrap()).unwrap(),
+        T::from_cbor(&value.to_cbor().unwrap()).unwrap(),
+    ]
+}
+
+fn processing_error() -> ProcessingError {
+    ProcessingError::new(
+        Some("scan=2".to_string()),
+        ProcessingStage::Searching,
+        ProcessingErrorKind::Timeout,
+        "search engine did not finish within 60 s".to_string(),
+        true,
+    )
+}
+
+fn psms() -> DataFrame {
+    df!(
+        "plain_peptide" => &["PEPTIDE", "PEPTIDES"],
+        "xcorr" => &[Some(2.5), None],
+        "charge" => &[2i64, 3],
+        "is_decoy" => &[false, true]
+    )
+    .unwrap()
+}
+
+/// PSM table with columns but without rows
+///
+fn empty_psms() -> DataFrame {
+    psms().head(Some(0))
+}
+
+fn goodnesses() -> DataFrame {
+    df!("distribution" => &["gamma"], "p_value" => &[0.05]).unwrap()
+}
+
+fn minimal_identification() -> Identification {
+    Identification::new(None, None, Precursor::new(400.7, 2))
+}
+
+fn full_identification() -> Identification {
+    Identification::new(
+        Some(goodnesses()),
+        Some(psms()),
+        Precursor::new(400.7, 2)
+            .with_intensity(Some(1.5e6))
+            .with_isolation_window(Some(0.7), Some(0.8))
+            .with_monoisotopic_correction(Some(-1)),
+    )
+}
+
+fn minimal_spectrum() -> Spectrum {
+    Spectrum::new(
+        SEARCH_UUID.parse().unwrap(),
+        "run".parse().unwrap(),
+        "scan=1".parse().unwrap(),
+        Vec::new(),
+        Vec::new(),
+        Vec::new(),
+    )
+}
+
+fn full_spectrum() -> Spectrum {
+    Spectrum::new(
+        SEARCH_UUID.parse().unwrap(),
+        "run".parse().unwrap(),
+        "scan=1".parse().unwrap(),
+        vec![100.0, 200.5, 300.25],
+        vec![1.0, 20.0, 300.0],
+        vec![
+            full_identification(),
+            Identification::new(
+                Some(goodnesses()),
+                Some(empty_psms()),
+                Precursor::new(600.3, 3),
+            ),
+            minimal_identification(),
+        ],
+    )
+    .with_retention_time(Some(1234.5))
+    .with_ion_mobility(Some(0.85))
+    .with_ms_level(Some(2))
+    .with_scan_number(Some(1))
+    .with_sequence_tags(vec![
+        SequenceTag::new("PEP".to_string(), 98.06, 330.2)
+            .with_peaks(vec![0, 1, 2])
+            .with_score(Some(0.9))
+            .with_charge(Some(2)),
+        SequenceTag::new("TID".to_string(), 0.0, 0.0),
+    ])
+    .with_revision(3)
+    .seal()
+    .unwrap()
+}
+
+fn assert_spectrum_round_trip(spectrum: &Spectrum) {
+    for decoded in round_trip(spectrum) {
+        assert!(decoded.approx_eq(spectrum, 0.0));
+        assert_eq!(decoded.get_sequence_tags(), spectrum.get_sequence_tags());
+        assert_eq!(decoded.get_payload_digest(), spectrum.get_payload_digest());
+        assert_eq!(decoded.get_revision(), spectrum.get_revision());
+    }
+}
+
+fn assert_identification_round_trip(identification: &Identification) {
+    for decoded in round_trip(identification) {
+        assert!(decoded.approx_eq(identification, 0.0));
+    }
+}
+
+#[test]
+fn search_without_optional_fields() {
+    let search = Search::new(SEARCH_UUID.parse().unwrap(), Vec::new());
+    for decoded in round_trip(&search) {
+        assert!(decoded == search);
+    }
+}
+
+#[test]
+fn search_with_optional_fields() {
+    let search = Search::new(
+        SEARCH_UUID.parse().unwrap(),
+        vec!["run_1".parse().unwrap(), "run_2".parse().unwrap()],
+    )
+    .with_parameters(
+        SearchParameters::new("/data/human.fasta".to_string(), "trypsin".to_string(), 2)
+            .with_fixed_modification(SearchModification::new(
+                "Carbamidomethyl".to_string(),
+                "C".to_string(),
+                57.021464,
+            )),
+    )
+    .with_error(processing_error())
+    .with_revision(7);
+    for decoded in round_trip(&search) {
+        assert!(decoded == search);
+    }
+}
+
+#[test]
+fn ms_run_without_optional_fields() {
+    let ms_run = MsRun::new(
+        SEARCH_UUID.parse().unwrap(),
+        "run".parse().unwrap(),
+        Vec::new(),
+    );
+    for decoded in round_trip(&ms_run) {
+        assert!(decoded == ms_run);
+    }
+}
+
+#[test]
+fn ms_run_with_optional_fields() {
+    let ms_run = MsRun::new(
+        SEARCH_UUID.parse().unwrap(),
+        "run".parse().unwrap(),
+        vec!["scan=1".parse().unwrap(), "scan=2".parse().unwrap()],
+    )
+    .with_error(processing_error())
+    .with_revision(2);
+    for decoded in round_trip(&ms_run) {
+        assert!(decoded == ms_run);
+    }
+}
+
+#[test]
+fn spectrum_without_optional_fields() {
+    assert_spectrum_round_trip(&minimal_spectrum());
+}
+
+#[test]
+fn spectrum_with_optional_fields() {
+    assert_spectrum_round_trip(&full_spectrum());
+}
+
+#[test]
+fn identification_without_tables() {
+    assert_identification_round_trip(&minimal_identification());
+}
+
+#[test]
+fn identification_with_empty_tables() {
+    let identification = Identification::new(
+        Some(goodnesses().head(Some(0))),
+        Some(empty_psms()),
+        Precursor::new(400.7, 2),
+    );
+    for decoded in round_trip(&identification) {
+        assert!(decoded.approx_eq(&identification, 0.0));
+        let psms = decoded.get_psms().as_ref().unwrap();
+        assert_eq!(psms.height(), 0);
+        assert_eq!(psms.get_column_names(), empty_psms().get_column_names());
+    }
+}
+
+#[test]
+fn identification_with_optional_fields() {
+    assert_identification_round_trip(&full_identification());
+}
@@ -0,0 +1,50 @@
+//! PSM row iterators are handed to other threads by the results API server (async handlers, rayon),
+//! possibly after they were partially consumed
+
+// std imports
+use std::thread;
+
+// 3rd party imports
+use polars::prelude::*;
+
+// internal imports
+use maccoys_exchange_entities::results_api::{Identification, Precursor};
+
+#[test]
+fn row_iter_continues_on_other_thread() {
+    let psms = df!(
+        "plain_peptide" => &["PEPTIDE", "PEPTIDES", "PEPTIDER", "PEPTIDEK"],
+        "xcorr" => &[2.5, 1.5, 1.0, 0.5]
+    )
+    .unwrap();
+    let identification = Identification::new(None, Some(psms), Precursor::new(400.7, 2));
+
+    let mut rows = identification.iter_psm_rows().unwrap();
+    let first = rows.next().unwrap();
+    assert_eq!(first.get_str("plain_peptide").unwrap(), "PEPTIDE");
+
+    let remaining = thread::scope(|scope| {
+        scope
+            .spawn(move || {
+                rows.map(|row| {
+                    (
+                        row.get_str("plain_peptide").unwrap().to_string(),
+                        row.get_f64("xcorr").unwrap(),
+                    )
+                })
+                .collect::<Vec<(String, f64)>>()
+            })
+            .join()
+            .unwrap()
+    });
+    assert_eq!(
+        remaining,
+        vec![
+            ("PEPTIDES".to_string(), 1.5),
+            ("PEPTIDER".to_string(), 1.0),
+            ("PEPTIDEK".to_string(), 0.5),
+        ]
+    );
+    // the row taken before is still valid
+    assert_eq!(first.get_f64("xcorr").unwrap(), 2.5);
+}